@@ -0,0 +1,144 @@
+use crate::query::{self, QueryGame};
+
+use gstat_core::{
+    bulk::QueryMany,
+    document,
+    encode::{Encode, Json},
+    prelude::{TimeoutSettings, Value},
+    resolve::HostPort,
+    script::ScriptRegistry,
+};
+
+use std::{
+    collections::BTreeMap,
+    io::{self, BufRead, Write},
+};
+
+/// The default number of servers queried at the same time.
+pub const DEFAULT_CONCURRENCY: usize = 64;
+
+/// A line of the input, numbered from one.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Line {
+    /// The line number.
+    pub number: usize,
+    /// The contents of the line, without surrounding whitespace.
+    pub text: String,
+}
+
+/// Returns the lines naming a server, skipping blank lines and `#` comments.
+///
+/// The lines are read lazily, so servers are queried while the input is still being read.
+///
+/// # Parameters
+///
+/// * `input`: The server list.
+pub fn lines(input: impl BufRead + Send) -> impl Iterator<Item = Result<Line, String>> + Send {
+    input
+        .lines()
+        .enumerate()
+        .map(|(index, line)| match line {
+            Ok(text) => Ok(Line {
+                number: index + 1,
+                text: text.trim().to_string(),
+            }),
+            Err(err) => Err(format!("failed to read line {}: {err}", index + 1)),
+        })
+        .filter(|line| {
+            line.as_ref().map_or(true, |line| {
+                !line.text.is_empty() && !line.text.starts_with('#')
+            })
+        })
+}
+
+/// Splits a line into its game and server address, e.g. `tf2 play.example.com:27015`.
+fn parse(line: &str) -> Result<(&str, HostPort), String> {
+    let mut fields = line.split_whitespace();
+
+    match (fields.next(), fields.next(), fields.next()) {
+        (Some(game), Some(address), None) => {
+            Ok((game, address.parse().map_err(|err| format!("{err}"))?))
+        }
+        _ => Err(format!("expected `<game> <address>`, found `{line}`")),
+    }
+}
+
+/// Looks up the game and parses the address of a line.
+fn target<'s>(
+    scripts: &'s ScriptRegistry,
+    line: &Result<Line, String>,
+) -> Result<(QueryGame<'s>, HostPort), String> {
+    let (game, address) = parse(&line.as_ref()?.text)?;
+
+    Ok((query::find_game(scripts, game)?, address))
+}
+
+/// Queries every server of the input and prints each result as a JSON document on its own
+/// line, in the order the queries complete.
+///
+/// Successful queries print the document of `--format json` with the `line` and `target`
+/// it came from. Lines that can't be parsed and servers that can't be queried print a
+/// document with the `line`, the `target` and the `error`, and don't stop the others.
+///
+/// # Parameters
+///
+/// * `scripts`: The scripted games loaded with `--script`.
+/// * `input`: The server list, one `<game> <address>` per line.
+/// * `concurrency`: The largest number of queries in flight.
+/// * `timeouts`: The time limits for each query.
+///
+/// # Returns
+///
+/// A `Result` containing either the number of servers and of failures, or the error of
+/// reading the input or writing the results.
+pub fn run(
+    scripts: &ScriptRegistry,
+    input: impl BufRead + Send,
+    concurrency: usize,
+    timeouts: TimeoutSettings,
+) -> Result<(usize, usize), String> {
+    let mut results = QueryMany::new(lines(input), concurrency, |line| {
+        let target = target(scripts, line);
+
+        async move {
+            let (game, address) = target?;
+            let fetched = query::fetch(game, &address, timeouts).await?;
+
+            Ok::<_, String>(query::document(game, &fetched))
+        }
+    });
+
+    query::runtime()?.block_on(async {
+        let (mut total, mut failed) = (0, 0);
+        let mut stdout = io::stdout();
+
+        while let Some((line, result)) = results.next().await {
+            let line = line?;
+            let document = match result {
+                Ok(Value::Map(mut document)) => {
+                    document.insert("line".to_string(), Value::Int(line.number as i64));
+                    document.insert("target".to_string(), line.text.into());
+                    Value::Map(document)
+                }
+                Ok(document) => document,
+                Err(err) => {
+                    failed += 1;
+
+                    Value::Map(BTreeMap::from([
+                        ("version".to_string(), document::VERSION.into()),
+                        ("line".to_string(), Value::Int(line.number as i64)),
+                        ("target".to_string(), line.text.into()),
+                        ("error".to_string(), err.into()),
+                    ]))
+                }
+            };
+
+            total += 1;
+            stdout
+                .write_all(&[Json.encode(&document), b"\n".to_vec()].concat())
+                .map_err(|err| format!("failed to write: {err}"))?;
+        }
+
+        Ok((total, failed))
+    })
+}
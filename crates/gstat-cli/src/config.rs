@@ -0,0 +1,273 @@
+use gstat_core::{duration, encode::Encoding, prelude::TimeoutSettings};
+
+use std::{
+    collections::BTreeMap,
+    env,
+    error::Error as StdError,
+    fmt::{Display, Formatter, Result as FmtResult},
+    fs, io,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use serde::Deserialize;
+
+/// The name of the profile used when none is selected.
+pub const DEFAULT_PROFILE: &str = "default";
+
+/// How results are printed.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    /// Human-readable lines.
+    #[default]
+    Plain,
+    /// Aligned columns.
+    Table,
+    /// JSON, for piping into other tools.
+    Json,
+    /// CSV, with nested fields flattened into columns, for spreadsheets.
+    Csv,
+    /// MessagePack, a compact binary form of the JSON document.
+    #[serde(rename = "msgpack")]
+    #[value(name = "msgpack")]
+    MessagePack,
+    /// XML, for legacy panels.
+    Xml,
+}
+
+impl OutputFormat {
+    /// Returns the encoding of the document the format prints, or `None` for the formats
+    /// meant to be read by people.
+    pub fn encoding(self) -> Option<Encoding> {
+        match self {
+            Self::Plain | Self::Table => None,
+            Self::Json => Some(Encoding::Json),
+            Self::Csv => Some(Encoding::Csv),
+            Self::MessagePack => Some(Encoding::MessagePack),
+            Self::Xml => Some(Encoding::Xml),
+        }
+    }
+}
+
+impl Display for OutputFormat {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            Self::Plain => write!(f, "plain"),
+            Self::Table => write!(f, "table"),
+            Self::Json => write!(f, "json"),
+            Self::Csv => write!(f, "csv"),
+            Self::MessagePack => write!(f, "msgpack"),
+            Self::Xml => write!(f, "xml"),
+        }
+    }
+}
+
+/// A server saved under a short name.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Favorite {
+    /// The game identifier, e.g. `tf2`.
+    pub game: String,
+    /// The server address, e.g. `play.example.com:27015`.
+    pub address: String,
+}
+
+/// A named set of defaults.
+///
+/// Every setting is optional; settings missing from a profile fall back to the `default`
+/// profile, and from there to the built-in defaults.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Profile {
+    /// The limit applied to every step of a query, e.g. `"5s"`.
+    #[serde(default, deserialize_with = "duration::deserialize_optional")]
+    pub timeout: Option<Duration>,
+    /// The limit for connecting, overriding `timeout`.
+    #[serde(default, deserialize_with = "duration::deserialize_optional")]
+    pub connect_timeout: Option<Duration>,
+    /// The limit for receiving a response, overriding `timeout`.
+    #[serde(default, deserialize_with = "duration::deserialize_optional")]
+    pub read_timeout: Option<Duration>,
+    /// How results are printed.
+    pub format: Option<OutputFormat>,
+    /// Servers saved under short names.
+    #[serde(default)]
+    pub servers: BTreeMap<String, Favorite>,
+    /// API keys by service, e.g. `steam`.
+    #[serde(default)]
+    pub api_keys: BTreeMap<String, String>,
+}
+
+impl Profile {
+    /// Returns this profile with unset settings taken from `base`.
+    ///
+    /// Favorite servers and API keys are merged, with this profile's entries winning.
+    pub fn over(mut self, base: &Profile) -> Self {
+        self.timeout = self.timeout.or(base.timeout);
+        self.connect_timeout = self.connect_timeout.or(base.connect_timeout);
+        self.read_timeout = self.read_timeout.or(base.read_timeout);
+        self.format = self.format.or(base.format);
+
+        for (name, favorite) in &base.servers {
+            self.servers
+                .entry(name.clone())
+                .or_insert_with(|| favorite.clone());
+        }
+
+        for (service, key) in &base.api_keys {
+            self.api_keys
+                .entry(service.clone())
+                .or_insert_with(|| key.clone());
+        }
+
+        self
+    }
+
+    /// Returns the time limits described by the profile.
+    pub fn timeouts(&self) -> TimeoutSettings {
+        let mut timeouts = self
+            .timeout
+            .map(TimeoutSettings::uniform)
+            .unwrap_or_default();
+
+        if let Some(limit) = self.connect_timeout {
+            timeouts = timeouts.with_connect(limit);
+        }
+
+        if let Some(limit) = self.read_timeout {
+            timeouts = timeouts.with_read(limit);
+        }
+
+        timeouts
+    }
+}
+
+/// The contents of the configuration file.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// The profile used when `--profile` is not given.
+    pub default_profile: Option<String>,
+    /// The profiles by name.
+    #[serde(default)]
+    pub profiles: BTreeMap<String, Profile>,
+}
+
+impl Config {
+    /// Loads the configuration file.
+    ///
+    /// A missing file is only an error if its path was given explicitly.
+    ///
+    /// # Parameters
+    ///
+    /// * `path`: The path given with `--config`, or `None` to use `default_path`.
+    pub fn load(path: Option<&Path>) -> Result<Self, ConfigError> {
+        let (path, explicit) = match path {
+            Some(path) => (path.to_path_buf(), true),
+            None => match default_path() {
+                Some(path) => (path, false),
+                None => return Ok(Config::default()),
+            },
+        };
+
+        let contents = match fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == io::ErrorKind::NotFound && !explicit => {
+                return Ok(Config::default())
+            }
+            Err(source) => return Err(ConfigError::Io { path, source }),
+        };
+
+        toml::from_str(&contents).map_err(|source| ConfigError::Parse { path, source })
+    }
+
+    /// Resolves the profile to use.
+    ///
+    /// The profile named by `selected`, or else by `default_profile`, is layered over the
+    /// `default` profile. Naming a profile that doesn't exist is an error.
+    ///
+    /// # Parameters
+    ///
+    /// * `selected`: The profile given with `--profile`, if any.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing either the name and settings of the profile or a `ConfigError`.
+    pub fn profile(&self, selected: Option<&str>) -> Result<(String, Profile), ConfigError> {
+        let base = self
+            .profiles
+            .get(DEFAULT_PROFILE)
+            .cloned()
+            .unwrap_or_default();
+
+        let Some(name) = selected.or(self.default_profile.as_deref()) else {
+            return Ok((DEFAULT_PROFILE.to_string(), base));
+        };
+
+        match self.profiles.get(name) {
+            Some(profile) => Ok((name.to_string(), profile.clone().over(&base))),
+            None => Err(ConfigError::UnknownProfile(name.to_string())),
+        }
+    }
+}
+
+/// Returns the default location of the configuration file.
+///
+/// This is `$GSTAT_CONFIG` if set, otherwise `gstat/config.toml` under `$XDG_CONFIG_HOME`,
+/// falling back to `~/.config`.
+pub fn default_path() -> Option<PathBuf> {
+    if let Some(path) = env::var_os("GSTAT_CONFIG") {
+        return Some(PathBuf::from(path));
+    }
+
+    let base = match env::var_os("XDG_CONFIG_HOME") {
+        Some(base) if !base.is_empty() => PathBuf::from(base),
+        _ => PathBuf::from(env::var_os("HOME")?).join(".config"),
+    };
+
+    Some(base.join("gstat").join("config.toml"))
+}
+
+/// An error raised while loading the configuration.
+#[derive(Debug)]
+pub enum ConfigError {
+    /// The configuration file could not be read.
+    Io {
+        /// The path of the file.
+        path: PathBuf,
+        /// The underlying I/O error.
+        source: io::Error,
+    },
+    /// The configuration file is not valid.
+    Parse {
+        /// The path of the file.
+        path: PathBuf,
+        /// The underlying TOML error.
+        source: toml::de::Error,
+    },
+    /// The selected profile does not exist.
+    UnknownProfile(String),
+}
+
+impl Display for ConfigError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            Self::Io { path, source } => write!(f, "failed to read {}: {source}", path.display()),
+            Self::Parse { path, source } => {
+                write!(f, "invalid config {}: {source}", path.display())
+            }
+            Self::UnknownProfile(name) => write!(f, "unknown profile `{name}`"),
+        }
+    }
+}
+
+impl StdError for ConfigError {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        match self {
+            Self::Io { source, .. } => Some(source),
+            Self::Parse { source, .. } => Some(source),
+            Self::UnknownProfile(_) => None,
+        }
+    }
+}
@@ -0,0 +1,60 @@
+use crate::query;
+
+use gstat_core::{
+    detect::{detect, detect_ip, Detection},
+    resolve::{resolve, HostPort, SystemResolver},
+};
+
+use std::time::Duration;
+
+/// Guesses the protocol and game of a server, on a runtime of its own.
+///
+/// With a port in `target`, every probe is sent to that port; without one, each protocol
+/// is probed on the default query ports of its games. A host name resolving to several
+/// addresses is probed at each address in turn until one answers.
+///
+/// # Parameters
+///
+/// * `target`: The host of the server, with an optional port.
+/// * `limit`: How long each probe waits for its answer.
+///
+/// # Returns
+///
+/// A `Result` containing either the detection or a description of why there is none.
+pub fn run(target: &HostPort, limit: Duration) -> Result<Detection, String> {
+    query::runtime()?.block_on(async {
+        let resolved = resolve(&SystemResolver, target, None)
+            .await
+            .map_err(|err| format!("{target}: {err}"))?;
+
+        for address in resolved.addresses {
+            let detection = match target.port {
+                Some(_) => detect(address, limit).await,
+                None => detect_ip(address.ip(), limit).await,
+            };
+
+            if let Some(detection) = detection {
+                return Ok(detection);
+            }
+        }
+
+        Err(format!("{target} did not answer any probe"))
+    })
+}
+
+/// Renders a detection as `label: value` lines.
+pub fn render(detection: &Detection) -> String {
+    let mut output = String::new();
+
+    if let Some(game) = detection.game {
+        output += &format!("game: {} ({})\n", game.name, game.id);
+    }
+
+    output += &format!(
+        "protocol: {} ({})\n",
+        detection.protocol.name, detection.protocol.id
+    );
+    output += &format!("address: {}\n", detection.address);
+    output += &format!("latency: {:?}\n", detection.latency);
+    output
+}
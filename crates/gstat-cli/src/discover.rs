@@ -0,0 +1,227 @@
+use crate::{
+    config::OutputFormat,
+    query::{self, Answered, QueryGame},
+};
+
+use gstat_core::{
+    bulk::QueryMany,
+    discovery::{self, MasterQuery, VALVE_MASTER},
+    document,
+    encode::{Encode, Encoding, Json},
+    prelude::{Fetched, Parser, Response, ResponseMeta, TimeoutSettings},
+    resolve::HostPort,
+    runtime,
+    script::{ScriptQuery, ScriptRegistry},
+};
+
+use std::{net::SocketAddr, time::Duration};
+
+use serde_json::json;
+
+/// The source naming Valve's master server.
+pub const VALVE_MASTER_SOURCE: &str = "valve-master";
+
+/// The number of servers queried at the same time when following up on a master list.
+const CONCURRENCY: usize = 64;
+
+/// How servers are discovered and what is printed about them.
+#[derive(Clone, Debug)]
+pub struct Options {
+    /// Broadcast the game's query to the local network.
+    pub lan: bool,
+    /// The master server filter, e.g. `\appid\440`.
+    pub filter: String,
+    /// The master server to ask instead of Valve's.
+    pub master: Option<HostPort>,
+    /// The game to query the servers of a master list with.
+    pub game: Option<String>,
+    /// Follow up on each server with a full query.
+    pub query: bool,
+    /// How long answers to a LAN broadcast are collected.
+    pub window: Duration,
+}
+
+/// Lists the servers found through `source` and prints one line per server as soon as it is
+/// known.
+///
+/// # Parameters
+///
+/// * `scripts`: The scripted games loaded with `--script`.
+/// * `source`: `valve-master`, or the game whose servers are looked for with `--lan`.
+/// * `options`: How servers are discovered and what is printed.
+/// * `timeouts`: The time limits for the master server and for each follow-up query.
+/// * `format`: How each server is printed: plain, table or JSON.
+///
+/// # Returns
+///
+/// A `Result` containing either the number of servers found or a description of why the
+/// servers could not be listed.
+pub fn run(
+    scripts: &ScriptRegistry,
+    source: &str,
+    options: &Options,
+    timeouts: TimeoutSettings,
+    format: OutputFormat,
+) -> Result<usize, String> {
+    if !matches!(format.encoding(), None | Some(Encoding::Json)) {
+        return Err(format!(
+            "servers are printed as they are found, one line each, which the {format} format can't do"
+        ));
+    }
+
+    let runtime = query::runtime()?;
+
+    if source == VALVE_MASTER_SOURCE {
+        if options.lan {
+            return Err(format!("`{VALVE_MASTER_SOURCE}` can't be asked with --lan"));
+        }
+
+        return runtime.block_on(master(scripts, options, timeouts, format));
+    }
+
+    if !options.lan {
+        return Err(format!(
+            "`{source}` is not a master server; pass --lan to look for its servers on the \
+             local network"
+        ));
+    }
+
+    let game = query::find_scripted(scripts, source)?;
+    let parser = game.parser();
+    let payload = parser
+        .serialize_query(&ScriptQuery)
+        .map_err(|err| err.to_string())?;
+    let answers = runtime
+        .block_on(discovery::broadcast(
+            discovery::lan(game.port()),
+            &payload,
+            options.window,
+        ))
+        .map_err(|err| format!("failed to broadcast: {err}"))?;
+
+    for answer in &answers {
+        let outcome = options.query.then(|| {
+            parser
+                .deserialize_response(answer.data.clone())
+                .map(|response| {
+                    let meta = ResponseMeta::new(answer.latency).with_address(answer.address);
+                    query::answered(Fetched::<_, String>::new(response, meta))
+                })
+                .map_err(|err| err.to_string())
+        });
+        let found = match &outcome {
+            Some(outcome) => Found::Queried(QueryGame::Scripted(game), outcome),
+            None => Found::Listed(Some(QueryGame::Scripted(game))),
+        };
+
+        println!("{}", line(answer.address, found, format));
+    }
+
+    Ok(answers.len())
+}
+
+/// Lists the servers of a master server, querying each of them if asked to.
+async fn master(
+    scripts: &ScriptRegistry,
+    options: &Options,
+    timeouts: TimeoutSettings,
+    format: OutputFormat,
+) -> Result<usize, String> {
+    let game = match (&options.game, options.query) {
+        (Some(game), _) => Some(query::find_game(scripts, game)?),
+        (None, true) => return Err("--query needs the --game the servers run".to_string()),
+        (None, false) => None,
+    };
+
+    let master = match &options.master {
+        Some(master) => master.clone(),
+        None => HostPort::new(VALVE_MASTER.0, Some(VALVE_MASTER.1)),
+    };
+    let address = runtime::lookup_host(&master.host, master.port.unwrap_or(VALVE_MASTER.1))
+        .await
+        .map_err(|err| format!("failed to resolve {master}: {err}"))?
+        .into_iter()
+        .find(SocketAddr::is_ipv4)
+        .ok_or_else(|| format!("{master} has no IPv4 address"))?;
+
+    let servers = discovery::query_master(
+        address,
+        &MasterQuery::new().with_filter(options.filter.clone()),
+        &timeouts,
+    )
+    .await
+    .map_err(|err| format!("{master}: {err}"))?;
+
+    let servers: Vec<SocketAddr> = servers.into_iter().map(SocketAddr::V4).collect();
+    let count = servers.len();
+
+    match (game, options.query) {
+        (Some(game), true) => {
+            let mut results = QueryMany::new(servers, CONCURRENCY, |server| {
+                let target = HostPort::new(server.ip().to_string(), Some(server.port()));
+
+                async move { query::fetch(game, &target, timeouts).await }
+            });
+
+            while let Some((server, outcome)) = results.next().await {
+                println!("{}", line(server, Found::Queried(game, &outcome), format));
+            }
+        }
+        (game, _) => {
+            for server in servers {
+                println!("{}", line(server, Found::Listed(game), format));
+            }
+        }
+    }
+
+    Ok(count)
+}
+
+/// What is known about a discovered server.
+enum Found<'g> {
+    /// The server was listed, running the game if known.
+    Listed(Option<QueryGame<'g>>),
+    /// The server was queried.
+    Queried(QueryGame<'g>, &'g Result<Answered, String>),
+}
+
+/// Returns the line printed for a discovered server.
+///
+/// # Parameters
+///
+/// * `address`: The address of the server.
+/// * `found`: What is known about the server.
+/// * `format`: How the server is printed.
+fn line(address: SocketAddr, found: Found, format: OutputFormat) -> String {
+    let game =
+        |game: Option<QueryGame>| game.map(|game| json!({ "id": game.id(), "name": game.name() }));
+
+    match (found, format) {
+        (Found::Listed(listed), OutputFormat::Json) => json!({
+            "version": document::VERSION,
+            "game": game(listed),
+            "address": address,
+        })
+        .to_string(),
+        (Found::Listed(_), _) => address.to_string(),
+        (Found::Queried(queried, Ok(fetched)), OutputFormat::Json) => {
+            String::from_utf8_lossy(&Json.encode(&query::document(queried, fetched))).into_owned()
+        }
+        (Found::Queried(_, Ok(fetched)), _) => {
+            let info = fetched.response.to_common();
+
+            format!(
+                "{address}: {} on {}, {}/{} players",
+                info.name, info.map, info.players, info.max_players
+            )
+        }
+        (Found::Queried(queried, Err(err)), OutputFormat::Json) => json!({
+            "version": document::VERSION,
+            "game": game(Some(queried)),
+            "address": address,
+            "error": err,
+        })
+        .to_string(),
+        (Found::Queried(_, Err(err)), _) => format!("{address}: {err}"),
+    }
+}
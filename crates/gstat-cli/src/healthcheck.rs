@@ -0,0 +1,83 @@
+use gstat_core::prelude::{Fetched, Response};
+
+use std::{
+    fmt::{Display, Formatter, Result as FmtResult},
+    process::ExitCode,
+    time::Duration,
+};
+
+/// What a healthy server must report.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Thresholds {
+    /// The fewest players a healthy server has.
+    pub min_players: u32,
+    /// The longest round trip a healthy server answers within, if limited.
+    pub max_latency: Option<Duration>,
+}
+
+/// The outcome of `gstat healthcheck`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Health {
+    /// The server answered within the thresholds.
+    Healthy(String),
+    /// The server answered, but outside the thresholds.
+    Degraded(String),
+    /// The server could not be queried.
+    Unreachable(String),
+}
+
+impl Health {
+    /// Judges the outcome of a query against `thresholds`.
+    ///
+    /// # Parameters
+    ///
+    /// * `outcome`: The response, or a description of why the query failed.
+    /// * `thresholds`: What a healthy server must report.
+    pub fn judge<R: Response, E>(
+        outcome: Result<Fetched<R, E>, String>,
+        thresholds: Thresholds,
+    ) -> Self {
+        let fetched = match outcome {
+            Ok(fetched) => fetched,
+            Err(err) => return Health::Unreachable(err),
+        };
+
+        let info = fetched.response.to_common();
+        let latency = fetched.meta.latency();
+        let summary = format!("{}/{} players, {latency:?}", info.players, info.max_players);
+
+        if info.players < thresholds.min_players {
+            return Health::Degraded(format!(
+                "{summary}, fewer than {} players",
+                thresholds.min_players
+            ));
+        }
+
+        match thresholds.max_latency {
+            Some(limit) if latency > limit => {
+                Health::Degraded(format!("{summary}, slower than {limit:?}"))
+            }
+            _ => Health::Healthy(summary),
+        }
+    }
+
+    /// Returns the exit status reporting the outcome: `0` if healthy, `1` if degraded and
+    /// `2` if unreachable.
+    pub fn exit_code(&self) -> ExitCode {
+        match self {
+            Health::Healthy(_) => ExitCode::from(0),
+            Health::Degraded(_) => ExitCode::from(1),
+            Health::Unreachable(_) => ExitCode::from(2),
+        }
+    }
+}
+
+impl Display for Health {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            Health::Healthy(detail) => write!(f, "healthy: {detail}"),
+            Health::Degraded(detail) => write!(f, "degraded: {detail}"),
+            Health::Unreachable(detail) => write!(f, "unreachable: {detail}"),
+        }
+    }
+}
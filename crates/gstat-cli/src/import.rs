@@ -0,0 +1,296 @@
+use gstat_core::registry;
+
+use std::{
+    collections::BTreeSet,
+    error::Error as StdError,
+    fmt::{Display, Formatter, Result as FmtResult},
+};
+
+use serde::Deserialize;
+
+/// GameDig identifiers that differ from gstat's identifiers and aliases.
+const GAMEDIG_IDS: &[(&str, &str)] = &[
+    ("ase", "ark"),
+    ("battlefield2", "bf2"),
+    ("battlefield4", "bf4"),
+    ("mbe", "minecraft-bedrock"),
+    ("minecraftbe", "minecraft-bedrock"),
+];
+
+/// The formats servers can be imported from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum ImportFormat {
+    /// A JSON array of GameDig query options, e.g. `[{"type": "tf2", "host": "..."}]`.
+    Gamedig,
+    /// A CSV export with a header row, as produced by most hosting panels.
+    Csv,
+}
+
+/// A server read from another tool's export.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Imported {
+    /// The name the server is saved under.
+    pub name: String,
+    /// The gstat game identifier, or the original identifier if gstat doesn't know it.
+    pub game: String,
+    /// The address of the server.
+    pub address: String,
+    /// Whether `game` was recognized.
+    pub known: bool,
+}
+
+/// An error raised while importing servers.
+#[derive(Debug)]
+pub enum ImportError {
+    /// The GameDig JSON could not be parsed.
+    Json(serde_json::Error),
+    /// The CSV export is missing a required column.
+    MissingColumn(&'static str),
+    /// A CSV row is missing a value.
+    MissingValue {
+        /// The 1-based line of the row.
+        line: usize,
+        /// The name of the column.
+        column: &'static str,
+    },
+}
+
+impl Display for ImportError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            Self::Json(err) => write!(f, "invalid GameDig JSON: {err}"),
+            Self::MissingColumn(column) => write!(f, "CSV export has no `{column}` column"),
+            Self::MissingValue { line, column } => {
+                write!(f, "line {line} of the CSV export has no `{column}`")
+            }
+        }
+    }
+}
+
+impl StdError for ImportError {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        match self {
+            Self::Json(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+/// The subset of GameDig's query options describing a server.
+#[derive(Deserialize)]
+struct GamedigEntry {
+    #[serde(rename = "type")]
+    game: String,
+    host: String,
+    port: Option<u16>,
+    name: Option<String>,
+}
+
+/// Reads servers from an export.
+///
+/// # Parameters
+///
+/// * `format`: The format of the export.
+/// * `input`: The contents of the export.
+///
+/// # Returns
+///
+/// A `Result` containing either the servers in the order they appear or an `ImportError`.
+pub fn import(format: ImportFormat, input: &str) -> Result<Vec<Imported>, ImportError> {
+    let servers = match format {
+        ImportFormat::Gamedig => gamedig(input)?,
+        ImportFormat::Csv => csv(input)?,
+    };
+
+    let mut taken = BTreeSet::new();
+
+    Ok(servers
+        .into_iter()
+        .map(|(name, game, address)| {
+            let resolved = resolve_game(&game);
+            let name = unique_name(&mut taken, &name);
+
+            Imported {
+                name,
+                known: resolved.is_some(),
+                game: resolved.map(str::to_string).unwrap_or(game),
+                address,
+            }
+        })
+        .collect())
+}
+
+/// Renders imported servers as a profile section for `config.toml`.
+///
+/// # Parameters
+///
+/// * `profile`: The name of the profile the servers are added to.
+/// * `servers`: The imported servers.
+pub fn to_toml(profile: &str, servers: &[Imported]) -> String {
+    let mut table = toml::Table::new();
+
+    for server in servers {
+        let mut favorite = toml::Table::new();
+        favorite.insert("game".into(), server.game.clone().into());
+        favorite.insert("address".into(), server.address.clone().into());
+        table.insert(server.name.clone(), favorite.into());
+    }
+
+    let mut servers = toml::Table::new();
+    servers.insert("servers".into(), table.into());
+
+    let mut profiles = toml::Table::new();
+    profiles.insert(profile.into(), servers.into());
+
+    let mut root = toml::Table::new();
+    root.insert("profiles".into(), profiles.into());
+
+    toml::to_string(&root).expect("tables of strings always serialize")
+}
+
+/// Reads a GameDig JSON array.
+fn gamedig(input: &str) -> Result<Vec<(String, String, String)>, ImportError> {
+    let entries: Vec<GamedigEntry> = serde_json::from_str(input).map_err(ImportError::Json)?;
+
+    Ok(entries
+        .into_iter()
+        .map(|entry| {
+            let address = match entry.port {
+                Some(port) => join_host_port(&entry.host, port),
+                None => entry.host,
+            };
+            let name = entry.name.unwrap_or_else(|| address.clone());
+
+            (name, entry.game, address)
+        })
+        .collect())
+}
+
+/// Reads a CSV export with `game` and `host` (or `ip`/`address`) columns, and optional
+/// `name` and `port` columns.
+fn csv(input: &str) -> Result<Vec<(String, String, String)>, ImportError> {
+    let mut lines = input
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty());
+
+    let Some((_, header)) = lines.next() else {
+        return Ok(Vec::new());
+    };
+
+    let header: Vec<String> = split_csv(header)
+        .into_iter()
+        .map(|column| column.trim().to_ascii_lowercase())
+        .collect();
+    let column = |names: &[&str]| header.iter().position(|column| names.contains(&&**column));
+
+    let game = column(&["game", "type"]).ok_or(ImportError::MissingColumn("game"))?;
+    let host = column(&["host", "ip", "address"]).ok_or(ImportError::MissingColumn("host"))?;
+    let port = column(&["port", "query_port"]);
+    let name = column(&["name", "server", "server_name"]);
+
+    lines
+        .map(|(index, line)| {
+            let fields = split_csv(line);
+            let field = |position: Option<usize>| {
+                position
+                    .and_then(|position| fields.get(position))
+                    .map(|field| field.trim())
+                    .filter(|field| !field.is_empty())
+            };
+            let required = |position, column| {
+                field(Some(position)).ok_or(ImportError::MissingValue {
+                    line: index + 1,
+                    column,
+                })
+            };
+
+            let game = required(game, "game")?.to_string();
+            let host = required(host, "host")?;
+            let address = match field(port).and_then(|port| port.parse().ok()) {
+                Some(port) => join_host_port(host, port),
+                None => host.to_string(),
+            };
+            let name = field(name).map_or_else(|| address.clone(), str::to_string);
+
+            Ok((name, game, address))
+        })
+        .collect()
+}
+
+/// Splits a CSV line into fields, honoring double-quoted fields.
+fn split_csv(line: &str) -> Vec<String> {
+    let mut fields = vec![String::new()];
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        let field = fields.last_mut().expect("there is always a field");
+
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(String::new()),
+            c => field.push(c),
+        }
+    }
+
+    fields
+}
+
+/// Maps a game identifier from another tool to gstat's identifier.
+fn resolve_game(game: &str) -> Option<&'static str> {
+    let game = GAMEDIG_IDS
+        .iter()
+        .find(|(other, _)| other.eq_ignore_ascii_case(game))
+        .map_or(game, |(_, id)| id);
+
+    registry::game(game).map(|entry| entry.id)
+}
+
+/// Joins a host and port, bracketing IPv6 addresses.
+fn join_host_port(host: &str, port: u16) -> String {
+    if host.contains(':') && !host.starts_with('[') {
+        format!("[{host}]:{port}")
+    } else {
+        format!("{host}:{port}")
+    }
+}
+
+/// Turns `name` into a key that is not yet taken.
+fn unique_name(taken: &mut BTreeSet<String>, name: &str) -> String {
+    let base: String = name
+        .trim()
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                c.to_ascii_lowercase()
+            } else {
+                '-'
+            }
+        })
+        .collect();
+    let base = base
+        .split('-')
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("-");
+    let base = match base.as_str() {
+        "" => "server",
+        base => base,
+    };
+
+    let name = (1..)
+        .map(|count| match count {
+            1 => base.to_string(),
+            count => format!("{base}-{count}"),
+        })
+        .find(|name| !taken.contains(name))
+        .expect("some suffix is free");
+
+    taken.insert(name.clone());
+    name
+}
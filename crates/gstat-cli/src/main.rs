@@ -0,0 +1,625 @@
+mod bulk;
+mod config;
+mod detect;
+mod discover;
+mod healthcheck;
+mod import;
+mod query;
+mod rcon;
+mod selftest;
+mod watch;
+
+use config::{Config, OutputFormat, Profile};
+use gstat_core::{
+    duration,
+    fleet::{Fleet, Sink},
+    resolve::HostPort,
+    uptime::Alert,
+};
+use healthcheck::{Health, Thresholds};
+use import::ImportFormat;
+use query::{Compat, Sections};
+use selftest::Report;
+
+use std::{
+    fs,
+    io::{self, Read, Write},
+    path::{Path, PathBuf},
+    process::ExitCode,
+    time::Duration,
+};
+
+use clap::{Parser, Subcommand};
+
+/// Query game servers from the command line.
+#[derive(Debug, Parser)]
+#[command(name = "gstat", version)]
+struct Cli {
+    /// The configuration file to use instead of `~/.config/gstat/config.toml`.
+    #[arg(long, global = true, value_name = "PATH")]
+    config: Option<PathBuf>,
+
+    /// The configuration profile to use.
+    #[arg(long, short, global = true, env = "GSTAT_PROFILE")]
+    profile: Option<String>,
+
+    /// A game script to load, making its game available to `query`. May be repeated.
+    #[arg(long = "script", global = true, value_name = "PATH")]
+    scripts: Vec<PathBuf>,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Query a server and print what it reports.
+    ///
+    /// Exits with a failure status if the server can't be queried.
+    Query {
+        /// The game the server runs, e.g. `tf2`.
+        game: String,
+        /// The server address, as `host` or `host:port`. Without a port, the game's
+        /// default port is used.
+        address: HostPort,
+        /// The limit for every step of the query, e.g. `2s`, overriding the profile.
+        #[arg(long, value_parser = duration::parse_duration)]
+        timeout: Option<Duration>,
+        /// Print the player list.
+        #[arg(long)]
+        players: bool,
+        /// Print the rules or key/value settings.
+        #[arg(long)]
+        rules: bool,
+        /// How the response is printed, overriding the profile. The JSON document always
+        /// includes the players and rules.
+        #[arg(long, value_enum)]
+        format: Option<OutputFormat>,
+        /// Print the response in the result shape of another query library instead, e.g.
+        /// `gamedig` for node-gamedig's JSON result, so its consumers can switch to gstat.
+        #[arg(long, value_enum, conflicts_with_all = ["format", "watch"])]
+        compat: Option<Compat>,
+        /// Query the server again every interval, e.g. `10s`, highlighting changes to the
+        /// map and player count, until interrupted.
+        #[arg(long, value_name = "INTERVAL", value_parser = duration::parse_duration)]
+        watch: Option<Duration>,
+        /// Save the packets exchanged with the server to a fixture file, for replaying
+        /// them in parser tests. Needs a build with the `record` feature.
+        #[arg(long, value_name = "PATH", conflicts_with = "watch")]
+        record: Option<PathBuf>,
+    },
+    /// Query many servers and print each result as a line of JSON as soon as it arrives.
+    ///
+    /// The input lists one `<game> <address>` per line; blank lines and lines starting
+    /// with `#` are skipped. Servers that fail are reported in the output and don't stop
+    /// the others.
+    Bulk {
+        /// The server list to read, or `-` for stdin.
+        #[arg(long, short, default_value = "-")]
+        input: PathBuf,
+        /// The largest number of servers queried at the same time.
+        #[arg(long, default_value_t = bulk::DEFAULT_CONCURRENCY)]
+        concurrency: usize,
+        /// The limit for every step of each query, e.g. `2s`, overriding the profile.
+        #[arg(long, value_parser = duration::parse_duration)]
+        timeout: Option<Duration>,
+    },
+    /// Check a server for a container HEALTHCHECK.
+    ///
+    /// Exits with 0 if the server is healthy, 1 if it answered outside the thresholds and
+    /// 2 if it could not be queried.
+    Healthcheck {
+        /// The game the server runs, e.g. `tf2`.
+        game: String,
+        /// The server address, as `host` or `host:port`.
+        address: HostPort,
+        /// The fewest players a healthy server has.
+        #[arg(long, default_value_t = 0)]
+        min_players: u32,
+        /// The longest round trip of a healthy server, e.g. `500ms`.
+        #[arg(long, value_parser = duration::parse_duration)]
+        max_latency: Option<Duration>,
+        /// The limit for every step of the query, e.g. `2s`, overriding the profile.
+        #[arg(long, value_parser = duration::parse_duration)]
+        timeout: Option<Duration>,
+    },
+    /// List servers from a master server or the local network.
+    ///
+    /// `gstat discover valve-master --filter '\\appid\\440'` lists the servers known to
+    /// Valve's master server, and `gstat discover <game> --lan` broadcasts the game's query
+    /// to the local network. Each server is printed as soon as it is found.
+    Discover {
+        /// `valve-master`, or the game to look for on the local network with `--lan`.
+        source: String,
+        /// Broadcast the game's query to the local network.
+        #[arg(long)]
+        lan: bool,
+        /// The master server filter, e.g. `\\appid\\440\\empty\\1`.
+        #[arg(long, default_value = "")]
+        filter: String,
+        /// The master server to ask instead of Valve's, as `host:port`.
+        #[arg(long)]
+        master: Option<HostPort>,
+        /// The game the servers of a master list run, e.g. to `--query` them.
+        #[arg(long)]
+        game: Option<String>,
+        /// Follow up on each server with a full query.
+        #[arg(long)]
+        query: bool,
+        /// How long answers to a LAN broadcast are collected, e.g. `2s`.
+        #[arg(long, value_parser = duration::parse_duration, default_value = "2s")]
+        window: Duration,
+        /// The limit for the master server and each follow-up query, e.g. `2s`.
+        #[arg(long, value_parser = duration::parse_duration)]
+        timeout: Option<Duration>,
+        /// How servers are printed, overriding the profile: plain, table or json.
+        #[arg(long, value_enum)]
+        format: Option<OutputFormat>,
+    },
+    /// Guess the protocol and game of a server from its address alone.
+    ///
+    /// Sends the status requests of several protocols at once and prints the most likely
+    /// answer. Without a port, each protocol is tried on the default query ports of its
+    /// games.
+    Detect {
+        /// The server address, as `host` or `host:port`.
+        address: HostPort,
+        /// How long each request waits for its answer, e.g. `2s`.
+        #[arg(long, value_parser = duration::parse_duration, default_value = "2s")]
+        timeout: Duration,
+    },
+    /// Open a server's remote console.
+    ///
+    /// Without `--exec`, commands are read from an interactive shell with history until
+    /// `exit`. Games without a Source RCON console are refused; pass `source-rcon` as the
+    /// game and the console's port to reach other servers speaking the protocol.
+    Rcon {
+        /// The game the server runs, e.g. `tf2`, or `source-rcon`.
+        game: String,
+        /// The console address, as `host` or `host:port`. Without a port, the game's
+        /// default port is used.
+        address: HostPort,
+        /// The RCON password.
+        #[arg(long, env = "GSTAT_RCON_PASSWORD", hide_env_values = true)]
+        password: String,
+        /// Run a single command, print its output and exit.
+        #[arg(long, value_name = "COMMAND")]
+        exec: Option<String>,
+        /// The limit for connecting and for each command, e.g. `2s`, overriding the profile.
+        #[arg(long, value_parser = duration::parse_duration)]
+        timeout: Option<Duration>,
+    },
+    /// Inspect the configuration profiles.
+    #[command(subcommand)]
+    Profiles(ProfilesCommand),
+    /// Convert another tool's server list into a profile for `config.toml`.
+    ///
+    /// The profile is printed to stdout, ready to be appended to the configuration file.
+    Import {
+        /// The format of the server list.
+        #[arg(long, value_enum)]
+        from: ImportFormat,
+        /// The file to read, or `-` for stdin.
+        input: PathBuf,
+        /// The name of the profile the servers are added to.
+        #[arg(long, default_value = "imported")]
+        into: String,
+    },
+    /// Check a fleet file and show the servers, alerts and sinks it describes.
+    ///
+    /// Loads the scripts the fleet names along with `--script`, and exits with a failure
+    /// status if the file is invalid or any of its games can't be queried.
+    Fleet {
+        /// The fleet file.
+        path: PathBuf,
+    },
+    /// Check that gstat can run here, e.g. before a deployment or for a support ticket.
+    ///
+    /// Validates the configuration, parses the embedded fixture corpus and opens test
+    /// sockets. Exits with a failure status if any check fails.
+    Selftest {
+        /// How the report is printed, overriding the profile.
+        #[arg(long, value_enum)]
+        format: Option<OutputFormat>,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum ProfilesCommand {
+    /// List the configured profiles, marking the selected one.
+    List,
+    /// Show the resolved settings of the selected profile.
+    Show,
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+
+    if let Command::Healthcheck { .. } = cli.command {
+        let health = healthcheck(cli);
+        println!("{health}");
+
+        return health.exit_code();
+    }
+
+    match run(cli) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("gstat: {err}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
+    if let Command::Import { from, input, into } = &cli.command {
+        return import_servers(*from, input, into);
+    }
+
+    if let Command::Fleet { path } = &cli.command {
+        return show_fleet(path, &cli.scripts);
+    }
+
+    if let Command::Selftest { format } = cli.command {
+        return selftest(cli.config.as_deref(), cli.profile.as_deref(), format);
+    }
+
+    let config = Config::load(cli.config.as_deref())?;
+    let (name, profile) = config.profile(cli.profile.as_deref())?;
+
+    match cli.command {
+        Command::Profiles(ProfilesCommand::List) => {
+            for profile in config.profiles.keys() {
+                let marker = if *profile == name { "*" } else { " " };
+                println!("{marker} {profile}");
+            }
+        }
+        Command::Profiles(ProfilesCommand::Show) => show_profile(&name, &profile),
+        Command::Query {
+            game,
+            address,
+            timeout,
+            players,
+            rules,
+            format,
+            compat,
+            watch,
+            record,
+        } => query_server(
+            &cli.scripts,
+            &game,
+            &address,
+            query::timeouts(timeout, &profile),
+            Sections { players, rules },
+            format.or(profile.format).unwrap_or_default(),
+            match watch {
+                Some(interval) => Repeat::Watch(interval),
+                None => Repeat::Once {
+                    record: record.as_deref(),
+                    compat,
+                },
+            },
+        )?,
+        Command::Bulk {
+            input,
+            concurrency,
+            timeout,
+        } => query_bulk(
+            &cli.scripts,
+            &input,
+            concurrency,
+            query::timeouts(timeout, &profile),
+        )?,
+        Command::Discover {
+            source,
+            lan,
+            filter,
+            master,
+            game,
+            query,
+            window,
+            timeout,
+            format,
+        } => {
+            let scripts = query::load_scripts(&cli.scripts)?;
+            let options = discover::Options {
+                lan,
+                filter,
+                master,
+                game,
+                query,
+                window,
+            };
+            let found = discover::run(
+                &scripts,
+                &source,
+                &options,
+                query::timeouts(timeout, &profile),
+                format.or(profile.format).unwrap_or_default(),
+            )?;
+
+            eprintln!("gstat: found {found} servers");
+        }
+        Command::Detect { address, timeout } => {
+            print!("{}", detect::render(&detect::run(&address, timeout)?));
+        }
+        Command::Rcon {
+            game,
+            address,
+            password,
+            exec,
+            timeout,
+        } => rcon_console(
+            &game,
+            &address,
+            &password,
+            exec.as_deref(),
+            query::timeouts(timeout, &profile),
+        )?,
+        Command::Healthcheck { .. } => unreachable!("health checks report their own status"),
+        Command::Import { .. } => unreachable!("imports don't need a configuration"),
+        Command::Fleet { .. } => unreachable!("fleets don't need a configuration"),
+        Command::Selftest { .. } => unreachable!("the self-test loads the configuration itself"),
+    }
+
+    Ok(())
+}
+
+/// How often `gstat query` queries the server.
+enum Repeat<'p> {
+    /// Once, saving the packets to a fixture file if `record` is set and printing the
+    /// response in the result shape of `compat` if it is set.
+    Once {
+        record: Option<&'p Path>,
+        compat: Option<Compat>,
+    },
+    /// Every interval until interrupted.
+    Watch(Duration),
+}
+
+/// Queries a server and prints the response, as often as `repeat` says.
+fn query_server(
+    scripts: &[PathBuf],
+    game: &str,
+    address: &HostPort,
+    timeouts: gstat_core::prelude::TimeoutSettings,
+    sections: Sections,
+    format: OutputFormat,
+    repeat: Repeat<'_>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let scripts = query::load_scripts(scripts)?;
+    let game = query::find_game(&scripts, game)?;
+
+    let (record, compat) = match repeat {
+        Repeat::Once { record, compat } => (record, compat),
+        Repeat::Watch(interval) if interval.is_zero() => {
+            return Err("the watch interval must be above zero".into());
+        }
+        Repeat::Watch(interval) => {
+            return Ok(watch::run(
+                game, address, timeouts, sections, format, interval,
+            )?);
+        }
+    };
+
+    let fetched = query::query(game, address, timeouts)?;
+
+    if let Some(path) = record {
+        query::record(game, &fetched, path)?;
+    }
+
+    let rendered = match compat {
+        Some(compat) => query::compat(compat, &fetched)?,
+        None => query::render(game, &fetched, sections, format),
+    };
+
+    let mut stdout = io::stdout().lock();
+    stdout.write_all(&rendered)?;
+    stdout.flush()?;
+
+    Ok(())
+}
+
+/// Queries the servers of a list, reporting how many failed on stderr.
+fn query_bulk(
+    scripts: &[PathBuf],
+    input: &Path,
+    concurrency: usize,
+    timeouts: gstat_core::prelude::TimeoutSettings,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let scripts = query::load_scripts(scripts)?;
+
+    let (total, failed) = if input.as_os_str() == "-" {
+        bulk::run(
+            &scripts,
+            io::BufReader::new(io::stdin()),
+            concurrency,
+            timeouts,
+        )?
+    } else {
+        let file = fs::File::open(input)
+            .map_err(|err| format!("failed to read {}: {err}", input.display()))?;
+
+        bulk::run(&scripts, io::BufReader::new(file), concurrency, timeouts)?
+    };
+
+    eprintln!("gstat: {} of {total} servers answered", total - failed);
+
+    Ok(())
+}
+
+/// Runs a single command on a remote console, or opens a shell on it.
+fn rcon_console(
+    game: &str,
+    address: &HostPort,
+    password: &str,
+    exec: Option<&str>,
+    timeouts: gstat_core::prelude::TimeoutSettings,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let target = rcon::target(game, address)?;
+    let runtime = query::runtime()?;
+    let mut console = runtime.block_on(rcon::open(&target, password, &timeouts))?;
+
+    match exec {
+        Some(command) => {
+            let output = runtime
+                .block_on(console.exec(command, &timeouts))
+                .map_err(|err| format!("{target}: {err}"))?;
+
+            rcon::print_output(&output);
+        }
+        None => rcon::shell(&runtime, &mut console, &timeouts)?,
+    }
+
+    let _ = runtime.block_on(console.close());
+
+    Ok(())
+}
+
+/// Queries a server and judges its health.
+///
+/// Anything preventing the query, such as an invalid configuration, counts as unreachable.
+fn healthcheck(cli: Cli) -> Health {
+    let Command::Healthcheck {
+        game,
+        address,
+        min_players,
+        max_latency,
+        timeout,
+    } = cli.command
+    else {
+        unreachable!("only called for health checks");
+    };
+
+    let outcome = (|| {
+        let config = Config::load(cli.config.as_deref()).map_err(|err| err.to_string())?;
+        let (_, profile) = config
+            .profile(cli.profile.as_deref())
+            .map_err(|err| err.to_string())?;
+        let scripts = query::load_scripts(&cli.scripts)?;
+        let game = query::find_game(&scripts, &game)?;
+
+        query::query(game, &address, query::timeouts(timeout, &profile))
+    })();
+
+    Health::judge(
+        outcome,
+        Thresholds {
+            min_players,
+            max_latency,
+        },
+    )
+}
+
+/// Prints the resolved settings of a profile, hiding API keys.
+fn show_profile(name: &str, profile: &Profile) {
+    let timeouts = profile.timeouts();
+    let limit = |limit: Option<std::time::Duration>| match limit {
+        Some(limit) => format!("{limit:?}"),
+        None => "none".to_string(),
+    };
+
+    println!("profile: {name}");
+    println!("format: {}", profile.format.unwrap_or_default());
+    println!("connect timeout: {}", limit(timeouts.connect));
+    println!("read timeout: {}", limit(timeouts.read));
+    println!("write timeout: {}", limit(timeouts.write));
+    println!("overall timeout: {}", limit(timeouts.overall));
+
+    for (server, favorite) in &profile.servers {
+        println!("server {server}: {} {}", favorite.game, favorite.address);
+    }
+
+    for service in profile.api_keys.keys() {
+        println!("api key {service}: ********");
+    }
+}
+
+/// Loads a fleet file and prints what it monitors, failing if any of its games can't be
+/// queried.
+fn show_fleet(path: &Path, scripts: &[PathBuf]) -> Result<(), Box<dyn std::error::Error>> {
+    let fleet = Fleet::from_path(path)?;
+    let loaded = query::load_scripts(&[fleet.scripts.as_slice(), scripts].concat())?;
+
+    for target in &fleet.targets {
+        let game = query::find_scripted(&loaded, &target.game)
+            .map_err(|err| format!("target `{}`: {err}", target.name))?;
+
+        println!(
+            "target {}: {} {} every {:?}",
+            target.name,
+            game.id(),
+            target.address,
+            fleet.interval_of(target)
+        );
+    }
+
+    for alert in &fleet.alerts {
+        match alert {
+            Alert::Down { polls } => println!("alert: down for {polls} poll(s)"),
+            Alert::PlayersBelow { players, polls } => {
+                println!("alert: below {players} player(s) for {polls} poll(s)")
+            }
+            Alert::AvailabilityBelow { ratio, window } => {
+                println!("alert: availability below {ratio} over {window:?}")
+            }
+        }
+    }
+
+    for sink in &fleet.sinks {
+        match sink {
+            Sink::Webhook(webhook) => println!("sink webhook: {}", webhook.url()),
+            Sink::Mqtt { broker, .. } => println!("sink mqtt: {broker}"),
+            Sink::Store { path } => println!("sink store: {}", path.display()),
+        }
+    }
+
+    Ok(())
+}
+
+/// Runs the self-test and prints its report, failing if any check failed.
+fn selftest(
+    config: Option<&Path>,
+    profile: Option<&str>,
+    format: Option<OutputFormat>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let (report, profile) = Report::run(config, profile);
+    let format = format
+        .or(profile.and_then(|profile| profile.format))
+        .unwrap_or_default();
+
+    report.print(format);
+
+    match report.failures() {
+        0 => Ok(()),
+        failures => Err(format!("{failures} of {} checks failed", report.checks.len()).into()),
+    }
+}
+
+/// Prints the servers of an export as a profile, warning about unrecognized games.
+fn import_servers(
+    format: ImportFormat,
+    input: &PathBuf,
+    profile: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let contents = if input.as_os_str() == "-" {
+        let mut contents = String::new();
+        io::stdin().read_to_string(&mut contents)?;
+        contents
+    } else {
+        fs::read_to_string(input)?
+    };
+
+    let servers = import::import(format, &contents)?;
+
+    for server in servers.iter().filter(|server| !server.known) {
+        eprintln!(
+            "gstat: warning: `{}` uses unknown game `{}`",
+            server.name, server.game
+        );
+    }
+
+    print!("{}", import::to_toml(profile, &servers));
+
+    Ok(())
+}
@@ -0,0 +1,519 @@
+use crate::config::{OutputFormat, Profile};
+
+#[cfg(feature = "record")]
+use gstat_core::prelude::Parser;
+use gstat_core::{
+    compat::gamedig::Gamedig,
+    document,
+    encode::{Encode, Json},
+    prelude::{
+        Error, Fetched, Game, Player, Response, ServerInfo, TimeoutSettings, Value as Field,
+    },
+    resolve::{HostPort, SystemResolver},
+    script::{ScriptGame, ScriptQuery, ScriptRegistry},
+};
+pub use gstat_tcp::lookup::QueryGame;
+use gstat_tcp::{
+    lookup::{self, LookupError},
+    slp::{Minecraft, SlpQuery},
+};
+#[cfg(feature = "record")]
+use gstat_test::Fixture;
+use gstat_udp::script::Scripted;
+
+use std::{
+    convert::Infallible,
+    fmt::Display,
+    fs,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use serde_json::Value;
+
+/// The limit applied to every step of a query when neither `--timeout` nor the profile
+/// sets one, so an unreachable server doesn't stall the command.
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// What the plain and table formats print besides the server description.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Sections {
+    /// Print the player list.
+    pub players: bool,
+    /// Print the rules or key/value settings.
+    pub rules: bool,
+}
+
+/// Loads the game scripts given with `--script`.
+///
+/// # Parameters
+///
+/// * `paths`: The script files, in the order they were given.
+///
+/// # Returns
+///
+/// A `Result` containing either the registry of scripted games or a description of the
+/// script that could not be loaded.
+pub fn load_scripts(paths: &[PathBuf]) -> Result<ScriptRegistry, String> {
+    let mut scripts = ScriptRegistry::new();
+
+    for path in paths {
+        let source = fs::read_to_string(path)
+            .map_err(|err| format!("failed to read {}: {err}", path.display()))?;
+
+        scripts
+            .load(&source)
+            .map_err(|err| format!("invalid script {}: {err}", path.display()))?;
+    }
+
+    Ok(scripts)
+}
+
+/// What a server answered, in the common model, so the responses of scripted and native
+/// games are rendered alike.
+#[derive(Clone, Debug, Default)]
+pub struct Answer {
+    info: ServerInfo,
+    players: Vec<Player>,
+}
+
+impl Response for Answer {
+    type E = Infallible;
+
+    fn new() -> Result<Self, Error<Self::E>> {
+        Ok(Answer::default())
+    }
+
+    fn to_common(&self) -> ServerInfo {
+        self.info.clone()
+    }
+
+    fn players(&self) -> Vec<Player> {
+        self.players.clone()
+    }
+}
+
+/// The answer of a server and how it was received, with the warnings described.
+pub type Answered = Fetched<Answer, String>;
+
+/// Converts the response of any game into an `Answered`.
+pub fn answered<R: Response, E: Display>(fetched: Fetched<R, E>) -> Answered {
+    Fetched {
+        response: Answer {
+            info: fetched.response.to_common(),
+            players: fetched.response.players(),
+        },
+        meta: fetched.meta,
+        warnings: fetched
+            .warnings
+            .into_iter()
+            .map(|warning| warning.map(|err| err.to_string()))
+            .collect(),
+    }
+}
+
+/// Looks up the game to query by its identifier or one of its aliases.
+///
+/// The lookup is the one of every frontend, `gstat_tcp::lookup::find_game`: scripted games
+/// over UDP are queried with their script, and registry games with the protocol
+/// implementations of `gstat-tcp`, which cover Minecraft: Java Edition.
+///
+/// # Parameters
+///
+/// * `scripts`: The scripted games loaded with `--script`.
+/// * `identifier`: The game given on the command line.
+///
+/// # Returns
+///
+/// A `Result` containing either the game or a description of why it can't be queried.
+pub fn find_game<'s>(
+    scripts: &'s ScriptRegistry,
+    identifier: &str,
+) -> Result<QueryGame<'s>, String> {
+    lookup::find_game(scripts, identifier).map_err(|err| match err {
+        LookupError::UnsupportedProtocol(_) => format!("{err} and pass it with --script"),
+        err => err.to_string(),
+    })
+}
+
+/// Looks up a scripted game, for the commands that need its script, e.g. to broadcast its
+/// query.
+///
+/// # Returns
+///
+/// A `Result` containing either the scripted game or a description of why it can't be
+/// used.
+pub fn find_scripted<'s>(
+    scripts: &'s ScriptRegistry,
+    identifier: &str,
+) -> Result<&'s ScriptGame, String> {
+    match find_game(scripts, identifier)? {
+        QueryGame::Scripted(game) => Ok(game),
+        QueryGame::Native(entry) => Err(format!(
+            "{} has no game script, which this command needs; pass one with --script",
+            entry.name
+        )),
+    }
+}
+
+/// Returns the time limits for a query.
+///
+/// `--timeout` overrides the profile, which falls back to `DEFAULT_TIMEOUT`.
+///
+/// # Parameters
+///
+/// * `timeout`: The limit given with `--timeout`, if any.
+/// * `profile`: The selected profile.
+pub fn timeouts(timeout: Option<Duration>, profile: &Profile) -> TimeoutSettings {
+    match timeout {
+        Some(limit) => TimeoutSettings::uniform(limit),
+        None => Profile {
+            timeout: profile.timeout.or(Some(DEFAULT_TIMEOUT)),
+            ..profile.clone()
+        }
+        .timeouts(),
+    }
+}
+
+/// Queries a server, on a runtime of its own.
+///
+/// # Parameters
+///
+/// * `game`: The game the server runs.
+/// * `target`: The host of the server, with an optional port.
+/// * `timeouts`: The time limits for each address.
+///
+/// # Returns
+///
+/// A `Result` containing either the response or a description of why the query failed.
+pub fn query(
+    game: QueryGame<'_>,
+    target: &HostPort,
+    timeouts: TimeoutSettings,
+) -> Result<Answered, String> {
+    runtime()?.block_on(fetch(game, target, timeouts))
+}
+
+/// Creates the runtime queries run on.
+pub fn runtime() -> Result<tokio::runtime::Runtime, String> {
+    tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .map_err(|err| format!("failed to start the runtime: {err}"))
+}
+
+/// Queries a server with `Game::fetch_host`.
+///
+/// Without a port in `target`, the game's default port is used, or the SRV record of
+/// registry games that publish one. A host name resolving to several addresses is queried
+/// at each address in turn until one answers.
+///
+/// # Parameters
+///
+/// * `game`: The game the server runs.
+/// * `target`: The host of the server, with an optional port.
+/// * `timeouts`: The time limits for each address.
+///
+/// # Returns
+///
+/// A `Result` containing either the response or a description of the error of the last
+/// address tried.
+pub async fn fetch(
+    game: QueryGame<'_>,
+    target: &HostPort,
+    timeouts: TimeoutSettings,
+) -> Result<Answered, String> {
+    let host = target.to_string();
+    let failed = |err: &dyn Display| format!("{host}: {err}");
+
+    match game {
+        QueryGame::Scripted(game) => Scripted(game)
+            .fetch_host(ScriptQuery, &host, &SystemResolver, timeouts)
+            .await
+            .map(answered)
+            .map_err(|err| failed(&err)),
+        QueryGame::Native(_) => Minecraft
+            .fetch_host(SlpQuery::default(), &host, &SystemResolver, timeouts)
+            .await
+            .map(answered)
+            .map_err(|err| failed(&err)),
+    }
+}
+
+/// Saves the packets of a query to a fixture file for `--record`.
+///
+/// The fixture format is the one of `gstat-test`, which only builds with the `record`
+/// feature are linked with.
+///
+/// # Parameters
+///
+/// * `game`: The game the server runs, whose parser serializes the query again.
+/// * `fetched`: The response, whose metadata holds the packets received.
+/// * `path`: The fixture file to write.
+#[cfg(feature = "record")]
+pub fn record(game: QueryGame<'_>, fetched: &Answered, path: &Path) -> Result<(), String> {
+    let QueryGame::Scripted(game) = game else {
+        return Err(format!(
+            "`{}` is queried over TCP, which fixtures can't record yet",
+            game.id()
+        ));
+    };
+    let request = game
+        .parser()
+        .serialize_query(&ScriptQuery)
+        .map_err(|err| format!("failed to serialize the query: {err}"))?;
+
+    Fixture::record(request, &fetched.meta)
+        .save(path)
+        .map_err(|err| format!("failed to write {}: {err}", path.display()))
+}
+
+/// Refuses `--record` in builds without the `record` feature.
+#[cfg(not(feature = "record"))]
+pub fn record(_game: QueryGame<'_>, _fetched: &Answered, _path: &Path) -> Result<(), String> {
+    Err("this build can't record fixtures; rebuild gstat with the `record` feature".to_string())
+}
+
+/// Renders a response in `format`.
+///
+/// The plain and table formats only include the players and rules if `sections` asks for
+/// them, while the JSON document always includes both, so scripts get the same shape
+/// whatever flags they pass. The CSV, MessagePack and XML formats encode the JSON document.
+///
+/// # Parameters
+///
+/// * `game`: The game the server runs.
+/// * `fetched`: The response and its metadata.
+/// * `sections`: What is rendered besides the server description.
+/// * `format`: How the response is rendered.
+pub fn render(
+    game: QueryGame<'_>,
+    fetched: &Answered,
+    sections: Sections,
+    format: OutputFormat,
+) -> Vec<u8> {
+    match format {
+        OutputFormat::Plain => plain(game, fetched, sections).into_bytes(),
+        OutputFormat::Table => table(game, fetched, sections).into_bytes(),
+        OutputFormat::Json => [Json.encode(&document(game, fetched)), b"\n".to_vec()].concat(),
+        format => format
+            .encoding()
+            .map(|encoding| encoding.encode(&document(game, fetched)))
+            .unwrap_or_default(),
+    }
+}
+
+/// The result shapes of other query libraries `--compat` renders responses in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum Compat {
+    /// node-gamedig's result object, as JSON.
+    Gamedig,
+}
+
+/// Renders a response in the result shape of another query library, for `--compat`.
+///
+/// # Parameters
+///
+/// * `compat`: The library whose result shape is rendered.
+/// * `fetched`: The response and its metadata.
+///
+/// # Returns
+///
+/// A `Result` containing either the rendered response or a description of why it can't be
+/// rendered.
+pub fn compat(compat: Compat, fetched: &Answered) -> Result<Vec<u8>, String> {
+    let address = fetched
+        .meta
+        .address()
+        .ok_or("the response has no address to report")?;
+
+    match compat {
+        Compat::Gamedig => Ok(format!(
+            "{}\n",
+            Gamedig::new(&fetched.response.info, address)
+                .with_players(&fetched.response.players)
+                .with_ping(fetched.meta.latency())
+        )
+        .into_bytes()),
+    }
+}
+
+/// Encodes a JSON document, such as the self-test report, in one of the formats with an
+/// `Encoding`.
+pub fn encode(format: OutputFormat, document: &Value) -> Vec<u8> {
+    match format.encoding() {
+        Some(encoding) => encoding.encode(&from_json(document)),
+        None => document.to_string().into_bytes(),
+    }
+}
+
+/// Converts a JSON document into the `Value`s the encoders take.
+fn from_json(document: &Value) -> Field {
+    match document {
+        Value::Null => Field::Null,
+        Value::Bool(value) => Field::Bool(*value),
+        Value::Number(number) => number.as_i64().map_or_else(
+            || Field::Float(number.as_f64().unwrap_or(f64::NAN)),
+            Field::Int,
+        ),
+        Value::String(value) => Field::String(value.clone()),
+        Value::Array(values) => Field::List(values.iter().map(from_json).collect()),
+        Value::Object(values) => Field::Map(
+            values
+                .iter()
+                .map(|(key, value)| (key.clone(), from_json(value)))
+                .collect(),
+        ),
+    }
+}
+
+/// Returns the server description as labelled values, in the order they are printed.
+fn summary(game: QueryGame<'_>, fetched: &Answered) -> Vec<(&'static str, String)> {
+    let info = fetched.response.to_common();
+    let mut rows = vec![("name", info.name)];
+
+    for (label, value) in [
+        ("map", info.map),
+        ("game", info.game),
+        ("version", info.version),
+    ] {
+        if !value.is_empty() {
+            rows.push((label, value));
+        }
+    }
+
+    rows.push(("players", format!("{}/{}", info.players, info.max_players)));
+    rows.push(("access", info.access.to_string()));
+
+    if let Some(address) = fetched.meta.address() {
+        rows.push(("address", format!("{address} ({})", game.name())));
+    }
+
+    rows.push(("latency", format!("{:?}", fetched.meta.latency())));
+    rows
+}
+
+/// Renders a response as `label: value` lines.
+fn plain(game: QueryGame<'_>, fetched: &Answered, sections: Sections) -> String {
+    let mut output = String::new();
+
+    for (label, value) in summary(game, fetched) {
+        output += &format!("{label}: {value}\n");
+    }
+
+    if sections.players {
+        for player in fetched.response.players() {
+            let mut details = Vec::new();
+
+            if let Some(score) = player.score {
+                details.push(format!("score {score}"));
+            }
+
+            if let Some(duration) = player.duration {
+                details.push(format!("online {}s", duration.as_secs()));
+            }
+
+            if let Some(ping) = player.ping {
+                details.push(format!("ping {ping}ms"));
+            }
+
+            output += &match details.is_empty() {
+                true => format!("player {}\n", player.name),
+                false => format!("player {}: {}\n", player.name, details.join(", ")),
+            };
+        }
+    }
+
+    if sections.rules {
+        for (key, value) in &fetched.response.to_common().extra {
+            output += &format!("rule {key}: {value}\n");
+        }
+    }
+
+    output
+}
+
+/// Renders a response as aligned columns, with the players and rules in tables of their own.
+fn table(game: QueryGame<'_>, fetched: &Answered, sections: Sections) -> String {
+    let optional = |value: Option<String>| value.unwrap_or_else(|| "-".to_string());
+    let rows: Vec<Vec<String>> = summary(game, fetched)
+        .into_iter()
+        .map(|(label, value)| vec![label.to_string(), value])
+        .collect();
+    let mut output = columns(&rows);
+
+    if sections.players {
+        let mut rows = vec![["NAME", "SCORE", "ONLINE", "PING"]
+            .map(String::from)
+            .to_vec()];
+
+        rows.extend(fetched.response.players().into_iter().map(|player| {
+            vec![
+                player.name,
+                optional(player.score.map(|score| score.to_string())),
+                optional(
+                    player
+                        .duration
+                        .map(|duration| format!("{}s", duration.as_secs())),
+                ),
+                optional(player.ping.map(|ping| format!("{ping}ms"))),
+            ]
+        }));
+
+        output += "\n";
+        output += &columns(&rows);
+    }
+
+    if sections.rules {
+        let mut rows = vec![vec!["RULE".to_string(), "VALUE".to_string()]];
+
+        rows.extend(
+            fetched
+                .response
+                .to_common()
+                .extra
+                .into_iter()
+                .map(|(key, value)| vec![key, value]),
+        );
+
+        output += "\n";
+        output += &columns(&rows);
+    }
+
+    output
+}
+
+/// Aligns rows of cells into columns separated by two spaces.
+fn columns(rows: &[Vec<String>]) -> String {
+    let mut widths = Vec::new();
+
+    for row in rows {
+        for (index, cell) in row.iter().enumerate() {
+            match widths.get_mut(index) {
+                Some(width) => *width = cell.chars().count().max(*width),
+                None => widths.push(cell.chars().count()),
+            }
+        }
+    }
+
+    let mut output = String::new();
+
+    for row in rows {
+        let cells: Vec<String> = row
+            .iter()
+            .zip(&widths)
+            .map(|(cell, width)| format!("{cell:width$}"))
+            .collect();
+
+        output += cells.join("  ").trim_end();
+        output += "\n";
+    }
+
+    output
+}
+
+/// Returns a response as the versioned document of `gstat_core::document`, which
+/// `--format json` prints.
+pub fn document(game: QueryGame<'_>, fetched: &Answered) -> Field {
+    document::document(game.id(), game.name(), fetched)
+}
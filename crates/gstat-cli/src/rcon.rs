@@ -0,0 +1,176 @@
+use gstat_core::{
+    prelude::TimeoutSettings,
+    registry::{self, Capabilities},
+    resolve::{resolve, HostPort, SystemResolver},
+};
+use gstat_tcp::SourceRcon;
+
+use std::{env, path::PathBuf};
+
+use rustyline::{error::ReadlineError, DefaultEditor};
+
+/// The protocol given instead of a game to open any Source RCON console, e.g. that of a
+/// Minecraft server.
+pub const SOURCE_RCON: &str = "source-rcon";
+
+/// Returns the address of a game's remote console, filling in the game port if `address`
+/// has none.
+///
+/// Source engine games accept RCON on their game port. Other servers speaking the protocol
+/// are reached by passing `source-rcon` and the port.
+///
+/// # Parameters
+///
+/// * `identifier`: The game given on the command line, or `source-rcon`.
+/// * `address`: The address given on the command line.
+///
+/// # Returns
+///
+/// A `Result` containing either the address or a description of why the game has no
+/// console this build can open.
+pub fn target(identifier: &str, address: &HostPort) -> Result<HostPort, String> {
+    if identifier == SOURCE_RCON {
+        return match address.port {
+            Some(_) => Ok(address.clone()),
+            None => Err(format!("`{SOURCE_RCON}` needs the port of the console")),
+        };
+    }
+
+    let game = registry::game(identifier).ok_or_else(|| format!("unknown game `{identifier}`"))?;
+
+    if !game.capabilities.contains(Capabilities::RCON) {
+        return Err(format!(
+            "{} has no Source RCON console; pass `{SOURCE_RCON}` and the port if the server \
+             speaks it anyway",
+            game.name
+        ));
+    }
+
+    Ok(HostPort::new(
+        address.host.clone(),
+        address.port.or(Some(game.game_port)),
+    ))
+}
+
+/// Connects to a remote console and logs in.
+///
+/// # Parameters
+///
+/// * `target`: The address of the console.
+/// * `password`: The RCON password.
+/// * `timeouts`: The time limits for connecting and logging in.
+///
+/// # Returns
+///
+/// A `Result` containing either the authenticated connection or a description of why it
+/// couldn't be opened.
+pub async fn open(
+    target: &HostPort,
+    password: &str,
+    timeouts: &TimeoutSettings,
+) -> Result<SourceRcon, String> {
+    let resolved = resolve(&SystemResolver, target, None)
+        .await
+        .map_err(|err| format!("{target}: {err}"))?;
+    let mut last = None;
+
+    for address in resolved.addresses {
+        match SourceRcon::connect(address, timeouts).await {
+            Ok(mut rcon) => {
+                rcon.authenticate(password, timeouts)
+                    .await
+                    .map_err(|err| format!("{target}: {err}"))?;
+
+                return Ok(rcon);
+            }
+            Err(err) => last = Some(err),
+        }
+    }
+
+    Err(match last {
+        Some(err) => format!("{target}: {err}"),
+        None => format!("{target} did not resolve to any address"),
+    })
+}
+
+/// Reads commands from the terminal and prints their output until `exit`, end of input
+/// or Ctrl-C.
+///
+/// Commands are kept in a history, which is saved to `rcon_history` under
+/// `$XDG_STATE_HOME/gstat` between sessions.
+///
+/// # Parameters
+///
+/// * `runtime`: The runtime commands run on.
+/// * `rcon`: The authenticated connection.
+/// * `timeouts`: The time limits for each command.
+///
+/// # Returns
+///
+/// A `Result` which is `Ok` once the shell is left, or a description of why it failed.
+pub fn shell(
+    runtime: &tokio::runtime::Runtime,
+    rcon: &mut SourceRcon,
+    timeouts: &TimeoutSettings,
+) -> Result<(), String> {
+    let mut editor = DefaultEditor::new().map_err(|err| err.to_string())?;
+    let history = history_path();
+
+    if let Some(history) = &history {
+        let _ = editor.load_history(history);
+    }
+
+    let prompt = format!("{}> ", rcon.peer());
+
+    loop {
+        let command = match editor.readline(&prompt) {
+            Ok(command) => command,
+            Err(ReadlineError::Eof | ReadlineError::Interrupted) => break,
+            Err(err) => return Err(err.to_string()),
+        };
+        let command = command.trim();
+
+        match command {
+            "" => continue,
+            "exit" | "quit" => break,
+            _ => {}
+        }
+
+        let _ = editor.add_history_entry(command);
+        let output = runtime
+            .block_on(rcon.exec(command, timeouts))
+            .map_err(|err| err.to_string())?;
+
+        print_output(&output);
+    }
+
+    if let Some(history) = &history {
+        if let Some(parent) = history.parent() {
+            let _ = std::fs::create_dir_all(parent);
+        }
+
+        let _ = editor.save_history(history);
+    }
+
+    Ok(())
+}
+
+/// Prints the output of a command, ending it with a newline if the server didn't.
+pub fn print_output(output: &str) {
+    if output.is_empty() || output.ends_with('\n') {
+        print!("{output}");
+    } else {
+        println!("{output}");
+    }
+}
+
+/// Returns where the shell history is kept: `gstat/rcon_history` under `$XDG_STATE_HOME`,
+/// or under `~/.local/state` if that isn't set.
+fn history_path() -> Option<PathBuf> {
+    let base = match env::var_os("XDG_STATE_HOME") {
+        Some(base) if !base.is_empty() => PathBuf::from(base),
+        _ => PathBuf::from(env::var_os("HOME")?).join(".local/state"),
+    };
+
+    Some(base.join("gstat").join("rcon_history"))
+}
@@ -0,0 +1,325 @@
+use crate::{
+    config::{Config, OutputFormat, Profile},
+    query,
+};
+
+use gstat_core::{
+    bytes::Bytes,
+    prelude::{Parser, Response},
+    registry,
+    script::ScriptGame,
+};
+
+use std::{
+    fmt::{Display, Formatter, Result as FmtResult},
+    io::{self, Write},
+    net::{Ipv4Addr, SocketAddr, TcpListener, UdpSocket},
+    path::Path,
+    time::Duration,
+};
+
+use serde_json::{json, Value};
+
+/// How long the loopback check waits for its own datagram.
+const LOOPBACK_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// A captured response together with the script that parses it and what it must yield.
+struct Fixture {
+    name: &'static str,
+    script: &'static str,
+    packet: &'static [u8],
+    server_name: &'static str,
+    map: &'static str,
+    players: u32,
+    player_names: &'static [&'static str],
+}
+
+/// The fixture corpus, embedded so the self-test doesn't depend on files next to the
+/// binary.
+const FIXTURES: &[Fixture] = &[
+    Fixture {
+        name: "source-info",
+        script: include_str!("../../gstat-test/fixtures/source-info.rhai"),
+        packet: include_bytes!("../../gstat-test/fixtures/source-info.bin"),
+        server_name: "gstat fixture",
+        map: "cp_badlands",
+        players: 12,
+        player_names: &[],
+    },
+    Fixture {
+        name: "source-players",
+        script: include_str!("../../gstat-test/fixtures/source-players.rhai"),
+        packet: include_bytes!("../../gstat-test/fixtures/source-players.bin"),
+        server_name: "",
+        map: "",
+        players: 2,
+        player_names: &["alice", "bob"],
+    },
+];
+
+/// Whether a check passed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Status {
+    /// The check passed.
+    Pass,
+    /// The check failed.
+    Fail,
+}
+
+impl Display for Status {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            Self::Pass => write!(f, "pass"),
+            Self::Fail => write!(f, "fail"),
+        }
+    }
+}
+
+/// The outcome of a single check.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Check {
+    /// What was checked.
+    pub name: String,
+    /// Whether the check passed.
+    pub status: Status,
+    /// What was found, or why the check failed.
+    pub detail: String,
+}
+
+impl Check {
+    /// Creates a check from its outcome.
+    fn new(name: impl Into<String>, outcome: Result<String, String>) -> Self {
+        let (status, detail) = match outcome {
+            Ok(detail) => (Status::Pass, detail),
+            Err(detail) => (Status::Fail, detail),
+        };
+
+        Check {
+            name: name.into(),
+            status,
+            detail,
+        }
+    }
+}
+
+/// The results of `gstat selftest`, meant to be attached to support tickets or checked by
+/// deployment pipelines.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Report {
+    /// The checks, in the order they ran.
+    pub checks: Vec<Check>,
+}
+
+impl Report {
+    /// Runs every check.
+    ///
+    /// Checks don't depend on each other, so a broken configuration is reported alongside
+    /// the results of the remaining checks.
+    ///
+    /// # Parameters
+    ///
+    /// * `config`: The path given with `--config`, if any.
+    /// * `profile`: The profile given with `--profile`, if any.
+    ///
+    /// # Returns
+    ///
+    /// The report, and the selected profile if the configuration is valid.
+    pub fn run(config: Option<&Path>, profile: Option<&str>) -> (Self, Option<Profile>) {
+        let (config, profile) = match check_config(config, profile) {
+            Ok((detail, profile)) => (Check::new("config", Ok(detail)), Some(profile)),
+            Err(detail) => (Check::new("config", Err(detail)), None),
+        };
+
+        let mut checks = vec![config];
+        checks.extend(FIXTURES.iter().map(|fixture| {
+            Check::new(format!("fixture {}", fixture.name), check_fixture(fixture))
+        }));
+        checks.push(Check::new("sockets", check_sockets()));
+        checks.push(Check::new("udp loopback", check_loopback()));
+
+        (Report { checks }, profile)
+    }
+
+    /// Returns the number of failed checks.
+    pub fn failures(&self) -> usize {
+        self.checks
+            .iter()
+            .filter(|check| check.status == Status::Fail)
+            .count()
+    }
+
+    /// Prints the report in `format`.
+    pub fn print(&self, format: OutputFormat) {
+        match format {
+            OutputFormat::Plain | OutputFormat::Table => print!("{self}"),
+            OutputFormat::Json => println!("{}", self.to_json()),
+            format => {
+                let _ = io::stdout().write_all(&query::encode(format, &self.to_json()));
+            }
+        }
+    }
+
+    /// Returns the report as JSON.
+    pub fn to_json(&self) -> Value {
+        let checks: Vec<Value> = self
+            .checks
+            .iter()
+            .map(|check| {
+                json!({
+                    "name": check.name,
+                    "status": check.status.to_string(),
+                    "detail": check.detail,
+                })
+            })
+            .collect();
+
+        json!({
+            "version": env!("CARGO_PKG_VERSION"),
+            "passed": self.failures() == 0,
+            "checks": checks,
+        })
+    }
+}
+
+impl Display for Report {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        let width = self
+            .checks
+            .iter()
+            .map(|check| check.name.len())
+            .max()
+            .unwrap_or(0);
+
+        writeln!(f, "gstat {} self-test", env!("CARGO_PKG_VERSION"))?;
+
+        for check in &self.checks {
+            writeln!(
+                f,
+                "{}  {:width$}  {}",
+                check.status, check.name, check.detail
+            )?;
+        }
+
+        writeln!(
+            f,
+            "{} of {} checks passed",
+            self.checks.len() - self.failures(),
+            self.checks.len()
+        )
+    }
+}
+
+/// Loads the configuration and checks that its favorite servers can be queried.
+fn check_config(path: Option<&Path>, selected: Option<&str>) -> Result<(String, Profile), String> {
+    let config = Config::load(path).map_err(|err| err.to_string())?;
+    let (name, profile) = config.profile(selected).map_err(|err| err.to_string())?;
+
+    let mut problems = Vec::new();
+
+    for (server, favorite) in &profile.servers {
+        if registry::game(&favorite.game).is_none() {
+            problems.push(format!(
+                "server {server} uses unknown game `{}`",
+                favorite.game
+            ));
+        }
+
+        let port = favorite
+            .address
+            .rsplit_once(':')
+            .and_then(|(_, port)| port.parse::<u16>().ok());
+
+        if port.is_none() {
+            problems.push(format!(
+                "server {server} has no port in `{}`",
+                favorite.address
+            ));
+        }
+    }
+
+    if !problems.is_empty() {
+        return Err(problems.join("; "));
+    }
+
+    let servers = match profile.servers.len() {
+        1 => "1 server".to_string(),
+        count => format!("{count} servers"),
+    };
+
+    Ok((format!("profile `{name}`, {servers}"), profile))
+}
+
+/// Parses a fixture and compares the result with what the fixture must yield.
+fn check_fixture(fixture: &Fixture) -> Result<String, String> {
+    let game = ScriptGame::compile(fixture.script).map_err(|err| err.to_string())?;
+    let response = game
+        .parser()
+        .deserialize_response(Bytes::from_static(fixture.packet))
+        .map_err(|err| err.to_string())?;
+
+    let info = response.to_common();
+    let players = response.players();
+    let names: Vec<&str> = players.iter().map(|player| player.name.as_str()).collect();
+
+    if info.name != fixture.server_name || info.map != fixture.map {
+        return Err(format!(
+            "parsed server {:?} on {:?}, expected {:?} on {:?}",
+            info.name, info.map, fixture.server_name, fixture.map
+        ));
+    }
+
+    if info.players != fixture.players || names != fixture.player_names {
+        return Err(format!(
+            "parsed {} players {names:?}, expected {} players {:?}",
+            info.players, fixture.players, fixture.player_names
+        ));
+    }
+
+    Ok(format!("{} bytes parsed", fixture.packet.len()))
+}
+
+/// Checks that the process may open the sockets queries use.
+fn check_sockets() -> Result<String, String> {
+    let any = SocketAddr::from((Ipv4Addr::UNSPECIFIED, 0));
+
+    UdpSocket::bind(any).map_err(|err| socket_error("UDP", err))?;
+    TcpListener::bind(any).map_err(|err| socket_error("TCP", err))?;
+
+    Ok("UDP and TCP sockets can be opened".to_string())
+}
+
+/// Describes why a socket could not be opened.
+fn socket_error(transport: &str, err: io::Error) -> String {
+    match err.kind() {
+        io::ErrorKind::PermissionDenied => {
+            format!("not permitted to open {transport} sockets: {err}")
+        }
+        _ => format!("failed to open a {transport} socket: {err}"),
+    }
+}
+
+/// Sends a datagram to a socket bound on the loopback interface and reads it back.
+fn check_loopback() -> Result<String, String> {
+    const PAYLOAD: &[u8] = b"gstat selftest";
+
+    let socket = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).map_err(|err| err.to_string())?;
+    let address = socket.local_addr().map_err(|err| err.to_string())?;
+
+    socket
+        .set_read_timeout(Some(LOOPBACK_TIMEOUT))
+        .map_err(|err| err.to_string())?;
+    socket
+        .send_to(PAYLOAD, address)
+        .map_err(|err| format!("failed to send to {address}: {err}"))?;
+
+    let mut buffer = [0; 64];
+    let (len, from) = socket
+        .recv_from(&mut buffer)
+        .map_err(|err| format!("no datagram received on {address}: {err}"))?;
+
+    if &buffer[..len] != PAYLOAD || from != address {
+        return Err(format!("received an unexpected datagram from {from}"));
+    }
+
+    Ok(format!("bound {address}"))
+}
@@ -0,0 +1,134 @@
+use crate::{
+    config::OutputFormat,
+    query::{self, QueryGame, Sections},
+};
+
+use gstat_core::{
+    prelude::{Response, ServerInfo, TimeoutSettings},
+    resolve::HostPort,
+};
+
+use std::{
+    io::{self, IsTerminal, Write},
+    thread,
+    time::{Duration, Instant},
+};
+
+/// Clears the terminal and moves the cursor to the top left corner.
+const CLEAR: &str = "\x1b[2J\x1b[H";
+
+/// Starts the highlighting of a changed line.
+const HIGHLIGHT: &str = "\x1b[1;33m";
+
+/// Ends the highlighting of a changed line.
+const RESET: &str = "\x1b[0m";
+
+/// Returns the labels of the watched fields that changed between two polls.
+///
+/// Only the map and the player count are watched, as they are what changes during a match
+/// and what a dashboard is watched for.
+///
+/// # Parameters
+///
+/// * `previous`: The server description of the previous poll.
+/// * `current`: The server description of this poll.
+pub fn changes(previous: &ServerInfo, current: &ServerInfo) -> Vec<&'static str> {
+    let mut changed = Vec::new();
+
+    if previous.map != current.map {
+        changed.push("map");
+    }
+
+    if (previous.players, previous.max_players) != (current.players, current.max_players) {
+        changed.push("players");
+    }
+
+    changed
+}
+
+/// Highlights the lines of a plain or table rendering that show one of the `changed` fields.
+///
+/// # Parameters
+///
+/// * `rendered`: The output of `query::render`.
+/// * `changed`: The labels of the changed fields.
+pub fn highlight(rendered: &str, changed: &[&str]) -> String {
+    rendered
+        .lines()
+        .map(|line| {
+            let label = line.split([':', ' ']).next().unwrap_or_default();
+
+            match changed.contains(&label) {
+                true => format!("{HIGHLIGHT}{line}{RESET}\n"),
+                false => format!("{line}\n"),
+            }
+        })
+        .collect()
+}
+
+/// Queries a server every `interval` until the process is interrupted.
+///
+/// On a terminal, each poll redraws the screen and highlights the fields that changed since
+/// the previous poll. Otherwise, and always with the JSON, CSV, MessagePack and XML
+/// formats, polls are printed one after the other, one document each. Failed polls are reported and the next
+/// poll is compared with the last successful one.
+///
+/// # Parameters
+///
+/// * `game`: The game the server runs.
+/// * `target`: The host of the server, with an optional port.
+/// * `timeouts`: The time limits for each poll.
+/// * `sections`: What is printed besides the server description.
+/// * `format`: How each poll is printed.
+/// * `interval`: The time between the starts of two polls.
+pub fn run(
+    game: QueryGame<'_>,
+    target: &HostPort,
+    timeouts: TimeoutSettings,
+    sections: Sections,
+    format: OutputFormat,
+    interval: Duration,
+) -> io::Result<()> {
+    let redraw = format.encoding().is_none() && io::stdout().is_terminal();
+    let mut previous: Option<ServerInfo> = None;
+
+    loop {
+        let started = Instant::now();
+        let mut frame = Vec::new();
+
+        if redraw {
+            frame.extend_from_slice(CLEAR.as_bytes());
+            frame.extend_from_slice(format!("every {interval:?}: {target}\n\n").as_bytes());
+        }
+
+        match query::query(game, target, timeouts) {
+            Ok(fetched) => {
+                let info = fetched.response.to_common();
+                let rendered = query::render(game, &fetched, sections, format);
+
+                match (&previous, redraw) {
+                    (Some(previous), true) => frame.extend_from_slice(
+                        highlight(
+                            &String::from_utf8_lossy(&rendered),
+                            &changes(previous, &info),
+                        )
+                        .as_bytes(),
+                    ),
+                    _ => frame.extend(rendered),
+                }
+
+                previous = Some(info);
+            }
+            Err(err) => {
+                eprintln!("gstat: {err}");
+            }
+        }
+
+        let mut stdout = io::stdout().lock();
+        stdout.write_all(&frame)?;
+        stdout.flush()?;
+        drop(stdout);
+
+        thread::sleep(interval.saturating_sub(started.elapsed()));
+    }
+}
@@ -0,0 +1,120 @@
+//! Querying server lists with `gstat bulk`.
+
+use gstat_test::{SOURCE_INFO_PACKET, SOURCE_INFO_SCRIPT_PATH};
+
+use std::{
+    collections::BTreeMap,
+    fs,
+    io::Write,
+    net::{Ipv4Addr, SocketAddr, UdpSocket},
+    process::{Command, Output, Stdio},
+    thread,
+};
+
+use serde_json::Value;
+
+/// Binds a server on the loopback interface answering one query with `SOURCE_INFO_PACKET`.
+fn server() -> SocketAddr {
+    let socket = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+    let address = socket.local_addr().unwrap();
+
+    thread::spawn(move || {
+        let mut buffer = [0; 64];
+        let (_, peer) = socket.recv_from(&mut buffer).unwrap();
+        socket.send_to(SOURCE_INFO_PACKET, peer).unwrap();
+    });
+
+    address
+}
+
+fn gstat(args: &[&str], stdin: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_gstat"))
+        .args([
+            "--script",
+            SOURCE_INFO_SCRIPT_PATH,
+            "bulk",
+            "--timeout",
+            "200ms",
+        ])
+        .args(args)
+        .env(
+            "GSTAT_CONFIG",
+            std::env::temp_dir().join("gstat-bulk-missing.toml"),
+        )
+        .env_remove("GSTAT_PROFILE")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(stdin.as_bytes())
+        .unwrap();
+
+    child.wait_with_output().unwrap()
+}
+
+/// Returns the documents of the output by their line number.
+fn documents(output: &Output) -> BTreeMap<u64, Value> {
+    String::from_utf8(output.stdout.clone())
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str::<Value>(line).unwrap())
+        .map(|document| (document["line"].as_u64().unwrap(), document))
+        .collect()
+}
+
+#[test]
+fn every_server_of_the_list_is_reported() {
+    let silent = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+    let list = format!(
+        "# servers\nselftest-source-info {}\n\nselftest-source-info {}\nnonexistent 127.0.0.1:1\nnot a server\n",
+        server(),
+        silent.local_addr().unwrap(),
+    );
+    let path = std::env::temp_dir().join(format!("gstat-{}-bulk.txt", std::process::id()));
+    fs::write(&path, list).unwrap();
+
+    let output = gstat(
+        &["--input", path.to_str().unwrap(), "--concurrency", "2"],
+        "",
+    );
+    let stderr = String::from_utf8(output.stderr.clone()).unwrap();
+    let documents = documents(&output);
+
+    assert!(output.status.success(), "{stderr}");
+    assert!(stderr.contains("1 of 4 servers answered"), "{stderr}");
+    assert_eq!(documents.keys().copied().collect::<Vec<_>>(), [2, 4, 5, 6]);
+
+    assert_eq!(documents[&2]["info"]["map"], "cp_badlands");
+    assert!(documents[&2]["error"].is_null());
+    assert!(documents[&4]["error"].is_string());
+    assert!(documents[&5]["error"]
+        .as_str()
+        .unwrap()
+        .contains("unknown game"));
+    assert!(documents[&6]["error"]
+        .as_str()
+        .unwrap()
+        .contains("expected `<game> <address>`"));
+}
+
+#[test]
+fn servers_are_read_from_stdin() {
+    let output = gstat(&[], &format!("selftest-source-info {}\n", server()));
+    let documents = documents(&output);
+
+    assert!(output.status.success());
+    assert_eq!(
+        documents[&1]["target"],
+        format!(
+            "selftest-source-info {}",
+            documents[&1]["address"].as_str().unwrap()
+        )
+    );
+    assert_eq!(documents[&1]["info"]["players"], 12);
+}
@@ -0,0 +1,45 @@
+//! Guessing what a server runs with `gstat detect`.
+
+use gstat_test::SOURCE_INFO_PACKET;
+
+use std::{
+    net::{Ipv4Addr, UdpSocket},
+    process::{Command, Output},
+    thread,
+};
+
+fn gstat(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_gstat"))
+        .args(args)
+        .env(
+            "GSTAT_CONFIG",
+            std::env::temp_dir().join("gstat-detect-missing.toml"),
+        )
+        .env_remove("GSTAT_PROFILE")
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn servers_are_detected_by_their_answers() {
+    let socket = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+    let address = socket.local_addr().unwrap().to_string();
+
+    thread::spawn(move || {
+        let mut buffer = [0; 64];
+
+        while let Ok((len, peer)) = socket.recv_from(&mut buffer) {
+            if buffer[..len].starts_with(b"\xFF\xFF\xFF\xFFTSource Engine Query") {
+                socket.send_to(SOURCE_INFO_PACKET, peer).unwrap();
+            }
+        }
+    });
+
+    let output = gstat(&["detect", &address, "--timeout", "300ms"]);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+
+    assert!(output.status.success(), "{stdout}");
+    assert!(stdout.contains("game: Team Fortress 2 (tf2)\n"), "{stdout}");
+    assert!(stdout.contains("protocol: Valve Source Query (a2s)\n"));
+    assert!(stdout.contains(&format!("address: {address}\n")));
+}
@@ -0,0 +1,118 @@
+//! Listing servers with `gstat discover`.
+
+use gstat_test::{SOURCE_INFO_PACKET, SOURCE_INFO_SCRIPT_PATH};
+
+use std::{
+    net::{Ipv4Addr, SocketAddr, SocketAddrV4, UdpSocket},
+    process::{Command, Output},
+    thread,
+};
+
+/// Binds a server on the loopback interface answering one query with `SOURCE_INFO_PACKET`.
+fn server() -> SocketAddrV4 {
+    let socket = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+    let SocketAddr::V4(address) = socket.local_addr().unwrap() else {
+        unreachable!("bound to an IPv4 address");
+    };
+
+    thread::spawn(move || {
+        let mut buffer = [0; 64];
+        let (_, peer) = socket.recv_from(&mut buffer).unwrap();
+        socket.send_to(SOURCE_INFO_PACKET, peer).unwrap();
+    });
+
+    address
+}
+
+/// Binds a master server on the loopback interface listing `servers` on a single page.
+fn master(servers: &[SocketAddrV4]) -> SocketAddr {
+    let socket = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+    let address = socket.local_addr().unwrap();
+
+    let mut page = vec![0xFF, 0xFF, 0xFF, 0xFF, 0x66, 0x0A];
+    for server in servers
+        .iter()
+        .chain([&SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, 0)])
+    {
+        page.extend(server.ip().octets());
+        page.extend(server.port().to_be_bytes());
+    }
+
+    thread::spawn(move || {
+        let mut buffer = [0; 256];
+        let (_, peer) = socket.recv_from(&mut buffer).unwrap();
+        socket.send_to(&page, peer).unwrap();
+    });
+
+    address
+}
+
+fn discover(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_gstat"))
+        .args(["--script", SOURCE_INFO_SCRIPT_PATH, "discover"])
+        .args(args)
+        .args(["--timeout", "500ms"])
+        .env(
+            "GSTAT_CONFIG",
+            std::env::temp_dir().join("gstat-discover-missing.toml"),
+        )
+        .env_remove("GSTAT_PROFILE")
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn master_servers_are_listed() {
+    let servers = [
+        SocketAddrV4::new(Ipv4Addr::new(192, 0, 2, 1), 27015),
+        SocketAddrV4::new(Ipv4Addr::new(192, 0, 2, 2), 27016),
+    ];
+    let master = master(&servers).to_string();
+
+    let output = discover(&[
+        "valve-master",
+        "--master",
+        &master,
+        "--filter",
+        "\\appid\\440",
+    ]);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let stderr = String::from_utf8(output.stderr).unwrap();
+
+    assert!(output.status.success(), "{stderr}");
+    assert_eq!(stdout, "192.0.2.1:27015\n192.0.2.2:27016\n");
+    assert!(stderr.contains("found 2 servers"));
+}
+
+#[test]
+fn listed_servers_are_queried() {
+    let server = server();
+    let master = master(&[server]).to_string();
+
+    let output = discover(&[
+        "valve-master",
+        "--master",
+        &master,
+        "--game",
+        "selftest-source-info",
+        "--query",
+        "--format",
+        "json",
+    ]);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+
+    assert!(output.status.success());
+
+    let document: serde_json::Value = serde_json::from_str(stdout.trim()).unwrap();
+    assert_eq!(document["address"], server.to_string());
+    assert_eq!(document["info"]["name"], "gstat fixture");
+    assert_eq!(document["info"]["players"], 12);
+}
+
+#[test]
+fn games_are_only_discovered_on_the_local_network() {
+    let output = discover(&["selftest-source-info"]);
+
+    assert!(!output.status.success());
+    assert!(String::from_utf8(output.stderr).unwrap().contains("--lan"));
+}
@@ -0,0 +1,59 @@
+//! Checking fleet files.
+
+use gstat_test::SOURCE_INFO_SCRIPT_PATH;
+
+use std::{
+    fs,
+    process::{Command, Output},
+};
+
+fn fleet(name: &str, contents: &str) -> Output {
+    let path = std::env::temp_dir().join(format!("gstat-fleet-{}-{name}.toml", std::process::id()));
+    fs::write(&path, contents).unwrap();
+
+    Command::new(env!("CARGO_BIN_EXE_gstat"))
+        .args(["--script", SOURCE_INFO_SCRIPT_PATH, "fleet"])
+        .arg(&path)
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn fleets_show_their_targets_alerts_and_sinks() {
+    let output = fleet(
+        "show",
+        r#"
+interval = "1m"
+
+[[target]]
+name = "main"
+game = "selftest-source-info"
+address = "127.0.0.1:27016"
+interval = "10s"
+
+[[alert]]
+kind = "down"
+polls = 3
+
+[[sink]]
+kind = "mqtt"
+broker = "broker.lan"
+"#,
+    );
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("target main: selftest-source-info 127.0.0.1:27016 every 10s"));
+    assert!(stdout.contains("alert: down for 3 poll(s)"));
+    assert!(stdout.contains("sink mqtt: broker.lan"));
+}
+
+#[test]
+fn fleets_with_unknown_games_fail() {
+    let output = fleet("unknown", "[[target]]\ngame = \"nope\"\naddress = \"a\"\n");
+
+    assert!(!output.status.success());
+    assert!(String::from_utf8(output.stderr)
+        .unwrap()
+        .contains("target `a`: unknown game `nope`"));
+}
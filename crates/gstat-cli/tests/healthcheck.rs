@@ -0,0 +1,79 @@
+//! Exit codes of `gstat healthcheck`.
+
+use gstat_test::{SOURCE_INFO_PACKET, SOURCE_INFO_SCRIPT_PATH};
+
+use std::{
+    net::{Ipv4Addr, SocketAddr, UdpSocket},
+    process::{Command, Output},
+    thread,
+};
+
+/// Binds a server on the loopback interface answering one query with `SOURCE_INFO_PACKET`, a server
+/// with 12 players.
+fn server() -> SocketAddr {
+    let socket = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+    let address = socket.local_addr().unwrap();
+
+    thread::spawn(move || {
+        let mut buffer = [0; 64];
+        let (_, peer) = socket.recv_from(&mut buffer).unwrap();
+        socket.send_to(SOURCE_INFO_PACKET, peer).unwrap();
+    });
+
+    address
+}
+
+fn healthcheck(address: SocketAddr, args: &[&str]) -> (Option<i32>, String) {
+    let output: Output = Command::new(env!("CARGO_BIN_EXE_gstat"))
+        .args([
+            "--script",
+            SOURCE_INFO_SCRIPT_PATH,
+            "healthcheck",
+            "selftest-source-info",
+        ])
+        .arg(address.to_string())
+        .args(["--timeout", "200ms"])
+        .args(args)
+        .env(
+            "GSTAT_CONFIG",
+            std::env::temp_dir().join("gstat-health-missing.toml"),
+        )
+        .env_remove("GSTAT_PROFILE")
+        .output()
+        .unwrap();
+
+    (
+        output.status.code(),
+        String::from_utf8(output.stdout).unwrap(),
+    )
+}
+
+#[test]
+fn servers_within_the_thresholds_are_healthy() {
+    let (code, stdout) = healthcheck(server(), &["--min-players", "12"]);
+
+    assert_eq!(code, Some(0), "{stdout}");
+    assert!(stdout.starts_with("healthy: 12/"));
+}
+
+#[test]
+fn servers_outside_the_thresholds_are_degraded() {
+    let (code, stdout) = healthcheck(server(), &["--min-players", "13"]);
+
+    assert_eq!(code, Some(1), "{stdout}");
+    assert!(stdout.contains("fewer than 13 players"));
+
+    let (code, stdout) = healthcheck(server(), &["--max-latency", "0ms"]);
+
+    assert_eq!(code, Some(1), "{stdout}");
+    assert!(stdout.contains("slower than"));
+}
+
+#[test]
+fn silent_servers_are_unreachable() {
+    let silent = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+    let (code, stdout) = healthcheck(silent.local_addr().unwrap(), &[]);
+
+    assert_eq!(code, Some(2), "{stdout}");
+    assert!(stdout.starts_with("unreachable: "));
+}
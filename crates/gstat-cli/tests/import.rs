@@ -0,0 +1,79 @@
+//! Importing server lists from other tools.
+
+use std::{fs, path::PathBuf, process::Command};
+
+fn file(name: &str, contents: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("gstat-import-{}-{name}", std::process::id()));
+    fs::write(&path, contents).unwrap();
+    path
+}
+
+fn gstat(args: &[&str]) -> (bool, String, String) {
+    let output = Command::new(env!("CARGO_BIN_EXE_gstat"))
+        .args(args)
+        .env_remove("GSTAT_PROFILE")
+        .output()
+        .unwrap();
+
+    (
+        output.status.success(),
+        String::from_utf8(output.stdout).unwrap(),
+        String::from_utf8(output.stderr).unwrap(),
+    )
+}
+
+#[test]
+fn gamedig_lists_become_a_loadable_profile() {
+    let input = file(
+        "gamedig.json",
+        r#"[
+            {"type": "teamfortress2", "host": "10.0.0.1", "port": 27015, "name": "Payload 24/7"},
+            {"type": "battlefield4", "host": "::1", "port": 47200},
+            {"type": "unknowngame", "host": "example.com", "name": "Payload 24/7"}
+        ]"#,
+    );
+
+    let (success, profile, warnings) = gstat(&[
+        "import",
+        "--from",
+        "gamedig",
+        input.to_str().unwrap(),
+        "--into",
+        "migrated",
+    ]);
+    assert!(success);
+    assert!(warnings.contains("unknown game `unknowngame`"));
+
+    let config = file("gamedig.toml", &profile);
+    let (success, shown, _) = gstat(&[
+        "--config",
+        config.to_str().unwrap(),
+        "-p",
+        "migrated",
+        "profiles",
+        "show",
+    ]);
+    assert!(success);
+    assert!(shown.contains("server payload-24-7: tf2 10.0.0.1:27015"));
+    assert!(shown.contains("server payload-24-7-2: unknowngame example.com"));
+    assert!(shown.contains("server 1-47200: bf4 [::1]:47200"));
+}
+
+#[test]
+fn panel_csv_exports_are_imported() {
+    let input = file(
+        "panel.csv",
+        "Name,Game,IP,Port\n\"Survival, EU\",minecraft,play.example.com,25565\nLobby,csgo,10.0.0.2,\n",
+    );
+
+    let (success, profile, _) = gstat(&["import", "--from", "csv", input.to_str().unwrap()]);
+    assert!(success);
+    assert!(profile.contains("[profiles.imported.servers.survival-eu]"));
+    assert!(profile.contains("address = \"play.example.com:25565\""));
+    assert!(profile.contains("game = \"cs2\""));
+
+    let missing = file("missing.csv", "Name,Port\nLobby,27015\n");
+    let (success, _, error) = gstat(&["import", "--from", "csv", missing.to_str().unwrap()]);
+    assert!(!success);
+    assert!(error.contains("no `game` column"));
+}
@@ -0,0 +1,97 @@
+//! Selecting and resolving configuration profiles.
+
+use std::{
+    fs,
+    path::PathBuf,
+    process::{Command, Output},
+};
+
+const CONFIG: &str = r#"
+default_profile = "staging"
+
+[profiles.default]
+timeout = "5s"
+format = "json"
+
+[profiles.default.servers]
+lobby = { game = "tf2", address = "127.0.0.1:27015" }
+
+[profiles.staging]
+read_timeout = "750ms"
+
+[profiles.production]
+timeout = "2s"
+format = "table"
+
+[profiles.production.api_keys]
+steam = "secret"
+"#;
+
+fn config(name: &str, contents: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("gstat-{}-{name}.toml", std::process::id()));
+    fs::write(&path, contents).unwrap();
+    path
+}
+
+fn gstat(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_gstat"))
+        .args(args)
+        .env_remove("GSTAT_PROFILE")
+        .output()
+        .unwrap()
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8(output.stdout.clone()).unwrap()
+}
+
+#[test]
+fn profiles_layer_over_the_default_profile() {
+    let path = config("layer", CONFIG);
+    let path = path.to_str().unwrap();
+
+    let staging = gstat(&["--config", path, "profiles", "show"]);
+    assert!(staging.status.success());
+    let staging = stdout(&staging);
+    assert!(staging.contains("profile: staging"));
+    assert!(staging.contains("format: json"));
+    assert!(staging.contains("connect timeout: 5s"));
+    assert!(staging.contains("read timeout: 750ms"));
+    assert!(staging.contains("server lobby: tf2 127.0.0.1:27015"));
+
+    let production = stdout(&gstat(&[
+        "--config",
+        path,
+        "profiles",
+        "show",
+        "--profile",
+        "production",
+    ]));
+    assert!(production.contains("format: table"));
+    assert!(production.contains("read timeout: 2s"));
+    assert!(production.contains("api key steam: ********"));
+    assert!(!production.contains("secret"));
+
+    let list = stdout(&gstat(&["--config", path, "profiles", "list"]));
+    assert_eq!(list, "  default\n  production\n* staging\n");
+}
+
+#[test]
+fn unknown_profiles_and_invalid_files_fail() {
+    let path = config("invalid", CONFIG);
+    let output = gstat(&[
+        "--config",
+        path.to_str().unwrap(),
+        "-p",
+        "missing",
+        "profiles",
+        "show",
+    ]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("unknown profile `missing`"));
+
+    let path = config("typo", "[profiles.default]\ntimeuot = \"5s\"\n");
+    let output = gstat(&["--config", path.to_str().unwrap(), "profiles", "list"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("invalid config"));
+}
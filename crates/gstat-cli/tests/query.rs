@@ -0,0 +1,305 @@
+//! Querying servers with `gstat query`.
+
+use gstat_test::{SOURCE_INFO_PACKET, SOURCE_INFO_SCRIPT_PATH};
+
+use std::{
+    io::{BufRead, BufReader, Read, Write},
+    net::{Ipv4Addr, SocketAddr, TcpListener, UdpSocket},
+    process::{Command, Output, Stdio},
+    thread,
+};
+
+/// Binds a server on the loopback interface answering the first `answers` queries with
+/// `SOURCE_INFO_PACKET` and ignoring the rest.
+fn server(answers: usize) -> (SocketAddr, UdpSocket) {
+    let socket = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+    let address = socket.local_addr().unwrap();
+    let responder = socket.try_clone().unwrap();
+
+    thread::spawn(move || {
+        let mut buffer = [0; 64];
+
+        for _ in 0..answers {
+            let (_, peer) = responder.recv_from(&mut buffer).unwrap();
+            responder.send_to(SOURCE_INFO_PACKET, peer).unwrap();
+        }
+    });
+
+    (address, socket)
+}
+
+/// The status document of the Minecraft server of `minecraft_server`.
+const MINECRAFT_STATUS: &str = r#"{"version":{"name":"1.21.1","protocol":767},"players":{"max":20,"online":3},"description":"A Minecraft Server"}"#;
+
+/// Binds a Minecraft server on the loopback interface answering one Server List Ping.
+fn minecraft_server() -> SocketAddr {
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+    let address = listener.local_addr().unwrap();
+
+    thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut len = [0];
+
+        // Packets and the status document are short enough for one byte lengths.
+        while stream.read_exact(&mut len).is_ok() {
+            let mut packet = vec![0; len[0].into()];
+            stream.read_exact(&mut packet).unwrap();
+
+            match packet[..] {
+                [0x00] => {
+                    let mut body = vec![0x00, MINECRAFT_STATUS.len() as u8];
+                    body.extend_from_slice(MINECRAFT_STATUS.as_bytes());
+                    stream.write_all(&[body.len() as u8]).unwrap();
+                    stream.write_all(&body).unwrap();
+                }
+                [0x01, ..] => {
+                    stream.write_all(&[9]).unwrap();
+                    stream.write_all(&packet).unwrap();
+                }
+                _ => {}
+            }
+        }
+    });
+
+    address
+}
+
+fn gstat(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_gstat"))
+        .args(args)
+        .env(
+            "GSTAT_CONFIG",
+            std::env::temp_dir().join("gstat-query-missing.toml"),
+        )
+        .env_remove("GSTAT_PROFILE")
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn scripted_games_are_queried() {
+    let (address, _socket) = server(1);
+    let address = address.to_string();
+    let output = gstat(&[
+        "--script",
+        SOURCE_INFO_SCRIPT_PATH,
+        "query",
+        "selftest-source-info",
+        &address,
+        "--rules",
+    ]);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+
+    assert!(output.status.success(), "{stdout}");
+    assert!(stdout.contains("name: gstat fixture\n"));
+    assert!(stdout.contains("map: cp_badlands\n"));
+    assert!(stdout.contains("players: 12/"));
+    assert!(stdout.contains(&format!("address: {address} (Source A2S_INFO)\n")));
+    assert!(stdout.contains("rule appid: 440\n"));
+}
+
+#[test]
+fn silent_servers_fail_after_the_timeout() {
+    let (address, _socket) = server(0);
+    let output = gstat(&[
+        "--script",
+        SOURCE_INFO_SCRIPT_PATH,
+        "query",
+        "selftest-source-info",
+        &address.to_string(),
+        "--timeout",
+        "200ms",
+    ]);
+    let stderr = String::from_utf8(output.stderr).unwrap();
+
+    assert!(!output.status.success());
+    assert!(stderr.contains(&address.to_string()), "{stderr}");
+}
+
+#[test]
+fn games_without_an_implementation_are_reported() {
+    let output = gstat(&["query", "tf2", "127.0.0.1"]);
+    let stderr = String::from_utf8(output.stderr).unwrap();
+
+    assert!(!output.status.success());
+    assert!(stderr.contains("Valve Source Query protocol"), "{stderr}");
+
+    let output = gstat(&["query", "nonexistent", "127.0.0.1:1"]);
+    let stderr = String::from_utf8(output.stderr).unwrap();
+
+    assert!(!output.status.success());
+    assert!(stderr.contains("unknown game `nonexistent`"), "{stderr}");
+}
+
+#[test]
+fn registry_games_are_queried_natively() {
+    let address = minecraft_server().to_string();
+    let output = gstat(&["query", "minecraft", &address, "--format", "json"]);
+    let document: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+
+    assert!(output.status.success());
+    assert_eq!(document["game"]["id"], "minecraft");
+    assert_eq!(document["address"], address.as_str());
+    assert_eq!(document["info"]["name"], "A Minecraft Server");
+    assert_eq!(document["info"]["players"], 3);
+    assert_eq!(document["info"]["max_players"], 20);
+    assert_eq!(document["rules"]["protocol"], "767");
+}
+
+#[test]
+fn json_output_includes_the_metadata() {
+    let (address, _socket) = server(1);
+    let address = address.to_string();
+    let output = gstat(&[
+        "--script",
+        SOURCE_INFO_SCRIPT_PATH,
+        "query",
+        "selftest-source-info",
+        &address,
+        "--format",
+        "json",
+    ]);
+    let document: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+
+    assert!(output.status.success());
+    assert_eq!(document["version"], 1);
+    assert_eq!(document["game"]["id"], "selftest-source-info");
+    assert_eq!(document["address"], address.as_str());
+    assert!(document["latency_ms"].as_f64().unwrap() >= 0.0);
+    assert_eq!(document["info"]["map"], "cp_badlands");
+    assert_eq!(document["info"]["players"], 12);
+    assert_eq!(document["players"], serde_json::json!([]));
+    assert_eq!(document["rules"]["appid"], "440");
+}
+
+#[test]
+fn gamedig_compat_output_has_the_gamedig_shape() {
+    let (address, _socket) = server(1);
+    let output = gstat(&[
+        "--script",
+        SOURCE_INFO_SCRIPT_PATH,
+        "query",
+        "selftest-source-info",
+        &address.to_string(),
+        "--compat",
+        "gamedig",
+    ]);
+    let result: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+
+    assert!(output.status.success());
+    assert_eq!(result["name"], "gstat fixture");
+    assert_eq!(result["map"], "cp_badlands");
+    assert_eq!(result["numplayers"], 12);
+    assert_eq!(result["players"], serde_json::json!([]));
+    assert_eq!(result["connect"], address.to_string());
+    assert_eq!(result["queryPort"], address.port());
+    assert!(result["ping"].is_u64());
+    assert_eq!(result["raw"]["appid"], "440");
+
+    let output = gstat(&[
+        "query",
+        "minecraft",
+        "127.0.0.1:1",
+        "--compat",
+        "gamedig",
+        "--format",
+        "json",
+    ]);
+
+    assert!(!output.status.success());
+}
+
+#[test]
+fn csv_output_flattens_the_json_document() {
+    let (address, _socket) = server(1);
+    let output = gstat(&[
+        "--script",
+        SOURCE_INFO_SCRIPT_PATH,
+        "query",
+        "selftest-source-info",
+        &address.to_string(),
+        "--format",
+        "csv",
+    ]);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let mut lines = stdout.lines();
+    let header: Vec<&str> = lines.next().unwrap().split(',').collect();
+    let row: Vec<&str> = lines.next().unwrap().split(',').collect();
+    let cell = |column| row[header.iter().position(|name| *name == column).unwrap()];
+
+    assert!(output.status.success(), "{stdout}");
+    assert_eq!(cell("info.map"), "cp_badlands");
+    assert_eq!(cell("rules.appid"), "440");
+    assert_eq!(lines.next(), None);
+}
+
+#[test]
+fn table_output_aligns_the_values() {
+    let (address, _socket) = server(1);
+    let output = gstat(&[
+        "--script",
+        SOURCE_INFO_SCRIPT_PATH,
+        "query",
+        "selftest-source-info",
+        &address.to_string(),
+        "--format",
+        "table",
+        "--rules",
+    ]);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+
+    assert!(output.status.success(), "{stdout}");
+    assert!(stdout.contains("name     gstat fixture\n"), "{stdout}");
+    assert!(stdout.contains("map      cp_badlands\n"), "{stdout}");
+    assert!(stdout.contains("RULE    VALUE\n"), "{stdout}");
+    assert!(stdout.contains("appid   440\n"), "{stdout}");
+}
+
+#[test]
+fn watch_mode_queries_on_an_interval() {
+    let (address, _socket) = server(3);
+    let mut child = Command::new(env!("CARGO_BIN_EXE_gstat"))
+        .args([
+            "--script",
+            SOURCE_INFO_SCRIPT_PATH,
+            "query",
+            "selftest-source-info",
+        ])
+        .arg(address.to_string())
+        .args(["--format", "json", "--watch", "50ms"])
+        .env(
+            "GSTAT_CONFIG",
+            std::env::temp_dir().join("gstat-query-missing.toml"),
+        )
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+
+    let mut lines = BufReader::new(child.stdout.take().unwrap()).lines();
+    let polls: Vec<serde_json::Value> = (0..3)
+        .map(|_| serde_json::from_str(&lines.next().unwrap().unwrap()).unwrap())
+        .collect();
+
+    child.kill().unwrap();
+    child.wait().unwrap();
+
+    assert!(polls
+        .iter()
+        .all(|poll| poll["info"]["map"] == "cp_badlands"));
+}
+
+#[test]
+fn watch_mode_rejects_a_zero_interval() {
+    let output = gstat(&[
+        "--script",
+        SOURCE_INFO_SCRIPT_PATH,
+        "query",
+        "selftest-source-info",
+        "127.0.0.1:1",
+        "--watch",
+        "0s",
+    ]);
+
+    assert!(!output.status.success());
+}
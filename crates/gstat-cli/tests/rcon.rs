@@ -0,0 +1,159 @@
+//! Running commands with `gstat rcon`.
+
+use std::{
+    io::{Read, Write},
+    net::{Ipv4Addr, SocketAddr, TcpListener, TcpStream},
+    process::{Command, Output, Stdio},
+    thread,
+};
+
+const PASSWORD: &str = "hunter2";
+
+fn read_packet(stream: &mut TcpStream) -> Option<(i32, i32, String)> {
+    let mut length = [0; 4];
+    stream.read_exact(&mut length).ok()?;
+
+    let mut packet = vec![0; i32::from_le_bytes(length) as usize];
+    stream.read_exact(&mut packet).ok()?;
+
+    let id = i32::from_le_bytes(packet[0..4].try_into().unwrap());
+    let kind = i32::from_le_bytes(packet[4..8].try_into().unwrap());
+    let body = String::from_utf8(packet[8..packet.len() - 2].to_vec()).unwrap();
+
+    Some((id, kind, body))
+}
+
+fn write_packet(stream: &mut TcpStream, id: i32, kind: i32, body: &str) {
+    let mut packet = Vec::new();
+    packet.extend(((body.len() + 10) as i32).to_le_bytes());
+    packet.extend(id.to_le_bytes());
+    packet.extend(kind.to_le_bytes());
+    packet.extend(body.as_bytes());
+    packet.extend([0, 0]);
+
+    stream.write_all(&packet).unwrap();
+}
+
+/// Binds a console on the loopback interface accepting `PASSWORD`, which answers every
+/// command with its name split across two packets.
+fn console() -> SocketAddr {
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+    let address = listener.local_addr().unwrap();
+
+    thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+
+        while let Some((id, kind, body)) = read_packet(&mut stream) {
+            match kind {
+                3 => {
+                    let answer = if body == PASSWORD { id } else { -1 };
+
+                    write_packet(&mut stream, id, 0, "");
+                    write_packet(&mut stream, answer, 2, "");
+                }
+                2 => {
+                    write_packet(&mut stream, id, 0, &format!("ran {body}\n"));
+                    write_packet(&mut stream, id, 0, "second line");
+                }
+                _ => write_packet(&mut stream, id, 0, ""),
+            }
+        }
+    });
+
+    address
+}
+
+fn rcon(args: &[&str], stdin: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_gstat"))
+        .arg("rcon")
+        .args(args)
+        .args(["--timeout", "500ms"])
+        .env(
+            "GSTAT_CONFIG",
+            std::env::temp_dir().join("gstat-rcon-missing.toml"),
+        )
+        .env(
+            "XDG_STATE_HOME",
+            std::env::temp_dir().join("gstat-rcon-state"),
+        )
+        .env_remove("GSTAT_PROFILE")
+        .env_remove("GSTAT_RCON_PASSWORD")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(stdin.as_bytes())
+        .unwrap();
+
+    child.wait_with_output().unwrap()
+}
+
+#[test]
+fn commands_are_run_once_with_exec() {
+    let address = console().to_string();
+    let output = rcon(
+        &["tf2", &address, "--password", PASSWORD, "--exec", "status"],
+        "",
+    );
+
+    assert!(output.status.success(), "{output:?}");
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "ran status\nsecond line\n"
+    );
+}
+
+#[test]
+fn commands_are_read_from_the_shell_until_exit() {
+    let address = console().to_string();
+    let output = rcon(
+        &["source-rcon", &address, "--password", PASSWORD],
+        "status\nusers\nexit\nignored\n",
+    );
+    assert!(output.status.success(), "{output:?}");
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("ran status\nsecond line\n"), "{stdout}");
+    assert!(stdout.contains("ran users\nsecond line\n"), "{stdout}");
+    assert!(!stdout.contains("ignored"));
+}
+
+#[test]
+fn wrong_passwords_are_rejected() {
+    let address = console().to_string();
+    let output = rcon(
+        &["tf2", &address, "--password", "guess", "--exec", "status"],
+        "",
+    );
+
+    assert!(!output.status.success());
+    assert!(String::from_utf8(output.stderr)
+        .unwrap()
+        .contains("rejected the credentials"));
+}
+
+#[test]
+fn games_without_a_console_are_refused() {
+    let output = rcon(
+        &[
+            "minecraft",
+            "127.0.0.1",
+            "--password",
+            PASSWORD,
+            "--exec",
+            "list",
+        ],
+        "",
+    );
+
+    assert!(!output.status.success());
+    assert!(String::from_utf8(output.stderr)
+        .unwrap()
+        .contains("has no Source RCON console"));
+}
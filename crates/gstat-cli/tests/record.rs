@@ -0,0 +1,69 @@
+//! Recording queries to fixture files with `gstat query --record`.
+
+use gstat_core::{
+    prelude::{Protocol, Response, TimeoutSettings},
+    script::{ScriptQuery, ScriptRegistry},
+};
+use gstat_test::{
+    loopback_server, Fixture, MockProtocol, Reply, SOURCE_INFO_PACKET, SOURCE_INFO_SCRIPT_PATH,
+};
+
+use std::{
+    fs,
+    process::{Command, Output},
+};
+
+fn gstat(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_gstat"))
+        .args(args)
+        .env(
+            "GSTAT_CONFIG",
+            std::env::temp_dir().join("gstat-record-missing.toml"),
+        )
+        .env_remove("GSTAT_PROFILE")
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn recorded_queries_replay_through_the_parser() {
+    let address = loopback_server(SOURCE_INFO_PACKET).address();
+    let path = std::env::temp_dir().join(format!("gstat-record-{}.txt", std::process::id()));
+    let output = gstat(&[
+        "--script",
+        SOURCE_INFO_SCRIPT_PATH,
+        "query",
+        "selftest-source-info",
+        &address.to_string(),
+        "--record",
+        path.to_str().unwrap(),
+    ]);
+    assert!(output.status.success());
+
+    let fixture = Fixture::load(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+    assert_eq!(
+        fixture.exchanges()[0].replies(),
+        [Reply::Packet(SOURCE_INFO_PACKET.into())]
+    );
+
+    let mut scripts = ScriptRegistry::new();
+    let game = scripts
+        .load(&fs::read_to_string(SOURCE_INFO_SCRIPT_PATH).unwrap())
+        .unwrap();
+    let protocol = MockProtocol::new(game.parser(), fixture.replay());
+    let timeouts = TimeoutSettings::default();
+    let response = tokio::runtime::Builder::new_current_thread()
+        .enable_time()
+        .build()
+        .unwrap()
+        .block_on(async {
+            let _guard = protocol.connect(address, &timeouts).await?;
+            protocol.send_query(ScriptQuery, &timeouts).await?;
+            protocol.receive_response(&timeouts).await
+        })
+        .unwrap();
+
+    assert_eq!(response.to_common().map, "cp_badlands");
+    protocol.transport().assert_finished();
+}
@@ -0,0 +1,61 @@
+//! Running the self-test against valid and broken configurations.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::{Command, Output},
+};
+
+fn config(name: &str, contents: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("gstat-{}-{name}.toml", std::process::id()));
+    fs::write(&path, contents).unwrap();
+    path
+}
+
+fn selftest(config: &Path, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_gstat"))
+        .arg("--config")
+        .arg(config)
+        .arg("selftest")
+        .args(args)
+        .env_remove("GSTAT_PROFILE")
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn selftest_passes_with_a_valid_config() {
+    let path = config(
+        "selftest-valid",
+        "[profiles.default.servers]\nlobby = { game = \"tf2\", address = \"127.0.0.1:27015\" }\n",
+    );
+    let output = selftest(&path, &[]);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+
+    assert!(output.status.success(), "{stdout}");
+    assert!(stdout.contains("pass  config"));
+    assert!(stdout.contains("profile `default`, 1 server"));
+    assert!(stdout.contains("pass  fixture source-info"));
+    assert!(stdout.contains("pass  fixture source-players"));
+    assert!(stdout.contains("pass  udp loopback"));
+}
+
+#[test]
+fn selftest_reports_config_problems_and_fails() {
+    let path = config(
+        "selftest-invalid",
+        "[profiles.default.servers]\nlobby = { game = \"nope\", address = \"127.0.0.1\" }\n",
+    );
+    let output = selftest(&path, &["--format", "json"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("1 of 5 checks failed"));
+
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(report["passed"], false);
+    assert_eq!(report["checks"][0]["status"], "fail");
+
+    let detail = report["checks"][0]["detail"].as_str().unwrap();
+    assert!(detail.contains("unknown game `nope`"));
+    assert!(detail.contains("no port in `127.0.0.1`"));
+    assert_eq!(report["checks"][1]["status"], "pass");
+}
@@ -0,0 +1,224 @@
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    env,
+    fmt::Write as _,
+    fs,
+    path::Path,
+};
+
+use serde::Deserialize;
+
+/// The declarative data file the registry tables are generated from.
+const DATA_FILE: &str = "data/games.toml";
+
+/// Capability names accepted in the data file, mapped to their `Capabilities` constant.
+const CAPABILITIES: &[(&str, &str)] = &[
+    ("players", "PLAYERS"),
+    ("rules", "RULES"),
+    ("challenge", "CHALLENGE"),
+    ("multi-packet", "MULTI_PACKET"),
+    ("rcon", "RCON"),
+];
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Data {
+    #[serde(default)]
+    protocol: Vec<ProtocolData>,
+    #[serde(default)]
+    game: Vec<GameData>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ProtocolData {
+    id: String,
+    name: String,
+    transport: String,
+    #[serde(default)]
+    capabilities: Vec<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct GameData {
+    id: String,
+    name: String,
+    #[serde(default)]
+    aliases: Vec<String>,
+    protocol: String,
+    release_year: u32,
+    game_port: u16,
+    query_port: Option<u16>,
+    query_port_offset: Option<i32>,
+    #[serde(default)]
+    steam_appids: Vec<u32>,
+    srv: Option<String>,
+    capabilities: Option<Vec<String>>,
+}
+
+fn main() {
+    println!("cargo:rerun-if-changed={DATA_FILE}");
+
+    let source = fs::read_to_string(DATA_FILE)
+        .unwrap_or_else(|err| panic!("failed to read {DATA_FILE}: {err}"));
+    let data: Data =
+        toml::from_str(&source).unwrap_or_else(|err| panic!("failed to parse {DATA_FILE}: {err}"));
+
+    let generated = generate(&data).unwrap_or_else(|err| panic!("invalid {DATA_FILE}: {err}"));
+
+    let out = Path::new(&env::var("OUT_DIR").unwrap()).join("registry.rs");
+    fs::write(out, generated).expect("failed to write generated registry");
+}
+
+/// Validates the data file and renders the static registry tables.
+fn generate(data: &Data) -> Result<String, String> {
+    let mut protocols = HashSet::new();
+    let mut identifiers = HashMap::new();
+    let mut appids = BTreeMap::new();
+
+    let mut out = String::new();
+
+    writeln!(out, "/// All known query protocols.").unwrap();
+    writeln!(out, "pub static PROTOCOLS: &[ProtocolEntry] = &[").unwrap();
+    for protocol in &data.protocol {
+        if !protocols.insert(protocol.id.as_str()) {
+            return Err(format!("duplicate protocol `{}`", protocol.id));
+        }
+
+        let transport = match protocol.transport.as_str() {
+            "udp" => "Udp",
+            "tcp" => "Tcp",
+            other => return Err(format!("unknown transport `{other}` in `{}`", protocol.id)),
+        };
+
+        writeln!(
+            out,
+            "    ProtocolEntry {{ id: {:?}, name: {:?}, transport: Transport::{transport}, capabilities: {} }},",
+            protocol.id,
+            protocol.name,
+            capabilities(&protocol.capabilities)?,
+        )
+        .unwrap();
+    }
+    writeln!(out, "];").unwrap();
+
+    writeln!(out, "/// All known games, in data file order.").unwrap();
+    writeln!(out, "pub static GAMES: &[GameEntry] = &[").unwrap();
+    for (index, game) in data.game.iter().enumerate() {
+        for identifier in std::iter::once(&game.id).chain(&game.aliases) {
+            // Lookups ignore ASCII case, so `Rust` and `rust` would shadow each other.
+            if let Some(previous) =
+                identifiers.insert(identifier.to_ascii_lowercase(), identifier.as_str())
+            {
+                return Err(format!(
+                    "duplicate game identifier `{identifier}` (collides with `{previous}`)"
+                ));
+            }
+        }
+
+        let protocol = data
+            .protocol
+            .iter()
+            .find(|protocol| protocol.id == game.protocol)
+            .ok_or_else(|| {
+                format!(
+                    "game `{}` uses unknown protocol `{}`",
+                    game.id, game.protocol
+                )
+            })?;
+
+        for appid in &game.steam_appids {
+            if let Some(previous) = appids.insert(*appid, index) {
+                return Err(format!(
+                    "steam appid {appid} is used by both `{}` and `{}`",
+                    data.game[previous].id, game.id
+                ));
+            }
+        }
+
+        let query_port = match (game.query_port, game.query_port_offset) {
+            (Some(_), Some(_)) => {
+                return Err(format!(
+                    "game `{}` sets both `query_port` and `query_port_offset`",
+                    game.id
+                ))
+            }
+            (Some(port), None) => format!("QueryPort::Fixed({port})"),
+            (None, Some(offset)) => {
+                let port = i32::from(game.game_port) + offset;
+
+                if u16::try_from(port).is_err() {
+                    return Err(format!(
+                        "game `{}` has a query port offset that moves its default port to {port}",
+                        game.id
+                    ));
+                }
+
+                format!("QueryPort::Offset({offset})")
+            }
+            (None, None) => "QueryPort::SameAsGame".to_string(),
+        };
+
+        if let Some(srv) = &game.srv {
+            let labels: Vec<&str> = srv.split('.').collect();
+
+            if labels.len() != 2
+                || labels
+                    .iter()
+                    .any(|label| label.len() < 2 || !label.starts_with('_'))
+            {
+                return Err(format!(
+                    "game `{}` has malformed srv `{srv}`, expected e.g. `_service._tcp`",
+                    game.id
+                ));
+            }
+        }
+
+        writeln!(
+            out,
+            "    GameEntry {{ id: {:?}, name: {:?}, aliases: &{:?}, protocol: {:?}, release_year: {}, game_port: {}, query_port: {query_port}, steam_appids: &{:?}, srv: {:?}, capabilities: {} }},",
+            game.id,
+            game.name,
+            game.aliases,
+            game.protocol,
+            game.release_year,
+            game.game_port,
+            game.steam_appids,
+            game.srv,
+            capabilities(game.capabilities.as_ref().unwrap_or(&protocol.capabilities))?,
+        )
+        .unwrap();
+    }
+    writeln!(out, "];").unwrap();
+
+    writeln!(
+        out,
+        "/// Steam AppIDs mapped to their index in `GAMES`, sorted by AppID."
+    )
+    .unwrap();
+    writeln!(out, "static APPIDS: &[(u32, usize)] = &[").unwrap();
+    for (appid, index) in &appids {
+        writeln!(out, "    ({appid}, {index}),").unwrap();
+    }
+    writeln!(out, "];").unwrap();
+
+    Ok(out)
+}
+
+/// Renders a list of capability names as a `Capabilities` constant expression.
+fn capabilities(names: &[String]) -> Result<String, String> {
+    let mut expression = String::from("Capabilities::empty()");
+
+    for name in names {
+        let constant = CAPABILITIES
+            .iter()
+            .find(|(capability, _)| capability == name)
+            .map(|(_, constant)| constant)
+            .ok_or_else(|| format!("unknown capability `{name}`"))?;
+
+        write!(expression, ".union(Capabilities::{constant})").unwrap();
+    }
+
+    Ok(expression)
+}
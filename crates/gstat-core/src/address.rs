@@ -0,0 +1,276 @@
+//! Server addresses that keep track of which game they belong to.
+
+use crate::registry::{self, GameEntry};
+
+use std::{
+    error::Error as StdError,
+    fmt::{Display, Formatter, Result as FmtResult},
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+    str::FromStr,
+};
+
+/// `ServerAddress` identifies a game server together with the game it runs.
+///
+/// Many games answer queries on a different port than the one players connect to, so a
+/// bare `SocketAddr` is ambiguous once results are collected from many servers. Carrying
+/// the game and both ports keeps every result attributable to the server it came from.
+///
+/// Addresses parse from `game@host` or `game@host:port`, where `port` is the game port and
+/// the query port is derived from the registry entry of the game, e.g. `tf2@192.0.2.1` or
+/// `rust@[2001:db8::1]:28015`.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct ServerAddress {
+    /// The identifier of the game the server runs.
+    pub game: String,
+    /// The address of the host.
+    pub host: IpAddr,
+    /// The port queries are sent to.
+    pub query_port: u16,
+    /// The port players connect to.
+    pub game_port: u16,
+}
+
+impl ServerAddress {
+    /// Creates an address from its parts.
+    ///
+    /// # Parameters
+    ///
+    /// * `game`: The identifier of the game the server runs.
+    /// * `host`: The address of the host.
+    /// * `query_port`: The port queries are sent to.
+    /// * `game_port`: The port players connect to.
+    pub fn new(game: impl Into<String>, host: IpAddr, query_port: u16, game_port: u16) -> Self {
+        ServerAddress {
+            game: game.into(),
+            host,
+            query_port,
+            game_port,
+        }
+    }
+
+    /// Creates the address of a server listening on the default ports of `game`.
+    ///
+    /// # Parameters
+    ///
+    /// * `game`: The registry entry of the game.
+    /// * `host`: The address of the host.
+    pub fn for_game(game: &GameEntry, host: IpAddr) -> Self {
+        Self::new(game.id, host, game.default_query_port(), game.game_port)
+    }
+
+    /// Creates the address of a server of `game` listening on `game_port`, deriving the
+    /// query port from the registry entry.
+    ///
+    /// # Parameters
+    ///
+    /// * `game`: The registry entry of the game.
+    /// * `host`: The address of the host.
+    /// * `game_port`: The port players connect to.
+    ///
+    /// # Returns
+    ///
+    /// The address, or `None` if the game's query port offset moves `game_port` out of the
+    /// port range.
+    pub fn for_game_port(game: &GameEntry, host: IpAddr, game_port: u16) -> Option<Self> {
+        let query_port = game.query_port_for(game_port)?;
+
+        Some(Self::new(game.id, host, query_port, game_port))
+    }
+
+    /// Returns the address queries are sent to.
+    pub fn query_addr(&self) -> SocketAddr {
+        SocketAddr::new(self.host, self.query_port)
+    }
+
+    /// Returns the address players connect to.
+    pub fn game_addr(&self) -> SocketAddr {
+        SocketAddr::new(self.host, self.game_port)
+    }
+
+    /// Returns the registry entry of the game, if it is a built-in game.
+    pub fn entry(&self) -> Option<&'static GameEntry> {
+        registry::game(&self.game)
+    }
+}
+
+impl From<&ServerAddress> for SocketAddr {
+    fn from(address: &ServerAddress) -> Self {
+        address.query_addr()
+    }
+}
+
+impl From<ServerAddress> for SocketAddr {
+    fn from(address: ServerAddress) -> Self {
+        address.query_addr()
+    }
+}
+
+impl Display for ServerAddress {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(f, "{}@{}", self.game, self.game_addr())?;
+
+        if self.query_port != self.game_port {
+            write!(f, " (query port {})", self.query_port)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl FromStr for ServerAddress {
+    type Err = InvalidAddress;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = |reason| InvalidAddress {
+            address: s.to_string(),
+            reason,
+        };
+
+        let (game, host) = s
+            .trim()
+            .split_once('@')
+            .ok_or_else(|| invalid(InvalidReason::MissingGame))?;
+
+        let entry = registry::game(game).ok_or_else(|| invalid(InvalidReason::UnknownGame))?;
+
+        if let Ok(address) = host.parse::<SocketAddr>() {
+            return Self::for_game_port(entry, address.ip(), address.port())
+                .ok_or_else(|| invalid(InvalidReason::InvalidPort));
+        }
+
+        host.trim_start_matches('[')
+            .trim_end_matches(']')
+            .parse::<IpAddr>()
+            .map(|host| Self::for_game(entry, host))
+            .map_err(|_| invalid(InvalidReason::InvalidHost))
+    }
+}
+
+/// `BindAddresses` holds the local addresses transports bind their sockets to.
+///
+/// Hosts with several interfaces or addresses, e.g. a dedicated scanning address, choose
+/// the source of their queries by binding to it. Binding is decided per address family,
+/// so a dual-stack host can configure one IPv4 and one IPv6 address and query servers of
+/// both families. Peers of a family without a configured address are queried from the
+/// unspecified address of that family, leaving the choice to the operating system.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct BindAddresses {
+    v4: Option<SocketAddr>,
+    v6: Option<SocketAddr>,
+}
+
+impl BindAddresses {
+    /// Creates bind addresses that leave the choice to the operating system.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Binds sockets for peers of the family of `local` to it, replacing the address
+    /// previously set for that family.
+    ///
+    /// # Parameters
+    ///
+    /// * `local`: The local address, usually with port `0` to pick any free port.
+    pub fn with(mut self, local: SocketAddr) -> Self {
+        match local {
+            SocketAddr::V4(_) => self.v4 = Some(local),
+            SocketAddr::V6(_) => self.v6 = Some(local),
+        }
+
+        self
+    }
+
+    /// Returns the address configured for the family of `peer`, if any.
+    pub fn configured_for(&self, peer: SocketAddr) -> Option<SocketAddr> {
+        match peer {
+            SocketAddr::V4(_) => self.v4,
+            SocketAddr::V6(_) => self.v6,
+        }
+    }
+
+    /// Returns the address to bind a socket for `peer` to: the configured address of its
+    /// family, or else the unspecified address of its family with any port.
+    pub fn for_peer(&self, peer: SocketAddr) -> SocketAddr {
+        self.configured_for(peer).unwrap_or_else(|| match peer {
+            SocketAddr::V4(_) => SocketAddr::from((Ipv4Addr::UNSPECIFIED, 0)),
+            SocketAddr::V6(_) => SocketAddr::from((Ipv6Addr::UNSPECIFIED, 0)),
+        })
+    }
+}
+
+/// Orders resolved addresses for dual-stack connection attempts.
+///
+/// Following RFC 8305, the families alternate, starting with the family of the first
+/// address, so a broken IPv6 or IPv4 path costs one failed attempt rather than one per
+/// address of that family. The relative order within each family is kept.
+///
+/// # Parameters
+///
+/// * `addresses`: The addresses in the order the resolver returned them.
+pub fn interleave_families(addresses: impl IntoIterator<Item = SocketAddr>) -> Vec<SocketAddr> {
+    let addresses: Vec<SocketAddr> = addresses.into_iter().collect();
+    let Some(first) = addresses.first() else {
+        return addresses;
+    };
+
+    let first_is_v6 = first.is_ipv6();
+    let (mut preferred, mut other): (Vec<_>, Vec<_>) = (Vec::new(), Vec::new());
+
+    for address in &addresses {
+        if address.is_ipv6() == first_is_v6 {
+            preferred.push(*address);
+        } else {
+            other.push(*address);
+        }
+    }
+
+    let mut preferred = preferred.into_iter();
+    let mut other = other.into_iter();
+    let mut ordered = Vec::with_capacity(addresses.len());
+
+    loop {
+        match (preferred.next(), other.next()) {
+            (None, None) => return ordered,
+            (first, second) => ordered.extend(first.into_iter().chain(second)),
+        }
+    }
+}
+
+/// Describes why a `ServerAddress` could not be parsed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InvalidReason {
+    /// The address does not start with `game@`.
+    MissingGame,
+    /// The game is not in the registry.
+    UnknownGame,
+    /// The host is not an IP address, optionally followed by a port.
+    InvalidHost,
+    /// The game has no query port for the given port.
+    InvalidPort,
+}
+
+/// A string that is not a valid `ServerAddress`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InvalidAddress {
+    /// The invalid address.
+    pub address: String,
+    /// Why the address is invalid.
+    pub reason: InvalidReason,
+}
+
+impl Display for InvalidAddress {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        let reason = match self.reason {
+            InvalidReason::MissingGame => "expected `game@host[:port]`",
+            InvalidReason::UnknownGame => "unknown game",
+            InvalidReason::InvalidHost => "invalid host",
+            InvalidReason::InvalidPort => "the port has no query port",
+        };
+
+        write!(f, "Invalid server address {:?}: {reason}", self.address)
+    }
+}
+
+impl StdError for InvalidAddress {}
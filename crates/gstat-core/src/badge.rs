@@ -0,0 +1,210 @@
+//! SVG status badges.
+//!
+//! READMEs and community forums embed small badges showing whether a server is online
+//! and how many players it has. `Badge` renders them in the flat style of common badge
+//! services, without depending on fonts or an image library: text widths are estimated
+//! from a table of average glyph widths.
+
+use crate::prelude::ServerInfo;
+
+use std::fmt::Write;
+
+/// The color of a badge for a server with free slots.
+const ONLINE: &str = "#4c1";
+
+/// The color of a badge for a full server.
+const FULL: &str = "#fe7d37";
+
+/// The color of a badge for a server that didn't answer.
+const OFFLINE: &str = "#9f9f9f";
+
+/// The color of the label side of a badge.
+const LABEL: &str = "#555";
+
+/// The horizontal padding on each side of a text, in pixels.
+const PADDING: u32 = 6;
+
+/// The longest label or message rendered before it is cut off with an ellipsis.
+const MAX_TEXT_CHARS: usize = 48;
+
+/// `Badge` is a two-part status badge: a label on the left and a colored message on the
+/// right, e.g. `My Server | 12/24 players`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Badge {
+    label: String,
+    message: String,
+    color: String,
+}
+
+impl Badge {
+    /// Creates the badge of a server that answered.
+    ///
+    /// The label is the server name, and the message its player count. The message is
+    /// green while slots are free and orange once the server is full.
+    ///
+    /// # Parameters
+    ///
+    /// * `info`: The normalized description of the server.
+    pub fn online(info: &ServerInfo) -> Self {
+        let message = if info.max_players > 0 {
+            format!("{}/{} players", info.players, info.max_players)
+        } else {
+            format!("{} players", info.players)
+        };
+
+        let label = match info.name.trim() {
+            "" => "server",
+            name => name,
+        };
+
+        Badge {
+            label: label.to_string(),
+            message,
+            color: if info.is_full() { FULL } else { ONLINE }.to_string(),
+        }
+    }
+
+    /// Creates the badge of a server that didn't answer.
+    pub fn offline() -> Self {
+        Badge {
+            label: "server".to_string(),
+            message: "offline".to_string(),
+            color: OFFLINE.to_string(),
+        }
+    }
+
+    /// Replaces the label, e.g. with a name that is shorter than the server's.
+    pub fn with_label(mut self, label: impl Into<String>) -> Self {
+        self.label = label.into();
+        self
+    }
+
+    /// Replaces the color of the message side.
+    ///
+    /// # Parameters
+    ///
+    /// * `color`: A CSS color, e.g. `#007ec6`. Characters other than ASCII letters, digits
+    ///   and `#` are dropped, so the color can't break out of the SVG attribute.
+    pub fn with_color(mut self, color: &str) -> Self {
+        self.color = color
+            .chars()
+            .filter(|c| c.is_ascii_alphanumeric() || *c == '#')
+            .collect();
+        self
+    }
+
+    /// Returns the label shown on the left.
+    pub fn label(&self) -> &str {
+        &self.label
+    }
+
+    /// Returns the message shown on the right.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// Returns the color of the message side.
+    pub fn color(&self) -> &str {
+        &self.color
+    }
+
+    /// Renders the badge as a standalone SVG document.
+    ///
+    /// Server names are chosen by server operators, so the label and message are escaped
+    /// and cut to a bounded length before they are embedded.
+    pub fn to_svg(&self) -> String {
+        let label = truncate(&self.label);
+        let message = truncate(&self.message);
+
+        let label_width = text_width(&label) + 2 * PADDING;
+        let message_width = text_width(&message) + 2 * PADDING;
+        let width = label_width + message_width;
+
+        let label = escape(&label);
+        let message = escape(&message);
+
+        let mut svg = String::new();
+
+        // Writing to a `String` can't fail.
+        let _ = write!(
+            svg,
+            concat!(
+                r##"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="20" "##,
+                r##"role="img" aria-label="{label}: {message}">"##,
+                r##"<title>{label}: {message}</title>"##,
+                r##"<clipPath id="r"><rect width="{width}" height="20" rx="3" fill="#fff"/></clipPath>"##,
+                r##"<g clip-path="url(#r)">"##,
+                r##"<rect width="{label_width}" height="20" fill="{label_color}"/>"##,
+                r##"<rect x="{label_width}" width="{message_width}" height="20" fill="{color}"/>"##,
+                r##"</g>"##,
+                r##"<g fill="#fff" text-anchor="middle" "##,
+                r##"font-family="Verdana,Geneva,DejaVu Sans,sans-serif" font-size="11">"##,
+                r##"<text x="{label_x}" y="14">{label}</text>"##,
+                r##"<text x="{message_x}" y="14">{message}</text>"##,
+                r##"</g></svg>"##,
+            ),
+            width = width,
+            label = label,
+            message = message,
+            label_width = label_width,
+            message_width = message_width,
+            label_color = LABEL,
+            color = self.color,
+            label_x = label_width / 2,
+            message_x = label_width + message_width / 2,
+        );
+
+        svg
+    }
+}
+
+/// Cuts `text` to `MAX_TEXT_CHARS` characters, ending it with an ellipsis if it was longer.
+fn truncate(text: &str) -> String {
+    let text = text.trim();
+
+    if text.chars().count() <= MAX_TEXT_CHARS {
+        return text.to_string();
+    }
+
+    let mut cut: String = text.chars().take(MAX_TEXT_CHARS - 1).collect();
+    cut.push('…');
+    cut
+}
+
+/// Estimates the width of `text` in 11px Verdana, in pixels.
+fn text_width(text: &str) -> u32 {
+    let tenths: u32 = text
+        .chars()
+        .map(|c| match c {
+            'i' | 'j' | 'l' | '.' | ',' | ':' | ';' | '\'' | '|' | '!' => 35,
+            'f' | 'r' | 't' | ' ' | '(' | ')' | '[' | ']' | '/' | '-' => 45,
+            'm' | 'w' | 'M' | 'W' | '%' | '@' => 100,
+            c if c.is_ascii_uppercase() || c.is_ascii_digit() => 75,
+            c if c.is_ascii() => 65,
+            // Wide scripts such as CJK take about a full em.
+            _ => 110,
+        })
+        .sum();
+
+    tenths.div_ceil(10)
+}
+
+/// Escapes the characters with a meaning in XML text and attributes.
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            // Control characters aren't allowed in XML 1.0 documents.
+            c if c.is_control() => {}
+            c => escaped.push(c),
+        }
+    }
+
+    escaped
+}
@@ -0,0 +1,367 @@
+use std::{
+    error::Error as StdError,
+    fmt::{Display, Formatter, Result as FmtResult},
+    fs, io,
+    net::{IpAddr, SocketAddr},
+    path::Path,
+    str::FromStr,
+};
+
+/// The ranges blocked by `Blocklist::reserved`.
+///
+/// The IPv6 transition ranges (IPv4-compatible, NAT64, Teredo and 6to4) are blocked whole,
+/// since each of them can carry an internal IPv4 address past the IPv4 rules.
+const RESERVED: &str = "
+    0.0.0.0/8
+    10.0.0.0/8
+    100.64.0.0/10
+    127.0.0.0/8
+    169.254.0.0/16
+    172.16.0.0/12
+    192.0.0.0/24
+    192.0.2.0/24
+    192.168.0.0/16
+    198.18.0.0/15
+    198.51.100.0/24
+    203.0.113.0/24
+    224.0.0.0/4
+    240.0.0.0/4
+    ::/96
+    64:ff9b::/96
+    100::/64
+    2001::/32
+    2001:db8::/32
+    2002::/16
+    fc00::/7
+    fe80::/10
+    ff00::/8
+";
+
+/// `IpNet` is a range of addresses in CIDR notation, such as `203.0.113.0/24`.
+///
+/// A bare address without a prefix length is treated as a range holding only itself.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct IpNet {
+    address: IpAddr,
+    prefix_len: u8,
+}
+
+impl IpNet {
+    /// Creates a range from a base address and a prefix length.
+    ///
+    /// Host bits of `address` below the prefix are ignored.
+    ///
+    /// # Parameters
+    ///
+    /// * `address`: Any address inside the range.
+    /// * `prefix_len`: The number of leading bits shared by every address in the range.
+    ///
+    /// # Returns
+    ///
+    /// The range, or `None` if `prefix_len` exceeds the width of the address family.
+    pub fn new(address: IpAddr, prefix_len: u8) -> Option<Self> {
+        let max = match address {
+            IpAddr::V4(_) => 32,
+            IpAddr::V6(_) => 128,
+        };
+
+        (prefix_len <= max).then_some(IpNet {
+            address,
+            prefix_len,
+        })
+    }
+
+    /// Returns the base address the range was created from.
+    pub fn address(&self) -> IpAddr {
+        self.address
+    }
+
+    /// Returns the prefix length of the range.
+    pub fn prefix_len(&self) -> u8 {
+        self.prefix_len
+    }
+
+    /// Returns `true` if `address` lies within the range.
+    ///
+    /// IPv4-mapped IPv6 addresses are matched as the IPv4 address they carry.
+    pub fn contains(&self, address: IpAddr) -> bool {
+        match (self.address, address.to_canonical()) {
+            (IpAddr::V4(net), IpAddr::V4(address)) => prefix_matches(
+                u32::from(net).into(),
+                u32::from(address).into(),
+                32 - self.prefix_len,
+            ),
+            (IpAddr::V6(net), IpAddr::V6(address)) => {
+                prefix_matches(u128::from(net), u128::from(address), 128 - self.prefix_len)
+            }
+            _ => false,
+        }
+    }
+}
+
+impl From<IpAddr> for IpNet {
+    fn from(address: IpAddr) -> Self {
+        let prefix_len = match address {
+            IpAddr::V4(_) => 32,
+            IpAddr::V6(_) => 128,
+        };
+
+        IpNet {
+            address,
+            prefix_len,
+        }
+    }
+}
+
+impl FromStr for IpNet {
+    type Err = InvalidEntry;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || InvalidEntry {
+            line: None,
+            entry: s.to_string(),
+        };
+
+        match s.split_once('/') {
+            Some((address, prefix_len)) => {
+                let address = address.trim().parse().map_err(|_| invalid())?;
+                let prefix_len = prefix_len.trim().parse().map_err(|_| invalid())?;
+
+                IpNet::new(address, prefix_len).ok_or_else(invalid)
+            }
+            None => s
+                .trim()
+                .parse::<IpAddr>()
+                .map(IpNet::from)
+                .map_err(|_| invalid()),
+        }
+    }
+}
+
+impl Display for IpNet {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(f, "{}/{}", self.address, self.prefix_len)
+    }
+}
+
+/// Returns `true` if two addresses are equal once their lowest `host_bits` bits are ignored.
+fn prefix_matches(net: u128, address: u128, host_bits: u8) -> bool {
+    let mask = u128::MAX.checked_shl(host_bits.into()).unwrap_or(0);
+
+    net & mask == address & mask
+}
+
+/// A single blocked range together with where it came from.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BlockRule {
+    /// The blocked range.
+    pub net: IpNet,
+    /// The list the range was loaded from, e.g. the path of an opt-out file.
+    pub source: Option<String>,
+}
+
+/// `Blocked` is the reason a target was skipped instead of queried.
+///
+/// Batch and daemon layers report blocked targets with this value rather than dropping
+/// them, so operators can show that abuse complaints and opt-out requests were honored.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Blocked {
+    /// The target that was not queried.
+    pub address: SocketAddr,
+    /// The rule that matched the target.
+    pub rule: BlockRule,
+}
+
+impl Display for Blocked {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(f, "{} is blocked by {}", self.address, self.rule.net)?;
+
+        if let Some(source) = &self.rule.source {
+            write!(f, " ({source})")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// `Blocklist` holds the addresses and ranges that must never be queried.
+///
+/// Entries are added programmatically or loaded from opt-out list files, which contain one
+/// address or CIDR range per line. Blank lines are ignored and `#` starts a comment.
+#[derive(Clone, Debug, Default)]
+pub struct Blocklist {
+    rules: Vec<BlockRule>,
+}
+
+impl Blocklist {
+    /// Creates an empty blocklist.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a blocklist of the private, loopback, link-local, multicast, documentation
+    /// and otherwise reserved ranges of IPv4 and IPv6.
+    ///
+    /// Services querying addresses given by untrusted users start from this list, so they
+    /// can't be made to probe the networks they run in.
+    pub fn reserved() -> Self {
+        let mut blocklist = Self::new();
+
+        blocklist
+            .extend_from_list(RESERVED, Some("reserved"))
+            .expect("the reserved ranges are valid");
+
+        blocklist
+    }
+
+    /// Blocks an address or range.
+    ///
+    /// # Parameters
+    ///
+    /// * `net`: The address or range to block.
+    pub fn block(&mut self, net: impl Into<IpNet>) -> &mut Self {
+        self.rules.push(BlockRule {
+            net: net.into(),
+            source: None,
+        });
+        self
+    }
+
+    /// Adds every entry of an opt-out list.
+    ///
+    /// # Parameters
+    ///
+    /// * `list`: The contents of the list, one entry per line.
+    /// * `source`: A name for the list reported alongside blocked targets.
+    ///
+    /// # Returns
+    ///
+    /// The blocklist, or the first invalid entry. Nothing is added if an entry is invalid.
+    pub fn extend_from_list(
+        &mut self,
+        list: &str,
+        source: Option<&str>,
+    ) -> Result<&mut Self, InvalidEntry> {
+        let mut rules = Vec::new();
+
+        for (index, line) in list.lines().enumerate() {
+            let entry = line.split('#').next().unwrap_or_default().trim();
+
+            if entry.is_empty() {
+                continue;
+            }
+
+            let net = entry.parse::<IpNet>().map_err(|err| InvalidEntry {
+                line: Some(index + 1),
+                ..err
+            })?;
+
+            rules.push(BlockRule {
+                net,
+                source: source.map(str::to_string),
+            });
+        }
+
+        self.rules.append(&mut rules);
+
+        Ok(self)
+    }
+
+    /// Adds every entry of an opt-out list file.
+    ///
+    /// # Parameters
+    ///
+    /// * `path`: The path of the file, which is also recorded as the source of its entries.
+    pub fn load(&mut self, path: impl AsRef<Path>) -> Result<&mut Self, BlocklistError> {
+        let path = path.as_ref();
+        let list = fs::read_to_string(path).map_err(BlocklistError::Io)?;
+
+        self.extend_from_list(&list, Some(&path.display().to_string()))
+            .map_err(BlocklistError::Invalid)
+    }
+
+    /// Returns the rules of the blocklist.
+    pub fn rules(&self) -> &[BlockRule] {
+        &self.rules
+    }
+
+    /// Returns `true` if the blocklist has no rules.
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// Checks whether a target may be queried.
+    ///
+    /// # Parameters
+    ///
+    /// * `address`: The target to check.
+    ///
+    /// # Returns
+    ///
+    /// `Ok` if the target may be queried, or the reason it must be skipped.
+    pub fn check(&self, address: SocketAddr) -> Result<(), Blocked> {
+        match self
+            .rules
+            .iter()
+            .find(|rule| rule.net.contains(address.ip()))
+        {
+            Some(rule) => Err(Blocked {
+                address,
+                rule: rule.clone(),
+            }),
+            None => Ok(()),
+        }
+    }
+
+    /// Returns `true` if `address` must not be queried.
+    pub fn is_blocked(&self, address: SocketAddr) -> bool {
+        self.check(address).is_err()
+    }
+}
+
+/// An entry of a blocklist that is neither an address nor a CIDR range.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InvalidEntry {
+    /// The line of the list the entry was found on, starting at 1.
+    pub line: Option<usize>,
+    /// The invalid entry.
+    pub entry: String,
+}
+
+impl Display for InvalidEntry {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self.line {
+            Some(line) => write!(f, "Invalid blocklist entry {:?} on line {line}", self.entry),
+            None => write!(f, "Invalid blocklist entry {:?}", self.entry),
+        }
+    }
+}
+
+impl StdError for InvalidEntry {}
+
+/// An error that occurred while loading an opt-out list file.
+#[derive(Debug)]
+pub enum BlocklistError {
+    /// The file could not be read.
+    Io(io::Error),
+    /// The file contains an invalid entry.
+    Invalid(InvalidEntry),
+}
+
+impl Display for BlocklistError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            BlocklistError::Io(err) => write!(f, "Failed to read blocklist: {err}"),
+            BlocklistError::Invalid(err) => Display::fmt(err, f),
+        }
+    }
+}
+
+impl StdError for BlocklistError {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        match self {
+            BlocklistError::Io(err) => Some(err),
+            BlocklistError::Invalid(err) => Some(err),
+        }
+    }
+}
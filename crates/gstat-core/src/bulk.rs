@@ -0,0 +1,285 @@
+//! Querying many servers concurrently.
+//!
+//! Dashboards and monitors poll hundreds of servers at once. `query_many` fans the queries
+//! out with a bound on how many run at the same time and yields each result as soon as it
+//! arrives, so one slow or failing server neither delays nor aborts the others.
+
+use crate::{
+    blocklist::{Blocked, Blocklist},
+    cancel::{CancellationToken, Cancelled},
+    prelude::{Game, Protocol, TimeoutSettings},
+    rate_limit::RateLimiter,
+    standards::{dyn_protocol::BoxFuture, game::FetchResult},
+};
+
+use std::{
+    fmt::{Debug, Formatter, Result as FmtResult},
+    future::{poll_fn, Future},
+    iter,
+    net::SocketAddr,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+/// Queries every target with the game's default query, running at most `concurrency`
+/// queries at a time.
+///
+/// # Parameters
+///
+/// * `game`: The game the servers run.
+/// * `targets`: The servers to query, e.g. `SocketAddr`s or `ServerAddress`es.
+/// * `concurrency`: The largest number of queries in flight. `0` is treated as `1`.
+/// * `timeouts`: The time limits applied to each query.
+///
+/// # Returns
+///
+/// A `QueryMany` yielding each target together with the outcome of its query, in the order
+/// the queries complete.
+pub fn query_many<'a, G, P, T, I>(
+    game: &'a G,
+    targets: I,
+    concurrency: usize,
+    timeouts: TimeoutSettings,
+) -> QueryMany<'a, T, FetchResult<P::R, P::E>>
+where
+    G: Game<'a, P> + Sync,
+    P: Protocol<'a> + 'a,
+    T: Clone + Into<SocketAddr> + Send + 'a,
+    I: IntoIterator<Item = T>,
+    I::IntoIter: Send + 'a,
+{
+    QueryMany::new(targets, concurrency, move |target: &T| {
+        game.fetch_with(game.default_query(), target.clone().into(), timeouts)
+    })
+}
+
+/// Starts the query of a target.
+type StartQuery<'a, T, O> = Box<dyn FnMut(&T) -> BoxFuture<'a, O> + Send + 'a>;
+
+/// `QueryMany` runs a query for each of its targets with bounded concurrency.
+///
+/// Targets are taken from the iterator lazily, only once a slot is free, so an iterator
+/// over a very large fleet is never buffered. Results are yielded by `next` in the order
+/// the queries complete; each carries its target, so failures stay attributable and don't
+/// affect the remaining queries.
+///
+/// `QueryMany` runs its queries within the task that polls it, without spawning, so it
+/// works on every runtime and may borrow the game. Queries only make progress while `next`
+/// is being awaited. With the `stream` feature, `QueryMany` implements `Stream`, so the
+/// results can be consumed with any stream combinators. Dropping a `QueryMany`, or cancelling it through
+/// `with_cancellation`, drops the queries in flight.
+pub struct QueryMany<'a, T, O> {
+    targets: Box<dyn Iterator<Item = T> + Send + 'a>,
+    start: StartQuery<'a, T, O>,
+    in_flight: Vec<BoxFuture<'a, (T, O)>>,
+    concurrency: usize,
+    cancelled: Option<Cancelled>,
+}
+
+impl<'a, T, O> QueryMany<'a, T, O>
+where
+    T: Send + 'a,
+    O: 'a,
+{
+    /// Creates a `QueryMany` running `query` for each target.
+    ///
+    /// # Parameters
+    ///
+    /// * `targets`: The targets to query.
+    /// * `concurrency`: The largest number of queries in flight. `0` is treated as `1`.
+    /// * `query`: Starts the query of a target, e.g. a call to `Game::fetch_with` or
+    ///   `DynGame::dyn_fetch`.
+    pub fn new<I, F, Fut>(targets: I, concurrency: usize, mut query: F) -> Self
+    where
+        I: IntoIterator<Item = T>,
+        I::IntoIter: Send + 'a,
+        F: FnMut(&T) -> Fut + Send + 'a,
+        Fut: Future<Output = O> + Send + 'a,
+    {
+        QueryMany {
+            targets: Box::new(targets.into_iter()),
+            start: Box::new(move |target| Box::pin(query(target))),
+            in_flight: Vec::new(),
+            concurrency: concurrency.max(1),
+            cancelled: None,
+        }
+    }
+
+    /// Paces the queries with `limiter`, taking a permit for each target before its query
+    /// starts. A query waiting for its permit occupies a concurrency slot.
+    ///
+    /// # Parameters
+    ///
+    /// * `limiter`: The limiter to take permits from, usually shared with other work
+    ///   querying the same networks.
+    pub fn with_rate_limiter(mut self, limiter: RateLimiter) -> Self
+    where
+        T: Clone + Into<SocketAddr>,
+    {
+        let mut start = self.start;
+
+        self.start = Box::new(move |target| {
+            let destination = target.clone().into();
+            let query = start(target);
+            let limiter = limiter.clone();
+
+            Box::pin(async move {
+                limiter.acquire(destination).await;
+                query.await
+            })
+        });
+
+        self
+    }
+
+    /// Skips the targets `blocklist` refuses instead of querying them.
+    ///
+    /// Blocked targets are yielded like any other, with the rule that matched them, so
+    /// callers can report that they were skipped. Call this after `with_rate_limiter`, so
+    /// blocked targets don't take permits.
+    ///
+    /// # Parameters
+    ///
+    /// * `blocklist`: The addresses and ranges that must not be queried.
+    ///
+    /// # Returns
+    ///
+    /// A `QueryMany` yielding `Ok` with the outcome of each query, or `Err` with the reason
+    /// a target was skipped.
+    pub fn with_blocklist(self, blocklist: Blocklist) -> QueryMany<'a, T, Result<O, Blocked>>
+    where
+        T: Clone + Into<SocketAddr>,
+    {
+        let mut start = self.start;
+        let in_flight = self
+            .in_flight
+            .into_iter()
+            .map(|query| -> BoxFuture<'a, (T, Result<O, Blocked>)> {
+                Box::pin(async move {
+                    let (target, output) = query.await;
+                    (target, Ok(output))
+                })
+            })
+            .collect();
+
+        QueryMany {
+            targets: self.targets,
+            start: Box::new(move |target| match blocklist.check(target.clone().into()) {
+                Ok(()) => {
+                    let query = start(target);
+                    Box::pin(async move { Ok(query.await) })
+                }
+                Err(blocked) => Box::pin(async move { Err(blocked) }),
+            }),
+            in_flight,
+            concurrency: self.concurrency,
+            cancelled: self.cancelled,
+        }
+    }
+
+    /// Stops the queries once `token` is cancelled.
+    ///
+    /// On cancellation, the queries in flight are dropped without results, no further
+    /// target is started, and `next` returns `None`. Results that completed before are
+    /// unaffected.
+    ///
+    /// # Parameters
+    ///
+    /// * `token`: The token that aborts the queries, e.g. one cancelled by a UI.
+    pub fn with_cancellation(mut self, token: &CancellationToken) -> Self {
+        self.cancelled = Some(token.cancelled());
+        self
+    }
+
+    /// Returns the number of queries currently in flight.
+    pub fn in_flight(&self) -> usize {
+        self.in_flight.len()
+    }
+
+    /// Waits for the next query to complete.
+    ///
+    /// # Returns
+    ///
+    /// The target and the outcome of its query, or `None` once every target was queried.
+    pub async fn next(&mut self) -> Option<(T, O)> {
+        poll_fn(|cx| self.poll_next(cx)).await
+    }
+
+    /// Waits for every query to complete.
+    ///
+    /// # Returns
+    ///
+    /// The targets and the outcomes of their queries, in the order the queries completed.
+    pub async fn collect(mut self) -> Vec<(T, O)> {
+        let mut results = Vec::new();
+
+        while let Some(result) = self.next().await {
+            results.push(result);
+        }
+
+        results
+    }
+
+    /// Starts queries until `concurrency` are in flight, then polls them.
+    ///
+    /// # Returns
+    ///
+    /// `Poll::Ready(Some(_))` with the first completed query, `Poll::Ready(None)` once
+    /// every target was queried, or `Poll::Pending` while all queries are waiting.
+    pub fn poll_next(&mut self, cx: &mut Context<'_>) -> Poll<Option<(T, O)>> {
+        if let Some(cancelled) = &mut self.cancelled {
+            if Pin::new(cancelled).poll(cx).is_ready() {
+                self.targets = Box::new(iter::empty());
+                self.in_flight.clear();
+
+                return Poll::Ready(None);
+            }
+        }
+
+        while self.in_flight.len() < self.concurrency {
+            let Some(target) = self.targets.next() else {
+                break;
+            };
+
+            let query = (self.start)(&target);
+            self.in_flight
+                .push(Box::pin(async move { (target, query.await) }));
+        }
+
+        if self.in_flight.is_empty() {
+            return Poll::Ready(None);
+        }
+
+        for position in 0..self.in_flight.len() {
+            if let Poll::Ready(result) = self.in_flight[position].as_mut().poll(cx) {
+                drop(self.in_flight.swap_remove(position));
+                return Poll::Ready(Some(result));
+            }
+        }
+
+        Poll::Pending
+    }
+}
+
+#[cfg(feature = "stream")]
+impl<'a, T, O> futures_core::Stream for QueryMany<'a, T, O>
+where
+    T: Send + 'a,
+    O: 'a,
+{
+    type Item = (T, O);
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        QueryMany::poll_next(self.get_mut(), cx)
+    }
+}
+
+impl<T, O> Debug for QueryMany<'_, T, O> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.debug_struct("QueryMany")
+            .field("in_flight", &self.in_flight.len())
+            .field("concurrency", &self.concurrency)
+            .field("cancellable", &self.cancelled.is_some())
+            .finish_non_exhaustive()
+    }
+}
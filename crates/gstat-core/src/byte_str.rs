@@ -0,0 +1,173 @@
+use std::{
+    borrow::Borrow,
+    fmt::{Debug, Display, Formatter, Result as FmtResult},
+    hash::{Hash, Hasher},
+    ops::Deref,
+    str::{self, Utf8Error},
+};
+
+use bytes::Bytes;
+
+/// `ByteStr` is an immutable UTF-8 string backed by `Bytes`.
+///
+/// Parsers receive each packet as `Bytes`, and a `ByteStr` can be a slice of it, so
+/// response fields such as names and map names share the packet's buffer instead of
+/// being copied into a `String` each. Cloning a `ByteStr` is cheap.
+#[derive(Clone, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct ByteStr {
+    bytes: Bytes,
+}
+
+impl ByteStr {
+    /// Creates a `ByteStr` from a static string without copying.
+    pub const fn from_static(string: &'static str) -> Self {
+        ByteStr {
+            bytes: Bytes::from_static(string.as_bytes()),
+        }
+    }
+
+    /// Creates a `ByteStr` from bytes, checking they are valid UTF-8.
+    ///
+    /// # Parameters
+    ///
+    /// * `bytes`: The bytes of the string, typically a slice of a packet.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing either the string or the `Utf8Error` describing invalid data.
+    pub fn from_utf8(bytes: Bytes) -> Result<Self, Utf8Error> {
+        str::from_utf8(&bytes)?;
+
+        Ok(ByteStr { bytes })
+    }
+
+    /// Creates a `ByteStr` from bytes, replacing invalid UTF-8 sequences with `U+FFFD`.
+    ///
+    /// Valid data is shared without copying; only invalid data is copied.
+    ///
+    /// # Parameters
+    ///
+    /// * `bytes`: The bytes of the string, typically a slice of a packet.
+    pub fn from_utf8_lossy(bytes: Bytes) -> Self {
+        match Self::from_utf8(bytes.clone()) {
+            Ok(string) => string,
+            Err(_) => Self::from(String::from_utf8_lossy(&bytes).into_owned()),
+        }
+    }
+
+    /// Returns the string as a `&str`.
+    pub fn as_str(&self) -> &str {
+        // SAFETY: `bytes` is only ever set from valid UTF-8.
+        unsafe { str::from_utf8_unchecked(&self.bytes) }
+    }
+
+    /// Returns the underlying bytes.
+    pub fn as_bytes(&self) -> &Bytes {
+        &self.bytes
+    }
+
+    /// Converts the string into its underlying bytes.
+    pub fn into_bytes(self) -> Bytes {
+        self.bytes
+    }
+}
+
+impl Deref for ByteStr {
+    type Target = str;
+
+    fn deref(&self) -> &Self::Target {
+        self.as_str()
+    }
+}
+
+impl AsRef<str> for ByteStr {
+    fn as_ref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl Borrow<str> for ByteStr {
+    fn borrow(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl Hash for ByteStr {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.as_str().hash(state)
+    }
+}
+
+impl From<String> for ByteStr {
+    fn from(string: String) -> Self {
+        ByteStr {
+            bytes: Bytes::from(string),
+        }
+    }
+}
+
+impl From<&'static str> for ByteStr {
+    fn from(string: &'static str) -> Self {
+        Self::from_static(string)
+    }
+}
+
+impl From<ByteStr> for String {
+    fn from(string: ByteStr) -> Self {
+        string.as_str().to_string()
+    }
+}
+
+impl PartialEq<str> for ByteStr {
+    fn eq(&self, other: &str) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl PartialEq<&str> for ByteStr {
+    fn eq(&self, other: &&str) -> bool {
+        self.as_str() == *other
+    }
+}
+
+impl Display for ByteStr {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        Display::fmt(self.as_str(), f)
+    }
+}
+
+impl Debug for ByteStr {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        Debug::fmt(self.as_str(), f)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for ByteStr {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for ByteStr {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer).map(ByteStr::from)
+    }
+}
+
+/// Byte strings are described as the strings they serialize to.
+#[cfg(feature = "schemars")]
+impl schemars::JsonSchema for ByteStr {
+    fn inline_schema() -> bool {
+        true
+    }
+
+    fn schema_name() -> std::borrow::Cow<'static, str> {
+        String::schema_name()
+    }
+
+    fn json_schema(generator: &mut schemars::SchemaGenerator) -> schemars::Schema {
+        String::json_schema(generator)
+    }
+}
@@ -0,0 +1,583 @@
+//! Caching responses for a time.
+//!
+//! Web frontends showing popular servers receive many requests for the same server within
+//! seconds. `CachedGame` answers them from one query per time-to-live instead of querying
+//! the server for each of them.
+
+use crate::{
+    clock::{Clock, RuntimeClock},
+    prelude::{Error, Fetched, Game, Protocol, TimeoutSettings},
+    runtime,
+};
+
+use std::{
+    collections::HashMap,
+    fmt::{Debug, Formatter, Result as FmtResult},
+    future::{poll_fn, Future},
+    marker::PhantomData,
+    net::SocketAddr,
+    sync::{Arc, Mutex, MutexGuard},
+    task::{Poll, Waker},
+    time::{Duration, Instant},
+};
+
+/// The number of servers a cache keeps responses for unless configured otherwise.
+const DEFAULT_MAX_ENTRIES: usize = 10_000;
+
+/// How a cached fetch was answered.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum CacheStatus {
+    /// The response was cached and within its time-to-live.
+    Hit,
+    /// The response had outlived its time-to-live, but was still within the
+    /// stale-while-revalidate window. It is refreshed in the background.
+    Stale,
+    /// The server was queried, because nothing usable was cached.
+    Miss,
+}
+
+/// `Cached` is the outcome of a successful `CachedGame::fetch`.
+pub struct Cached<R, E> {
+    /// The response, shared with every caller it was handed to.
+    pub fetched: Arc<Fetched<R, E>>,
+    /// How long ago the response was received.
+    pub age: Duration,
+    /// How the fetch was answered.
+    pub status: CacheStatus,
+}
+
+impl<R, E> Clone for Cached<R, E> {
+    fn clone(&self) -> Self {
+        Cached {
+            fetched: self.fetched.clone(),
+            age: self.age,
+            status: self.status,
+        }
+    }
+}
+
+impl<R, E> Debug for Cached<R, E> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.debug_struct("Cached")
+            .field("age", &self.age)
+            .field("status", &self.status)
+            .finish_non_exhaustive()
+    }
+}
+
+/// The cached response of a server and whether a query for it is in flight.
+struct Entry<R, E> {
+    fetched: Option<(Arc<Fetched<R, E>>, Instant)>,
+    loading: bool,
+    waiters: Vec<Waker>,
+}
+
+impl<R, E> Default for Entry<R, E> {
+    fn default() -> Self {
+        Entry {
+            fetched: None,
+            loading: false,
+            waiters: Vec::new(),
+        }
+    }
+}
+
+/// What `CachedGame::fetch` does after looking a server up.
+enum Lookup<R, E> {
+    /// Serves a cached response, refreshing it in the background if the flag is set.
+    Cached(Cached<R, E>, bool),
+    /// Waits for the query in flight.
+    Wait,
+    /// Queries the server.
+    Query,
+}
+
+struct Inner<G, R, E> {
+    game: G,
+    ttl: Duration,
+    stale_while_revalidate: Duration,
+    timeouts: TimeoutSettings,
+    clock: Arc<dyn Clock>,
+    max_entries: usize,
+    entries: Mutex<HashMap<SocketAddr, Entry<R, E>>>,
+}
+
+impl<G, R, E> Inner<G, R, E> {
+    fn lock(&self) -> MutexGuard<'_, HashMap<SocketAddr, Entry<R, E>>> {
+        self.entries.lock().expect("cache lock poisoned")
+    }
+
+    /// Looks up the response of a server, claiming its query if it has to be sent.
+    fn lookup(&self, address: SocketAddr) -> Lookup<R, E> {
+        let mut entries = self.lock();
+        let entry = entries.entry(address).or_default();
+
+        if let Some((fetched, at)) = &entry.fetched {
+            let age = self.clock.now().saturating_duration_since(*at);
+
+            if age < self.ttl {
+                let cached = Cached {
+                    fetched: fetched.clone(),
+                    age,
+                    status: CacheStatus::Hit,
+                };
+
+                return Lookup::Cached(cached, false);
+            }
+
+            if age < self.ttl + self.stale_while_revalidate {
+                let cached = Cached {
+                    fetched: fetched.clone(),
+                    age,
+                    status: CacheStatus::Stale,
+                };
+                let revalidate = !entry.loading;
+                entry.loading = true;
+
+                return Lookup::Cached(cached, revalidate);
+            }
+        }
+
+        if entry.loading {
+            return Lookup::Wait;
+        }
+
+        entry.loading = true;
+        Lookup::Query
+    }
+
+    /// Stores the outcome of a query and wakes the callers waiting for it.
+    fn finish(&self, address: SocketAddr, fetched: Option<Arc<Fetched<R, E>>>) {
+        let mut entries = self.lock();
+        let entry = entries.entry(address).or_default();
+
+        entry.loading = false;
+
+        if let Some(fetched) = fetched {
+            entry.fetched = Some((fetched, self.clock.now()));
+        }
+
+        let waiters = std::mem::take(&mut entry.waiters);
+        self.shrink(&mut entries);
+        drop(entries);
+
+        for waiter in waiters {
+            waiter.wake();
+        }
+    }
+
+    /// Removes every entry without a response that can still be served, unless its query
+    /// is in flight.
+    fn retain_servable(&self, entries: &mut HashMap<SocketAddr, Entry<R, E>>) {
+        let now = self.clock.now();
+        let keep = self.ttl + self.stale_while_revalidate;
+
+        entries.retain(|_, entry| {
+            let fresh = entry
+                .fetched
+                .as_ref()
+                .is_some_and(|(_, at)| now.saturating_duration_since(*at) < keep);
+
+            fresh || entry.loading
+        });
+    }
+
+    /// Keeps the cache within `max_entries` servers, removing the responses that can no
+    /// longer be served first and the oldest ones after that.
+    fn shrink(&self, entries: &mut HashMap<SocketAddr, Entry<R, E>>) {
+        if entries.len() <= self.max_entries {
+            return;
+        }
+
+        self.retain_servable(entries);
+
+        while entries.len() > self.max_entries {
+            let oldest = entries
+                .iter()
+                .filter(|(_, entry)| !entry.loading)
+                .filter_map(|(address, entry)| {
+                    entry.fetched.as_ref().map(|(_, at)| (*at, *address))
+                })
+                .min();
+
+            let Some((_, address)) = oldest else {
+                break;
+            };
+
+            entries.remove(&address);
+        }
+    }
+}
+
+/// Marks the query of an address as finished without a response if it is dropped early,
+/// e.g. because the caller was cancelled or the runtime of a background refresh shut down,
+/// so waiting callers query the server themselves.
+struct Loading<G, R, E> {
+    inner: Arc<Inner<G, R, E>>,
+    address: SocketAddr,
+    done: bool,
+}
+
+impl<G, R, E> Drop for Loading<G, R, E> {
+    fn drop(&mut self) {
+        if !self.done {
+            self.inner.finish(self.address, None);
+        }
+    }
+}
+
+/// `CachedGame` memoizes the responses of a game per server address.
+///
+/// Responses are served from the cache for their time-to-live. Within the optional
+/// stale-while-revalidate window after that, the stale response is served at once while a
+/// background query refreshes it, so no caller waits for a popular server. Concurrent
+/// callers missing the cache share a single query. Failed queries are not cached: every
+/// caller waiting for one retries on its own.
+///
+/// Background refreshes are spawned on the runtime gstat was built for. Without one, or
+/// with the `silent` feature, stale responses are served until the window ends and then
+/// fetched in the foreground. Clones share their cache.
+///
+/// ```no_run
+/// # use gstat_core::prelude::{Game, Protocol};
+/// # async fn run<G, P, R, E>(game: G)
+/// # where
+/// #     G: for<'a> Game<'a, P> + Send + Sync + 'static,
+/// #     P: for<'a> Protocol<'a, R = R, E = E>,
+/// #     R: Send + Sync + 'static,
+/// #     E: Send + Sync + 'static,
+/// # {
+/// use gstat_core::cache::CachedGame;
+/// use std::time::Duration;
+///
+/// let cached = CachedGame::builder(game, Duration::from_secs(10))
+///     .with_stale_while_revalidate(Duration::from_secs(50))
+///     .build();
+///
+/// let server = "192.0.2.1:27015".parse().unwrap();
+/// let first = cached.fetch(server).await;
+/// let second = cached.fetch(server).await; // answered from the cache
+/// # }
+/// ```
+pub struct CachedGame<G, P>
+where
+    P: for<'a> Protocol<'a>,
+{
+    inner: Arc<Inner<G, <P as Protocol<'static>>::R, <P as Protocol<'static>>::E>>,
+    _marker: PhantomData<fn() -> P>,
+}
+
+impl<G, P, R, E> CachedGame<G, P>
+where
+    G: for<'a> Game<'a, P> + Send + Sync + 'static,
+    P: for<'a> Protocol<'a, R = R, E = E>,
+    R: Send + Sync + 'static,
+    E: Send + Sync + 'static,
+{
+    /// Wraps `game`, caching its responses for `ttl` with the default settings.
+    ///
+    /// # Parameters
+    ///
+    /// * `game`: The game whose responses are cached.
+    /// * `ttl`: How long a response is served from the cache.
+    pub fn new(game: G, ttl: Duration) -> Self {
+        Self::builder(game, ttl).build()
+    }
+
+    /// Returns a builder wrapping `game`, to configure the cache before it is created.
+    ///
+    /// # Parameters
+    ///
+    /// * `game`: The game whose responses are cached.
+    /// * `ttl`: How long a response is served from the cache.
+    pub fn builder(game: G, ttl: Duration) -> CachedGameBuilder<G, P> {
+        CachedGameBuilder {
+            inner: Inner {
+                game,
+                ttl,
+                stale_while_revalidate: Duration::ZERO,
+                timeouts: TimeoutSettings::default(),
+                clock: Arc::new(RuntimeClock),
+                max_entries: DEFAULT_MAX_ENTRIES,
+                entries: Mutex::new(HashMap::new()),
+            },
+            _marker: PhantomData,
+        }
+    }
+
+    /// Returns the wrapped game.
+    pub fn game(&self) -> &G {
+        &self.inner.game
+    }
+
+    /// Returns how long a response is served from the cache.
+    pub fn ttl(&self) -> Duration {
+        self.inner.ttl
+    }
+
+    /// Returns the time limits of the queries sent on a miss.
+    pub fn timeouts(&self) -> TimeoutSettings {
+        self.inner.timeouts
+    }
+
+    /// Fetches the response of a server with the game's default query, from the cache if
+    /// possible.
+    ///
+    /// # Parameters
+    ///
+    /// * `address`: The address of the server.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing either the response and how it was answered, or the `Error`
+    /// of the query sent on a miss. The future is `Send`, so web servers running handlers
+    /// on several threads can await it.
+    pub fn fetch(
+        &self,
+        address: SocketAddr,
+    ) -> impl Future<Output = Result<Cached<R, E>, Error<E>>> + Send + '_ {
+        let inner = &*self.inner;
+
+        async move {
+            loop {
+                match inner.lookup(address) {
+                    Lookup::Cached(cached, revalidate) => {
+                        if revalidate {
+                            self.revalidate(address);
+                        }
+
+                        return Ok(cached);
+                    }
+                    Lookup::Wait => {
+                        self.wait(address).await;
+                        continue;
+                    }
+                    Lookup::Query => {}
+                }
+
+                let mut loading = Loading {
+                    inner: self.inner.clone(),
+                    address,
+                    done: false,
+                };
+
+                let fetched = inner
+                    .game
+                    .fetch_with(inner.game.default_query(), address, inner.timeouts)
+                    .await;
+
+                loading.done = true;
+
+                return match fetched {
+                    Ok(fetched) => {
+                        let fetched = Arc::new(fetched);
+                        inner.finish(address, Some(fetched.clone()));
+
+                        Ok(Cached {
+                            fetched,
+                            age: Duration::ZERO,
+                            status: CacheStatus::Miss,
+                        })
+                    }
+                    Err(err) => {
+                        inner.finish(address, None);
+                        Err(err)
+                    }
+                };
+            }
+        }
+    }
+
+    /// Removes the cached response of a server, so the next fetch queries it.
+    ///
+    /// # Parameters
+    ///
+    /// * `address`: The address of the server.
+    pub fn invalidate(&self, address: SocketAddr) {
+        if let Some(entry) = self.inner.lock().get_mut(&address) {
+            entry.fetched = None;
+        }
+    }
+
+    /// Removes every response that can no longer be served, stale or not.
+    ///
+    /// The cache only removes responses on its own once it holds `max_entries` servers, so
+    /// services fetching many different servers call this periodically to release the
+    /// memory of expired responses earlier.
+    pub fn purge(&self) {
+        self.inner.retain_servable(&mut self.inner.lock());
+    }
+
+    /// Returns the number of servers with a cached response.
+    pub fn len(&self) -> usize {
+        self.inner
+            .lock()
+            .values()
+            .filter(|entry| entry.fetched.is_some())
+            .count()
+    }
+
+    /// Returns `true` if no response is cached.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Waits until the query in flight for `address` finishes.
+    async fn wait(&self, address: SocketAddr) {
+        poll_fn(|cx| {
+            let mut entries = self.inner.lock();
+            let entry = entries.entry(address).or_default();
+
+            if !entry.loading {
+                return Poll::Ready(());
+            }
+
+            entry.waiters.push(cx.waker().clone());
+            Poll::Pending
+        })
+        .await
+    }
+
+    /// Refreshes the response of a server in the background.
+    ///
+    /// The refresh holds the same `Loading` guard as a query in the foreground, so the
+    /// entry is released if it panics, is dropped with its runtime or can't be spawned at
+    /// all because no runtime is available.
+    fn revalidate(&self, address: SocketAddr) {
+        let loading = Loading {
+            inner: self.inner.clone(),
+            address,
+            done: false,
+        };
+
+        runtime::spawn(async move {
+            let mut loading = loading;
+            let inner = loading.inner.clone();
+            let fetched = inner
+                .game
+                .fetch_with(inner.game.default_query(), address, inner.timeouts)
+                .await;
+
+            loading.done = true;
+            inner.finish(address, fetched.ok().map(Arc::new));
+        });
+    }
+}
+
+/// `CachedGameBuilder` configures a `CachedGame` before it is created.
+///
+/// ```no_run
+/// # use gstat_core::prelude::{Game, Protocol};
+/// # fn run<G, P, R, E>(game: G)
+/// # where
+/// #     G: for<'a> Game<'a, P> + Send + Sync + 'static,
+/// #     P: for<'a> Protocol<'a, R = R, E = E>,
+/// #     R: Send + Sync + 'static,
+/// #     E: Send + Sync + 'static,
+/// # {
+/// use gstat_core::cache::CachedGame;
+/// use std::time::Duration;
+///
+/// let cached = CachedGame::builder(game, Duration::from_secs(10))
+///     .with_stale_while_revalidate(Duration::from_secs(50))
+///     .with_max_entries(1000)
+///     .build();
+/// # }
+/// ```
+pub struct CachedGameBuilder<G, P>
+where
+    P: for<'a> Protocol<'a>,
+{
+    inner: Inner<G, <P as Protocol<'static>>::R, <P as Protocol<'static>>::E>,
+    _marker: PhantomData<fn() -> P>,
+}
+
+impl<G, P, R, E> CachedGameBuilder<G, P>
+where
+    G: for<'a> Game<'a, P> + Send + Sync + 'static,
+    P: for<'a> Protocol<'a, R = R, E = E>,
+    R: Send + Sync + 'static,
+    E: Send + Sync + 'static,
+{
+    /// Sets how long after its time-to-live a response is still served while it is
+    /// refreshed in the background.
+    ///
+    /// # Parameters
+    ///
+    /// * `window`: The stale-while-revalidate window. `Duration::ZERO` disables it.
+    pub fn with_stale_while_revalidate(mut self, window: Duration) -> Self {
+        self.inner.stale_while_revalidate = window;
+        self
+    }
+
+    /// Sets the time limits of the queries sent to the servers.
+    pub fn with_timeouts(mut self, timeouts: TimeoutSettings) -> Self {
+        self.inner.timeouts = timeouts;
+        self
+    }
+
+    /// Sets the clock responses are aged on, e.g. a `ManualClock` in tests.
+    pub fn with_clock(mut self, clock: impl Clock + 'static) -> Self {
+        self.inner.clock = Arc::new(clock);
+        self
+    }
+
+    /// Sets the largest number of servers responses are kept for, 10 000 by default.
+    ///
+    /// When a query finishes with more servers cached, the responses that can no longer be
+    /// served are removed, then the oldest ones. Queries in flight are never removed.
+    ///
+    /// # Parameters
+    ///
+    /// * `max_entries`: The largest number of servers. `0` is treated as `1`.
+    pub fn with_max_entries(mut self, max_entries: usize) -> Self {
+        self.inner.max_entries = max_entries.max(1);
+        self
+    }
+
+    /// Creates the cache.
+    pub fn build(self) -> CachedGame<G, P> {
+        CachedGame {
+            inner: Arc::new(self.inner),
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<G, P> Debug for CachedGameBuilder<G, P>
+where
+    P: for<'a> Protocol<'a>,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.debug_struct("CachedGameBuilder")
+            .field("ttl", &self.inner.ttl)
+            .field("stale_while_revalidate", &self.inner.stale_while_revalidate)
+            .field("max_entries", &self.inner.max_entries)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<G, P> Clone for CachedGame<G, P>
+where
+    P: for<'a> Protocol<'a>,
+{
+    fn clone(&self) -> Self {
+        CachedGame {
+            inner: self.inner.clone(),
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<G, P> Debug for CachedGame<G, P>
+where
+    P: for<'a> Protocol<'a>,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.debug_struct("CachedGame")
+            .field("ttl", &self.inner.ttl)
+            .field("stale_while_revalidate", &self.inner.stale_while_revalidate)
+            .field("max_entries", &self.inner.max_entries)
+            .finish_non_exhaustive()
+    }
+}
@@ -0,0 +1,228 @@
+//! Cooperative cancellation of queries.
+//!
+//! A server browser aborting a scan shouldn't wait for every in-flight query to time out.
+//! A `CancellationToken` is handed to the work that should stop together, such as
+//! `Game::fetch_cancellable` and `QueryMany::with_cancellation`; cancelling it stops that
+//! work at once.
+//!
+//! Stopping a query drops its future. Queries are safe to drop at any point: the
+//! `ConnectionGuard` of an abandoned exchange schedules a disconnect, so sockets are
+//! released without waiting for their timeouts.
+
+use crate::prelude::{Error, ErrorDetail, ErrorKind};
+
+use std::{
+    collections::BTreeMap,
+    fmt::{Debug, Formatter, Result as FmtResult},
+    future::Future,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex, MutexGuard, Weak,
+    },
+    task::{Context, Poll, Waker},
+};
+
+#[derive(Default)]
+struct Waiters {
+    /// The wakers of the pending `Cancelled` futures, by future id.
+    wakers: BTreeMap<u64, Waker>,
+    next_id: u64,
+    /// The tokens created by `child`.
+    children: Vec<Weak<Inner>>,
+}
+
+#[derive(Default)]
+struct Inner {
+    cancelled: AtomicBool,
+    waiters: Mutex<Waiters>,
+}
+
+impl Inner {
+    fn lock(&self) -> MutexGuard<'_, Waiters> {
+        self.waiters
+            .lock()
+            .expect("cancellation token lock poisoned")
+    }
+
+    /// Cancels the token and its children, waking everything waiting for them.
+    fn cancel(&self) {
+        if self.cancelled.swap(true, Ordering::AcqRel) {
+            return;
+        }
+
+        let mut waiters = self.lock();
+        let wakers = std::mem::take(&mut waiters.wakers);
+        let children = std::mem::take(&mut waiters.children);
+        drop(waiters);
+
+        for waker in wakers.into_values() {
+            waker.wake();
+        }
+
+        for child in children.iter().filter_map(Weak::upgrade) {
+            child.cancel();
+        }
+    }
+}
+
+/// `CancellationToken` signals that work should stop.
+///
+/// Clones share their state, so one clone can be kept by a UI to cancel while the others
+/// are handed to the queries. Tokens created with `child` are cancelled together with
+/// their parent, but can also be cancelled on their own, e.g. to abort a single target of
+/// a scan. Cancelling is permanent.
+#[derive(Clone, Default)]
+pub struct CancellationToken {
+    inner: Arc<Inner>,
+}
+
+impl CancellationToken {
+    /// Creates a token that is not cancelled.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a token that is cancelled when this token is, or when it is cancelled
+    /// itself.
+    pub fn child(&self) -> Self {
+        let child = CancellationToken::new();
+        let mut waiters = self.inner.lock();
+
+        // Checked under the lock, so a concurrent `cancel` either sees the child or has
+        // already set the flag.
+        if self.is_cancelled() {
+            drop(waiters);
+            child.cancel();
+        } else {
+            waiters.children.retain(|child| child.strong_count() > 0);
+            waiters.children.push(Arc::downgrade(&child.inner));
+        }
+
+        child
+    }
+
+    /// Cancels the token and every child token.
+    pub fn cancel(&self) {
+        self.inner.cancel();
+    }
+
+    /// Returns `true` once the token was cancelled.
+    pub fn is_cancelled(&self) -> bool {
+        self.inner.cancelled.load(Ordering::Acquire)
+    }
+
+    /// Returns a future that completes once the token is cancelled.
+    pub fn cancelled(&self) -> Cancelled {
+        Cancelled {
+            token: self.clone(),
+            id: None,
+        }
+    }
+
+    /// Runs `future` until it completes or the token is cancelled, whichever is first.
+    ///
+    /// # Returns
+    ///
+    /// The output of the future, or `None` if the token was cancelled first, in which case
+    /// the future is dropped.
+    pub async fn run_until_cancelled<F: Future>(&self, future: F) -> Option<F::Output> {
+        let mut future = std::pin::pin!(future);
+        let mut cancelled = self.cancelled();
+
+        std::future::poll_fn(|cx| {
+            if Pin::new(&mut cancelled).poll(cx).is_ready() {
+                return Poll::Ready(None);
+            }
+
+            future.as_mut().poll(cx).map(Some)
+        })
+        .await
+    }
+}
+
+impl Debug for CancellationToken {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.debug_struct("CancellationToken")
+            .field("cancelled", &self.is_cancelled())
+            .finish()
+    }
+}
+
+/// The future returned by `CancellationToken::cancelled`.
+///
+/// Dropping it stops waiting, so it can be raced against other futures freely.
+pub struct Cancelled {
+    token: CancellationToken,
+    id: Option<u64>,
+}
+
+impl Future for Cancelled {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        if self.token.is_cancelled() {
+            return Poll::Ready(());
+        }
+
+        let inner = self.token.inner.clone();
+        let mut waiters = inner.lock();
+
+        // Checked again under the lock, since `cancel` takes the wakers under it.
+        if self.token.is_cancelled() {
+            return Poll::Ready(());
+        }
+
+        let id = *self.id.get_or_insert_with(|| {
+            waiters.next_id += 1;
+            waiters.next_id
+        });
+
+        waiters.wakers.insert(id, cx.waker().clone());
+        Poll::Pending
+    }
+}
+
+impl Drop for Cancelled {
+    fn drop(&mut self) {
+        if let Some(id) = self.id {
+            self.token.inner.lock().wakers.remove(&id);
+        }
+    }
+}
+
+impl Debug for Cancelled {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.debug_struct("Cancelled")
+            .field("cancelled", &self.token.is_cancelled())
+            .finish()
+    }
+}
+
+/// Runs `future` until it completes, failing with a `QueryError` of kind
+/// `ErrorKind::Cancelled` if `token` is cancelled first.
+///
+/// # Parameters
+///
+/// * `token`: The token to watch.
+/// * `message`: The error message used if the token is cancelled.
+/// * `future`: The operation to run. It is dropped when the token is cancelled.
+///
+/// # Returns
+///
+/// A `Result` containing either the output of the operation or an `Error`.
+pub async fn with_cancellation<T, E, F>(
+    token: &CancellationToken,
+    message: &str,
+    future: F,
+) -> Result<T, Error<E>>
+where
+    F: Future<Output = Result<T, Error<E>>>,
+{
+    match token.run_until_cancelled(future).await {
+        Some(output) => output,
+        None => Err(Error::QueryError(
+            ErrorDetail::new(message, None).with_kind(ErrorKind::Cancelled),
+        )),
+    }
+}
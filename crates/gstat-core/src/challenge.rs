@@ -0,0 +1,223 @@
+use crate::clock::{Clock, RuntimeClock};
+
+use std::{
+    collections::HashMap,
+    fmt::{Debug, Formatter, Result as FmtResult},
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+/// The default time a remembered requirement is trusted for.
+const DEFAULT_TTL: Duration = Duration::from_secs(30 * 60);
+
+/// The default number of hosts remembered at once.
+const DEFAULT_MAX_HOSTS: usize = 10_000;
+
+/// Whether a host requires a challenge before answering a query.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Requirement {
+    /// Nothing is known about the host, or what was known has expired.
+    #[default]
+    Unknown,
+    /// The host answered a query without a challenge.
+    NotRequired,
+    /// The host answered a query with a challenge.
+    Required,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Entry {
+    requirement: Requirement,
+    /// When the requirement was recorded, or `None` on a host without a clock, where
+    /// entries don't expire.
+    recorded: Option<Instant>,
+}
+
+/// `ChallengeMemory` remembers per host whether queries must be preceded by a challenge.
+///
+/// Challenge-based protocols such as A2S cost an extra round trip per query, but some
+/// servers answer without one. Protocols record what each host did with
+/// `record_not_required` and `record_required`, and consult `skip_challenge` before
+/// querying, so later queries to permissive hosts go out directly. When a remembered host
+/// starts answering with a challenge again, recording it flips the host back, and every
+/// entry expires after a time-to-live so hosts are re-probed eventually.
+///
+/// At most `max_hosts` hosts are remembered: recording a host beyond that removes the
+/// expired entries first and the oldest ones after that, so a scanner touching millions of
+/// hosts doesn't grow the memory without bound.
+///
+/// The memory is cheap to clone, and clones share their entries, so one memory can serve
+/// every protocol instance of a scanner.
+#[derive(Clone)]
+pub struct ChallengeMemory {
+    ttl: Duration,
+    max_hosts: usize,
+    clock: Arc<dyn Clock>,
+    hosts: Arc<Mutex<HashMap<SocketAddr, Entry>>>,
+}
+
+impl ChallengeMemory {
+    /// Creates an empty memory whose entries are trusted for 30 minutes, remembering at
+    /// most 10 000 hosts.
+    pub fn new() -> Self {
+        ChallengeMemory {
+            ttl: DEFAULT_TTL,
+            max_hosts: DEFAULT_MAX_HOSTS,
+            clock: Arc::new(RuntimeClock),
+            hosts: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Sets how long a remembered requirement is trusted for.
+    ///
+    /// # Parameters
+    ///
+    /// * `ttl`: The time-to-live of every entry.
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    /// Sets the largest number of hosts remembered at once.
+    ///
+    /// # Parameters
+    ///
+    /// * `max_hosts`: The largest number of hosts. `0` is treated as `1`.
+    pub fn with_max_hosts(mut self, max_hosts: usize) -> Self {
+        self.max_hosts = max_hosts.max(1);
+        self
+    }
+
+    /// Sets the clock entries are aged on, e.g. a `ManualClock` in tests.
+    pub fn with_clock(mut self, clock: impl Clock + 'static) -> Self {
+        self.clock = Arc::new(clock);
+        self
+    }
+
+    /// Returns what is known about `host`.
+    pub fn requirement(&self, host: SocketAddr) -> Requirement {
+        let now = self.clock.try_now();
+        let mut hosts = self.hosts.lock().unwrap();
+
+        match hosts.get(&host) {
+            Some(entry) if !self.expired(entry, now) => entry.requirement,
+            Some(_) => {
+                hosts.remove(&host);
+                Requirement::Unknown
+            }
+            None => Requirement::Unknown,
+        }
+    }
+
+    /// Returns `true` if a query to `host` may be sent without a challenge.
+    ///
+    /// Unknown hosts are probed without a challenge too, since a permissive host saves the
+    /// round trip and a strict one answers with the challenge anyway.
+    pub fn skip_challenge(&self, host: SocketAddr) -> bool {
+        self.requirement(host) != Requirement::Required
+    }
+
+    /// Records that `host` answered a query without a challenge.
+    pub fn record_not_required(&self, host: SocketAddr) {
+        self.record(host, Requirement::NotRequired);
+    }
+
+    /// Records that `host` answered a query with a challenge.
+    pub fn record_required(&self, host: SocketAddr) {
+        self.record(host, Requirement::Required);
+    }
+
+    /// Forgets everything known about `host`.
+    pub fn forget(&self, host: SocketAddr) {
+        self.hosts.lock().unwrap().remove(&host);
+    }
+
+    /// Removes every expired entry.
+    ///
+    /// Expired entries are only removed on their own once `max_hosts` hosts are
+    /// remembered, so services querying many different hosts call this periodically to
+    /// release their memory earlier.
+    pub fn purge(&self) {
+        let now = self.clock.try_now();
+
+        self.hosts
+            .lock()
+            .unwrap()
+            .retain(|_, entry| !self.expired(entry, now));
+    }
+
+    /// Returns the number of hosts remembered, including expired entries not yet removed.
+    pub fn len(&self) -> usize {
+        self.hosts.lock().unwrap().len()
+    }
+
+    /// Returns `true` if no host is remembered.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn record(&self, host: SocketAddr, requirement: Requirement) {
+        let now = self.clock.try_now();
+        let mut hosts = self.hosts.lock().unwrap();
+
+        hosts.insert(
+            host,
+            Entry {
+                requirement,
+                recorded: now,
+            },
+        );
+
+        self.shrink(&mut hosts, now);
+    }
+
+    /// Returns `true` if `entry` is older than the time-to-live at `now`.
+    ///
+    /// Without a clock, nothing expires.
+    fn expired(&self, entry: &Entry, now: Option<Instant>) -> bool {
+        match (entry.recorded, now) {
+            (Some(recorded), Some(now)) => now.saturating_duration_since(recorded) >= self.ttl,
+            _ => false,
+        }
+    }
+
+    /// Keeps the memory within `max_hosts` hosts, removing the expired entries first and
+    /// the oldest ones after that.
+    fn shrink(&self, hosts: &mut HashMap<SocketAddr, Entry>, now: Option<Instant>) {
+        if hosts.len() <= self.max_hosts {
+            return;
+        }
+
+        hosts.retain(|_, entry| !self.expired(entry, now));
+
+        while hosts.len() > self.max_hosts {
+            let oldest = hosts
+                .iter()
+                .map(|(host, entry)| (entry.recorded, *host))
+                .min();
+
+            let Some((_, host)) = oldest else {
+                break;
+            };
+
+            hosts.remove(&host);
+        }
+    }
+}
+
+impl Debug for ChallengeMemory {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.debug_struct("ChallengeMemory")
+            .field("ttl", &self.ttl)
+            .field("max_hosts", &self.max_hosts)
+            .field("hosts", &self.len())
+            .finish_non_exhaustive()
+    }
+}
+
+impl Default for ChallengeMemory {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,238 @@
+//! Sources of time for code that waits.
+//!
+//! Monitors, retries and rate limiters read the time and sleep. Doing both through a
+//! `Clock` lets tests replace real time with a `ManualClock`, which only moves when it is
+//! advanced, so scheduling, backoff and timeouts can be checked deterministically without
+//! real sleeps.
+
+use crate::runtime;
+
+use std::{
+    collections::BTreeMap,
+    fmt::{Debug, Formatter, Result as FmtResult},
+    future::Future,
+    pin::Pin,
+    sync::{Arc, Mutex, MutexGuard},
+    task::{Context, Poll, Waker},
+    time::{Duration, Instant},
+};
+
+/// The future returned by `Clock::sleep`.
+pub type Sleep = Pin<Box<dyn Future<Output = ()> + Send>>;
+
+/// `Clock` tells the time and waits for it to pass.
+pub trait Clock: Send + Sync {
+    /// Returns the current instant.
+    fn now(&self) -> Instant;
+
+    /// Returns the current instant, or `None` on a host without a clock.
+    ///
+    /// Only `RuntimeClock` on `wasm32-unknown-unknown` has no clock; other clocks return
+    /// `now`.
+    fn try_now(&self) -> Option<Instant> {
+        Some(self.now())
+    }
+
+    /// Returns a future that completes once `duration` has passed on this clock.
+    fn sleep(&self, duration: Duration) -> Sleep;
+}
+
+/// `RuntimeClock` is the clock of the async runtime gstat was built for.
+///
+/// On Tokio, it follows Tokio's clock, so code using it can also run under
+/// `tokio::time::pause`, where time advances automatically whenever the runtime is idle.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct RuntimeClock;
+
+impl Clock for RuntimeClock {
+    fn now(&self) -> Instant {
+        runtime::now()
+    }
+
+    fn try_now(&self) -> Option<Instant> {
+        runtime::try_now()
+    }
+
+    fn sleep(&self, duration: Duration) -> Sleep {
+        Box::pin(runtime::sleep(duration))
+    }
+}
+
+impl<C: Clock + ?Sized> Clock for Arc<C> {
+    fn now(&self) -> Instant {
+        (**self).now()
+    }
+
+    fn try_now(&self) -> Option<Instant> {
+        (**self).try_now()
+    }
+
+    fn sleep(&self, duration: Duration) -> Sleep {
+        (**self).sleep(duration)
+    }
+}
+
+#[derive(Default)]
+struct Timeline {
+    /// How far the clock was advanced since it was created.
+    elapsed: Duration,
+    /// The deadlines and wakers of the pending sleeps, by sleep id.
+    sleepers: BTreeMap<u64, (Duration, Option<Waker>)>,
+    next_id: u64,
+}
+
+/// `ManualClock` is a clock that only moves when it is advanced.
+///
+/// Sleeps on a manual clock complete once `advance` moves the clock past their deadline;
+/// nothing ever waits for real time. Clones share their time, so a test keeps one clone
+/// and hands the others to the code under test.
+#[derive(Clone)]
+pub struct ManualClock {
+    start: Instant,
+    timeline: Arc<Mutex<Timeline>>,
+}
+
+impl ManualClock {
+    /// Creates a clock standing at the current instant.
+    pub fn new() -> Self {
+        ManualClock {
+            start: Instant::now(),
+            timeline: Arc::default(),
+        }
+    }
+
+    /// Moves the clock forward, waking every sleep whose deadline has passed.
+    ///
+    /// # Parameters
+    ///
+    /// * `duration`: How far to move the clock.
+    pub fn advance(&self, duration: Duration) {
+        let mut timeline = self.lock();
+        timeline.elapsed += duration;
+
+        let elapsed = timeline.elapsed;
+        let wakers: Vec<Waker> = timeline
+            .sleepers
+            .values_mut()
+            .filter(|(deadline, _)| *deadline <= elapsed)
+            .filter_map(|(_, waker)| waker.take())
+            .collect();
+
+        drop(timeline);
+
+        for waker in wakers {
+            waker.wake();
+        }
+    }
+
+    /// Moves the clock forward to the earliest pending deadline, waking the sleeps that
+    /// were waiting for it.
+    ///
+    /// # Returns
+    ///
+    /// How far the clock moved, or `None` if no sleep is pending.
+    pub fn advance_to_next(&self) -> Option<Duration> {
+        let step = self.until_next()?;
+        self.advance(step);
+
+        Some(step)
+    }
+
+    /// Returns how long until the earliest pending deadline, or `None` if no sleep is
+    /// pending.
+    pub fn until_next(&self) -> Option<Duration> {
+        let timeline = self.lock();
+
+        timeline
+            .sleepers
+            .values()
+            .map(|(deadline, _)| deadline.saturating_sub(timeline.elapsed))
+            .min()
+    }
+
+    /// Returns how far the clock was advanced since it was created.
+    pub fn elapsed(&self) -> Duration {
+        self.lock().elapsed
+    }
+
+    /// Returns the number of sleeps that are waiting for the clock.
+    pub fn sleepers(&self) -> usize {
+        self.lock().sleepers.len()
+    }
+
+    /// Locks the timeline.
+    fn lock(&self) -> MutexGuard<'_, Timeline> {
+        self.timeline.lock().expect("manual clock lock poisoned")
+    }
+}
+
+impl Default for ManualClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Debug for ManualClock {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        let timeline = self.lock();
+
+        f.debug_struct("ManualClock")
+            .field("elapsed", &timeline.elapsed)
+            .field("sleepers", &timeline.sleepers.len())
+            .finish()
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> Instant {
+        self.start + self.elapsed()
+    }
+
+    fn sleep(&self, duration: Duration) -> Sleep {
+        let mut timeline = self.lock();
+        let deadline = timeline.elapsed.saturating_add(duration);
+        let id = timeline.next_id;
+
+        timeline.next_id += 1;
+        timeline.sleepers.insert(id, (deadline, None));
+
+        Box::pin(ManualSleep {
+            clock: self.clone(),
+            id,
+        })
+    }
+}
+
+/// A sleep on a `ManualClock`.
+struct ManualSleep {
+    clock: ManualClock,
+    id: u64,
+}
+
+impl Future for ManualSleep {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        let mut timeline = self.clock.lock();
+        let elapsed = timeline.elapsed;
+
+        let Some((deadline, waker)) = timeline.sleepers.get_mut(&self.id) else {
+            return Poll::Ready(());
+        };
+
+        if *deadline <= elapsed {
+            timeline.sleepers.remove(&self.id);
+            return Poll::Ready(());
+        }
+
+        *waker = Some(cx.waker().clone());
+        Poll::Pending
+    }
+}
+
+impl Drop for ManualSleep {
+    /// Stops waiting for the clock, so a cancelled sleep isn't counted as pending.
+    fn drop(&mut self) {
+        self.clock.lock().sleepers.remove(&self.id);
+    }
+}
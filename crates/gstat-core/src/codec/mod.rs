@@ -0,0 +1,65 @@
+//! Encoding and decoding of the primitives game protocols are built from.
+//!
+//! `ByteReader` and `ByteWriter` cover the integers, floats and strings found across
+//! protocols, including the LEB128 varints of Minecraft (VarInt and VarLong), UTF-16LE
+//! strings of Unreal Engine and GameSpy games, and length-prefixed Pascal strings, so
+//! parsers don't reimplement them.
+
+pub mod reader;
+pub mod wire;
+pub mod writer;
+
+/// The longest encoding of a 64-bit varint, in bytes.
+pub const MAX_VARINT_LEN: usize = 10;
+
+/// Returns the number of bytes the LEB128 varint encoding of `value` takes, e.g. to size a
+/// Minecraft packet before writing it.
+pub const fn varint_len(value: u64) -> usize {
+    let bits = u64::BITS - value.leading_zeros();
+
+    if bits == 0 {
+        1
+    } else {
+        bits.div_ceil(7) as usize
+    }
+}
+
+/// The byte order of a multi-byte value.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Endian {
+    /// Least significant byte first.
+    Little,
+    /// Most significant byte first.
+    Big,
+}
+
+/// The width of a length field or length prefix.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LengthWidth {
+    /// A single byte.
+    U8,
+    /// Two bytes.
+    U16,
+    /// Four bytes.
+    U32,
+}
+
+impl LengthWidth {
+    /// Returns the number of bytes the length field occupies.
+    pub fn bytes(self) -> usize {
+        match self {
+            Self::U8 => 1,
+            Self::U16 => 2,
+            Self::U32 => 4,
+        }
+    }
+
+    /// Returns the largest length the field can represent.
+    pub fn max(self) -> usize {
+        match self {
+            Self::U8 => u8::MAX as usize,
+            Self::U16 => u16::MAX as usize,
+            Self::U32 => u32::MAX as usize,
+        }
+    }
+}
@@ -0,0 +1,477 @@
+use crate::{
+    byte_str::ByteStr,
+    codec::{Endian, LengthWidth, MAX_VARINT_LEN},
+    prelude::{Error, ErrorDetail, ErrorKind},
+};
+
+use std::{
+    error::Error as StdError,
+    fmt::{Display, Formatter, Result as FmtResult},
+};
+
+use bytes::Bytes;
+
+/// Describes why a `ByteReader` could not decode a value.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DecodeError {
+    /// The packet ended before the value did.
+    Truncated {
+        /// The position the value started at.
+        offset: usize,
+        /// The number of bytes the value needed.
+        needed: usize,
+        /// The number of bytes that were left.
+        remaining: usize,
+    },
+    /// A string was not terminated before the end of the packet.
+    Unterminated {
+        /// The position the string started at.
+        offset: usize,
+    },
+    /// A string was not valid UTF-8.
+    InvalidUtf8 {
+        /// The position the string started at.
+        offset: usize,
+    },
+    /// A string was not valid UTF-16.
+    InvalidUtf16 {
+        /// The position the string started at.
+        offset: usize,
+    },
+    /// A varint was longer than its type allows.
+    VarintOverflow {
+        /// The position the varint started at.
+        offset: usize,
+    },
+    /// A fixed sequence, such as a packet header, had different bytes.
+    UnexpectedBytes {
+        /// The position the sequence started at.
+        offset: usize,
+    },
+}
+
+impl Display for DecodeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            Self::Truncated {
+                offset,
+                needed,
+                remaining,
+            } => write!(
+                f,
+                "needed {needed} bytes at offset {offset}, but only {remaining} remain"
+            ),
+            Self::Unterminated { offset } => {
+                write!(f, "string at offset {offset} is not terminated")
+            }
+            Self::InvalidUtf8 { offset } => write!(f, "string at offset {offset} is not UTF-8"),
+            Self::InvalidUtf16 { offset } => {
+                write!(f, "string at offset {offset} is not UTF-16")
+            }
+            Self::VarintOverflow { offset } => write!(f, "varint at offset {offset} overflows"),
+            Self::UnexpectedBytes { offset } => {
+                write!(f, "unexpected bytes at offset {offset}")
+            }
+        }
+    }
+}
+
+impl StdError for DecodeError {}
+
+/// The result of a `ByteReader` operation.
+pub type DecodeResult<T> = Result<T, Error<DecodeError>>;
+
+/// A cursor for decoding binary packets with bounds checking.
+///
+/// Every `read_*` method checks that enough bytes remain before consuming anything, and
+/// returns an `Error::ParserError` describing the shortfall otherwise, so parsers never
+/// index past the end of a packet. A failed read leaves the position unchanged.
+///
+/// The reader works on `Bytes`, so `read_bytes` and the string methods return slices of
+/// the packet rather than copies.
+#[derive(Clone, Debug)]
+pub struct ByteReader {
+    data: Bytes,
+    position: usize,
+}
+
+macro_rules! read_numbers {
+    ($($(#[$doc:meta])* $name:ident => $ty:ty, $from:ident;)*) => {
+        $(
+            $(#[$doc])*
+            pub fn $name(&mut self) -> DecodeResult<$ty> {
+                let bytes = self.take_array::<{ size_of::<$ty>() }>()?;
+
+                Ok(<$ty>::$from(bytes))
+            }
+        )*
+    };
+}
+
+impl ByteReader {
+    /// Creates a reader positioned at the start of `data`.
+    ///
+    /// # Parameters
+    ///
+    /// * `data`: The packet to decode.
+    pub fn new(data: impl Into<Bytes>) -> Self {
+        ByteReader {
+            data: data.into(),
+            position: 0,
+        }
+    }
+
+    /// Returns the number of bytes consumed so far.
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    /// Returns the number of bytes left to read.
+    pub fn remaining(&self) -> usize {
+        self.data.len() - self.position
+    }
+
+    /// Returns `true` if every byte has been read.
+    pub fn is_empty(&self) -> bool {
+        self.remaining() == 0
+    }
+
+    /// Returns the next byte without consuming it.
+    pub fn peek_u8(&self) -> DecodeResult<u8> {
+        self.ensure(1)?;
+
+        Ok(self.data[self.position])
+    }
+
+    /// Skips `len` bytes.
+    pub fn skip(&mut self, len: usize) -> DecodeResult<()> {
+        self.ensure(len)?;
+        self.position += len;
+
+        Ok(())
+    }
+
+    /// Reads `len` bytes as a slice of the packet.
+    pub fn read_bytes(&mut self, len: usize) -> DecodeResult<Bytes> {
+        self.ensure(len)?;
+
+        let bytes = self.data.slice(self.position..self.position + len);
+        self.position += len;
+
+        Ok(bytes)
+    }
+
+    /// Consumes `expected` if the packet continues with it, e.g. to check a header.
+    ///
+    /// # Parameters
+    ///
+    /// * `expected`: The bytes the packet must continue with.
+    pub fn expect_bytes(&mut self, expected: &[u8]) -> DecodeResult<()> {
+        self.ensure(expected.len())?;
+
+        let offset = self.position;
+
+        if &self.data[offset..offset + expected.len()] != expected {
+            return Err(invalid(DecodeError::UnexpectedBytes { offset }));
+        }
+
+        self.position += expected.len();
+
+        Ok(())
+    }
+
+    /// Reads every remaining byte as a slice of the packet.
+    pub fn read_rest(&mut self) -> Bytes {
+        let bytes = self.data.slice(self.position..);
+        self.position = self.data.len();
+
+        bytes
+    }
+
+    read_numbers! {
+        /// Reads an unsigned byte.
+        read_u8 => u8, from_le_bytes;
+        /// Reads a signed byte.
+        read_i8 => i8, from_le_bytes;
+        /// Reads a little-endian `u16`.
+        read_u16_le => u16, from_le_bytes;
+        /// Reads a big-endian `u16`.
+        read_u16_be => u16, from_be_bytes;
+        /// Reads a little-endian `i16`.
+        read_i16_le => i16, from_le_bytes;
+        /// Reads a big-endian `i16`.
+        read_i16_be => i16, from_be_bytes;
+        /// Reads a little-endian `u32`.
+        read_u32_le => u32, from_le_bytes;
+        /// Reads a big-endian `u32`.
+        read_u32_be => u32, from_be_bytes;
+        /// Reads a little-endian `i32`.
+        read_i32_le => i32, from_le_bytes;
+        /// Reads a big-endian `i32`.
+        read_i32_be => i32, from_be_bytes;
+        /// Reads a little-endian `u64`.
+        read_u64_le => u64, from_le_bytes;
+        /// Reads a big-endian `u64`.
+        read_u64_be => u64, from_be_bytes;
+        /// Reads a little-endian `i64`.
+        read_i64_le => i64, from_le_bytes;
+        /// Reads a big-endian `i64`.
+        read_i64_be => i64, from_be_bytes;
+        /// Reads a little-endian `f32`.
+        read_f32_le => f32, from_le_bytes;
+        /// Reads a little-endian `f64`.
+        read_f64_le => f64, from_le_bytes;
+    }
+
+    /// Reads a null-terminated UTF-8 string, consuming the terminator.
+    pub fn read_cstring(&mut self) -> DecodeResult<ByteStr> {
+        let offset = self.position;
+        let bytes = self.take_until_nul()?;
+
+        ByteStr::from_utf8(bytes).map_err(|_| {
+            self.position = offset;
+            invalid(DecodeError::InvalidUtf8 { offset })
+        })
+    }
+
+    /// Reads a null-terminated string, consuming the terminator and replacing invalid
+    /// UTF-8 sequences with `U+FFFD`.
+    ///
+    /// Many servers send names in arbitrary encodings; this reads them without failing.
+    pub fn read_cstring_lossy(&mut self) -> DecodeResult<ByteStr> {
+        self.take_until_nul().map(ByteStr::from_utf8_lossy)
+    }
+
+    /// Reads a UTF-8 string preceded by a one-byte length.
+    pub fn read_pascal_string(&mut self) -> DecodeResult<ByteStr> {
+        let offset = self.position;
+        let len = self.read_u8()? as usize;
+
+        self.read_utf8(len, offset)
+    }
+
+    /// Reads a UTF-8 string preceded by a length of any width, e.g. the two-byte lengths of
+    /// Quake 3 engine games.
+    ///
+    /// # Parameters
+    ///
+    /// * `width`: The width of the length prefix.
+    /// * `endian`: The byte order of the length prefix.
+    pub fn read_prefixed_string(
+        &mut self,
+        width: LengthWidth,
+        endian: Endian,
+    ) -> DecodeResult<ByteStr> {
+        let offset = self.position;
+        let len = match (width, endian) {
+            (LengthWidth::U8, _) => self.read_u8()? as usize,
+            (LengthWidth::U16, Endian::Little) => self.read_u16_le()? as usize,
+            (LengthWidth::U16, Endian::Big) => self.read_u16_be()? as usize,
+            (LengthWidth::U32, Endian::Little) => self.read_u32_le()? as usize,
+            (LengthWidth::U32, Endian::Big) => self.read_u32_be()? as usize,
+        };
+
+        self.read_utf8(len, offset)
+    }
+
+    /// Reads a string of `units` UTF-16LE code units, as used by Unreal Engine and some
+    /// GameSpy games.
+    ///
+    /// UCS-2 strings are read as well, since UCS-2 is the subset of UTF-16 without
+    /// surrogate pairs.
+    ///
+    /// # Parameters
+    ///
+    /// * `units`: The length of the string in code units, i.e. half its length in bytes.
+    pub fn read_utf16le_string(&mut self, units: usize) -> DecodeResult<ByteStr> {
+        let offset = self.position;
+        let Some(len) = units.checked_mul(2) else {
+            return Err(truncated(offset, usize::MAX, self.remaining()));
+        };
+
+        let bytes = self.read_bytes(len)?;
+
+        decode_utf16le(&bytes).map_err(|_| {
+            self.position = offset;
+            invalid(DecodeError::InvalidUtf16 { offset })
+        })
+    }
+
+    /// Reads a UTF-16LE string terminated by a null code unit, consuming the terminator.
+    pub fn read_utf16le_cstring(&mut self) -> DecodeResult<ByteStr> {
+        let offset = self.position;
+        let bytes = self.take_until_nul_u16()?;
+
+        decode_utf16le(&bytes).map_err(|_| {
+            self.position = offset;
+            invalid(DecodeError::InvalidUtf16 { offset })
+        })
+    }
+
+    /// Reads a UTF-16LE string terminated by a null code unit, consuming the terminator and
+    /// replacing unpaired surrogates with `U+FFFD`.
+    pub fn read_utf16le_cstring_lossy(&mut self) -> DecodeResult<ByteStr> {
+        let bytes = self.take_until_nul_u16()?;
+        let units = bytes
+            .chunks_exact(2)
+            .map(|unit| u16::from_le_bytes([unit[0], unit[1]]));
+
+        Ok(char::decode_utf16(units)
+            .map(|unit| unit.unwrap_or(char::REPLACEMENT_CHARACTER))
+            .collect::<String>()
+            .into())
+    }
+
+    /// Reads a UTF-8 string preceded by a varint length, as used by Minecraft.
+    pub fn read_varint_string(&mut self) -> DecodeResult<ByteStr> {
+        let offset = self.position;
+        let len = self.read_varint_u32()? as usize;
+
+        self.read_utf8(len, offset)
+    }
+
+    /// Reads an unsigned LEB128 varint of at most 32 bits.
+    pub fn read_varint_u32(&mut self) -> DecodeResult<u32> {
+        let offset = self.position;
+        let value = self.read_varint_u64()?;
+
+        u32::try_from(value).map_err(|_| {
+            self.position = offset;
+            invalid(DecodeError::VarintOverflow { offset })
+        })
+    }
+
+    /// Reads a varint holding a two's complement `i32`, as used by Minecraft.
+    pub fn read_varint_i32(&mut self) -> DecodeResult<i32> {
+        self.read_varint_u32().map(|value| value as i32)
+    }
+
+    /// Reads a varint holding a two's complement `i64`, as used by Minecraft's VarLong.
+    pub fn read_varint_i64(&mut self) -> DecodeResult<i64> {
+        self.read_varint_u64().map(|value| value as i64)
+    }
+
+    /// Reads an unsigned LEB128 varint of at most 64 bits.
+    pub fn read_varint_u64(&mut self) -> DecodeResult<u64> {
+        let offset = self.position;
+        let mut value = 0u64;
+
+        for index in 0..MAX_VARINT_LEN {
+            let Some(&byte) = self.data.get(offset + index) else {
+                return Err(truncated(offset, index + 1, self.remaining()));
+            };
+
+            let bits = u64::from(byte & 0x7f);
+
+            if index == MAX_VARINT_LEN - 1 && bits > 1 {
+                break;
+            }
+
+            value |= bits << (7 * index);
+
+            if byte & 0x80 == 0 {
+                self.position = offset + index + 1;
+                return Ok(value);
+            }
+        }
+
+        Err(invalid(DecodeError::VarintOverflow { offset }))
+    }
+
+    /// Fails unless `len` bytes remain.
+    fn ensure(&self, len: usize) -> DecodeResult<()> {
+        if self.remaining() < len {
+            return Err(truncated(self.position, len, self.remaining()));
+        }
+
+        Ok(())
+    }
+
+    /// Consumes a fixed number of bytes.
+    fn take_array<const N: usize>(&mut self) -> DecodeResult<[u8; N]> {
+        self.ensure(N)?;
+
+        let mut bytes = [0; N];
+        bytes.copy_from_slice(&self.data[self.position..self.position + N]);
+        self.position += N;
+
+        Ok(bytes)
+    }
+
+    /// Consumes bytes up to and including the next null byte, returning those before it.
+    fn take_until_nul(&mut self) -> DecodeResult<Bytes> {
+        let offset = self.position;
+        let Some(len) = self.data[offset..].iter().position(|&byte| byte == 0) else {
+            return Err(truncated_with(DecodeError::Unterminated { offset }));
+        };
+
+        let bytes = self.data.slice(offset..offset + len);
+        self.position += len + 1;
+
+        Ok(bytes)
+    }
+
+    /// Consumes UTF-16 code units up to and including the next null unit, returning those
+    /// before it.
+    fn take_until_nul_u16(&mut self) -> DecodeResult<Bytes> {
+        let offset = self.position;
+        let Some(units) = self.data[offset..]
+            .chunks_exact(2)
+            .position(|unit| unit == [0, 0])
+        else {
+            return Err(truncated_with(DecodeError::Unterminated { offset }));
+        };
+
+        let bytes = self.data.slice(offset..offset + units * 2);
+        self.position += units * 2 + 2;
+
+        Ok(bytes)
+    }
+
+    /// Consumes a UTF-8 string of `len` bytes whose encoding started at `offset`.
+    fn read_utf8(&mut self, len: usize, offset: usize) -> DecodeResult<ByteStr> {
+        let bytes = self
+            .read_bytes(len)
+            .inspect_err(|_| self.position = offset)?;
+
+        ByteStr::from_utf8(bytes).map_err(|_| {
+            self.position = offset;
+            invalid(DecodeError::InvalidUtf8 { offset })
+        })
+    }
+}
+
+/// Decodes UTF-16LE code units into a string.
+fn decode_utf16le(bytes: &[u8]) -> Result<ByteStr, std::char::DecodeUtf16Error> {
+    let units = bytes
+        .chunks_exact(2)
+        .map(|unit| u16::from_le_bytes([unit[0], unit[1]]));
+
+    char::decode_utf16(units)
+        .collect::<Result<String, _>>()
+        .map(ByteStr::from)
+}
+
+/// Builds the error returned when the packet ends early.
+fn truncated(offset: usize, needed: usize, remaining: usize) -> Error<DecodeError> {
+    truncated_with(DecodeError::Truncated {
+        offset,
+        needed,
+        remaining,
+    })
+}
+
+/// Wraps a decode error caused by the packet ending early.
+fn truncated_with(err: DecodeError) -> Error<DecodeError> {
+    Error::ParserError(
+        ErrorDetail::new("Packet ended unexpectedly", Some(err)).with_kind(ErrorKind::Truncated),
+    )
+}
+
+/// Wraps a decode error caused by malformed data.
+fn invalid(err: DecodeError) -> Error<DecodeError> {
+    Error::ParserError(
+        ErrorDetail::new("Packet contains an invalid value", Some(err))
+            .with_kind(ErrorKind::InvalidPacket),
+    )
+}
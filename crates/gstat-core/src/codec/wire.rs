@@ -0,0 +1,118 @@
+//! Packets described as structs whose fields are encoded in declaration order.
+//!
+//! `Encode` and `Decode` are what `#[derive(GstatQuery)]` and `#[derive(GstatResponse)]`
+//! implement with the `derive` feature, but they can be implemented by hand as well.
+//! `WireParser` connects such types to a `Protocol`, so simple binary protocols need no
+//! parser code at all.
+
+use crate::{
+    codec::{
+        reader::{ByteReader, DecodeError, DecodeResult},
+        writer::{ByteWriter, EncodeError, EncodeResult},
+    },
+    prelude::{Error, Parser, Query, Response},
+};
+
+use std::{
+    fmt::{Debug, Formatter, Result as FmtResult},
+    marker::PhantomData,
+};
+
+use bytes::Bytes;
+
+/// A type that writes itself to a packet.
+pub trait Encode {
+    /// Writes `self` at the end of `writer`.
+    ///
+    /// Failures, such as a string containing a null byte, are recorded by the writer and
+    /// reported when it is finished.
+    ///
+    /// # Parameters
+    ///
+    /// * `writer`: The packet being written.
+    fn encode(&self, writer: &mut ByteWriter);
+
+    /// Encodes `self` as a packet of its own.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing either the packet or an `Error::ParserError`.
+    fn to_bytes(&self) -> EncodeResult<Vec<u8>> {
+        let mut writer = ByteWriter::new();
+        self.encode(&mut writer);
+
+        writer.finish()
+    }
+}
+
+/// A type that reads itself from a packet.
+pub trait Decode: Sized {
+    /// Reads a value from the current position of `reader`.
+    ///
+    /// # Parameters
+    ///
+    /// * `reader`: The packet being read.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing either the value or an `Error::ParserError`.
+    fn decode(reader: &mut ByteReader) -> DecodeResult<Self>;
+
+    /// Decodes a value from the start of `data`, ignoring any bytes after it.
+    ///
+    /// # Parameters
+    ///
+    /// * `data`: The packet to decode.
+    fn from_bytes(data: impl Into<Bytes>) -> DecodeResult<Self> {
+        Self::decode(&mut ByteReader::new(data))
+    }
+}
+
+/// A `Parser` for queries implementing `Encode` and responses implementing `Decode`.
+pub struct WireParser<Q, R> {
+    _marker: PhantomData<fn() -> (Q, R)>,
+}
+
+impl<Q, R> WireParser<Q, R> {
+    /// Creates the parser.
+    pub fn new() -> Self {
+        WireParser {
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<Q, R> Default for WireParser<Q, R> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<Q, R> Clone for WireParser<Q, R> {
+    fn clone(&self) -> Self {
+        Self::new()
+    }
+}
+
+impl<Q, R> Debug for WireParser<Q, R> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.write_str("WireParser")
+    }
+}
+
+impl<'a, Q, R> Parser<'a, Q, R> for WireParser<Q, R>
+where
+    Q: Query + Encode + 'a,
+    R: Response + Decode + 'a,
+{
+    type SE = Error<EncodeError>;
+    type DE = Error<DecodeError>;
+
+    fn _serialize_query(&self, query: &Q) -> Result<Vec<u8>, Self::SE> {
+        query.to_bytes()
+    }
+
+    fn _deserialize_response(&self, data: Bytes) -> Result<R, Self::DE> {
+        R::from_bytes(data)
+    }
+}
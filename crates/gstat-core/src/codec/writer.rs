@@ -0,0 +1,406 @@
+use crate::{
+    codec::{Endian, LengthWidth},
+    prelude::{Error, ErrorDetail},
+};
+
+use std::{
+    error::Error as StdError,
+    fmt::{Display, Formatter, Result as FmtResult},
+};
+
+use bytes::Bytes;
+
+/// Describes why a `ByteWriter` could not encode a packet.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum EncodeError {
+    /// A null-terminated string contained a null byte.
+    InteriorNul {
+        /// The position the string would have started at.
+        offset: usize,
+    },
+    /// A length did not fit in its field.
+    LengthOverflow {
+        /// The length that had to be written.
+        length: usize,
+        /// The width of the field.
+        width: LengthWidth,
+    },
+    /// `close_length` was called without a matching `open_length`.
+    UnbalancedLength,
+}
+
+impl Display for EncodeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            Self::InteriorNul { offset } => {
+                write!(f, "string at offset {offset} contains a null byte")
+            }
+            Self::LengthOverflow { length, width } => write!(
+                f,
+                "length {length} does not fit in a {} byte field",
+                width.bytes()
+            ),
+            Self::UnbalancedLength => write!(f, "length closed without being opened"),
+        }
+    }
+}
+
+impl StdError for EncodeError {}
+
+/// The result of finishing a `ByteWriter`.
+pub type EncodeResult<T> = Result<T, Error<EncodeError>>;
+
+/// A checksum that can be appended to a packet.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Checksum {
+    /// The IEEE CRC-32, as used by A2S for compressed split packets.
+    Crc32,
+    /// The sum of every byte, truncated to a byte.
+    Sum8,
+    /// The exclusive or of every byte.
+    Xor8,
+}
+
+impl Checksum {
+    /// Computes the checksum of `data`.
+    pub fn compute(self, data: &[u8]) -> u32 {
+        match self {
+            Self::Crc32 => crc32(data),
+            Self::Sum8 => data.iter().fold(0u8, |sum, byte| sum.wrapping_add(*byte)) as u32,
+            Self::Xor8 => data.iter().fold(0u8, |xor, byte| xor ^ byte) as u32,
+        }
+    }
+
+    /// Returns the number of bytes the checksum occupies in a packet.
+    pub fn width(self) -> usize {
+        match self {
+            Self::Crc32 => 4,
+            Self::Sum8 | Self::Xor8 => 1,
+        }
+    }
+}
+
+/// A length prefix that has been reserved but not yet filled in.
+#[derive(Clone, Copy, Debug)]
+struct OpenLength {
+    position: usize,
+    width: LengthWidth,
+    endian: Endian,
+    inclusive: bool,
+}
+
+/// A builder for binary query payloads, complementing `ByteReader`.
+///
+/// Methods append to the packet and return the writer, so a payload can be built in a
+/// single chain. Operations that can fail, such as writing a string containing a null
+/// byte, don't interrupt the chain; the first failure is reported by `finish` instead.
+///
+/// Length prefixes whose value depends on what follows are reserved with `open_length`
+/// and filled in by the matching `close_length`; they can be nested.
+#[derive(Clone, Debug, Default)]
+pub struct ByteWriter {
+    data: Vec<u8>,
+    open: Vec<OpenLength>,
+    error: Option<EncodeError>,
+}
+
+macro_rules! write_numbers {
+    ($($(#[$doc:meta])* $name:ident => $ty:ty, $to:ident;)*) => {
+        $(
+            $(#[$doc])*
+            pub fn $name(&mut self, value: $ty) -> &mut Self {
+                self.data.extend_from_slice(&value.$to());
+                self
+            }
+        )*
+    };
+}
+
+impl ByteWriter {
+    /// Creates an empty writer.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates an empty writer with room for `capacity` bytes.
+    pub fn with_capacity(capacity: usize) -> Self {
+        ByteWriter {
+            data: Vec::with_capacity(capacity),
+            ..Self::default()
+        }
+    }
+
+    /// Returns the number of bytes written so far.
+    pub fn position(&self) -> usize {
+        self.data.len()
+    }
+
+    /// Returns the bytes written so far.
+    pub fn as_slice(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// Writes raw bytes, such as a packet header.
+    pub fn write_bytes(&mut self, bytes: &[u8]) -> &mut Self {
+        self.data.extend_from_slice(bytes);
+        self
+    }
+
+    write_numbers! {
+        /// Writes an unsigned byte.
+        write_u8 => u8, to_le_bytes;
+        /// Writes a signed byte.
+        write_i8 => i8, to_le_bytes;
+        /// Writes a little-endian `u16`.
+        write_u16_le => u16, to_le_bytes;
+        /// Writes a big-endian `u16`.
+        write_u16_be => u16, to_be_bytes;
+        /// Writes a little-endian `i16`.
+        write_i16_le => i16, to_le_bytes;
+        /// Writes a big-endian `i16`.
+        write_i16_be => i16, to_be_bytes;
+        /// Writes a little-endian `u32`.
+        write_u32_le => u32, to_le_bytes;
+        /// Writes a big-endian `u32`.
+        write_u32_be => u32, to_be_bytes;
+        /// Writes a little-endian `i32`.
+        write_i32_le => i32, to_le_bytes;
+        /// Writes a big-endian `i32`.
+        write_i32_be => i32, to_be_bytes;
+        /// Writes a little-endian `u64`.
+        write_u64_le => u64, to_le_bytes;
+        /// Writes a big-endian `u64`.
+        write_u64_be => u64, to_be_bytes;
+        /// Writes a little-endian `i64`.
+        write_i64_le => i64, to_le_bytes;
+        /// Writes a big-endian `i64`.
+        write_i64_be => i64, to_be_bytes;
+        /// Writes a little-endian `f32`.
+        write_f32_le => f32, to_le_bytes;
+        /// Writes a little-endian `f64`.
+        write_f64_le => f64, to_le_bytes;
+    }
+
+    /// Writes a string followed by a null byte.
+    ///
+    /// Fails when finished if the string contains a null byte.
+    pub fn write_cstring(&mut self, string: &str) -> &mut Self {
+        if string.as_bytes().contains(&0) {
+            return self.fail(EncodeError::InteriorNul {
+                offset: self.position(),
+            });
+        }
+
+        self.data.extend_from_slice(string.as_bytes());
+        self.data.push(0);
+        self
+    }
+
+    /// Writes a string preceded by a one-byte length.
+    ///
+    /// Fails when finished if the string is longer than 255 bytes.
+    pub fn write_pascal_string(&mut self, string: &str) -> &mut Self {
+        self.write_prefixed_string(string, LengthWidth::U8, Endian::Little)
+    }
+
+    /// Writes a string preceded by its length in bytes.
+    ///
+    /// Fails when finished if the length doesn't fit in the prefix.
+    ///
+    /// # Parameters
+    ///
+    /// * `string`: The string to write.
+    /// * `width`: The width of the length prefix.
+    /// * `endian`: The byte order of the length prefix.
+    pub fn write_prefixed_string(
+        &mut self,
+        string: &str,
+        width: LengthWidth,
+        endian: Endian,
+    ) -> &mut Self {
+        self.open_length(width, endian)
+            .write_bytes(string.as_bytes())
+            .close_length()
+    }
+
+    /// Writes a string as UTF-16LE code units, without a terminator or length.
+    pub fn write_utf16le_string(&mut self, string: &str) -> &mut Self {
+        for unit in string.encode_utf16() {
+            self.data.extend_from_slice(&unit.to_le_bytes());
+        }
+
+        self
+    }
+
+    /// Writes a string as UTF-16LE code units followed by a null code unit.
+    ///
+    /// Fails when finished if the string contains a null character.
+    pub fn write_utf16le_cstring(&mut self, string: &str) -> &mut Self {
+        if string.contains('\0') {
+            return self.fail(EncodeError::InteriorNul {
+                offset: self.position(),
+            });
+        }
+
+        self.write_utf16le_string(string).write_u16_le(0)
+    }
+
+    /// Writes an unsigned LEB128 varint.
+    pub fn write_varint_u64(&mut self, mut value: u64) -> &mut Self {
+        loop {
+            let byte = (value & 0x7f) as u8;
+            value >>= 7;
+
+            if value == 0 {
+                self.data.push(byte);
+                return self;
+            }
+
+            self.data.push(byte | 0x80);
+        }
+    }
+
+    /// Writes an unsigned LEB128 varint of at most 32 bits.
+    pub fn write_varint_u32(&mut self, value: u32) -> &mut Self {
+        self.write_varint_u64(value.into())
+    }
+
+    /// Writes a two's complement `i32` as a varint, as used by Minecraft.
+    pub fn write_varint_i32(&mut self, value: i32) -> &mut Self {
+        self.write_varint_u32(value as u32)
+    }
+
+    /// Writes a two's complement `i64` as a varint, as used by Minecraft's VarLong.
+    pub fn write_varint_i64(&mut self, value: i64) -> &mut Self {
+        self.write_varint_u64(value as u64)
+    }
+
+    /// Writes a string preceded by its length as a varint, as used by Minecraft.
+    pub fn write_varint_string(&mut self, string: &str) -> &mut Self {
+        self.write_varint_u64(string.len() as u64)
+            .write_bytes(string.as_bytes())
+    }
+
+    /// Reserves a length prefix covering everything written until the matching
+    /// `close_length`.
+    ///
+    /// # Parameters
+    ///
+    /// * `width`: The width of the prefix.
+    /// * `endian`: The byte order of the prefix.
+    pub fn open_length(&mut self, width: LengthWidth, endian: Endian) -> &mut Self {
+        self.reserve_length(width, endian, false)
+    }
+
+    /// Reserves a length prefix that also counts its own bytes.
+    ///
+    /// # Parameters
+    ///
+    /// * `width`: The width of the prefix.
+    /// * `endian`: The byte order of the prefix.
+    pub fn open_length_inclusive(&mut self, width: LengthWidth, endian: Endian) -> &mut Self {
+        self.reserve_length(width, endian, true)
+    }
+
+    /// Fills in the most recently reserved length prefix.
+    ///
+    /// Fails when finished if no prefix is open or the length doesn't fit.
+    pub fn close_length(&mut self) -> &mut Self {
+        let Some(open) = self.open.pop() else {
+            return self.fail(EncodeError::UnbalancedLength);
+        };
+
+        let start = open.position
+            + if open.inclusive {
+                0
+            } else {
+                open.width.bytes()
+            };
+        let length = self.data.len() - start;
+
+        if length > open.width.max() {
+            return self.fail(EncodeError::LengthOverflow {
+                length,
+                width: open.width,
+            });
+        }
+
+        let width = open.width.bytes();
+        let field = &mut self.data[open.position..open.position + width];
+
+        match open.endian {
+            Endian::Little => field.copy_from_slice(&(length as u32).to_le_bytes()[..width]),
+            Endian::Big => field.copy_from_slice(&(length as u32).to_be_bytes()[4 - width..]),
+        }
+
+        self
+    }
+
+    /// Appends a checksum of everything written from `from` onwards.
+    ///
+    /// # Parameters
+    ///
+    /// * `checksum`: The checksum to compute.
+    /// * `from`: The position the checksummed data starts at, e.g. `0` for the whole packet.
+    /// * `endian`: The byte order of multi-byte checksums.
+    pub fn write_checksum(&mut self, checksum: Checksum, from: usize, endian: Endian) -> &mut Self {
+        let value = checksum.compute(&self.data[from.min(self.data.len())..]);
+        let width = checksum.width();
+
+        match endian {
+            Endian::Little => self.write_bytes(&value.to_le_bytes()[..width]),
+            Endian::Big => self.write_bytes(&value.to_be_bytes()[4 - width..]),
+        }
+    }
+
+    /// Finishes the packet.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing either the packet or an `Error::ParserError` describing the
+    /// first operation that failed.
+    pub fn finish(self) -> EncodeResult<Vec<u8>> {
+        let error = match (self.error, self.open.is_empty()) {
+            (Some(error), _) => error,
+            (None, true) => return Ok(self.data),
+            (None, false) => EncodeError::UnbalancedLength,
+        };
+
+        Err(Error::ParserError(ErrorDetail::new(
+            "Failed to encode packet",
+            Some(error),
+        )))
+    }
+
+    /// Finishes the packet as `Bytes`.
+    pub fn finish_bytes(self) -> EncodeResult<Bytes> {
+        self.finish().map(Bytes::from)
+    }
+
+    /// Reserves zeroed space for a length prefix and remembers where it is.
+    fn reserve_length(&mut self, width: LengthWidth, endian: Endian, inclusive: bool) -> &mut Self {
+        self.open.push(OpenLength {
+            position: self.data.len(),
+            width,
+            endian,
+            inclusive,
+        });
+        self.data.resize(self.data.len() + width.bytes(), 0);
+        self
+    }
+
+    /// Records `error` unless an earlier operation already failed.
+    fn fail(&mut self, error: EncodeError) -> &mut Self {
+        self.error.get_or_insert(error);
+        self
+    }
+}
+
+/// Computes the IEEE CRC-32 of `data`.
+fn crc32(data: &[u8]) -> u32 {
+    !data.iter().fold(!0u32, |crc, byte| {
+        (0..8).fold(crc ^ *byte as u32, |crc, _| {
+            (crc >> 1) ^ (0xedb8_8320 & (crc & 1).wrapping_neg())
+        })
+    })
+}
@@ -0,0 +1,156 @@
+use crate::prelude::{Player, ServerInfo};
+
+use std::{
+    fmt::{Display, Formatter, Result as FmtResult},
+    net::SocketAddr,
+    time::Duration,
+};
+
+use serde_json::{Map, Value};
+
+/// `Gamedig` renders a server in the result shape of node-gamedig.
+///
+/// Dashboards written against GameDig read a fixed set of top-level fields (`name`, `map`,
+/// `password`, `numplayers`, `maxplayers`, `players`, `bots`, `connect`, `ping`,
+/// `queryPort`, `version` and `raw`), so rendering gstat results in that shape lets them
+/// swap backends without rewriting their parsers. Game-specific data goes into `raw`, as
+/// it does in GameDig.
+#[derive(Clone, Debug)]
+pub struct Gamedig<'a> {
+    info: &'a ServerInfo,
+    players: &'a [Player],
+    address: SocketAddr,
+    connect: Option<String>,
+    ping: Option<Duration>,
+}
+
+impl<'a> Gamedig<'a> {
+    /// Creates a result for a server without a player list.
+    ///
+    /// # Parameters
+    ///
+    /// * `info`: The normalized description of the server.
+    /// * `address`: The address the server was queried at.
+    pub fn new(info: &'a ServerInfo, address: SocketAddr) -> Self {
+        Gamedig {
+            info,
+            players: &[],
+            address,
+            connect: None,
+            ping: None,
+        }
+    }
+
+    /// Sets the players listed under `players`.
+    ///
+    /// # Parameters
+    ///
+    /// * `players`: The players connected to the server.
+    pub fn with_players(mut self, players: &'a [Player]) -> Self {
+        self.players = players;
+        self
+    }
+
+    /// Sets the address players connect to, if it differs from the query address.
+    ///
+    /// # Parameters
+    ///
+    /// * `connect`: The address reported under `connect`, e.g. `play.example.com:27015`.
+    pub fn with_connect(mut self, connect: impl Into<String>) -> Self {
+        self.connect = Some(connect.into());
+        self
+    }
+
+    /// Sets the round trip time reported under `ping`.
+    ///
+    /// # Parameters
+    ///
+    /// * `ping`: The time the query took.
+    pub fn with_ping(mut self, ping: Duration) -> Self {
+        self.ping = Some(ping);
+        self
+    }
+
+    /// Returns the result as a JSON value.
+    pub fn to_value(&self) -> Value {
+        let info = self.info;
+
+        let mut raw: Map<String, Value> = strings(&info.extra);
+        if !info.game.is_empty() {
+            raw.insert("game".into(), info.game.clone().into());
+        }
+
+        let mut result = Map::new();
+        result.insert("name".into(), info.name.clone().into());
+        result.insert("map".into(), info.map.clone().into());
+        result.insert("password".into(), info.password.into());
+        result.insert("numplayers".into(), info.players.into());
+        result.insert("maxplayers".into(), info.max_players.into());
+        result.insert(
+            "players".into(),
+            self.players.iter().map(player).collect::<Vec<_>>().into(),
+        );
+        result.insert("bots".into(), Vec::<Value>::new().into());
+        result.insert(
+            "connect".into(),
+            self.connect
+                .clone()
+                .unwrap_or_else(|| self.address.to_string())
+                .into(),
+        );
+        result.insert(
+            "ping".into(),
+            self.ping
+                .map_or(0, |ping| {
+                    u64::try_from(ping.as_millis()).unwrap_or(u64::MAX)
+                })
+                .into(),
+        );
+        result.insert("queryPort".into(), self.address.port().into());
+        result.insert("version".into(), info.version.clone().into());
+        result.insert("raw".into(), raw.into());
+
+        result.into()
+    }
+}
+
+impl Display for Gamedig<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(f, "{}", self.to_value())
+    }
+}
+
+/// Renders a player as GameDig does, with everything but the name under `raw`.
+fn player(player: &Player) -> Value {
+    let mut raw = strings(&player.extra);
+
+    if let Some(score) = player.score {
+        raw.insert("score".into(), score.into());
+    }
+
+    if let Some(duration) = player.duration {
+        raw.insert("time".into(), duration.as_secs_f64().into());
+    }
+
+    if let Some(ping) = player.ping {
+        raw.insert("ping".into(), ping.into());
+    }
+
+    if let Some(team) = &player.team {
+        raw.insert("team".into(), team.clone().into());
+    }
+
+    let mut result = Map::new();
+    result.insert("name".into(), player.name.clone().into());
+    result.insert("raw".into(), raw.into());
+
+    result.into()
+}
+
+/// Copies a map of game-specific strings into a JSON object.
+fn strings<'m>(extra: impl IntoIterator<Item = (&'m String, &'m String)>) -> Map<String, Value> {
+    extra
+        .into_iter()
+        .map(|(key, value)| (key.clone(), value.clone().into()))
+        .collect()
+}
@@ -0,0 +1,3 @@
+//! Renderers producing the output formats of other query tools.
+
+pub mod gamedig;
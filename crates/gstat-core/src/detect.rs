@@ -0,0 +1,320 @@
+//! Guessing the game of a server from its address alone.
+//!
+//! `detect` sends the cheapest status request of several protocols to a server at once and
+//! reports the protocols that answered, with the game when the answer or the registry
+//! tells it. `detect_ip` does the same for a bare IP address, probing the default query
+//! port of every game in the registry. Answers are only checked for the framing of their
+//! protocol, not parsed; querying the server with the detected game gives its status.
+
+use crate::{
+    bulk::QueryMany,
+    codec::reader::ByteReader,
+    registry::{self, GameEntry, ProtocolEntry, GAMES},
+    runtime::{self, UdpSocket},
+    slp::{self, SlpConnection},
+    timeout::TimeoutSettings,
+};
+
+use std::{
+    io,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+    time::Duration,
+};
+
+/// The largest answer read by a probe.
+const MAX_ANSWER: usize = 4096;
+
+/// The magic bytes of RakNet offline messages.
+const RAKNET_MAGIC: [u8; 16] = [
+    0x00, 0xFF, 0xFF, 0x00, 0xFE, 0xFE, 0xFE, 0xFE, 0xFD, 0xFD, 0xFD, 0xFD, 0x12, 0x34, 0x56, 0x78,
+];
+
+/// The session ID of GameSpy handshakes; GameSpy 4 servers ignore its high bits.
+const GAMESPY_SESSION: [u8; 4] = [0x01, 0x02, 0x03, 0x04];
+
+/// A status request identifying one protocol.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Probe {
+    /// A Valve `A2S_INFO` request, answered with the Steam AppID of the game.
+    A2s,
+    /// A GameSpy 3 and 4 challenge handshake.
+    GameSpy,
+    /// A Minecraft Server List Ping status request, over TCP.
+    MinecraftSlp,
+    /// A RakNet unconnected ping, as answered by Minecraft Bedrock.
+    RakNet,
+    /// A Quake III `getstatus` request.
+    Quake3,
+}
+
+impl Probe {
+    /// Every probe.
+    pub const ALL: [Probe; 5] = [
+        Probe::A2s,
+        Probe::GameSpy,
+        Probe::MinecraftSlp,
+        Probe::RakNet,
+        Probe::Quake3,
+    ];
+
+    /// Returns the probe identifying the protocol with the registry identifier `id`.
+    pub fn for_protocol(id: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|probe| probe.protocol_id() == id)
+    }
+
+    fn protocol_id(self) -> &'static str {
+        match self {
+            Probe::A2s => "a2s",
+            Probe::GameSpy => "gamespy3",
+            Probe::MinecraftSlp => "minecraft-slp",
+            Probe::RakNet => "raknet",
+            Probe::Quake3 => "quake3",
+        }
+    }
+
+    /// Returns the registry entry of the protocol the probe identifies.
+    pub fn protocol(self) -> &'static ProtocolEntry {
+        registry::protocol(self.protocol_id()).expect("every probe has a registry entry")
+    }
+
+    /// Sends the probe to `address` and checks the answer.
+    ///
+    /// # Returns
+    ///
+    /// The game, if the answer tells it, or an `io::Error` if the server did not answer in
+    /// the framing of the protocol.
+    pub(crate) async fn run(self, address: SocketAddr) -> io::Result<Option<&'static GameEntry>> {
+        match self {
+            Probe::A2s => a2s(address).await,
+            Probe::GameSpy => {
+                let mut request = vec![0xFE, 0xFD, 0x09];
+                request.extend_from_slice(&GAMESPY_SESSION);
+
+                let answer = exchange(address, &request).await?;
+                let mut expected = vec![0x09];
+                expected.extend_from_slice(&GAMESPY_SESSION);
+
+                recognized(answer.starts_with(&expected))
+            }
+            Probe::MinecraftSlp => minecraft_slp(address).await,
+            Probe::RakNet => {
+                let mut request = vec![0x01];
+                request.extend_from_slice(&0u64.to_be_bytes());
+                request.extend_from_slice(&RAKNET_MAGIC);
+                request.extend_from_slice(&fastrand::u64(..).to_be_bytes());
+
+                let answer = exchange(address, &request).await?;
+
+                recognized(
+                    answer.first() == Some(&0x1C) && answer.get(17..33) == Some(&RAKNET_MAGIC),
+                )
+            }
+            Probe::Quake3 => {
+                let answer = exchange(address, b"\xFF\xFF\xFF\xFFgetstatus\n").await?;
+
+                recognized(answer.starts_with(b"\xFF\xFF\xFF\xFFstatusResponse"))
+            }
+        }
+    }
+}
+
+/// A protocol a server answered.
+#[derive(Clone, Copy, Debug)]
+pub struct Detection {
+    /// The address that answered.
+    pub address: SocketAddr,
+    /// The protocol the server answered.
+    pub protocol: &'static ProtocolEntry,
+    /// The game of the server, if the answer tells it or the protocol is only used by one
+    /// game of the registry.
+    pub game: Option<&'static GameEntry>,
+    /// The time from sending the probe to recognizing the answer.
+    pub latency: Duration,
+}
+
+/// Probes a server with every `Probe` at once and returns its most likely protocol.
+///
+/// # Parameters
+///
+/// * `address`: The address of the server, with its query port.
+/// * `limit`: How long each probe waits for its answer.
+///
+/// # Returns
+///
+/// The detection naming a game if there is one, otherwise the fastest, or `None` if no
+/// probe was answered.
+pub async fn detect(address: SocketAddr, limit: Duration) -> Option<Detection> {
+    detect_all(Probe::ALL.map(|probe| (probe, address)), limit)
+        .await
+        .into_iter()
+        .next()
+}
+
+/// Probes an IP address on the default query port of every game in the registry.
+///
+/// # Parameters
+///
+/// * `ip`: The address of the server.
+/// * `limit`: How long each probe waits for its answer.
+///
+/// # Returns
+///
+/// The most likely detection, as for `detect`, or `None` if no probe was answered.
+pub async fn detect_ip(ip: IpAddr, limit: Duration) -> Option<Detection> {
+    let mut targets = Vec::new();
+
+    for game in GAMES {
+        let Some(probe) = Probe::for_protocol(game.protocol) else {
+            continue;
+        };
+        let target = (probe, SocketAddr::new(ip, game.default_query_port()));
+
+        if !targets.contains(&target) {
+            targets.push(target);
+        }
+    }
+
+    detect_all(targets, limit).await.into_iter().next()
+}
+
+/// Runs probes at once and returns the answered ones, most likely first.
+///
+/// # Parameters
+///
+/// * `targets`: The probes and the addresses they are sent to.
+/// * `limit`: How long each probe waits for its answer.
+///
+/// # Returns
+///
+/// The detections naming a game first, each group ordered by latency.
+pub async fn detect_all(
+    targets: impl IntoIterator<Item = (Probe, SocketAddr)>,
+    limit: Duration,
+) -> Vec<Detection> {
+    let targets: Vec<_> = targets.into_iter().collect();
+    let concurrency = targets.len();
+
+    let mut detections: Vec<Detection> =
+        QueryMany::new(targets, concurrency, |&(probe, address)| async move {
+            let started = runtime::try_now();
+            let game = runtime::timeout(limit, probe.run(address))
+                .await
+                .ok()?
+                .ok()?;
+
+            Some(Detection {
+                address,
+                protocol: probe.protocol(),
+                game: game.or_else(|| sole_game(probe.protocol_id())),
+                latency: runtime::elapsed(started),
+            })
+        })
+        .collect()
+        .await
+        .into_iter()
+        .filter_map(|(_, detection)| detection)
+        .collect();
+
+    detections.sort_by_key(|detection| (detection.game.is_none(), detection.latency));
+    detections
+}
+
+/// Returns the game using `protocol`, if no other game of the registry does.
+fn sole_game(protocol: &str) -> Option<&'static GameEntry> {
+    let mut games = GAMES.iter().filter(|game| game.protocol == protocol);
+
+    match (games.next(), games.next()) {
+        (Some(game), None) => Some(game),
+        _ => None,
+    }
+}
+
+/// Turns the check of an answer into the outcome of a probe that can't name a game.
+fn recognized(matches: bool) -> io::Result<Option<&'static GameEntry>> {
+    if matches {
+        Ok(None)
+    } else {
+        Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "unrecognized answer",
+        ))
+    }
+}
+
+/// Sends a datagram to `address` and returns the first datagram it answers with.
+pub(crate) async fn exchange(address: SocketAddr, request: &[u8]) -> io::Result<Vec<u8>> {
+    let socket = UdpSocket::bind(unspecified(address)).await?;
+    socket.connect(address).await?;
+
+    exchange_on(&socket, request).await
+}
+
+async fn exchange_on(socket: &UdpSocket, request: &[u8]) -> io::Result<Vec<u8>> {
+    socket.send(request).await?;
+
+    let mut buffer = vec![0; MAX_ANSWER];
+    let len = socket.recv(&mut buffer).await?;
+    buffer.truncate(len);
+
+    Ok(buffer)
+}
+
+/// Sends `A2S_INFO`, answering a challenge once, and looks the game up by its AppID.
+async fn a2s(address: SocketAddr) -> io::Result<Option<&'static GameEntry>> {
+    const REQUEST: &[u8] = b"\xFF\xFF\xFF\xFFTSource Engine Query\0";
+
+    let socket = UdpSocket::bind(unspecified(address)).await?;
+    socket.connect(address).await?;
+
+    let mut answer = exchange_on(&socket, REQUEST).await?;
+
+    if let Some(challenge) = answer.strip_prefix(b"\xFF\xFF\xFF\xFFA") {
+        let mut request = REQUEST.to_vec();
+        request.extend_from_slice(challenge);
+        answer = exchange_on(&socket, &request).await?;
+    }
+
+    let mut reader = ByteReader::new(answer);
+    let invalid = |_| io::Error::new(io::ErrorKind::InvalidData, "not an A2S_INFO answer");
+
+    reader.expect_bytes(b"\xFF\xFF\xFF\xFF").map_err(invalid)?;
+
+    match reader.read_u8().map_err(invalid)? {
+        // GoldSrc servers answer in the obsolete format, without an AppID.
+        b'm' => Ok(None),
+        b'I' => {
+            reader.skip(1).map_err(invalid)?;
+
+            for _ in 0..4 {
+                reader.read_cstring_lossy().map_err(invalid)?;
+            }
+
+            let appid = reader.read_u16_le().map_err(invalid)?;
+
+            Ok(registry::game_by_appid(appid.into()))
+        }
+        _ => recognized(false),
+    }
+}
+
+/// Sends a Server List Ping handshake and status request, and checks that the answer is
+/// a JSON document.
+async fn minecraft_slp(address: SocketAddr) -> io::Result<Option<&'static GameEntry>> {
+    let mut connection = SlpConnection::connect(address, &TimeoutSettings::default())
+        .await
+        .map_err(slp::into_io)?;
+    let status = connection.status().await.map_err(slp::into_io)?;
+    connection.close().await;
+
+    recognized(status.trim_start().starts_with('{'))
+}
+
+/// Returns the unspecified address of the family of `address`, on any port.
+fn unspecified(address: SocketAddr) -> SocketAddr {
+    match address {
+        SocketAddr::V4(_) => SocketAddr::from((Ipv4Addr::UNSPECIFIED, 0)),
+        SocketAddr::V6(_) => SocketAddr::from((Ipv6Addr::UNSPECIFIED, 0)),
+    }
+}
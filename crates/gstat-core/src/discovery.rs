@@ -0,0 +1,327 @@
+//! Finding servers to query.
+//!
+//! Server lists come from master servers, which index the servers of a game, or from the
+//! local network, where servers answer queries sent to the broadcast address.
+//! `query_master` lists the servers known to a Valve master server, optionally narrowed by
+//! a `MasterQuery` filter, and `broadcast` sends a game's query to the local network and
+//! collects the servers that answer it.
+
+use crate::{
+    codec::reader::{ByteReader, DecodeResult},
+    runtime::{self, UdpSocket},
+    timeout::TimeoutSettings,
+};
+
+use std::{
+    io,
+    net::{Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV4},
+    time::Duration,
+};
+
+use bytes::Bytes;
+
+/// The host and port of Valve's master server for Source and GoldSrc games.
+pub const VALVE_MASTER: (&str, u16) = ("hl2master.steampowered.com", 27011);
+
+/// The header of a master server's reply.
+const REPLY_HEADER: &[u8] = &[0xFF, 0xFF, 0xFF, 0xFF, 0x66, 0x0A];
+
+/// The address a master server starts and ends its list with.
+const SEED: SocketAddrV4 = SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, 0);
+
+/// The largest datagram read while discovering servers.
+const MAX_DATAGRAM: usize = 64 * 1024;
+
+/// The region a master server lists servers of.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Region {
+    /// The east coast of the United States.
+    UsEast,
+    /// The west coast of the United States.
+    UsWest,
+    /// South America.
+    SouthAmerica,
+    /// Europe.
+    Europe,
+    /// Asia.
+    Asia,
+    /// Australia.
+    Australia,
+    /// The Middle East.
+    MiddleEast,
+    /// Africa.
+    Africa,
+    /// Every region.
+    #[default]
+    World,
+}
+
+impl Region {
+    /// Returns the code of the region in master server queries.
+    pub fn code(self) -> u8 {
+        match self {
+            Region::UsEast => 0x00,
+            Region::UsWest => 0x01,
+            Region::SouthAmerica => 0x02,
+            Region::Europe => 0x03,
+            Region::Asia => 0x04,
+            Region::Australia => 0x05,
+            Region::MiddleEast => 0x06,
+            Region::Africa => 0x07,
+            Region::World => 0xFF,
+        }
+    }
+}
+
+/// `MasterQuery` describes the servers to list from a Valve master server.
+///
+/// The filter uses the master server syntax of backslash-separated keys and values, e.g.
+/// `\appid\440\empty\1` for non-empty Team Fortress 2 servers. Master servers answer in
+/// pages of about 230 servers, each requested with the last address of the previous page;
+/// `with_max_pages` bounds how many are requested.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MasterQuery {
+    region: Region,
+    filter: String,
+    max_pages: usize,
+}
+
+impl Default for MasterQuery {
+    fn default() -> Self {
+        MasterQuery {
+            region: Region::World,
+            filter: String::new(),
+            max_pages: 16,
+        }
+    }
+}
+
+impl MasterQuery {
+    /// Creates a query listing every server of every region, up to 16 pages.
+    pub fn new() -> Self {
+        MasterQuery::default()
+    }
+
+    /// Lists the servers of `region` only.
+    pub fn with_region(mut self, region: Region) -> Self {
+        self.region = region;
+        self
+    }
+
+    /// Lists the servers matching `filter`, e.g. `\appid\440`.
+    pub fn with_filter(mut self, filter: impl Into<String>) -> Self {
+        self.filter = filter.into();
+        self
+    }
+
+    /// Requests at most `pages` pages. `0` is treated as `1`.
+    pub fn with_max_pages(mut self, pages: usize) -> Self {
+        self.max_pages = pages.max(1);
+        self
+    }
+
+    /// Returns the region the servers are listed from.
+    pub fn region(&self) -> Region {
+        self.region
+    }
+
+    /// Returns the filter the servers must match.
+    pub fn filter(&self) -> &str {
+        &self.filter
+    }
+
+    /// Returns the request for the page following `seed`.
+    ///
+    /// # Parameters
+    ///
+    /// * `seed`: The last address of the previous page, or `0.0.0.0:0` for the first page.
+    pub fn encode(&self, seed: SocketAddrV4) -> Vec<u8> {
+        let mut request = vec![0x31, self.region.code()];
+
+        request.extend_from_slice(seed.to_string().as_bytes());
+        request.push(0);
+        request.extend_from_slice(self.filter.as_bytes());
+        request.push(0);
+
+        request
+    }
+}
+
+/// A page of a master server's list.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MasterPage {
+    /// The servers on the page, without the end marker.
+    pub servers: Vec<SocketAddrV4>,
+    /// Whether the page ends the list.
+    pub last: bool,
+}
+
+impl MasterPage {
+    /// Decodes a master server's reply.
+    ///
+    /// # Parameters
+    ///
+    /// * `data`: The reply, starting with its `FF FF FF FF 66 0A` header.
+    ///
+    /// # Returns
+    ///
+    /// A `DecodeResult` containing either the page or an `Error::ParserError`.
+    pub fn decode(data: Bytes) -> DecodeResult<Self> {
+        let mut reader = ByteReader::new(data);
+        let mut page = MasterPage::default();
+
+        reader.expect_bytes(REPLY_HEADER)?;
+
+        while !reader.is_empty() {
+            let ip = Ipv4Addr::from(reader.read_u32_be()?);
+            let address = SocketAddrV4::new(ip, reader.read_u16_be()?);
+
+            if address == SEED {
+                page.last = true;
+                break;
+            }
+
+            page.servers.push(address);
+        }
+
+        Ok(page)
+    }
+}
+
+/// Lists the servers known to a master server.
+///
+/// # Parameters
+///
+/// * `master`: The address of the master server, e.g. `VALVE_MASTER` once resolved.
+/// * `query`: The servers to list.
+/// * `timeouts`: The limit for each page (`read`) and for the whole list (`overall`).
+///
+/// # Returns
+///
+/// A `Result` containing the listed servers, or an `io::Error` if the master server could
+/// not be reached or sent an invalid page. If the overall limit or the page limit elapses
+/// after the first page, the servers listed so far are returned.
+pub async fn query_master(
+    master: SocketAddr,
+    query: &MasterQuery,
+    timeouts: &TimeoutSettings,
+) -> io::Result<Vec<SocketAddrV4>> {
+    let socket = UdpSocket::bind(unspecified(master)).await?;
+    socket.connect(master).await?;
+
+    let started = runtime::try_now();
+    let mut servers: Vec<SocketAddrV4> = Vec::new();
+    let mut buffer = vec![0; MAX_DATAGRAM];
+
+    for _ in 0..query.max_pages {
+        let seed = servers.last().copied().unwrap_or(SEED);
+        socket.send(&query.encode(seed)).await?;
+
+        let overall = timeouts
+            .overall
+            .map(|overall| overall.saturating_sub(runtime::elapsed(started)));
+        let limit = match (timeouts.read, overall) {
+            (Some(read), Some(overall)) => Some(read.min(overall)),
+            (read, overall) => read.or(overall),
+        };
+
+        let received = match limit {
+            Some(limit) => runtime::timeout(limit, socket.recv(&mut buffer)).await,
+            None => Ok(socket.recv(&mut buffer).await),
+        };
+
+        let len = match received {
+            Ok(received) => received?,
+            Err(_) if !servers.is_empty() => break,
+            Err(_) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::TimedOut,
+                    format!("{master} did not answer"),
+                ))
+            }
+        };
+
+        let page = MasterPage::decode(Bytes::copy_from_slice(&buffer[..len]))
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err.to_string()))?;
+
+        servers.extend(page.servers);
+
+        if page.last {
+            break;
+        }
+    }
+
+    Ok(servers)
+}
+
+/// A server's answer to a broadcast query.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Answer {
+    /// The address the answer came from.
+    pub address: SocketAddr,
+    /// The answer, to be decoded by the parser of the game that was queried.
+    pub data: Bytes,
+    /// The time from sending the query to receiving the answer.
+    pub latency: Duration,
+}
+
+/// Returns the broadcast address of the local network for servers listening on `port`.
+pub fn lan(port: u16) -> SocketAddr {
+    SocketAddr::from((Ipv4Addr::BROADCAST, port))
+}
+
+/// Sends a query to `destination` and collects the answers arriving within `window`.
+///
+/// With a broadcast `destination`, such as `lan(port)`, every server of the local network
+/// listening on the port answers, so the answers are both the list of servers and their
+/// responses to the query.
+///
+/// # Parameters
+///
+/// * `destination`: The address the query is sent to.
+/// * `payload`: The query, e.g. the serialized default query of the game.
+/// * `window`: How long answers are collected for.
+///
+/// # Returns
+///
+/// A `Result` containing the first answer of each server in the order they arrived, or an
+/// `io::Error` if the query could not be sent.
+pub async fn broadcast(
+    destination: SocketAddr,
+    payload: &[u8],
+    window: Duration,
+) -> io::Result<Vec<Answer>> {
+    let socket = UdpSocket::bind(unspecified(destination)).await?;
+    socket.set_broadcast(true)?;
+    socket.send_to(payload, destination).await?;
+
+    let started = runtime::try_now();
+    let mut answers: Vec<Answer> = Vec::new();
+    let mut buffer = vec![0; MAX_DATAGRAM];
+
+    loop {
+        let remaining = window.saturating_sub(runtime::elapsed(started));
+
+        let Ok(received) = runtime::timeout(remaining, socket.recv_from(&mut buffer)).await else {
+            return Ok(answers);
+        };
+
+        let (len, from) = received?;
+
+        if answers.iter().all(|answer| answer.address != from) {
+            answers.push(Answer {
+                address: from,
+                data: Bytes::copy_from_slice(&buffer[..len]),
+                latency: runtime::elapsed(started),
+            });
+        }
+    }
+}
+
+/// Returns the unspecified address of the family of `address`, on any port.
+fn unspecified(address: SocketAddr) -> SocketAddr {
+    match address {
+        SocketAddr::V4(_) => SocketAddr::from((Ipv4Addr::UNSPECIFIED, 0)),
+        SocketAddr::V6(_) => SocketAddr::from((Ipv6Addr::UNSPECIFIED, 0)),
+    }
+}
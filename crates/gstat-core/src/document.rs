@@ -0,0 +1,99 @@
+//! The versioned document describing the answer to a query.
+//!
+//! `gstat query --format json`, the REST API of `gstat-serve` and the C bindings all print
+//! the same document, built here as a `Value` and encoded with `encode::Json`, so every
+//! front end reports a server the same way.
+
+use crate::{
+    models::value::Value,
+    standards::{game::Fetched, response::Response},
+};
+
+use std::collections::BTreeMap;
+
+/// The version of the document.
+///
+/// Fields are only ever added to the document; renaming or removing one bumps the version.
+pub const VERSION: u32 = 1;
+
+/// Returns a response as a document.
+///
+/// Besides the response, the document holds the metadata of the query: the game, the
+/// address that answered and the round-trip latency in milliseconds. The players and the
+/// rules are always included, so programs get the same shape whatever the game reports.
+///
+/// # Parameters
+///
+/// * `id`: The identifier of the game the server runs.
+/// * `name`: The name of the game the server runs.
+/// * `fetched`: The response and its metadata.
+///
+/// # Returns
+///
+/// The document, to encode with `encode::Json`.
+pub fn document<R: Response, E>(id: &str, name: &str, fetched: &Fetched<R, E>) -> Value {
+    let info = fetched.response.to_common();
+    let players = fetched
+        .response
+        .players()
+        .into_iter()
+        .map(|player| {
+            map([
+                ("name", player.name.into()),
+                ("score", player.score.into()),
+                (
+                    "duration_secs",
+                    player
+                        .duration
+                        .map(|duration| duration.as_secs_f64())
+                        .into(),
+                ),
+                ("ping_ms", player.ping.into()),
+                ("team", player.team.into()),
+                ("extra", player.extra.into()),
+            ])
+        })
+        .collect::<Vec<_>>();
+
+    map([
+        ("version", VERSION.into()),
+        ("game", map([("id", id.into()), ("name", name.into())])),
+        (
+            "address",
+            fetched
+                .meta
+                .address()
+                .map(|address| address.to_string())
+                .into(),
+        ),
+        (
+            "latency_ms",
+            (fetched.meta.latency().as_secs_f64() * 1000.0).into(),
+        ),
+        (
+            "info",
+            map([
+                ("name", info.name.into()),
+                ("map", info.map.into()),
+                ("game", info.game.into()),
+                ("version", info.version.into()),
+                ("players", info.players.into()),
+                ("max_players", info.max_players.into()),
+                ("password", info.password.into()),
+                ("access", info.access.to_string().into()),
+            ]),
+        ),
+        ("players", players.into()),
+        ("rules", info.extra.into()),
+    ])
+}
+
+/// Returns named values as a `Value::Map`.
+fn map<const N: usize>(entries: [(&str, Value); N]) -> Value {
+    Value::Map(
+        entries
+            .into_iter()
+            .map(|(key, value)| (key.to_string(), value))
+            .collect::<BTreeMap<_, _>>(),
+    )
+}
@@ -0,0 +1,51 @@
+//! Reading human-readable durations from command lines and configuration files.
+
+use std::time::Duration;
+
+/// Parses a human-readable duration such as `500ms`, `5s`, `2m` or `1h`.
+///
+/// A number without a unit is read as seconds.
+///
+/// # Parameters
+///
+/// * `input`: The duration to parse.
+///
+/// # Returns
+///
+/// A `Result` containing either the duration or a description of why it is invalid.
+pub fn parse_duration(input: &str) -> Result<Duration, String> {
+    let input = input.trim();
+    let split = input
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(input.len());
+    let (value, unit) = input.split_at(split);
+
+    let value: f64 = value
+        .parse()
+        .map_err(|_| format!("invalid duration `{input}`"))?;
+
+    let seconds = match unit.trim() {
+        "ms" => value / 1000.0,
+        "" | "s" => value,
+        "m" => value * 60.0,
+        "h" => value * 60.0 * 60.0,
+        unit => return Err(format!("unknown duration unit `{unit}` in `{input}`")),
+    };
+
+    Duration::try_from_secs_f64(seconds).map_err(|_| format!("invalid duration `{input}`"))
+}
+
+/// Deserializes an optional duration written as a string, e.g. `timeout = "5s"`.
+///
+/// Use it with `#[serde(default, deserialize_with = "deserialize_optional")]`.
+#[cfg(feature = "serde")]
+pub fn deserialize_optional<'de, D>(deserializer: D) -> Result<Option<Duration>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    use serde::Deserialize;
+
+    Option::<String>::deserialize(deserializer)?
+        .map(|value| parse_duration(&value).map_err(serde::de::Error::custom))
+        .transpose()
+}
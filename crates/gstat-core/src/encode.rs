@@ -0,0 +1,502 @@
+//! Encoding normalized responses for other programs.
+//!
+//! `Response::fields` describes any response as `Value`s. An `Encode` implementation turns
+//! such a document into bytes: `Json` for web tools, `Csv` for spreadsheets and panels that
+//! import flat tables, `MessagePack` for compact binary feeds and `Xml` for legacy panels.
+//! `Encoding` names the built-in encoders, so a format can be picked from a configuration
+//! file or a command line flag.
+
+use crate::models::value::Value;
+
+use std::{
+    collections::BTreeMap,
+    error::Error as StdError,
+    fmt::{Display, Formatter, Result as FmtResult, Write as _},
+    str::FromStr,
+};
+
+/// A trait for serializers of `Value` documents.
+pub trait Encode {
+    /// Returns the MIME type of the encoded documents, e.g. for a `Content-Type` header.
+    fn content_type(&self) -> &'static str;
+
+    /// Encodes a document.
+    ///
+    /// # Parameters
+    ///
+    /// * `value`: The document, e.g. `Value::Map(response.fields())`.
+    ///
+    /// # Returns
+    ///
+    /// The encoded document.
+    fn encode(&self, value: &Value) -> Vec<u8>;
+}
+
+/// Encodes documents as compact JSON.
+///
+/// Non-finite floats, which JSON can't represent, are encoded as `null`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Json;
+
+impl Encode for Json {
+    fn content_type(&self) -> &'static str {
+        "application/json"
+    }
+
+    fn encode(&self, value: &Value) -> Vec<u8> {
+        let mut output = String::new();
+        json(value, &mut output);
+        output.into_bytes()
+    }
+}
+
+fn json(value: &Value, output: &mut String) {
+    match value {
+        Value::Null => output.push_str("null"),
+        Value::Bool(value) => output.push_str(if *value { "true" } else { "false" }),
+        Value::Int(value) => {
+            let _ = write!(output, "{value}");
+        }
+        Value::Float(value) if value.is_finite() => {
+            let _ = write!(output, "{value}");
+        }
+        Value::Float(_) => output.push_str("null"),
+        Value::String(value) => json_string(value, output),
+        Value::List(values) => {
+            output.push('[');
+
+            for (index, value) in values.iter().enumerate() {
+                if index > 0 {
+                    output.push(',');
+                }
+
+                json(value, output);
+            }
+
+            output.push(']');
+        }
+        Value::Map(values) => {
+            output.push('{');
+
+            for (index, (key, value)) in values.iter().enumerate() {
+                if index > 0 {
+                    output.push(',');
+                }
+
+                json_string(key, output);
+                output.push(':');
+                json(value, output);
+            }
+
+            output.push('}');
+        }
+    }
+}
+
+fn json_string(value: &str, output: &mut String) {
+    output.push('"');
+
+    for char in value.chars() {
+        match char {
+            '"' => output.push_str("\\\""),
+            '\\' => output.push_str("\\\\"),
+            '\n' => output.push_str("\\n"),
+            '\r' => output.push_str("\\r"),
+            '\t' => output.push_str("\\t"),
+            char if char.is_control() => {
+                let _ = write!(output, "\\u{:04x}", char as u32);
+            }
+            char => output.push(char),
+        }
+    }
+
+    output.push('"');
+}
+
+/// Encodes documents as a CSV table, with nested values flattened into columns.
+///
+/// A list of maps, such as the results of a bulk query, becomes one row per element; any
+/// other document becomes a single row. Nested maps and lists are flattened into columns
+/// named by their path, e.g. `player_list.0.name`, and the header holds every column in the
+/// order the columns first appear. Rows end with CRLF, as RFC 4180 specifies.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Csv;
+
+impl Encode for Csv {
+    fn content_type(&self) -> &'static str {
+        "text/csv"
+    }
+
+    fn encode(&self, value: &Value) -> Vec<u8> {
+        let documents = match value {
+            Value::List(values) if values.iter().all(|value| value.as_map().is_some()) => {
+                values.iter().collect()
+            }
+            value => vec![value],
+        };
+
+        let rows: Vec<Vec<(String, &Value)>> = documents
+            .into_iter()
+            .map(|document| {
+                let mut row = Vec::new();
+                flatten(String::new(), document, &mut row);
+                row
+            })
+            .collect();
+
+        let mut columns: Vec<&str> = Vec::new();
+
+        for (column, _) in rows.iter().flatten() {
+            if !columns.contains(&column.as_str()) {
+                columns.push(column);
+            }
+        }
+
+        let mut output = String::new();
+        csv_row(columns.iter().copied().map(Into::into), &mut output);
+
+        for row in &rows {
+            let cells: BTreeMap<&str, &Value> = row
+                .iter()
+                .map(|(column, value)| (column.as_str(), *value))
+                .collect();
+
+            csv_row(
+                columns.iter().map(|column| {
+                    cells
+                        .get(column)
+                        .map_or_else(String::new, |value| value.to_string())
+                }),
+                &mut output,
+            );
+        }
+
+        output.into_bytes()
+    }
+}
+
+/// Collects the scalar values of `value` with their column names.
+fn flatten<'v>(path: String, value: &'v Value, row: &mut Vec<(String, &'v Value)>) {
+    let child = |key: &dyn Display| match path.as_str() {
+        "" => key.to_string(),
+        path => format!("{path}.{key}"),
+    };
+
+    match value {
+        Value::Map(values) => {
+            for (key, value) in values {
+                flatten(child(key), value, row);
+            }
+        }
+        Value::List(values) => {
+            for (index, value) in values.iter().enumerate() {
+                flatten(child(&index), value, row);
+            }
+        }
+        value => row.push((path, value)),
+    }
+}
+
+fn csv_row(cells: impl Iterator<Item = String>, output: &mut String) {
+    for (index, cell) in cells.enumerate() {
+        if index > 0 {
+            output.push(',');
+        }
+
+        if cell.contains([',', '"', '\n', '\r']) {
+            output.push('"');
+            output.push_str(&cell.replace('"', "\"\""));
+            output.push('"');
+        } else {
+            output.push_str(&cell);
+        }
+    }
+
+    output.push_str("\r\n");
+}
+
+/// Encodes documents as MessagePack.
+///
+/// Integers take the smallest encoding that holds them, and floats are always encoded
+/// with 64 bits.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct MessagePack;
+
+impl Encode for MessagePack {
+    fn content_type(&self) -> &'static str {
+        "application/msgpack"
+    }
+
+    fn encode(&self, value: &Value) -> Vec<u8> {
+        let mut output = Vec::new();
+        msgpack(value, &mut output);
+        output
+    }
+}
+
+fn msgpack(value: &Value, output: &mut Vec<u8>) {
+    match value {
+        Value::Null => output.push(0xC0),
+        Value::Bool(false) => output.push(0xC2),
+        Value::Bool(true) => output.push(0xC3),
+        Value::Int(value) => msgpack_int(*value, output),
+        Value::Float(value) => {
+            output.push(0xCB);
+            output.extend_from_slice(&value.to_be_bytes());
+        }
+        Value::String(value) => msgpack_str(value, output),
+        Value::List(values) => {
+            msgpack_len(values.len(), [0x90, 0xDC, 0xDD], output);
+
+            for value in values {
+                msgpack(value, output);
+            }
+        }
+        Value::Map(values) => {
+            msgpack_len(values.len(), [0x80, 0xDE, 0xDF], output);
+
+            for (key, value) in values {
+                msgpack_str(key, output);
+                msgpack(value, output);
+            }
+        }
+    }
+}
+
+fn msgpack_int(value: i64, output: &mut Vec<u8>) {
+    match value {
+        // Positive and negative fixints.
+        -32..=0x7F => output.push(value as u8),
+        0x80..=0xFF => output.extend_from_slice(&[0xCC, value as u8]),
+        0x100..=0xFFFF => {
+            output.push(0xCD);
+            output.extend_from_slice(&(value as u16).to_be_bytes());
+        }
+        0x1_0000..=0xFFFF_FFFF => {
+            output.push(0xCE);
+            output.extend_from_slice(&(value as u32).to_be_bytes());
+        }
+        0x1_0000_0000.. => {
+            output.push(0xCF);
+            output.extend_from_slice(&(value as u64).to_be_bytes());
+        }
+        -0x80.. => output.extend_from_slice(&[0xD0, value as u8]),
+        -0x8000.. => {
+            output.push(0xD1);
+            output.extend_from_slice(&(value as i16).to_be_bytes());
+        }
+        -0x8000_0000.. => {
+            output.push(0xD2);
+            output.extend_from_slice(&(value as i32).to_be_bytes());
+        }
+        _ => {
+            output.push(0xD3);
+            output.extend_from_slice(&value.to_be_bytes());
+        }
+    }
+}
+
+fn msgpack_str(value: &str, output: &mut Vec<u8>) {
+    match value.len() {
+        len @ 0..=31 => output.push(0xA0 | len as u8),
+        len @ 0..=0xFF => output.extend_from_slice(&[0xD9, len as u8]),
+        len @ 0..=0xFFFF => {
+            output.push(0xDA);
+            output.extend_from_slice(&(len as u16).to_be_bytes());
+        }
+        len => {
+            output.push(0xDB);
+            output.extend_from_slice(&(len as u32).to_be_bytes());
+        }
+    }
+
+    output.extend_from_slice(value.as_bytes());
+}
+
+/// Writes the length of an array or map: in the marker itself up to 15, otherwise after a
+/// 16 or 32 bit marker.
+fn msgpack_len(len: usize, [fix, short, long]: [u8; 3], output: &mut Vec<u8>) {
+    if len <= 15 {
+        output.push(fix | len as u8);
+    } else if let Ok(len) = u16::try_from(len) {
+        output.push(short);
+        output.extend_from_slice(&len.to_be_bytes());
+    } else {
+        output.push(long);
+        output.extend_from_slice(&(len as u32).to_be_bytes());
+    }
+}
+
+/// Encodes documents as XML.
+///
+/// The document is wrapped in a `<response>` element. Map entries become elements named
+/// by their key, or `<field name="...">` elements when the key isn't a valid XML name, and
+/// list elements become `<item>` elements. `Null` values are empty elements.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Xml;
+
+impl Encode for Xml {
+    fn content_type(&self) -> &'static str {
+        "application/xml"
+    }
+
+    fn encode(&self, value: &Value) -> Vec<u8> {
+        let mut output = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        xml_element("response", value, &mut output);
+        output.push('\n');
+        output.into_bytes()
+    }
+}
+
+fn xml_element(name: &str, value: &Value, output: &mut String) {
+    let (open, close) = if is_xml_name(name) {
+        (name.to_string(), name)
+    } else {
+        (format!("field name=\"{}\"", xml_escape(name)), "field")
+    };
+
+    if value.is_null() {
+        let _ = write!(output, "<{open}/>");
+        return;
+    }
+
+    let _ = write!(output, "<{open}>");
+
+    match value {
+        Value::List(values) => {
+            for value in values {
+                xml_element("item", value, output);
+            }
+        }
+        Value::Map(values) => {
+            for (key, value) in values {
+                xml_element(key, value, output);
+            }
+        }
+        value => output.push_str(&xml_escape(&value.to_string())),
+    }
+
+    let _ = write!(output, "</{close}>");
+}
+
+/// Returns `true` if `name` can be used as an element name as it is.
+fn is_xml_name(name: &str) -> bool {
+    let mut chars = name.chars();
+
+    chars
+        .next()
+        .is_some_and(|char| char.is_ascii_alphabetic() || char == '_')
+        && chars.all(|char| char.is_ascii_alphanumeric() || matches!(char, '_' | '-' | '.'))
+        && !name
+            .get(..3)
+            .is_some_and(|prefix| prefix.eq_ignore_ascii_case("xml"))
+}
+
+fn xml_escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+
+    for char in text.chars() {
+        match char {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            // Control characters other than whitespace are not allowed in XML 1.0.
+            char if char.is_control() && !matches!(char, '\t' | '\n' | '\r') => {
+                escaped.push('\u{FFFD}')
+            }
+            char => escaped.push(char),
+        }
+    }
+
+    escaped
+}
+
+/// The built-in encoders, selectable by name.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Encoding {
+    /// `Json`.
+    #[default]
+    Json,
+    /// `Csv`.
+    Csv,
+    /// `MessagePack`.
+    MessagePack,
+    /// `Xml`.
+    Xml,
+}
+
+impl Encoding {
+    /// Every built-in encoding.
+    pub const ALL: [Encoding; 4] = [
+        Encoding::Json,
+        Encoding::Csv,
+        Encoding::MessagePack,
+        Encoding::Xml,
+    ];
+
+    /// Returns the name the encoding is parsed from, e.g. `msgpack`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Encoding::Json => "json",
+            Encoding::Csv => "csv",
+            Encoding::MessagePack => "msgpack",
+            Encoding::Xml => "xml",
+        }
+    }
+
+    /// Returns `true` if the encoding produces text, which can be printed to a terminal.
+    pub fn is_text(self) -> bool {
+        self != Encoding::MessagePack
+    }
+}
+
+impl Encode for Encoding {
+    fn content_type(&self) -> &'static str {
+        match self {
+            Encoding::Json => Json.content_type(),
+            Encoding::Csv => Csv.content_type(),
+            Encoding::MessagePack => MessagePack.content_type(),
+            Encoding::Xml => Xml.content_type(),
+        }
+    }
+
+    fn encode(&self, value: &Value) -> Vec<u8> {
+        match self {
+            Encoding::Json => Json.encode(value),
+            Encoding::Csv => Csv.encode(value),
+            Encoding::MessagePack => MessagePack.encode(value),
+            Encoding::Xml => Xml.encode(value),
+        }
+    }
+}
+
+impl Display for Encoding {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Encoding {
+    type Err = ParseEncodingError;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        Encoding::ALL
+            .into_iter()
+            .find(|encoding| encoding.name().eq_ignore_ascii_case(name))
+            .ok_or_else(|| ParseEncodingError(name.to_string()))
+    }
+}
+
+/// The error returned when a string names no built-in encoding.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseEncodingError(String);
+
+impl Display for ParseEncodingError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(f, "`{}` is not a gstat encoding", self.0)
+    }
+}
+
+impl StdError for ParseEncodingError {}
@@ -1,20 +1,159 @@
+use crate::runtime::Elapsed;
+
 use std::{
     error::Error as StdError,
     fmt::{Debug, Display, Formatter, Result as FmtResult},
+    io,
 };
 
+/// `ErrorKind` describes what went wrong, independently of the category of an `Error`.
+///
+/// The category of an `Error` tells which standard failed, while the kind tells why, so
+/// callers can distinguish e.g. a timeout from a malformed packet. Every kind has a stable
+/// numeric code that is never reused or renumbered, for matching across versions and
+/// across language boundaries.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ErrorKind {
+    /// The cause of the error is not classified.
+    #[default]
+    Other,
+    /// An operation did not complete within its time limit.
+    Timeout,
+    /// The server actively refused the connection.
+    ConnectionRefused,
+    /// The server reset or closed the connection unexpectedly.
+    ConnectionReset,
+    /// A host name could not be resolved.
+    Dns,
+    /// The server or its network was unreachable.
+    Network,
+    /// Another I/O error occurred.
+    Io,
+    /// An operation required a connection, but none was established.
+    NotConnected,
+    /// A packet ended before all of its data could be read.
+    Truncated,
+    /// A packet was received but its contents are malformed.
+    InvalidPacket,
+    /// The server or the game does not support the requested operation.
+    Unsupported,
+    /// The operation was cancelled through a `CancellationToken`.
+    Cancelled,
+    /// Every address of the target is refused by a `Blocklist`.
+    Blocked,
+}
+
+impl ErrorKind {
+    /// Every kind, in the order of their codes.
+    pub const ALL: [ErrorKind; 13] = [
+        ErrorKind::Other,
+        ErrorKind::Timeout,
+        ErrorKind::ConnectionRefused,
+        ErrorKind::ConnectionReset,
+        ErrorKind::Dns,
+        ErrorKind::Network,
+        ErrorKind::Io,
+        ErrorKind::NotConnected,
+        ErrorKind::Truncated,
+        ErrorKind::InvalidPacket,
+        ErrorKind::Unsupported,
+        ErrorKind::Cancelled,
+        ErrorKind::Blocked,
+    ];
+
+    /// Returns the stable numeric code of the kind.
+    pub fn code(&self) -> u16 {
+        match self {
+            ErrorKind::Other => 0,
+            ErrorKind::Timeout => 1,
+            ErrorKind::ConnectionRefused => 2,
+            ErrorKind::ConnectionReset => 3,
+            ErrorKind::Dns => 4,
+            ErrorKind::Network => 5,
+            ErrorKind::Io => 6,
+            ErrorKind::NotConnected => 7,
+            ErrorKind::Truncated => 8,
+            ErrorKind::InvalidPacket => 9,
+            ErrorKind::Unsupported => 10,
+            ErrorKind::Cancelled => 11,
+            ErrorKind::Blocked => 12,
+        }
+    }
+
+    /// Returns the kind with the given stable numeric code, if there is one.
+    ///
+    /// # Parameters
+    ///
+    /// * `code`: A code previously returned by `ErrorKind::code`.
+    pub fn from_code(code: u16) -> Option<Self> {
+        Self::ALL.into_iter().find(|kind| kind.code() == code)
+    }
+
+    /// Returns a stable, human-readable name for the kind.
+    pub fn name(&self) -> &'static str {
+        match self {
+            ErrorKind::Other => "other",
+            ErrorKind::Timeout => "timeout",
+            ErrorKind::ConnectionRefused => "connection_refused",
+            ErrorKind::ConnectionReset => "connection_reset",
+            ErrorKind::Dns => "dns",
+            ErrorKind::Network => "network",
+            ErrorKind::Io => "io",
+            ErrorKind::NotConnected => "not_connected",
+            ErrorKind::Truncated => "truncated",
+            ErrorKind::InvalidPacket => "invalid_packet",
+            ErrorKind::Unsupported => "unsupported",
+            ErrorKind::Cancelled => "cancelled",
+            ErrorKind::Blocked => "blocked",
+        }
+    }
+
+    /// Classifies an I/O error.
+    ///
+    /// # Parameters
+    ///
+    /// * `err`: The I/O error to classify.
+    pub fn from_io(err: &io::Error) -> Self {
+        match err.kind() {
+            io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock => ErrorKind::Timeout,
+            io::ErrorKind::ConnectionRefused => ErrorKind::ConnectionRefused,
+            io::ErrorKind::ConnectionReset
+            | io::ErrorKind::ConnectionAborted
+            | io::ErrorKind::BrokenPipe => ErrorKind::ConnectionReset,
+            io::ErrorKind::NotConnected => ErrorKind::NotConnected,
+            io::ErrorKind::UnexpectedEof => ErrorKind::Truncated,
+            io::ErrorKind::InvalidData => ErrorKind::InvalidPacket,
+            io::ErrorKind::Unsupported => ErrorKind::Unsupported,
+            io::ErrorKind::AddrNotAvailable
+            | io::ErrorKind::HostUnreachable
+            | io::ErrorKind::NetworkUnreachable
+            | io::ErrorKind::NetworkDown => ErrorKind::Network,
+            _ => ErrorKind::Io,
+        }
+    }
+}
+
+impl Display for ErrorKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.write_str(self.name())
+    }
+}
+
 /// `ErrorDetail` is a structure that encapsulates an error message and its associated data.
 ///
 /// `E` is the type of the error data that can be associated with the error message.
 pub struct ErrorDetail<E> {
     /// The error message.
     message: String,
+    /// What went wrong.
+    kind: ErrorKind,
     /// The optional data associated with the error.
     inner: Option<E>,
 }
 
 impl<E> ErrorDetail<E> {
-    /// Creates a new `ErrorDetail` instance.
+    /// Creates a new `ErrorDetail` instance of kind `ErrorKind::Other`.
     ///
     /// # Parameters
     ///
@@ -23,10 +162,67 @@ impl<E> ErrorDetail<E> {
     pub fn new(message: &str, inner: Option<E>) -> Self {
         ErrorDetail {
             message: message.to_string(),
+            kind: ErrorKind::Other,
             inner,
         }
     }
 
+    /// Sets what went wrong.
+    ///
+    /// # Parameters
+    ///
+    /// * `kind`: The kind of the error.
+    pub fn with_kind(mut self, kind: ErrorKind) -> Self {
+        self.kind = kind;
+        self
+    }
+
+    /// Returns the error message.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// Returns what went wrong.
+    pub fn kind(&self) -> ErrorKind {
+        self.kind
+    }
+
+    /// Returns the data associated with the error, if any.
+    pub fn inner(&self) -> Option<&E> {
+        self.inner.as_ref()
+    }
+
+    /// Takes the data associated with the error, if any.
+    pub fn into_inner(self) -> Option<E> {
+        self.inner
+    }
+
+    /// Prefixes the error message with a description of what was being attempted.
+    ///
+    /// Contexts stack, so `detail.context("reading player list").context("querying server")`
+    /// produces the message `querying server: reading player list: <message>`.
+    ///
+    /// # Parameters
+    ///
+    /// * `context`: A description of the operation that failed.
+    pub fn context(mut self, context: impl Display) -> Self {
+        self.message = format!("{context}: {}", self.message);
+        self
+    }
+
+    /// Converts the associated error data into another type.
+    ///
+    /// # Parameters
+    ///
+    /// * `f`: The conversion applied to the associated data.
+    pub fn map<F>(self, f: impl FnOnce(E) -> F) -> ErrorDetail<F> {
+        ErrorDetail {
+            message: self.message,
+            kind: self.kind,
+            inner: self.inner.map(f),
+        }
+    }
+
     /// Formats the error message and its associated category for display.
     ///
     /// # Parameters
@@ -54,6 +250,74 @@ pub enum Error<E> {
     ResponseError(ErrorDetail<E>),
 }
 
+impl<E> Error<E> {
+    /// Returns the details of the error regardless of its category.
+    pub fn detail(&self) -> &ErrorDetail<E> {
+        match self {
+            Self::GameError(detail)
+            | Self::ParserError(detail)
+            | Self::ProtocolError(detail)
+            | Self::QueryError(detail)
+            | Self::ResponseError(detail) => detail,
+        }
+    }
+
+    /// Takes the details of the error regardless of its category.
+    pub fn into_detail(self) -> ErrorDetail<E> {
+        match self {
+            Self::GameError(detail)
+            | Self::ParserError(detail)
+            | Self::ProtocolError(detail)
+            | Self::QueryError(detail)
+            | Self::ResponseError(detail) => detail,
+        }
+    }
+
+    /// Returns what went wrong, regardless of the category of the error.
+    pub fn kind(&self) -> ErrorKind {
+        self.detail().kind
+    }
+
+    /// Returns the stable numeric code of the kind of the error.
+    pub fn code(&self) -> u16 {
+        self.kind().code()
+    }
+
+    /// Prefixes the error message with a description of what was being attempted, keeping
+    /// the category.
+    ///
+    /// # Parameters
+    ///
+    /// * `context`: A description of the operation that failed.
+    pub fn context(self, context: impl Display) -> Self {
+        match self {
+            Self::GameError(detail) => Self::GameError(detail.context(context)),
+            Self::ParserError(detail) => Self::ParserError(detail.context(context)),
+            Self::ProtocolError(detail) => Self::ProtocolError(detail.context(context)),
+            Self::QueryError(detail) => Self::QueryError(detail.context(context)),
+            Self::ResponseError(detail) => Self::ResponseError(detail.context(context)),
+        }
+    }
+
+    /// Converts the associated error data into another type, keeping the category.
+    ///
+    /// This is useful when an error produced by one standard (for example a `Parser`)
+    /// has to be returned from another one (for example a `Protocol`).
+    ///
+    /// # Parameters
+    ///
+    /// * `f`: The conversion applied to the associated data.
+    pub fn map<F>(self, f: impl FnOnce(E) -> F) -> Error<F> {
+        match self {
+            Self::GameError(detail) => Error::GameError(detail.map(f)),
+            Self::ParserError(detail) => Error::ParserError(detail.map(f)),
+            Self::ProtocolError(detail) => Error::ProtocolError(detail.map(f)),
+            Self::QueryError(detail) => Error::QueryError(detail.map(f)),
+            Self::ResponseError(detail) => Error::ResponseError(detail.map(f)),
+        }
+    }
+}
+
 impl<E: Debug> Display for Error<E> {
     /// Formats the error for display.
     fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
@@ -74,30 +338,35 @@ impl<E: Debug> Debug for Error<E> {
             Self::GameError(detail) => f
                 .debug_struct("GameError")
                 .field("message", &detail.message)
+                .field("kind", &detail.kind)
                 .field("inner", &detail.inner)
                 .finish(),
 
             Self::ParserError(detail) => f
                 .debug_struct("ParserError")
                 .field("message", &detail.message)
+                .field("kind", &detail.kind)
                 .field("inner", &detail.inner)
                 .finish(),
 
             Self::ProtocolError(detail) => f
                 .debug_struct("ProtocolError")
                 .field("message", &detail.message)
+                .field("kind", &detail.kind)
                 .field("inner", &detail.inner)
                 .finish(),
 
             Self::QueryError(detail) => f
                 .debug_struct("QueryError")
                 .field("message", &detail.message)
+                .field("kind", &detail.kind)
                 .field("inner", &detail.inner)
                 .finish(),
 
             Self::ResponseError(detail) => f
                 .debug_struct("ResponseError")
                 .field("message", &detail.message)
+                .field("kind", &detail.kind)
                 .field("inner", &detail.inner)
                 .finish(),
         }
@@ -105,4 +374,35 @@ impl<E: Debug> Debug for Error<E> {
 }
 
 /// Allows `Error` to be treated like a standard library error.
-impl<E: Debug + 'static> StdError for Error<E> {}
+///
+/// The associated error data, if any, is exposed as the source of the error.
+impl<E: StdError + 'static> StdError for Error<E> {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        self.detail()
+            .inner()
+            .map(|inner| inner as &(dyn StdError + 'static))
+    }
+}
+
+/// Converts an I/O error into a `ProtocolError` classified with `ErrorKind::from_io`.
+///
+/// This allows `?` to be used on I/O operations inside protocol implementations whose error
+/// type can be built from an `io::Error`.
+impl<E: From<io::Error>> From<io::Error> for Error<E> {
+    fn from(err: io::Error) -> Self {
+        let kind = ErrorKind::from_io(&err);
+
+        Error::ProtocolError(
+            ErrorDetail::new("I/O operation failed", Some(E::from(err))).with_kind(kind),
+        )
+    }
+}
+
+/// Converts an elapsed time limit into a `ProtocolError` of kind `ErrorKind::Timeout`.
+impl<E> From<Elapsed> for Error<E> {
+    fn from(_: Elapsed) -> Self {
+        Error::ProtocolError(
+            ErrorDetail::new("Operation timed out", None).with_kind(ErrorKind::Timeout),
+        )
+    }
+}
@@ -0,0 +1,133 @@
+//! Validators for conditional queries.
+//!
+//! Status widgets poll the same server every few seconds, and most of the time nothing
+//! changed in between. An `ETag` summarizes what a response reports, so a service caching
+//! responses can answer a poll carrying the previous tag with "not modified" instead of
+//! the full response.
+
+use crate::{
+    fingerprint::Fnv1a,
+    prelude::{Player, ServerInfo},
+};
+
+use std::{
+    error::Error as StdError,
+    fmt::{Display, Formatter, Result as FmtResult},
+    str::FromStr,
+};
+
+/// `ETag` is a strong validator of the data a server reported.
+///
+/// The tag is a hash of every field of the `ServerInfo` and of the players' names, scores
+/// and teams. Player durations and pings change on every query without anyone noticing,
+/// so they are left out; a tag only changes when a status widget would show something
+/// different.
+///
+/// Tags render like HTTP entity tags, e.g. `"5f0c8a1e93b2d447"`, and parse from that form,
+/// with or without quotes or a weak `W/` prefix. Like fingerprints, tags are stable across
+/// releases and platforms.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(transparent)
+)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct ETag(u64);
+
+impl ETag {
+    /// Computes the tag of a response.
+    ///
+    /// # Parameters
+    ///
+    /// * `info`: The normalized description of the server.
+    /// * `players`: The players the server reported.
+    pub fn of(info: &ServerInfo, players: &[Player]) -> Self {
+        let mut hasher = Fnv1a::new();
+
+        hasher.field(info.name.as_bytes());
+        hasher.field(info.map.as_bytes());
+        hasher.field(info.game.as_bytes());
+        hasher.field(&info.players.to_le_bytes());
+        hasher.field(&info.max_players.to_le_bytes());
+        hasher.field(&[u8::from(info.password)]);
+        hasher.field(info.access.as_str().as_bytes());
+        hasher.field(info.version.as_bytes());
+
+        for (key, value) in &info.extra {
+            hasher.field(key.as_bytes());
+            hasher.field(value.as_bytes());
+        }
+
+        for player in players {
+            hasher.field(player.name.as_bytes());
+            hasher.field(&player.score.unwrap_or(i64::MIN).to_le_bytes());
+            hasher.field(player.team.as_deref().unwrap_or_default().as_bytes());
+        }
+
+        ETag(hasher.finish())
+    }
+
+    /// Creates a tag from a previously computed value.
+    pub fn from_u64(value: u64) -> Self {
+        ETag(value)
+    }
+
+    /// Returns the tag as an integer.
+    pub fn as_u64(&self) -> u64 {
+        self.0
+    }
+
+    /// Returns `true` if the response is unchanged for a client that already holds one of
+    /// the tags in `if_none_match`.
+    ///
+    /// # Parameters
+    ///
+    /// * `if_none_match`: A comma-separated list of tags, as sent in an `If-None-Match`
+    ///   header or an `if_changed_since` parameter. `*` matches every tag, and entries that
+    ///   aren't tags are ignored.
+    pub fn matches_any(&self, if_none_match: &str) -> bool {
+        if_none_match.split(',').map(str::trim).any(|candidate| {
+            candidate == "*" || candidate.parse::<ETag>().is_ok_and(|tag| tag == *self)
+        })
+    }
+}
+
+impl Display for ETag {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(f, "\"{:016x}\"", self.0)
+    }
+}
+
+impl FromStr for ETag {
+    type Err = ParseETagError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let tag = s.trim();
+        let tag = tag.strip_prefix("W/").unwrap_or(tag);
+        let tag = tag
+            .strip_prefix('"')
+            .and_then(|tag| tag.strip_suffix('"'))
+            .unwrap_or(tag);
+
+        if tag.len() != 16 {
+            return Err(ParseETagError(s.to_string()));
+        }
+
+        u64::from_str_radix(tag, 16)
+            .map(ETag)
+            .map_err(|_| ParseETagError(s.to_string()))
+    }
+}
+
+/// The error returned when a string is not an `ETag`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseETagError(String);
+
+impl Display for ParseETagError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(f, "`{}` is not a gstat entity tag", self.0)
+    }
+}
+
+impl StdError for ParseETagError {}
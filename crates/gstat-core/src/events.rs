@@ -0,0 +1,167 @@
+use std::{
+    fmt::{Debug, Formatter, Result as FmtResult},
+    net::SocketAddr,
+    sync::Arc,
+};
+
+/// A step in the lifecycle of a connection and of the queries sent over it.
+///
+/// Connection changes are reported by persistent protocols. The progress of a query, from
+/// `ConnectStarted` through the packets exchanged to a `ParseFailed`, is reported by
+/// `Game::fetch_with`, `Handshake::perform_handshake` and the transports, so GUIs can show
+/// each query as it runs and tools can time its steps.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ConnectionEvent {
+    /// An attempt to query `peer` started connecting.
+    ConnectStarted {
+        /// The address of the server.
+        peer: SocketAddr,
+    },
+    /// A connection to `peer` was established for the first time.
+    Connected {
+        /// The address of the server.
+        peer: SocketAddr,
+    },
+    /// A connection to `peer` was established again after an earlier one ended.
+    Reconnected {
+        /// The address of the server.
+        peer: SocketAddr,
+    },
+    /// The server at `peer` rejected the credentials presented to it.
+    AuthFailed {
+        /// The address of the server.
+        peer: SocketAddr,
+        /// The error returned by the authentication attempt.
+        reason: String,
+    },
+    /// The server at `peer` closed the connection.
+    ClosedByPeer {
+        /// The address of the server.
+        peer: SocketAddr,
+    },
+    /// The connection to `peer` was closed locally.
+    Disconnected {
+        /// The address of the server.
+        peer: SocketAddr,
+    },
+    /// A packet was sent to `peer`.
+    PacketSent {
+        /// The address of the server.
+        peer: SocketAddr,
+        /// The length of the packet in bytes.
+        bytes: usize,
+    },
+    /// A packet was received from `peer`.
+    PacketReceived {
+        /// The address of the server.
+        peer: SocketAddr,
+        /// The length of the packet in bytes.
+        bytes: usize,
+    },
+    /// The server at `peer` answered the challenge request of a handshake.
+    ChallengeReceived {
+        /// The address of the server.
+        peer: SocketAddr,
+        /// Whether the server issued a challenge token, rather than answering without one.
+        required: bool,
+    },
+    /// The response of `peer` couldn't be parsed.
+    ParseFailed {
+        /// The address of the server.
+        peer: SocketAddr,
+        /// The error returned by the parser.
+        reason: String,
+    },
+}
+
+impl ConnectionEvent {
+    /// Returns the address of the server the event concerns.
+    pub fn peer(&self) -> SocketAddr {
+        match self {
+            Self::ConnectStarted { peer }
+            | Self::Connected { peer }
+            | Self::Reconnected { peer }
+            | Self::AuthFailed { peer, .. }
+            | Self::ClosedByPeer { peer }
+            | Self::Disconnected { peer }
+            | Self::PacketSent { peer, .. }
+            | Self::PacketReceived { peer, .. }
+            | Self::ChallengeReceived { peer, .. }
+            | Self::ParseFailed { peer, .. } => *peer,
+        }
+    }
+}
+
+/// A trait for receiving the connection events of a protocol.
+///
+/// Events are delivered synchronously from within the protocol, so implementations should
+/// return quickly, e.g. by updating a status table or forwarding the event to a channel.
+///
+/// The trait is implemented for closures taking a `&ConnectionEvent`.
+pub trait ConnectionSubscriber: Send + Sync {
+    /// Called for each connection event.
+    ///
+    /// # Parameters
+    ///
+    /// * `event`: The event that occurred.
+    fn on_event(&self, event: &ConnectionEvent);
+}
+
+impl<F> ConnectionSubscriber for F
+where
+    F: Fn(&ConnectionEvent) + Send + Sync,
+{
+    fn on_event(&self, event: &ConnectionEvent) {
+        self(event)
+    }
+}
+
+/// The subscribers of a protocol, notified in the order they were added.
+///
+/// Cloning `Subscribers` is cheap, and clones notify the same subscribers.
+#[derive(Clone, Default)]
+pub struct Subscribers {
+    subscribers: Vec<Arc<dyn ConnectionSubscriber>>,
+}
+
+impl Subscribers {
+    /// Creates an empty list of subscribers.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a subscriber.
+    ///
+    /// # Parameters
+    ///
+    /// * `subscriber`: The subscriber to notify of every later event.
+    pub fn add(&mut self, subscriber: impl ConnectionSubscriber + 'static) {
+        self.subscribers.push(Arc::new(subscriber));
+    }
+
+    /// Notifies every subscriber of `event`.
+    pub fn emit(&self, event: ConnectionEvent) {
+        for subscriber in &self.subscribers {
+            subscriber.on_event(&event);
+        }
+    }
+
+    /// Returns the number of subscribers.
+    pub fn len(&self) -> usize {
+        self.subscribers.len()
+    }
+
+    /// Returns `true` if there are no subscribers.
+    pub fn is_empty(&self) -> bool {
+        self.subscribers.is_empty()
+    }
+}
+
+impl Debug for Subscribers {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.debug_struct("Subscribers")
+            .field("len", &self.subscribers.len())
+            .finish()
+    }
+}
@@ -0,0 +1,130 @@
+use crate::prelude::ServerInfo;
+
+use std::fmt::{Display, Formatter, Result as FmtResult};
+
+/// The offset basis of the 64-bit FNV-1a hash.
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+
+/// The prime of the 64-bit FNV-1a hash.
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+/// `Fingerprint` identifies a server by what it reports rather than where it is hosted.
+///
+/// The fingerprint is a hash of the characteristics of a `ServerInfo` that rarely change
+/// over the lifetime of a server: the game, the shape of the name, the set of rule keys
+/// and the major and minor version. Values that fluctuate between queries, such as the
+/// player counts, the map or the rule values, are ignored, so trackers can keep following
+/// a server after it migrates to another host.
+///
+/// The hash is stable across releases and platforms, which makes fingerprints suitable
+/// for persisting.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(transparent)
+)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct Fingerprint(u64);
+
+impl Fingerprint {
+    /// Computes the fingerprint of a server.
+    ///
+    /// # Parameters
+    ///
+    /// * `info`: The normalized description of the server.
+    pub fn of(info: &ServerInfo) -> Self {
+        let mut hasher = Fnv1a::new();
+
+        hasher.field(info.game.trim().to_lowercase().as_bytes());
+        hasher.field(name_pattern(&info.name).as_bytes());
+        hasher.field(version_prefix(&info.version).as_bytes());
+
+        for key in info.extra.keys() {
+            hasher.field(key.as_bytes());
+        }
+
+        Fingerprint(hasher.finish())
+    }
+
+    /// Creates a fingerprint from a previously computed value.
+    pub fn from_u64(value: u64) -> Self {
+        Fingerprint(value)
+    }
+
+    /// Returns the fingerprint as an integer.
+    pub fn as_u64(&self) -> u64 {
+        self.0
+    }
+}
+
+impl Display for Fingerprint {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(f, "{:016x}", self.0)
+    }
+}
+
+impl ServerInfo {
+    /// Returns the `Fingerprint` of the server.
+    pub fn fingerprint(&self) -> Fingerprint {
+        Fingerprint::of(self)
+    }
+}
+
+/// A 64-bit FNV-1a hasher; unlike `DefaultHasher`, its output never changes.
+pub(crate) struct Fnv1a(u64);
+
+impl Fnv1a {
+    pub(crate) fn new() -> Self {
+        Fnv1a(FNV_OFFSET_BASIS)
+    }
+
+    /// Hashes a field followed by a separator, so adjacent fields cannot run together.
+    pub(crate) fn field(&mut self, bytes: &[u8]) {
+        for byte in bytes.iter().chain(&[0xff]) {
+            self.0 ^= u64::from(*byte);
+            self.0 = self.0.wrapping_mul(FNV_PRIME);
+        }
+    }
+
+    pub(crate) fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+/// Reduces a server name to its shape.
+///
+/// Names often embed counters, dates or slot counts (e.g. `"Rust #3 | wiped 12/04"`), so
+/// every run of digits becomes a single `#`, letters are lowercased and runs of whitespace
+/// collapse into a single space.
+fn name_pattern(name: &str) -> String {
+    let mut pattern = String::with_capacity(name.len());
+
+    for c in name.trim().chars() {
+        let c = if c.is_ascii_digit() {
+            '#'
+        } else if c.is_whitespace() {
+            ' '
+        } else {
+            c
+        };
+
+        if (c == '#' || c == ' ') && pattern.ends_with(c) {
+            continue;
+        }
+
+        pattern.extend(c.to_lowercase());
+    }
+
+    pattern
+}
+
+/// Returns the major and minor components of a version, which survive patch releases.
+fn version_prefix(version: &str) -> &str {
+    let version = version.trim();
+
+    match version.match_indices('.').nth(1) {
+        Some((index, _)) => &version[..index],
+        None => version,
+    }
+}
@@ -0,0 +1,472 @@
+//! Monitoring sets described in TOML files.
+//!
+//! A `Fleet` lists the servers to monitor with their games and intervals, the alert rules
+//! to evaluate on them and the sinks their state is sent to. The same file drives the
+//! library, through `Fleet::from_path`, and the binaries that accept a fleet file:
+//!
+//! ```toml
+//! interval = "30s"
+//! timeout = "5s"
+//! scripts = ["games/tf2.rhai"]
+//!
+//! [[target]]
+//! name = "main"
+//! game = "tf2"
+//! address = "play.example.com"
+//! port = 27015
+//! interval = "10s"
+//!
+//! [[alert]]
+//! kind = "down"
+//! polls = 3
+//!
+//! [[alert]]
+//! kind = "availability_below"
+//! ratio = 0.99
+//! window = "24h"
+//!
+//! [[sink]]
+//! kind = "webhook"
+//! url = "http://alerts.lan/gstat"
+//! format = "discord"
+//! targets = ["main"]
+//! ```
+//!
+//! `FleetWatcher` notices when the file changes and loads it again, so monitors pick up new
+//! servers and rules without restarting.
+
+use crate::{
+    duration::parse_duration,
+    notify::{EventKind, Format, Webhook},
+    resolve::HostPort,
+    runtime,
+    uptime::{Alert, UptimeMonitor},
+};
+
+use std::{
+    collections::HashSet,
+    error::Error as StdError,
+    fmt::{Display, Formatter, Result as FmtResult},
+    fs, io,
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::{Duration, SystemTime},
+};
+
+use serde::Deserialize;
+
+/// How often targets are polled when neither they nor the file set an interval.
+pub const DEFAULT_INTERVAL: Duration = Duration::from_secs(30);
+
+/// The limit applied to every step of a poll when the file sets none.
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// A server of a fleet.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FleetTarget {
+    /// The name of the server in alerts and sinks, its address as written by default.
+    pub name: String,
+    /// The game the server runs, as the identifier of a built-in or scripted game.
+    pub game: String,
+    /// The address of the server. Without a port, the game's default port is used.
+    pub address: HostPort,
+    /// How often the server is polled, overriding the interval of the fleet.
+    pub interval: Option<Duration>,
+}
+
+/// Where the state of a fleet is sent.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Sink {
+    /// Notifications posted to a webhook, naming servers by their `FleetTarget::name`.
+    Webhook(Webhook<String>),
+    /// Snapshots published to an MQTT broker.
+    Mqtt {
+        /// The broker, as `host` or `host:port`.
+        broker: String,
+        /// The first level of every topic.
+        prefix: Option<String>,
+    },
+    /// Records stored in a SQLite database.
+    Store {
+        /// The database file.
+        path: PathBuf,
+    },
+}
+
+/// `Fleet` describes a set of monitored servers.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Fleet {
+    /// How often servers without an interval of their own are polled.
+    pub interval: Duration,
+    /// The limit applied to every step of a poll.
+    pub timeout: Duration,
+    /// The game scripts to load. `Fleet::from_path` resolves them against the directory
+    /// of the file.
+    pub scripts: Vec<PathBuf>,
+    /// The servers to poll, with unique names.
+    pub targets: Vec<FleetTarget>,
+    /// The alerts evaluated on every server.
+    pub alerts: Vec<Alert>,
+    /// Where the state of the servers is sent.
+    pub sinks: Vec<Sink>,
+}
+
+impl Fleet {
+    /// Loads a fleet file.
+    ///
+    /// # Parameters
+    ///
+    /// * `path`: The path of the file.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing either the fleet or a `FleetError` if the file can't be read
+    /// or describes an invalid fleet.
+    pub fn from_path(path: impl AsRef<Path>) -> Result<Self, FleetError> {
+        let path = path.as_ref();
+        let mut fleet: Fleet = fs::read_to_string(path)?.parse()?;
+
+        let base = path.parent().unwrap_or(Path::new(""));
+        fleet.scripts = fleet
+            .scripts
+            .into_iter()
+            .map(|script| base.join(script))
+            .collect();
+
+        Ok(fleet)
+    }
+
+    /// Returns how often a server of the fleet is polled.
+    pub fn interval_of(&self, target: &FleetTarget) -> Duration {
+        target.interval.unwrap_or(self.interval)
+    }
+
+    /// Returns the server with a name.
+    pub fn target(&self, name: &str) -> Option<&FleetTarget> {
+        self.targets.iter().find(|target| target.name == name)
+    }
+
+    /// Creates an uptime monitor evaluating the alerts of the fleet, for snapshots whose
+    /// targets are the names of the servers.
+    pub fn monitor(&self) -> UptimeMonitor<String> {
+        self.alerts
+            .iter()
+            .fold(UptimeMonitor::new(), |monitor, alert| {
+                monitor.with_alert(*alert)
+            })
+    }
+
+    /// Returns the webhooks among the sinks.
+    pub fn webhooks(&self) -> impl Iterator<Item = &Webhook<String>> {
+        self.sinks.iter().filter_map(|sink| match sink {
+            Sink::Webhook(webhook) => Some(webhook),
+            _ => None,
+        })
+    }
+
+    /// Creates a watcher reloading the fleet file at `path` when it changes.
+    pub fn watch(path: impl Into<PathBuf>) -> FleetWatcher {
+        FleetWatcher::new(path)
+    }
+}
+
+impl FromStr for Fleet {
+    type Err = FleetError;
+
+    /// Parses a fleet file, leaving script paths as written.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let raw: RawFleet =
+            toml::from_str(s).map_err(|err| FleetError::Invalid(err.to_string()))?;
+
+        let targets = raw
+            .target
+            .into_iter()
+            .map(RawTarget::into_target)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut names = HashSet::new();
+
+        if let Some(target) = targets.iter().find(|target| !names.insert(&target.name)) {
+            return Err(FleetError::Invalid(format!(
+                "more than one target is named `{}`",
+                target.name
+            )));
+        }
+
+        let sinks = raw
+            .sink
+            .into_iter()
+            .map(|sink| sink.into_sink(&targets))
+            .collect::<Result<_, _>>()?;
+
+        Ok(Fleet {
+            interval: optional_duration(raw.interval)?.unwrap_or(DEFAULT_INTERVAL),
+            timeout: optional_duration(raw.timeout)?.unwrap_or(DEFAULT_TIMEOUT),
+            scripts: raw.scripts,
+            targets,
+            alerts: raw
+                .alert
+                .into_iter()
+                .map(RawAlert::into_alert)
+                .collect::<Result<_, _>>()?,
+            sinks,
+        })
+    }
+}
+
+/// An error loading a fleet file.
+#[derive(Debug)]
+pub enum FleetError {
+    /// The file could not be read.
+    Io(io::Error),
+    /// The file does not describe a valid fleet.
+    Invalid(String),
+}
+
+impl Display for FleetError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            FleetError::Io(err) => write!(f, "failed to read the fleet file: {err}"),
+            FleetError::Invalid(reason) => write!(f, "invalid fleet file: {reason}"),
+        }
+    }
+}
+
+impl StdError for FleetError {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        match self {
+            FleetError::Io(err) => Some(err),
+            FleetError::Invalid(_) => None,
+        }
+    }
+}
+
+impl From<io::Error> for FleetError {
+    fn from(err: io::Error) -> Self {
+        FleetError::Io(err)
+    }
+}
+
+/// `FleetWatcher` reloads a fleet file when it changes.
+///
+/// Changes are noticed by checking the modification time and size of the file on an
+/// interval, which works the same on every platform and file system.
+#[derive(Clone, Debug)]
+pub struct FleetWatcher {
+    path: PathBuf,
+    interval: Duration,
+    version: Option<(SystemTime, u64)>,
+}
+
+impl FleetWatcher {
+    /// Creates a watcher checking the file every second, from its current version.
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        let path = path.into();
+
+        FleetWatcher {
+            version: version(&path),
+            path,
+            interval: Duration::from_secs(1),
+        }
+    }
+
+    /// Sets how often the file is checked.
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Waits for the file to change and loads it.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing either the new fleet or the `FleetError` of loading it, e.g.
+    /// while the file is only partly written. Keep the previous fleet on errors; the next
+    /// change is reported again.
+    pub async fn changed(&mut self) -> Result<Fleet, FleetError> {
+        loop {
+            runtime::sleep(self.interval).await;
+
+            let current = version(&self.path);
+
+            if current.is_some() && current != self.version {
+                self.version = current;
+                return Fleet::from_path(&self.path);
+            }
+        }
+    }
+}
+
+/// Returns the modification time and size of a file, or `None` if it can't be read.
+fn version(path: &Path) -> Option<(SystemTime, u64)> {
+    let metadata = fs::metadata(path).ok()?;
+    Some((metadata.modified().ok()?, metadata.len()))
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawFleet {
+    interval: Option<String>,
+    timeout: Option<String>,
+    #[serde(default)]
+    scripts: Vec<PathBuf>,
+    #[serde(default)]
+    target: Vec<RawTarget>,
+    #[serde(default)]
+    alert: Vec<RawAlert>,
+    #[serde(default)]
+    sink: Vec<RawSink>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawTarget {
+    name: Option<String>,
+    game: String,
+    address: String,
+    port: Option<u16>,
+    interval: Option<String>,
+}
+
+impl RawTarget {
+    fn into_target(self) -> Result<FleetTarget, FleetError> {
+        let mut address: HostPort = self.address.parse().map_err(|err| {
+            FleetError::Invalid(format!("invalid address `{}`: {err}", self.address))
+        })?;
+
+        if self.port.is_some() {
+            address.port = self.port;
+        }
+
+        Ok(FleetTarget {
+            name: self.name.unwrap_or(self.address),
+            game: self.game,
+            address,
+            interval: optional_duration(self.interval)?,
+        })
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case", deny_unknown_fields)]
+enum RawAlert {
+    Down {
+        polls: u32,
+    },
+    PlayersBelow {
+        players: u32,
+        #[serde(default = "one")]
+        polls: u32,
+    },
+    AvailabilityBelow {
+        ratio: f64,
+        window: String,
+    },
+}
+
+fn one() -> u32 {
+    1
+}
+
+impl RawAlert {
+    fn into_alert(self) -> Result<Alert, FleetError> {
+        Ok(match self {
+            RawAlert::Down { polls } => Alert::Down { polls },
+            RawAlert::PlayersBelow { players, polls } => Alert::PlayersBelow { players, polls },
+            RawAlert::AvailabilityBelow { ratio, window } => Alert::AvailabilityBelow {
+                ratio,
+                window: duration(&window)?,
+            },
+        })
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case", deny_unknown_fields)]
+enum RawSink {
+    Webhook {
+        url: String,
+        #[serde(default)]
+        format: RawFormat,
+        targets: Option<Vec<String>>,
+        events: Option<Vec<RawEvent>>,
+    },
+    Mqtt {
+        broker: String,
+        prefix: Option<String>,
+    },
+    Store {
+        path: PathBuf,
+    },
+}
+
+impl RawSink {
+    fn into_sink(self, targets: &[FleetTarget]) -> Result<Sink, FleetError> {
+        Ok(match self {
+            RawSink::Webhook {
+                url,
+                format,
+                targets: names,
+                events,
+            } => {
+                let mut webhook = Webhook::new(url).with_format(match format {
+                    RawFormat::Json => Format::Json,
+                    RawFormat::Discord => Format::Discord,
+                    RawFormat::Slack => Format::Slack,
+                });
+
+                if let Some(names) = names {
+                    if let Some(name) = names
+                        .iter()
+                        .find(|name| targets.iter().all(|target| &target.name != *name))
+                    {
+                        return Err(FleetError::Invalid(format!(
+                            "a webhook names the unknown target `{name}`"
+                        )));
+                    }
+
+                    webhook = webhook.with_targets(names);
+                }
+
+                if let Some(events) = events {
+                    webhook = webhook.with_kinds(events.into_iter().map(|event| match event {
+                        RawEvent::Online => EventKind::Online,
+                        RawEvent::Offline => EventKind::Offline,
+                        RawEvent::PlayersChanged => EventKind::PlayersChanged,
+                        RawEvent::MapChanged => EventKind::MapChanged,
+                    }));
+                }
+
+                Sink::Webhook(webhook)
+            }
+            RawSink::Mqtt { broker, prefix } => Sink::Mqtt { broker, prefix },
+            RawSink::Store { path } => Sink::Store { path },
+        })
+    }
+}
+
+#[derive(Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum RawFormat {
+    #[default]
+    Json,
+    Discord,
+    Slack,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum RawEvent {
+    Online,
+    Offline,
+    PlayersChanged,
+    MapChanged,
+}
+
+fn optional_duration(input: Option<String>) -> Result<Option<Duration>, FleetError> {
+    input.as_deref().map(duration).transpose()
+}
+
+fn duration(input: &str) -> Result<Duration, FleetError> {
+    parse_duration(input).map_err(FleetError::Invalid)
+}
@@ -0,0 +1,218 @@
+//! Runtime dispatch from user-provided names to game implementations.
+
+use crate::{registry::GameEntry, standards::dyn_game::DynGame};
+
+use std::{
+    collections::HashMap,
+    error::Error as StdError,
+    fmt::{Debug, Display, Formatter, Result as FmtResult},
+};
+
+/// A game registered in a `GameRegistry`, with the identifiers it can be looked up by.
+pub struct RegisteredGame {
+    id: String,
+    aliases: Vec<String>,
+    appids: Vec<u32>,
+    game: Box<dyn DynGame>,
+}
+
+impl RegisteredGame {
+    /// Returns the unique game identifier.
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// Returns the alternative identifiers the game can be looked up by.
+    pub fn aliases(&self) -> &[String] {
+        &self.aliases
+    }
+
+    /// Returns the Steam AppIDs the game can be looked up by.
+    pub fn appids(&self) -> &[u32] {
+        &self.appids
+    }
+
+    /// Returns the game.
+    pub fn game(&self) -> &dyn DynGame {
+        self.game.as_ref()
+    }
+}
+
+impl Debug for RegisteredGame {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.debug_struct("RegisteredGame")
+            .field("id", &self.id)
+            .field("aliases", &self.aliases)
+            .field("appids", &self.appids)
+            .field("name", &self.game.dyn_name())
+            .finish()
+    }
+}
+
+/// `GameRegistry` maps identifiers such as `"tf2"` or `"minecraft"` to game
+/// implementations.
+///
+/// CLIs and servers register the games they support once and then dispatch on the names
+/// users provide. Lookups ignore ASCII case and also match aliases, like `registry::game`.
+/// Games can also be looked up by the Steam AppID a server reports, e.g. in `A2S_INFO` or a
+/// master server listing, to query a server without knowing its game up front.
+/// Games are stored as `Box<dyn DynGame>`; wrap a `Game` in `ErasedGame` to register it.
+#[derive(Debug, Default)]
+pub struct GameRegistry {
+    games: Vec<RegisteredGame>,
+    index: HashMap<String, usize>,
+    appids: HashMap<u32, usize>,
+}
+
+impl GameRegistry {
+    /// Creates an empty registry.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers a game under the identifier, aliases and Steam AppIDs of its metadata.
+    ///
+    /// # Parameters
+    ///
+    /// * `entry`: The registry entry of the game.
+    /// * `game`: The game implementation.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` which is `Ok` once the game was registered, or a `DuplicateGame` if an
+    /// identifier or AppID is already taken, in which case the registry is left unchanged.
+    pub fn register_entry(
+        &mut self,
+        entry: &GameEntry,
+        game: impl DynGame + 'static,
+    ) -> Result<(), DuplicateGame> {
+        self.register_with_appids(entry.id, entry.aliases, entry.steam_appids, game)
+    }
+
+    /// Registers a game.
+    ///
+    /// # Parameters
+    ///
+    /// * `id`: The unique game identifier.
+    /// * `aliases`: Alternative identifiers the game can be looked up by.
+    /// * `game`: The game implementation.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` which is `Ok` once the game was registered, or a `DuplicateGame` if an
+    /// identifier is already taken, in which case the registry is left unchanged.
+    pub fn register<S: AsRef<str>>(
+        &mut self,
+        id: &str,
+        aliases: &[S],
+        game: impl DynGame + 'static,
+    ) -> Result<(), DuplicateGame> {
+        self.register_with_appids(id, aliases, &[], game)
+    }
+
+    /// Registers a game that can also be looked up by Steam AppID.
+    ///
+    /// # Parameters
+    ///
+    /// * `id`: The unique game identifier.
+    /// * `aliases`: Alternative identifiers the game can be looked up by.
+    /// * `appids`: The Steam AppIDs reported by the game's servers.
+    /// * `game`: The game implementation.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` which is `Ok` once the game was registered, or a `DuplicateGame` if an
+    /// identifier or AppID is already taken, in which case the registry is left unchanged.
+    pub fn register_with_appids<S: AsRef<str>>(
+        &mut self,
+        id: &str,
+        aliases: &[S],
+        appids: &[u32],
+        game: impl DynGame + 'static,
+    ) -> Result<(), DuplicateGame> {
+        let aliases: Vec<String> = aliases.iter().map(|alias| alias.as_ref().into()).collect();
+        let keys: Vec<String> = std::iter::once(id)
+            .chain(aliases.iter().map(String::as_str))
+            .map(str::to_ascii_lowercase)
+            .collect();
+
+        for (position, key) in keys.iter().enumerate() {
+            if self.index.contains_key(key) || keys[..position].contains(key) {
+                return Err(DuplicateGame {
+                    identifier: key.clone(),
+                });
+            }
+        }
+
+        for (position, appid) in appids.iter().enumerate() {
+            if self.appids.contains_key(appid) || appids[..position].contains(appid) {
+                return Err(DuplicateGame {
+                    identifier: format!("appid {appid}"),
+                });
+            }
+        }
+
+        let position = self.games.len();
+        self.index
+            .extend(keys.into_iter().map(|key| (key, position)));
+        self.appids
+            .extend(appids.iter().map(|&appid| (appid, position)));
+        self.games.push(RegisteredGame {
+            id: id.into(),
+            aliases,
+            appids: appids.to_vec(),
+            game: Box::new(game),
+        });
+
+        Ok(())
+    }
+
+    /// Looks up a game by its identifier or one of its aliases, ignoring ASCII case.
+    pub fn get(&self, identifier: &str) -> Option<&RegisteredGame> {
+        self.index
+            .get(&identifier.to_ascii_lowercase())
+            .map(|&position| &self.games[position])
+    }
+
+    /// Looks up the game whose servers report the Steam AppID `appid`.
+    pub fn get_by_appid(&self, appid: u32) -> Option<&RegisteredGame> {
+        self.appids
+            .get(&appid)
+            .map(|&position| &self.games[position])
+    }
+
+    /// Returns `true` if a game is registered under `identifier`.
+    pub fn contains(&self, identifier: &str) -> bool {
+        self.get(identifier).is_some()
+    }
+
+    /// Returns the registered games, in the order they were registered.
+    pub fn iter(&self) -> impl Iterator<Item = &RegisteredGame> {
+        self.games.iter()
+    }
+
+    /// Returns the number of registered games.
+    pub fn len(&self) -> usize {
+        self.games.len()
+    }
+
+    /// Returns `true` if no games are registered.
+    pub fn is_empty(&self) -> bool {
+        self.games.is_empty()
+    }
+}
+
+/// An identifier or AppID that is already taken by another game of a `GameRegistry`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DuplicateGame {
+    /// The identifier in lowercase, or `appid` followed by the AppID.
+    pub identifier: String,
+}
+
+impl Display for DuplicateGame {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(f, "A game is already registered as {:?}", self.identifier)
+    }
+}
+
+impl StdError for DuplicateGame {}
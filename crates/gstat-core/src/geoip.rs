@@ -0,0 +1,213 @@
+//! Locating servers with MaxMind databases.
+//!
+//! With the `geoip` feature, a `GeoIp` built from one or more MaxMind DB files, such as
+//! GeoLite2 City and GeoLite2 ASN, looks up the country, city and autonomous system of an
+//! address. `GeoIp::enrich` attaches the location of the server to the `ResponseMeta` of a
+//! fetched response, and `GeoIp::enrich_all` does so for the results of `query_many`, so
+//! server lists can show where servers are without a lookup service of their own.
+//! Databases are read into memory once; lookups don't touch the file system.
+
+use crate::standards::game::{FetchResult, Fetched};
+
+use std::{
+    error::Error as StdError,
+    fmt::{Debug, Display, Formatter, Result as FmtResult},
+    fs, io,
+    net::IpAddr,
+    path::Path,
+};
+
+use maxminddb::{
+    geoip2::{Asn, City},
+    Reader,
+};
+
+/// The language of the country and city names.
+const LANGUAGE: &str = "en";
+
+/// An error that occurred while loading a MaxMind database.
+#[derive(Debug)]
+pub enum GeoIpError {
+    /// The file could not be read.
+    Io(io::Error),
+    /// The data is not a MaxMind database.
+    Invalid(String),
+}
+
+impl Display for GeoIpError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            GeoIpError::Io(err) => write!(f, "Failed to read GeoIP database: {err}"),
+            GeoIpError::Invalid(message) => write!(f, "Invalid GeoIP database: {message}"),
+        }
+    }
+}
+
+impl StdError for GeoIpError {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        match self {
+            GeoIpError::Io(err) => Some(err),
+            GeoIpError::Invalid(_) => None,
+        }
+    }
+}
+
+impl From<io::Error> for GeoIpError {
+    fn from(err: io::Error) -> Self {
+        GeoIpError::Io(err)
+    }
+}
+
+/// Where an address is, as far as the databases of a `GeoIp` know.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Location {
+    /// The ISO 3166-1 alpha-2 code of the country, e.g. `DE`.
+    pub country_code: Option<String>,
+    /// The English name of the country.
+    pub country: Option<String>,
+    /// The English name of the city.
+    pub city: Option<String>,
+    /// The number of the autonomous system announcing the address.
+    pub asn: Option<u32>,
+    /// The organization operating the autonomous system.
+    pub as_organization: Option<String>,
+}
+
+impl Location {
+    fn is_empty(&self) -> bool {
+        *self == Location::default()
+    }
+
+    /// Fills the fields this location lacks from `other`.
+    fn merge(&mut self, other: Location) {
+        self.country_code = self.country_code.take().or(other.country_code);
+        self.country = self.country.take().or(other.country);
+        self.city = self.city.take().or(other.city);
+        self.asn = self.asn.or(other.asn);
+        self.as_organization = self.as_organization.take().or(other.as_organization);
+    }
+}
+
+/// A set of MaxMind databases to look addresses up in.
+pub struct GeoIp {
+    databases: Vec<Reader<Vec<u8>>>,
+}
+
+impl GeoIp {
+    /// Loads a MaxMind database file.
+    ///
+    /// # Parameters
+    ///
+    /// * `path`: The `.mmdb` file, e.g. GeoLite2 City or GeoLite2 ASN.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, GeoIpError> {
+        Self::from_bytes(fs::read(path)?)
+    }
+
+    /// Loads a MaxMind database from memory.
+    ///
+    /// # Parameters
+    ///
+    /// * `data`: The contents of a `.mmdb` file.
+    pub fn from_bytes(data: Vec<u8>) -> Result<Self, GeoIpError> {
+        Ok(GeoIp {
+            databases: vec![read(data)?],
+        })
+    }
+
+    /// Loads another database file, consulted for the fields the previous ones lack.
+    ///
+    /// # Parameters
+    ///
+    /// * `path`: The `.mmdb` file, e.g. GeoLite2 ASN next to GeoLite2 City.
+    pub fn with_database(mut self, path: impl AsRef<Path>) -> Result<Self, GeoIpError> {
+        self.databases.push(read(fs::read(path)?)?);
+        Ok(self)
+    }
+
+    /// Looks an address up in every database.
+    ///
+    /// # Returns
+    ///
+    /// The location of `ip`, or `None` if no database knows anything about it.
+    pub fn lookup(&self, ip: IpAddr) -> Option<Location> {
+        let mut location = Location::default();
+
+        for database in &self.databases {
+            if let Ok(city) = database.lookup::<City>(ip) {
+                let country = city.country.as_ref();
+
+                location.merge(Location {
+                    country_code: country.and_then(|country| country.iso_code).map(Into::into),
+                    country: country
+                        .and_then(|country| country.names.as_ref())
+                        .and_then(|names| names.get(LANGUAGE))
+                        .map(|&name| name.into()),
+                    city: city
+                        .city
+                        .as_ref()
+                        .and_then(|city| city.names.as_ref())
+                        .and_then(|names| names.get(LANGUAGE))
+                        .map(|&name| name.into()),
+                    ..Location::default()
+                });
+            }
+
+            if let Ok(asn) = database.lookup::<Asn>(ip) {
+                location.merge(Location {
+                    asn: asn.autonomous_system_number,
+                    as_organization: asn.autonomous_system_organization.map(Into::into),
+                    ..Location::default()
+                });
+            }
+        }
+
+        (!location.is_empty()).then_some(location)
+    }
+
+    /// Attaches the location of the server to a fetched response.
+    ///
+    /// Nothing is attached if the response doesn't know the server's address, as with
+    /// responses built by hand, or if no database knows the address.
+    pub fn enrich<R, E>(&self, fetched: &mut Fetched<R, E>) {
+        if let Some(location) = fetched
+            .meta
+            .address()
+            .and_then(|address| self.lookup(address.ip()))
+        {
+            fetched.meta.set_location(location);
+        }
+    }
+
+    /// Attaches the location of the servers to the successful results of a bulk query.
+    ///
+    /// # Parameters
+    ///
+    /// * `results`: The results, e.g. collected from `query_many`.
+    pub fn enrich_all<T, R, E>(&self, results: &mut [(T, FetchResult<R, E>)]) {
+        for (_, result) in results {
+            if let Ok(fetched) = result {
+                self.enrich(fetched);
+            }
+        }
+    }
+}
+
+impl Debug for GeoIp {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.debug_struct("GeoIp")
+            .field(
+                "databases",
+                &self
+                    .databases
+                    .iter()
+                    .map(|database| &database.metadata.database_type)
+                    .collect::<Vec<_>>(),
+            )
+            .finish()
+    }
+}
+
+fn read(data: Vec<u8>) -> Result<Reader<Vec<u8>>, GeoIpError> {
+    Reader::from_source(data).map_err(|err| GeoIpError::Invalid(err.to_string()))
+}
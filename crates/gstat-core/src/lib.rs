@@ -1,10 +1,85 @@
+#[macro_use]
+mod trace;
+
+pub mod address;
+#[cfg(feature = "badge")]
+pub mod badge;
+pub mod blocklist;
+pub mod bulk;
+pub mod byte_str;
+pub mod cache;
+pub mod cancel;
+pub mod challenge;
+pub mod clock;
+pub mod codec;
+#[cfg(feature = "compat-gamedig")]
+pub mod compat;
+pub mod detect;
+pub mod discovery;
+pub mod document;
+pub mod duration;
+pub mod encode;
 pub mod error;
+pub mod etag;
+pub mod events;
+pub mod fingerprint;
+#[cfg(feature = "fleet")]
+pub mod fleet;
+pub mod game_registry;
+#[cfg(feature = "geoip")]
+pub mod geoip;
+pub mod memory;
+pub mod metrics;
+pub mod models;
+pub mod notify;
+#[cfg(feature = "otel")]
+pub mod otel;
+pub mod ping;
+pub mod poller;
+pub mod rate_limit;
+pub mod reassembly;
+pub mod registry;
+pub mod resolve;
+pub mod retry;
+pub mod runtime;
+pub mod schedule;
+#[cfg(feature = "schemars")]
+pub mod schema;
+#[cfg(feature = "script-rhai")]
+pub mod script;
+#[cfg(feature = "tower")]
+pub mod service;
+pub mod slp;
 pub mod standards;
+pub mod subscribe;
+pub mod timeout;
+pub mod uptime;
+pub use bytes;
+#[cfg(feature = "derive")]
+pub use gstat_derive::{GstatQuery, GstatResponse};
+#[cfg(feature = "schemars")]
+pub use schemars;
+
 pub mod prelude {
-    pub use crate::error::{Error, ErrorDetail};
-    pub use crate::standards::game::Game;
+    pub use crate::address::ServerAddress;
+    pub use crate::error::{Error, ErrorDetail, ErrorKind};
+    pub use crate::fingerprint::Fingerprint;
+    pub use crate::models::{
+        access::AccessPolicy, player::Player, provenance::Provenance, server_info::ServerInfo,
+        value::Value,
+    };
+    pub use crate::retry::RetryPolicy;
+    pub use crate::standards::authenticate::Authenticate;
+    pub use crate::standards::connection::ConnectionGuard;
+    pub use crate::standards::correlation::Correlated;
+    pub use crate::standards::game::{Fetched, Game};
+    pub use crate::standards::handshake::Handshake;
+    pub use crate::standards::link::Link;
     pub use crate::standards::parser::Parser;
     pub use crate::standards::protocol::Protocol;
-    pub use crate::standards::query::Query;
-    pub use crate::standards::response::Response;
+    pub use crate::standards::query::{Query, QueryOptions};
+    pub use crate::standards::raw_transport::RawTransport;
+    pub use crate::standards::response::{Response, ResponseMeta};
+    pub use crate::standards::session::Session;
+    pub use crate::timeout::TimeoutSettings;
 }
@@ -0,0 +1,380 @@
+//! Approximate memory accounting for long-running consumers.
+//!
+//! Aggregators that keep responses for many servers need their memory use to be
+//! predictable. `HeapSize` estimates how much heap memory a value owns, `MemoryBudget`
+//! adds those estimates up per subsystem against an optional global limit, and `LruStore`
+//! keeps values within that limit by evicting the least recently used ones.
+
+use crate::{
+    byte_str::ByteStr,
+    prelude::{Player, Provenance, ResponseMeta, ServerInfo},
+};
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    hash::Hash,
+    mem::{size_of, size_of_val},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use bytes::Bytes;
+
+/// A trait for estimating the heap memory owned by a value.
+///
+/// Estimates count the bytes allocated for the value's own data, not the allocator's
+/// bookkeeping, so they are lower bounds that are good enough for budgeting.
+pub trait HeapSize {
+    /// Returns the approximate number of heap bytes owned by `self`.
+    fn heap_size(&self) -> usize;
+}
+
+/// Returns the approximate memory used by `value`, inline and on the heap.
+pub fn total_size<T: HeapSize>(value: &T) -> usize {
+    size_of::<T>() + value.heap_size()
+}
+
+macro_rules! no_heap {
+    ($($ty:ty),*) => {
+        $(
+            impl HeapSize for $ty {
+                fn heap_size(&self) -> usize {
+                    0
+                }
+            }
+        )*
+    };
+}
+
+no_heap!(bool, u8, u16, u32, u64, usize, i8, i16, i32, i64, isize, f32, f64, Duration);
+
+impl HeapSize for String {
+    fn heap_size(&self) -> usize {
+        self.capacity()
+    }
+}
+
+/// Counts the length of the slice, although the memory may be shared with other handles.
+impl HeapSize for Bytes {
+    fn heap_size(&self) -> usize {
+        self.len()
+    }
+}
+
+impl HeapSize for ByteStr {
+    fn heap_size(&self) -> usize {
+        self.len()
+    }
+}
+
+impl<T: HeapSize> HeapSize for Option<T> {
+    fn heap_size(&self) -> usize {
+        self.as_ref().map_or(0, HeapSize::heap_size)
+    }
+}
+
+impl<T: HeapSize> HeapSize for Vec<T> {
+    fn heap_size(&self) -> usize {
+        self.capacity() * size_of::<T>() + self.iter().map(HeapSize::heap_size).sum::<usize>()
+    }
+}
+
+impl<K: HeapSize, V: HeapSize> HeapSize for BTreeMap<K, V> {
+    fn heap_size(&self) -> usize {
+        self.iter()
+            .map(|(key, value)| total_size(key) + total_size(value))
+            .sum()
+    }
+}
+
+impl HeapSize for ServerInfo {
+    fn heap_size(&self) -> usize {
+        self.name.heap_size()
+            + self.map.heap_size()
+            + self.game.heap_size()
+            + self.version.heap_size()
+            + self.extra.heap_size()
+    }
+}
+
+impl HeapSize for Player {
+    fn heap_size(&self) -> usize {
+        self.name.heap_size() + self.team.heap_size() + self.extra.heap_size()
+    }
+}
+
+impl HeapSize for Provenance {
+    fn heap_size(&self) -> usize {
+        self.iter()
+            .map(|(field, source)| 2 * size_of::<String>() + field.len() + source.len())
+            .sum()
+    }
+}
+
+impl HeapSize for ResponseMeta {
+    fn heap_size(&self) -> usize {
+        size_of_val(self.raw()) + self.bytes_received()
+    }
+}
+
+/// `MemoryBudget` tracks the approximate memory held by each subsystem, such as a cache or
+/// a monitor, against an optional global limit.
+///
+/// The budget is cheap to clone, and clones share their accounting, so one budget can be
+/// handed to every subsystem of an application. Subsystems charge what they hold through an
+/// `LruStore` or by calling `charge` and `release` themselves.
+#[derive(Clone, Debug, Default)]
+pub struct MemoryBudget {
+    limit: Option<usize>,
+    usage: Arc<Mutex<BTreeMap<&'static str, usize>>>,
+}
+
+impl MemoryBudget {
+    /// Creates a budget that only tracks usage.
+    pub fn unlimited() -> Self {
+        Self::default()
+    }
+
+    /// Creates a budget whose subsystems together hold at most `limit` bytes.
+    ///
+    /// # Parameters
+    ///
+    /// * `limit`: The largest number of bytes held across all subsystems.
+    pub fn with_limit(limit: usize) -> Self {
+        MemoryBudget {
+            limit: Some(limit),
+            ..Self::default()
+        }
+    }
+
+    /// Returns the limit, if any.
+    pub fn limit(&self) -> Option<usize> {
+        self.limit
+    }
+
+    /// Records that `subsystem` holds `bytes` more.
+    ///
+    /// # Parameters
+    ///
+    /// * `subsystem`: The name the usage is reported under.
+    /// * `bytes`: The number of bytes.
+    pub fn charge(&self, subsystem: &'static str, bytes: usize) {
+        *self
+            .usage
+            .lock()
+            .expect("budget lock poisoned")
+            .entry(subsystem)
+            .or_default() += bytes;
+    }
+
+    /// Records that `subsystem` released `bytes`.
+    ///
+    /// # Parameters
+    ///
+    /// * `subsystem`: The name the usage is reported under.
+    /// * `bytes`: The number of bytes.
+    pub fn release(&self, subsystem: &'static str, bytes: usize) {
+        if let Some(used) = self
+            .usage
+            .lock()
+            .expect("budget lock poisoned")
+            .get_mut(subsystem)
+        {
+            *used = used.saturating_sub(bytes);
+        }
+    }
+
+    /// Returns the number of bytes held across all subsystems.
+    pub fn used(&self) -> usize {
+        self.usage
+            .lock()
+            .expect("budget lock poisoned")
+            .values()
+            .sum()
+    }
+
+    /// Returns the number of bytes held by `subsystem`.
+    pub fn used_by(&self, subsystem: &str) -> usize {
+        self.usage
+            .lock()
+            .expect("budget lock poisoned")
+            .get(subsystem)
+            .copied()
+            .unwrap_or(0)
+    }
+
+    /// Returns the number of bytes held by each subsystem, e.g. for reporting as metrics.
+    pub fn usage(&self) -> BTreeMap<&'static str, usize> {
+        self.usage.lock().expect("budget lock poisoned").clone()
+    }
+
+    /// Returns `true` if the subsystems hold more than the limit.
+    pub fn is_exceeded(&self) -> bool {
+        self.limit.is_some_and(|limit| self.used() > limit)
+    }
+}
+
+/// An entry of an `LruStore`.
+#[derive(Debug)]
+struct Slot<V> {
+    value: V,
+    size: usize,
+    used_at: u64,
+}
+
+/// `LruStore` is a map whose entries are charged to a `MemoryBudget`.
+///
+/// Whenever the budget is exceeded after an insertion, the store evicts its least recently
+/// used entries until the budget is met again or the store is empty. A value larger than
+/// the whole budget is therefore not kept. Entries are charged at their `total_size` when
+/// inserted and released when removed, evicted or dropped with the store.
+#[derive(Debug)]
+pub struct LruStore<K, V> {
+    budget: MemoryBudget,
+    subsystem: &'static str,
+    entries: HashMap<K, Slot<V>>,
+    recency: BTreeMap<u64, K>,
+    clock: u64,
+    evictions: u64,
+}
+
+impl<K, V> LruStore<K, V>
+where
+    K: Clone + Eq + Hash,
+    V: HeapSize,
+{
+    /// Creates an empty store.
+    ///
+    /// # Parameters
+    ///
+    /// * `budget`: The budget entries are charged to.
+    /// * `subsystem`: The name the store's usage is reported under.
+    pub fn new(budget: MemoryBudget, subsystem: &'static str) -> Self {
+        LruStore {
+            budget,
+            subsystem,
+            entries: HashMap::new(),
+            recency: BTreeMap::new(),
+            clock: 0,
+            evictions: 0,
+        }
+    }
+
+    /// Inserts a value, evicting the least recently used entries if the budget is exceeded.
+    ///
+    /// # Parameters
+    ///
+    /// * `key`: The key of the value.
+    /// * `value`: The value.
+    ///
+    /// # Returns
+    ///
+    /// The value previously stored under `key`, if any.
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        let previous = self.remove(&key);
+        let size = total_size(&value);
+        let used_at = self.tick();
+
+        self.budget.charge(self.subsystem, size);
+        self.recency.insert(used_at, key.clone());
+        self.entries.insert(
+            key,
+            Slot {
+                value,
+                size,
+                used_at,
+            },
+        );
+
+        while self.budget.is_exceeded() && self.evict_oldest() {}
+
+        previous
+    }
+
+    /// Returns the value stored under `key` and marks it as recently used.
+    pub fn get(&mut self, key: &K) -> Option<&V> {
+        let used_at = self.tick();
+        let slot = self.entries.get_mut(key)?;
+
+        let key = self
+            .recency
+            .remove(&slot.used_at)
+            .expect("entries are tracked by recency");
+        self.recency.insert(used_at, key);
+        slot.used_at = used_at;
+
+        Some(&slot.value)
+    }
+
+    /// Returns the value stored under `key` without marking it as recently used.
+    pub fn peek(&self, key: &K) -> Option<&V> {
+        self.entries.get(key).map(|slot| &slot.value)
+    }
+
+    /// Removes the value stored under `key`.
+    pub fn remove(&mut self, key: &K) -> Option<V> {
+        let slot = self.entries.remove(key)?;
+
+        self.recency.remove(&slot.used_at);
+        self.budget.release(self.subsystem, slot.size);
+
+        Some(slot.value)
+    }
+
+    /// Removes every entry.
+    pub fn clear(&mut self) {
+        let size = self.size();
+
+        self.entries.clear();
+        self.recency.clear();
+        self.budget.release(self.subsystem, size);
+    }
+
+    /// Returns the number of entries.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns `true` if the store has no entries.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Returns the number of bytes charged for the entries.
+    pub fn size(&self) -> usize {
+        self.entries.values().map(|slot| slot.size).sum()
+    }
+
+    /// Returns the number of entries evicted to meet the budget so far.
+    pub fn evictions(&self) -> u64 {
+        self.evictions
+    }
+
+    /// Returns the next point in the store's usage order.
+    fn tick(&mut self) -> u64 {
+        self.clock += 1;
+        self.clock
+    }
+
+    /// Evicts the least recently used entry, returning `false` if the store is empty.
+    fn evict_oldest(&mut self) -> bool {
+        let Some((_, key)) = self.recency.pop_first() else {
+            return false;
+        };
+
+        if let Some(slot) = self.entries.remove(&key) {
+            self.budget.release(self.subsystem, slot.size);
+            self.evictions += 1;
+        }
+
+        true
+    }
+}
+
+impl<K, V> Drop for LruStore<K, V> {
+    fn drop(&mut self) {
+        let size = self.entries.values().map(|slot| slot.size).sum();
+
+        self.budget.release(self.subsystem, size);
+    }
+}
@@ -0,0 +1,275 @@
+//! Reporting counters and round-trip times to a metrics system.
+//!
+//! `Game::fetch_with` reports every query, response and failure to the `MetricsSink`
+//! returned by `Protocol::metrics`, and the transports report the packets they exchange.
+//! Operators implement the trait to forward the values to the metrics system of their
+//! choice, or use `Counters` to keep them in memory. Protocols report nothing unless a sink
+//! is configured, e.g. with `with_metrics` on a transport or with a `MetricsLayer`.
+
+use crate::prelude::ErrorKind;
+
+use std::{
+    fmt::{Debug, Formatter, Result as FmtResult},
+    net::SocketAddr,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+/// The upper bounds of the round-trip time buckets of `Counters`.
+///
+/// A last, unbounded bucket holds the round trips above the largest bound.
+pub const RTT_BUCKETS: [Duration; 8] = [
+    Duration::from_millis(10),
+    Duration::from_millis(25),
+    Duration::from_millis(50),
+    Duration::from_millis(100),
+    Duration::from_millis(250),
+    Duration::from_millis(500),
+    Duration::from_secs(1),
+    Duration::from_secs(2),
+];
+
+/// A trait for receivers of query metrics.
+///
+/// Every method does nothing by default, so sinks only implement what they record. The
+/// methods are called on the query path and should return quickly, e.g. by updating an
+/// atomic counter.
+pub trait MetricsSink: Send + Sync {
+    /// Counts a query sent to a server.
+    ///
+    /// # Parameters
+    ///
+    /// * `game`: The name of the game queried.
+    /// * `peer`: The address of the server.
+    fn query_sent(&self, game: &str, peer: SocketAddr) {
+        let _ = (game, peer);
+    }
+
+    /// Counts a response received and parsed successfully.
+    ///
+    /// # Parameters
+    ///
+    /// * `game`: The name of the game queried.
+    /// * `peer`: The address of the server.
+    fn response_ok(&self, game: &str, peer: SocketAddr) {
+        let _ = (game, peer);
+    }
+
+    /// Counts a response that was received but couldn't be parsed.
+    ///
+    /// # Parameters
+    ///
+    /// * `game`: The name of the game queried.
+    /// * `peer`: The address of the server.
+    fn parse_failure(&self, game: &str, peer: SocketAddr) {
+        let _ = (game, peer);
+    }
+
+    /// Counts a fetch that failed after all of its attempts.
+    ///
+    /// # Parameters
+    ///
+    /// * `game`: The name of the game queried.
+    /// * `peer`: The address of the server.
+    /// * `kind`: What went wrong.
+    fn query_failed(&self, game: &str, peer: SocketAddr, kind: ErrorKind) {
+        let _ = (game, peer, kind);
+    }
+
+    /// Records the round-trip time of a successful query, typically in a histogram.
+    ///
+    /// # Parameters
+    ///
+    /// * `game`: The name of the game queried.
+    /// * `peer`: The address of the server.
+    /// * `rtt`: The time from sending the query to receiving the response.
+    fn rtt(&self, game: &str, peer: SocketAddr, rtt: Duration) {
+        let _ = (game, peer, rtt);
+    }
+
+    /// Counts a packet sent by a transport.
+    ///
+    /// # Parameters
+    ///
+    /// * `peer`: The address of the server.
+    /// * `bytes`: The size of the packet.
+    fn packet_sent(&self, peer: SocketAddr, bytes: usize) {
+        let _ = (peer, bytes);
+    }
+
+    /// Counts a packet received by a transport.
+    ///
+    /// # Parameters
+    ///
+    /// * `peer`: The address of the server.
+    /// * `bytes`: The size of the packet.
+    fn packet_received(&self, peer: SocketAddr, bytes: usize) {
+        let _ = (peer, bytes);
+    }
+}
+
+impl<S: MetricsSink + ?Sized> MetricsSink for Arc<S> {
+    fn query_sent(&self, game: &str, peer: SocketAddr) {
+        (**self).query_sent(game, peer);
+    }
+
+    fn response_ok(&self, game: &str, peer: SocketAddr) {
+        (**self).response_ok(game, peer);
+    }
+
+    fn parse_failure(&self, game: &str, peer: SocketAddr) {
+        (**self).parse_failure(game, peer);
+    }
+
+    fn query_failed(&self, game: &str, peer: SocketAddr, kind: ErrorKind) {
+        (**self).query_failed(game, peer, kind);
+    }
+
+    fn rtt(&self, game: &str, peer: SocketAddr, rtt: Duration) {
+        (**self).rtt(game, peer, rtt);
+    }
+
+    fn packet_sent(&self, peer: SocketAddr, bytes: usize) {
+        (**self).packet_sent(peer, bytes);
+    }
+
+    fn packet_received(&self, peer: SocketAddr, bytes: usize) {
+        (**self).packet_received(peer, bytes);
+    }
+}
+
+/// A sink recording nothing.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct NoopMetrics;
+
+impl MetricsSink for NoopMetrics {}
+
+/// A sink keeping totals across all games and servers in memory.
+///
+/// Share it through an `Arc` and read it with `snapshot`, e.g. to expose the totals on a
+/// status page or to scrape them periodically.
+#[derive(Default)]
+pub struct Counters {
+    queries_sent: AtomicU64,
+    responses_ok: AtomicU64,
+    parse_failures: AtomicU64,
+    queries_failed: AtomicU64,
+    packets_sent: AtomicU64,
+    packets_received: AtomicU64,
+    bytes_sent: AtomicU64,
+    bytes_received: AtomicU64,
+    rtt_buckets: [AtomicU64; RTT_BUCKETS.len() + 1],
+    rtt_sum_micros: AtomicU64,
+}
+
+impl Counters {
+    /// Creates a sink with all totals at zero.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the current totals.
+    ///
+    /// The totals are read one at a time, so a snapshot taken while queries are running
+    /// can be slightly inconsistent, e.g. count a response whose query isn't counted yet.
+    pub fn snapshot(&self) -> CountersSnapshot {
+        let load = |counter: &AtomicU64| counter.load(Ordering::Relaxed);
+
+        CountersSnapshot {
+            queries_sent: load(&self.queries_sent),
+            responses_ok: load(&self.responses_ok),
+            parse_failures: load(&self.parse_failures),
+            queries_failed: load(&self.queries_failed),
+            packets_sent: load(&self.packets_sent),
+            packets_received: load(&self.packets_received),
+            bytes_sent: load(&self.bytes_sent),
+            bytes_received: load(&self.bytes_received),
+            rtt_buckets: self.rtt_buckets.each_ref().map(load),
+            rtt_sum: Duration::from_micros(load(&self.rtt_sum_micros)),
+        }
+    }
+}
+
+impl MetricsSink for Counters {
+    fn query_sent(&self, _game: &str, _peer: SocketAddr) {
+        self.queries_sent.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn response_ok(&self, _game: &str, _peer: SocketAddr) {
+        self.responses_ok.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn parse_failure(&self, _game: &str, _peer: SocketAddr) {
+        self.parse_failures.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn query_failed(&self, _game: &str, _peer: SocketAddr, _kind: ErrorKind) {
+        self.queries_failed.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn rtt(&self, _game: &str, _peer: SocketAddr, rtt: Duration) {
+        let bucket = RTT_BUCKETS
+            .iter()
+            .position(|bound| rtt <= *bound)
+            .unwrap_or(RTT_BUCKETS.len());
+        let micros = u64::try_from(rtt.as_micros()).unwrap_or(u64::MAX);
+
+        self.rtt_buckets[bucket].fetch_add(1, Ordering::Relaxed);
+        self.rtt_sum_micros.fetch_add(micros, Ordering::Relaxed);
+    }
+
+    fn packet_sent(&self, _peer: SocketAddr, bytes: usize) {
+        self.packets_sent.fetch_add(1, Ordering::Relaxed);
+        self.bytes_sent.fetch_add(bytes as u64, Ordering::Relaxed);
+    }
+
+    fn packet_received(&self, _peer: SocketAddr, bytes: usize) {
+        self.packets_received.fetch_add(1, Ordering::Relaxed);
+        self.bytes_received
+            .fetch_add(bytes as u64, Ordering::Relaxed);
+    }
+}
+
+impl Debug for Counters {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        Debug::fmt(&self.snapshot(), f)
+    }
+}
+
+/// The totals of `Counters` at one point in time.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CountersSnapshot {
+    /// The number of queries sent.
+    pub queries_sent: u64,
+    /// The number of responses received and parsed successfully.
+    pub responses_ok: u64,
+    /// The number of responses that couldn't be parsed.
+    pub parse_failures: u64,
+    /// The number of fetches that failed after all of their attempts.
+    pub queries_failed: u64,
+    /// The number of packets sent by transports.
+    pub packets_sent: u64,
+    /// The number of packets received by transports.
+    pub packets_received: u64,
+    /// The number of bytes sent by transports.
+    pub bytes_sent: u64,
+    /// The number of bytes received by transports.
+    pub bytes_received: u64,
+    /// The number of round trips per bucket of `RTT_BUCKETS`, followed by the number of
+    /// round trips above the largest bound.
+    pub rtt_buckets: [u64; RTT_BUCKETS.len() + 1],
+    /// The sum of all round-trip times.
+    pub rtt_sum: Duration,
+}
+
+impl CountersSnapshot {
+    /// Returns the mean round-trip time, or `None` if no round trip was recorded.
+    pub fn mean_rtt(&self) -> Option<Duration> {
+        let count: u64 = self.rtt_buckets.iter().sum();
+
+        (count > 0).then(|| self.rtt_sum / u32::try_from(count).unwrap_or(u32::MAX))
+    }
+}
@@ -0,0 +1,107 @@
+use std::{
+    error::Error as StdError,
+    fmt::{Display, Formatter, Result as FmtResult},
+    str::FromStr,
+};
+
+/// `AccessPolicy` describes who may join a game server.
+///
+/// Games signal restricted access in many ways: a password flag in the info packet, a
+/// whitelist rule, a visibility or lobby type setting. Parsers map whichever signal their
+/// game has to an `AccessPolicy`, so server browsers can filter on one field.
+///
+/// Policies are ordered from the least to the most restrictive. When a game reports
+/// several signals, e.g. a password and a whitelist, the most restrictive one wins, which
+/// is what `max` yields.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub enum AccessPolicy {
+    /// Anyone may join. Games that don't report access restrictions are treated as open.
+    #[default]
+    Open,
+    /// Joining requires a password.
+    Password,
+    /// Only players on the server's whitelist may join.
+    Whitelist,
+    /// Only invited players may join, e.g. friends-only or private lobbies.
+    InviteOnly,
+}
+
+impl AccessPolicy {
+    /// Combines the access signals a game reports into a single policy.
+    ///
+    /// # Parameters
+    ///
+    /// * `password`: Whether the server requires a password.
+    /// * `whitelist`: Whether the server only admits whitelisted players.
+    /// * `invite_only`: Whether the server only admits invited players.
+    ///
+    /// # Returns
+    ///
+    /// The most restrictive policy among the signals that are set.
+    pub const fn from_signals(password: bool, whitelist: bool, invite_only: bool) -> Self {
+        if invite_only {
+            AccessPolicy::InviteOnly
+        } else if whitelist {
+            AccessPolicy::Whitelist
+        } else if password {
+            AccessPolicy::Password
+        } else {
+            AccessPolicy::Open
+        }
+    }
+
+    /// Returns `true` if anyone may join.
+    pub const fn is_open(self) -> bool {
+        matches!(self, AccessPolicy::Open)
+    }
+
+    /// Returns the name of the policy, as used by `Display` and `FromStr`.
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            AccessPolicy::Open => "open",
+            AccessPolicy::Password => "password",
+            AccessPolicy::Whitelist => "whitelist",
+            AccessPolicy::InviteOnly => "invite_only",
+        }
+    }
+}
+
+impl Display for AccessPolicy {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Parses a policy from its name, or from one of the terms games commonly use for it:
+/// `public`, `passworded`, `allowlist`, `private` or `friends`. Case is ignored.
+impl FromStr for AccessPolicy {
+    type Err = ParseAccessPolicyError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "open" | "public" => Ok(AccessPolicy::Open),
+            "password" | "passworded" => Ok(AccessPolicy::Password),
+            "whitelist" | "allowlist" => Ok(AccessPolicy::Whitelist),
+            "invite_only" | "private" | "friends" => Ok(AccessPolicy::InviteOnly),
+            _ => Err(ParseAccessPolicyError(s.to_string())),
+        }
+    }
+}
+
+/// The error returned when a string names no `AccessPolicy`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseAccessPolicyError(String);
+
+impl Display for ParseAccessPolicyError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(f, "unknown access policy `{}`", self.0)
+    }
+}
+
+impl StdError for ParseAccessPolicyError {}
@@ -0,0 +1,5 @@
+pub mod access;
+pub mod player;
+pub mod provenance;
+pub mod server_info;
+pub mod value;
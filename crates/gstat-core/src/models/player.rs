@@ -0,0 +1,57 @@
+use crate::models::value::Value;
+
+use std::{collections::BTreeMap, time::Duration};
+
+/// `Player` is the normalized description of a player connected to a game server.
+///
+/// Games report different subsets of player data, so every field other than the name is
+/// optional. Game-specific data without a common equivalent is kept in `extra`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(default)
+)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct Player {
+    /// The name of the player.
+    pub name: String,
+    /// The score of the player.
+    pub score: Option<i64>,
+    /// How long the player has been connected.
+    pub duration: Option<Duration>,
+    /// The latency of the player in milliseconds.
+    pub ping: Option<u32>,
+    /// The team the player belongs to.
+    pub team: Option<String>,
+    /// Game-specific data without a common equivalent.
+    pub extra: BTreeMap<String, String>,
+}
+
+impl Player {
+    /// Creates a new `Player` with only a name set.
+    ///
+    /// # Parameters
+    ///
+    /// * `name`: The name of the player.
+    pub fn new(name: impl Into<String>) -> Self {
+        Player {
+            name: name.into(),
+            ..Player::default()
+        }
+    }
+
+    /// Returns the fields of the player as named `Value`s, with `extra` as a nested map.
+    ///
+    /// Fields the game doesn't report are `Value::Null`, and the duration is in seconds.
+    pub fn fields(&self) -> BTreeMap<String, Value> {
+        BTreeMap::from([
+            ("name".to_string(), self.name.clone().into()),
+            ("score".to_string(), self.score.into()),
+            ("duration".to_string(), self.duration.into()),
+            ("ping".to_string(), self.ping.into()),
+            ("team".to_string(), self.team.clone().into()),
+            ("extra".to_string(), self.extra.clone().into()),
+        ])
+    }
+}
@@ -0,0 +1,88 @@
+use std::{
+    collections::BTreeMap,
+    fmt::{Display, Formatter, Result as FmtResult},
+};
+
+/// `Provenance` records which protocol field each normalized value was taken from.
+///
+/// Games often report the same value in several places, e.g. the player count both as a
+/// byte of the info packet and in the keywords, and pick one when converting the response
+/// through `Response::to_common`. When a normalized number looks wrong, the provenance
+/// tells which field to look at. Fields are named like their normalized counterparts:
+/// `players` for `ServerInfo::players`, and `extra.<key>` for entries of
+/// `ServerInfo::extra`.
+///
+/// Provenance is only built on request through `Response::provenance`, so recording it
+/// costs nothing on the query path.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(transparent)
+)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct Provenance {
+    sources: BTreeMap<String, String>,
+}
+
+impl Provenance {
+    /// Creates an empty provenance.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records where a normalized value came from, replacing any previous record.
+    ///
+    /// # Parameters
+    ///
+    /// * `field`: The normalized field, e.g. `players`.
+    /// * `source`: The protocol field it was taken from, e.g. `A2S_INFO players byte`.
+    pub fn record(&mut self, field: impl Into<String>, source: impl Into<String>) -> &mut Self {
+        self.sources.insert(field.into(), source.into());
+        self
+    }
+
+    /// Returns this provenance with the source of `field` recorded.
+    ///
+    /// # Parameters
+    ///
+    /// * `field`: The normalized field, e.g. `players`.
+    /// * `source`: The protocol field it was taken from, e.g. `A2S_INFO players byte`.
+    pub fn with(mut self, field: impl Into<String>, source: impl Into<String>) -> Self {
+        self.record(field, source);
+        self
+    }
+
+    /// Returns the protocol field `field` was taken from, if recorded.
+    pub fn source(&self, field: &str) -> Option<&str> {
+        self.sources.get(field).map(String::as_str)
+    }
+
+    /// Returns the recorded fields and their sources, ordered by field.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.sources
+            .iter()
+            .map(|(field, source)| (field.as_str(), source.as_str()))
+    }
+
+    /// Returns the number of recorded fields.
+    pub fn len(&self) -> usize {
+        self.sources.len()
+    }
+
+    /// Returns `true` if nothing was recorded.
+    pub fn is_empty(&self) -> bool {
+        self.sources.is_empty()
+    }
+}
+
+/// Renders one `field <- source` line per recorded field.
+impl Display for Provenance {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        for (field, source) in self.iter() {
+            writeln!(f, "{field} <- {source}")?;
+        }
+
+        Ok(())
+    }
+}
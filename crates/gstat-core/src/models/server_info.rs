@@ -0,0 +1,65 @@
+use crate::models::{access::AccessPolicy, value::Value};
+
+use std::collections::BTreeMap;
+
+/// `ServerInfo` is the normalized description of a game server shared by every game.
+///
+/// Each game returns its own response layout; converting it into a `ServerInfo` through
+/// `Response::to_common` lets consumers such as server browsers treat all games uniformly.
+/// Fields a game does not report are left at their default value, and game-specific data
+/// that has no common equivalent is kept in `extra`.
+///
+/// With the `serde` feature, `ServerInfo` can be serialized for web backends; missing
+/// fields deserialize to their default value.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(default)
+)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct ServerInfo {
+    /// The name of the server.
+    pub name: String,
+    /// The map currently being played.
+    pub map: String,
+    /// The game or game mode reported by the server.
+    pub game: String,
+    /// The number of players currently connected.
+    pub players: u32,
+    /// The maximum number of players the server accepts.
+    pub max_players: u32,
+    /// Whether joining the server requires a password.
+    ///
+    /// Kept for consumers that only care about passwords; `access` also covers whitelists
+    /// and invite-only servers.
+    pub password: bool,
+    /// Who may join the server.
+    pub access: AccessPolicy,
+    /// The version of the game server.
+    pub version: String,
+    /// Game-specific data without a common equivalent.
+    pub extra: BTreeMap<String, String>,
+}
+
+impl ServerInfo {
+    /// Returns `true` if the server has no free player slots left.
+    pub fn is_full(&self) -> bool {
+        self.max_players > 0 && self.players >= self.max_players
+    }
+
+    /// Returns the fields of the server as named `Value`s, with `extra` as a nested map.
+    pub fn fields(&self) -> BTreeMap<String, Value> {
+        BTreeMap::from([
+            ("name".to_string(), self.name.clone().into()),
+            ("map".to_string(), self.map.clone().into()),
+            ("game".to_string(), self.game.clone().into()),
+            ("players".to_string(), self.players.into()),
+            ("max_players".to_string(), self.max_players.into()),
+            ("password".to_string(), self.password.into()),
+            ("access".to_string(), self.access.as_str().into()),
+            ("version".to_string(), self.version.clone().into()),
+            ("extra".to_string(), self.extra.clone().into()),
+        ])
+    }
+}
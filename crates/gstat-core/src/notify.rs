@@ -0,0 +1,611 @@
+//! Posting webhook notifications when polled servers change state.
+//!
+//! A `Notifier` turns the `PollEvent`s of a `Poller` into HTTP POST requests: a generic JSON
+//! document for custom receivers, or the message layouts Discord and Slack expect from their
+//! incoming webhooks. Each `Webhook` picks the servers and kinds of events it is told about.
+//! Failed deliveries are retried under a `RetryPolicy`, and the same message is only posted
+//! once per webhook within the dedup window, so a flapping server doesn't flood a channel.
+//!
+//! Requests go through a `WebhookClient`. `HttpClient` speaks plain HTTP/1.1 over the
+//! selected runtime, which suits receivers on a private network; `https://` endpoints, such
+//! as the ones of Discord and Slack, need a client backed by a TLS library.
+
+use crate::{
+    clock::{Clock, RuntimeClock},
+    encode::{Encode, Json},
+    error::{Error, ErrorDetail},
+    models::value::Value,
+    poller::PollEvent,
+    retry::RetryPolicy,
+    runtime,
+};
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    error::Error as StdError,
+    fmt::{Debug, Display, Formatter, Result as FmtResult},
+    future::Future,
+    io,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+/// The future returned by `WebhookClient::post`.
+pub type Post<'a> = Pin<Box<dyn Future<Output = io::Result<u16>> + Send + 'a>>;
+
+/// `WebhookClient` sends the HTTP requests of a `Notifier`.
+pub trait WebhookClient: Send + Sync {
+    /// Posts a body to a URL.
+    ///
+    /// # Parameters
+    ///
+    /// * `url`: The URL of the webhook.
+    /// * `content_type`: The MIME type of the body.
+    /// * `body`: The body.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the HTTP status code of the response, or an `io::Error` if no
+    /// response was received.
+    fn post<'a>(&'a self, url: &'a str, content_type: &'a str, body: &'a [u8]) -> Post<'a>;
+}
+
+/// `HttpClient` posts to `http://` URLs over plain HTTP/1.1.
+///
+/// It opens a connection per request and only reads the status line of the response.
+/// `https://` URLs fail with `io::ErrorKind::Unsupported`.
+#[derive(Clone, Copy, Debug)]
+pub struct HttpClient {
+    timeout: Duration,
+}
+
+impl HttpClient {
+    /// Creates a client giving up on requests after 10 seconds.
+    pub fn new() -> Self {
+        HttpClient {
+            timeout: Duration::from_secs(10),
+        }
+    }
+
+    /// Sets the time limit for a request, from connecting to reading the status line.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    async fn send(&self, url: &str, content_type: &str, body: &[u8]) -> io::Result<u16> {
+        let Some(rest) = url.strip_prefix("http://") else {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                format!("{url} is not a plain HTTP URL"),
+            ));
+        };
+
+        let (authority, path) = match rest.find('/') {
+            Some(index) => rest.split_at(index),
+            None => (rest, "/"),
+        };
+        let (host, port) = split_authority(authority)?;
+
+        let address = runtime::lookup_host(host, port)
+            .await?
+            .into_iter()
+            .next()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no address for host"))?;
+
+        let mut stream = runtime::TcpStream::connect(address).await?;
+        let mut request = format!(
+            "POST {path} HTTP/1.1\r\nHost: {authority}\r\nContent-Type: {content_type}\r\n\
+             Content-Length: {}\r\nUser-Agent: gstat\r\nConnection: close\r\n\r\n",
+            body.len()
+        )
+        .into_bytes();
+        request.extend_from_slice(body);
+        stream.write_all(&request).await?;
+
+        let mut response = Vec::new();
+        let mut buffer = [0; 512];
+
+        let line = loop {
+            if let Some(end) = response.windows(2).position(|pair| pair == b"\r\n") {
+                break &response[..end];
+            }
+
+            let read = stream.read(&mut buffer).await?;
+
+            if read == 0 {
+                return Err(io::ErrorKind::UnexpectedEof.into());
+            }
+
+            response.extend_from_slice(&buffer[..read]);
+        };
+
+        status(line)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "malformed HTTP status line"))
+    }
+}
+
+impl Default for HttpClient {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl WebhookClient for HttpClient {
+    fn post<'a>(&'a self, url: &'a str, content_type: &'a str, body: &'a [u8]) -> Post<'a> {
+        Box::pin(async move {
+            runtime::timeout(self.timeout, self.send(url, content_type, body))
+                .await
+                .map_err(|_| io::Error::from(io::ErrorKind::TimedOut))?
+        })
+    }
+}
+
+/// Splits `host:port`, with the host of IPv6 addresses in brackets and port 80 by default.
+fn split_authority(authority: &str) -> io::Result<(&str, u16)> {
+    let invalid = || io::Error::new(io::ErrorKind::InvalidInput, "invalid host in URL");
+
+    let (host, port) = match authority.strip_prefix('[') {
+        Some(rest) => {
+            let (host, after) = rest.split_once(']').ok_or_else(invalid)?;
+            (host, after.strip_prefix(':'))
+        }
+        None => match authority.split_once(':') {
+            Some((host, port)) => (host, Some(port)),
+            None => (authority, None),
+        },
+    };
+
+    let port = match port {
+        Some(port) => port.parse().map_err(|_| invalid())?,
+        None => 80,
+    };
+
+    match host.is_empty() {
+        true => Err(invalid()),
+        false => Ok((host, port)),
+    }
+}
+
+/// Parses the status code out of a status line such as `HTTP/1.1 204 No Content`.
+fn status(line: &[u8]) -> Option<u16> {
+    let line = std::str::from_utf8(line).ok()?;
+    let mut parts = line.split(' ');
+
+    parts
+        .next()?
+        .starts_with("HTTP/")
+        .then(|| parts.next()?.parse().ok())?
+}
+
+/// The kinds of `PollEvent`s.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum EventKind {
+    /// `PollEvent::Online`.
+    Online,
+    /// `PollEvent::Offline`.
+    Offline,
+    /// `PollEvent::PlayersChanged`.
+    PlayersChanged,
+    /// `PollEvent::MapChanged`.
+    MapChanged,
+}
+
+impl EventKind {
+    /// Every kind of event.
+    pub const ALL: [EventKind; 4] = [
+        EventKind::Online,
+        EventKind::Offline,
+        EventKind::PlayersChanged,
+        EventKind::MapChanged,
+    ];
+
+    /// Returns the kind of an event.
+    pub fn of<T, E>(event: &PollEvent<T, E>) -> Self {
+        match event {
+            PollEvent::Online { .. } => EventKind::Online,
+            PollEvent::Offline { .. } => EventKind::Offline,
+            PollEvent::PlayersChanged { .. } => EventKind::PlayersChanged,
+            PollEvent::MapChanged { .. } => EventKind::MapChanged,
+        }
+    }
+
+    /// Returns the name of the kind in JSON payloads, e.g. `map_changed`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            EventKind::Online => "online",
+            EventKind::Offline => "offline",
+            EventKind::PlayersChanged => "players_changed",
+            EventKind::MapChanged => "map_changed",
+        }
+    }
+}
+
+/// The layout of the body posted to a webhook.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Format {
+    /// A JSON document with the kind of event, the server, a message and the details of
+    /// the event.
+    #[default]
+    Json,
+    /// A Discord message with a colored embed.
+    Discord,
+    /// A Slack message with a colored attachment.
+    Slack,
+}
+
+/// An endpoint notified of poll events.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Webhook<T> {
+    url: String,
+    format: Format,
+    targets: Option<Vec<T>>,
+    kinds: Vec<EventKind>,
+}
+
+impl<T> Webhook<T> {
+    /// Creates a webhook posting JSON documents about every server going online or
+    /// offline and changing map.
+    ///
+    /// # Parameters
+    ///
+    /// * `url`: The URL the notifications are posted to.
+    pub fn new(url: impl Into<String>) -> Self {
+        Webhook {
+            url: url.into(),
+            format: Format::Json,
+            targets: None,
+            kinds: vec![EventKind::Online, EventKind::Offline, EventKind::MapChanged],
+        }
+    }
+
+    /// Sets the layout of the posted bodies.
+    pub fn with_format(mut self, format: Format) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Limits the webhook to the events of some servers.
+    ///
+    /// # Parameters
+    ///
+    /// * `targets`: The servers, as the poller names them.
+    pub fn with_targets(mut self, targets: impl IntoIterator<Item = T>) -> Self {
+        self.targets = Some(targets.into_iter().collect());
+        self
+    }
+
+    /// Sets the kinds of events the webhook is notified of.
+    ///
+    /// # Parameters
+    ///
+    /// * `kinds`: The kinds, e.g. `EventKind::ALL` to include player count changes.
+    pub fn with_kinds(mut self, kinds: impl IntoIterator<Item = EventKind>) -> Self {
+        self.kinds = kinds.into_iter().collect();
+        self
+    }
+
+    /// Returns the URL the notifications are posted to.
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+}
+
+/// An error delivering a notification to a webhook.
+#[derive(Debug)]
+pub struct NotifyError {
+    /// The URL of the webhook.
+    pub url: String,
+    /// The attempts made.
+    pub attempts: u32,
+    /// The error of the last attempt.
+    pub error: io::Error,
+}
+
+impl Display for NotifyError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(
+            f,
+            "notifying {} failed after {} attempt(s): {}",
+            self.url, self.attempts, self.error
+        )
+    }
+}
+
+impl StdError for NotifyError {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        Some(&self.error)
+    }
+}
+
+/// `Notifier` posts poll events to webhooks.
+///
+/// A 2xx response counts as delivered. Connection errors, `408`, `429` and `5xx` responses
+/// are retried; other responses fail at once, since the receiver rejected the body.
+///
+/// # Examples
+///
+/// ```no_run
+/// # async fn forward(mut poller: gstat_core::poller::Poller<'_, String, std::io::Error>) {
+/// use gstat_core::notify::{Format, Notifier, Webhook};
+///
+/// let notifier = Notifier::new()
+///     .with_webhook(Webhook::new("http://alerts.lan/gstat").with_format(Format::Slack));
+///
+/// while let Some(event) = poller.next().await {
+///     for err in notifier.notify(&event).await {
+///         eprintln!("{err}");
+///     }
+/// }
+/// # }
+/// ```
+pub struct Notifier<T> {
+    webhooks: Vec<Webhook<T>>,
+    client: Arc<dyn WebhookClient>,
+    retry: RetryPolicy,
+    dedup: Duration,
+    /// The clock of the dedup window and of the waits between attempts.
+    clock: Arc<dyn Clock>,
+    /// When each webhook last received each message.
+    sent: Mutex<HashMap<(usize, String), Instant>>,
+}
+
+impl<T> Notifier<T>
+where
+    T: PartialEq + Display,
+{
+    /// Creates a notifier without webhooks, posting with an `HttpClient`, making up to 3
+    /// attempts per delivery and dropping repeated messages within 5 minutes.
+    pub fn new() -> Self {
+        Notifier {
+            webhooks: Vec::new(),
+            client: Arc::new(HttpClient::new()),
+            retry: RetryPolicy::new(3),
+            dedup: Duration::from_secs(300),
+            clock: Arc::new(RuntimeClock),
+            sent: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Adds a webhook.
+    pub fn with_webhook(mut self, webhook: Webhook<T>) -> Self {
+        self.webhooks.push(webhook);
+        self
+    }
+
+    /// Sets the client posting the notifications, e.g. one supporting HTTPS.
+    pub fn with_client(mut self, client: impl WebhookClient + 'static) -> Self {
+        self.client = Arc::new(client);
+        self
+    }
+
+    /// Sets how failed deliveries are retried. Connection failures and the statuses 408,
+    /// 429 and 5xx are transient; other statuses are only retried under `RetryOn::Any`.
+    /// The policy waits on the clock of the notifier.
+    pub fn with_retry(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry.with_clock(Arc::clone(&self.clock));
+        self
+    }
+
+    /// Sets the clock of the dedup window and of the waits between attempts, e.g. a
+    /// `ManualClock` in tests.
+    pub fn with_clock(mut self, clock: impl Clock + 'static) -> Self {
+        self.clock = Arc::new(clock);
+        self.retry = self.retry.with_clock(Arc::clone(&self.clock));
+        self
+    }
+
+    /// Sets how long a message posted to a webhook isn't posted to it again.
+    ///
+    /// # Parameters
+    ///
+    /// * `window`: The window. `Duration::ZERO` posts every event.
+    pub fn with_dedup(mut self, window: Duration) -> Self {
+        self.dedup = window;
+        self
+    }
+
+    /// Posts an event to the webhooks interested in it.
+    ///
+    /// # Parameters
+    ///
+    /// * `event`: An event of a poller.
+    ///
+    /// # Returns
+    ///
+    /// The errors of the deliveries that failed, empty if every webhook was notified.
+    pub async fn notify<E: Display>(&self, event: &PollEvent<T, E>) -> Vec<NotifyError> {
+        let kind = EventKind::of(event);
+        let message = message(event);
+        let mut errors = Vec::new();
+
+        for (index, webhook) in self.webhooks.iter().enumerate() {
+            let interested = webhook.kinds.contains(&kind)
+                && webhook
+                    .targets
+                    .as_ref()
+                    .is_none_or(|targets| targets.contains(event.target()));
+
+            if !interested || self.is_duplicate(index, &message) {
+                continue;
+            }
+
+            let body = Json.encode(&payload(webhook.format, event, &message));
+
+            if let Err(err) = self.deliver(&webhook.url, &body).await {
+                self.forget(index, &message);
+                errors.push(err);
+            }
+        }
+
+        errors
+    }
+
+    /// Records `message` as sent to the webhook at `index`, returning `true` if it already
+    /// was within the dedup window.
+    fn is_duplicate(&self, index: usize, message: &str) -> bool {
+        if self.dedup.is_zero() {
+            return false;
+        }
+
+        let now = self.clock.now();
+        let mut sent = self.sent.lock().unwrap_or_else(|err| err.into_inner());
+
+        sent.retain(|_, at| now.saturating_duration_since(*at) < self.dedup);
+
+        sent.insert((index, message.to_string()), now).is_some()
+    }
+
+    /// Forgets a message that could not be delivered, so the next occurrence is posted.
+    fn forget(&self, index: usize, message: &str) {
+        self.sent
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .remove(&(index, message.to_string()));
+    }
+
+    /// Posts a body to a webhook under the retry policy.
+    async fn deliver(&self, url: &str, body: &[u8]) -> Result<(), NotifyError> {
+        let mut attempts = 0;
+
+        let delivery = self
+            .retry
+            .run(|attempt| {
+                attempts = attempt;
+
+                async move {
+                    match self.client.post(url, Json.content_type(), body).await {
+                        Ok(200..=299) => Ok(()),
+                        Ok(code) => {
+                            let err = io::Error::other(format!("HTTP status {code}"));
+                            let detail = ErrorDetail::new("webhook rejected the post", Some(err));
+
+                            // Only the statuses a later attempt may change are transient.
+                            Err(match code {
+                                408 | 429 | 500.. => Error::ResponseError(detail),
+                                _ => Error::QueryError(detail),
+                            })
+                        }
+                        Err(err) => Err(Error::ProtocolError(ErrorDetail::new(
+                            "webhook post failed",
+                            Some(err),
+                        ))),
+                    }
+                }
+            })
+            .await;
+
+        delivery.map_err(|err| NotifyError {
+            url: url.to_string(),
+            attempts,
+            error: err
+                .into_detail()
+                .into_inner()
+                .unwrap_or_else(|| io::Error::other("webhook delivery failed")),
+        })
+    }
+}
+
+impl<T: PartialEq + Display> Default for Notifier<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Debug> Debug for Notifier<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.debug_struct("Notifier")
+            .field("webhooks", &self.webhooks)
+            .field("dedup", &self.dedup)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Describes an event in a sentence.
+fn message<T: Display, E: Display>(event: &PollEvent<T, E>) -> String {
+    match event {
+        PollEvent::Online { target, info } => format!(
+            "{target} is online on {} with {}/{} players",
+            info.map, info.players, info.max_players
+        ),
+        PollEvent::Offline { target, error } => format!("{target} went offline: {error}"),
+        PollEvent::PlayersChanged { target, from, to } => {
+            let direction = if to > from { "up" } else { "down" };
+            format!("{target} has {to} players, {direction} from {from}")
+        }
+        PollEvent::MapChanged { target, from, to } => {
+            format!("{target} changed map from {from} to {to}")
+        }
+    }
+}
+
+/// Builds the document posted for an event.
+fn payload<T: Display, E: Display>(
+    format: Format,
+    event: &PollEvent<T, E>,
+    message: &str,
+) -> Value {
+    let kind = EventKind::of(event);
+    let color: u32 = match kind {
+        EventKind::Online => 0x2E_CC_71,
+        EventKind::Offline => 0xE7_4C_3C,
+        EventKind::PlayersChanged | EventKind::MapChanged => 0x34_98_DB,
+    };
+
+    match format {
+        Format::Json => {
+            let mut document = BTreeMap::from([
+                ("event".to_string(), kind.name().into()),
+                ("target".to_string(), event.target().to_string().into()),
+                ("message".to_string(), message.into()),
+            ]);
+
+            match event {
+                PollEvent::Online { info, .. } => {
+                    document.insert("info".to_string(), Value::Map(info.fields()));
+                }
+                PollEvent::Offline { error, .. } => {
+                    document.insert("error".to_string(), error.to_string().into());
+                }
+                PollEvent::PlayersChanged { from, to, .. } => {
+                    document.insert("from".to_string(), (*from).into());
+                    document.insert("to".to_string(), (*to).into());
+                }
+                PollEvent::MapChanged { from, to, .. } => {
+                    document.insert("from".to_string(), from.as_str().into());
+                    document.insert("to".to_string(), to.as_str().into());
+                }
+            }
+
+            Value::Map(document)
+        }
+        Format::Discord => map([(
+            "embeds",
+            Value::List(vec![map([
+                ("title", event.target().to_string().into()),
+                ("description", message.into()),
+                ("color", color.into()),
+            ])]),
+        )]),
+        Format::Slack => map([
+            ("text", message.into()),
+            (
+                "attachments",
+                Value::List(vec![map([
+                    ("color", format!("#{color:06X}").into()),
+                    ("text", message.into()),
+                ])]),
+            ),
+        ]),
+    }
+}
+
+fn map<const N: usize>(entries: [(&str, Value); N]) -> Value {
+    Value::Map(
+        entries
+            .into_iter()
+            .map(|(key, value)| (key.to_string(), value))
+            .collect(),
+    )
+}
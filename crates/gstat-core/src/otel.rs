@@ -0,0 +1,226 @@
+//! OpenTelemetry spans and metrics.
+//!
+//! With the `otel` feature, `Game::fetch_with` emits a client span per fetch through the
+//! global tracer provider, and `OtelMetrics` reports query metrics through a meter. gstat
+//! only uses the OpenTelemetry API: install a tracer and meter provider with an exporter,
+//! e.g. OTLP from `opentelemetry-otlp`, to send the data to an observability stack. Without
+//! a provider, spans and measurements are discarded.
+//!
+//! The `silent` feature disables the fetch spans; `OtelMetrics` keeps reporting, since it
+//! is only used when attached explicitly.
+
+use crate::{metrics::MetricsSink, prelude::ErrorKind};
+
+use std::{
+    fmt::{Debug, Formatter, Result as FmtResult},
+    net::SocketAddr,
+    time::Duration,
+};
+
+use opentelemetry::{
+    global::{self, BoxedSpan},
+    metrics::{Counter, Histogram, Meter},
+    trace::{Span, SpanKind, Status, Tracer},
+    KeyValue,
+};
+
+/// The name of the instrumentation scope of the spans and metrics.
+pub const SCOPE: &str = "gstat";
+
+/// The span of a single `Game::fetch_with`.
+pub(crate) struct FetchSpan {
+    span: Option<BoxedSpan>,
+}
+
+impl FetchSpan {
+    /// Starts the span of a fetch of `game` from `address`.
+    pub(crate) fn start(game: &str, protocol: &'static str, address: SocketAddr) -> Self {
+        if cfg!(feature = "silent") {
+            return FetchSpan { span: None };
+        }
+
+        let tracer = global::tracer(SCOPE);
+        let span = tracer
+            .span_builder("fetch")
+            .with_kind(SpanKind::Client)
+            .with_attributes([
+                KeyValue::new("gstat.game", game.to_string()),
+                KeyValue::new("gstat.protocol", protocol),
+                KeyValue::new("server.address", address.ip().to_string()),
+                KeyValue::new("server.port", i64::from(address.port())),
+            ])
+            .start(&tracer);
+
+        FetchSpan { span: Some(span) }
+    }
+
+    /// Ends the span with the outcome of the fetch.
+    ///
+    /// # Parameters
+    ///
+    /// * `outcome`: The round-trip time of the response, or the kind and message of the
+    ///   error the fetch failed with.
+    pub(crate) fn end(mut self, outcome: Result<Duration, (ErrorKind, String)>) {
+        let Some(span) = &mut self.span else {
+            return;
+        };
+
+        match outcome {
+            Ok(rtt) => {
+                span.set_attribute(KeyValue::new("gstat.rtt", rtt.as_secs_f64()));
+                span.set_status(Status::Ok);
+            }
+            Err((kind, message)) => {
+                span.set_attribute(KeyValue::new("error.type", kind.name()));
+                span.set_status(Status::error(message));
+            }
+        }
+
+        span.end();
+    }
+}
+
+/// A `MetricsSink` recording OpenTelemetry metrics.
+///
+/// Counters and the query duration histogram carry the game as the `gstat.game` attribute,
+/// and the protocol as `gstat.protocol` if one is set with `with_protocol`; failures carry
+/// the kind of their error as `error.type`. Servers are deliberately not an attribute, to
+/// keep the number of time series bounded when scanning many servers.
+///
+/// | Instrument | Kind | Unit |
+/// |---|---|---|
+/// | `gstat.queries.sent` | Counter | `{query}` |
+/// | `gstat.responses.ok` | Counter | `{response}` |
+/// | `gstat.responses.invalid` | Counter | `{response}` |
+/// | `gstat.queries.failed` | Counter | `{query}` |
+/// | `gstat.query.duration` | Histogram | `s` |
+/// | `gstat.packets.sent`, `gstat.packets.received` | Counter | `{packet}` |
+/// | `gstat.bytes.sent`, `gstat.bytes.received` | Counter | `By` |
+#[derive(Clone)]
+pub struct OtelMetrics {
+    queries_sent: Counter<u64>,
+    responses_ok: Counter<u64>,
+    parse_failures: Counter<u64>,
+    queries_failed: Counter<u64>,
+    duration: Histogram<f64>,
+    packets_sent: Counter<u64>,
+    packets_received: Counter<u64>,
+    bytes_sent: Counter<u64>,
+    bytes_received: Counter<u64>,
+    protocol: Option<KeyValue>,
+}
+
+impl OtelMetrics {
+    /// Creates a sink recording through the meter of the global meter provider.
+    pub fn new() -> Self {
+        Self::with_meter(&global::meter(SCOPE))
+    }
+
+    /// Creates a sink recording through `meter`.
+    pub fn with_meter(meter: &Meter) -> Self {
+        let counter = |name: &'static str, unit: &'static str, description: &'static str| {
+            meter
+                .u64_counter(name)
+                .with_unit(unit)
+                .with_description(description)
+                .build()
+        };
+
+        OtelMetrics {
+            queries_sent: counter("gstat.queries.sent", "{query}", "Queries sent"),
+            responses_ok: counter(
+                "gstat.responses.ok",
+                "{response}",
+                "Responses received and parsed",
+            ),
+            parse_failures: counter(
+                "gstat.responses.invalid",
+                "{response}",
+                "Responses that couldn't be parsed",
+            ),
+            queries_failed: counter(
+                "gstat.queries.failed",
+                "{query}",
+                "Fetches that failed after all attempts",
+            ),
+            duration: meter
+                .f64_histogram("gstat.query.duration")
+                .with_unit("s")
+                .with_description("Round-trip time of successful queries")
+                .with_boundaries(vec![0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.0, 5.0, 10.0])
+                .build(),
+            packets_sent: counter("gstat.packets.sent", "{packet}", "Packets sent"),
+            packets_received: counter("gstat.packets.received", "{packet}", "Packets received"),
+            bytes_sent: counter("gstat.bytes.sent", "By", "Bytes sent"),
+            bytes_received: counter("gstat.bytes.received", "By", "Bytes received"),
+            protocol: None,
+        }
+    }
+
+    /// Sets the protocol recorded as the `gstat.protocol` attribute.
+    pub fn with_protocol(mut self, protocol: impl Into<String>) -> Self {
+        self.protocol = Some(KeyValue::new("gstat.protocol", protocol.into()));
+        self
+    }
+
+    /// Returns the attributes of a measurement for `game`.
+    fn attributes(&self, game: &str) -> Vec<KeyValue> {
+        let mut attributes = vec![KeyValue::new("gstat.game", game.to_string())];
+        attributes.extend(self.protocol.clone());
+        attributes
+    }
+}
+
+impl Default for OtelMetrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Debug for OtelMetrics {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.debug_struct("OtelMetrics")
+            .field("protocol", &self.protocol)
+            .finish_non_exhaustive()
+    }
+}
+
+impl MetricsSink for OtelMetrics {
+    fn query_sent(&self, game: &str, _peer: SocketAddr) {
+        self.queries_sent.add(1, &self.attributes(game));
+    }
+
+    fn response_ok(&self, game: &str, _peer: SocketAddr) {
+        self.responses_ok.add(1, &self.attributes(game));
+    }
+
+    fn parse_failure(&self, game: &str, _peer: SocketAddr) {
+        self.parse_failures.add(1, &self.attributes(game));
+    }
+
+    fn query_failed(&self, game: &str, _peer: SocketAddr, kind: ErrorKind) {
+        let mut attributes = self.attributes(game);
+        attributes.push(KeyValue::new("error.type", kind.name()));
+
+        self.queries_failed.add(1, &attributes);
+    }
+
+    fn rtt(&self, game: &str, _peer: SocketAddr, rtt: Duration) {
+        self.duration
+            .record(rtt.as_secs_f64(), &self.attributes(game));
+    }
+
+    fn packet_sent(&self, _peer: SocketAddr, bytes: usize) {
+        let attributes: Vec<KeyValue> = self.protocol.iter().cloned().collect();
+
+        self.packets_sent.add(1, &attributes);
+        self.bytes_sent.add(bytes as u64, &attributes);
+    }
+
+    fn packet_received(&self, _peer: SocketAddr, bytes: usize) {
+        let attributes: Vec<KeyValue> = self.protocol.iter().cloned().collect();
+
+        self.packets_received.add(1, &attributes);
+        self.bytes_received.add(bytes as u64, &attributes);
+    }
+}
@@ -0,0 +1,244 @@
+//! Measuring the round-trip time to a server without querying its status.
+//!
+//! `ping` repeats the lightest exchange a protocol offers, e.g. an `A2A_PING`, a RakNet
+//! unconnected ping, a Minecraft Server List Ping without its status or a bare TCP
+//! connect, and summarizes the round-trip times of the
+//! samples that were answered. Server browsers filling a latency column use it to avoid
+//! downloading and parsing a full status for every row.
+
+use crate::{
+    detect::{self, Probe},
+    registry::{ProtocolEntry, Transport},
+    runtime::{self, TcpStream},
+    slp::{self, SlpConnection},
+    timeout::TimeoutSettings,
+};
+
+use std::{io, net::SocketAddr, time::Duration};
+
+/// The settings of a series of pings.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Ping {
+    samples: usize,
+    interval: Duration,
+    limit: Duration,
+}
+
+impl Default for Ping {
+    fn default() -> Self {
+        Ping {
+            samples: 5,
+            interval: Duration::from_millis(100),
+            limit: Duration::from_secs(1),
+        }
+    }
+}
+
+impl Ping {
+    /// Creates a series of 5 pings, 100 milliseconds apart, each waiting up to a second.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the number of pings sent.
+    ///
+    /// # Parameters
+    ///
+    /// * `samples`: The number of round trips to measure, at least one.
+    pub fn with_samples(mut self, samples: usize) -> Self {
+        self.samples = samples.max(1);
+        self
+    }
+
+    /// Sets the pause between the end of a ping and the start of the next.
+    ///
+    /// # Parameters
+    ///
+    /// * `interval`: The pause, so the server doesn't see the pings as a flood.
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Sets how long each ping waits for its answer before counting as lost.
+    ///
+    /// # Parameters
+    ///
+    /// * `limit`: The time limit of a single ping.
+    pub fn with_limit(mut self, limit: Duration) -> Self {
+        self.limit = limit;
+        self
+    }
+
+    /// Pings a server repeatedly.
+    ///
+    /// # Parameters
+    ///
+    /// * `address`: The query address of the server.
+    /// * `protocol`: The protocol the server answers on that address.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the round-trip times of the answered pings, or an `io::Error`
+    /// with the kind `Unsupported` if gstat has no ping for the protocol.
+    pub async fn run(&self, address: SocketAddr, protocol: &ProtocolEntry) -> io::Result<Rtt> {
+        let exchange = Exchange::for_protocol(protocol).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::Unsupported,
+                format!("no ping for the {} protocol", protocol.name),
+            )
+        })?;
+        let mut rtt = Rtt::default();
+
+        for sample in 0..self.samples {
+            if sample > 0 {
+                runtime::sleep(self.interval).await;
+            }
+
+            let started = runtime::try_now();
+
+            match runtime::timeout(self.limit, exchange.run(address)).await {
+                Ok(Ok(())) => rtt.samples.push(runtime::elapsed(started)),
+                _ => rtt.lost += 1,
+            }
+        }
+
+        rtt.samples.sort_unstable();
+
+        Ok(rtt)
+    }
+}
+
+/// Pings a server with the default settings of `Ping`.
+///
+/// # Parameters
+///
+/// * `address`: The query address of the server.
+/// * `protocol`: The protocol the server answers on that address.
+///
+/// # Returns
+///
+/// A `Result` containing the round-trip times of the answered pings, or an `io::Error` if
+/// gstat has no ping for the protocol.
+pub async fn ping(address: SocketAddr, protocol: &ProtocolEntry) -> io::Result<Rtt> {
+    Ping::new().run(address, protocol).await
+}
+
+/// The round-trip times measured by a series of pings.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Rtt {
+    samples: Vec<Duration>,
+    lost: usize,
+}
+
+impl Rtt {
+    /// Returns the round-trip times of the answered pings, fastest first.
+    pub fn samples(&self) -> &[Duration] {
+        &self.samples
+    }
+
+    /// Returns the number of pings that were not answered in time.
+    pub fn lost(&self) -> usize {
+        self.lost
+    }
+
+    /// Returns the share of pings that were not answered in time, between 0 and 1.
+    pub fn loss(&self) -> f64 {
+        match self.samples.len() + self.lost {
+            0 => 0.0,
+            sent => self.lost as f64 / sent as f64,
+        }
+    }
+
+    /// Returns a percentile of the round-trip times, by the nearest-rank method.
+    ///
+    /// # Parameters
+    ///
+    /// * `percentile`: The percentile, clamped between 0 and 100.
+    ///
+    /// # Returns
+    ///
+    /// The smallest round-trip time at least `percentile` percent of the answered pings
+    /// took, or `None` if no ping was answered.
+    pub fn percentile(&self, percentile: f64) -> Option<Duration> {
+        let rank = (percentile.clamp(0.0, 100.0) / 100.0 * self.samples.len() as f64).ceil();
+
+        self.samples.get((rank as usize).max(1) - 1).copied()
+    }
+
+    /// Returns the median round-trip time, or `None` if no ping was answered.
+    pub fn median(&self) -> Option<Duration> {
+        self.percentile(50.0)
+    }
+
+    /// Returns the fastest round-trip time, or `None` if no ping was answered.
+    pub fn min(&self) -> Option<Duration> {
+        self.samples.first().copied()
+    }
+
+    /// Returns the slowest round-trip time, or `None` if no ping was answered.
+    pub fn max(&self) -> Option<Duration> {
+        self.samples.last().copied()
+    }
+}
+
+/// The lightest exchange a protocol offers.
+#[derive(Clone, Copy)]
+enum Exchange {
+    /// A Valve `A2A_PING`, answered with `A2A_ACK`.
+    A2aPing,
+    /// A Minecraft Server List Ping handshake followed by a ping alone, skipping the
+    /// status document.
+    SlpPing,
+    /// The cheapest status request of the protocol, as sent by `detect`.
+    Probe(Probe),
+    /// A TCP connect, timing the handshake alone.
+    Connect,
+}
+
+impl Exchange {
+    fn for_protocol(protocol: &ProtocolEntry) -> Option<Self> {
+        match (protocol.id, protocol.transport) {
+            ("a2s", _) => Some(Exchange::A2aPing),
+            ("minecraft-slp", _) => Some(Exchange::SlpPing),
+            (_, Transport::Tcp) => Some(Exchange::Connect),
+            (id, Transport::Udp) => Probe::for_protocol(id).map(Exchange::Probe),
+        }
+    }
+
+    async fn run(self, address: SocketAddr) -> io::Result<()> {
+        match self {
+            Exchange::A2aPing => {
+                let answer = detect::exchange(address, b"\xFF\xFF\xFF\xFFi").await?;
+
+                if answer.starts_with(b"\xFF\xFF\xFF\xFFj") {
+                    Ok(())
+                } else {
+                    Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        "not an A2A_ACK answer",
+                    ))
+                }
+            }
+            Exchange::SlpPing => slp_ping(address).await,
+            Exchange::Probe(probe) => probe.run(address).await.map(drop),
+            Exchange::Connect => {
+                let mut stream = TcpStream::connect(address).await?;
+                let _ = stream.shutdown().await;
+
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Sends a Server List Ping handshake and a ping, and checks that the server echoes it.
+async fn slp_ping(address: SocketAddr) -> io::Result<()> {
+    let mut connection = SlpConnection::connect(address, &TimeoutSettings::default())
+        .await
+        .map_err(slp::into_io)?;
+    connection.ping().await.map_err(slp::into_io)?;
+    connection.close().await;
+
+    Ok(())
+}
@@ -0,0 +1,592 @@
+//! Polling servers on an interval and reporting what changed.
+//!
+//! Monitoring bots query their servers every few seconds and announce when one goes down,
+//! comes back, changes map or gains players. `Poller` does the polling on a `Schedule` and
+//! turns the results into `PollEvent`s, holding back transitions between online and offline
+//! until they were seen on several polls in a row, so a single lost packet doesn't announce
+//! an outage.
+
+use crate::{
+    cancel::{CancellationToken, Cancelled},
+    clock::{Clock, RuntimeClock, Sleep},
+    prelude::{Error, Game, Protocol, Response, ServerInfo, TimeoutSettings},
+    schedule::Schedule,
+    standards::dyn_protocol::BoxFuture,
+    subscribe::{Feed, Snapshot},
+};
+
+use std::{
+    collections::VecDeque,
+    fmt::{Debug, Display, Formatter, Result as FmtResult},
+    future::{poll_fn, Future},
+    hash::Hash,
+    net::SocketAddr,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+    time::Instant,
+};
+
+/// A change in the state of a polled server.
+#[derive(Clone, Debug, PartialEq)]
+pub enum PollEvent<T, E> {
+    /// The server answered after being offline or unknown.
+    Online {
+        /// The server.
+        target: T,
+        /// What the server reported.
+        info: ServerInfo,
+    },
+    /// The server stopped answering, or never answered.
+    Offline {
+        /// The server.
+        target: T,
+        /// The error of the poll that confirmed the outage.
+        error: E,
+    },
+    /// The number of players changed while the server was online.
+    PlayersChanged {
+        /// The server.
+        target: T,
+        /// The number of players on the previous poll.
+        from: u32,
+        /// The number of players now.
+        to: u32,
+    },
+    /// The map changed while the server was online.
+    MapChanged {
+        /// The server.
+        target: T,
+        /// The map on the previous poll.
+        from: String,
+        /// The map now.
+        to: String,
+    },
+}
+
+impl<T, E> PollEvent<T, E> {
+    /// Returns the server the event is about.
+    pub fn target(&self) -> &T {
+        match self {
+            PollEvent::Online { target, .. }
+            | PollEvent::Offline { target, .. }
+            | PollEvent::PlayersChanged { target, .. }
+            | PollEvent::MapChanged { target, .. } => target,
+        }
+    }
+}
+
+/// How many polls in a row must agree before a server is reported online or offline.
+///
+/// Servers that drop the odd packet or restart quickly would otherwise flap between the two
+/// states. The counts apply to every transition, including the first report of a server.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Debounce {
+    /// The failed polls in a row after which a server is reported offline.
+    pub offline_after: u32,
+    /// The answered polls in a row after which a server is reported online.
+    pub online_after: u32,
+}
+
+impl Debounce {
+    /// Creates a debounce reporting servers offline after 2 failed polls in a row and
+    /// online after the first answer.
+    pub fn new() -> Self {
+        Debounce {
+            offline_after: 2,
+            online_after: 1,
+        }
+    }
+
+    /// Reports every transition as soon as it is seen.
+    pub fn none() -> Self {
+        Debounce {
+            offline_after: 1,
+            online_after: 1,
+        }
+    }
+
+    /// Sets the failed polls in a row after which a server is reported offline.
+    ///
+    /// # Parameters
+    ///
+    /// * `polls`: The number of polls. `0` is treated as `1`.
+    pub fn with_offline_after(mut self, polls: u32) -> Self {
+        self.offline_after = polls.max(1);
+        self
+    }
+
+    /// Sets the answered polls in a row after which a server is reported online.
+    ///
+    /// # Parameters
+    ///
+    /// * `polls`: The number of polls. `0` is treated as `1`.
+    pub fn with_online_after(mut self, polls: u32) -> Self {
+        self.online_after = polls.max(1);
+        self
+    }
+}
+
+impl Default for Debounce {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Whether a server was last reported online or offline.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Status {
+    Unknown,
+    Online,
+    Offline,
+}
+
+/// A polled server and what is known about it.
+struct Tracked<T> {
+    target: T,
+    schedule: Schedule,
+    /// When the server is polled next, or `None` while a poll is in flight.
+    due: Option<Instant>,
+    status: Status,
+    /// The answered polls in a row, or the failed polls in a row if negative.
+    streak: i64,
+    /// What the server reported on the last poll while online.
+    last: Option<ServerInfo>,
+}
+
+/// Hands the outcome of every poll to a `Feed`.
+trait Publish<T, E>: Send {
+    /// Publishes the state of `target` after a poll.
+    fn publish(&self, target: &T, outcome: &Result<ServerInfo, E>, online: bool, at: Instant);
+
+    /// Closes the feed.
+    fn close(&self);
+}
+
+impl<T, E> Publish<T, E> for Feed<T>
+where
+    T: Clone + Eq + Hash + Send,
+    E: Display,
+{
+    fn publish(&self, target: &T, outcome: &Result<ServerInfo, E>, online: bool, at: Instant) {
+        let (info, error) = match outcome {
+            Ok(info) => (Some(info.clone()), None),
+            Err(error) => (None, Some(error.to_string())),
+        };
+
+        Feed::publish(
+            self,
+            Snapshot {
+                target: target.clone(),
+                info,
+                error,
+                online,
+                polled_at: at,
+            },
+        );
+    }
+
+    fn close(&self) {
+        Feed::close(self);
+    }
+}
+
+/// Starts the poll of a server.
+type StartPoll<'a, T, E> = Box<dyn FnMut(&T) -> BoxFuture<'a, Result<ServerInfo, E>> + Send + 'a>;
+
+/// `Poller` polls servers on their schedule and yields the changes it sees.
+///
+/// Each server is first polled after `Schedule::first_delay` and then every
+/// `Schedule::next_delay`, with the next delay counted from the end of the previous poll, so
+/// a slow server is never polled twice at once. Answers and failures pass through the
+/// `Debounce` before they are reported.
+///
+/// Like `QueryMany`, the poller runs its polls within the task awaiting `next`, without
+/// spawning. It waits on its `Clock`, so tests can drive it with a `ManualClock`, and polls
+/// until it is dropped or its `CancellationToken` is cancelled.
+pub struct Poller<'a, T, E> {
+    targets: Vec<Tracked<T>>,
+    schedule: Schedule,
+    debounce: Debounce,
+    start: StartPoll<'a, T, E>,
+    in_flight: Vec<BoxFuture<'a, (usize, Result<ServerInfo, E>)>>,
+    events: VecDeque<PollEvent<T, E>>,
+    clock: Arc<dyn Clock>,
+    sleep: Option<(Instant, Sleep)>,
+    cancelled: Option<Cancelled>,
+    feed: Option<Box<dyn Publish<T, E> + Send + 'a>>,
+}
+
+/// Polls the servers of `game` with its default query, reporting what their responses
+/// have in common.
+///
+/// # Parameters
+///
+/// * `game`: The game the servers run.
+/// * `targets`: The servers to poll, e.g. `SocketAddr`s or `ServerAddress`es.
+/// * `schedule`: When each server is polled.
+/// * `timeouts`: The time limits applied to each poll.
+pub fn poll_game<'a, G, P, T, I>(
+    game: &'a G,
+    targets: I,
+    schedule: Schedule,
+    timeouts: TimeoutSettings,
+) -> Poller<'a, T, Error<P::E>>
+where
+    G: Game<'a, P> + Sync,
+    P: Protocol<'a> + 'a,
+    T: Clone + Hash + Into<SocketAddr> + Send + 'a,
+    I: IntoIterator<Item = T>,
+{
+    Poller::new(targets, schedule, move |target: &T| {
+        let fetch = game.fetch_with(game.default_query(), target.clone().into(), timeouts);
+
+        async move { fetch.await.map(|fetched| fetched.response.to_common()) }
+    })
+}
+
+impl<'a, T, E> Poller<'a, T, E>
+where
+    T: Clone + Hash + Send + 'a,
+    E: 'a,
+{
+    /// Creates a poller running `poll` for each target on `schedule`.
+    ///
+    /// # Parameters
+    ///
+    /// * `targets`: The servers to poll.
+    /// * `schedule`: When each server is polled, unless added with its own schedule.
+    /// * `poll`: Polls a server, e.g. with `Game::fetch_with` and `Response::to_common`.
+    pub fn new<I, F, Fut>(targets: I, schedule: Schedule, mut poll: F) -> Self
+    where
+        I: IntoIterator<Item = T>,
+        F: FnMut(&T) -> Fut + Send + 'a,
+        Fut: Future<Output = Result<ServerInfo, E>> + Send + 'a,
+    {
+        let mut poller = Poller {
+            targets: Vec::new(),
+            schedule,
+            debounce: Debounce::default(),
+            start: Box::new(move |target| Box::pin(poll(target))),
+            in_flight: Vec::new(),
+            events: VecDeque::new(),
+            clock: Arc::new(RuntimeClock),
+            sleep: None,
+            cancelled: None,
+            feed: None,
+        };
+
+        for target in targets {
+            poller.add(target, schedule);
+        }
+
+        poller
+    }
+
+    /// Adds a server polled on its own schedule, e.g. a more frequent one for a tournament
+    /// server.
+    ///
+    /// # Parameters
+    ///
+    /// * `target`: The server to poll.
+    /// * `schedule`: When the server is polled.
+    pub fn with_target(mut self, target: T, schedule: Schedule) -> Self {
+        self.add(target, schedule);
+        self
+    }
+
+    /// Sets how many polls in a row must agree before a server is reported online or
+    /// offline.
+    pub fn with_debounce(mut self, debounce: Debounce) -> Self {
+        self.debounce = debounce;
+        self
+    }
+
+    /// Sets the clock the poller waits on, e.g. a `ManualClock` in tests.
+    ///
+    /// The first poll of every server added so far is rescheduled on the new clock.
+    pub fn with_clock(mut self, clock: impl Clock + 'static) -> Self {
+        self.clock = Arc::new(clock);
+        self.sleep = None;
+
+        let now = self.clock.now();
+
+        for tracked in &mut self.targets {
+            tracked.due = Some(now + tracked.schedule.first_delay(&tracked.target));
+        }
+
+        self
+    }
+
+    /// Stops polling once `token` is cancelled.
+    ///
+    /// On cancellation, the polls in flight are dropped and `next` returns `None`.
+    ///
+    /// # Parameters
+    ///
+    /// * `token`: The token that stops the poller, e.g. one cancelled on shutdown.
+    pub fn with_cancellation(mut self, token: &CancellationToken) -> Self {
+        self.cancelled = Some(token.cancelled());
+        self
+    }
+
+    /// Publishes the outcome of every poll to `feed`, which is closed when the poller is
+    /// dropped.
+    ///
+    /// Consumers subscribe to the feed instead of polling the servers themselves, so any
+    /// number of them share this poller's traffic.
+    ///
+    /// # Parameters
+    ///
+    /// * `feed`: The feed to publish to.
+    pub fn with_feed(mut self, feed: Feed<T>) -> Self
+    where
+        T: Eq,
+        E: Display,
+    {
+        self.feed = Some(Box::new(feed));
+        self
+    }
+
+    /// Returns the schedule servers are polled on unless added with their own.
+    pub fn schedule(&self) -> Schedule {
+        self.schedule
+    }
+
+    /// Returns the number of servers polled.
+    pub fn len(&self) -> usize {
+        self.targets.len()
+    }
+
+    /// Returns `true` if no server is polled.
+    pub fn is_empty(&self) -> bool {
+        self.targets.is_empty()
+    }
+
+    /// Returns the number of polls currently in flight.
+    pub fn in_flight(&self) -> usize {
+        self.in_flight.len()
+    }
+
+    /// Waits for the next change.
+    ///
+    /// # Returns
+    ///
+    /// The change, or `None` once the poller was cancelled or if it has no servers.
+    pub async fn next(&mut self) -> Option<PollEvent<T, E>> {
+        poll_fn(|cx| self.poll_next(cx)).await
+    }
+
+    /// Polls until the poller is cancelled, discarding the changes.
+    ///
+    /// This drives a poller whose results are consumed through its feed.
+    pub async fn run(mut self) {
+        while self.next().await.is_some() {}
+    }
+
+    /// Starts the polls that are due and drives those in flight until one of them
+    /// produces a change.
+    ///
+    /// # Returns
+    ///
+    /// `Poll::Ready(Some(_))` with the next change, `Poll::Ready(None)` once the poller
+    /// was cancelled or if it has no servers, or `Poll::Pending` while waiting.
+    pub fn poll_next(&mut self, cx: &mut Context<'_>) -> Poll<Option<PollEvent<T, E>>> {
+        if let Some(cancelled) = &mut self.cancelled {
+            if Pin::new(cancelled).poll(cx).is_ready() {
+                self.in_flight.clear();
+                self.events.clear();
+                self.sleep = None;
+
+                return Poll::Ready(None);
+            }
+        }
+
+        if self.targets.is_empty() {
+            return Poll::Ready(None);
+        }
+
+        loop {
+            if let Some(event) = self.events.pop_front() {
+                return Poll::Ready(Some(event));
+            }
+
+            self.start_due();
+
+            let mut position = 0;
+
+            while position < self.in_flight.len() {
+                match self.in_flight[position].as_mut().poll(cx) {
+                    Poll::Ready((index, outcome)) => {
+                        drop(self.in_flight.swap_remove(position));
+                        self.complete(index, outcome);
+                    }
+                    Poll::Pending => position += 1,
+                }
+            }
+
+            if !self.events.is_empty() {
+                continue;
+            }
+
+            let Some(due) = self.targets.iter().filter_map(|tracked| tracked.due).min() else {
+                return Poll::Pending;
+            };
+
+            if self.sleep.as_ref().is_none_or(|(until, _)| *until != due) {
+                let wait = due.saturating_duration_since(self.clock.now());
+                self.sleep = Some((due, self.clock.sleep(wait)));
+            }
+
+            let (_, sleep) = self.sleep.as_mut().expect("sleep was just set");
+
+            if sleep.as_mut().poll(cx).is_pending() {
+                return Poll::Pending;
+            }
+
+            self.sleep = None;
+        }
+    }
+
+    /// Adds a server, scheduling its first poll.
+    fn add(&mut self, target: T, schedule: Schedule) {
+        let due = self.clock.now() + schedule.first_delay(&target);
+
+        self.targets.push(Tracked {
+            target,
+            schedule,
+            due: Some(due),
+            status: Status::Unknown,
+            streak: 0,
+            last: None,
+        });
+    }
+
+    /// Starts the poll of every server whose time has come.
+    fn start_due(&mut self) {
+        let now = self.clock.now();
+
+        for (index, tracked) in self.targets.iter_mut().enumerate() {
+            if tracked.due.is_some_and(|due| due <= now) {
+                tracked.due = None;
+
+                let poll = (self.start)(&tracked.target);
+                self.in_flight
+                    .push(Box::pin(async move { (index, poll.await) }));
+            }
+        }
+    }
+
+    /// Records the outcome of a poll, queueing the changes it reveals and scheduling the
+    /// next poll.
+    fn complete(&mut self, index: usize, outcome: Result<ServerInfo, E>) {
+        let debounce = self.debounce;
+        let now = self.clock.now();
+        let tracked = &mut self.targets[index];
+        tracked.due = Some(now + tracked.schedule.next_delay());
+
+        let transition = match &outcome {
+            Ok(_) => {
+                tracked.streak = tracked.streak.max(0) + 1;
+
+                tracked.status != Status::Online
+                    && tracked.streak >= i64::from(debounce.online_after)
+            }
+            Err(_) => {
+                tracked.streak = tracked.streak.min(0) - 1;
+
+                tracked.status != Status::Offline
+                    && -tracked.streak >= i64::from(debounce.offline_after)
+            }
+        };
+
+        if transition {
+            tracked.status = match outcome {
+                Ok(_) => Status::Online,
+                Err(_) => Status::Offline,
+            };
+        }
+
+        if let Some(feed) = &self.feed {
+            feed.publish(
+                &tracked.target,
+                &outcome,
+                tracked.status == Status::Online,
+                now,
+            );
+        }
+
+        match outcome {
+            Ok(info) if transition => {
+                tracked.last = Some(info.clone());
+
+                self.events.push_back(PollEvent::Online {
+                    target: tracked.target.clone(),
+                    info,
+                });
+            }
+            Ok(info) if tracked.status == Status::Online => {
+                if let Some(last) = &tracked.last {
+                    changes(&tracked.target, last, &info, &mut self.events);
+                }
+
+                tracked.last = Some(info);
+            }
+            Err(error) if transition => {
+                tracked.last = None;
+
+                self.events.push_back(PollEvent::Offline {
+                    target: tracked.target.clone(),
+                    error,
+                });
+            }
+            Ok(_) | Err(_) => {}
+        }
+    }
+}
+
+impl<T, E> Drop for Poller<'_, T, E> {
+    /// Closes the feed, ending its subscriptions.
+    fn drop(&mut self) {
+        if let Some(feed) = &self.feed {
+            feed.close();
+        }
+    }
+}
+
+/// Queues the changes between two answers of an online server.
+fn changes<T: Clone, E>(
+    target: &T,
+    last: &ServerInfo,
+    info: &ServerInfo,
+    events: &mut VecDeque<PollEvent<T, E>>,
+) {
+    if last.map != info.map {
+        events.push_back(PollEvent::MapChanged {
+            target: target.clone(),
+            from: last.map.clone(),
+            to: info.map.clone(),
+        });
+    }
+
+    if last.players != info.players {
+        events.push_back(PollEvent::PlayersChanged {
+            target: target.clone(),
+            from: last.players,
+            to: info.players,
+        });
+    }
+}
+
+impl<T, E> Debug for Poller<'_, T, E> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.debug_struct("Poller")
+            .field("targets", &self.targets.len())
+            .field("schedule", &self.schedule)
+            .field("debounce", &self.debounce)
+            .field("in_flight", &self.in_flight.len())
+            .field("cancellable", &self.cancelled.is_some())
+            .field("feed", &self.feed.is_some())
+            .finish_non_exhaustive()
+    }
+}
@@ -0,0 +1,330 @@
+//! Pacing outgoing packets with token buckets.
+//!
+//! Hosting providers throttle or block sources that send bursts of queries to their
+//! networks. A `RateLimiter` paces packets globally and per destination subnet; transports
+//! take a permit before every packet they send, and `QueryMany` before every query it
+//! starts.
+
+use crate::clock::{Clock, RuntimeClock};
+
+use std::{
+    collections::HashMap,
+    fmt::{Debug, Formatter, Result as FmtResult},
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+/// The number of subnet buckets kept before idle ones are first discarded.
+const MAX_IDLE_SUBNETS: usize = 4096;
+
+/// A rate in packets per second together with the burst allowed on top of it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RateLimit {
+    /// The sustained number of packets per second.
+    pub packets_per_second: f64,
+    /// The number of packets that may be sent at once after a quiet period.
+    pub burst: u32,
+}
+
+impl RateLimit {
+    /// Creates a rate limit.
+    ///
+    /// # Parameters
+    ///
+    /// * `packets_per_second`: The sustained rate. Rates that aren't positive are treated
+    ///   as one packet per hour, so a misconfiguration can't disable the limit.
+    /// * `burst`: The number of packets that may be sent at once. `0` is treated as `1`.
+    pub fn new(packets_per_second: f64, burst: u32) -> Self {
+        let packets_per_second = if packets_per_second > 0.0 {
+            packets_per_second
+        } else {
+            1.0 / 3600.0
+        };
+
+        RateLimit {
+            packets_per_second,
+            burst: burst.max(1),
+        }
+    }
+}
+
+/// The state of a single token bucket.
+#[derive(Clone, Copy, Debug)]
+struct Bucket {
+    /// The tokens available, negative while packets are waiting for their turn.
+    tokens: f64,
+    refilled: Instant,
+}
+
+impl Bucket {
+    /// Creates a full bucket.
+    fn full(limit: &RateLimit, now: Instant) -> Self {
+        Bucket {
+            tokens: f64::from(limit.burst),
+            refilled: now,
+        }
+    }
+
+    /// Adds the tokens accumulated since the last refill.
+    fn refill(&mut self, limit: &RateLimit, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.refilled).as_secs_f64();
+
+        self.tokens =
+            (self.tokens + elapsed * limit.packets_per_second).min(f64::from(limit.burst));
+        self.refilled = now;
+    }
+
+    /// Takes a token, returning how long the caller must wait until it is its turn.
+    fn reserve(&mut self, limit: &RateLimit, now: Instant) -> Duration {
+        self.refill(limit, now);
+        self.tokens -= 1.0;
+
+        if self.tokens >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-self.tokens / limit.packets_per_second)
+        }
+    }
+
+    /// Takes `tokens` tokens, which `available_in` found available.
+    fn take(&mut self, limit: &RateLimit, now: Instant, tokens: f64) {
+        self.refill(limit, now);
+        self.tokens -= tokens;
+    }
+
+    /// Returns how long until `tokens` tokens are available, without taking them.
+    fn available_in(&mut self, limit: &RateLimit, now: Instant, tokens: f64) -> Duration {
+        self.refill(limit, now);
+
+        if self.tokens >= tokens {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64((tokens - self.tokens) / limit.packets_per_second)
+        }
+    }
+
+    /// Returns `true` if the bucket is full, so forgetting it changes nothing.
+    fn is_full(&self, limit: &RateLimit) -> bool {
+        self.tokens >= f64::from(limit.burst)
+    }
+}
+
+#[derive(Default)]
+struct Limits {
+    global: Option<(RateLimit, Bucket)>,
+    subnet: Option<RateLimit>,
+    subnets: HashMap<IpAddr, Bucket>,
+    /// The number of subnet buckets at which idle ones are discarded next.
+    prune_at: usize,
+}
+
+impl Limits {
+    /// Returns the bucket of the subnet `destination` belongs to, if subnets are limited.
+    fn subnet_bucket(
+        &mut self,
+        destination: IpAddr,
+        now: Instant,
+    ) -> Option<(RateLimit, &mut Bucket)> {
+        let limit = self.subnet?;
+
+        // Pruning only once the table doubled keeps the cost per packet constant while
+        // many subnets are busy.
+        if self.subnets.len() >= self.prune_at.max(MAX_IDLE_SUBNETS) {
+            self.subnets.retain(|_, bucket| {
+                bucket.refill(&limit, now);
+                !bucket.is_full(&limit)
+            });
+            self.prune_at = self.subnets.len() * 2;
+        }
+
+        let bucket = self
+            .subnets
+            .entry(subnet_of(destination))
+            .or_insert_with(|| Bucket::full(&limit, now));
+
+        Some((limit, bucket))
+    }
+}
+
+/// `RateLimiter` paces outgoing packets with a global and a per-subnet token bucket.
+///
+/// Each bucket holds up to `burst` tokens and refills at `packets_per_second`; every packet
+/// takes a token. Subnets are the /24 of IPv4 destinations and the /64 of IPv6
+/// destinations, the usual granularity at which providers assign addresses and rate
+/// limit.
+///
+/// Permits are handed out in order: a packet that has to wait reserves its token right
+/// away, so later packets queue behind it instead of racing for the next token. Clones
+/// share their buckets, so one limiter can be handed to every protocol instance and to
+/// `QueryMany`. A limiter without limits never waits.
+#[derive(Clone)]
+pub struct RateLimiter {
+    limits: Arc<Mutex<Limits>>,
+    clock: Arc<dyn Clock>,
+}
+
+impl RateLimiter {
+    /// Creates a limiter without limits.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Limits the packets sent to all destinations together.
+    pub fn with_global(self, limit: RateLimit) -> Self {
+        let now = self.clock.now();
+        self.lock().global = Some((limit, Bucket::full(&limit, now)));
+        self
+    }
+
+    /// Limits the packets sent to each /24 (IPv4) or /64 (IPv6) subnet.
+    pub fn with_per_subnet(self, limit: RateLimit) -> Self {
+        let mut limits = self.lock();
+        limits.subnet = Some(limit);
+        limits.subnets.clear();
+        drop(limits);
+
+        self
+    }
+
+    /// Sets the clock buckets refill by, e.g. a `ManualClock` in tests.
+    ///
+    /// Every bucket starts out full again on the new clock.
+    pub fn with_clock(mut self, clock: impl Clock + 'static) -> Self {
+        let now = clock.now();
+        self.clock = Arc::new(clock);
+
+        let mut limits = self.lock();
+        if let Some((limit, bucket)) = &mut limits.global {
+            *bucket = Bucket::full(limit, now);
+        }
+        limits.subnets.clear();
+        drop(limits);
+
+        self
+    }
+
+    /// Waits until a packet may be sent to `destination`.
+    ///
+    /// Cancelling the wait doesn't return the reserved token, so a cancelled packet still
+    /// counts against the limits.
+    pub async fn acquire(&self, destination: SocketAddr) {
+        let wait = self.reserve(destination);
+
+        if !wait.is_zero() {
+            self.clock.sleep(wait).await;
+        }
+    }
+
+    /// Reserves a permit for a packet to `destination` without waiting.
+    ///
+    /// # Returns
+    ///
+    /// How long the caller must wait before sending the packet; zero if it may be sent
+    /// right away.
+    pub fn reserve(&self, destination: SocketAddr) -> Duration {
+        let now = self.clock.now();
+        let mut limits = self.lock();
+        let mut wait = Duration::ZERO;
+
+        if let Some((limit, bucket)) = &mut limits.global {
+            wait = wait.max(bucket.reserve(limit, now));
+        }
+
+        if let Some((limit, bucket)) = limits.subnet_bucket(destination.ip(), now) {
+            wait = wait.max(bucket.reserve(&limit, now));
+        }
+
+        wait
+    }
+
+    /// Takes a permit for a packet to `destination` only if one is available right away.
+    ///
+    /// Unlike `reserve`, a refused caller doesn't queue: no token is taken, so callers that
+    /// drop refused work, such as an HTTP service answering 429, aren't pushed further
+    /// back by every refusal.
+    ///
+    /// # Returns
+    ///
+    /// `Ok` if the permit was taken, or how long until one is available.
+    pub fn try_acquire(&self, destination: SocketAddr) -> Result<(), Duration> {
+        self.try_acquire_many(destination, 1)
+    }
+
+    /// Takes `permits` permits for `destination` at once, only if all of them are available
+    /// right away.
+    ///
+    /// Like `try_acquire`, a refused caller takes no token. A request for more permits
+    /// than a bucket's burst takes the whole burst instead, so it is slowed down rather
+    /// than refused forever.
+    ///
+    /// # Parameters
+    ///
+    /// * `destination`: The address whose subnet bucket the permits are taken from.
+    /// * `permits`: The number of permits, e.g. one per server a request asks for.
+    ///
+    /// # Returns
+    ///
+    /// `Ok` if the permits were taken, or how long until they are available.
+    pub fn try_acquire_many(&self, destination: SocketAddr, permits: u32) -> Result<(), Duration> {
+        let now = self.clock.now();
+        let mut limits = self.lock();
+        let mut wait = Duration::ZERO;
+        let tokens = |limit: &RateLimit| f64::from(permits.min(limit.burst));
+
+        if let Some((limit, bucket)) = &mut limits.global {
+            wait = wait.max(bucket.available_in(limit, now, tokens(limit)));
+        }
+
+        if let Some((limit, bucket)) = limits.subnet_bucket(destination.ip(), now) {
+            wait = wait.max(bucket.available_in(&limit, now, tokens(&limit)));
+        }
+
+        if !wait.is_zero() {
+            return Err(wait);
+        }
+
+        if let Some((limit, bucket)) = &mut limits.global {
+            bucket.take(limit, now, tokens(limit));
+        }
+
+        if let Some((limit, bucket)) = limits.subnet_bucket(destination.ip(), now) {
+            bucket.take(&limit, now, tokens(&limit));
+        }
+
+        Ok(())
+    }
+
+    /// Locks the limits.
+    fn lock(&self) -> std::sync::MutexGuard<'_, Limits> {
+        self.limits.lock().expect("rate limiter lock poisoned")
+    }
+}
+
+impl Default for RateLimiter {
+    fn default() -> Self {
+        RateLimiter {
+            limits: Arc::default(),
+            clock: Arc::new(RuntimeClock),
+        }
+    }
+}
+
+impl Debug for RateLimiter {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        let limits = self.lock();
+
+        f.debug_struct("RateLimiter")
+            .field("global", &limits.global.map(|(limit, _)| limit))
+            .field("per_subnet", &limits.subnet)
+            .finish()
+    }
+}
+
+/// Returns the subnet `ip` belongs to, as its network address.
+fn subnet_of(ip: IpAddr) -> IpAddr {
+    match ip.to_canonical() {
+        IpAddr::V4(ip) => Ipv4Addr::from(u32::from(ip) & 0xffff_ff00).into(),
+        IpAddr::V6(ip) => Ipv6Addr::from(u128::from(ip) & (!0 << 64)).into(),
+    }
+}
@@ -0,0 +1,322 @@
+use crate::{
+    prelude::{Error, ErrorDetail, ErrorKind},
+    runtime,
+};
+
+use std::{
+    collections::HashMap,
+    error::Error as StdError,
+    fmt::{Display, Formatter, Result as FmtResult},
+    future::Future,
+    time::{Duration, Instant},
+};
+
+use bytes::{Bytes, BytesMut};
+
+/// The default time an incomplete set is kept waiting for its remaining fragments.
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The default number of incomplete sets kept at once.
+const DEFAULT_MAX_SETS: usize = 16;
+
+/// The default number of fragments a single set may consist of.
+const DEFAULT_MAX_FRAGMENTS: usize = 128;
+
+/// One part of a response that was split across several packets.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Fragment {
+    /// Identifies the set the fragment belongs to, e.g. the A2S split packet ID.
+    pub id: u32,
+    /// The position of the fragment within its set, starting at `0`.
+    pub index: usize,
+    /// The number of fragments in the set.
+    pub total: usize,
+    /// The part of the response carried by the fragment, without its header.
+    pub payload: Bytes,
+}
+
+/// A packet as classified by a protocol before reassembly.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Packet {
+    /// A packet holding a whole response.
+    Single(Bytes),
+    /// A packet holding part of a response.
+    Fragment(Fragment),
+}
+
+/// Describes why a fragment was rejected.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ReassemblyError {
+    /// The fragment's index is outside its set.
+    InvalidIndex {
+        /// The index of the fragment.
+        index: usize,
+        /// The number of fragments in the set.
+        total: usize,
+    },
+    /// The fragment disagrees with earlier fragments about the size of its set.
+    TotalMismatch {
+        /// The number of fragments announced by earlier fragments.
+        expected: usize,
+        /// The number of fragments announced by this fragment.
+        found: usize,
+    },
+    /// The set is larger than the configured limit.
+    TooManyFragments {
+        /// The number of fragments announced.
+        total: usize,
+        /// The largest number accepted.
+        max: usize,
+    },
+}
+
+impl Display for ReassemblyError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            Self::InvalidIndex { index, total } => {
+                write!(f, "fragment {index} is outside a set of {total}")
+            }
+            Self::TotalMismatch { expected, found } => write!(
+                f,
+                "fragment announces {found} fragments, but its set has {expected}"
+            ),
+            Self::TooManyFragments { total, max } => {
+                write!(f, "set of {total} fragments exceeds the maximum of {max}")
+            }
+        }
+    }
+}
+
+impl StdError for ReassemblyError {}
+
+/// A set whose fragments have not all arrived yet.
+#[derive(Debug)]
+struct PartialSet {
+    fragments: Vec<Option<Bytes>>,
+    received: usize,
+    /// When the first fragment arrived, or `None` on a host without a clock.
+    started: Option<Instant>,
+}
+
+/// `Reassembler` joins responses that were split across several packets.
+///
+/// Protocols such as A2S, GameSpy 3 and GS4 split large responses into numbered fragments
+/// that may arrive in any order, interleaved with fragments of other responses. A protocol
+/// parses each packet's header into a `Fragment` and pushes it; once every fragment of a
+/// set has arrived, the payloads are joined in order. Duplicate fragments are ignored.
+///
+/// Incomplete sets are dropped once they are older than the timeout, and at most a fixed
+/// number of sets and fragments per set are kept, so a misbehaving server can't make the
+/// reassembler grow without bound.
+#[derive(Debug)]
+pub struct Reassembler {
+    sets: HashMap<u32, PartialSet>,
+    timeout: Duration,
+    max_sets: usize,
+    max_fragments: usize,
+}
+
+impl Reassembler {
+    /// Creates a reassembler that waits 5 seconds for incomplete sets, keeping at most 16
+    /// sets of up to 128 fragments each.
+    pub fn new() -> Self {
+        Reassembler {
+            sets: HashMap::new(),
+            timeout: DEFAULT_TIMEOUT,
+            max_sets: DEFAULT_MAX_SETS,
+            max_fragments: DEFAULT_MAX_FRAGMENTS,
+        }
+    }
+
+    /// Sets how long an incomplete set is kept waiting for its remaining fragments.
+    ///
+    /// # Parameters
+    ///
+    /// * `timeout`: The age after which incomplete sets are dropped.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Sets how many incomplete sets are kept at once; the oldest is dropped beyond that.
+    ///
+    /// # Parameters
+    ///
+    /// * `max_sets`: The largest number of incomplete sets.
+    pub fn with_max_sets(mut self, max_sets: usize) -> Self {
+        self.max_sets = max_sets.max(1);
+        self
+    }
+
+    /// Sets how many fragments a single set may consist of.
+    ///
+    /// # Parameters
+    ///
+    /// * `max_fragments`: The largest number of fragments per set.
+    pub fn with_max_fragments(mut self, max_fragments: usize) -> Self {
+        self.max_fragments = max_fragments;
+        self
+    }
+
+    /// Adds a fragment.
+    ///
+    /// # Parameters
+    ///
+    /// * `fragment`: The fragment to add.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing either the joined response, if the fragment completed its set,
+    /// or `None` if fragments are still missing; or an `Error::ParserError` if the fragment
+    /// is inconsistent with its set.
+    pub fn push(&mut self, fragment: Fragment) -> Result<Option<Bytes>, Error<ReassemblyError>> {
+        let Fragment {
+            id,
+            index,
+            total,
+            payload,
+        } = fragment;
+
+        if total > self.max_fragments {
+            return Err(invalid(ReassemblyError::TooManyFragments {
+                total,
+                max: self.max_fragments,
+            }));
+        }
+
+        if index >= total {
+            return Err(invalid(ReassemblyError::InvalidIndex { index, total }));
+        }
+
+        if total == 1 {
+            return Ok(Some(payload));
+        }
+
+        self.expire();
+
+        if !self.sets.contains_key(&id) && self.sets.len() >= self.max_sets {
+            self.drop_oldest();
+        }
+
+        let set = self.sets.entry(id).or_insert_with(|| PartialSet {
+            fragments: vec![None; total],
+            received: 0,
+            started: runtime::try_now(),
+        });
+
+        if set.fragments.len() != total {
+            return Err(invalid(ReassemblyError::TotalMismatch {
+                expected: set.fragments.len(),
+                found: total,
+            }));
+        }
+
+        if set.fragments[index].is_none() {
+            set.fragments[index] = Some(payload);
+            set.received += 1;
+        }
+
+        if set.received < total {
+            return Ok(None);
+        }
+
+        let set = self.sets.remove(&id).expect("set is present");
+        let len = set.fragments.iter().flatten().map(Bytes::len).sum();
+        let mut joined = BytesMut::with_capacity(len);
+
+        for payload in set.fragments.into_iter().flatten() {
+            joined.extend_from_slice(&payload);
+        }
+
+        Ok(Some(joined.freeze()))
+    }
+
+    /// Receives packets until a whole response is available.
+    ///
+    /// This is the loop a `Protocol::receive_response` implementation would otherwise write
+    /// by hand: `next` receives one packet and classifies it, single packets are returned
+    /// directly and fragments are pushed until their set is complete. The caller's read
+    /// time limit should wrap the whole call.
+    ///
+    /// # Parameters
+    ///
+    /// * `next`: Receives and classifies the next packet.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing either the whole response or an `Error`.
+    pub async fn assemble<E, F, Fut>(&mut self, mut next: F) -> Result<Bytes, Error<E>>
+    where
+        E: From<ReassemblyError>,
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<Packet, Error<E>>>,
+    {
+        loop {
+            match next().await? {
+                Packet::Single(payload) => return Ok(payload),
+                Packet::Fragment(fragment) => {
+                    if let Some(payload) = self.push(fragment).map_err(|err| err.map(E::from))? {
+                        return Ok(payload);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Drops incomplete sets that are older than the timeout.
+    ///
+    /// # Returns
+    ///
+    /// The number of sets dropped.
+    pub fn expire(&mut self) -> usize {
+        let before = self.sets.len();
+        let timeout = self.timeout;
+
+        self.sets
+            .retain(|_, set| runtime::elapsed(set.started) < timeout);
+
+        before - self.sets.len()
+    }
+
+    /// Returns `true` if fragments of the set `id` are waiting for the rest of their set.
+    pub fn is_pending(&self, id: u32) -> bool {
+        self.sets.contains_key(&id)
+    }
+
+    /// Returns the number of incomplete sets.
+    pub fn pending(&self) -> usize {
+        self.sets.len()
+    }
+
+    /// Drops every incomplete set.
+    pub fn clear(&mut self) {
+        self.sets.clear();
+    }
+
+    /// Drops the incomplete set that started first.
+    fn drop_oldest(&mut self) {
+        let oldest = self
+            .sets
+            .iter()
+            .min_by_key(|(_, set)| set.started)
+            .map(|(id, _)| *id);
+
+        if let Some(id) = oldest {
+            self.sets.remove(&id);
+        }
+    }
+}
+
+impl Default for Reassembler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Wraps a rejected fragment as a parser error.
+fn invalid(err: ReassemblyError) -> Error<ReassemblyError> {
+    Error::ParserError(
+        ErrorDetail::new("Invalid response fragment", Some(err))
+            .with_kind(ErrorKind::InvalidPacket),
+    )
+}
@@ -0,0 +1,169 @@
+//! Static game and protocol metadata.
+//!
+//! The tables in this module are generated at build time from `data/games.toml`,
+//! so supporting a new game's metadata only requires editing that file.
+
+/// The transport a query protocol runs over.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Transport {
+    /// The protocol exchanges datagrams over UDP.
+    Udp,
+    /// The protocol uses a TCP stream.
+    Tcp,
+}
+
+/// A set of features supported by a protocol or game.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Capabilities(u32);
+
+impl Capabilities {
+    /// The server can report its player list.
+    pub const PLAYERS: Self = Self(1 << 0);
+    /// The server can report its rules or key/value settings.
+    pub const RULES: Self = Self(1 << 1);
+    /// Queries must obtain a challenge token before the real request.
+    pub const CHALLENGE: Self = Self(1 << 2);
+    /// Responses may be split across several packets.
+    pub const MULTI_PACKET: Self = Self(1 << 3);
+    /// The server accepts remote console commands.
+    pub const RCON: Self = Self(1 << 4);
+
+    /// Returns an empty set of capabilities.
+    pub const fn empty() -> Self {
+        Self(0)
+    }
+
+    /// Returns the union of both capability sets.
+    pub const fn union(self, other: Self) -> Self {
+        Self(self.0 | other.0)
+    }
+
+    /// Returns `true` if every capability in `other` is also in `self`.
+    pub const fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// Returns the raw bit representation of the capability set.
+    pub const fn bits(self) -> u32 {
+        self.0
+    }
+}
+
+/// How the query port of a game relates to the port players connect to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum QueryPort {
+    /// Queries are answered on the game port itself.
+    SameAsGame,
+    /// Queries are answered on the game port plus an offset.
+    Offset(i32),
+    /// Queries are answered on a fixed port regardless of the game port.
+    Fixed(u16),
+}
+
+impl QueryPort {
+    /// Returns the query port for a server listening on `game_port`.
+    ///
+    /// # Returns
+    ///
+    /// The query port, or `None` if the offset moves `game_port` out of the port range.
+    pub fn for_game_port(self, game_port: u16) -> Option<u16> {
+        match self {
+            QueryPort::SameAsGame => Some(game_port),
+            QueryPort::Offset(offset) => {
+                u16::try_from(i32::from(game_port).checked_add(offset)?).ok()
+            }
+            QueryPort::Fixed(port) => Some(port),
+        }
+    }
+}
+
+/// Metadata describing a query protocol.
+#[derive(Debug)]
+pub struct ProtocolEntry {
+    /// The unique protocol identifier.
+    pub id: &'static str,
+    /// The human readable protocol name.
+    pub name: &'static str,
+    /// The transport the protocol runs over.
+    pub transport: Transport,
+    /// The features the protocol supports.
+    pub capabilities: Capabilities,
+}
+
+/// Metadata describing a game.
+#[derive(Debug)]
+pub struct GameEntry {
+    /// The unique game identifier.
+    pub id: &'static str,
+    /// The human readable game name.
+    pub name: &'static str,
+    /// Alternative identifiers the game can be looked up by.
+    pub aliases: &'static [&'static str],
+    /// The identifier of the protocol the game is queried with.
+    pub protocol: &'static str,
+    /// The year the game was released.
+    pub release_year: u32,
+    /// The default port players connect to.
+    pub game_port: u16,
+    /// How the query port is derived from the game port.
+    pub query_port: QueryPort,
+    /// The Steam AppIDs reported by the game's servers.
+    pub steam_appids: &'static [u32],
+    /// The SRV service and protocol labels hosts publish the game port under, e.g.
+    /// `_minecraft._tcp`.
+    pub srv: Option<&'static str>,
+    /// The features supported when querying the game.
+    pub capabilities: Capabilities,
+}
+
+impl GameEntry {
+    /// Returns the metadata of the protocol the game is queried with.
+    pub fn protocol(&self) -> &'static ProtocolEntry {
+        protocol(self.protocol).expect("registry entries are validated at build time")
+    }
+
+    /// Returns the query port for a server listening on `game_port`, or `None` if the
+    /// game's offset moves it out of the port range.
+    pub fn query_port_for(&self, game_port: u16) -> Option<u16> {
+        self.query_port.for_game_port(game_port)
+    }
+
+    /// Returns the query port for a server listening on the default game port.
+    pub fn default_query_port(&self) -> u16 {
+        self.query_port_for(self.game_port)
+            .expect("registry entries are validated at build time")
+    }
+}
+
+include!(concat!(env!("OUT_DIR"), "/registry.rs"));
+
+/// Looks up a game by its identifier or one of its aliases, ignoring ASCII case.
+pub fn game(identifier: &str) -> Option<&'static GameEntry> {
+    GAMES.iter().find(|game| {
+        game.id.eq_ignore_ascii_case(identifier)
+            || game
+                .aliases
+                .iter()
+                .any(|alias| alias.eq_ignore_ascii_case(identifier))
+    })
+}
+
+/// Looks up a game by the Steam AppID its servers report.
+pub fn game_by_appid(appid: u32) -> Option<&'static GameEntry> {
+    APPIDS
+        .binary_search_by_key(&appid, |(appid, _)| *appid)
+        .ok()
+        .map(|index| &GAMES[APPIDS[index].1])
+}
+
+/// Returns every Steam AppID known to the registry with its game, sorted by AppID.
+///
+/// This is the table `game_by_appid` searches, e.g. for building a lookup of its own.
+pub fn appids() -> impl Iterator<Item = (u32, &'static GameEntry)> {
+    APPIDS.iter().map(|&(appid, index)| (appid, &GAMES[index]))
+}
+
+/// Looks up a protocol by its identifier.
+pub fn protocol(identifier: &str) -> Option<&'static ProtocolEntry> {
+    PROTOCOLS.iter().find(|protocol| protocol.id == identifier)
+}
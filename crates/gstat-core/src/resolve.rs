@@ -0,0 +1,466 @@
+//! Resolving host names to the addresses servers are queried at.
+//!
+//! Server lists and players name servers by host name, e.g. `play.example.com`, and some
+//! games let hosts publish their port in an SRV record, such as `_minecraft._tcp` for
+//! Minecraft: Java Edition. `resolve` turns a `HostPort` into the addresses to query,
+//! looking up the game's SRV record when no port was given, and `Game::fetch_host`
+//! queries the result.
+//!
+//! Lookups go through a `Resolver`. `SystemResolver` asks the operating system, which can
+//! resolve A and AAAA records but not SRV records; resolvers backed by a DNS client
+//! library add SRV support by implementing `Resolver::lookup_srv`.
+
+use crate::{address::interleave_families, registry::GameEntry, runtime};
+
+use std::{
+    collections::HashMap,
+    error::Error as StdError,
+    fmt::{Display, Formatter, Result as FmtResult},
+    future::Future,
+    io,
+    net::{IpAddr, SocketAddr},
+    pin::Pin,
+    str::FromStr,
+};
+
+/// The future returned by the lookups of a `Resolver`.
+pub type Lookup<'a, T> = Pin<Box<dyn Future<Output = io::Result<T>> + Send + 'a>>;
+
+/// `Resolver` looks up the DNS records needed to reach a server.
+pub trait Resolver: Send + Sync {
+    /// Looks up the IP addresses of a host, from its A and AAAA records.
+    ///
+    /// # Parameters
+    ///
+    /// * `host`: The host name, without a port.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the addresses in the order the records were returned, or an
+    /// `io::Error`.
+    fn lookup_ip<'a>(&'a self, host: &'a str) -> Lookup<'a, Vec<IpAddr>>;
+
+    /// Looks up the SRV records of a service.
+    ///
+    /// The default implementation supports no SRV records and returns none.
+    ///
+    /// # Parameters
+    ///
+    /// * `name`: The full name of the records, e.g. `_minecraft._tcp.example.com`.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the records in any order, or an `io::Error`.
+    fn lookup_srv<'a>(&'a self, name: &'a str) -> Lookup<'a, Vec<SrvRecord>> {
+        let _ = name;
+        Box::pin(async { Ok(Vec::new()) })
+    }
+}
+
+/// `SystemResolver` resolves host names with the operating system's resolver.
+///
+/// It honours the system's configuration, such as `/etc/hosts`, but can't look up SRV
+/// records.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SystemResolver;
+
+impl Resolver for SystemResolver {
+    fn lookup_ip<'a>(&'a self, host: &'a str) -> Lookup<'a, Vec<IpAddr>> {
+        Box::pin(async move {
+            let addresses = runtime::lookup_host(host, 0).await?;
+            Ok(addresses.into_iter().map(|address| address.ip()).collect())
+        })
+    }
+}
+
+/// `StaticResolver` answers from records configured up front.
+///
+/// It serves as a fixed override of DNS, e.g. for servers on a private network, and as a
+/// resolver for tests. Names are matched ignoring ASCII case and a trailing dot; names
+/// without records fail with `io::ErrorKind::NotFound`.
+#[derive(Clone, Debug, Default)]
+pub struct StaticResolver {
+    hosts: HashMap<String, Vec<IpAddr>>,
+    services: HashMap<String, Vec<SrvRecord>>,
+}
+
+impl StaticResolver {
+    /// Creates a resolver without records.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds the IP addresses of a host.
+    ///
+    /// # Parameters
+    ///
+    /// * `host`: The host name.
+    /// * `addresses`: The addresses, in the order they are returned.
+    pub fn with_host(mut self, host: &str, addresses: impl IntoIterator<Item = IpAddr>) -> Self {
+        self.hosts
+            .entry(normalize(host))
+            .or_default()
+            .extend(addresses);
+        self
+    }
+
+    /// Adds an SRV record.
+    ///
+    /// # Parameters
+    ///
+    /// * `name`: The full name of the record, e.g. `_minecraft._tcp.example.com`.
+    /// * `record`: The record.
+    pub fn with_srv(mut self, name: &str, record: SrvRecord) -> Self {
+        self.services
+            .entry(normalize(name))
+            .or_default()
+            .push(record);
+        self
+    }
+}
+
+impl Resolver for StaticResolver {
+    fn lookup_ip<'a>(&'a self, host: &'a str) -> Lookup<'a, Vec<IpAddr>> {
+        let found = self.hosts.get(&normalize(host)).cloned();
+
+        Box::pin(async move { found.ok_or_else(|| not_found(host)) })
+    }
+
+    fn lookup_srv<'a>(&'a self, name: &'a str) -> Lookup<'a, Vec<SrvRecord>> {
+        let found = self.services.get(&normalize(name)).cloned();
+
+        Box::pin(async move { Ok(found.unwrap_or_default()) })
+    }
+}
+
+/// An SRV record, pointing a service at a host and port.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SrvRecord {
+    /// The priority of the target; lower values are tried first.
+    pub priority: u16,
+    /// The relative weight among targets of the same priority.
+    pub weight: u16,
+    /// The port the service listens on.
+    pub port: u16,
+    /// The host name of the target.
+    pub target: String,
+}
+
+impl SrvRecord {
+    /// Creates an SRV record.
+    pub fn new(priority: u16, weight: u16, port: u16, target: impl Into<String>) -> Self {
+        SrvRecord {
+            priority,
+            weight,
+            port,
+            target: target.into(),
+        }
+    }
+
+    /// Returns `true` if the record states that the service is not available, which RFC
+    /// 2782 signals with the target `.`.
+    pub fn is_unavailable(&self) -> bool {
+        self.target == "."
+    }
+
+    /// Orders records in the order they should be tried.
+    ///
+    /// Following RFC 2782, records are sorted by priority, and records of the same
+    /// priority are shuffled with a probability proportional to their weight.
+    ///
+    /// # Parameters
+    ///
+    /// * `records`: The records of a service, in any order.
+    pub fn order(mut records: Vec<SrvRecord>) -> Vec<SrvRecord> {
+        records.sort_by_key(|record| record.priority);
+
+        let mut ordered = Vec::with_capacity(records.len());
+        let mut records = records.into_iter().peekable();
+
+        while let Some(first) = records.next() {
+            let mut group = vec![first];
+
+            while let Some(record) = records.next_if(|next| next.priority == group[0].priority) {
+                group.push(record);
+            }
+
+            while !group.is_empty() {
+                let total: u32 = group.iter().map(|record| u32::from(record.weight)).sum();
+                let mut pick = fastrand::u32(0..=total);
+
+                // Weight 0 records come first in the running sum, so they are only picked
+                // when the draw is 0 or when no weighted records are left.
+                group.sort_by_key(|record| record.weight);
+
+                let index = group
+                    .iter()
+                    .position(|record| {
+                        let weight = u32::from(record.weight);
+
+                        if pick <= weight {
+                            true
+                        } else {
+                            pick -= weight;
+                            false
+                        }
+                    })
+                    .unwrap_or(group.len() - 1);
+
+                ordered.push(group.remove(index));
+            }
+        }
+
+        ordered
+    }
+}
+
+/// `HostPort` is a host, given by name or IP address, with an optional port.
+///
+/// It parses from `host`, `host:port`, `[ipv6]` and `[ipv6]:port`. A bare IPv6 address
+/// without brackets is taken as a host without a port.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct HostPort {
+    /// The host name or IP address literal.
+    pub host: String,
+    /// The port, if one was given.
+    pub port: Option<u16>,
+}
+
+impl HostPort {
+    /// Creates a host with an optional port.
+    pub fn new(host: impl Into<String>, port: Option<u16>) -> Self {
+        HostPort {
+            host: host.into(),
+            port,
+        }
+    }
+
+    /// Returns the IP address if the host is an IP address literal.
+    pub fn ip(&self) -> Option<IpAddr> {
+        self.host.parse().ok()
+    }
+}
+
+impl Display for HostPort {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match (self.ip(), self.port) {
+            (Some(IpAddr::V6(ip)), Some(port)) => write!(f, "[{ip}]:{port}"),
+            (_, Some(port)) => write!(f, "{}:{port}", self.host),
+            (_, None) => f.write_str(&self.host),
+        }
+    }
+}
+
+impl FromStr for HostPort {
+    type Err = ParseHostError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || ParseHostError(s.to_string());
+        let s = s.trim();
+
+        let (host, port) = if let Some(rest) = s.strip_prefix('[') {
+            let (host, rest) = rest.split_once(']').ok_or_else(invalid)?;
+
+            match rest {
+                "" => (host, None),
+                _ => (host, Some(rest.strip_prefix(':').ok_or_else(invalid)?)),
+            }
+        } else {
+            match s.rsplit_once(':') {
+                // More than one colon without brackets is an IPv6 address.
+                Some((host, _)) if host.contains(':') => (s, None),
+                Some((host, port)) => (host, Some(port)),
+                None => (s, None),
+            }
+        };
+
+        if host.is_empty() || host.contains(char::is_whitespace) {
+            return Err(invalid());
+        }
+
+        let port = port
+            .map(|port| port.parse::<u16>().map_err(|_| invalid()))
+            .transpose()?;
+
+        Ok(HostPort::new(host, port))
+    }
+}
+
+/// The error returned when a string is not a valid `HostPort`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseHostError(String);
+
+impl Display for ParseHostError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(f, "invalid host `{}`", self.0)
+    }
+}
+
+impl StdError for ParseHostError {}
+
+/// How a host name was resolved, as reported in `ResponseMeta`.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Resolution {
+    /// The host that was resolved, as it was given.
+    pub host: String,
+    /// The SRV record the address was taken from, if any.
+    pub srv: Option<SrvRecord>,
+}
+
+/// The outcome of `resolve`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Resolved {
+    /// How the host was resolved.
+    pub resolution: Resolution,
+    /// The addresses to query, with the address families interleaved.
+    pub addresses: Vec<SocketAddr>,
+}
+
+/// Resolves a host to the addresses its server is queried at.
+///
+/// IP address literals are used as they are. Otherwise, if no port was given and the game
+/// publishes an SRV record, the record is looked up and its targets are tried in the order
+/// of `SrvRecord::order`. Without SRV records, or if looking them up fails, the host's own
+/// A and AAAA records are used.
+///
+/// Ports given in `target` and taken from SRV records are game ports, which are mapped to
+/// query ports through `game`, like in `ServerAddress`. Without a port, the game's default
+/// query port is used.
+///
+/// # Parameters
+///
+/// * `resolver`: The resolver to look up records with.
+/// * `target`: The host to resolve.
+/// * `game`: The registry entry of the game the server runs, if known.
+///
+/// # Returns
+///
+/// A `Result` containing the addresses to query, or an `io::Error` if the host could not be
+/// resolved. A host without a port for a game without a registry entry, and a port the
+/// game's query port offset moves out of the port range, fail with
+/// `io::ErrorKind::InvalidInput`.
+pub async fn resolve(
+    resolver: &dyn Resolver,
+    target: &HostPort,
+    game: Option<&GameEntry>,
+) -> io::Result<Resolved> {
+    let query_port = |game_port: u16| match game {
+        Some(game) => game.query_port_for(game_port).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("port {game_port} has no query port for `{}`", game.id),
+            )
+        }),
+        None => Ok(game_port),
+    };
+    let resolution = |srv: Option<SrvRecord>| Resolution {
+        host: target.host.clone(),
+        srv,
+    };
+
+    let port = match (target.port, game) {
+        (Some(port), _) => query_port(port)?,
+        (None, Some(game)) => game.default_query_port(),
+        (None, None) => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("no port given for `{}`", target.host),
+            ))
+        }
+    };
+
+    if let Some(ip) = target.ip() {
+        return Ok(Resolved {
+            resolution: resolution(None),
+            addresses: vec![SocketAddr::new(ip, port)],
+        });
+    }
+
+    let service = game
+        .and_then(|game| game.srv)
+        .filter(|_| target.port.is_none());
+
+    if let Some(service) = service {
+        let name = format!("{service}.{}", target.host.trim_end_matches('.'));
+
+        match resolver.lookup_srv(&name).await {
+            Ok(records) if records.iter().any(SrvRecord::is_unavailable) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::NotFound,
+                    format!("`{name}` states that the service is not available"),
+                ));
+            }
+            Ok(records) if !records.is_empty() => {
+                return resolve_srv(resolver, SrvRecord::order(records), query_port)
+                    .await
+                    .map(|(record, addresses)| Resolved {
+                        resolution: resolution(Some(record)),
+                        addresses,
+                    });
+            }
+            Ok(_) => {}
+            Err(_err) => {
+                trace_event!(debug, %name, error = %_err, "SRV lookup failed, using the host");
+            }
+        }
+    }
+
+    let ips = resolver.lookup_ip(&target.host).await?;
+
+    if ips.is_empty() {
+        return Err(not_found(&target.host));
+    }
+
+    Ok(Resolved {
+        resolution: resolution(None),
+        addresses: interleave_families(ips.into_iter().map(|ip| SocketAddr::new(ip, port))),
+    })
+}
+
+/// Resolves the targets of ordered SRV records until one of them resolves.
+async fn resolve_srv(
+    resolver: &dyn Resolver,
+    records: Vec<SrvRecord>,
+    query_port: impl Fn(u16) -> io::Result<u16>,
+) -> io::Result<(SrvRecord, Vec<SocketAddr>)> {
+    let mut last = None;
+
+    for record in records {
+        match resolver
+            .lookup_ip(record.target.trim_end_matches('.'))
+            .await
+        {
+            Ok(ips) if !ips.is_empty() => {
+                let port = match query_port(record.port) {
+                    Ok(port) => port,
+                    Err(err) => {
+                        last = Some(err);
+                        continue;
+                    }
+                };
+                let addresses = ips.into_iter().map(|ip| SocketAddr::new(ip, port));
+
+                return Ok((record, interleave_families(addresses)));
+            }
+            Ok(_) => last = Some(not_found(&record.target)),
+            Err(err) => last = Some(err),
+        }
+    }
+
+    Err(last.unwrap_or_else(|| not_found("SRV target")))
+}
+
+/// Normalizes a DNS name for comparison.
+fn normalize(name: &str) -> String {
+    name.trim_end_matches('.').to_ascii_lowercase()
+}
+
+/// Creates the error of a name without records.
+fn not_found(name: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::NotFound,
+        format!("no records found for `{name}`"),
+    )
+}
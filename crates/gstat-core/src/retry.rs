@@ -0,0 +1,281 @@
+use crate::{
+    clock::{Clock, RuntimeClock},
+    prelude::Error,
+};
+
+use std::{
+    fmt::{Debug, Formatter, Result as FmtResult},
+    future::Future,
+    sync::Arc,
+    time::Duration,
+};
+
+/// How the delay between attempts grows.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Backoff {
+    /// The delay before the first retry.
+    pub initial: Duration,
+    /// The factor the delay is multiplied by after every retry.
+    pub multiplier: f64,
+    /// The upper bound for the delay.
+    pub max: Duration,
+}
+
+impl Default for Backoff {
+    fn default() -> Self {
+        Backoff {
+            initial: Duration::from_millis(100),
+            multiplier: 2.0,
+            max: Duration::from_secs(2),
+        }
+    }
+}
+
+/// How randomness is applied to the backoff delay.
+///
+/// Randomizing delays keeps many clients that failed at the same time from retrying in
+/// lockstep.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Jitter {
+    /// Use the backoff delay as is.
+    None,
+    /// Pick a delay between zero and the backoff delay.
+    #[default]
+    Full,
+    /// Pick a delay between half the backoff delay and the backoff delay.
+    Equal,
+}
+
+/// Which errors are worth another attempt.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum RetryOn {
+    /// Retry protocol and response errors, which are typically caused by lost packets or
+    /// unresponsive servers. Game, parser and query errors are not retried, since sending
+    /// the same query again would fail the same way.
+    #[default]
+    Transient,
+    /// Retry every error.
+    Any,
+}
+
+/// A single attempt made under a `RetryPolicy`, as reported to attempt observers.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Attempt {
+    /// The number of the attempt, starting at 1.
+    pub number: u32,
+    /// The error the attempt failed with, if it failed.
+    pub error: Option<String>,
+    /// The delay before the next attempt, if another attempt will be made.
+    pub retry_in: Option<Duration>,
+}
+
+/// A callback observing every attempt made under a `RetryPolicy`.
+type Observer = Arc<dyn Fn(&Attempt) + Send + Sync>;
+
+/// `RetryPolicy` decides whether and when a failed operation is attempted again.
+///
+/// Query packets are frequently dropped on the public internet, so `Game::fetch_with`
+/// runs every exchange under the policy returned by `Protocol::retry_policy`. The default
+/// policy makes a single attempt.
+#[derive(Clone)]
+pub struct RetryPolicy {
+    /// The maximum number of attempts, including the first one.
+    pub max_attempts: u32,
+    /// How the delay between attempts grows.
+    pub backoff: Backoff,
+    /// How randomness is applied to the delay between attempts.
+    pub jitter: Jitter,
+    /// Which errors are worth another attempt.
+    pub retry_on: RetryOn,
+    /// The callbacks observing each attempt.
+    observers: Vec<Observer>,
+    /// The clock waited on between attempts.
+    clock: Arc<dyn Clock>,
+}
+
+impl RetryPolicy {
+    /// Creates a policy that makes a single attempt.
+    pub fn none() -> Self {
+        Self::new(1)
+    }
+
+    /// Creates a policy making up to `max_attempts` attempts with the default backoff.
+    ///
+    /// # Parameters
+    ///
+    /// * `max_attempts`: The maximum number of attempts, including the first one.
+    pub fn new(max_attempts: u32) -> Self {
+        RetryPolicy {
+            max_attempts: max_attempts.max(1),
+            backoff: Backoff::default(),
+            jitter: Jitter::default(),
+            retry_on: RetryOn::default(),
+            observers: Vec::new(),
+            clock: Arc::new(RuntimeClock),
+        }
+    }
+
+    /// Sets how the delay between attempts grows.
+    pub fn with_backoff(mut self, backoff: Backoff) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    /// Sets how randomness is applied to the delay between attempts.
+    pub fn with_jitter(mut self, jitter: Jitter) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    /// Sets which errors are worth another attempt.
+    pub fn with_retry_on(mut self, retry_on: RetryOn) -> Self {
+        self.retry_on = retry_on;
+        self
+    }
+
+    /// Adds a callback that is invoked after every attempt.
+    ///
+    /// # Parameters
+    ///
+    /// * `observer`: The callback receiving each `Attempt`.
+    pub fn on_attempt(mut self, observer: impl Fn(&Attempt) + Send + Sync + 'static) -> Self {
+        self.observers.push(Arc::new(observer));
+        self
+    }
+
+    /// Sets the clock waited on between attempts, e.g. a `ManualClock` in tests.
+    pub fn with_clock(mut self, clock: impl Clock + 'static) -> Self {
+        self.clock = Arc::new(clock);
+        self
+    }
+
+    /// Returns `true` if `error`, produced by attempt number `attempt`, should be retried.
+    ///
+    /// # Parameters
+    ///
+    /// * `attempt`: The number of the failed attempt, starting at 1.
+    /// * `error`: The error the attempt failed with.
+    pub fn should_retry<E>(&self, attempt: u32, error: &Error<E>) -> bool {
+        if attempt >= self.max_attempts {
+            return false;
+        }
+
+        match self.retry_on {
+            RetryOn::Transient => {
+                matches!(error, Error::ProtocolError(_) | Error::ResponseError(_))
+            }
+            RetryOn::Any => true,
+        }
+    }
+
+    /// Returns the delay to wait after the failed attempt number `attempt`.
+    ///
+    /// # Parameters
+    ///
+    /// * `attempt`: The number of the failed attempt, starting at 1.
+    pub fn delay_for(&self, attempt: u32) -> Duration {
+        let exponent = attempt.saturating_sub(1).min(i32::MAX as u32) as i32;
+        let delay = self
+            .backoff
+            .initial
+            .mul_f64(self.backoff.multiplier.max(1.0).powi(exponent).min(1e9))
+            .min(self.backoff.max);
+
+        match self.jitter {
+            Jitter::None => delay,
+            Jitter::Full => delay.mul_f64(fastrand::f64()),
+            Jitter::Equal => delay / 2 + (delay / 2).mul_f64(fastrand::f64()),
+        }
+    }
+
+    /// Runs `operation` until it succeeds or the policy gives up.
+    ///
+    /// # Parameters
+    ///
+    /// * `operation`: Produces the future for an attempt, given the attempt number.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing either the output of the successful attempt or the `Error` of
+    /// the last attempt.
+    pub async fn run<T, E, F, Fut>(&self, mut operation: F) -> Result<T, Error<E>>
+    where
+        F: FnMut(u32) -> Fut,
+        Fut: Future<Output = Result<T, Error<E>>>,
+        E: Debug,
+    {
+        let mut number = 0;
+
+        loop {
+            number += 1;
+
+            let attempt = instrument!(operation(number), "attempt", number);
+
+            let retry_in = match attempt.await {
+                Ok(output) => {
+                    self.observe(Attempt {
+                        number,
+                        error: None,
+                        retry_in: None,
+                    });
+
+                    return Ok(output);
+                }
+                Err(err) => {
+                    let retry_in = self
+                        .should_retry(number, &err)
+                        .then(|| self.delay_for(number));
+
+                    trace_event!(
+                        debug,
+                        attempt = number,
+                        max_attempts = self.max_attempts,
+                        error = %err,
+                        retry_in = ?retry_in,
+                        "attempt failed"
+                    );
+
+                    self.observe(Attempt {
+                        number,
+                        error: Some(err.to_string()),
+                        retry_in,
+                    });
+
+                    match retry_in {
+                        Some(retry_in) => retry_in,
+                        None => return Err(err),
+                    }
+                }
+            };
+
+            if !retry_in.is_zero() {
+                self.clock.sleep(retry_in).await;
+            }
+        }
+    }
+
+    /// Reports an attempt to every observer.
+    fn observe(&self, attempt: Attempt) {
+        for observer in &self.observers {
+            observer(&attempt);
+        }
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self::none()
+    }
+}
+
+impl Debug for RetryPolicy {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.debug_struct("RetryPolicy")
+            .field("max_attempts", &self.max_attempts)
+            .field("backoff", &self.backoff)
+            .field("jitter", &self.jitter)
+            .field("retry_on", &self.retry_on)
+            .field("observers", &self.observers.len())
+            .finish()
+    }
+}
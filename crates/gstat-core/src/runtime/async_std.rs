@@ -0,0 +1,62 @@
+//! The async-std backend.
+
+use std::{
+    future::Future,
+    io,
+    net::{Shutdown, SocketAddr},
+    time::{Duration, Instant},
+};
+
+use async_std::io::{ReadExt, WriteExt};
+
+pub(super) use async_std::net::{TcpStream as RawTcpStream, UdpSocket as RawUdpSocket};
+
+pub(super) const NAME: &str = "async-std";
+
+pub(super) fn now() -> Instant {
+    Instant::now()
+}
+
+pub(super) async fn sleep(duration: Duration) {
+    async_std::task::sleep(duration).await
+}
+
+pub(super) async fn timeout<F: Future>(duration: Duration, future: F) -> Option<F::Output> {
+    async_std::future::timeout(duration, future).await.ok()
+}
+
+pub(super) async fn lookup_host(host: &str, port: u16) -> io::Result<Vec<SocketAddr>> {
+    Ok(
+        async_std::net::ToSocketAddrs::to_socket_addrs(&(host, port))
+            .await?
+            .collect(),
+    )
+}
+
+pub(super) fn spawn(future: impl Future<Output = ()> + Send + 'static) -> bool {
+    async_std::task::spawn(future);
+    true
+}
+
+pub(super) async fn connect_from(
+    _local: SocketAddr,
+    _address: SocketAddr,
+) -> io::Result<RawTcpStream> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "binding TCP streams to a local address requires the `rt-tokio` runtime",
+    ))
+}
+
+pub(super) async fn read(stream: &mut RawTcpStream, buffer: &mut [u8]) -> io::Result<usize> {
+    stream.read(buffer).await
+}
+
+pub(super) async fn write_all(stream: &mut RawTcpStream, data: &[u8]) -> io::Result<()> {
+    stream.write_all(data).await
+}
+
+pub(super) async fn shutdown(stream: &mut RawTcpStream) -> io::Result<()> {
+    stream.flush().await?;
+    stream.shutdown(Shutdown::Write)
+}
@@ -0,0 +1,113 @@
+//! The host backend, used on `wasm32` targets when no runtime feature is enabled.
+//!
+//! It has no reactor, timer or executor, so it compiles for `wasm32-unknown-unknown`.
+//! Sockets and name resolution fail with `io::ErrorKind::Unsupported`, since the host is
+//! expected to bring its own transport by implementing `Protocol`. Sleeps complete at once,
+//! time limits never elapse and nothing is spawned; the host enforces deadlines, e.g. by
+//! dropping the future.
+
+use std::{
+    future::Future,
+    io,
+    net::SocketAddr,
+    time::{Duration, Instant},
+};
+
+pub(super) const NAME: &str = "host";
+
+fn unsupported() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::Unsupported,
+        "sockets require one of the `rt-tokio`, `rt-async-std` or `rt-smol` features",
+    )
+}
+
+/// Returns the current instant.
+///
+/// `wasm32-unknown-unknown` has no clock, so `Instant::now` panics there; everything that
+/// reads the time, such as `Game::fetch`, reassembly and `ChallengeMemory`, goes through
+/// `try_now`, which doesn't call this function on such hosts.
+pub(super) fn now() -> Instant {
+    Instant::now()
+}
+
+pub(super) async fn sleep(_duration: Duration) {}
+
+pub(super) async fn timeout<F: Future>(_duration: Duration, future: F) -> Option<F::Output> {
+    Some(future.await)
+}
+
+pub(super) async fn lookup_host(_host: &str, _port: u16) -> io::Result<Vec<SocketAddr>> {
+    Err(unsupported())
+}
+
+pub(super) fn spawn(_future: impl Future<Output = ()> + Send + 'static) -> bool {
+    false
+}
+
+/// A TCP stream, which can't be opened without a runtime.
+#[derive(Debug)]
+pub(super) enum RawTcpStream {}
+
+impl RawTcpStream {
+    pub(super) async fn connect(_address: SocketAddr) -> io::Result<Self> {
+        Err(unsupported())
+    }
+}
+
+/// A UDP socket, which can't be bound without a runtime.
+#[derive(Debug)]
+pub(super) enum RawUdpSocket {}
+
+impl RawUdpSocket {
+    pub(super) async fn bind(_address: SocketAddr) -> io::Result<Self> {
+        Err(unsupported())
+    }
+
+    pub(super) fn local_addr(&self) -> io::Result<SocketAddr> {
+        match *self {}
+    }
+
+    pub(super) fn set_broadcast(&self, _on: bool) -> io::Result<()> {
+        match *self {}
+    }
+
+    pub(super) async fn connect(&self, _address: SocketAddr) -> io::Result<()> {
+        match *self {}
+    }
+
+    pub(super) async fn send(&self, _data: &[u8]) -> io::Result<usize> {
+        match *self {}
+    }
+
+    pub(super) async fn recv(&self, _buffer: &mut [u8]) -> io::Result<usize> {
+        match *self {}
+    }
+
+    pub(super) async fn send_to(&self, _data: &[u8], _address: SocketAddr) -> io::Result<usize> {
+        match *self {}
+    }
+
+    pub(super) async fn recv_from(&self, _buffer: &mut [u8]) -> io::Result<(usize, SocketAddr)> {
+        match *self {}
+    }
+}
+
+pub(super) async fn connect_from(
+    _local: SocketAddr,
+    _address: SocketAddr,
+) -> io::Result<RawTcpStream> {
+    Err(unsupported())
+}
+
+pub(super) async fn read(stream: &mut RawTcpStream, _buffer: &mut [u8]) -> io::Result<usize> {
+    match *stream {}
+}
+
+pub(super) async fn write_all(stream: &mut RawTcpStream, _data: &[u8]) -> io::Result<()> {
+    match *stream {}
+}
+
+pub(super) async fn shutdown(stream: &mut RawTcpStream) -> io::Result<()> {
+    match *stream {}
+}
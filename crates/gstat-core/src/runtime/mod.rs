@@ -0,0 +1,246 @@
+//! The async runtime the library runs on.
+//!
+//! Everything in gstat that needs an executor (sockets, sleeps, timeouts and background
+//! tasks) goes through this module, so the transports and the `Game::fetch` pipeline work
+//! on any supported runtime. The runtime is selected with a feature flag:
+//!
+//! * `rt-tokio` (default): Tokio. A Tokio runtime must be running when gstat is used.
+//! * `rt-async-std`: async-std.
+//! * `rt-smol`: smol.
+//!
+//! If several runtime features are enabled, the first one in the list above is used. With
+//! none of them, building fails, except on `wasm32` targets, where gstat is built for a
+//! host that brings its own transport, such as a browser or a Workers runtime: the codec,
+//! parsers and response models work as usual, but the sockets of this module fail with
+//! `io::ErrorKind::Unsupported`, sleeps complete at once, time limits never elapse and
+//! nothing is spawned. Games query through such a host by implementing `Protocol` over its
+//! networking.
+//!
+//! With the `silent` feature, gstat never spawns background tasks: `spawn` drops its
+//! future, and work that would run in the background, such as a best-effort disconnect,
+//! is skipped or done in place.
+
+#[cfg(feature = "rt-tokio")]
+#[path = "tokio.rs"]
+mod backend;
+
+#[cfg(all(feature = "rt-async-std", not(feature = "rt-tokio")))]
+#[path = "async_std.rs"]
+mod backend;
+
+#[cfg(all(
+    feature = "rt-smol",
+    not(any(feature = "rt-tokio", feature = "rt-async-std"))
+))]
+#[path = "smol.rs"]
+mod backend;
+
+#[cfg(all(
+    target_arch = "wasm32",
+    not(any(feature = "rt-tokio", feature = "rt-async-std", feature = "rt-smol"))
+))]
+#[path = "host.rs"]
+mod backend;
+
+#[cfg(all(
+    not(target_arch = "wasm32"),
+    not(any(feature = "rt-tokio", feature = "rt-async-std", feature = "rt-smol"))
+))]
+compile_error!("gstat-core requires one of the `rt-tokio`, `rt-async-std` or `rt-smol` features");
+
+use std::{
+    error::Error as StdError,
+    fmt::{Display, Formatter, Result as FmtResult},
+    future::Future,
+    io,
+    net::SocketAddr,
+    time::{Duration, Instant},
+};
+
+/// Returns the name of the runtime gstat was built for.
+pub fn name() -> &'static str {
+    backend::NAME
+}
+
+/// Returns the current instant on the runtime's clock.
+///
+/// On Tokio this is Tokio's clock, which stands still under `tokio::time::pause`; the other
+/// runtimes use the system's monotonic clock.
+pub fn now() -> Instant {
+    backend::now()
+}
+
+/// Returns the current instant on the runtime's clock, or `None` on a host without a clock.
+///
+/// `wasm32-unknown-unknown` has no clock, so `now` panics there. Latencies and the ages of
+/// reassembled sets and remembered challenges are measured with this function instead, so
+/// they also work in browsers and Workers runtimes, where latencies are reported as zero
+/// and nothing expires.
+pub fn try_now() -> Option<Instant> {
+    match cfg!(all(target_arch = "wasm32", target_os = "unknown")) {
+        true => None,
+        false => Some(now()),
+    }
+}
+
+/// Returns the time elapsed on the runtime's clock since an instant of `try_now`, or zero
+/// if it gave none.
+pub(crate) fn elapsed(since: Option<Instant>) -> Duration {
+    since.map_or(Duration::ZERO, |since| {
+        now().saturating_duration_since(since)
+    })
+}
+
+/// Waits until `duration` has elapsed.
+pub async fn sleep(duration: Duration) {
+    backend::sleep(duration).await
+}
+
+/// Runs `future` to completion, giving up once `duration` has elapsed.
+///
+/// # Parameters
+///
+/// * `duration`: The time limit.
+/// * `future`: The operation to run.
+///
+/// # Returns
+///
+/// A `Result` containing either the output of the operation or `Elapsed`.
+pub async fn timeout<F: Future>(duration: Duration, future: F) -> Result<F::Output, Elapsed> {
+    backend::timeout(duration, future).await.ok_or(Elapsed)
+}
+
+/// Resolves a host name to socket addresses with the operating system's resolver.
+///
+/// # Parameters
+///
+/// * `host`: The host name, or an IP address literal.
+/// * `port`: The port of the returned addresses.
+///
+/// # Returns
+///
+/// A `Result` containing the addresses in the order the resolver returned them, or an
+/// `io::Error`.
+pub async fn lookup_host(host: &str, port: u16) -> io::Result<Vec<SocketAddr>> {
+    backend::lookup_host(host, port).await
+}
+
+/// Runs `future` in the background.
+///
+/// # Returns
+///
+/// `true` if the future was spawned, or `false` if no runtime is available to run it or
+/// the `silent` feature is enabled, in which case the future is dropped.
+pub fn spawn(future: impl Future<Output = ()> + Send + 'static) -> bool {
+    if cfg!(feature = "silent") {
+        return false;
+    }
+
+    backend::spawn(future)
+}
+
+/// The error returned by `timeout` when the time limit elapses.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Elapsed;
+
+impl Display for Elapsed {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(f, "deadline has elapsed")
+    }
+}
+
+impl StdError for Elapsed {}
+
+/// The number of bytes TCP clients request from a `TcpStream` per read.
+pub const READ_CHUNK_LEN: usize = 4096;
+
+/// A TCP stream on the selected runtime.
+#[derive(Debug)]
+pub struct TcpStream {
+    inner: backend::RawTcpStream,
+}
+
+impl TcpStream {
+    /// Opens a connection to `address`.
+    pub async fn connect(address: SocketAddr) -> io::Result<Self> {
+        let inner = backend::RawTcpStream::connect(address).await?;
+
+        Ok(TcpStream { inner })
+    }
+
+    /// Opens a connection to `address` from the local address `local`.
+    ///
+    /// Only the Tokio backend can bind a stream before connecting it; the other backends
+    /// fail with `io::ErrorKind::Unsupported`.
+    pub async fn connect_from(local: SocketAddr, address: SocketAddr) -> io::Result<Self> {
+        let inner = backend::connect_from(local, address).await?;
+
+        Ok(TcpStream { inner })
+    }
+
+    /// Reads some bytes into `buffer`, returning how many were read. `0` means the peer
+    /// closed the connection.
+    pub async fn read(&mut self, buffer: &mut [u8]) -> io::Result<usize> {
+        backend::read(&mut self.inner, buffer).await
+    }
+
+    /// Writes all of `data` to the stream.
+    pub async fn write_all(&mut self, data: &[u8]) -> io::Result<()> {
+        backend::write_all(&mut self.inner, data).await
+    }
+
+    /// Shuts down the writing half of the stream.
+    pub async fn shutdown(&mut self) -> io::Result<()> {
+        backend::shutdown(&mut self.inner).await
+    }
+}
+
+/// A UDP socket on the selected runtime.
+#[derive(Debug)]
+pub struct UdpSocket {
+    inner: backend::RawUdpSocket,
+}
+
+impl UdpSocket {
+    /// Binds a socket to `address`.
+    pub async fn bind(address: SocketAddr) -> io::Result<Self> {
+        let inner = backend::RawUdpSocket::bind(address).await?;
+
+        Ok(UdpSocket { inner })
+    }
+
+    /// Returns the local address the socket is bound to.
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.inner.local_addr()
+    }
+
+    /// Allows or forbids sending datagrams to broadcast addresses.
+    pub fn set_broadcast(&self, on: bool) -> io::Result<()> {
+        self.inner.set_broadcast(on)
+    }
+
+    /// Connects the socket to `address`, so `send` and `recv` exchange datagrams with it.
+    pub async fn connect(&self, address: SocketAddr) -> io::Result<()> {
+        self.inner.connect(address).await
+    }
+
+    /// Sends a datagram to the connected peer.
+    pub async fn send(&self, data: &[u8]) -> io::Result<usize> {
+        self.inner.send(data).await
+    }
+
+    /// Receives a datagram from the connected peer.
+    pub async fn recv(&self, buffer: &mut [u8]) -> io::Result<usize> {
+        self.inner.recv(buffer).await
+    }
+
+    /// Sends a datagram to `address`.
+    pub async fn send_to(&self, data: &[u8], address: SocketAddr) -> io::Result<usize> {
+        self.inner.send_to(data, address).await
+    }
+
+    /// Receives a datagram from any peer, returning its length and source address.
+    pub async fn recv_from(&self, buffer: &mut [u8]) -> io::Result<(usize, SocketAddr)> {
+        self.inner.recv_from(buffer).await
+    }
+}
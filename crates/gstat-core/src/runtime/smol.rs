@@ -0,0 +1,64 @@
+//! The smol backend.
+
+use std::{
+    future::Future,
+    io,
+    net::SocketAddr,
+    time::{Duration, Instant},
+};
+
+use smol::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    Timer,
+};
+
+pub(super) use smol::net::{TcpStream as RawTcpStream, UdpSocket as RawUdpSocket};
+
+pub(super) const NAME: &str = "smol";
+
+pub(super) fn now() -> Instant {
+    Instant::now()
+}
+
+pub(super) async fn sleep(duration: Duration) {
+    Timer::after(duration).await;
+}
+
+pub(super) async fn timeout<F: Future>(duration: Duration, future: F) -> Option<F::Output> {
+    smol::future::or(async { Some(future.await) }, async {
+        Timer::after(duration).await;
+        None
+    })
+    .await
+}
+
+pub(super) async fn lookup_host(host: &str, port: u16) -> io::Result<Vec<SocketAddr>> {
+    smol::net::resolve((host, port)).await
+}
+
+pub(super) fn spawn(future: impl Future<Output = ()> + Send + 'static) -> bool {
+    smol::spawn(future).detach();
+    true
+}
+
+pub(super) async fn connect_from(
+    _local: SocketAddr,
+    _address: SocketAddr,
+) -> io::Result<RawTcpStream> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "binding TCP streams to a local address requires the `rt-tokio` runtime",
+    ))
+}
+
+pub(super) async fn read(stream: &mut RawTcpStream, buffer: &mut [u8]) -> io::Result<usize> {
+    stream.read(buffer).await
+}
+
+pub(super) async fn write_all(stream: &mut RawTcpStream, data: &[u8]) -> io::Result<()> {
+    stream.write_all(data).await
+}
+
+pub(super) async fn shutdown(stream: &mut RawTcpStream) -> io::Result<()> {
+    stream.close().await
+}
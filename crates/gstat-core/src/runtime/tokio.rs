@@ -0,0 +1,69 @@
+//! The Tokio backend.
+
+use std::{
+    future::Future,
+    io,
+    net::SocketAddr,
+    time::{Duration, Instant},
+};
+
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpSocket,
+    runtime::Handle,
+};
+
+pub(super) use tokio::net::{TcpStream as RawTcpStream, UdpSocket as RawUdpSocket};
+
+pub(super) const NAME: &str = "tokio";
+
+pub(super) fn now() -> Instant {
+    tokio::time::Instant::now().into_std()
+}
+
+pub(super) async fn sleep(duration: Duration) {
+    tokio::time::sleep(duration).await
+}
+
+pub(super) async fn timeout<F: Future>(duration: Duration, future: F) -> Option<F::Output> {
+    tokio::time::timeout(duration, future).await.ok()
+}
+
+pub(super) async fn lookup_host(host: &str, port: u16) -> io::Result<Vec<SocketAddr>> {
+    Ok(tokio::net::lookup_host((host, port)).await?.collect())
+}
+
+pub(super) fn spawn(future: impl Future<Output = ()> + Send + 'static) -> bool {
+    match Handle::try_current() {
+        Ok(handle) => {
+            handle.spawn(future);
+            true
+        }
+        Err(_) => false,
+    }
+}
+
+pub(super) async fn connect_from(
+    local: SocketAddr,
+    address: SocketAddr,
+) -> io::Result<RawTcpStream> {
+    let socket = match local {
+        SocketAddr::V4(_) => TcpSocket::new_v4()?,
+        SocketAddr::V6(_) => TcpSocket::new_v6()?,
+    };
+
+    socket.bind(local)?;
+    socket.connect(address).await
+}
+
+pub(super) async fn read(stream: &mut RawTcpStream, buffer: &mut [u8]) -> io::Result<usize> {
+    stream.read(buffer).await
+}
+
+pub(super) async fn write_all(stream: &mut RawTcpStream, data: &[u8]) -> io::Result<()> {
+    stream.write_all(data).await
+}
+
+pub(super) async fn shutdown(stream: &mut RawTcpStream) -> io::Result<()> {
+    stream.shutdown().await
+}
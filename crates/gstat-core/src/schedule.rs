@@ -0,0 +1,289 @@
+use crate::prelude::ServerInfo;
+
+use std::{
+    hash::{DefaultHasher, Hash, Hasher},
+    time::Duration,
+};
+
+/// How the first poll of each target is placed within the polling interval.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Phase {
+    /// Poll every target at the start of the interval.
+    ///
+    /// This is what a naive loop does, and produces a burst of queries every interval.
+    Aligned,
+    /// Spread targets evenly over the interval by their position in the plan.
+    ///
+    /// This gives the smoothest load for a fixed set of targets, but every offset shifts
+    /// when targets are added or removed.
+    Even,
+    /// Place each target at an offset derived from its address.
+    ///
+    /// Offsets are uniformly distributed for large fleets and don't change when other
+    /// targets are added or removed.
+    #[default]
+    Hashed,
+}
+
+/// Which targets are polled on each tick of a schedule.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Mode {
+    /// Poll every target on every tick.
+    #[default]
+    Every,
+    /// Poll a random subset of the targets on each tick, favouring heavier targets.
+    Sample(Sampling),
+}
+
+/// `Sampling` picks a weighted random subset of targets to poll on each tick.
+///
+/// Aggregators tracking millions of servers can't afford to poll all of them every interval.
+/// Sampling polls `per_tick` targets per tick, drawn without replacement with a probability
+/// proportional to their weight, e.g. their popularity or how much they changed recently.
+/// Every weight is raised to at least `floor`, so quiet targets are still polled eventually.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Sampling {
+    /// The number of targets polled per tick.
+    pub per_tick: usize,
+    /// The smallest weight a target is given.
+    pub floor: f64,
+}
+
+impl Sampling {
+    /// Creates a sampling mode polling `per_tick` targets per tick with a floor weight of 1.
+    ///
+    /// # Parameters
+    ///
+    /// * `per_tick`: The number of targets polled per tick.
+    pub fn new(per_tick: usize) -> Self {
+        Sampling {
+            per_tick,
+            floor: 1.0,
+        }
+    }
+
+    /// Sets the smallest weight a target is given.
+    ///
+    /// # Parameters
+    ///
+    /// * `floor`: The floor weight. Non-positive values are replaced by the smallest
+    ///   positive weight.
+    pub fn with_floor(mut self, floor: f64) -> Self {
+        self.floor = floor;
+        self
+    }
+
+    /// Draws the targets to poll on this tick.
+    ///
+    /// # Parameters
+    ///
+    /// * `candidates`: Every target paired with its weight.
+    ///
+    /// # Returns
+    ///
+    /// At most `per_tick` targets, heaviest draw first.
+    pub fn select<T>(&self, candidates: impl IntoIterator<Item = (T, f64)>) -> Vec<T> {
+        let floor = if self.floor > 0.0 {
+            self.floor
+        } else {
+            f64::MIN_POSITIVE
+        };
+
+        // Weighted sampling without replacement (Efraimidis and Spirakis): each candidate
+        // draws the key `ln(u) / weight`, and the largest keys win.
+        let mut keyed: Vec<(f64, T)> = candidates
+            .into_iter()
+            .map(|(target, weight)| {
+                let weight = if weight.is_nan() {
+                    floor
+                } else {
+                    weight.max(floor)
+                };
+                let draw = 1.0 - fastrand::f64();
+
+                (draw.ln() / weight, target)
+            })
+            .collect();
+
+        keyed.sort_by(|(a, _), (b, _)| b.total_cmp(a));
+        keyed.truncate(self.per_tick);
+
+        keyed.into_iter().map(|(_, target)| target).collect()
+    }
+
+    /// Returns a weight favouring servers with many players.
+    ///
+    /// # Parameters
+    ///
+    /// * `info`: The last known state of the server.
+    pub fn popularity(info: &ServerInfo) -> f64 {
+        f64::from(info.players)
+    }
+
+    /// Returns a weight favouring servers whose state changed between two polls.
+    ///
+    /// # Parameters
+    ///
+    /// * `previous`: The state of the server on the poll before last.
+    /// * `current`: The state of the server on the last poll.
+    pub fn volatility(previous: &ServerInfo, current: &ServerInfo) -> f64 {
+        let players = f64::from(previous.players.abs_diff(current.players));
+        let changed = [
+            previous.name != current.name,
+            previous.map != current.map,
+            previous.version != current.version,
+            previous.max_players != current.max_players,
+        ];
+
+        players + changed.into_iter().filter(|changed| *changed).count() as f64 * 10.0
+    }
+}
+
+/// `Schedule` decides when the targets of a monitor are polled.
+///
+/// When thousands of servers are polled on the same interval, starting them all at once
+/// produces a spike of traffic every interval. A schedule spreads the first poll of every
+/// target over the interval according to its `Phase`, and randomizes each following delay
+/// by up to `jitter` so targets that drift into lockstep separate again. In `Mode::Sample`,
+/// only a weighted subset of the targets is polled on each tick.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Schedule {
+    /// The average time between two polls of the same target.
+    pub interval: Duration,
+    /// The largest deviation from `interval`, as a fraction of it between `0.0` and `1.0`.
+    /// Values outside that range are clamped when the delays are computed.
+    pub jitter: f64,
+    /// How first polls are placed within the interval.
+    pub phase: Phase,
+    /// Which targets are polled on each tick.
+    pub mode: Mode,
+}
+
+impl Schedule {
+    /// Creates a schedule polling every target every `interval`, with hashed phases and no
+    /// jitter.
+    ///
+    /// # Parameters
+    ///
+    /// * `interval`: The average time between two polls of the same target.
+    pub fn new(interval: Duration) -> Self {
+        Schedule {
+            interval,
+            jitter: 0.0,
+            phase: Phase::default(),
+            mode: Mode::default(),
+        }
+    }
+
+    /// Sets the largest deviation from the interval, as a fraction of it.
+    ///
+    /// # Parameters
+    ///
+    /// * `jitter`: The fraction, clamped to between `0.0` and `1.0`.
+    pub fn with_jitter(mut self, jitter: f64) -> Self {
+        self.jitter = clamp_jitter(jitter);
+        self
+    }
+
+    /// Sets how first polls are placed within the interval.
+    pub fn with_phase(mut self, phase: Phase) -> Self {
+        self.phase = phase;
+        self
+    }
+
+    /// Sets which targets are polled on each tick.
+    pub fn with_mode(mut self, mode: Mode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Returns the targets to poll on this tick.
+    ///
+    /// # Parameters
+    ///
+    /// * `candidates`: Every target paired with its weight. Weights are ignored in
+    ///   `Mode::Every`.
+    pub fn select<T>(&self, candidates: impl IntoIterator<Item = (T, f64)>) -> Vec<T> {
+        match self.mode {
+            Mode::Every => candidates.into_iter().map(|(target, _)| target).collect(),
+            Mode::Sample(sampling) => sampling.select(candidates),
+        }
+    }
+
+    /// Returns the delay before the first poll of every target.
+    ///
+    /// # Parameters
+    ///
+    /// * `targets`: The targets to poll, usually `ServerAddress`es or `SocketAddr`s.
+    ///
+    /// # Returns
+    ///
+    /// The targets paired with their offset from the start of the schedule, in the order
+    /// they were given.
+    pub fn plan<T: Clone + Hash>(&self, targets: &[T]) -> Vec<(T, Duration)> {
+        let count = targets.len().max(1) as u32;
+
+        targets
+            .iter()
+            .enumerate()
+            .map(|(index, target)| {
+                let offset = match self.phase {
+                    Phase::Aligned => Duration::ZERO,
+                    Phase::Even => self.interval / count * index as u32,
+                    Phase::Hashed => self.hashed_offset(target),
+                };
+
+                (target.clone(), offset)
+            })
+            .collect()
+    }
+
+    /// Returns the delay before the first poll of a single target.
+    ///
+    /// `Phase::Even` needs the whole plan to place a target, so it is treated as
+    /// `Phase::Hashed` here.
+    ///
+    /// # Parameters
+    ///
+    /// * `target`: The target to poll.
+    pub fn first_delay<T: Hash>(&self, target: T) -> Duration {
+        match self.phase {
+            Phase::Aligned => Duration::ZERO,
+            Phase::Even | Phase::Hashed => self.hashed_offset(&target),
+        }
+    }
+
+    /// Returns the delay between a poll and the next poll of the same target.
+    pub fn next_delay(&self) -> Duration {
+        // `jitter` is public, so it is clamped here too: a deviation past the interval
+        // would make the delay negative.
+        let jitter = clamp_jitter(self.jitter);
+
+        if jitter == 0.0 {
+            return self.interval;
+        }
+
+        let deviation = jitter * (fastrand::f64() * 2.0 - 1.0);
+
+        self.interval.mul_f64(1.0 + deviation)
+    }
+
+    /// Returns a stable offset within the interval derived from `target`.
+    fn hashed_offset<T: Hash>(&self, target: &T) -> Duration {
+        let mut hasher = DefaultHasher::new();
+        target.hash(&mut hasher);
+
+        let fraction = (hasher.finish() >> 11) as f64 / (1u64 << 53) as f64;
+
+        self.interval.mul_f64(fraction)
+    }
+}
+
+/// Clamps a jitter to between `0.0` and `1.0`, treating NaN as no jitter.
+fn clamp_jitter(jitter: f64) -> f64 {
+    if jitter.is_nan() {
+        0.0
+    } else {
+        jitter.clamp(0.0, 1.0)
+    }
+}
@@ -0,0 +1,50 @@
+//! JSON Schemas of the data gstat emits.
+//!
+//! With the `schemars` feature, every serializable model implements
+//! `schemars::JsonSchema`, and its schema describes exactly the JSON produced by the
+//! `serde` feature. Web services can publish the schemas returned by `schemas` to validate
+//! payloads, or to generate types for clients, e.g. with `json-schema-to-typescript`.
+
+use crate::{
+    address::ServerAddress,
+    etag::ETag,
+    prelude::{AccessPolicy, Fingerprint, Player, Provenance, ServerInfo, Value},
+};
+
+use std::collections::BTreeMap;
+
+use schemars::{JsonSchema, Schema, SchemaGenerator};
+
+/// Returns the root schema of `T`, with the types it refers to under `$defs`.
+pub fn schema_for<T: JsonSchema>() -> Schema {
+    SchemaGenerator::default().into_root_schema_for::<T>()
+}
+
+/// Returns the root schemas of the public models by type name.
+///
+/// The set covers the common response model, its players and rules (`ServerInfo::extra`),
+/// the dynamic `Value`s of `Response::fields`, and the values derived from responses.
+pub fn schemas() -> BTreeMap<&'static str, Schema> {
+    let schemas = BTreeMap::from([
+        ("AccessPolicy", schema_for::<AccessPolicy>()),
+        ("ETag", schema_for::<ETag>()),
+        ("Fingerprint", schema_for::<Fingerprint>()),
+        ("Player", schema_for::<Player>()),
+        ("Provenance", schema_for::<Provenance>()),
+        ("ServerAddress", schema_for::<ServerAddress>()),
+        ("ServerInfo", schema_for::<ServerInfo>()),
+        ("Value", schema_for::<Value>()),
+    ]);
+
+    #[cfg(feature = "script-rhai")]
+    let schemas = {
+        let mut schemas = schemas;
+        schemas.insert(
+            "ScriptResponse",
+            schema_for::<crate::script::ScriptResponse>(),
+        );
+        schemas
+    };
+
+    schemas
+}
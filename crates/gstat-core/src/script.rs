@@ -0,0 +1,674 @@
+//! Custom game adapters written in Rhai.
+//!
+//! Long-tail games with a simple query format don't warrant a native implementation. A
+//! script describes such a game with three functions:
+//!
+//! ```rhai
+//! fn game() {
+//!     #{ id: "mygame", name: "My Game", transport: "udp", port: 7777, aliases: ["mg"] }
+//! }
+//!
+//! fn serialize() {
+//!     let query = blob();
+//!     query.push(0x01);
+//!     query
+//! }
+//!
+//! fn parse(reader) {
+//!     #{
+//!         name: reader.read_cstring(),
+//!         map: reader.read_cstring(),
+//!         players: reader.read_u8(),
+//!         max_players: reader.read_u8(),
+//!     }
+//! }
+//! ```
+//!
+//! `serialize` returns the query payload as a blob, and `parse` decodes a response through
+//! the `ByteReader` API (`read_u8`, `read_u16_le`, `read_cstring`, `read_varint_string`,
+//! `skip` and so on) into a map of `ServerInfo` fields, with `access` given by name, e.g.
+//! `"whitelist"`. The map may also hold `extra`, a map
+//! of game-specific strings, `player_list`, an array of player maps with `name`,
+//! `score`, `duration` (in seconds), `ping`, `team` and `extra`, and `provenance`, a map
+//! from field names to the protocol fields they were read from.
+//!
+//! The resulting `ScriptParser` pairs with the generic UDP or TCP protocols like any native
+//! parser. Scripts run with an operation limit, so a looping script fails instead of
+//! stalling the query.
+
+use crate::{
+    codec::reader::{ByteReader, DecodeResult},
+    memory::HeapSize,
+    prelude::{
+        AccessPolicy, Error, ErrorDetail, ErrorKind, Parser, Player, Provenance, Query, Response,
+        ServerInfo,
+    },
+    registry::{self, Transport},
+    standards::query::QueryOptions,
+};
+
+use std::{
+    collections::BTreeMap,
+    error::Error as StdError,
+    fmt::{Display, Formatter, Result as FmtResult},
+    sync::Arc,
+    time::Duration,
+};
+
+use bytes::Bytes;
+use rhai::{Array, Blob, Dynamic, Engine, EvalAltResult, Map, Scope, AST, FLOAT, INT};
+
+/// The number of operations a single script call may perform.
+const MAX_OPERATIONS: u64 = 1_000_000;
+
+/// The functions every game script must define, with their number of parameters.
+const REQUIRED_FUNCTIONS: &[(&str, usize)] = &[("game", 0), ("serialize", 0), ("parse", 1)];
+
+/// Describes why a game script could not be loaded or run.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ScriptError {
+    /// The script is not valid Rhai.
+    Compile(String),
+    /// The script does not define a required function.
+    MissingFunction(&'static str),
+    /// The metadata returned by `game()` is incomplete or malformed.
+    InvalidDefinition(String),
+    /// The script failed while running, e.g. because a read ran past the end of a packet.
+    Runtime(String),
+    /// A function returned a value of the wrong shape.
+    InvalidResult(String),
+    /// Another game is already registered under the identifier.
+    Duplicate(String),
+    /// No game is registered under the identifier.
+    UnknownGame(String),
+    /// The game is queried over a transport scripted games can't use yet.
+    UnsupportedTransport(String),
+}
+
+impl Display for ScriptError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            Self::Compile(err) => write!(f, "script does not compile: {err}"),
+            Self::MissingFunction(name) => write!(f, "script does not define `{name}`"),
+            Self::InvalidDefinition(err) => write!(f, "invalid game definition: {err}"),
+            Self::Runtime(err) => write!(f, "script failed: {err}"),
+            Self::InvalidResult(err) => write!(f, "script returned an invalid value: {err}"),
+            Self::Duplicate(id) => write!(f, "a game named `{id}` is already registered"),
+            Self::UnknownGame(id) => write!(f, "unknown game `{id}`; load its script"),
+            Self::UnsupportedTransport(id) => write!(
+                f,
+                "`{id}` is queried over TCP, which scripted games can't use yet"
+            ),
+        }
+    }
+}
+
+impl StdError for ScriptError {}
+
+/// The query sent to scripted games; its payload comes from the script's `serialize`.
+#[derive(Clone, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ScriptQuery;
+
+impl Query for ScriptQuery {
+    type E = ScriptError;
+    type Options = ();
+
+    fn from_options(_options: QueryOptions) -> Self {
+        ScriptQuery
+    }
+}
+
+/// A response decoded by a script's `parse` function.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(default)
+)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct ScriptResponse {
+    /// The server description returned by the script.
+    pub info: ServerInfo,
+    /// The players returned by the script under `player_list`.
+    pub players: Vec<Player>,
+    /// The sources the script recorded under `provenance`.
+    pub provenance: Provenance,
+}
+
+impl Response for ScriptResponse {
+    type E = ScriptError;
+
+    fn new() -> Result<Self, Error<Self::E>> {
+        Ok(ScriptResponse::default())
+    }
+
+    fn to_common(&self) -> ServerInfo {
+        self.info.clone()
+    }
+
+    fn players(&self) -> Vec<Player> {
+        self.players.clone()
+    }
+
+    fn provenance(&self) -> Provenance {
+        self.provenance.clone()
+    }
+
+    fn heap_size(&self) -> usize {
+        self.info.heap_size() + self.players.heap_size() + self.provenance.heap_size()
+    }
+}
+
+/// A compiled game script together with the engine that runs it.
+struct Compiled {
+    engine: Engine,
+    ast: AST,
+}
+
+impl Compiled {
+    /// Calls a function defined by the script.
+    fn call(&self, name: &str, args: impl rhai::FuncArgs) -> Result<Dynamic, ScriptError> {
+        self.engine
+            .call_fn::<Dynamic>(&mut Scope::new(), &self.ast, name, args)
+            .map_err(|err| ScriptError::Runtime(err.to_string()))
+    }
+}
+
+/// `ScriptGame` is a game whose query format is described by a Rhai script.
+#[derive(Clone)]
+pub struct ScriptGame {
+    id: String,
+    name: String,
+    aliases: Vec<String>,
+    transport: Transport,
+    port: u16,
+    script: Arc<Compiled>,
+}
+
+impl ScriptGame {
+    /// Compiles a game script and reads its metadata.
+    ///
+    /// # Parameters
+    ///
+    /// * `source`: The Rhai source of the script.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing either the game or an `Error::GameError` describing why the
+    /// script was rejected.
+    pub fn compile(source: &str) -> Result<Self, Error<ScriptError>> {
+        let engine = engine();
+        let ast = engine
+            .compile(source)
+            .map_err(|err| game_error(ScriptError::Compile(err.to_string())))?;
+
+        for &(name, params) in REQUIRED_FUNCTIONS {
+            let defined = ast
+                .iter_functions()
+                .any(|function| function.name == name && function.params.len() == params);
+
+            if !defined {
+                return Err(game_error(ScriptError::MissingFunction(name)));
+            }
+        }
+
+        let script = Compiled { engine, ast };
+        let definition = script
+            .call("game", ())
+            .and_then(|value| to_map(value, "game()"))
+            .map_err(game_error)?;
+
+        let text = |key: &str| match definition.get(key) {
+            Some(value) => value
+                .clone()
+                .into_string()
+                .map_err(|_| ScriptError::InvalidDefinition(format!("`{key}` must be a string"))),
+            None => Err(ScriptError::InvalidDefinition(format!(
+                "`{key}` is missing"
+            ))),
+        };
+
+        let id = text("id").map_err(game_error)?;
+        let name = text("name").map_err(game_error)?;
+        let transport = match text("transport").map_err(game_error)?.as_str() {
+            "udp" => Transport::Udp,
+            "tcp" => Transport::Tcp,
+            other => {
+                return Err(game_error(ScriptError::InvalidDefinition(format!(
+                    "unknown transport `{other}`"
+                ))))
+            }
+        };
+        let port = definition
+            .get("port")
+            .and_then(|port| port.as_int().ok())
+            .and_then(|port| u16::try_from(port).ok())
+            .ok_or_else(|| {
+                game_error(ScriptError::InvalidDefinition(
+                    "`port` must be a port number".into(),
+                ))
+            })?;
+        let aliases = match definition.get("aliases") {
+            Some(aliases) => strings(aliases.clone(), "aliases")
+                .map_err(|err| game_error(ScriptError::InvalidDefinition(err.to_string())))?,
+            None => Vec::new(),
+        };
+
+        Ok(ScriptGame {
+            id,
+            name,
+            aliases,
+            transport,
+            port,
+            script: Arc::new(script),
+        })
+    }
+
+    /// Returns the unique game identifier.
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// Returns the human readable game name.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the alternative identifiers the game can be looked up by.
+    pub fn aliases(&self) -> &[String] {
+        &self.aliases
+    }
+
+    /// Returns the transport the game is queried over.
+    pub fn transport(&self) -> Transport {
+        self.transport
+    }
+
+    /// Returns the default query port.
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+
+    /// Returns a parser running the script, to be paired with a protocol for `transport`.
+    pub fn parser(&self) -> ScriptParser {
+        ScriptParser {
+            script: self.script.clone(),
+        }
+    }
+
+    /// Returns `true` if the game is known as `identifier`, ignoring ASCII case.
+    pub fn matches(&self, identifier: &str) -> bool {
+        self.id.eq_ignore_ascii_case(identifier)
+            || self
+                .aliases
+                .iter()
+                .any(|alias| alias.eq_ignore_ascii_case(identifier))
+    }
+}
+
+impl std::fmt::Debug for ScriptGame {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.debug_struct("ScriptGame")
+            .field("id", &self.id)
+            .field("name", &self.name)
+            .field("aliases", &self.aliases)
+            .field("transport", &self.transport)
+            .field("port", &self.port)
+            .finish_non_exhaustive()
+    }
+}
+
+/// A `Parser` that runs a game script's `serialize` and `parse` functions.
+#[derive(Clone)]
+pub struct ScriptParser {
+    script: Arc<Compiled>,
+}
+
+impl<'a> Parser<'a, ScriptQuery, ScriptResponse> for ScriptParser {
+    type SE = ScriptError;
+    type DE = ScriptError;
+
+    fn _serialize_query(&self, _query: &ScriptQuery) -> Result<Vec<u8>, Self::SE> {
+        let payload = self.script.call("serialize", ())?;
+
+        if payload.is_string() {
+            return Ok(payload.into_string().unwrap_or_default().into_bytes());
+        }
+
+        payload
+            .into_blob()
+            .map_err(|found| ScriptError::InvalidResult(format!("serialize() returned {found}")))
+    }
+
+    fn _deserialize_response(&self, data: Bytes) -> Result<ScriptResponse, Self::DE> {
+        let reader = ByteReader::new(data);
+        let result = to_map(self.script.call("parse", (reader,))?, "parse()")?;
+
+        let mut info = ServerInfo::default();
+        let mut players = Vec::new();
+        let mut provenance = Provenance::new();
+
+        for (key, value) in result {
+            match key.as_str() {
+                "name" => info.name = string(value, "name")?,
+                "map" => info.map = string(value, "map")?,
+                "game" => info.game = string(value, "game")?,
+                "version" => info.version = string(value, "version")?,
+                "players" => info.players = int(value, "players")?,
+                "max_players" => info.max_players = int(value, "max_players")?,
+                "password" => {
+                    info.password = value.as_bool().map_err(|found| {
+                        ScriptError::InvalidResult(format!("`password` is {found}"))
+                    })?
+                }
+                "access" => {
+                    info.access = string(value, "access")?
+                        .parse()
+                        .map_err(|err| ScriptError::InvalidResult(format!("`access`: {err}")))?
+                }
+                "extra" => info.extra = extra(value, "extra")?,
+                "provenance" => {
+                    for (field, source) in extra(value, "provenance")? {
+                        provenance.record(field, source);
+                    }
+                }
+                "player_list" => {
+                    players = array(value, "player_list")?
+                        .into_iter()
+                        .map(player)
+                        .collect::<Result<_, _>>()?
+                }
+                _ => {
+                    info.extra.insert(key.to_string(), value.to_string());
+                }
+            }
+        }
+
+        // A password flag without a stricter policy still restricts access.
+        if info.password {
+            info.access = info.access.max(AccessPolicy::Password);
+        }
+
+        Ok(ScriptResponse {
+            info,
+            players,
+            provenance,
+        })
+    }
+}
+
+/// `ScriptRegistry` holds the scripted games loaded at runtime.
+///
+/// Identifiers and aliases are unique across the registry and must not shadow a game from
+/// the built-in `registry`, so a script can't silently replace a native implementation.
+#[derive(Clone, Debug, Default)]
+pub struct ScriptRegistry {
+    games: Vec<ScriptGame>,
+}
+
+impl ScriptRegistry {
+    /// Creates an empty registry.
+    pub fn new() -> Self {
+        ScriptRegistry::default()
+    }
+
+    /// Compiles a game script and registers the game.
+    ///
+    /// # Parameters
+    ///
+    /// * `source`: The Rhai source of the script.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing either the registered game or an `Error::GameError`.
+    pub fn load(&mut self, source: &str) -> Result<&ScriptGame, Error<ScriptError>> {
+        let game = ScriptGame::compile(source)?;
+
+        self.register(game)
+    }
+
+    /// Registers a compiled game.
+    ///
+    /// # Parameters
+    ///
+    /// * `game`: The game to register.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing either the registered game or an `Error::GameError` if one of
+    /// its identifiers is taken.
+    pub fn register(&mut self, game: ScriptGame) -> Result<&ScriptGame, Error<ScriptError>> {
+        let taken = std::iter::once(&game.id)
+            .chain(&game.aliases)
+            .find(|identifier| {
+                registry::game(identifier).is_some() || self.get(identifier).is_some()
+            });
+
+        if let Some(identifier) = taken {
+            return Err(game_error(ScriptError::Duplicate(identifier.clone())));
+        }
+
+        self.games.push(game);
+
+        Ok(self.games.last().expect("game was just pushed"))
+    }
+
+    /// Looks up a game by its identifier or one of its aliases, ignoring ASCII case.
+    pub fn get(&self, identifier: &str) -> Option<&ScriptGame> {
+        self.games.iter().find(|game| game.matches(identifier))
+    }
+
+    /// Looks up a game to query, as services and bindings do for the targets they are given.
+    ///
+    /// Scripted games are queried with the UDP protocol of `gstat-udp`, so games declaring
+    /// the TCP transport are refused.
+    ///
+    /// # Parameters
+    ///
+    /// * `identifier`: The identifier or alias of the game, ignoring ASCII case.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing either the game, or a `ScriptError::UnknownGame` or
+    /// `ScriptError::UnsupportedTransport`.
+    pub fn find_queryable(&self, identifier: &str) -> Result<&ScriptGame, ScriptError> {
+        let game = self
+            .get(identifier)
+            .ok_or_else(|| ScriptError::UnknownGame(identifier.to_string()))?;
+
+        match game.transport() {
+            Transport::Udp => Ok(game),
+            Transport::Tcp => Err(ScriptError::UnsupportedTransport(game.id().to_string())),
+        }
+    }
+
+    /// Returns the registered games in the order they were registered.
+    pub fn iter(&self) -> impl Iterator<Item = &ScriptGame> {
+        self.games.iter()
+    }
+
+    /// Returns the number of registered games.
+    pub fn len(&self) -> usize {
+        self.games.len()
+    }
+
+    /// Returns `true` if no games are registered.
+    pub fn is_empty(&self) -> bool {
+        self.games.is_empty()
+    }
+}
+
+/// Creates an engine exposing the `ByteReader` API to scripts.
+fn engine() -> Engine {
+    let mut engine = Engine::new();
+    engine.set_max_operations(MAX_OPERATIONS);
+
+    engine
+        .register_type_with_name::<ByteReader>("ByteReader")
+        .register_get("position", |reader: &mut ByteReader| {
+            reader.position() as INT
+        })
+        .register_get("remaining", |reader: &mut ByteReader| {
+            reader.remaining() as INT
+        })
+        .register_fn("is_empty", |reader: &mut ByteReader| reader.is_empty())
+        .register_fn("peek_u8", |reader: &mut ByteReader| {
+            decode(reader.peek_u8()).map(INT::from)
+        })
+        .register_fn("skip", |reader: &mut ByteReader, len: INT| {
+            decode(reader.skip(length(len)?))
+        })
+        .register_fn("read_bytes", |reader: &mut ByteReader, len: INT| {
+            decode(reader.read_bytes(length(len)?)).map(|bytes| Blob::from(&bytes[..]))
+        })
+        .register_fn("read_rest", |reader: &mut ByteReader| {
+            Blob::from(&reader.read_rest()[..])
+        })
+        .register_fn("read_cstring", |reader: &mut ByteReader| {
+            decode(reader.read_cstring()).map(String::from)
+        })
+        .register_fn("read_cstring_lossy", |reader: &mut ByteReader| {
+            decode(reader.read_cstring_lossy()).map(String::from)
+        })
+        .register_fn("read_pascal_string", |reader: &mut ByteReader| {
+            decode(reader.read_pascal_string()).map(String::from)
+        })
+        .register_fn("read_varint_string", |reader: &mut ByteReader| {
+            decode(reader.read_varint_string()).map(String::from)
+        })
+        .register_fn(
+            "read_utf16le_string",
+            |reader: &mut ByteReader, units: INT| {
+                decode(reader.read_utf16le_string(length(units)?)).map(String::from)
+            },
+        )
+        .register_fn("read_utf16le_cstring", |reader: &mut ByteReader| {
+            decode(reader.read_utf16le_cstring()).map(String::from)
+        })
+        .register_fn("read_f32_le", |reader: &mut ByteReader| {
+            decode(reader.read_f32_le()).map(FLOAT::from)
+        })
+        .register_fn("read_f64_le", |reader: &mut ByteReader| {
+            decode(reader.read_f64_le())
+        });
+
+    macro_rules! register_integers {
+        ($($name:ident),*) => {
+            $(
+                engine.register_fn(stringify!($name), |reader: &mut ByteReader| {
+                    decode(reader.$name()).map(INT::from)
+                });
+            )*
+        };
+    }
+
+    register_integers!(
+        read_u8,
+        read_i8,
+        read_u16_le,
+        read_u16_be,
+        read_i16_le,
+        read_i16_be,
+        read_u32_le,
+        read_u32_be,
+        read_i32_le,
+        read_i32_be,
+        read_i64_le,
+        read_i64_be,
+        read_varint_u32,
+        read_varint_i32,
+        read_varint_i64
+    );
+
+    engine
+}
+
+/// Converts the result of a read into a script result.
+fn decode<T>(result: DecodeResult<T>) -> Result<T, Box<EvalAltResult>> {
+    result.map_err(|err| err.to_string().into())
+}
+
+/// Converts a script integer into a length.
+fn length(len: INT) -> Result<usize, Box<EvalAltResult>> {
+    usize::try_from(len).map_err(|_| format!("invalid length {len}").into())
+}
+
+/// Wraps a script error as a game error.
+fn game_error(err: ScriptError) -> Error<ScriptError> {
+    Error::GameError(ErrorDetail::new("Invalid game script", Some(err)).with_kind(ErrorKind::Other))
+}
+
+/// Reads a value returned by the script as a map.
+fn to_map(value: Dynamic, context: &str) -> Result<Map, ScriptError> {
+    value
+        .try_cast_result::<Map>()
+        .map_err(|found| ScriptError::InvalidResult(format!("{context} returned {found}")))
+}
+
+/// Reads a field returned by the script as a string.
+fn string(value: Dynamic, key: &str) -> Result<String, ScriptError> {
+    value
+        .into_string()
+        .map_err(|found| ScriptError::InvalidResult(format!("`{key}` is {found}")))
+}
+
+/// Reads a field returned by the script as a non-negative integer.
+fn int<T: TryFrom<INT>>(value: Dynamic, key: &str) -> Result<T, ScriptError> {
+    value
+        .as_int()
+        .ok()
+        .and_then(|value| T::try_from(value).ok())
+        .ok_or_else(|| ScriptError::InvalidResult(format!("`{key}` is out of range")))
+}
+
+/// Reads a field returned by the script as an array.
+fn array(value: Dynamic, key: &str) -> Result<Array, ScriptError> {
+    value
+        .try_cast_result::<Array>()
+        .map_err(|found| ScriptError::InvalidResult(format!("`{key}` is {found}")))
+}
+
+/// Reads a field returned by the script as an array of strings.
+fn strings(value: Dynamic, key: &str) -> Result<Vec<String>, ScriptError> {
+    array(value, key)?
+        .into_iter()
+        .map(|value| string(value, key))
+        .collect()
+}
+
+/// Reads a field returned by the script as a map of game-specific strings.
+fn extra(value: Dynamic, key: &str) -> Result<BTreeMap<String, String>, ScriptError> {
+    Ok(to_map(value, key)?
+        .into_iter()
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .collect())
+}
+
+/// Reads a player map returned by the script.
+fn player(value: Dynamic) -> Result<Player, ScriptError> {
+    let mut player = Player::default();
+
+    for (key, value) in to_map(value, "player_list")? {
+        match key.as_str() {
+            "name" => player.name = string(value, "name")?,
+            "score" => player.score = Some(int(value, "score")?),
+            "ping" => player.ping = Some(int(value, "ping")?),
+            "team" => player.team = Some(string(value, "team")?),
+            "duration" => {
+                let seconds = match value.as_float() {
+                    Ok(seconds) => seconds,
+                    Err(_) => int::<INT>(value, "duration")? as FLOAT,
+                };
+
+                player.duration = Duration::try_from_secs_f64(seconds).ok();
+            }
+            "extra" => player.extra = extra(value, "extra")?,
+            _ => {
+                player.extra.insert(key.to_string(), value.to_string());
+            }
+        }
+    }
+
+    Ok(player)
+}
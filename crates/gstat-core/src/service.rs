@@ -0,0 +1,168 @@
+//! `tower::Service` integration for the query pipeline.
+//!
+//! `GameService` answers `QueryRequest`s with `Game::fetch_with`, so the standard tower
+//! middleware (timeouts, buffers, rate and concurrency limits, load shedding) can be
+//! stacked in front of a game, and gstat can be embedded in tower-based applications.
+//!
+//! ```no_run
+//! # use gstat_core::prelude::{Game, Protocol, Query};
+//! # async fn run<G, P, Q, R, E>(game: G)
+//! # where
+//! #     G: for<'a> Game<'a, P> + Send + Sync + 'static,
+//! #     P: for<'a> Protocol<'a, Q = Q, R = R, E = E>,
+//! #     Q: Query + Send + 'static,
+//! #     R: Send + 'static,
+//! #     E: Send + 'static,
+//! # {
+//! use gstat_core::service::{GameService, QueryRequest};
+//! use tower_service::Service;
+//!
+//! let mut service = GameService::<G, P>::new(game);
+//!
+//! let server = "192.0.2.1:27015".parse().unwrap();
+//! let fetched = service.call(QueryRequest::new(server)).await;
+//! # }
+//! ```
+
+use crate::{
+    prelude::{Error, Fetched, Game, Protocol, Query, TimeoutSettings},
+    standards::dyn_protocol::BoxFuture,
+};
+
+use std::{
+    fmt::{Debug, Formatter, Result as FmtResult},
+    marker::PhantomData,
+    net::SocketAddr,
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+use tower_service::Service;
+
+/// A query for `GameService`: the server to query and, optionally, what to ask it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct QueryRequest<Q> {
+    /// The address of the server.
+    pub address: SocketAddr,
+    /// The query to send, or `None` for the game's `Game::default_query`.
+    pub query: Option<Q>,
+    /// The time limits of the query, or `None` for the limits of the service.
+    pub timeouts: Option<TimeoutSettings>,
+}
+
+impl<Q> QueryRequest<Q> {
+    /// Creates a request for the game's default query.
+    ///
+    /// # Parameters
+    ///
+    /// * `address`: The address of the server.
+    pub fn new(address: SocketAddr) -> Self {
+        QueryRequest {
+            address,
+            query: None,
+            timeouts: None,
+        }
+    }
+
+    /// Sets the query to send instead of the game's default query.
+    pub fn with_query(mut self, query: Q) -> Self {
+        self.query = Some(query);
+        self
+    }
+
+    /// Sets the time limits of this query, overriding those of the service.
+    pub fn with_timeouts(mut self, timeouts: TimeoutSettings) -> Self {
+        self.timeouts = Some(timeouts);
+        self
+    }
+}
+
+impl<Q> From<SocketAddr> for QueryRequest<Q> {
+    fn from(address: SocketAddr) -> Self {
+        QueryRequest::new(address)
+    }
+}
+
+/// `GameService` is a `tower::Service` fetching the responses of a game's servers.
+///
+/// It is always ready: every call starts its own exchange, so back-pressure and limits are
+/// left to the middleware in front of it. The futures own the game and are `Send`, so the
+/// service works behind `tower::buffer::Buffer` and on multi-threaded runtimes. Clones
+/// share the game.
+pub struct GameService<G, P> {
+    game: Arc<G>,
+    timeouts: TimeoutSettings,
+    _marker: PhantomData<fn() -> P>,
+}
+
+impl<G, P> GameService<G, P> {
+    /// Wraps `game`.
+    ///
+    /// # Parameters
+    ///
+    /// * `game`: The game whose servers are queried.
+    pub fn new(game: G) -> Self {
+        GameService {
+            game: Arc::new(game),
+            timeouts: TimeoutSettings::default(),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Sets the time limits of requests that don't set their own.
+    pub fn with_timeouts(mut self, timeouts: TimeoutSettings) -> Self {
+        self.timeouts = timeouts;
+        self
+    }
+
+    /// Returns the wrapped game.
+    pub fn game(&self) -> &G {
+        &self.game
+    }
+}
+
+impl<G, P, Q, R, E> Service<QueryRequest<Q>> for GameService<G, P>
+where
+    G: for<'a> Game<'a, P> + Send + Sync + 'static,
+    P: for<'a> Protocol<'a, Q = Q, R = R, E = E>,
+    Q: Query + Send + 'static,
+    R: Send + 'static,
+    E: Send + 'static,
+{
+    type Response = Fetched<R, E>;
+    type Error = Error<E>;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: QueryRequest<Q>) -> Self::Future {
+        let game = self.game.clone();
+        let timeouts = request.timeouts.unwrap_or(self.timeouts);
+
+        Box::pin(async move {
+            let query = request.query.unwrap_or_else(|| game.default_query());
+
+            game.fetch_with(query, request.address, timeouts).await
+        })
+    }
+}
+
+impl<G, P> Clone for GameService<G, P> {
+    fn clone(&self) -> Self {
+        GameService {
+            game: self.game.clone(),
+            timeouts: self.timeouts,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<G, P> Debug for GameService<G, P> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.debug_struct("GameService")
+            .field("timeouts", &self.timeouts)
+            .finish_non_exhaustive()
+    }
+}
@@ -0,0 +1,268 @@
+//! The Minecraft Server List Ping exchange.
+//!
+//! A client connects over TCP, sends a handshake switching the connection to the status
+//! state, and then asks for the status document, times a ping, or both. `detect` and
+//! `ping` use `SlpConnection` to recognize and time Minecraft servers, and the `SlpQuery`
+//! of `gstat-tcp` is built on it, so the handshake and the framing are only written once.
+
+use crate::{
+    codec::writer::ByteWriter,
+    prelude::{Error, ErrorDetail, ErrorKind, TimeoutSettings},
+    runtime::{self, TcpStream, READ_CHUNK_LEN},
+    timeout::with_timeout,
+};
+
+use std::{io, net::SocketAddr, time::Duration};
+
+use bytes::{Buf, Bytes, BytesMut};
+
+/// The packet ID of status requests and responses.
+const STATUS: u8 = 0x00;
+/// The packet ID of pings and pongs.
+const PING: u8 = 0x01;
+
+/// The largest packet accepted from the server, in bytes, which is the most a 3 byte
+/// length prefix can announce.
+const MAX_PACKET_LEN: usize = (1 << 21) - 1;
+
+/// What a Server List Ping asks the server for.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SlpMode {
+    /// The status document, followed by a ping timing the connection.
+    #[default]
+    Status,
+    /// A ping alone after the handshake, skipping the status document, for latency checks
+    /// across many servers.
+    PingOnly,
+}
+
+/// A connection to a Minecraft server in the status state.
+///
+/// Errors carry an `io::Error` of kind `InvalidData` when the server breaks the framing,
+/// and of kind `UnexpectedEof` when it closes the connection early.
+#[derive(Debug)]
+pub struct SlpConnection {
+    stream: TcpStream,
+    /// The bytes received but not read yet.
+    buffer: BytesMut,
+    /// The handshake, sent along with the first request.
+    handshake: Option<Vec<u8>>,
+    timeouts: TimeoutSettings,
+}
+
+impl SlpConnection {
+    /// Connects to a server.
+    ///
+    /// # Parameters
+    ///
+    /// * `address`: The address of the server, which the handshake repeats.
+    /// * `timeouts`: The time limits for connecting, and for writing and reading each packet.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing either the connection or an `Error`.
+    pub async fn connect(
+        address: SocketAddr,
+        timeouts: &TimeoutSettings,
+    ) -> Result<Self, Error<io::Error>> {
+        let connect = async { Ok(TcpStream::connect(address).await?) };
+        let stream = with_timeout(timeouts.connect, "SLP connect timed out", connect).await?;
+
+        Ok(SlpConnection {
+            stream,
+            buffer: BytesMut::new(),
+            handshake: Some(handshake(address)),
+            timeouts: *timeouts,
+        })
+    }
+
+    /// Requests the status document.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing either the JSON document as sent by the server or an `Error`.
+    pub async fn status(&mut self) -> Result<String, Error<io::Error>> {
+        self.write(&[0x01, STATUS]).await?;
+
+        let mut packet = self.read(STATUS).await?;
+        let (len, prefix) =
+            read_varint(&packet)?.ok_or_else(|| truncated("status document length"))?;
+        packet.advance(prefix);
+
+        if packet.len() < len {
+            return Err(truncated("status document"));
+        }
+
+        Ok(String::from_utf8_lossy(&packet[..len]).into_owned())
+    }
+
+    /// Sends a ping and waits for the pong repeating it.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing either the time the ping took to be answered or an `Error`.
+    pub async fn ping(&mut self) -> Result<Duration, Error<io::Error>> {
+        // A ping is its length, its ID and a payload the pong repeats.
+        let payload = fastrand::i64(..);
+        let mut ping = ByteWriter::new();
+        ping.write_varint_u32(9)
+            .write_u8(PING)
+            .write_i64_be(payload);
+
+        let started = runtime::try_now();
+        self.write(ping.as_slice()).await?;
+        let pong = self.read(PING).await?;
+        let latency = runtime::elapsed(started);
+
+        if pong[..] != payload.to_be_bytes() {
+            return Err(invalid("pong doesn't repeat the ping".to_string()));
+        }
+
+        Ok(latency)
+    }
+
+    /// Closes the connection, ignoring errors since the exchange is over.
+    pub async fn close(mut self) {
+        let _ = self.stream.shutdown().await;
+    }
+
+    /// Writes a packet within the write time limit, after the handshake if it wasn't sent.
+    async fn write(&mut self, packet: &[u8]) -> Result<(), Error<io::Error>> {
+        let mut data = self.handshake.take().unwrap_or_default();
+        data.extend_from_slice(packet);
+
+        let write = async { Ok(self.stream.write_all(&data).await?) };
+
+        with_timeout(self.timeouts.write, "SLP write timed out", write).await
+    }
+
+    /// Reads the next packet within the read time limit and checks its ID.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing either the packet without its length and ID, or an `Error`.
+    async fn read(&mut self, id: u8) -> Result<Bytes, Error<io::Error>> {
+        let (stream, buffer) = (&mut self.stream, &mut self.buffer);
+
+        let read = async {
+            loop {
+                if let Some((len, prefix)) = read_varint(buffer)? {
+                    if len > MAX_PACKET_LEN {
+                        return Err(invalid(format!(
+                            "SLP packet of {len} bytes exceeds the limit of {MAX_PACKET_LEN}"
+                        )));
+                    }
+
+                    if buffer.len() >= prefix + len {
+                        buffer.advance(prefix);
+                        let mut packet = buffer.split_to(len).freeze();
+
+                        return match packet.first() {
+                            Some(&answer) if answer == id => {
+                                packet.advance(1);
+                                Ok(packet)
+                            }
+                            _ => Err(invalid(format!(
+                                "expected an SLP packet with the ID {id:#04x}"
+                            ))),
+                        };
+                    }
+                }
+
+                let mut chunk = [0; READ_CHUNK_LEN];
+                let read = stream.read(&mut chunk).await?;
+
+                if read == 0 {
+                    return Err(Error::ProtocolError(
+                        ErrorDetail::new(
+                            "SLP connection closed by peer",
+                            Some(io::Error::from(io::ErrorKind::UnexpectedEof)),
+                        )
+                        .with_kind(ErrorKind::Truncated),
+                    ));
+                }
+
+                buffer.extend_from_slice(&chunk[..read]);
+            }
+        };
+
+        with_timeout(self.timeouts.read, "SLP read timed out", read).await
+    }
+}
+
+/// Encodes the handshake switching the connection to the status state.
+///
+/// # Parameters
+///
+/// * `address`: The address the connection is made to, which the handshake repeats.
+///
+/// # Returns
+///
+/// The handshake packet with its length prefix.
+fn handshake(address: SocketAddr) -> Vec<u8> {
+    let mut handshake = ByteWriter::new();
+    handshake
+        .write_u8(0x00)
+        .write_varint_i32(-1)
+        .write_varint_string(&address.ip().to_string())
+        .write_u16_be(address.port())
+        .write_u8(0x01);
+    let handshake = handshake.as_slice();
+
+    let mut packet = ByteWriter::new();
+    packet
+        .write_varint_u32(handshake.len() as u32)
+        .write_bytes(handshake);
+
+    packet.as_slice().to_vec()
+}
+
+/// Reads a varint from the start of `data`.
+///
+/// # Returns
+///
+/// A `Result` containing the value and the number of bytes it took, `None` if `data` ends
+/// before the varint does, or an `Error` if the varint is longer than 3 bytes, the most a
+/// packet length takes.
+fn read_varint(data: &[u8]) -> Result<Option<(usize, usize)>, Error<io::Error>> {
+    let mut value = 0;
+
+    for (index, &byte) in data.iter().enumerate().take(3) {
+        value |= usize::from(byte & 0x7F) << (7 * index);
+
+        if byte & 0x80 == 0 {
+            return Ok(Some((value, index + 1)));
+        }
+    }
+
+    if data.len() < 3 {
+        Ok(None)
+    } else {
+        Err(invalid("SLP varint is longer than 3 bytes".to_string()))
+    }
+}
+
+/// Returns the error of a packet breaking the framing.
+fn invalid(message: String) -> Error<io::Error> {
+    Error::ProtocolError(
+        ErrorDetail::new(
+            "Invalid SLP packet",
+            Some(io::Error::new(io::ErrorKind::InvalidData, message)),
+        )
+        .with_kind(ErrorKind::InvalidPacket),
+    )
+}
+
+/// Returns the error of a packet ending before one of its values.
+fn truncated(value: &str) -> Error<io::Error> {
+    invalid(format!("SLP packet ends before its {value}"))
+}
+
+/// Turns an error of the exchange into the `io::Error` it carries, or one describing it.
+pub(crate) fn into_io(err: Error<io::Error>) -> io::Error {
+    let message = err.to_string();
+
+    err.into_detail()
+        .into_inner()
+        .unwrap_or_else(|| io::Error::other(message))
+}
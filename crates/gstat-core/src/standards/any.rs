@@ -0,0 +1,456 @@
+use crate::{
+    etag::ETag,
+    memory::HeapSize,
+    prelude::{
+        Error, Game, Player, Protocol, Provenance, Query, QueryOptions, Response, ServerInfo,
+        TimeoutSettings, Value,
+    },
+    retry::RetryPolicy,
+    standards::{
+        dyn_game::{erase, BoxError, ErasedGame},
+        dyn_protocol::BoxFuture,
+        game::{FetchResult, Fetched},
+    },
+};
+
+use std::{
+    any::{type_name, Any},
+    collections::BTreeMap,
+    error::Error as StdError,
+    fmt::{Debug, Formatter, Result as FmtResult},
+    net::{IpAddr, SocketAddr},
+};
+
+/// The object-safe part of `Response`, implemented for every response type.
+trait ErasedResponse: Send + Sync {
+    fn to_common(&self) -> ServerInfo;
+
+    fn players(&self) -> Vec<Player>;
+
+    fn provenance(&self) -> Provenance;
+
+    fn fields(&self) -> BTreeMap<String, Value>;
+
+    fn etag(&self) -> ETag;
+
+    fn heap_size(&self) -> usize;
+
+    fn type_name(&self) -> &'static str;
+
+    fn as_any(&self) -> &dyn Any;
+
+    fn into_any(self: Box<Self>) -> Box<dyn Any + Send + Sync>;
+}
+
+impl<R: Response + 'static> ErasedResponse for R {
+    fn to_common(&self) -> ServerInfo {
+        Response::to_common(self)
+    }
+
+    fn players(&self) -> Vec<Player> {
+        Response::players(self)
+    }
+
+    fn provenance(&self) -> Provenance {
+        Response::provenance(self)
+    }
+
+    fn fields(&self) -> BTreeMap<String, Value> {
+        Response::fields(self)
+    }
+
+    fn etag(&self) -> ETag {
+        Response::etag(self)
+    }
+
+    fn heap_size(&self) -> usize {
+        Response::heap_size(self)
+    }
+
+    fn type_name(&self) -> &'static str {
+        type_name::<R>()
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn into_any(self: Box<Self>) -> Box<dyn Any + Send + Sync> {
+        self
+    }
+}
+
+/// A response whose type is only known at runtime.
+///
+/// Unlike `DynFetched`, which converts the response into the common model right away,
+/// `AnyResponse` keeps the game-specific response, so the common model is only built when
+/// it is asked for. Callers that know the game get the response back with `downcast_ref`;
+/// generic tools print it through `fields`:
+///
+/// ```ignore
+/// let fetched = game.fetch(address).await?;
+///
+/// if let Some(info) = fetched.response.downcast_ref::<A2sInfo>() {
+///     println!("VAC secured: {}", info.vac);
+/// }
+///
+/// for (name, value) in fetched.response.fields() {
+///     println!("{name}: {value}");
+/// }
+/// ```
+pub struct AnyResponse {
+    response: Box<dyn ErasedResponse>,
+}
+
+impl AnyResponse {
+    /// Erases the type of `response`.
+    ///
+    /// # Parameters
+    ///
+    /// * `response`: The game-specific response.
+    pub fn new<R: Response + 'static>(response: R) -> Self {
+        AnyResponse {
+            response: Box::new(response),
+        }
+    }
+
+    /// Returns the server information, as returned by `Response::to_common`.
+    pub fn info(&self) -> ServerInfo {
+        self.response.to_common()
+    }
+
+    /// Returns the players, as returned by `Response::players`.
+    pub fn players(&self) -> Vec<Player> {
+        self.response.players()
+    }
+
+    /// Returns the provenance of the server information, as returned by
+    /// `Response::provenance`.
+    pub fn provenance(&self) -> Provenance {
+        self.response.provenance()
+    }
+
+    /// Returns the data of the response as named values, as returned by `Response::fields`.
+    pub fn fields(&self) -> BTreeMap<String, Value> {
+        self.response.fields()
+    }
+
+    /// Returns the validator of the response, as returned by `Response::etag`.
+    pub fn etag(&self) -> ETag {
+        self.response.etag()
+    }
+
+    /// Returns the name of the type of the game-specific response, for diagnostics.
+    pub fn type_name(&self) -> &'static str {
+        self.response.type_name()
+    }
+
+    /// Returns `true` if the game-specific response is an `R`.
+    pub fn is<R: Response + 'static>(&self) -> bool {
+        self.response.as_any().is::<R>()
+    }
+
+    /// Returns the game-specific response, if it is an `R`.
+    pub fn downcast_ref<R: Response + 'static>(&self) -> Option<&R> {
+        self.response.as_any().downcast_ref()
+    }
+
+    /// Unwraps the game-specific response if it is an `R`, or returns `self` otherwise.
+    pub fn downcast<R: Response + 'static>(self) -> Result<R, Self> {
+        if !self.is::<R>() {
+            return Err(self);
+        }
+
+        let response = self
+            .response
+            .into_any()
+            .downcast()
+            .expect("response type checked");
+
+        Ok(*response)
+    }
+}
+
+impl HeapSize for AnyResponse {
+    fn heap_size(&self) -> usize {
+        size_of_val(&*self.response) + self.response.heap_size()
+    }
+}
+
+impl Debug for AnyResponse {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.debug_struct("AnyResponse")
+            .field("type", &self.type_name())
+            .finish_non_exhaustive()
+    }
+}
+
+/// An object-safe game returning type-erased responses.
+///
+/// This is what `Box<dyn AnyGame>` callers use to query games chosen at runtime, e.g. from
+/// a configuration file, while keeping access to the game-specific response. `DynGame`
+/// serves the same purpose for callers that only need the common model. Wrap a game in
+/// `ErasedGame` to use it as a `Box<dyn AnyGame>`.
+pub trait AnyGame: Send + Sync {
+    /// Returns the name of the game.
+    fn name(&self) -> &str;
+
+    /// Returns the year the game was released.
+    fn release_year(&self) -> u32;
+
+    /// Returns the address to query for a server on `host`.
+    ///
+    /// # Parameters
+    ///
+    /// * `host`: The address of the host.
+    /// * `game_port`: The port players connect to, or `None` for the default port.
+    fn query_address(&self, host: IpAddr, game_port: Option<u16>) -> Option<SocketAddr>;
+
+    /// Returns whether the game requests players and rules by default, as configured by
+    /// `Game::query_options`.
+    fn query_options(&self) -> QueryOptions;
+
+    /// Fetches data from the game server with the game's query.
+    ///
+    /// # Parameters
+    ///
+    /// * `options`: Whether to request players and rules. These replace the values
+    ///   returned by `Game::query_options`; the game-specific options are kept.
+    /// * `address`: The address of the server.
+    /// * `timeouts`: The time limits for each step and for the whole exchange.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing either the response and any warnings, or an `Error`.
+    fn fetch_with(
+        &self,
+        options: QueryOptions,
+        address: SocketAddr,
+        timeouts: TimeoutSettings,
+    ) -> BoxFuture<'_, FetchResult<AnyResponse, BoxError>>;
+
+    /// Fetches data from the game server with the game's default query and without any
+    /// time limits.
+    ///
+    /// # Parameters
+    ///
+    /// * `address`: The address of the server.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing either the response and any warnings, or an `Error`.
+    fn fetch(&self, address: SocketAddr) -> BoxFuture<'_, FetchResult<AnyResponse, BoxError>> {
+        self.fetch_with(self.query_options(), address, TimeoutSettings::default())
+    }
+}
+
+impl<G, P, R, E> AnyGame for ErasedGame<G, P>
+where
+    G: for<'a> Game<'a, P> + Send + Sync,
+    P: for<'a> Protocol<'a, R = R, E = E>,
+    R: Response + 'static,
+    E: StdError + Send + Sync + 'static,
+{
+    fn name(&self) -> &str {
+        self.game().game_name()
+    }
+
+    fn release_year(&self) -> u32 {
+        G::RELEASE_YEAR
+    }
+
+    fn query_address(&self, host: IpAddr, game_port: Option<u16>) -> Option<SocketAddr> {
+        self.game().query_address(host, game_port)
+    }
+
+    fn query_options(&self) -> QueryOptions {
+        let options = self.game().query_options();
+
+        QueryOptions {
+            players: options.players,
+            rules: options.rules,
+            extra: (),
+        }
+    }
+
+    fn fetch_with(
+        &self,
+        options: QueryOptions,
+        address: SocketAddr,
+        timeouts: TimeoutSettings,
+    ) -> BoxFuture<'_, FetchResult<AnyResponse, BoxError>> {
+        let query = self
+            .game()
+            .query_builder()
+            .players(options.players)
+            .rules(options.rules);
+
+        Box::pin(async move {
+            let fetched = self
+                .game()
+                .fetch_with(query, address, timeouts)
+                .await
+                .map_err(erase)?;
+
+            Ok(Fetched {
+                response: AnyResponse::new(fetched.response),
+                meta: fetched.meta,
+                warnings: fetched.warnings.into_iter().map(erase).collect(),
+            })
+        })
+    }
+}
+
+/// An object-safe protocol with its query, response and error types erased.
+///
+/// `DynProtocol` keeps these types as parameters, so only protocols sharing them can be
+/// stored together. `AnyProtocol` builds queries from `QueryOptions` and returns
+/// `AnyResponse`s instead, so any protocol can be driven step by step through a
+/// `Box<dyn AnyProtocol>`. Wrap a protocol in `ErasedProtocol` to use it this way.
+pub trait AnyProtocol: Send + Sync {
+    /// Returns the retry policy of the protocol.
+    fn retry_policy(&self) -> RetryPolicy;
+
+    /// Connects to the server.
+    ///
+    /// # Parameters
+    ///
+    /// * `address`: The address of the server.
+    /// * `timeouts`: The time limits to respect; `TimeoutSettings::connect` applies here.
+    fn connect<'s>(
+        &'s self,
+        address: SocketAddr,
+        timeouts: &'s TimeoutSettings,
+    ) -> BoxFuture<'s, Result<(), Error<BoxError>>>;
+
+    /// Performs the protocol's handshake, if any.
+    ///
+    /// # Parameters
+    ///
+    /// * `address`: The address of the server.
+    /// * `timeouts`: The time limits to respect.
+    fn handshake<'s>(
+        &'s self,
+        address: SocketAddr,
+        timeouts: &'s TimeoutSettings,
+    ) -> BoxFuture<'s, Result<(), Error<BoxError>>>;
+
+    /// Sends the protocol's query built from `options` and receives the response.
+    ///
+    /// # Parameters
+    ///
+    /// * `options`: Whether to request players and rules. The game-specific options are
+    ///   left at their defaults.
+    /// * `timeouts`: The time limits to respect; `TimeoutSettings::write` applies to
+    ///   sending and `TimeoutSettings::read` to receiving.
+    fn query<'s>(
+        &'s self,
+        options: QueryOptions,
+        timeouts: &'s TimeoutSettings,
+    ) -> BoxFuture<'s, Result<AnyResponse, Error<BoxError>>>;
+
+    /// Disconnects from the connected server.
+    fn disconnect(&self) -> BoxFuture<'_, Result<(), Error<BoxError>>>;
+
+    /// Schedules a disconnect, for callers that can't await `disconnect`.
+    fn schedule_disconnect(&self);
+}
+
+/// `ErasedProtocol` implements `AnyProtocol` for a `Protocol`.
+pub struct ErasedProtocol<P> {
+    protocol: P,
+}
+
+impl<P> ErasedProtocol<P> {
+    /// Wraps `protocol`.
+    ///
+    /// # Parameters
+    ///
+    /// * `protocol`: The protocol to erase.
+    pub fn new(protocol: P) -> Self {
+        ErasedProtocol { protocol }
+    }
+
+    /// Returns the wrapped protocol.
+    pub fn protocol(&self) -> &P {
+        &self.protocol
+    }
+
+    /// Unwraps the protocol.
+    pub fn into_inner(self) -> P {
+        self.protocol
+    }
+}
+
+impl<P, Q, R, E> AnyProtocol for ErasedProtocol<P>
+where
+    P: for<'a> Protocol<'a, Q = Q, R = R, E = E>,
+    Q: Query + 'static,
+    R: Response + 'static,
+    E: StdError + Send + Sync + 'static,
+{
+    fn retry_policy(&self) -> RetryPolicy {
+        self.protocol.retry_policy()
+    }
+
+    fn connect<'s>(
+        &'s self,
+        address: SocketAddr,
+        timeouts: &'s TimeoutSettings,
+    ) -> BoxFuture<'s, Result<(), Error<BoxError>>> {
+        Box::pin(async move {
+            self.protocol
+                ._connect(address, timeouts)
+                .await
+                .map_err(erase)
+        })
+    }
+
+    fn handshake<'s>(
+        &'s self,
+        address: SocketAddr,
+        timeouts: &'s TimeoutSettings,
+    ) -> BoxFuture<'s, Result<(), Error<BoxError>>> {
+        Box::pin(async move {
+            self.protocol
+                .handshake(address, timeouts)
+                .await
+                .map_err(erase)
+        })
+    }
+
+    fn query<'s>(
+        &'s self,
+        options: QueryOptions,
+        timeouts: &'s TimeoutSettings,
+    ) -> BoxFuture<'s, Result<AnyResponse, Error<BoxError>>> {
+        let query = Q::from_options(QueryOptions {
+            players: options.players,
+            rules: options.rules,
+            extra: Default::default(),
+        });
+
+        Box::pin(async move {
+            self.protocol
+                .send_query(query, timeouts)
+                .await
+                .map_err(erase)?;
+
+            let response = self
+                .protocol
+                .receive_response(timeouts)
+                .await
+                .map_err(erase)?;
+
+            Ok(AnyResponse::new(response))
+        })
+    }
+
+    fn disconnect(&self) -> BoxFuture<'_, Result<(), Error<BoxError>>> {
+        Box::pin(async move { self.protocol.disconnect().await.map_err(erase) })
+    }
+
+    fn schedule_disconnect(&self) {
+        self.protocol.schedule_disconnect();
+    }
+}
@@ -0,0 +1,29 @@
+use crate::prelude::{Error, Protocol, TimeoutSettings};
+
+use std::future::Future;
+
+/// A trait for protocols that require a connection to be authenticated before queries are
+/// accepted, such as RCON or TeamSpeak's ServerQuery.
+///
+/// Authentication is performed through `Link::authenticate`, which only becomes available
+/// once the link is connected and only returns an authenticated link on success.
+pub trait Authenticate<'a>: Protocol<'a> {
+    /// The credentials required to authenticate, e.g. a password.
+    type Credentials: Send + Sync;
+
+    /// Authenticate the established connection.
+    ///
+    /// # Parameters
+    ///
+    /// * `credentials`: The credentials to present to the server.
+    /// * `timeouts`: The time limits to respect.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` which is `Ok` if the server accepted the credentials, or an `Error`.
+    fn authenticate(
+        &self,
+        credentials: &Self::Credentials,
+        timeouts: &TimeoutSettings,
+    ) -> impl Future<Output = Result<(), Error<Self::E>>> + Send;
+}
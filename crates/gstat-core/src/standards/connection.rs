@@ -0,0 +1,76 @@
+use crate::prelude::{Error, Protocol};
+
+use std::ops::Deref;
+
+/// A `ConnectionGuard` represents an established connection of a `Protocol`.
+///
+/// It is returned by `Protocol::connect` and dereferences to the protocol, so queries can be
+/// sent through it. Dropping the guard without calling `disconnect` (for example because an
+/// early return or `?` skipped it) schedules a best-effort disconnect through
+/// `Protocol::schedule_disconnect`, so connections are never leaked.
+pub struct ConnectionGuard<'p, P> {
+    /// The connected protocol.
+    protocol: &'p P,
+    /// The hook invoked when the guard is dropped while still armed.
+    on_drop: Option<fn(&P)>,
+}
+
+impl<'p, P> ConnectionGuard<'p, P> {
+    /// Creates a new `ConnectionGuard` instance.
+    ///
+    /// # Parameters
+    ///
+    /// * `protocol`: The connected protocol.
+    /// * `on_drop`: The hook to invoke if the guard is dropped before disconnecting.
+    pub fn new(protocol: &'p P, on_drop: fn(&P)) -> Self {
+        ConnectionGuard {
+            protocol,
+            on_drop: Some(on_drop),
+        }
+    }
+
+    /// Returns the connected protocol.
+    pub fn protocol(&self) -> &'p P {
+        self.protocol
+    }
+
+    /// Releases the guard without disconnecting, leaving the connection open.
+    ///
+    /// # Returns
+    ///
+    /// The protocol, which is now responsible for disconnecting itself.
+    pub fn release(mut self) -> &'p P {
+        self.on_drop = None;
+        self.protocol
+    }
+
+    /// Disconnects the protocol and consumes the guard.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` which is `Ok` if the protocol disconnected cleanly, or an `Error`.
+    pub async fn disconnect<'a>(mut self) -> Result<(), Error<P::E>>
+    where
+        P: Protocol<'a>,
+    {
+        self.on_drop = None;
+        self.protocol.disconnect().await
+    }
+}
+
+impl<P> Deref for ConnectionGuard<'_, P> {
+    type Target = P;
+
+    fn deref(&self) -> &Self::Target {
+        self.protocol
+    }
+}
+
+impl<P> Drop for ConnectionGuard<'_, P> {
+    /// Schedules a disconnect if the guard was not explicitly disconnected or released.
+    fn drop(&mut self) {
+        if let Some(on_drop) = self.on_drop.take() {
+            on_drop(self.protocol);
+        }
+    }
+}
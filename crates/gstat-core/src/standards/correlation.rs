@@ -0,0 +1,269 @@
+use crate::prelude::{Error, RawTransport, TimeoutSettings};
+
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt::{Display, Formatter, Result as FmtResult},
+    future::Future,
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        Arc, Mutex,
+    },
+};
+
+use bytes::Bytes;
+
+/// The default number of packets a `Correlator` holds for requests not yet waiting on them.
+const DEFAULT_MAX_STASHED: usize = 64;
+
+/// Identifies which request a response belongs to.
+///
+/// Protocols carry these under different names: the request ID of Source RCON, the
+/// sequence number of the Battlefield protocol or the session ID of GameSpy 4 queries.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct CorrelationId(u32);
+
+impl CorrelationId {
+    /// Creates an identifier from the value carried on the wire.
+    pub fn from_u32(value: u32) -> Self {
+        CorrelationId(value)
+    }
+
+    /// Returns the value carried on the wire.
+    pub fn as_u32(&self) -> u32 {
+        self.0
+    }
+}
+
+impl Display for CorrelationId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(f, "#{}", self.0)
+    }
+}
+
+/// `CorrelationIds` hands out identifiers for outgoing requests.
+///
+/// Identifiers count up from 1 and wrap within a mask, skipping 0, which several protocols
+/// reserve (Source RCON answers failed authentication with -1, for instance). Clones share
+/// their counter, so every connection of a multiplexed socket can draw from one sequence.
+#[derive(Clone, Debug)]
+pub struct CorrelationIds {
+    next: Arc<AtomicU32>,
+    mask: u32,
+}
+
+impl CorrelationIds {
+    /// Creates a sequence using the full 32-bit range.
+    pub fn new() -> Self {
+        CorrelationIds {
+            next: Arc::new(AtomicU32::new(1)),
+            mask: u32::MAX,
+        }
+    }
+
+    /// Restricts identifiers to the bits in `mask`, e.g. `0x7fff_ffff` for protocols that
+    /// carry them as positive `i32`s.
+    ///
+    /// # Parameters
+    ///
+    /// * `mask`: The bits identifiers may use.
+    pub fn with_mask(mut self, mask: u32) -> Self {
+        self.mask = mask;
+        self
+    }
+
+    /// Returns the next identifier.
+    pub fn next_id(&self) -> CorrelationId {
+        loop {
+            let value = self.next.fetch_add(1, Ordering::Relaxed) & self.mask;
+
+            if value != 0 {
+                return CorrelationId(value);
+            }
+        }
+    }
+}
+
+impl Default for CorrelationIds {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// `Correlator` holds responses that arrived for requests other than the one being read.
+///
+/// When several requests are in flight on one connection, a reader waiting for its own
+/// response may receive another request's first. It stashes that packet here, where the
+/// other request's reader picks it up. The correlator is cheap to clone, and clones share
+/// their packets. At most a fixed number of packets are held; beyond that the oldest is
+/// dropped, so responses nobody waits for can't accumulate.
+#[derive(Clone, Debug)]
+pub struct Correlator {
+    max_stashed: usize,
+    stash: Arc<Mutex<Stash>>,
+}
+
+/// The packets held by a `Correlator`, with their arrival order for eviction.
+#[derive(Debug, Default)]
+struct Stash {
+    packets: HashMap<CorrelationId, VecDeque<Bytes>>,
+    order: VecDeque<CorrelationId>,
+}
+
+impl Correlator {
+    /// Creates an empty correlator holding at most 64 packets.
+    pub fn new() -> Self {
+        Correlator {
+            max_stashed: DEFAULT_MAX_STASHED,
+            stash: Arc::new(Mutex::new(Stash::default())),
+        }
+    }
+
+    /// Sets how many packets are held at most.
+    ///
+    /// # Parameters
+    ///
+    /// * `max_stashed`: The largest number of packets held.
+    pub fn with_max_stashed(mut self, max_stashed: usize) -> Self {
+        self.max_stashed = max_stashed.max(1);
+        self
+    }
+
+    /// Holds a packet for the request `id`.
+    ///
+    /// # Parameters
+    ///
+    /// * `id`: The request the packet answers.
+    /// * `packet`: The packet.
+    pub fn stash(&self, id: CorrelationId, packet: Bytes) {
+        let mut stash = self.stash.lock().expect("correlator lock poisoned");
+
+        if stash.order.len() >= self.max_stashed {
+            if let Some(oldest) = stash.order.pop_front() {
+                if let Some(packets) = stash.packets.get_mut(&oldest) {
+                    packets.pop_front();
+
+                    if packets.is_empty() {
+                        stash.packets.remove(&oldest);
+                    }
+                }
+            }
+        }
+
+        stash.packets.entry(id).or_default().push_back(packet);
+        stash.order.push_back(id);
+    }
+
+    /// Takes the oldest packet held for the request `id`.
+    pub fn take(&self, id: CorrelationId) -> Option<Bytes> {
+        let mut stash = self.stash.lock().expect("correlator lock poisoned");
+
+        let packets = stash.packets.get_mut(&id)?;
+        let packet = packets.pop_front();
+
+        if packets.is_empty() {
+            stash.packets.remove(&id);
+        }
+
+        if let Some(position) = stash.order.iter().position(|held| *held == id) {
+            stash.order.remove(position);
+        }
+
+        packet
+    }
+
+    /// Returns the number of packets held.
+    pub fn len(&self) -> usize {
+        self.stash
+            .lock()
+            .expect("correlator lock poisoned")
+            .order
+            .len()
+    }
+
+    /// Returns `true` if no packets are held.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Drops every held packet.
+    pub fn clear(&self) {
+        let mut stash = self.stash.lock().expect("correlator lock poisoned");
+
+        stash.packets.clear();
+        stash.order.clear();
+    }
+}
+
+impl Default for Correlator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A trait for protocols whose packets carry a correlation ID.
+///
+/// Implementors describe where the ID sits in requests and responses, which lets
+/// `receive_correlated` and multiplexing layers match responses to requests even when
+/// they arrive out of order.
+pub trait Correlated<'a>: RawTransport<'a> {
+    /// Reads the correlation ID of a response.
+    ///
+    /// # Parameters
+    ///
+    /// * `packet`: The response packet.
+    ///
+    /// # Returns
+    ///
+    /// The ID, or `None` if the packet carries none, e.g. because it is malformed.
+    fn response_id(&self, packet: &[u8]) -> Option<CorrelationId>;
+
+    /// Reads the correlation ID of an outgoing request.
+    ///
+    /// The default implementation assumes requests carry the ID where responses do.
+    ///
+    /// # Parameters
+    ///
+    /// * `payload`: The request payload.
+    fn request_id(&self, payload: &[u8]) -> Option<CorrelationId> {
+        self.response_id(payload)
+    }
+
+    /// Receives the next response to the request `id`.
+    ///
+    /// Packets held in `correlator` are returned first. Packets received for other
+    /// requests are stashed in `correlator`, and packets without an ID are discarded.
+    ///
+    /// # Parameters
+    ///
+    /// * `id`: The request to receive a response for.
+    /// * `correlator`: Holds responses to other requests.
+    /// * `timeouts`: The time limits to respect for each packet.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing either the response or an `Error`.
+    fn receive_correlated(
+        &self,
+        id: CorrelationId,
+        correlator: &Correlator,
+        timeouts: &TimeoutSettings,
+    ) -> impl Future<Output = Result<Bytes, Error<Self::E>>> + Send {
+        async move {
+            loop {
+                if let Some(packet) = correlator.take(id) {
+                    return Ok(packet);
+                }
+
+                let packet = self.receive(timeouts).await?;
+
+                match self.response_id(&packet) {
+                    Some(found) if found == id => return Ok(packet),
+                    Some(found) => correlator.stash(found, packet),
+                    None => {
+                        trace_event!(debug, bytes = packet.len(), "discarded uncorrelated packet");
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,90 @@
+//! `define_game!`, for games that only differ in their metadata and query options.
+//!
+//! Most games built on a shared protocol, such as the Source engine titles speaking A2S,
+//! are fully described by their name, release year, protocol, ports and a few protocol
+//! options. `define_game!` expands such a description into a unit struct and its `Game`
+//! implementation.
+
+/// Defines a unit struct implementing `Game` from a short description.
+///
+/// The entries must appear in the order below; the optional ones may be left out.
+///
+/// * `name`: The name of the game, for `Game::GAME_NAME`.
+/// * `release_year`: The release year, for `Game::RELEASE_YEAR`.
+/// * `protocol`: An expression building the protocol, for `Game::_protocol`. The type of
+///   the protocol follows the struct name.
+/// * `registry` (optional): The identifier of the game in the built-in registry, for
+///   `Game::registry_entry`, from which the default and query ports are derived.
+/// * `default_port` (optional): The default game port, for games without a registry
+///   entry or to override it.
+/// * `players`, `rules` (optional): Whether queries request players and rules by default.
+/// * `quirks` (optional): The game-specific options of the protocol's query, e.g. the
+///   AppID a shared protocol filters on or the protocol variant the game speaks.
+///
+/// ```ignore
+/// define_game! {
+///     /// Rust, queried over A2S on the game port plus two.
+///     pub struct Rust: UdpProtocol<A2sQuery, A2sResponse, A2sParser> {
+///         name: "Rust",
+///         release_year: 2018,
+///         protocol: UdpProtocol::new(A2sParser),
+///         registry: "rust",
+///         players: true,
+///         quirks: A2sOptions { app_id: Some(252490), ..A2sOptions::default() },
+///     }
+/// }
+/// ```
+#[macro_export]
+macro_rules! define_game {
+    (
+        $(#[$meta:meta])*
+        $vis:vis struct $game:ident: $protocol:ty {
+            name: $name:expr,
+            release_year: $release_year:expr,
+            protocol: $build:expr,
+            $(registry: $registry:expr,)?
+            $(default_port: $default_port:expr,)?
+            $(players: $players:expr,)?
+            $(rules: $rules:expr,)?
+            $(quirks: $quirks:expr,)?
+        }
+    ) => {
+        $(#[$meta])*
+        #[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+        $vis struct $game;
+
+        impl<'a> $crate::prelude::Game<'a, $protocol> for $game {
+            const GAME_NAME: &'static str = $name;
+            const RELEASE_YEAR: u32 = $release_year;
+
+            fn _protocol(&self) -> $protocol {
+                $build
+            }
+
+            $(
+                fn registry_entry(&self) -> ::core::option::Option<&'static $crate::registry::GameEntry> {
+                    $crate::registry::game($registry)
+                }
+            )?
+
+            $(
+                fn default_port(&self) -> ::core::option::Option<u16> {
+                    ::core::option::Option::Some($default_port)
+                }
+            )?
+
+            fn query_options(
+                &self,
+            ) -> $crate::prelude::QueryOptions<
+                <<$protocol as $crate::prelude::Protocol<'a>>::Q as $crate::prelude::Query>::Options,
+            > {
+                #[allow(unused_mut)]
+                let mut options = $crate::prelude::QueryOptions::default();
+                $(options.players = $players;)?
+                $(options.rules = $rules;)?
+                $(options.extra = $quirks;)?
+                options
+            }
+        }
+    };
+}
@@ -0,0 +1,172 @@
+use crate::{
+    memory::HeapSize,
+    prelude::{
+        Error, Game, Player, Protocol, QueryOptions, Response, ResponseMeta, ServerInfo,
+        TimeoutSettings,
+    },
+    standards::{dyn_protocol::BoxFuture, game::Fetched},
+};
+
+use std::{
+    error::Error as StdError,
+    marker::PhantomData,
+    net::{IpAddr, SocketAddr},
+};
+
+/// The error data of type-erased games.
+pub type BoxError = Box<dyn StdError + Send + Sync>;
+
+/// `DynFetched` is the outcome of a successful `DynGame::dyn_fetch`.
+///
+/// The game-specific response is converted into the common model, since its type is not
+/// known to the caller.
+#[derive(Debug)]
+pub struct DynFetched {
+    /// The server information, as returned by `Response::to_common`.
+    pub info: ServerInfo,
+    /// The players, as returned by `Response::players`.
+    pub players: Vec<Player>,
+    /// How the response was received.
+    pub meta: ResponseMeta,
+    /// Errors that occurred after the response was received.
+    pub warnings: Vec<Error<BoxError>>,
+}
+
+impl HeapSize for DynFetched {
+    fn heap_size(&self) -> usize {
+        self.info.heap_size()
+            + self.players.heap_size()
+            + self.meta.heap_size()
+            + self.warnings.capacity() * std::mem::size_of::<Error<BoxError>>()
+    }
+}
+
+impl<R, E> From<Fetched<R, E>> for DynFetched
+where
+    R: Response,
+    E: StdError + Send + Sync + 'static,
+{
+    /// Converts the response of any game into the common model, e.g. so callers that query
+    /// games of several types handle their responses alike.
+    fn from(fetched: Fetched<R, E>) -> Self {
+        DynFetched {
+            info: fetched.response.to_common(),
+            players: fetched.response.players(),
+            meta: fetched.meta,
+            warnings: fetched.warnings.into_iter().map(erase).collect(),
+        }
+    }
+}
+
+/// An object-safe counterpart of `Game`.
+///
+/// `Game` is generic over its protocol and returns unboxed futures, so games of different
+/// types can't be stored together. Wrap a game in `ErasedGame` to use it as a
+/// `Box<dyn DynGame>`, e.g. in a `GameRegistry`.
+pub trait DynGame: Send + Sync {
+    /// Returns the name of the game.
+    fn dyn_name(&self) -> &str;
+
+    /// Returns the year the game was released.
+    fn dyn_release_year(&self) -> u32;
+
+    /// Returns the address to query for a server on `host`.
+    ///
+    /// # Parameters
+    ///
+    /// * `host`: The address of the host.
+    /// * `game_port`: The port players connect to, or `None` for the default port.
+    fn dyn_query_address(&self, host: IpAddr, game_port: Option<u16>) -> Option<SocketAddr>;
+
+    /// Fetches data from the game server with the game's query.
+    ///
+    /// # Parameters
+    ///
+    /// * `options`: Whether to request players and rules. These replace the values
+    ///   returned by `Game::query_options`; the game-specific options are kept.
+    /// * `address`: The address of the server.
+    /// * `timeouts`: The time limits for each step and for the whole exchange.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing either the converted response and any warnings, or an `Error`.
+    fn dyn_fetch(
+        &self,
+        options: QueryOptions,
+        address: SocketAddr,
+        timeouts: TimeoutSettings,
+    ) -> BoxFuture<'_, Result<DynFetched, Error<BoxError>>>;
+}
+
+/// `ErasedGame` implements `DynGame` and `AnyGame` for a `Game` using the protocol `P`.
+pub struct ErasedGame<G, P> {
+    game: G,
+    _marker: PhantomData<fn() -> P>,
+}
+
+impl<G, P> ErasedGame<G, P> {
+    /// Wraps `game`.
+    ///
+    /// # Parameters
+    ///
+    /// * `game`: The game to erase.
+    pub fn new(game: G) -> Self {
+        ErasedGame {
+            game,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Returns the wrapped game.
+    pub fn game(&self) -> &G {
+        &self.game
+    }
+}
+
+impl<G, P, R, E> DynGame for ErasedGame<G, P>
+where
+    G: for<'a> Game<'a, P> + Send + Sync,
+    P: for<'a> Protocol<'a, R = R, E = E>,
+    R: Response,
+    E: StdError + Send + Sync + 'static,
+{
+    fn dyn_name(&self) -> &str {
+        self.game.game_name()
+    }
+
+    fn dyn_release_year(&self) -> u32 {
+        G::RELEASE_YEAR
+    }
+
+    fn dyn_query_address(&self, host: IpAddr, game_port: Option<u16>) -> Option<SocketAddr> {
+        self.game.query_address(host, game_port)
+    }
+
+    fn dyn_fetch(
+        &self,
+        options: QueryOptions,
+        address: SocketAddr,
+        timeouts: TimeoutSettings,
+    ) -> BoxFuture<'_, Result<DynFetched, Error<BoxError>>> {
+        let query = self
+            .game
+            .query_builder()
+            .players(options.players)
+            .rules(options.rules);
+
+        Box::pin(async move {
+            let fetched = self
+                .game
+                .fetch_with(query, address, timeouts)
+                .await
+                .map_err(erase)?;
+
+            Ok(DynFetched::from(fetched))
+        })
+    }
+}
+
+/// Boxes the error data of `err`.
+pub(crate) fn erase<E: StdError + Send + Sync + 'static>(err: Error<E>) -> Error<BoxError> {
+    err.map(|err| Box::new(err) as BoxError)
+}
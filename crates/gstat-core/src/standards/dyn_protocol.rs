@@ -0,0 +1,212 @@
+use crate::prelude::{Error, Protocol, RawTransport, RetryPolicy, TimeoutSettings};
+
+use std::{future::Future, net::SocketAddr, pin::Pin};
+
+use bytes::Bytes;
+
+/// A boxed, sendable future, as returned by the methods of `DynProtocol`.
+pub type BoxFuture<'f, T> = Pin<Box<dyn Future<Output = T> + Send + 'f>>;
+
+/// An object-safe counterpart of `Protocol`.
+///
+/// `Protocol` returns unboxed futures, which keeps calls allocation-free but means protocols
+/// can't be used as trait objects. `DynProtocol` is implemented for every `Protocol` and
+/// boxes each future instead, so protocols of different types can be stored together, e.g.
+/// as `Box<dyn DynProtocol<'a, Q, R, E>>`. Only use it where dynamic dispatch is needed.
+///
+/// The methods mirror those of `Protocol` with a `dyn_` prefix, so both traits can be in
+/// scope at once.
+///
+/// `'a`, `Q`, `R` and `E` are the lifetime, query, response and error types of the underlying
+/// protocol.
+pub trait DynProtocol<'a, Q, R, E>: Send + Sync {
+    /// Returns the policy used to retry failed exchanges over this protocol.
+    fn dyn_retry_policy(&self) -> RetryPolicy;
+
+    /// Connects to a specific IP address.
+    ///
+    /// Unlike `Protocol::connect`, no `ConnectionGuard` is returned; the caller is
+    /// responsible for calling `dyn_disconnect` or `dyn_schedule_disconnect`.
+    ///
+    /// # Parameters
+    ///
+    /// * `address`: The target IP address for connection establishment.
+    /// * `timeouts`: The time limits to respect; `TimeoutSettings::connect` applies here.
+    fn dyn_connect<'s>(
+        &'s self,
+        address: SocketAddr,
+        timeouts: &'s TimeoutSettings,
+    ) -> BoxFuture<'s, Result<(), Error<E>>>
+    where
+        'a: 's;
+
+    /// Schedules a best-effort disconnect without waiting for it.
+    fn dyn_schedule_disconnect(&self);
+
+    /// Performs the handshake the server requires before it answers queries.
+    ///
+    /// # Parameters
+    ///
+    /// * `address`: The address of the connected server.
+    /// * `timeouts`: The time limits to respect for each packet of the handshake.
+    fn dyn_handshake<'s>(
+        &'s self,
+        address: SocketAddr,
+        timeouts: &'s TimeoutSettings,
+    ) -> BoxFuture<'s, Result<(), Error<E>>>
+    where
+        'a: 's;
+
+    /// Sends a query to the connected server.
+    ///
+    /// # Parameters
+    ///
+    /// * `query`: The query object to be sent.
+    /// * `timeouts`: The time limits to respect; `TimeoutSettings::write` applies here.
+    fn dyn_send_query<'s>(
+        &'s self,
+        query: Q,
+        timeouts: &'s TimeoutSettings,
+    ) -> BoxFuture<'s, Result<(), Error<E>>>
+    where
+        'a: 's;
+
+    /// Receives a response from the connected server.
+    ///
+    /// # Parameters
+    ///
+    /// * `timeouts`: The time limits to respect; `TimeoutSettings::read` applies here.
+    fn dyn_receive_response<'s>(
+        &'s self,
+        timeouts: &'s TimeoutSettings,
+    ) -> BoxFuture<'s, Result<R, Error<E>>>
+    where
+        'a: 's;
+
+    /// Disconnects from the connected server.
+    fn dyn_disconnect<'s>(&'s self) -> BoxFuture<'s, Result<(), Error<E>>>
+    where
+        'a: 's;
+}
+
+impl<'a, P> DynProtocol<'a, P::Q, P::R, P::E> for P
+where
+    P: Protocol<'a>,
+{
+    fn dyn_retry_policy(&self) -> RetryPolicy {
+        Protocol::retry_policy(self)
+    }
+
+    fn dyn_connect<'s>(
+        &'s self,
+        address: SocketAddr,
+        timeouts: &'s TimeoutSettings,
+    ) -> BoxFuture<'s, Result<(), Error<P::E>>>
+    where
+        'a: 's,
+    {
+        Box::pin(Protocol::_connect(self, address, timeouts))
+    }
+
+    fn dyn_schedule_disconnect(&self) {
+        Protocol::schedule_disconnect(self)
+    }
+
+    fn dyn_handshake<'s>(
+        &'s self,
+        address: SocketAddr,
+        timeouts: &'s TimeoutSettings,
+    ) -> BoxFuture<'s, Result<(), Error<P::E>>>
+    where
+        'a: 's,
+    {
+        Box::pin(Protocol::handshake(self, address, timeouts))
+    }
+
+    fn dyn_send_query<'s>(
+        &'s self,
+        query: P::Q,
+        timeouts: &'s TimeoutSettings,
+    ) -> BoxFuture<'s, Result<(), Error<P::E>>>
+    where
+        'a: 's,
+    {
+        Box::pin(Protocol::send_query(self, query, timeouts))
+    }
+
+    fn dyn_receive_response<'s>(
+        &'s self,
+        timeouts: &'s TimeoutSettings,
+    ) -> BoxFuture<'s, Result<P::R, Error<P::E>>>
+    where
+        'a: 's,
+    {
+        Box::pin(Protocol::receive_response(self, timeouts))
+    }
+
+    fn dyn_disconnect<'s>(&'s self) -> BoxFuture<'s, Result<(), Error<P::E>>>
+    where
+        'a: 's,
+    {
+        Box::pin(Protocol::disconnect(self))
+    }
+}
+
+/// An object-safe counterpart of `RawTransport`.
+///
+/// This is implemented for every `RawTransport`, so protocols exposing their raw packets can
+/// be used as `Box<dyn DynRawTransport<'a, Q, R, E>>` with both the `DynProtocol` methods
+/// and raw packet access.
+pub trait DynRawTransport<'a, Q, R, E>: DynProtocol<'a, Q, R, E> {
+    /// Sends raw data, bypassing the parser.
+    ///
+    /// # Parameters
+    ///
+    /// * `data`: The raw data to be sent across the network.
+    /// * `timeouts`: The time limits to respect; `TimeoutSettings::write` applies here.
+    fn dyn_send<'s>(
+        &'s self,
+        data: &'s [u8],
+        timeouts: &'s TimeoutSettings,
+    ) -> BoxFuture<'s, Result<(), Error<E>>>
+    where
+        'a: 's;
+
+    /// Receives raw data, bypassing the parser.
+    ///
+    /// # Parameters
+    ///
+    /// * `timeouts`: The time limits to respect; `TimeoutSettings::read` applies here.
+    fn dyn_receive<'s>(
+        &'s self,
+        timeouts: &'s TimeoutSettings,
+    ) -> BoxFuture<'s, Result<Bytes, Error<E>>>
+    where
+        'a: 's;
+}
+
+impl<'a, P> DynRawTransport<'a, P::Q, P::R, P::E> for P
+where
+    P: RawTransport<'a>,
+{
+    fn dyn_send<'s>(
+        &'s self,
+        data: &'s [u8],
+        timeouts: &'s TimeoutSettings,
+    ) -> BoxFuture<'s, Result<(), Error<P::E>>>
+    where
+        'a: 's,
+    {
+        Box::pin(RawTransport::send(self, data, timeouts))
+    }
+
+    fn dyn_receive<'s>(
+        &'s self,
+        timeouts: &'s TimeoutSettings,
+    ) -> BoxFuture<'s, Result<Bytes, Error<P::E>>>
+    where
+        'a: 's,
+    {
+        Box::pin(RawTransport::receive(self, timeouts))
+    }
+}
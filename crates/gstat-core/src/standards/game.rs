@@ -1,15 +1,75 @@
-use crate::prelude::{Error, Protocol};
+use crate::{
+    address::interleave_families,
+    blocklist::Blocklist,
+    cancel::{with_cancellation, CancellationToken},
+    events::ConnectionEvent,
+    prelude::{Error, ErrorDetail, ErrorKind, Protocol, Query, QueryOptions, TimeoutSettings},
+    registry::GameEntry,
+    resolve::{self, HostPort, Resolved, Resolver},
+    runtime,
+    standards::{
+        query::{IntoQuery, QueryBuilder},
+        response::ResponseMeta,
+        session::Session,
+    },
+    timeout::with_timeout,
+};
 
-use std::net::SocketAddr;
+use std::{
+    future::Future,
+    net::{IpAddr, SocketAddr},
+};
 
-use async_trait::async_trait;
+/// `Fetched` is the outcome of a successful `Game::fetch`.
+///
+/// Once a response has been received, later failures (such as an unclean disconnect) no
+/// longer invalidate it. They are collected as non-fatal warnings instead.
+///
+/// `R` is the type of the response and `E` is the type of the protocol error data.
+pub struct Fetched<R, E> {
+    /// The response received from the server.
+    pub response: R,
+    /// How the response was received.
+    pub meta: ResponseMeta,
+    /// Errors that occurred after the response was received.
+    pub warnings: Vec<Error<E>>,
+}
+
+impl<R, E> Fetched<R, E> {
+    /// Creates a new `Fetched` instance without warnings.
+    ///
+    /// # Parameters
+    ///
+    /// * `response`: The response received from the server.
+    /// * `meta`: How the response was received.
+    pub fn new(response: R, meta: ResponseMeta) -> Self {
+        Fetched {
+            response,
+            meta,
+            warnings: Vec::new(),
+        }
+    }
+
+    /// Returns `true` if any non-fatal error occurred.
+    pub fn has_warnings(&self) -> bool {
+        !self.warnings.is_empty()
+    }
+
+    /// Discards the warnings and returns the response.
+    pub fn into_response(self) -> R {
+        self.response
+    }
+}
+
+/// The outcome of `Game::fetch`: the response and any warnings, or the error that
+/// prevented a response from being received.
+pub type FetchResult<R, E> = Result<Fetched<R, E>, Error<E>>;
 
 /// The `Game` trait represents a specific game that can interact with a game server.
 ///
 /// It provides an associated type for the specific `Protocol` to be used for network operations.
 /// It also provides associated constants for the game's name and the year it was released,
 /// and a method to fetch data from a game server.
-#[async_trait]
 pub trait Game<'a, P>
 where
     P: Protocol<'a>,
@@ -26,34 +86,548 @@ where
     /// method without causing lifetime issues or requiring cloning.
     fn _protocol(&self) -> P;
 
+    /// Returns the name the game is reported under in metrics, spans and logs.
+    ///
+    /// Games whose name is only known at runtime, such as scripted ones, override this.
+    /// The default implementation returns `GAME_NAME`.
+    fn game_name(&self) -> &str {
+        Self::GAME_NAME
+    }
+
+    /// Returns the metadata of the game in the built-in registry, if it has an entry.
+    ///
+    /// The port methods below are derived from it, so games listed in `data/games.toml`
+    /// only need to return `registry::game` with their identifier here. The default
+    /// implementation returns `None`.
+    fn registry_entry(&self) -> Option<&'static GameEntry> {
+        None
+    }
+
+    /// Returns the port players connect to by default, if it is known.
+    fn default_port(&self) -> Option<u16> {
+        self.registry_entry().map(|entry| entry.game_port)
+    }
+
+    /// Returns the query port for a server whose players connect to `game_port`.
+    ///
+    /// Many games answer queries on a port offset from the game port or on a fixed port.
+    /// Without a registry entry, the game port is assumed to answer queries.
+    ///
+    /// # Parameters
+    ///
+    /// * `game_port`: The port players connect to.
+    ///
+    /// # Returns
+    ///
+    /// The query port, or `None` if the game's offset moves `game_port` out of the port
+    /// range.
+    fn query_port_for(&self, game_port: u16) -> Option<u16> {
+        self.registry_entry()
+            .map_or(Some(game_port), |entry| entry.query_port_for(game_port))
+    }
+
+    /// Returns the address to query for a server on `host`.
+    ///
+    /// This lets callers pass just an IP address, or the address players join with.
+    ///
+    /// # Parameters
+    ///
+    /// * `host`: The address of the host.
+    /// * `game_port`: The port players connect to, or `None` for the default port.
+    ///
+    /// # Returns
+    ///
+    /// The query address, or `None` if no game port was given and the default is unknown,
+    /// or if the game port has no query port.
+    fn query_address(&self, host: IpAddr, game_port: Option<u16>) -> Option<SocketAddr> {
+        let game_port = game_port.or_else(|| self.default_port())?;
+
+        Some(SocketAddr::new(host, self.query_port_for(game_port)?))
+    }
+
+    /// Returns the options queries for this game start from.
+    ///
+    /// Games override this to pre-configure game-specific options, such as the AppID a
+    /// shared protocol filters on or the protocol variant the game speaks. The default
+    /// implementation returns the default options of the query type.
+    fn query_options(&self) -> QueryOptions<<P::Q as Query>::Options> {
+        QueryOptions::default()
+    }
+
+    /// Returns a builder starting from the options returned by `query_options`.
+    ///
+    /// Use this to adjust the game's query, for example to request the player list, without
+    /// losing the options the game pre-configures.
+    fn query_builder(&self) -> QueryBuilder<P::Q> {
+        QueryBuilder::from_options(self.query_options())
+    }
+
+    /// Returns the query sent by `fetch_default`, built from `query_options`.
+    fn default_query(&self) -> P::Q {
+        P::Q::from_options(self.query_options())
+    }
+
+    /// Opens a `Session` to the game server, keeping one connection for several queries.
+    ///
+    /// # Parameters
+    ///
+    /// * `address`: The address of the server.
+    /// * `timeouts`: The time limits for connecting and for each query.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing either the connected session or an `Error`.
+    fn session(
+        &'a self,
+        address: SocketAddr,
+        timeouts: TimeoutSettings,
+    ) -> impl Future<Output = Result<Session<'a, P>, Error<P::E>>> + Send
+    where
+        Self: Sync,
+    {
+        Session::open(self._protocol(), address, timeouts)
+    }
+
+    /// Fetches data from the game server with the game's default query and without any
+    /// time limits.
+    ///
+    /// This is equivalent to calling `fetch` with `default_query`.
+    ///
+    /// # Parameters
+    ///
+    /// * `address`: The address of the server.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing either the parsed server response and any warnings, or an `Error`.
+    fn fetch_default(
+        &'a self,
+        address: SocketAddr,
+    ) -> impl Future<Output = FetchResult<P::R, P::E>> + Send
+    where
+        Self: Sync,
+    {
+        self.fetch(self.default_query(), address)
+    }
+
+    /// Fetches data from the game server without any time limits.
+    ///
+    /// This is equivalent to calling `fetch_with` using the default `TimeoutSettings`.
+    ///
+    /// # Parameters
+    ///
+    /// * `query`: The query to send to the server, or a `QueryBuilder` for it.
+    /// * `address`: The address of the server.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing either the parsed server response and any warnings, or an `Error`.
+    fn fetch(
+        &'a self,
+        query: impl IntoQuery<P::Q>,
+        address: SocketAddr,
+    ) -> impl Future<Output = FetchResult<P::R, P::E>> + Send
+    where
+        Self: Sync,
+    {
+        self.fetch_with(query, address, TimeoutSettings::default())
+    }
+
     /// Fetches data from the game server.
     ///
     /// This asynchronous method performs several operations. First, it connects to the game
-    /// server using the provided protocol. Then, it sends a query to the server. The specifics
+    /// server using the provided protocol and performs the protocol's handshake, if any.
+    /// Then, it sends a query to the server. The specifics
     /// of this query are determined by the `query` parameter. After sending the query, it
     /// waits for a response from the server.
     ///
+    /// If any step fails, the connection guard schedules a disconnect as it goes out of
+    /// scope, so the connection is released even though `disconnect` is never reached.
+    ///
     /// The method returns the response from the server, parsed into the appropriate type
-    /// determined by the protocol. If any errors occur during these operations, it returns
-    /// an `Error` variant instead.
+    /// determined by the protocol. If any errors occur before the response is received, it
+    /// returns an `Error` variant instead. A failure to disconnect afterwards does not
+    /// discard the response; it is reported in `Fetched::warnings`. The round-trip latency
+    /// and, if the protocol records them, the raw packets are reported in `Fetched::meta`.
+    ///
+    /// Each protocol operation is bounded by the matching limit in `timeouts`, and the
+    /// whole exchange is bounded by `TimeoutSettings::overall`. Failed exchanges are
+    /// retried according to `Protocol::retry_policy`, within the same overall limit.
+    ///
+    /// Queries, responses, parse failures, round-trip times and failed fetches are reported
+    /// to `Protocol::metrics`, if the protocol has a sink. The start of every attempt and
+    /// parse failures are reported to `Protocol::subscribers` as `ConnectionEvent`s.
+    ///
+    /// With the `tracing` feature, the exchange runs in a `fetch` span carrying the game,
+    /// protocol and address, with a child span per attempt and per step. With the `otel`
+    /// feature, it is also reported as an OpenTelemetry client span.
+    ///
+    /// # Parameters
+    ///
+    /// * `query`: The query to send to the server, or a `QueryBuilder` for it.
+    /// * `address`: The address of the server.
+    /// * `timeouts`: The time limits for each step and for the whole exchange.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing either the parsed server response and any warnings, or an `Error`.
+    fn fetch_with(
+        &'a self,
+        query: impl IntoQuery<P::Q>,
+        address: SocketAddr,
+        timeouts: TimeoutSettings,
+    ) -> impl Future<Output = FetchResult<P::R, P::E>> + Send
+    where
+        Self: Sync,
+    {
+        let query = query.into_query();
+
+        async move {
+            let name = self.game_name();
+            let protocol = self._protocol();
+            let policy = protocol.retry_policy();
+
+            let exchange = policy.run(|_| {
+                let (protocol, query, timeouts) = (&protocol, query.clone(), &timeouts);
+
+                async move {
+                    if let Some(subscribers) = protocol.subscribers() {
+                        subscribers.emit(ConnectionEvent::ConnectStarted { peer: address });
+                    }
+
+                    let connection =
+                        instrument!(protocol.connect(address, timeouts), "connect").await?;
+                    instrument!(connection.handshake(address, timeouts), "handshake").await?;
+
+                    if let Some(meter) = protocol.meter() {
+                        meter.start();
+                    }
+
+                    let metrics = protocol.metrics();
+
+                    let sent_at = runtime::try_now();
+                    instrument!(connection.send_query(query, timeouts), "send_query").await?;
+
+                    if let Some(metrics) = metrics {
+                        metrics.query_sent(name, address);
+                    }
+
+                    let response =
+                        instrument!(connection.receive_response(timeouts), "receive_response")
+                            .await
+                            .inspect_err(|err| {
+                                if !matches!(err, Error::ParserError(_)) {
+                                    return;
+                                }
+
+                                if let Some(metrics) = metrics {
+                                    metrics.parse_failure(name, address);
+                                }
+
+                                if let Some(subscribers) = protocol.subscribers() {
+                                    subscribers.emit(ConnectionEvent::ParseFailed {
+                                        peer: address,
+                                        reason: err.to_string(),
+                                    });
+                                }
+                            })?;
+
+                    let rtt = runtime::elapsed(sent_at);
+
+                    if let Some(metrics) = metrics {
+                        metrics.response_ok(name, address);
+                        metrics.rtt(name, address, rtt);
+                    }
+
+                    let mut meta = ResponseMeta::new(rtt).with_address(address);
+
+                    if let Some(meter) = protocol.meter() {
+                        meta = meta.with_packets(meter.take());
+                    }
+
+                    let disconnected = instrument!(connection.disconnect(), "disconnect").await;
+                    let mut fetched = Fetched::new(response, meta);
+
+                    if let Err(err) = disconnected {
+                        trace_event!(debug, error = ?err, "disconnect failed after response");
+                        fetched.warnings.push(err);
+                    }
+
+                    Ok(fetched)
+                }
+            });
+
+            #[cfg(feature = "otel")]
+            let span = crate::otel::FetchSpan::start(name, std::any::type_name::<P>(), address);
+
+            let fetched = instrument!(
+                with_timeout(timeouts.overall, "Fetch timed out", exchange),
+                "fetch",
+                game = name,
+                protocol = std::any::type_name::<P>(),
+                %address,
+            )
+            .await;
+
+            #[cfg(feature = "otel")]
+            span.end(match &fetched {
+                Ok(fetched) => Ok(fetched.meta.latency()),
+                Err(err) => Err((err.kind(), err.to_string())),
+            });
+
+            if let (Some(metrics), Err(err)) = (protocol.metrics(), &fetched) {
+                metrics.query_failed(name, address, err.kind());
+            }
+
+            #[cfg(all(feature = "tracing", not(feature = "silent")))]
+            if let Err(err) = &fetched {
+                tracing::warn!(game = name, %address, error = ?err, "fetch failed");
+            }
+
+            fetched
+        }
+    }
+
+    /// Fetches data from the game server until the fetch completes or `token` is cancelled.
+    ///
+    /// Cancelling drops the exchange wherever it is, e.g. while waiting for a response,
+    /// and its connection guard schedules a disconnect, so the socket is released at once
+    /// instead of after its timeout.
     ///
     /// # Parameters
     ///
-    /// * `query`: The query to send to the server.
+    /// * `query`: The query to send to the server, or a `QueryBuilder` for it.
     /// * `address`: The address of the server.
+    /// * `timeouts`: The time limits for each step and for the whole exchange.
+    /// * `token`: The token that aborts the fetch.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing either the parsed server response and any warnings, or an
+    /// `Error`. A cancelled fetch fails with an `Error::QueryError` of kind
+    /// `ErrorKind::Cancelled`.
+    fn fetch_cancellable(
+        &'a self,
+        query: impl IntoQuery<P::Q>,
+        address: SocketAddr,
+        timeouts: TimeoutSettings,
+        token: &'a CancellationToken,
+    ) -> impl Future<Output = FetchResult<P::R, P::E>> + Send
+    where
+        Self: Sync,
+    {
+        let fetch = self.fetch_with(query, address, timeouts);
+
+        async move { with_cancellation(token, "Fetch cancelled", fetch).await }
+    }
+
+    /// Fetches data from a server reachable under several addresses, trying them one after
+    /// another until one answers.
+    ///
+    /// This is the dual-stack fallback for hostnames with both A and AAAA records: the
+    /// addresses are tried in the order of `interleave_families`, so a host whose IPv6 path
+    /// is broken is still reached over IPv4 after a single failed attempt. Each attempt is
+    /// a complete `fetch_with`, with its own time limits and retries.
+    ///
+    /// # Parameters
+    ///
+    /// * `query`: The query to send to the server, or a `QueryBuilder` for it.
+    /// * `addresses`: The addresses of the server, in the order the resolver returned them.
+    /// * `timeouts`: The time limits for each attempt.
     ///
     /// # Returns
     ///
-    /// A `Result` containing either the parsed server response or an `Error`.
-    async fn fetch(&'a self, query: P::Q, address: SocketAddr) -> Result<P::R, Error<P::E>> {
-        let protocol = self._protocol();
+    /// A `Result` containing either the first response received, or the `Error` of the last
+    /// attempt. Without any address, the error is an `Error::QueryError` of kind
+    /// `ErrorKind::Dns`.
+    fn fetch_any(
+        &'a self,
+        query: impl IntoQuery<P::Q>,
+        addresses: impl IntoIterator<Item = SocketAddr>,
+        timeouts: TimeoutSettings,
+    ) -> impl Future<Output = FetchResult<P::R, P::E>> + Send
+    where
+        Self: Sync,
+    {
+        let query = query.into_query();
+        let addresses = interleave_families(addresses);
+
+        async move {
+            let mut addresses = addresses.into_iter().peekable();
+
+            // Errors are not kept across attempts, since protocol errors needn't be `Send`.
+            while let Some(address) = addresses.next() {
+                let fetched = self.fetch_with(query.clone(), address, timeouts).await;
+
+                if fetched.is_ok() || addresses.peek().is_none() {
+                    return fetched;
+                }
+
+                trace_event!(debug, %address, "address failed, trying the next one");
+            }
+
+            Err(Error::QueryError(
+                ErrorDetail::new("No address to query", None).with_kind(ErrorKind::Dns),
+            ))
+        }
+    }
+
+    /// Fetches data from a server given by host name.
+    ///
+    /// The host is resolved with `resolve_host`, using the SRV service of the game's
+    /// registry entry when no port is given, and the resolved addresses are queried with
+    /// `fetch_any`. How the host was resolved is reported in `ResponseMeta::resolution`,
+    /// and the address that answered in `ResponseMeta::address`.
+    ///
+    /// Resolving is bounded by `TimeoutSettings::connect`.
+    ///
+    /// # Parameters
+    ///
+    /// * `query`: The query to send to the server, or a `QueryBuilder` for it.
+    /// * `host`: The host, e.g. `play.example.com`, `play.example.com:25565` or
+    ///   `[2001:db8::1]:27015`. Ports are game ports, like in `ServerAddress`.
+    /// * `resolver`: The resolver to look up the host with, e.g. `SystemResolver`.
+    /// * `timeouts`: The time limits for resolving and for each attempt.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing either the first response received, or an `Error`. Hosts that
+    /// can't be parsed or resolved fail with an `Error::QueryError` of kind
+    /// `ErrorKind::Dns`.
+    fn fetch_host(
+        &'a self,
+        query: impl IntoQuery<P::Q>,
+        host: &'a str,
+        resolver: &'a dyn Resolver,
+        timeouts: TimeoutSettings,
+    ) -> impl Future<Output = FetchResult<P::R, P::E>> + Send
+    where
+        Self: Sync,
+    {
+        self.fetch_host_checked(query, host, resolver, None, timeouts)
+    }
+
+    /// Fetches data from a server given by host name, skipping the addresses a blocklist
+    /// refuses.
+    ///
+    /// This is `fetch_host` for services that query hosts named by their users, such as
+    /// web APIs and language bindings, which must not be turned against private networks
+    /// or servers that opted out.
+    ///
+    /// # Parameters
+    ///
+    /// * `query`: The query to send to the server, or a `QueryBuilder` for it.
+    /// * `host`: The host, with an optional game port.
+    /// * `resolver`: The resolver to look up the host with, e.g. `SystemResolver`.
+    /// * `blocklist`: The addresses that must not be queried, if any.
+    /// * `timeouts`: The time limits for resolving and for each attempt.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing either the first response received, or an `Error`. Besides
+    /// the errors of `fetch_host`, a host whose every address is refused fails with an
+    /// `Error::QueryError` of kind `ErrorKind::Blocked`.
+    fn fetch_host_checked(
+        &'a self,
+        query: impl IntoQuery<P::Q>,
+        host: &'a str,
+        resolver: &'a dyn Resolver,
+        blocklist: Option<&'a Blocklist>,
+        timeouts: TimeoutSettings,
+    ) -> impl Future<Output = FetchResult<P::R, P::E>> + Send
+    where
+        Self: Sync,
+    {
+        let query = query.into_query();
+
+        async move {
+            let resolved = self
+                .resolve_host(host, resolver, blocklist, timeouts)
+                .await?;
+
+            let mut fetched = self.fetch_any(query, resolved.addresses, timeouts).await?;
+
+            fetched.meta = fetched.meta.with_resolution(resolved.resolution);
+
+            Ok(fetched)
+        }
+    }
+
+    /// Resolves a host name to the addresses its server is queried at.
+    ///
+    /// The host is resolved with `resolve::resolve`, using the SRV service of the game's
+    /// registry entry when no port is given. Games without a registry entry fall back to
+    /// their `default_port`. Resolving is bounded by `TimeoutSettings::connect`.
+    ///
+    /// # Parameters
+    ///
+    /// * `host`: The host, with an optional game port.
+    /// * `resolver`: The resolver to look up the host with, e.g. `SystemResolver`.
+    /// * `blocklist`: The addresses to leave out, if any.
+    /// * `timeouts`: The time limits, of which only `connect` is used.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing either the addresses that may be queried, or an
+    /// `Error::QueryError` of kind `ErrorKind::Dns` if the host can't be parsed or resolved,
+    /// or of kind `ErrorKind::Blocked` if `blocklist` refuses every address.
+    fn resolve_host(
+        &'a self,
+        host: &'a str,
+        resolver: &'a dyn Resolver,
+        blocklist: Option<&'a Blocklist>,
+        timeouts: TimeoutSettings,
+    ) -> impl Future<Output = Result<Resolved, Error<P::E>>> + Send
+    where
+        Self: Sync,
+    {
+        async move {
+            let dns_error = |message: String| {
+                Error::QueryError(ErrorDetail::new(&message, None).with_kind(ErrorKind::Dns))
+            };
+
+            let mut target: HostPort = host.parse().map_err(|err| dns_error(format!("{err}")))?;
+            let entry = self.registry_entry();
+
+            if entry.is_none() {
+                target.port = target.port.or(self.default_port());
+            }
+
+            let lookup = async {
+                resolve::resolve(resolver, &target, entry)
+                    .await
+                    .map_err(|err| dns_error(format!("Failed to resolve `{target}`: {err}")))
+            };
+
+            let mut resolved = instrument!(
+                with_timeout(timeouts.connect, "Resolving timed out", lookup),
+                "resolve",
+                host = %target,
+            )
+            .await?;
+
+            let Some(blocklist) = blocklist else {
+                return Ok(resolved);
+            };
 
-        protocol.connect(address).await?;
-        protocol.send_query(query).await?;
+            let mut blocked = None;
 
-        let response = protocol.receive_response().await?;
+            resolved
+                .addresses
+                .retain(|&address| match blocklist.check(address) {
+                    Ok(()) => true,
+                    Err(refused) => {
+                        blocked = Some(refused);
+                        false
+                    }
+                });
 
-        protocol.disconnect().await?;
-        Ok(response)
+            match (resolved.addresses.is_empty(), blocked) {
+                (true, Some(blocked)) => Err(Error::QueryError(
+                    ErrorDetail::new(&blocked.to_string(), None).with_kind(ErrorKind::Blocked),
+                )),
+                _ => Ok(resolved),
+            }
+        }
     }
 }
@@ -0,0 +1,136 @@
+use crate::{
+    challenge::{ChallengeMemory, Requirement},
+    events::ConnectionEvent,
+    prelude::{Error, ErrorKind, RawTransport, TimeoutSettings},
+};
+
+use std::{future::Future, net::SocketAddr};
+
+use bytes::Bytes;
+
+/// The outcome of a handshake, handed to `Handshake::accept_challenge`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Challenge {
+    /// The server issued a challenge token to include in the query.
+    Token(Bytes),
+    /// The server answered the challenge request without a challenge.
+    ///
+    /// This holds the packet it answered with, which for protocols such as A2S (where the
+    /// challenge request is the query itself) is already the response.
+    NotRequired(Bytes),
+    /// No challenge was requested, because the server is known not to require one.
+    Skipped,
+}
+
+/// A trait for protocols that obtain a challenge token before the real query.
+///
+/// Implementors describe how to request and recognize a challenge and where to keep it;
+/// `perform_handshake` runs the exchange, retrying requests that time out. To make the
+/// handshake part of `Game::fetch`, forward `Protocol::handshake` to `perform_handshake`.
+pub trait Handshake<'a>: RawTransport<'a> {
+    /// Returns the packet that asks the server for a challenge.
+    fn challenge_request(&self) -> Vec<u8>;
+
+    /// Interprets the server's answer to the challenge request.
+    ///
+    /// # Parameters
+    ///
+    /// * `packet`: The packet the server answered with.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing either the `Challenge` (`Token` or `NotRequired`) or an `Error`
+    /// if the packet is malformed.
+    fn read_challenge(&self, packet: Bytes) -> Result<Challenge, Error<Self::E>>;
+
+    /// Stores the outcome of the handshake for the following query.
+    ///
+    /// # Parameters
+    ///
+    /// * `challenge`: The outcome of the handshake.
+    fn accept_challenge(&self, challenge: Challenge);
+
+    /// Returns the memory consulted to skip handshakes with servers known not to need one.
+    ///
+    /// The default implementation has no memory, so every connection performs a handshake.
+    fn challenge_memory(&self) -> Option<&ChallengeMemory> {
+        None
+    }
+
+    /// Returns the number of times a challenge request is sent before a timeout is final.
+    fn challenge_attempts(&self) -> usize {
+        3
+    }
+
+    /// Runs the handshake: requests a challenge, retrying on timeouts, and passes the
+    /// outcome to `accept_challenge`.
+    ///
+    /// The answer is reported to `Protocol::subscribers` as a
+    /// `ConnectionEvent::ChallengeReceived`.
+    ///
+    /// # Parameters
+    ///
+    /// * `address`: The address of the connected server.
+    /// * `timeouts`: The time limits to respect for each packet.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` which is `Ok` once the outcome was accepted, or an `Error`.
+    fn perform_handshake(
+        &self,
+        address: SocketAddr,
+        timeouts: &TimeoutSettings,
+    ) -> impl Future<Output = Result<(), Error<Self::E>>> + Send {
+        async move {
+            let memory = self.challenge_memory();
+
+            if memory.is_some_and(|memory| memory.requirement(address) == Requirement::NotRequired)
+            {
+                self.accept_challenge(Challenge::Skipped);
+                return Ok(());
+            }
+
+            let request = self.challenge_request();
+            let mut attempt = 1;
+
+            let packet = loop {
+                let exchange = async {
+                    self.send(&request, timeouts).await?;
+                    self.receive(timeouts).await
+                };
+
+                match exchange.await {
+                    Ok(packet) => break packet,
+                    Err(err)
+                        if err.kind() == ErrorKind::Timeout
+                            && attempt < self.challenge_attempts() =>
+                    {
+                        attempt += 1;
+                    }
+                    Err(err) => return Err(err),
+                }
+            };
+
+            let challenge = self.read_challenge(packet)?;
+
+            if let Some(subscribers) = self.subscribers() {
+                subscribers.emit(ConnectionEvent::ChallengeReceived {
+                    peer: address,
+                    required: matches!(challenge, Challenge::Token(_)),
+                });
+            }
+
+            if let Some(memory) = memory {
+                match challenge {
+                    Challenge::Token(_) => memory.record_required(address),
+                    Challenge::NotRequired(_) => memory.record_not_required(address),
+                    Challenge::Skipped => {}
+                }
+            }
+
+            self.accept_challenge(challenge);
+
+            Ok(())
+        }
+    }
+}
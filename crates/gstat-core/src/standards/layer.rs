@@ -0,0 +1,513 @@
+use crate::{
+    events::Subscribers,
+    metrics::MetricsSink,
+    prelude::{
+        Authenticate, Error, ErrorDetail, ErrorKind, Protocol, RawTransport, RetryPolicy,
+        TimeoutSettings,
+    },
+    rate_limit::RateLimiter,
+    runtime,
+    standards::response::Meter,
+};
+
+use std::{
+    fmt::{Debug, Display, Formatter, Result as FmtResult},
+    future::Future,
+    net::SocketAddr,
+    sync::Mutex,
+    time::Duration,
+};
+
+use bytes::Bytes;
+
+/// An operation of a `Protocol` passing through a `ProtocolLayer`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Operation {
+    /// `Protocol::connect`.
+    Connect,
+    /// `Protocol::handshake`.
+    Handshake,
+    /// `Authenticate::authenticate`.
+    Authenticate,
+    /// `Protocol::send_query`.
+    SendQuery,
+    /// `Protocol::receive_response`.
+    ReceiveResponse,
+    /// `RawTransport::send`.
+    Send,
+    /// `RawTransport::receive`.
+    Receive,
+    /// `Protocol::disconnect`.
+    Disconnect,
+}
+
+impl Operation {
+    /// Returns a stable, human-readable name for the operation.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Operation::Connect => "connect",
+            Operation::Handshake => "handshake",
+            Operation::Authenticate => "authenticate",
+            Operation::SendQuery => "send_query",
+            Operation::ReceiveResponse => "receive_response",
+            Operation::Send => "send",
+            Operation::Receive => "receive",
+            Operation::Disconnect => "disconnect",
+        }
+    }
+}
+
+impl Display for Operation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.write_str(self.name())
+    }
+}
+
+/// A single call of an operation, as seen by a `ProtocolLayer`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Call {
+    /// The operation being called.
+    pub operation: Operation,
+    /// The address the protocol is connected to, or connecting to for `Operation::Connect`.
+    ///
+    /// This is `None` before the first connection.
+    pub peer: Option<SocketAddr>,
+}
+
+/// A trait for middleware wrapping the operations of a `Protocol`.
+///
+/// Layers add behavior such as logging, rate limiting, fault injection or metrics to any
+/// protocol without changing its implementation. They are applied with `Protocol::layer`,
+/// which returns a `Layered` protocol; applying further layers wraps the previous ones, so
+/// the last layer applied sees each call first:
+///
+/// ```ignore
+/// let protocol = UdpProtocol::new(parser)
+///     .layer(RetryLayer::new(RetryPolicy::new(3)))
+///     .layer(MetricsLayer::new(counters.clone()));
+/// ```
+///
+/// Every method has a default implementation that changes nothing, so a layer only
+/// implements the hooks it needs. Layers that don't depend on the wrapped protocol should
+/// be implemented for every `P`, so they can be stacked in any order.
+pub trait ProtocolLayer<'a, P: Protocol<'a>>: Send + Sync {
+    /// Wraps a call of one of the protocol's operations.
+    ///
+    /// The default implementation returns `future` unchanged.
+    ///
+    /// # Parameters
+    ///
+    /// * `call`: The operation being called and the peer it concerns.
+    /// * `future`: The call of the wrapped protocol. Awaiting it performs the operation;
+    ///   dropping it skips the operation.
+    ///
+    /// # Returns
+    ///
+    /// A future resolving to the result reported to the caller.
+    fn around<T, F>(
+        &self,
+        call: Call,
+        future: F,
+    ) -> impl Future<Output = Result<T, Error<P::E>>> + Send
+    where
+        F: Future<Output = Result<T, Error<P::E>>> + Send,
+    {
+        let _ = call;
+
+        future
+    }
+
+    /// Returns the retry policy of the layered protocol.
+    ///
+    /// The default implementation returns the policy of the wrapped protocol.
+    ///
+    /// # Parameters
+    ///
+    /// * `inner`: The retry policy of the wrapped protocol.
+    fn retry_policy(&self, inner: RetryPolicy) -> RetryPolicy {
+        inner
+    }
+
+    /// Returns the metrics sink of the layered protocol.
+    ///
+    /// The default implementation returns the sink of the wrapped protocol.
+    ///
+    /// # Parameters
+    ///
+    /// * `inner`: The metrics sink of the wrapped protocol.
+    fn metrics<'s>(&'s self, inner: Option<&'s dyn MetricsSink>) -> Option<&'s dyn MetricsSink> {
+        inner
+    }
+}
+
+/// A `Protocol` wrapped by a `ProtocolLayer`, as returned by `Protocol::layer`.
+///
+/// It behaves like the wrapped protocol, passing every operation through the layer. It
+/// implements `RawTransport` and `Authenticate` if the wrapped protocol does. Protocols
+/// implementing `Handshake` keep performing their handshake, which the layer sees as a
+/// single `Operation::Handshake`.
+pub struct Layered<P, L> {
+    inner: P,
+    layer: L,
+    /// The address of the last connection.
+    peer: Mutex<Option<SocketAddr>>,
+}
+
+impl<P, L> Layered<P, L> {
+    /// Wraps `protocol` with `layer`.
+    ///
+    /// # Parameters
+    ///
+    /// * `protocol`: The protocol to wrap.
+    /// * `layer`: The layer every operation passes through.
+    pub fn new(protocol: P, layer: L) -> Self {
+        Layered {
+            inner: protocol,
+            layer,
+            peer: Mutex::new(None),
+        }
+    }
+
+    /// Returns the wrapped protocol.
+    pub fn protocol(&self) -> &P {
+        &self.inner
+    }
+
+    /// Unwraps the protocol, discarding the layer.
+    pub fn into_inner(self) -> P {
+        self.inner
+    }
+
+    /// Returns the call of `operation` on the current peer.
+    fn call(&self, operation: Operation) -> Call {
+        Call {
+            operation,
+            peer: *self.peer.lock().expect("layered protocol lock poisoned"),
+        }
+    }
+}
+
+impl<P: Debug, L: Debug> Debug for Layered<P, L> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.debug_struct("Layered")
+            .field("protocol", &self.inner)
+            .field("layer", &self.layer)
+            .finish()
+    }
+}
+
+impl<'a, P, L> Protocol<'a> for Layered<P, L>
+where
+    P: Protocol<'a>,
+    L: ProtocolLayer<'a, P>,
+{
+    type Q = P::Q;
+    type R = P::R;
+    type P = P::P;
+    type E = P::E;
+
+    fn retry_policy(&self) -> RetryPolicy {
+        self.layer.retry_policy(self.inner.retry_policy())
+    }
+
+    fn subscribers(&self) -> Option<&Subscribers> {
+        self.inner.subscribers()
+    }
+
+    fn meter(&self) -> Option<&Meter> {
+        self.inner.meter()
+    }
+
+    fn metrics(&self) -> Option<&dyn MetricsSink> {
+        self.layer.metrics(self.inner.metrics())
+    }
+
+    async fn _connect(
+        &self,
+        address: SocketAddr,
+        timeouts: &TimeoutSettings,
+    ) -> Result<(), Error<Self::E>> {
+        *self.peer.lock().expect("layered protocol lock poisoned") = Some(address);
+
+        let call = self.call(Operation::Connect);
+
+        self.layer
+            .around(call, self.inner._connect(address, timeouts))
+            .await
+    }
+
+    fn schedule_disconnect(&self) {
+        self.inner.schedule_disconnect();
+    }
+
+    async fn handshake(
+        &self,
+        address: SocketAddr,
+        timeouts: &TimeoutSettings,
+    ) -> Result<(), Error<Self::E>> {
+        let call = self.call(Operation::Handshake);
+
+        self.layer
+            .around(call, self.inner.handshake(address, timeouts))
+            .await
+    }
+
+    async fn send_query(
+        &self,
+        query: Self::Q,
+        timeouts: &TimeoutSettings,
+    ) -> Result<(), Error<Self::E>> {
+        let call = self.call(Operation::SendQuery);
+
+        self.layer
+            .around(call, self.inner.send_query(query, timeouts))
+            .await
+    }
+
+    async fn receive_response(
+        &self,
+        timeouts: &TimeoutSettings,
+    ) -> Result<Self::R, Error<Self::E>> {
+        let call = self.call(Operation::ReceiveResponse);
+
+        self.layer
+            .around(call, self.inner.receive_response(timeouts))
+            .await
+    }
+
+    async fn disconnect(&self) -> Result<(), Error<Self::E>> {
+        let call = self.call(Operation::Disconnect);
+
+        self.layer.around(call, self.inner.disconnect()).await
+    }
+}
+
+impl<'a, P, L> RawTransport<'a> for Layered<P, L>
+where
+    P: RawTransport<'a>,
+    L: ProtocolLayer<'a, P>,
+{
+    async fn send(&self, data: &[u8], timeouts: &TimeoutSettings) -> Result<(), Error<Self::E>> {
+        let call = self.call(Operation::Send);
+
+        self.layer
+            .around(call, self.inner.send(data, timeouts))
+            .await
+    }
+
+    async fn receive(&self, timeouts: &TimeoutSettings) -> Result<Bytes, Error<Self::E>> {
+        let call = self.call(Operation::Receive);
+
+        self.layer.around(call, self.inner.receive(timeouts)).await
+    }
+}
+
+impl<'a, P, L> Authenticate<'a> for Layered<P, L>
+where
+    P: Authenticate<'a>,
+    L: ProtocolLayer<'a, P>,
+{
+    type Credentials = P::Credentials;
+
+    async fn authenticate(
+        &self,
+        credentials: &Self::Credentials,
+        timeouts: &TimeoutSettings,
+    ) -> Result<(), Error<Self::E>> {
+        let call = self.call(Operation::Authenticate);
+
+        self.layer
+            .around(call, self.inner.authenticate(credentials, timeouts))
+            .await
+    }
+}
+
+/// A layer replacing the retry policy of the wrapped protocol.
+#[derive(Clone)]
+pub struct RetryLayer {
+    policy: RetryPolicy,
+}
+
+impl RetryLayer {
+    /// Creates a layer making `Game::fetch_with` retry under `policy`.
+    pub fn new(policy: RetryPolicy) -> Self {
+        RetryLayer { policy }
+    }
+}
+
+impl Debug for RetryLayer {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.debug_struct("RetryLayer")
+            .field("max_attempts", &self.policy.max_attempts)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<'a, P: Protocol<'a>> ProtocolLayer<'a, P> for RetryLayer {
+    fn retry_policy(&self, _inner: RetryPolicy) -> RetryPolicy {
+        self.policy.clone()
+    }
+}
+
+/// A layer reporting the queries of the wrapped protocol to a `MetricsSink`.
+///
+/// The sink replaces any sink of the wrapped protocol. Pass an `Arc` to share one sink
+/// between protocols.
+pub struct MetricsLayer<S> {
+    sink: S,
+}
+
+impl<S: MetricsSink> MetricsLayer<S> {
+    /// Creates a layer reporting to `sink`.
+    pub fn new(sink: S) -> Self {
+        MetricsLayer { sink }
+    }
+}
+
+impl<S: Debug> Debug for MetricsLayer<S> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.debug_struct("MetricsLayer")
+            .field("sink", &self.sink)
+            .finish()
+    }
+}
+
+impl<'a, P: Protocol<'a>, S: MetricsSink> ProtocolLayer<'a, P> for MetricsLayer<S> {
+    fn metrics<'s>(&'s self, _inner: Option<&'s dyn MetricsSink>) -> Option<&'s dyn MetricsSink> {
+        Some(&self.sink)
+    }
+}
+
+/// A layer pacing the queries and packets sent to each peer with a `RateLimiter`.
+///
+/// A permit is taken before every `Operation::SendQuery` and `Operation::Send`. Clones of
+/// a limiter share their buckets, so protocols layered with clones are paced together.
+#[derive(Clone, Debug)]
+pub struct RateLimitLayer {
+    limiter: RateLimiter,
+}
+
+impl RateLimitLayer {
+    /// Creates a layer taking its permits from `limiter`.
+    pub fn new(limiter: RateLimiter) -> Self {
+        RateLimitLayer { limiter }
+    }
+}
+
+impl<'a, P: Protocol<'a>> ProtocolLayer<'a, P> for RateLimitLayer {
+    async fn around<T, F>(&self, call: Call, future: F) -> Result<T, Error<P::E>>
+    where
+        F: Future<Output = Result<T, Error<P::E>>> + Send,
+    {
+        if let (Operation::SendQuery | Operation::Send, Some(peer)) = (call.operation, call.peer) {
+            self.limiter.acquire(peer).await;
+        }
+
+        future.await
+    }
+}
+
+/// A layer making operations fail or slow down, to test how callers cope with unreliable
+/// servers.
+///
+/// By default, a fault makes `Operation::Connect`, `Operation::SendQuery` and
+/// `Operation::ReceiveResponse` fail with a `ProtocolError` of kind `ErrorKind::Timeout`
+/// without performing them.
+#[derive(Clone, Debug)]
+pub struct FaultLayer {
+    rate: f64,
+    kind: ErrorKind,
+    delay: Duration,
+    operations: Vec<Operation>,
+}
+
+impl FaultLayer {
+    /// Creates a layer injecting faults into a fraction of the calls.
+    ///
+    /// # Parameters
+    ///
+    /// * `rate`: The probability of a fault per call, from `0.0` (never) to `1.0` (always).
+    pub fn new(rate: f64) -> Self {
+        FaultLayer {
+            rate: rate.clamp(0.0, 1.0),
+            kind: ErrorKind::Timeout,
+            delay: Duration::ZERO,
+            operations: vec![
+                Operation::Connect,
+                Operation::SendQuery,
+                Operation::ReceiveResponse,
+            ],
+        }
+    }
+
+    /// Sets the kind of the injected errors.
+    pub fn with_kind(mut self, kind: ErrorKind) -> Self {
+        self.kind = kind;
+        self
+    }
+
+    /// Sets the delay added before every call of the affected operations, faulty or not.
+    pub fn with_delay(mut self, delay: Duration) -> Self {
+        self.delay = delay;
+        self
+    }
+
+    /// Sets the operations affected by the layer.
+    pub fn with_operations(mut self, operations: impl IntoIterator<Item = Operation>) -> Self {
+        self.operations = operations.into_iter().collect();
+        self
+    }
+}
+
+impl<'a, P: Protocol<'a>> ProtocolLayer<'a, P> for FaultLayer {
+    async fn around<T, F>(&self, call: Call, future: F) -> Result<T, Error<P::E>>
+    where
+        F: Future<Output = Result<T, Error<P::E>>> + Send,
+    {
+        if !self.operations.contains(&call.operation) {
+            return future.await;
+        }
+
+        if !self.delay.is_zero() {
+            runtime::sleep(self.delay).await;
+        }
+
+        if self.rate > 0.0 && fastrand::f64() < self.rate {
+            return Err(Error::ProtocolError(
+                ErrorDetail::new(&format!("Injected fault in {}", call.operation), None)
+                    .with_kind(self.kind),
+            ));
+        }
+
+        future.await
+    }
+}
+
+/// A layer running every operation in a `DEBUG` span and logging failed operations.
+///
+/// This layer records nothing with the `silent` feature.
+#[cfg(feature = "tracing")]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct TraceLayer;
+
+#[cfg(feature = "tracing")]
+impl<'a, P: Protocol<'a>> ProtocolLayer<'a, P> for TraceLayer {
+    async fn around<T, F>(&self, _call: Call, future: F) -> Result<T, Error<P::E>>
+    where
+        F: Future<Output = Result<T, Error<P::E>>> + Send,
+    {
+        let result = instrument!(
+            future,
+            "layer",
+            operation = _call.operation.name(),
+            peer = ?_call.peer
+        )
+        .await;
+
+        if let Err(_err) = &result {
+            trace_event!(debug, operation = _call.operation.name(), error = %_err, "operation failed");
+        }
+
+        result
+    }
+}
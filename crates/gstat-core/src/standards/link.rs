@@ -0,0 +1,239 @@
+use crate::{
+    events::ConnectionEvent,
+    prelude::{Authenticate, ConnectionGuard, Error, Protocol, TimeoutSettings},
+};
+
+use std::{marker::PhantomData, net::SocketAddr};
+
+mod sealed {
+    pub trait Sealed {}
+}
+
+/// The state of a `Link`.
+///
+/// This trait is sealed; the states are `Disconnected`, `Connected` and `Authenticated`.
+pub trait State: sealed::Sealed {}
+
+/// A state in which queries can be sent, i.e. `Connected` or `Authenticated`.
+pub trait Open: State {}
+
+/// The state of a `Link` that has no connection.
+#[derive(Debug)]
+pub enum Disconnected {}
+
+/// The state of a `Link` that is connected but not authenticated.
+#[derive(Debug)]
+pub enum Connected {}
+
+/// The state of a `Link` that is connected and authenticated.
+#[derive(Debug)]
+pub enum Authenticated {}
+
+impl sealed::Sealed for Disconnected {}
+impl sealed::Sealed for Connected {}
+impl sealed::Sealed for Authenticated {}
+
+impl State for Disconnected {}
+impl State for Connected {}
+impl State for Authenticated {}
+
+impl Open for Connected {}
+impl Open for Authenticated {}
+
+/// A `Link` drives a `Protocol` through its connection states at the type level.
+///
+/// Each transition consumes the link and returns it in its new state, so operations are
+/// only available in the states where they are valid: queries can't be sent before
+/// `connect`, and `authenticate` is only available on connected links of protocols that
+/// implement `Authenticate`. Misuse is a compile-time error rather than a runtime error or
+/// a hang:
+///
+/// ```text
+/// Disconnected --connect--> Connected --authenticate--> Authenticated
+///       ^                       |                             |
+///       +-------disconnect------+-----------disconnect--------+
+/// ```
+///
+/// A connected link holds a `ConnectionGuard`, so dropping it schedules a disconnect.
+///
+/// Failed authentication is reported to the protocol's subscribers as
+/// `ConnectionEvent::AuthFailed`.
+pub struct Link<'p, P, S: State> {
+    protocol: &'p P,
+    peer: Option<SocketAddr>,
+    guard: Option<ConnectionGuard<'p, P>>,
+    _state: PhantomData<S>,
+}
+
+impl<'p, P, S: State> Link<'p, P, S> {
+    /// Returns the underlying protocol.
+    pub fn protocol(&self) -> &'p P {
+        self.protocol
+    }
+
+    /// Returns the address the link is connected to, if any.
+    pub fn peer(&self) -> Option<SocketAddr> {
+        self.peer
+    }
+
+    /// Moves the link into another state, keeping its connection.
+    fn into_state<T: State>(self) -> Link<'p, P, T> {
+        Link {
+            protocol: self.protocol,
+            peer: self.peer,
+            guard: self.guard,
+            _state: PhantomData,
+        }
+    }
+}
+
+impl<'p, P> Link<'p, P, Disconnected> {
+    /// Creates a disconnected link over `protocol`.
+    ///
+    /// # Parameters
+    ///
+    /// * `protocol`: The protocol to drive.
+    pub fn new(protocol: &'p P) -> Self {
+        Link {
+            protocol,
+            peer: None,
+            guard: None,
+            _state: PhantomData,
+        }
+    }
+
+    /// Connects to a specific IP address.
+    ///
+    /// # Parameters
+    ///
+    /// * `address`: The target IP address for connection establishment.
+    /// * `timeouts`: The time limits to respect; `TimeoutSettings::connect` applies here.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing either the connected link or an `Error`.
+    pub async fn connect<'a>(
+        self,
+        address: SocketAddr,
+        timeouts: &TimeoutSettings,
+    ) -> Result<Link<'p, P, Connected>, Error<P::E>>
+    where
+        P: Protocol<'a>,
+    {
+        let guard = self.protocol.connect(address, timeouts).await?;
+
+        Ok(Link {
+            protocol: self.protocol,
+            peer: Some(address),
+            guard: Some(guard),
+            _state: PhantomData,
+        })
+    }
+}
+
+impl<'p, P> Link<'p, P, Connected> {
+    /// Authenticates the connection.
+    ///
+    /// If authentication fails, the link is dropped, which schedules a disconnect.
+    ///
+    /// # Parameters
+    ///
+    /// * `credentials`: The credentials to present to the server.
+    /// * `timeouts`: The time limits to respect.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing either the authenticated link or an `Error`.
+    pub async fn authenticate<'a>(
+        self,
+        credentials: &P::Credentials,
+        timeouts: &TimeoutSettings,
+    ) -> Result<Link<'p, P, Authenticated>, Error<P::E>>
+    where
+        P: Authenticate<'a>,
+    {
+        if let Err(err) = self.protocol.authenticate(credentials, timeouts).await {
+            if let (Some(subscribers), Some(peer)) = (self.protocol.subscribers(), self.peer) {
+                subscribers.emit(ConnectionEvent::AuthFailed {
+                    peer,
+                    reason: err.to_string(),
+                });
+            }
+
+            return Err(err);
+        }
+
+        Ok(self.into_state())
+    }
+}
+
+impl<'p, P, S: Open> Link<'p, P, S> {
+    /// Sends a query to the connected server.
+    ///
+    /// # Parameters
+    ///
+    /// * `query`: The query object to be sent.
+    /// * `timeouts`: The time limits to respect; `TimeoutSettings::write` applies here.
+    pub async fn send_query<'a>(
+        &self,
+        query: P::Q,
+        timeouts: &TimeoutSettings,
+    ) -> Result<(), Error<P::E>>
+    where
+        P: Protocol<'a>,
+    {
+        self.protocol.send_query(query, timeouts).await
+    }
+
+    /// Receives a response from the connected server.
+    ///
+    /// # Parameters
+    ///
+    /// * `timeouts`: The time limits to respect; `TimeoutSettings::read` applies here.
+    pub async fn receive_response<'a>(
+        &self,
+        timeouts: &TimeoutSettings,
+    ) -> Result<P::R, Error<P::E>>
+    where
+        P: Protocol<'a>,
+    {
+        self.protocol.receive_response(timeouts).await
+    }
+
+    /// Sends a query and receives its response.
+    ///
+    /// # Parameters
+    ///
+    /// * `query`: The query object to be sent.
+    /// * `timeouts`: The time limits to respect.
+    pub async fn query<'a>(
+        &self,
+        query: P::Q,
+        timeouts: &TimeoutSettings,
+    ) -> Result<P::R, Error<P::E>>
+    where
+        P: Protocol<'a>,
+    {
+        self.send_query(query, timeouts).await?;
+        self.receive_response(timeouts).await
+    }
+
+    /// Disconnects from the server.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing either the disconnected link, ready to connect again, or an
+    /// `Error`.
+    pub async fn disconnect<'a>(mut self) -> Result<Link<'p, P, Disconnected>, Error<P::E>>
+    where
+        P: Protocol<'a>,
+    {
+        if let Some(guard) = self.guard.take() {
+            guard.disconnect().await?;
+        }
+
+        self.peer = None;
+
+        Ok(self.into_state())
+    }
+}
@@ -1,5 +1,17 @@
+pub mod any;
+pub mod authenticate;
+pub mod connection;
+pub mod correlation;
+pub mod define_game;
+pub mod dyn_game;
+pub mod dyn_protocol;
 pub mod game;
+pub mod handshake;
+pub mod layer;
+pub mod link;
 pub mod parser;
 pub mod protocol;
 pub mod query;
-pub mod response;
\ No newline at end of file
+pub mod raw_transport;
+pub mod response;
+pub mod session;
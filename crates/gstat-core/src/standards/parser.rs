@@ -1,6 +1,8 @@
-use crate::prelude::{Error, ErrorDetail, Query, Response};
+use crate::prelude::{Error, ErrorDetail, ErrorKind, Query, Response};
 
-use std::{error::Error as StdError, io::Cursor};
+use std::error::Error as StdError;
+
+use bytes::Bytes;
 
 /// `Parser` is a trait which outlines the necessary methods for
 /// serializing and deserializing data between queries and responses.
@@ -32,9 +34,14 @@ where
     ///
     /// A `Result` containing either the serialized `query` as a byte vector or an `Error`.
     fn serialize_query(&self, query: &Q) -> Result<Vec<u8>, Error<Self::SE>> {
-        self._serialize_query(query).map_err(|err| {
+        let payload = self._serialize_query(query).map_err(|err| {
+            trace_event!(debug, error = %err, "failed to serialize query");
             Error::ParserError(ErrorDetail::new("Failed to serialize query", Some(err)))
-        })
+        })?;
+
+        trace_event!(trace, bytes = payload.len(), "serialized query");
+
+        Ok(payload)
     }
 
     /// Internal method for serializing the provided `query` into a byte vector.
@@ -48,33 +55,41 @@ where
     /// A `Result` containing either the serialized `query` as a byte vector or an `Error`.
     fn _serialize_query(&self, query: &Q) -> Result<Vec<u8>, Self::SE>;
 
-    /// Deserialize a byte stream from a provided Cursor into a `Response`.
-    /// If deserialization fails, an `Error` wrapping the deserialization error is returned.
+    /// Deserialize a packet into a `Response`.
+    ///
+    /// The packet is passed as `Bytes`, so responses can keep cheap slices of it (for
+    /// example as `ByteStr` fields) instead of copying data out.
+    /// If deserialization fails, an `Error` of kind `ErrorKind::InvalidPacket` wrapping the
+    /// deserialization error is returned.
     ///
     /// # Parameters
     ///
-    /// * `data`: A Cursor over the data to deserialize.
+    /// * `data`: The packet to deserialize.
     ///
     /// # Returns
     ///
     /// A `Result` containing either the deserialized `Response` or an `Error`.
-    fn deserialize_response(&self, data: Cursor<Vec<u8>>) -> Result<R, Error<Self::DE>> {
+    fn deserialize_response(&self, data: Bytes) -> Result<R, Error<Self::DE>> {
+        trace_event!(trace, bytes = data.len(), "deserializing response");
+
         self._deserialize_response(data).map_err(|err| {
-            Error::ParserError(ErrorDetail::new(
-                "Failed to deserialize response",
-                Some(err),
-            ))
+            trace_event!(debug, error = %err, "failed to deserialize response");
+
+            Error::ParserError(
+                ErrorDetail::new("Failed to deserialize response", Some(err))
+                    .with_kind(ErrorKind::InvalidPacket),
+            )
         })
     }
 
-    /// Internal method for deserializing a byte stream from a provided Cursor into a `Response`.
+    /// Internal method for deserializing a packet into a `Response`.
     ///
     /// # Parameters
     ///
-    /// * `data`: A Cursor over the data to deserialize.
+    /// * `data`: The packet to deserialize.
     ///
     /// # Returns
     ///
     /// A `Result` containing either the deserialized `Response` or an `Error`.
-    fn _deserialize_response(&self, data: Cursor<Vec<u8>>) -> Result<R, Self::DE>;
+    fn _deserialize_response(&self, data: Bytes) -> Result<R, Self::DE>;
 }
@@ -1,8 +1,14 @@
-use crate::prelude::{Error, Parser, Query, Response};
+use crate::{
+    events::Subscribers,
+    metrics::MetricsSink,
+    prelude::{ConnectionGuard, Error, Parser, Query, Response, RetryPolicy, TimeoutSettings},
+    standards::{
+        layer::{Layered, ProtocolLayer},
+        response::Meter,
+    },
+};
 
-use std::{error::Error as StdError, net::SocketAddr};
-
-use async_trait::async_trait;
+use std::{error::Error as StdError, future::Future, net::SocketAddr};
 
 /// A trait defining the standard behavior of a network protocol.
 ///
@@ -11,8 +17,14 @@ use async_trait::async_trait;
 /// Each operation is asynchronous and returns a `Result` to facilitate error handling.
 ///
 /// This trait uses associated types for Query `Q`, Response `R`, Parser `P` and Error `E` allowing flexibility for various network protocols.
-#[async_trait]
-pub trait Protocol<'a> 
+///
+/// Every exchange goes through the parser. Protocols that also expose their raw packets,
+/// e.g. for handshakes, implement `RawTransport`.
+///
+/// The asynchronous methods return unboxed futures, so implementations can be written with
+/// plain `async fn` and calls don't allocate. The futures must be `Send`. To store protocols
+/// of different types behind one pointer, use `DynProtocol`.
+pub trait Protocol<'a>
 where
     Self: Send + Sync + Sized,
 {
@@ -30,14 +42,122 @@ where
     /// The type of error that can occur when using this protocol.
     type E: StdError;
 
+    /// Returns the policy used to retry failed exchanges over this protocol.
+    ///
+    /// `Game::fetch_with` consults this policy automatically. The default implementation
+    /// makes a single attempt; transports typically make the policy configurable.
+    fn retry_policy(&self) -> RetryPolicy {
+        RetryPolicy::default()
+    }
+
+    /// Returns the subscribers notified of this protocol's connection events.
+    ///
+    /// Persistent protocols report when connections are established, lost and closed, and
+    /// `Link` reports failed authentication through them. Transports report the packets they
+    /// exchange, and `Game::fetch_with` and `Handshake::perform_handshake` report the steps
+    /// of each query. The default implementation has no subscribers and reports nothing.
+    fn subscribers(&self) -> Option<&Subscribers> {
+        None
+    }
+
+    /// Returns the meter this protocol records the packets it receives in.
+    ///
+    /// `Game::fetch_with` uses it to attach the raw packets to `ResponseMeta`. The default
+    /// implementation records nothing, which leaves the packets of `ResponseMeta` empty.
+    fn meter(&self) -> Option<&Meter> {
+        None
+    }
+
+    /// Returns the sink this protocol's queries and packets are reported to.
+    ///
+    /// `Game::fetch_with` reports every query, response and failure to it, and transports
+    /// report the packets they exchange. The default implementation reports nothing.
+    fn metrics(&self) -> Option<&dyn MetricsSink> {
+        None
+    }
+
+    /// Wraps the protocol with a `ProtocolLayer`, passing every operation through it.
+    ///
+    /// Calls can be chained to stack several layers; the last layer applied sees each call
+    /// first.
+    ///
+    /// # Parameters
+    ///
+    /// * `layer`: The layer to apply, e.g. a `RetryLayer` or a `FaultLayer`.
+    ///
+    /// # Returns
+    ///
+    /// The `Layered` protocol.
+    fn layer<L: ProtocolLayer<'a, Self>>(self, layer: L) -> Layered<Self, L> {
+        Layered::new(self, layer)
+    }
+
     /// Connect to a specific IP address asynchronously.
     ///
     /// This method attempts to establish a network connection with a server or network device at the specified IP address.
+    /// The returned `ConnectionGuard` disconnects on a best-effort basis if it is dropped before
+    /// `ConnectionGuard::disconnect` is called, so early returns can't leak the connection.
+    ///
+    /// # Parameters
+    ///
+    /// * `address`: The target IP address for connection establishment.
+    /// * `timeouts`: The time limits to respect; `TimeoutSettings::connect` applies here.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing either a guard over the connected protocol or an `Error`.
+    fn connect<'p>(
+        &'p self,
+        address: SocketAddr,
+        timeouts: &TimeoutSettings,
+    ) -> impl Future<Output = Result<ConnectionGuard<'p, Self>, Error<Self::E>>> + Send {
+        async move {
+            self._connect(address, timeouts).await?;
+
+            Ok(ConnectionGuard::new(self, Self::schedule_disconnect))
+        }
+    }
+
+    /// Internal method for connecting to a specific IP address asynchronously.
     ///
     /// # Parameters
     ///
     /// * `address`: The target IP address for connection establishment.
-    async fn connect(&self, address: SocketAddr) -> Result<(), Error<Self::E>>;
+    /// * `timeouts`: The time limits to respect; `TimeoutSettings::connect` applies here.
+    fn _connect(
+        &self,
+        address: SocketAddr,
+        timeouts: &TimeoutSettings,
+    ) -> impl Future<Output = Result<(), Error<Self::E>>> + Send;
+
+    /// Schedule a best-effort disconnect without waiting for it.
+    ///
+    /// This is called when a `ConnectionGuard` is dropped while still connected, where the
+    /// asynchronous `disconnect` can't be awaited. Implementations should release their
+    /// connection here, for example by spawning `disconnect` on their runtime or by closing
+    /// the socket synchronously. The default implementation does nothing.
+    fn schedule_disconnect(&self) {}
+
+    /// Perform the handshake the server requires before it answers queries.
+    ///
+    /// `Game::fetch_with` calls this after connecting and before sending the query. Protocols
+    /// whose servers hand out challenge tokens (such as A2S or GameSpy 4) implement
+    /// `Handshake` and forward this method to `Handshake::perform_handshake`, which runs the
+    /// request, challenge and retry loop for them. The default implementation does nothing.
+    ///
+    /// # Parameters
+    ///
+    /// * `address`: The address of the connected server.
+    /// * `timeouts`: The time limits to respect for each packet of the handshake.
+    fn handshake(
+        &self,
+        address: SocketAddr,
+        timeouts: &TimeoutSettings,
+    ) -> impl Future<Output = Result<(), Error<Self::E>>> + Send {
+        let _ = (address, timeouts);
+
+        async { Ok(()) }
+    }
 
     /// Send a query to the connected server or device asynchronously.
     ///
@@ -46,33 +166,27 @@ where
     /// # Parameters
     ///
     /// * `query`: The query object to be sent.
-    async fn send_query(&self, query: Self::Q) -> Result<(), Error<Self::E>>;
+    /// * `timeouts`: The time limits to respect; `TimeoutSettings::write` applies here.
+    fn send_query(
+        &self,
+        query: Self::Q,
+        timeouts: &TimeoutSettings,
+    ) -> impl Future<Output = Result<(), Error<Self::E>>> + Send;
 
     /// Receive a response from the connected server or device asynchronously.
     ///
     /// The received response is parsed using the associated Parser into the Response type.
-    async fn receive_response(&self) -> Result<Self::R, Error<Self::E>>;
-
-    /// Disconnect from the connected server or device asynchronously.
-    ///
-    /// This method closes the active network connection or session.
-    async fn disconnect(&self) -> Result<(), Error<Self::E>>;
-
-    /// Send a data packet over the network asynchronously.
-    ///
-    /// This method is intended to send raw bytes and does not involve the associated Query or Response types.
     ///
     /// # Parameters
     ///
-    /// * `data`: The raw data to be sent across the network.
-
-    // This should be classed as a unsafe function as it is not bound by the library
-    async fn send(&self, data: &[u8]) -> Result<(), Error<Self::E>>;
+    /// * `timeouts`: The time limits to respect; `TimeoutSettings::read` applies here.
+    fn receive_response(
+        &self,
+        timeouts: &TimeoutSettings,
+    ) -> impl Future<Output = Result<Self::R, Error<Self::E>>> + Send;
 
-    /// Receive a data packet from the network asynchronously.
+    /// Disconnect from the connected server or device asynchronously.
     ///
-    /// This method retrieves raw data from the network and does not involve the associated Query or Response types.
-
-    // This should be classed as a unsafe function as it is not bound by the library
-    async fn receive(&self) -> Result<Vec<u8>, Error<Self::E>>;
+    /// This method closes the active network connection or session.
+    fn disconnect(&self) -> impl Future<Output = Result<(), Error<Self::E>>> + Send;
 }
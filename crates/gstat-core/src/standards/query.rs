@@ -2,17 +2,51 @@ use crate::prelude::Error;
 
 use std::error::Error as StdError;
 
+/// The options a query is built from.
+///
+/// Every game understands `players` and `rules`; options only some games have, such as
+/// protocol variants or AppID filters, go in `extra`, whose type is chosen by the query
+/// through `Query::Options`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct QueryOptions<O = ()> {
+    /// Whether the player list is requested.
+    pub players: bool,
+    /// Whether the rules or key/value settings are requested.
+    pub rules: bool,
+    /// The game-specific options.
+    pub extra: O,
+}
+
 /// A `Query` trait represents a type that can be instantiated and then sent to a protocol.
 ///
-/// This trait is generic over the type of Query Error `E`.
+/// Queries must be cloneable so they can be sent again when an attempt is retried.
+///
+/// A query is built from `QueryOptions`, either directly with `from_options` or through
+/// the builder returned by `builder`:
+///
+/// ```ignore
+/// let query = StatusQuery::builder().players(true).rules(false).build();
+/// ```
+///
+/// This trait is generic over the type of Query Error `E` and the game-specific `Options`.
 pub trait Query
 where
-    Self: Send + Sync + Sized,
- {
+    Self: Clone + Send + Sync + Sized,
+{
     /// The type for query errors.
     type E: StdError + 'static;
 
-    /// Creates a new instance of the Query.
+    /// The game-specific options, or `()` if the query has none.
+    type Options: Clone + Default + Send + Sync;
+
+    /// Creates a query requesting what `options` describe.
+    ///
+    /// # Parameters
+    ///
+    /// * `options`: The common and game-specific options.
+    fn from_options(options: QueryOptions<Self::Options>) -> Self;
+
+    /// Creates a new instance of the Query with the default options.
     ///
     /// This method is expected to return a `Result` containing the newly created
     /// `Query` or an `Error` if the instantiation fails.
@@ -22,5 +56,111 @@ where
     /// # Returns
     ///
     /// A `Result` containing either a new instance of the Query or an `Error`.
-    fn new() -> Result<Self, Error<Self::E>>;
+    fn new() -> Result<Self, Error<Self::E>> {
+        Ok(Self::from_options(QueryOptions::default()))
+    }
+
+    /// Returns a builder starting from the default options.
+    fn builder() -> QueryBuilder<Self> {
+        QueryBuilder::new()
+    }
+}
+
+/// `QueryBuilder` collects `QueryOptions` before building a query.
+#[derive(Clone, Debug)]
+pub struct QueryBuilder<Q: Query> {
+    options: QueryOptions<Q::Options>,
+}
+
+impl<Q: Query> QueryBuilder<Q> {
+    /// Creates a builder starting from the default options.
+    pub fn new() -> Self {
+        Self::from_options(QueryOptions::default())
+    }
+
+    /// Creates a builder starting from `options`.
+    ///
+    /// # Parameters
+    ///
+    /// * `options`: The options to start from.
+    pub fn from_options(options: QueryOptions<Q::Options>) -> Self {
+        QueryBuilder { options }
+    }
+
+    /// Sets whether the player list is requested.
+    ///
+    /// # Parameters
+    ///
+    /// * `players`: `true` to request the player list.
+    pub fn players(mut self, players: bool) -> Self {
+        self.options.players = players;
+        self
+    }
+
+    /// Sets whether the rules are requested.
+    ///
+    /// # Parameters
+    ///
+    /// * `rules`: `true` to request the rules.
+    pub fn rules(mut self, rules: bool) -> Self {
+        self.options.rules = rules;
+        self
+    }
+
+    /// Replaces the game-specific options.
+    ///
+    /// # Parameters
+    ///
+    /// * `extra`: The game-specific options.
+    pub fn extra(mut self, extra: Q::Options) -> Self {
+        self.options.extra = extra;
+        self
+    }
+
+    /// Adjusts the game-specific options in place.
+    ///
+    /// # Parameters
+    ///
+    /// * `configure`: Modifies the game-specific options.
+    pub fn configure(mut self, configure: impl FnOnce(&mut Q::Options)) -> Self {
+        configure(&mut self.options.extra);
+        self
+    }
+
+    /// Returns the options collected so far.
+    pub fn options(&self) -> &QueryOptions<Q::Options> {
+        &self.options
+    }
+
+    /// Builds the query.
+    pub fn build(self) -> Q {
+        Q::from_options(self.options)
+    }
+}
+
+impl<Q: Query> Default for QueryBuilder<Q> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Conversion into a query, accepted wherever a query is sent.
+///
+/// This is implemented for every `Query` and for its `QueryBuilder`, so `Game::fetch`
+/// takes either a finished query or a builder.
+pub trait IntoQuery<Q> {
+    /// Converts `self` into the query.
+    fn into_query(self) -> Q;
+}
+
+impl<Q: Query> IntoQuery<Q> for Q {
+    fn into_query(self) -> Q {
+        self
+    }
+}
+
+impl<Q: Query> IntoQuery<Q> for QueryBuilder<Q> {
+    fn into_query(self) -> Q {
+        self.build()
+    }
 }
@@ -0,0 +1,44 @@
+use crate::prelude::{Error, Protocol, TimeoutSettings};
+
+use std::future::Future;
+
+use bytes::Bytes;
+
+/// A trait for protocols that expose the raw packets they exchange.
+///
+/// `Protocol` only moves typed queries and responses through its parser. Sending or reading
+/// raw bytes bypasses the parser, so the bytes are neither validated nor framed for the
+/// game, and a stray read can consume a packet the next `receive_response` expects. These
+/// methods therefore live in their own trait, which callers have to import deliberately.
+///
+/// Building blocks that exchange packets outside the parser, such as `Handshake` and
+/// `Correlated`, require it.
+pub trait RawTransport<'a>: Protocol<'a> {
+    /// Send a data packet over the network asynchronously.
+    ///
+    /// This method sends raw bytes and does not involve the associated Query or Response
+    /// types.
+    ///
+    /// # Parameters
+    ///
+    /// * `data`: The raw data to be sent across the network.
+    /// * `timeouts`: The time limits to respect; `TimeoutSettings::write` applies here.
+    fn send(
+        &self,
+        data: &[u8],
+        timeouts: &TimeoutSettings,
+    ) -> impl Future<Output = Result<(), Error<Self::E>>> + Send;
+
+    /// Receive a data packet from the network asynchronously.
+    ///
+    /// This method retrieves raw data from the network and does not involve the associated
+    /// Query or Response types.
+    ///
+    /// # Parameters
+    ///
+    /// * `timeouts`: The time limits to respect; `TimeoutSettings::read` applies here.
+    fn receive(
+        &self,
+        timeouts: &TimeoutSettings,
+    ) -> impl Future<Output = Result<Bytes, Error<Self::E>>> + Send;
+}
@@ -1,11 +1,28 @@
-use crate::prelude::Error;
+use crate::{
+    etag::ETag,
+    memory::HeapSize,
+    prelude::{Error, Player, Provenance, ServerInfo, Value},
+    resolve::Resolution,
+    runtime,
+};
 
-use std::error::Error as StdError;
+use std::{
+    collections::BTreeMap,
+    error::Error as StdError,
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use bytes::Bytes;
+
+#[cfg(feature = "geoip")]
+use crate::geoip::Location;
 
 /// The `Response` trait represents a type that encapsulates the data received from a protocol.
 ///
 /// This trait is generic over the type of Response Error `E`.
-pub trait Response 
+pub trait Response
 where
     Self: Send + Sync + Sized,
 {
@@ -24,6 +41,253 @@ where
     /// A `Result` containing either a new instance of the Response or an `Error`.
     fn new() -> Result<Self, Error<Self::E>>;
 
+    /// Converts the response into the normalized `ServerInfo` model.
+    ///
+    /// This allows consumers to treat the responses of all games uniformly. Fields the game
+    /// does not report should be left at their default value, and game-specific data without
+    /// a common equivalent should be placed in `ServerInfo::extra`.
+    ///
+    /// # Returns
+    ///
+    /// The normalized `ServerInfo` describing the server.
+    fn to_common(&self) -> ServerInfo;
+
+    /// Returns the players contained in the response in the normalized `Player` model.
+    ///
+    /// This allows tools to render player lists without knowing the game-specific response
+    /// layout. The default implementation returns no players, which is appropriate for
+    /// responses that don't carry a player list.
+    ///
+    /// # Returns
+    ///
+    /// The players reported by the server.
+    fn players(&self) -> Vec<Player> {
+        Vec::new()
+    }
+
+    /// Returns which protocol field each normalized value was taken from, for debugging.
+    ///
+    /// Responses that combine or choose between several protocol fields when converting to
+    /// `ServerInfo` should record their choices. Nothing is recorded by default.
+    ///
+    /// # Returns
+    ///
+    /// The `Provenance` of the fields returned by `to_common`.
+    fn provenance(&self) -> Provenance {
+        Provenance::default()
+    }
+
+    /// Returns the data of the response as named values, for generic tools.
+    ///
+    /// This lets the CLI or a web API print any game's response without knowing its type.
+    /// The default implementation returns the fields of `to_common`, and the fields of each
+    /// of `players` under `player_list` if there are any. Responses carrying data beyond the
+    /// common model, e.g. raw protocol fields, should add it.
+    ///
+    /// # Returns
+    ///
+    /// The fields of the response by name.
+    fn fields(&self) -> BTreeMap<String, Value> {
+        let mut fields = self.to_common().fields();
+        let players = self.players();
+
+        if !players.is_empty() {
+            let players = players
+                .iter()
+                .map(|player| Value::Map(player.fields()))
+                .collect();
+
+            fields.insert("player_list".to_string(), Value::List(players));
+        }
+
+        fields
+    }
+
+    /// Returns a validator of what the response reports, for conditional queries.
+    ///
+    /// The default implementation hashes the normalized models, so responses that are
+    /// equal for every consumer get equal tags.
+    ///
+    /// # Returns
+    ///
+    /// The `ETag` of `to_common` and `players`.
+    fn etag(&self) -> ETag {
+        ETag::of(&self.to_common(), &self.players())
+    }
+
+    /// Returns the approximate heap memory owned by the response, for memory budgets.
+    ///
+    /// The default implementation estimates it from the normalized models, which builds
+    /// them first. Responses that implement `HeapSize` should return its estimate instead.
+    fn heap_size(&self) -> usize {
+        self.to_common().heap_size() + self.players().heap_size()
+    }
+
     // Add more response specific methods
     // Keep in mind this is about managing response data, not its serialization or deserialization
 }
+
+/// `ResponseMeta` describes how a response was received.
+///
+/// `Game::fetch_with` attaches it to every `Fetched` response, so monitoring tools can
+/// report round-trip times and keep the raw bytes without querying the server again. The
+/// latency and arrival time are known wherever the runtime has a clock; the packets are
+/// only available from protocols that record them through `Protocol::meter`, which the UDP
+/// and TCP transports do. Responses fetched by host name also report how the name was
+/// resolved.
+#[derive(Clone, Debug)]
+pub struct ResponseMeta {
+    latency: Duration,
+    received_at: Option<Instant>,
+    packets: Vec<Bytes>,
+    address: Option<SocketAddr>,
+    resolution: Option<Resolution>,
+    #[cfg(feature = "geoip")]
+    location: Option<Location>,
+}
+
+impl ResponseMeta {
+    /// Creates the metadata of a response that has just been received.
+    ///
+    /// # Parameters
+    ///
+    /// * `latency`: The time between sending the query and receiving the response.
+    pub fn new(latency: Duration) -> Self {
+        ResponseMeta {
+            latency,
+            received_at: runtime::try_now(),
+            packets: Vec::new(),
+            address: None,
+            resolution: None,
+            #[cfg(feature = "geoip")]
+            location: None,
+        }
+    }
+
+    /// Sets the packets the response was parsed from.
+    ///
+    /// # Parameters
+    ///
+    /// * `packets`: The packets, in the order they were received.
+    pub fn with_packets(mut self, packets: Vec<Bytes>) -> Self {
+        self.packets = packets;
+        self
+    }
+
+    /// Sets the address the response was received from.
+    pub fn with_address(mut self, address: SocketAddr) -> Self {
+        self.address = Some(address);
+        self
+    }
+
+    /// Sets how the host name of the server was resolved.
+    pub fn with_resolution(mut self, resolution: Resolution) -> Self {
+        self.resolution = Some(resolution);
+        self
+    }
+
+    /// Sets where the server is located.
+    #[cfg(feature = "geoip")]
+    pub fn with_location(mut self, location: Location) -> Self {
+        self.set_location(location);
+        self
+    }
+
+    #[cfg(feature = "geoip")]
+    pub(crate) fn set_location(&mut self, location: Location) {
+        self.location = Some(location);
+    }
+
+    /// Returns the address the response was received from, if it is known.
+    ///
+    /// `Game::fetch_with` and `Session` always set it.
+    pub fn address(&self) -> Option<SocketAddr> {
+        self.address
+    }
+
+    /// Returns how the host name of the server was resolved, if the response was fetched
+    /// by host name.
+    pub fn resolution(&self) -> Option<&Resolution> {
+        self.resolution.as_ref()
+    }
+
+    /// Returns where the server is located, if a `GeoIp` enriched the response.
+    #[cfg(feature = "geoip")]
+    pub fn location(&self) -> Option<&Location> {
+        self.location.as_ref()
+    }
+
+    /// Returns the time between sending the query and receiving the response.
+    pub fn latency(&self) -> Duration {
+        self.latency
+    }
+
+    /// Returns when the response was received, or `None` on a host without a clock, such
+    /// as `wasm32-unknown-unknown`.
+    pub fn received_at(&self) -> Option<Instant> {
+        self.received_at
+    }
+
+    /// Returns the number of bytes the response was parsed from.
+    pub fn bytes_received(&self) -> usize {
+        self.packets.iter().map(Bytes::len).sum()
+    }
+
+    /// Returns the number of packets the response was parsed from.
+    pub fn packet_count(&self) -> usize {
+        self.packets.len()
+    }
+
+    /// Returns the raw packets the response was parsed from, in the order they were
+    /// received.
+    ///
+    /// This is empty if the protocol does not record its packets.
+    pub fn raw(&self) -> &[Bytes] {
+        &self.packets
+    }
+}
+
+/// `Meter` records the packets a protocol receives for a response.
+///
+/// Protocols record every packet they read and return the meter from `Protocol::meter`.
+/// `Game::fetch_with` starts the meter before sending the query and collects the packets
+/// into the `ResponseMeta` once the response has been parsed. Packets read while the meter
+/// is stopped, such as those of a long-lived connection driven without `fetch`, are not
+/// kept. Recording a packet only clones its `Bytes` handle. Clones of a meter share their
+/// packets.
+#[derive(Clone, Debug, Default)]
+pub struct Meter {
+    packets: Arc<Mutex<Option<Vec<Bytes>>>>,
+}
+
+impl Meter {
+    /// Creates a stopped meter.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Discards any recorded packets and starts recording.
+    pub fn start(&self) {
+        *self.packets.lock().expect("meter lock poisoned") = Some(Vec::new());
+    }
+
+    /// Records a received packet if the meter is started.
+    ///
+    /// # Parameters
+    ///
+    /// * `packet`: The packet as it was received.
+    pub fn record(&self, packet: &Bytes) {
+        if let Some(packets) = self.packets.lock().expect("meter lock poisoned").as_mut() {
+            packets.push(packet.clone());
+        }
+    }
+
+    /// Stops recording and returns the recorded packets.
+    pub fn take(&self) -> Vec<Bytes> {
+        self.packets
+            .lock()
+            .expect("meter lock poisoned")
+            .take()
+            .unwrap_or_default()
+    }
+}
@@ -0,0 +1,197 @@
+use crate::{
+    prelude::{Error, Protocol, TimeoutSettings},
+    runtime,
+    standards::{
+        game::{FetchResult, Fetched},
+        query::IntoQuery,
+        response::ResponseMeta,
+    },
+    timeout::with_timeout,
+};
+
+use std::{marker::PhantomData, net::SocketAddr};
+
+/// A `Session` keeps a `Protocol` connected to one server across several queries.
+///
+/// `Game::fetch` connects, performs the handshake and disconnects for every query. A
+/// session does this once, so related queries such as info, players and rules share one
+/// connection, and protocols whose servers only answer within a connection, such as RCON
+/// or TeamSpeak ServerQuery, can be queried at all.
+///
+/// Each query runs under the protocol's `RetryPolicy`, retrying over the same connection,
+/// and is bounded by the session's `TimeoutSettings`, with `TimeoutSettings::overall`
+/// applying to each query rather than to the session. Queries take the session mutably,
+/// so responses can't be mixed up between concurrent queries. If the connection breaks,
+/// `reconnect` establishes it again.
+///
+/// Dropping a session without calling `disconnect` schedules a best-effort disconnect
+/// through `Protocol::schedule_disconnect`, like dropping a `ConnectionGuard`.
+pub struct Session<'a, P: Protocol<'a>> {
+    protocol: P,
+    address: SocketAddr,
+    timeouts: TimeoutSettings,
+    connected: bool,
+    _marker: PhantomData<&'a ()>,
+}
+
+impl<'a, P: Protocol<'a>> Session<'a, P> {
+    /// Connects `protocol` to a server and performs its handshake.
+    ///
+    /// # Parameters
+    ///
+    /// * `protocol`: The protocol to keep connected.
+    /// * `address`: The address of the server.
+    /// * `timeouts`: The time limits for connecting and for each query. The overall limit
+    ///   applies to connecting and the handshake together.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing either the connected session or an `Error`.
+    pub async fn open(
+        protocol: P,
+        address: SocketAddr,
+        timeouts: TimeoutSettings,
+    ) -> Result<Self, Error<P::E>> {
+        let mut session = Session {
+            protocol,
+            address,
+            timeouts,
+            connected: false,
+            _marker: PhantomData,
+        };
+
+        session.establish().await?;
+
+        Ok(session)
+    }
+
+    /// Returns the protocol of the session.
+    pub fn protocol(&self) -> &P {
+        &self.protocol
+    }
+
+    /// Returns the address of the server.
+    pub fn address(&self) -> SocketAddr {
+        self.address
+    }
+
+    /// Returns the time limits applied to each query.
+    pub fn timeouts(&self) -> &TimeoutSettings {
+        &self.timeouts
+    }
+
+    /// Returns `true` unless the session was disconnected or failed to reconnect.
+    pub fn is_connected(&self) -> bool {
+        self.connected
+    }
+
+    /// Sends a query over the session's connection and receives its response.
+    ///
+    /// # Parameters
+    ///
+    /// * `query`: The query to send to the server, or a `QueryBuilder` for it.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing either the parsed server response, or an `Error`. The
+    /// session stays usable after a failed query.
+    pub async fn query(&mut self, query: impl IntoQuery<P::Q>) -> FetchResult<P::R, P::E> {
+        let query = query.into_query();
+        let (protocol, address, timeouts) = (&self.protocol, self.address, &self.timeouts);
+        let policy = protocol.retry_policy();
+
+        let exchange = policy.run(|_| {
+            let query = query.clone();
+
+            async move {
+                if let Some(meter) = protocol.meter() {
+                    meter.start();
+                }
+
+                let sent_at = runtime::try_now();
+                instrument!(protocol.send_query(query, timeouts), "send_query").await?;
+
+                let response =
+                    instrument!(protocol.receive_response(timeouts), "receive_response").await?;
+
+                let mut meta = ResponseMeta::new(runtime::elapsed(sent_at)).with_address(address);
+
+                if let Some(meter) = protocol.meter() {
+                    meta = meta.with_packets(meter.take());
+                }
+
+                Ok(Fetched::new(response, meta))
+            }
+        });
+
+        instrument!(
+            with_timeout(timeouts.overall, "Session query timed out", exchange),
+            "session_query",
+            %address,
+        )
+        .await
+    }
+
+    /// Disconnects and connects again, performing the handshake anew.
+    ///
+    /// A failure to disconnect the broken connection is ignored, since the connection is
+    /// replaced either way.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` which is `Ok` once the session is connected again, or an `Error`.
+    pub async fn reconnect(&mut self) -> Result<(), Error<P::E>> {
+        if self.connected {
+            self.connected = false;
+
+            if let Err(_err) = self.protocol.disconnect().await {
+                trace_event!(debug, error = ?_err, "disconnect failed before reconnecting");
+            }
+        }
+
+        self.establish().await
+    }
+
+    /// Disconnects from the server and ends the session.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` which is `Ok` if the protocol disconnected cleanly, or an `Error`.
+    pub async fn disconnect(mut self) -> Result<(), Error<P::E>> {
+        if !self.connected {
+            return Ok(());
+        }
+
+        self.connected = false;
+        self.protocol.disconnect().await
+    }
+
+    /// Connects the protocol and performs the handshake within the overall time limit.
+    async fn establish(&mut self) -> Result<(), Error<P::E>> {
+        let (protocol, address, timeouts) = (&self.protocol, self.address, &self.timeouts);
+
+        let establish = async {
+            let connection = instrument!(protocol.connect(address, timeouts), "connect").await?;
+            instrument!(connection.handshake(address, timeouts), "handshake").await?;
+
+            // The session disconnects the protocol itself from now on.
+            connection.release();
+
+            Ok(())
+        };
+
+        with_timeout(timeouts.overall, "Session connect timed out", establish).await?;
+        self.connected = true;
+
+        Ok(())
+    }
+}
+
+impl<'a, P: Protocol<'a>> Drop for Session<'a, P> {
+    /// Schedules a disconnect if the session was not explicitly disconnected.
+    fn drop(&mut self) {
+        if self.connected {
+            self.protocol.schedule_disconnect();
+        }
+    }
+}
@@ -0,0 +1,328 @@
+//! Sharing the results of one `Poller` among many consumers.
+//!
+//! A web UI, an alerting rule and a logger often watch the same servers. Polling them once
+//! per consumer multiplies the traffic, so a poller publishes every result to a `Feed`
+//! instead, and each consumer subscribes to the servers it cares about. With the `stream`
+//! feature, subscriptions implement `futures_core::Stream`.
+
+use crate::prelude::ServerInfo;
+
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt::{Debug, Formatter, Result as FmtResult},
+    future::poll_fn,
+    hash::Hash,
+    sync::{Arc, Mutex, MutexGuard},
+    task::{Context, Poll, Waker},
+    time::Instant,
+};
+
+/// The default number of snapshots a subscription holds before it starts missing some.
+const DEFAULT_CAPACITY: usize = 64;
+
+/// The state of a server after one of its polls.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Snapshot<T> {
+    /// The server.
+    pub target: T,
+    /// What the server reported, or `None` if the poll failed.
+    pub info: Option<ServerInfo>,
+    /// Why the poll failed, if it did.
+    pub error: Option<String>,
+    /// Whether the server is reported online, after the poller's debounce.
+    pub online: bool,
+    /// When the poll completed, on the poller's clock.
+    pub polled_at: Instant,
+}
+
+/// A subscriber and the snapshots it has yet to take.
+struct Subscriber<T> {
+    /// The server the subscriber watches, or `None` for every server.
+    target: Option<T>,
+    queue: VecDeque<Snapshot<T>>,
+    missed: u64,
+    waker: Option<Waker>,
+}
+
+struct Shared<T> {
+    subscribers: HashMap<u64, Subscriber<T>>,
+    /// The last snapshot of every server, handed to new subscribers.
+    latest: HashMap<T, Snapshot<T>>,
+    next_id: u64,
+    closed: bool,
+}
+
+/// `Feed` fans the snapshots of a poller out to its subscribers.
+///
+/// Every subscription holds up to `capacity` snapshots. A subscriber that falls further
+/// behind misses the oldest ones instead of slowing the poller down, and can tell how many
+/// through `Subscription::missed`. New subscriptions start with the last snapshot of each
+/// server they watch, so a consumer joining late still sees the current state.
+///
+/// Clones share their subscribers. Once the feed is closed, e.g. because its poller was
+/// dropped, subscriptions yield the snapshots they hold and then end.
+pub struct Feed<T> {
+    capacity: usize,
+    shared: Arc<Mutex<Shared<T>>>,
+}
+
+impl<T> Feed<T>
+where
+    T: Clone + Eq + Hash,
+{
+    /// Creates a feed whose subscriptions hold up to 64 snapshots.
+    pub fn new() -> Self {
+        Feed {
+            capacity: DEFAULT_CAPACITY,
+            shared: Arc::new(Mutex::new(Shared {
+                subscribers: HashMap::new(),
+                latest: HashMap::new(),
+                next_id: 0,
+                closed: false,
+            })),
+        }
+    }
+
+    /// Sets how many snapshots each subscription holds before it starts missing some.
+    ///
+    /// # Parameters
+    ///
+    /// * `capacity`: The number of snapshots. `0` is treated as `1`.
+    pub fn with_capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity.max(1);
+        self
+    }
+
+    /// Subscribes to the snapshots of one server.
+    ///
+    /// # Parameters
+    ///
+    /// * `target`: The server to watch.
+    pub fn subscribe(&self, target: T) -> Subscription<T> {
+        self.add(Some(target))
+    }
+
+    /// Subscribes to the snapshots of every server.
+    pub fn subscribe_all(&self) -> Subscription<T> {
+        self.add(None)
+    }
+
+    /// Hands a snapshot to every subscription watching its server.
+    ///
+    /// Pollers publish their results themselves once added with `Poller::with_feed`.
+    ///
+    /// # Parameters
+    ///
+    /// * `snapshot`: The state of a server after a poll.
+    pub fn publish(&self, snapshot: Snapshot<T>) {
+        let mut shared = self.lock();
+
+        if shared.closed {
+            return;
+        }
+
+        let mut wakers = Vec::new();
+
+        for subscriber in shared.subscribers.values_mut() {
+            if subscriber
+                .target
+                .as_ref()
+                .is_some_and(|target| *target != snapshot.target)
+            {
+                continue;
+            }
+
+            if subscriber.queue.len() >= self.capacity {
+                subscriber.queue.pop_front();
+                subscriber.missed += 1;
+            }
+
+            subscriber.queue.push_back(snapshot.clone());
+            wakers.extend(subscriber.waker.take());
+        }
+
+        shared.latest.insert(snapshot.target.clone(), snapshot);
+        drop(shared);
+
+        for waker in wakers {
+            waker.wake();
+        }
+    }
+
+    /// Returns the last snapshot of a server, if it was polled.
+    ///
+    /// # Parameters
+    ///
+    /// * `target`: The server.
+    pub fn latest(&self, target: &T) -> Option<Snapshot<T>> {
+        self.lock().latest.get(target).cloned()
+    }
+
+    /// Returns the number of open subscriptions.
+    pub fn subscribers(&self) -> usize {
+        self.lock().subscribers.len()
+    }
+
+    /// Closes the feed, ending every subscription once it has yielded what it holds.
+    pub fn close(&self) {
+        let mut shared = self.lock();
+        shared.closed = true;
+
+        let wakers: Vec<Waker> = shared
+            .subscribers
+            .values_mut()
+            .filter_map(|subscriber| subscriber.waker.take())
+            .collect();
+
+        drop(shared);
+
+        for waker in wakers {
+            waker.wake();
+        }
+    }
+
+    /// Returns `true` once the feed was closed.
+    pub fn is_closed(&self) -> bool {
+        self.lock().closed
+    }
+
+    /// Adds a subscriber, starting it with the last snapshots it would have received.
+    fn add(&self, target: Option<T>) -> Subscription<T> {
+        let mut shared = self.lock();
+        let id = shared.next_id;
+        shared.next_id += 1;
+
+        let queue: VecDeque<Snapshot<T>> = match &target {
+            Some(target) => shared.latest.get(target).cloned().into_iter().collect(),
+            None => shared.latest.values().cloned().collect(),
+        };
+
+        shared.subscribers.insert(
+            id,
+            Subscriber {
+                target,
+                queue,
+                missed: 0,
+                waker: None,
+            },
+        );
+
+        Subscription {
+            id,
+            shared: self.shared.clone(),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Shared<T>> {
+        self.shared.lock().expect("feed lock poisoned")
+    }
+}
+
+impl<T> Clone for Feed<T> {
+    fn clone(&self) -> Self {
+        Feed {
+            capacity: self.capacity,
+            shared: self.shared.clone(),
+        }
+    }
+}
+
+impl<T: Clone + Eq + Hash> Default for Feed<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Debug for Feed<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        let shared = self.shared.lock().expect("feed lock poisoned");
+
+        f.debug_struct("Feed")
+            .field("capacity", &self.capacity)
+            .field("subscribers", &shared.subscribers.len())
+            .field("closed", &shared.closed)
+            .finish()
+    }
+}
+
+/// `Subscription` yields the snapshots of the servers it watches.
+///
+/// Dropping the subscription unsubscribes it.
+pub struct Subscription<T> {
+    id: u64,
+    shared: Arc<Mutex<Shared<T>>>,
+}
+
+impl<T> Subscription<T> {
+    /// Waits for the next snapshot.
+    ///
+    /// # Returns
+    ///
+    /// The snapshot, or `None` once the feed was closed and every held snapshot taken.
+    pub async fn next(&mut self) -> Option<Snapshot<T>> {
+        poll_fn(|cx| self.poll_next(cx)).await
+    }
+
+    /// Takes the next snapshot if one is held.
+    ///
+    /// # Returns
+    ///
+    /// `Poll::Ready(Some(_))` with the next snapshot, `Poll::Ready(None)` once the feed was
+    /// closed and the subscription is empty, or `Poll::Pending` while waiting.
+    pub fn poll_next(&mut self, cx: &mut Context<'_>) -> Poll<Option<Snapshot<T>>> {
+        let mut shared = self.lock();
+        let closed = shared.closed;
+        let subscriber = shared
+            .subscribers
+            .get_mut(&self.id)
+            .expect("subscriptions stay registered until dropped");
+
+        if let Some(snapshot) = subscriber.queue.pop_front() {
+            return Poll::Ready(Some(snapshot));
+        }
+
+        if closed {
+            return Poll::Ready(None);
+        }
+
+        subscriber.waker = Some(cx.waker().clone());
+        Poll::Pending
+    }
+
+    /// Returns the number of snapshots dropped because the subscription was full.
+    pub fn missed(&self) -> u64 {
+        self.lock()
+            .subscribers
+            .get(&self.id)
+            .map_or(0, |subscriber| subscriber.missed)
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Shared<T>> {
+        self.shared.lock().expect("feed lock poisoned")
+    }
+}
+
+impl<T> Drop for Subscription<T> {
+    fn drop(&mut self) {
+        if let Ok(mut shared) = self.shared.lock() {
+            shared.subscribers.remove(&self.id);
+        }
+    }
+}
+
+impl<T> Debug for Subscription<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.debug_struct("Subscription")
+            .field("id", &self.id)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(feature = "stream")]
+impl<T> futures_core::Stream for Subscription<T> {
+    type Item = Snapshot<T>;
+
+    fn poll_next(self: std::pin::Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        Subscription::poll_next(self.get_mut(), cx)
+    }
+}
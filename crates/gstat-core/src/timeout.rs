@@ -0,0 +1,110 @@
+use crate::{
+    prelude::{Error, ErrorDetail, ErrorKind},
+    runtime,
+};
+
+use std::{
+    future::Future,
+    time::{Duration, Instant},
+};
+
+/// `TimeoutSettings` bounds how long the individual steps of a query may take.
+///
+/// Each limit is optional; `None` means the step may block indefinitely. The settings are
+/// passed to every `Protocol` operation, and `Game::fetch_with` additionally enforces the
+/// `overall` limit across the whole exchange.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TimeoutSettings {
+    /// The limit for establishing a connection.
+    pub connect: Option<Duration>,
+    /// The limit for receiving a single response or packet.
+    pub read: Option<Duration>,
+    /// The limit for sending a single query or packet.
+    pub write: Option<Duration>,
+    /// The limit for the whole exchange, from connecting to disconnecting.
+    pub overall: Option<Duration>,
+}
+
+impl TimeoutSettings {
+    /// Creates settings that apply the same limit to every step and to the whole exchange.
+    ///
+    /// # Parameters
+    ///
+    /// * `limit`: The limit applied to every step.
+    pub fn uniform(limit: Duration) -> Self {
+        TimeoutSettings {
+            connect: Some(limit),
+            read: Some(limit),
+            write: Some(limit),
+            overall: Some(limit),
+        }
+    }
+
+    /// Sets the limit for establishing a connection.
+    pub fn with_connect(mut self, limit: Duration) -> Self {
+        self.connect = Some(limit);
+        self
+    }
+
+    /// Sets the limit for receiving a single response or packet.
+    pub fn with_read(mut self, limit: Duration) -> Self {
+        self.read = Some(limit);
+        self
+    }
+
+    /// Sets the limit for sending a single query or packet.
+    pub fn with_write(mut self, limit: Duration) -> Self {
+        self.write = Some(limit);
+        self
+    }
+
+    /// Sets the limit for the whole exchange.
+    pub fn with_overall(mut self, limit: Duration) -> Self {
+        self.overall = Some(limit);
+        self
+    }
+
+    /// Bounds the whole exchange by a deadline, e.g. the deadline of the scan a query is
+    /// part of.
+    ///
+    /// The overall limit becomes the time left until `deadline`, unless the current limit
+    /// is shorter. A deadline that has passed leaves no time at all.
+    ///
+    /// # Parameters
+    ///
+    /// * `deadline`: The instant by which the exchange must be complete.
+    pub fn until(mut self, deadline: Instant) -> Self {
+        let left = deadline.saturating_duration_since(runtime::now());
+
+        self.overall = Some(self.overall.map_or(left, |limit| limit.min(left)));
+        self
+    }
+}
+
+/// Runs `future` to completion, failing with a `ProtocolError` of kind `ErrorKind::Timeout`
+/// if `limit` elapses first.
+///
+/// # Parameters
+///
+/// * `limit`: The optional time limit. `None` waits indefinitely.
+/// * `message`: The error message used if the limit elapses.
+/// * `future`: The operation to run.
+///
+/// # Returns
+///
+/// A `Result` containing either the output of the operation or an `Error`.
+pub async fn with_timeout<T, E, F>(
+    limit: Option<Duration>,
+    message: &str,
+    future: F,
+) -> Result<T, Error<E>>
+where
+    F: Future<Output = Result<T, Error<E>>>,
+{
+    match limit {
+        Some(limit) => runtime::timeout(limit, future).await.map_err(|_| {
+            Error::ProtocolError(ErrorDetail::new(message, None).with_kind(ErrorKind::Timeout))
+        })?,
+        None => future.await,
+    }
+}
@@ -0,0 +1,28 @@
+//! Internal helpers for the optional `tracing` instrumentation.
+//!
+//! The macros expand to nothing unless the `tracing` feature is enabled, and always with
+//! the `silent` feature, so instrumented code needs no `cfg` attributes of its own. Values
+//! referenced only by a macro should be cheap to compute, since they are still evaluated
+//! without the feature.
+
+/// Emits a `tracing` event at the given level.
+macro_rules! trace_event {
+    ($level:ident, $($arg:tt)+) => {
+        #[cfg(all(feature = "tracing", not(feature = "silent")))]
+        {
+            tracing::$level!($($arg)+);
+        }
+    };
+}
+
+/// Runs a future inside a `DEBUG` span.
+macro_rules! instrument {
+    ($future:expr, $($span:tt)+) => {{
+        let future = $future;
+
+        #[cfg(all(feature = "tracing", not(feature = "silent")))]
+        let future = tracing::Instrument::instrument(future, tracing::debug_span!($($span)+));
+
+        future
+    }};
+}
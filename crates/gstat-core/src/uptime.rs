@@ -0,0 +1,339 @@
+//! Tracking the availability of polled servers and alerting on thresholds.
+//!
+//! `UptimeMonitor` reads the snapshots a `Poller` publishes to its `Feed`, keeps the
+//! outcome of every recent poll of each server and evaluates `Alert`s on them: a server
+//! down for several polls in a row, a player count staying below a floor, or an
+//! availability dropping below a ratio over a sliding window. Alerts are reported as
+//! `UptimeEvent`s when they start and stop firing, and the figures behind them can be
+//! read at any time, e.g. to show the availability of each server on a status page.
+
+use crate::subscribe::{Snapshot, Subscription};
+
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt::{Debug, Formatter, Result as FmtResult},
+    future::poll_fn,
+    hash::Hash,
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+
+/// How long the outcomes of polls are kept when no alert needs them longer.
+const DEFAULT_RETENTION: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// A condition on the recent polls of a server.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Alert {
+    /// The server failed to answer this many polls in a row.
+    Down {
+        /// The failed polls in a row. `0` is treated as `1`.
+        polls: u32,
+    },
+    /// The server answered this many polls in a row with fewer players than the floor.
+    PlayersBelow {
+        /// The floor.
+        players: u32,
+        /// The answered polls in a row. `0` is treated as `1`.
+        polls: u32,
+    },
+    /// The share of answered polls over the window is below the ratio.
+    AvailabilityBelow {
+        /// The ratio, between 0 and 1.
+        ratio: f64,
+        /// The window, ending at the last poll of the server.
+        window: Duration,
+    },
+}
+
+/// An alert starting or stopping to fire for a server.
+#[derive(Clone, Debug, PartialEq)]
+pub enum UptimeEvent<T> {
+    /// The condition of the alert is now met.
+    Firing {
+        /// The server.
+        target: T,
+        /// The alert.
+        alert: Alert,
+    },
+    /// The condition of the alert is no longer met.
+    Resolved {
+        /// The server.
+        target: T,
+        /// The alert.
+        alert: Alert,
+    },
+}
+
+impl<T> UptimeEvent<T> {
+    /// Returns the server the event is about.
+    pub fn target(&self) -> &T {
+        match self {
+            UptimeEvent::Firing { target, .. } | UptimeEvent::Resolved { target, .. } => target,
+        }
+    }
+
+    /// Returns the alert the event is about.
+    pub fn alert(&self) -> &Alert {
+        match self {
+            UptimeEvent::Firing { alert, .. } | UptimeEvent::Resolved { alert, .. } => alert,
+        }
+    }
+}
+
+/// What is known about a monitored server.
+#[derive(Default)]
+struct Server {
+    /// When the server was polled and whether it answered, oldest first.
+    history: VecDeque<(Instant, bool)>,
+    /// The failed polls in a row.
+    failures: u32,
+    /// The answered polls in a row, by the alert they were counted for, below its floor.
+    low: Vec<u32>,
+    /// Whether each alert is firing.
+    firing: Vec<bool>,
+}
+
+impl Server {
+    fn availability(&self, window: Duration) -> Option<f64> {
+        let &(last, _) = self.history.back()?;
+        let (answered, polls) = self
+            .history
+            .iter()
+            .rev()
+            .take_while(|(at, _)| last.saturating_duration_since(*at) < window)
+            .fold((0u32, 0u32), |(answered, polls), &(_, ok)| {
+                (answered + u32::from(ok), polls + 1)
+            });
+
+        Some(f64::from(answered) / f64::from(polls))
+    }
+}
+
+/// `UptimeMonitor` evaluates alerts on the polls of servers.
+///
+/// Feed it snapshots with `observe`, or hand it a `Subscription` and await `next` for its
+/// events. Availability only counts whether a poll was answered, regardless of the debounce
+/// of the poller, so it matches what players experienced.
+pub struct UptimeMonitor<T> {
+    alerts: Vec<Alert>,
+    retention: Duration,
+    servers: HashMap<T, Server>,
+    subscription: Option<Subscription<T>>,
+    events: VecDeque<UptimeEvent<T>>,
+}
+
+impl<T> UptimeMonitor<T>
+where
+    T: Clone + Eq + Hash,
+{
+    /// Creates a monitor without alerts, keeping a day of polls.
+    pub fn new() -> Self {
+        UptimeMonitor {
+            alerts: Vec::new(),
+            retention: DEFAULT_RETENTION,
+            servers: HashMap::new(),
+            subscription: None,
+            events: VecDeque::new(),
+        }
+    }
+
+    /// Adds an alert, evaluated for every server.
+    ///
+    /// The polls of an `AvailabilityBelow` alert's window are kept even beyond the
+    /// retention.
+    pub fn with_alert(mut self, alert: Alert) -> Self {
+        let alert = match alert {
+            Alert::Down { polls } => Alert::Down {
+                polls: polls.max(1),
+            },
+            Alert::PlayersBelow { players, polls } => Alert::PlayersBelow {
+                players,
+                polls: polls.max(1),
+            },
+            Alert::AvailabilityBelow { ratio, window } => {
+                self.retention = self.retention.max(window);
+                Alert::AvailabilityBelow { ratio, window }
+            }
+        };
+
+        self.alerts.push(alert);
+        self
+    }
+
+    /// Sets how long the outcomes of polls are kept for `availability`.
+    ///
+    /// # Parameters
+    ///
+    /// * `retention`: The longest window availability is asked for.
+    pub fn with_retention(mut self, retention: Duration) -> Self {
+        self.retention = retention;
+        self
+    }
+
+    /// Sets the subscription `next` reads snapshots from.
+    ///
+    /// # Parameters
+    ///
+    /// * `subscription`: A subscription to the feed of a poller, e.g. from
+    ///   `Feed::subscribe_all`.
+    pub fn with_subscription(mut self, subscription: Subscription<T>) -> Self {
+        self.subscription = Some(subscription);
+        self
+    }
+
+    /// Records the outcome of a poll and evaluates the alerts of its server.
+    ///
+    /// # Parameters
+    ///
+    /// * `snapshot`: The state of a server after a poll.
+    ///
+    /// # Returns
+    ///
+    /// The alerts that started or stopped firing for the server.
+    pub fn observe(&mut self, snapshot: &Snapshot<T>) -> Vec<UptimeEvent<T>> {
+        let retention = self.retention;
+        let server = self.servers.entry(snapshot.target.clone()).or_default();
+        let at = snapshot.polled_at;
+        let answered = snapshot.info.is_some();
+
+        server.history.push_back((at, answered));
+
+        while server
+            .history
+            .front()
+            .is_some_and(|(polled_at, _)| at.saturating_duration_since(*polled_at) > retention)
+        {
+            server.history.pop_front();
+        }
+
+        server.failures = if answered { 0 } else { server.failures + 1 };
+        server.low.resize(self.alerts.len(), 0);
+        server.firing.resize(self.alerts.len(), false);
+
+        let mut events = Vec::new();
+
+        for (index, alert) in self.alerts.iter().enumerate() {
+            let firing = match *alert {
+                Alert::Down { polls } => server.failures >= polls,
+                Alert::PlayersBelow { players, polls } => match &snapshot.info {
+                    Some(info) if info.players < players => {
+                        server.low[index] += 1;
+                        server.low[index] >= polls
+                    }
+                    Some(_) => {
+                        server.low[index] = 0;
+                        false
+                    }
+                    // The player count of a silent server is unknown, so the alert keeps
+                    // its state until the server answers.
+                    None => server.firing[index],
+                },
+                Alert::AvailabilityBelow { ratio, window } => server
+                    .availability(window)
+                    .is_some_and(|availability| availability < ratio),
+            };
+
+            if firing == server.firing[index] {
+                continue;
+            }
+
+            server.firing[index] = firing;
+
+            let target = snapshot.target.clone();
+            let alert = *alert;
+
+            events.push(match firing {
+                true => UptimeEvent::Firing { target, alert },
+                false => UptimeEvent::Resolved { target, alert },
+            });
+        }
+
+        events
+    }
+
+    /// Returns the failed polls in a row of a server.
+    pub fn consecutive_failures(&self, target: &T) -> u32 {
+        self.servers.get(target).map_or(0, |server| server.failures)
+    }
+
+    /// Returns the share of the polls of a server that were answered, over a window.
+    ///
+    /// # Parameters
+    ///
+    /// * `target`: The server.
+    /// * `window`: The window, ending at the last poll of the server. Polls older than the
+    ///   retention are no longer counted.
+    ///
+    /// # Returns
+    ///
+    /// The availability between 0 and 1, or `None` if the server was never polled.
+    pub fn availability(&self, target: &T, window: Duration) -> Option<f64> {
+        self.servers.get(target)?.availability(window)
+    }
+
+    /// Returns the alerts firing for a server.
+    pub fn firing(&self, target: &T) -> Vec<Alert> {
+        let Some(server) = self.servers.get(target) else {
+            return Vec::new();
+        };
+
+        self.alerts
+            .iter()
+            .zip(&server.firing)
+            .filter(|(_, firing)| **firing)
+            .map(|(alert, _)| *alert)
+            .collect()
+    }
+
+    /// Waits for the next alert to start or stop firing.
+    ///
+    /// # Returns
+    ///
+    /// The event, or `None` once the subscription ended or if the monitor has none.
+    pub async fn next(&mut self) -> Option<UptimeEvent<T>> {
+        poll_fn(|cx| self.poll_next(cx)).await
+    }
+
+    /// Observes the snapshots the subscription holds until one of them produces an event.
+    ///
+    /// # Returns
+    ///
+    /// `Poll::Ready(Some(_))` with the next event, `Poll::Ready(None)` once the subscription
+    /// ended or if the monitor has none, or `Poll::Pending` while waiting.
+    pub fn poll_next(&mut self, cx: &mut Context<'_>) -> Poll<Option<UptimeEvent<T>>> {
+        loop {
+            if let Some(event) = self.events.pop_front() {
+                return Poll::Ready(Some(event));
+            }
+
+            let Some(subscription) = &mut self.subscription else {
+                return Poll::Ready(None);
+            };
+
+            match subscription.poll_next(cx) {
+                Poll::Ready(Some(snapshot)) => {
+                    let events = self.observe(&snapshot);
+                    self.events.extend(events);
+                }
+                Poll::Ready(None) => return Poll::Ready(None),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
+
+impl<T: Clone + Eq + Hash> Default for UptimeMonitor<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Debug for UptimeMonitor<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.debug_struct("UptimeMonitor")
+            .field("alerts", &self.alerts)
+            .field("retention", &self.retention)
+            .field("servers", &self.servers.len())
+            .finish_non_exhaustive()
+    }
+}
@@ -0,0 +1,48 @@
+//! Normalizing access restrictions into an `AccessPolicy`.
+
+use gstat_core::prelude::{AccessPolicy, ServerInfo};
+
+#[test]
+fn the_most_restrictive_signal_wins() {
+    assert_eq!(
+        AccessPolicy::from_signals(false, false, false),
+        AccessPolicy::Open
+    );
+    assert_eq!(
+        AccessPolicy::from_signals(true, false, false),
+        AccessPolicy::Password
+    );
+    assert_eq!(
+        AccessPolicy::from_signals(true, true, false),
+        AccessPolicy::Whitelist
+    );
+    assert_eq!(
+        AccessPolicy::from_signals(true, true, true),
+        AccessPolicy::InviteOnly
+    );
+
+    assert_eq!(
+        AccessPolicy::Password.max(AccessPolicy::Whitelist),
+        AccessPolicy::Whitelist
+    );
+    assert!(ServerInfo::default().access.is_open());
+}
+
+#[test]
+fn policies_parse_from_the_terms_games_use() {
+    for policy in [
+        AccessPolicy::Open,
+        AccessPolicy::Password,
+        AccessPolicy::Whitelist,
+        AccessPolicy::InviteOnly,
+    ] {
+        assert_eq!(policy.to_string().parse::<AccessPolicy>(), Ok(policy));
+    }
+
+    assert_eq!("Private".parse(), Ok(AccessPolicy::InviteOnly));
+    assert_eq!("passworded".parse(), Ok(AccessPolicy::Password));
+    assert_eq!(
+        "members".parse::<AccessPolicy>().unwrap_err().to_string(),
+        "unknown access policy `members`"
+    );
+}
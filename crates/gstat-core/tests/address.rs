@@ -0,0 +1,54 @@
+//! Parsing and deriving `ServerAddress`es.
+
+use gstat_core::{
+    address::{InvalidReason, ServerAddress},
+    registry,
+    schedule::{Phase, Schedule},
+};
+
+use std::{net::SocketAddr, time::Duration};
+
+#[test]
+fn parsing_derives_the_query_port_from_the_registry() {
+    let rust: ServerAddress = "rust@[2001:db8::1]:28100".parse().unwrap();
+
+    assert_eq!(rust.game, "rust");
+    assert_eq!((rust.game_port, rust.query_port), (28100, 28102));
+    assert_eq!(
+        rust.query_addr(),
+        "[2001:db8::1]:28102".parse::<SocketAddr>().unwrap()
+    );
+    assert_eq!(
+        rust.to_string(),
+        "rust@[2001:db8::1]:28100 (query port 28102)"
+    );
+
+    let csgo: ServerAddress = "csgo@192.0.2.1".parse().unwrap();
+    assert_eq!(csgo.game, "cs2");
+    assert_eq!(csgo, ServerAddress::new("cs2", csgo.host, 27015, 27015));
+    assert_eq!(csgo.to_string(), "cs2@192.0.2.1:27015");
+}
+
+#[test]
+fn invalid_addresses_report_why() {
+    let reason = |address: &str| address.parse::<ServerAddress>().unwrap_err().reason;
+
+    assert_eq!(reason("192.0.2.1:27015"), InvalidReason::MissingGame);
+    assert_eq!(reason("pong@192.0.2.1"), InvalidReason::UnknownGame);
+    assert_eq!(reason("tf2@example.com:27015"), InvalidReason::InvalidHost);
+    assert_eq!(reason("rust@192.0.2.1:65535"), InvalidReason::InvalidPort);
+}
+
+#[test]
+fn schedules_plan_server_addresses() {
+    let tf2 = registry::game("tf2").unwrap();
+    let targets: Vec<ServerAddress> = (1..=4)
+        .map(|host| ServerAddress::for_game(tf2, [192, 0, 2, host].into()))
+        .collect();
+
+    let schedule = Schedule::new(Duration::from_secs(60)).with_phase(Phase::Hashed);
+    let plan = schedule.plan(&targets);
+
+    assert_eq!(plan[2].0, targets[2]);
+    assert_eq!(schedule.first_delay(&targets[2]), plan[2].1);
+}
@@ -0,0 +1,222 @@
+//! Querying games and protocols of different types through `AnyGame` and `AnyProtocol`.
+
+use gstat_core::{
+    prelude::{
+        Error, ErrorKind, Game, Player, QueryOptions, Response, ServerInfo, TimeoutSettings, Value,
+    },
+    standards::{
+        any::{AnyGame, AnyProtocol, ErasedProtocol},
+        dyn_game::ErasedGame,
+    },
+};
+
+use std::net::SocketAddr;
+
+use common::{block_on, failure, Mock, MockError, MockProtocol};
+
+mod common;
+
+/// A response listing players, if they were requested.
+#[derive(Default)]
+struct Lobby {
+    players: Vec<&'static str>,
+}
+
+impl Response for Lobby {
+    type E = MockError;
+
+    fn new() -> Result<Self, Error<Self::E>> {
+        Ok(Lobby {
+            players: Vec::new(),
+        })
+    }
+
+    fn to_common(&self) -> ServerInfo {
+        ServerInfo {
+            name: "Lobby".into(),
+            ..ServerInfo::default()
+        }
+    }
+
+    fn players(&self) -> Vec<Player> {
+        self.players.iter().copied().map(Player::new).collect()
+    }
+}
+
+/// A response without a player list.
+#[derive(Debug, Default)]
+struct Heartbeat;
+
+impl Response for Heartbeat {
+    type E = MockError;
+
+    fn new() -> Result<Self, Error<Self::E>> {
+        Ok(Heartbeat)
+    }
+
+    fn to_common(&self) -> ServerInfo {
+        ServerInfo {
+            name: "Heartbeat".into(),
+            ..ServerInfo::default()
+        }
+    }
+}
+
+fn lobby(options: &QueryOptions) -> Lobby {
+    Lobby {
+        players: if options.players {
+            vec!["alice", "bob"]
+        } else {
+            Vec::new()
+        },
+    }
+}
+
+/// Returns a server answering with a `Lobby`.
+fn lobbies() -> Mock<(), Lobby> {
+    Mock::default().respond(|exchange| Ok(lobby(&exchange.query.0)))
+}
+
+/// A game requesting players by default.
+struct Lobbies;
+
+impl<'a> Game<'a, MockProtocol<(), Lobby>> for Lobbies {
+    const GAME_NAME: &'static str = "Lobbies";
+    const RELEASE_YEAR: u32 = 2020;
+
+    fn _protocol(&self) -> MockProtocol<(), Lobby> {
+        lobbies().protocol()
+    }
+
+    fn query_options(&self) -> QueryOptions {
+        QueryOptions {
+            players: true,
+            ..QueryOptions::default()
+        }
+    }
+}
+
+struct Heartbeats;
+
+impl<'a> Game<'a, MockProtocol<(), Heartbeat>> for Heartbeats {
+    const GAME_NAME: &'static str = "Heartbeats";
+    const RELEASE_YEAR: u32 = 2022;
+
+    fn _protocol(&self) -> MockProtocol<(), Heartbeat> {
+        Mock::default().protocol()
+    }
+}
+
+fn address() -> SocketAddr {
+    SocketAddr::from(([192, 0, 2, 1], 27015))
+}
+
+fn games() -> Vec<Box<dyn AnyGame>> {
+    vec![
+        Box::new(ErasedGame::new(Lobbies)),
+        Box::new(ErasedGame::new(Heartbeats)),
+    ]
+}
+
+#[test]
+fn games_with_different_responses_are_stored_together() {
+    let games = games();
+    let names: Vec<&str> = games.iter().map(|game| game.name()).collect();
+    assert_eq!(names, ["Lobbies", "Heartbeats"]);
+
+    let lobby = block_on(games[0].fetch(address())).unwrap().response;
+    assert!(lobby.is::<Lobby>());
+    assert_eq!(lobby.info().name, "Lobby");
+    assert_eq!(lobby.players().len(), 2);
+
+    let heartbeat = block_on(games[1].fetch(address())).unwrap().response;
+    assert!(heartbeat.is::<Heartbeat>());
+    assert!(!heartbeat.is::<Lobby>());
+    assert!(heartbeat.type_name().ends_with("Heartbeat"));
+    assert!(heartbeat.players().is_empty());
+}
+
+#[test]
+fn options_replace_the_defaults_of_the_game() {
+    let games = games();
+    assert!(games[0].query_options().players);
+
+    let fetched = block_on(games[0].fetch_with(
+        QueryOptions::default(),
+        address(),
+        TimeoutSettings::default(),
+    ))
+    .unwrap();
+
+    assert!(fetched.response.players().is_empty());
+}
+
+#[test]
+fn responses_are_downcast_to_their_type() {
+    let games = games();
+    let response = block_on(games[0].fetch(address())).unwrap().response;
+
+    assert_eq!(
+        response.downcast_ref::<Lobby>().unwrap().players[0],
+        "alice"
+    );
+    assert!(response.downcast_ref::<Heartbeat>().is_none());
+
+    let response = response.downcast::<Heartbeat>().unwrap_err();
+    assert_eq!(response.downcast::<Lobby>().ok().unwrap().players.len(), 2);
+}
+
+#[test]
+fn responses_are_described_by_their_fields() {
+    let games = games();
+    let fields = block_on(games[0].fetch(address()))
+        .unwrap()
+        .response
+        .fields();
+
+    assert_eq!(fields["name"], Value::from("Lobby"));
+    assert_eq!(fields["max_players"], Value::Int(0));
+
+    let players = fields["player_list"].as_list().unwrap();
+    assert_eq!(players.len(), 2);
+    assert_eq!(players[1].as_map().unwrap()["name"].to_string(), "bob");
+
+    let fields = block_on(games[1].fetch(address()))
+        .unwrap()
+        .response
+        .fields();
+    assert!(!fields.contains_key("player_list"));
+}
+
+#[test]
+fn protocols_with_different_responses_are_driven_step_by_step() {
+    let protocols: Vec<Box<dyn AnyProtocol>> = vec![
+        Box::new(ErasedProtocol::new(lobbies().protocol())),
+        Box::new(ErasedProtocol::new(
+            Mock::<(), Heartbeat>::default()
+                .respond(|_| Err(failure(ErrorKind::Timeout, "No response")))
+                .protocol(),
+        )),
+    ];
+    let timeouts = TimeoutSettings::default();
+    let options = QueryOptions {
+        players: true,
+        ..QueryOptions::default()
+    };
+
+    let mut outcomes = Vec::new();
+
+    for protocol in &protocols {
+        block_on(protocol.connect(address(), &timeouts)).unwrap();
+        block_on(protocol.handshake(address(), &timeouts)).unwrap();
+        outcomes.push(block_on(protocol.query(options.clone(), &timeouts)));
+        block_on(protocol.disconnect()).unwrap();
+    }
+
+    assert_eq!(outcomes[0].as_ref().unwrap().players().len(), 2);
+
+    let Err(err) = &outcomes[1] else {
+        panic!("the silent protocol answered");
+    };
+    assert_eq!(err.kind(), ErrorKind::Timeout);
+}
@@ -0,0 +1,345 @@
+//! Public API surface checks for `gstat-core`.
+//!
+//! These tests drive the mock protocol of `common`, which implements every standard the way a
+//! downstream protocol implementer would, and pin down the bounds callers rely on. A change to
+//! a required method, a provided method's signature or a trait bound makes this file or the
+//! mock fail to compile, so such changes can't land without deliberately updating them.
+
+use gstat_core::{
+    byte_str::ByteStr,
+    bytes::Bytes,
+    prelude::{
+        ConnectionGuard, Error, ErrorDetail, ErrorKind, Fetched, Game, Parser, Player, Protocol,
+        Query, RawTransport, Response, ServerInfo, TimeoutSettings,
+    },
+    registry::{self, Capabilities, GameEntry, ProtocolEntry, QueryPort, Transport},
+    standards::dyn_protocol::{DynProtocol, DynRawTransport},
+};
+
+use std::{error::Error as StdError, future::Future, net::SocketAddr, time::Duration};
+
+use common::{
+    block_on, Mock, MockError, MockGame, MockParser, MockProtocol, MockQuery, MockResponse,
+};
+
+mod common;
+
+/// Returns a full server with one player.
+fn downstream() -> MockResponse {
+    let mut response = MockResponse::named("Downstream").with_players(&["downstream"]);
+    response.info.players = 4;
+    response.info.max_players = 4;
+
+    response
+}
+
+fn requires_send_sync<T: Send + Sync>() {}
+
+fn requires_std_error<T: StdError + 'static>() {}
+
+fn requires_send<T: Send>(_: &T) {}
+
+/// Every query must be shareable across threads.
+fn query_bounds<Q: Query>() {
+    requires_send_sync::<Q>();
+    requires_std_error::<Q::E>();
+}
+
+/// Every response must be shareable across threads.
+fn response_bounds<R: Response>() {
+    requires_send_sync::<R>();
+    requires_std_error::<R::E>();
+}
+
+/// Parser errors must be standard errors that can be boxed.
+fn parser_bounds<'a, Q: Query + 'a, R: Response + 'a, P: Parser<'a, Q, R>>() {
+    requires_std_error::<P::SE>();
+    requires_std_error::<P::DE>();
+}
+
+/// Protocols must be shareable across threads and tie their associated types together.
+fn protocol_bounds<'a, P: Protocol<'a>>() {
+    requires_send_sync::<P>();
+    query_bounds::<P::Q>();
+    response_bounds::<P::R>();
+    parser_bounds::<P::Q, P::R, P::P>();
+}
+
+#[test]
+fn trait_bounds_hold_for_downstream_implementations() {
+    query_bounds::<MockQuery>();
+    response_bounds::<MockResponse>();
+    parser_bounds::<MockQuery, MockResponse, MockParser>();
+    protocol_bounds::<MockProtocol>();
+}
+
+#[test]
+fn game_constants_and_fetch_are_available() {
+    assert_eq!(<MockGame as Game<MockProtocol>>::GAME_NAME, "Mock");
+    assert_eq!(<MockGame as Game<MockProtocol>>::RELEASE_YEAR, 2024);
+
+    let game = Mock::new().game();
+    let address: SocketAddr = "127.0.0.1:27015".parse().unwrap();
+    let fetched: Result<Fetched<MockResponse, MockError>, Error<MockError>> =
+        block_on(game.fetch(MockQuery::new().unwrap(), address));
+
+    let fetched = fetched.unwrap();
+    let _ = block_on(game.fetch_with(MockQuery::default(), address, TimeoutSettings::default()));
+    let _: &Vec<Error<MockError>> = &fetched.warnings;
+
+    assert!(!fetched.has_warnings());
+    let _: MockResponse = fetched.into_response();
+}
+
+#[test]
+fn protocol_and_game_futures_are_send() {
+    let game = Mock::new().game();
+    let protocol = Mock::new().protocol();
+    let address: SocketAddr = "127.0.0.1:27015".parse().unwrap();
+    let timeouts = TimeoutSettings::default();
+
+    let fetch = game.fetch(MockQuery::default(), address);
+    requires_send(&fetch);
+    drop(fetch);
+
+    let connect = protocol.connect(address, &timeouts);
+    requires_send(&connect);
+    drop(connect);
+
+    fn returns_future<F: Future + Send>(future: F) -> F {
+        future
+    }
+
+    let _ = block_on(returns_future(protocol.receive(&timeouts)));
+}
+
+#[test]
+fn protocols_can_be_used_as_trait_objects() {
+    let server = Mock::new().with_packets(&[Some(b"raw")]);
+    let protocols: Vec<Box<dyn DynRawTransport<MockQuery, MockResponse, MockError>>> =
+        vec![Box::new(server.protocol()), Box::new(server.protocol())];
+    let address: SocketAddr = "127.0.0.1:27015".parse().unwrap();
+    let timeouts = TimeoutSettings::default();
+
+    for protocol in &protocols {
+        assert_eq!(protocol.dyn_retry_policy().max_attempts, 1);
+        block_on(protocol.dyn_connect(address, &timeouts)).unwrap();
+        block_on(protocol.dyn_send_query(MockQuery::default(), &timeouts)).unwrap();
+        let _: MockResponse = block_on(protocol.dyn_receive_response(&timeouts)).unwrap();
+        block_on(protocol.dyn_send(b"raw", &timeouts)).unwrap();
+        let _: Bytes = block_on(protocol.dyn_receive(&timeouts)).unwrap();
+        block_on(protocol.dyn_disconnect()).unwrap();
+        protocol.dyn_schedule_disconnect();
+    }
+
+    let upcast: &dyn DynProtocol<MockQuery, MockResponse, MockError> = protocols[0].as_ref();
+    assert_eq!(upcast.dyn_retry_policy().max_attempts, 1);
+}
+
+#[test]
+fn connect_returns_a_connection_guard() {
+    let protocol = Mock::new().protocol();
+    let address: SocketAddr = "127.0.0.1:27015".parse().unwrap();
+
+    let timeouts = TimeoutSettings::uniform(Duration::from_secs(1))
+        .with_connect(Duration::from_millis(500))
+        .with_read(Duration::from_millis(500))
+        .with_write(Duration::from_millis(500))
+        .with_overall(Duration::from_secs(2));
+    let _: Option<Duration> = timeouts.connect;
+
+    let guard: ConnectionGuard<'_, MockProtocol> =
+        block_on(protocol.connect(address, &TimeoutSettings::default())).unwrap();
+    let _: &MockProtocol = guard.protocol();
+
+    let disconnected: Result<(), Error<MockError>> = block_on(guard.disconnect());
+    assert!(disconnected.is_ok());
+
+    let guard = block_on(protocol.connect(address, &timeouts)).unwrap();
+    let _: &MockProtocol = guard.release();
+
+    protocol.schedule_disconnect();
+}
+
+#[test]
+fn responses_convert_to_the_common_model() {
+    let info: ServerInfo = downstream().to_common();
+
+    let _: &str = &info.map;
+    let _: &str = &info.game;
+    let _: bool = info.password;
+    let _: &str = &info.version;
+    let _: &std::collections::BTreeMap<String, String> = &info.extra;
+
+    assert_eq!(info.name, "Downstream");
+    assert!(info.is_full());
+
+    let players: Vec<Player> = downstream().players();
+    let player = &players[0];
+
+    let _: Option<i64> = player.score;
+    let _: Option<std::time::Duration> = player.duration;
+    let _: Option<u32> = player.ping;
+    let _: &Option<String> = &player.team;
+    let _: &std::collections::BTreeMap<String, String> = &player.extra;
+
+    assert_eq!(player.name, "downstream");
+}
+
+#[test]
+fn parser_provided_methods_wrap_internal_methods() {
+    fn check<'a, P>(parser: P)
+    where
+        P: Parser<'a, MockQuery, MockResponse, SE = MockError, DE = MockError>,
+    {
+        let serialized: Result<Vec<u8>, Error<MockError>> =
+            parser.serialize_query(&MockQuery::default());
+        let deserialized: Result<MockResponse, Error<MockError>> =
+            parser.deserialize_response(Bytes::from_static(b"info"));
+        let garbled: Result<MockResponse, Error<MockError>> =
+            parser.deserialize_response(Bytes::new());
+
+        assert_eq!(serialized.unwrap(), b"status");
+        assert!(deserialized.is_ok());
+        assert!(matches!(garbled, Err(Error::ParserError(_))));
+    }
+
+    check(MockParser);
+}
+
+#[test]
+fn error_categories_and_accessors_are_public() {
+    let errors: [Error<MockError>; 5] = [
+        Error::GameError(ErrorDetail::new("game", None)),
+        Error::ParserError(ErrorDetail::new("parser", None)),
+        Error::ProtocolError(ErrorDetail::new("protocol", None)),
+        Error::QueryError(ErrorDetail::new("query", None)),
+        Error::ResponseError(ErrorDetail::new("response", Some(MockError("response")))),
+    ];
+
+    for error in errors {
+        let message = error.detail().message().to_string();
+        let mapped: Error<String> = error.map(|inner| inner.to_string());
+
+        assert_eq!(mapped.detail().message(), message);
+    }
+
+    requires_std_error::<Error<MockError>>();
+}
+
+#[test]
+fn errors_chain_sources_and_convert_with_question_mark() {
+    let error: Error<MockError> =
+        Error::ResponseError(ErrorDetail::new("bad", Some(MockError("downstream error"))));
+    let source = error.source().unwrap();
+
+    assert_eq!(source.to_string(), "downstream error");
+    assert!(
+        Error::<MockError>::GameError(ErrorDetail::new("game", None))
+            .source()
+            .is_none()
+    );
+
+    fn read() -> Result<(), Error<std::io::Error>> {
+        Err(std::io::Error::from(std::io::ErrorKind::ConnectionRefused))?
+    }
+
+    let error = read().unwrap_err();
+    assert!(matches!(error, Error::ProtocolError(_)));
+    assert_eq!(error.kind(), ErrorKind::ConnectionRefused);
+    assert!(error.source().is_some());
+
+    let error = error.context("querying server");
+    assert_eq!(
+        error.detail().message(),
+        "querying server: I/O operation failed"
+    );
+    assert_eq!(
+        ErrorDetail::<()>::new("eof", None)
+            .context("reading players")
+            .context("fetching")
+            .message(),
+        "fetching: reading players: eof"
+    );
+}
+
+#[test]
+fn error_kind_codes_are_stable() {
+    let codes: Vec<(u16, &str)> = ErrorKind::ALL
+        .iter()
+        .map(|kind| (kind.code(), kind.name()))
+        .collect();
+
+    assert_eq!(
+        codes,
+        [
+            (0, "other"),
+            (1, "timeout"),
+            (2, "connection_refused"),
+            (3, "connection_reset"),
+            (4, "dns"),
+            (5, "network"),
+            (6, "io"),
+            (7, "not_connected"),
+            (8, "truncated"),
+            (9, "invalid_packet"),
+            (10, "unsupported"),
+            (11, "cancelled"),
+            (12, "blocked"),
+        ]
+    );
+
+    for kind in ErrorKind::ALL {
+        assert_eq!(ErrorKind::from_code(kind.code()), Some(kind));
+    }
+
+    let error: Error<MockError> =
+        Error::ProtocolError(ErrorDetail::new("timed out", None).with_kind(ErrorKind::Timeout));
+
+    assert_eq!(error.kind(), ErrorKind::Timeout);
+    assert_eq!(error.code(), 1);
+    assert_eq!(error.map(|_| ()).kind(), ErrorKind::Timeout);
+    assert_eq!(
+        ErrorKind::from_io(&std::io::Error::from(std::io::ErrorKind::ConnectionRefused)),
+        ErrorKind::ConnectionRefused
+    );
+    assert_eq!(
+        Error::<MockError>::GameError(ErrorDetail::new("game", None)).kind(),
+        ErrorKind::Other
+    );
+}
+
+#[test]
+fn registry_tables_are_public() {
+    let game: &'static GameEntry = registry::game("tf2").unwrap();
+    let protocol: &'static ProtocolEntry = game.protocol();
+
+    let _: &str = game.name;
+    let _: &[&str] = game.aliases;
+    let _: u32 = game.release_year;
+    let _: u16 = game.game_port;
+    let _: QueryPort = game.query_port;
+    let _: &[u32] = game.steam_appids;
+    let _: Capabilities = game.capabilities;
+    let _: Transport = protocol.transport;
+
+    assert!(registry::game_by_appid(440).is_some());
+    assert!(registry::protocol(game.protocol).is_some());
+    assert!(!registry::GAMES.is_empty());
+    assert!(!registry::PROTOCOLS.is_empty());
+}
+
+#[test]
+fn byte_str_shares_packet_memory() {
+    let packet = Bytes::from_static(b"\x00de_dust2\x00");
+    let map = ByteStr::from_utf8(packet.slice(1..9)).unwrap();
+
+    assert_eq!(map, "de_dust2");
+    assert_eq!(map.len(), 8);
+    assert_eq!(map.as_bytes().as_ptr(), packet[1..].as_ptr());
+    assert!(ByteStr::from_utf8(Bytes::from_static(b"\xff")).is_err());
+    assert_eq!(
+        ByteStr::from_utf8_lossy(Bytes::from_static(b"a\xff")),
+        "a\u{fffd}"
+    );
+}
@@ -0,0 +1,63 @@
+//! Rendering status badges.
+
+use gstat_core::{badge::Badge, prelude::ServerInfo};
+
+fn server(name: &str, players: u32, max_players: u32) -> ServerInfo {
+    ServerInfo {
+        name: name.into(),
+        players,
+        max_players,
+        ..ServerInfo::default()
+    }
+}
+
+#[test]
+fn badges_show_the_player_count_and_status() {
+    let open = Badge::online(&server("Badlands", 12, 24));
+    assert_eq!(
+        (open.label(), open.message()),
+        ("Badlands", "12/24 players")
+    );
+
+    let full = Badge::online(&server("", 24, 24));
+    assert_eq!(full.label(), "server");
+    assert_ne!(full.color(), open.color());
+
+    let offline = Badge::offline().with_label("Badlands");
+    assert_eq!(offline.message(), "offline");
+
+    let svg = open.to_svg();
+    assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\""));
+    assert!(svg.contains(">Badlands</text>"));
+    assert!(svg.contains(">12/24 players</text>"));
+    assert!(svg.ends_with("</svg>"));
+
+    // Longer texts make wider badges.
+    let width = |svg: &str| -> u32 {
+        let start = svg.find("width=\"").unwrap() + 7;
+        svg[start..svg[start..].find('"').unwrap() + start]
+            .parse()
+            .unwrap()
+    };
+    assert!(
+        width(&Badge::online(&server("A much longer server name", 1, 8)).to_svg()) > width(&svg)
+    );
+}
+
+#[test]
+fn server_names_cannot_inject_markup() {
+    let name = "<script>alert(1)</script>\" onload=\"x & y\u{7}";
+    let svg = Badge::online(&server(name, 1, 8))
+        .with_color("red\" onclick=\"evil()")
+        .to_svg();
+
+    assert!(!svg.contains("<script>"));
+    assert!(!svg.contains("onload=\""));
+    assert!(!svg.contains("onclick="));
+    assert!(svg.contains("&lt;script&gt;alert(1)&lt;/script&gt;&quot; onload=&quot;x &amp; y"));
+    assert!(!svg.contains('\u{7}'));
+
+    let long = Badge::online(&server(&"x".repeat(500), 1, 8)).to_svg();
+    assert!(long.contains(&format!("{}…", "x".repeat(47))));
+    assert!(long.len() < 1500);
+}
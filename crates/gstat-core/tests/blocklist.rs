@@ -0,0 +1,120 @@
+//! Matching targets against blocklists and opt-out list files.
+
+use gstat_core::blocklist::{Blocklist, BlocklistError, IpNet};
+
+use std::net::{IpAddr, SocketAddr};
+
+fn target(address: &str) -> SocketAddr {
+    address.parse().unwrap()
+}
+
+#[test]
+fn cidr_ranges_match_their_addresses() {
+    let net: IpNet = "203.0.113.0/24".parse().unwrap();
+
+    assert!(net.contains("203.0.113.7".parse().unwrap()));
+    assert!(!net.contains("203.0.114.7".parse().unwrap()));
+    assert!(net.contains("::ffff:203.0.113.7".parse().unwrap()));
+
+    let v6: IpNet = "2001:db8::/32".parse().unwrap();
+    assert!(v6.contains("2001:db8:1::1".parse().unwrap()));
+    assert!(!v6.contains("203.0.113.7".parse().unwrap()));
+
+    let all: IpNet = "0.0.0.0/0".parse().unwrap();
+    assert!(all.contains("198.51.100.1".parse().unwrap()));
+
+    assert!("203.0.113.0/33".parse::<IpNet>().is_err());
+    assert!("not-an-address".parse::<IpNet>().is_err());
+}
+
+#[test]
+fn blocked_targets_report_the_matching_rule() {
+    let mut blocklist = Blocklist::new();
+    blocklist
+        .block("198.51.100.10".parse::<IpAddr>().unwrap())
+        .extend_from_list(
+            "# opt-out requests\n\n203.0.113.0/24 # hosting provider\n",
+            Some("opt-out.txt"),
+        )
+        .unwrap();
+
+    assert!(blocklist.check(target("192.0.2.1:27015")).is_ok());
+    assert!(blocklist.is_blocked(target("198.51.100.10:27015")));
+
+    let blocked = blocklist.check(target("203.0.113.9:27015")).unwrap_err();
+    assert_eq!(blocked.rule.net, "203.0.113.0/24".parse().unwrap());
+    assert_eq!(blocked.rule.source.as_deref(), Some("opt-out.txt"));
+    assert_eq!(
+        blocked.to_string(),
+        "203.0.113.9:27015 is blocked by 203.0.113.0/24 (opt-out.txt)"
+    );
+}
+
+#[test]
+fn invalid_list_entries_are_rejected_with_their_line() {
+    let mut blocklist = Blocklist::new();
+    let err = blocklist
+        .extend_from_list("192.0.2.1\nbogus\n", None)
+        .unwrap_err();
+
+    assert_eq!(err.line, Some(2));
+    assert_eq!(err.entry, "bogus");
+    assert!(blocklist.is_empty());
+}
+
+#[test]
+fn missing_list_files_fail_to_load() {
+    let err = Blocklist::new()
+        .load("/nonexistent/gstat-opt-out.txt")
+        .unwrap_err();
+
+    assert!(matches!(err, BlocklistError::Io(_)));
+}
+
+#[test]
+fn reserved_ranges_block_internal_networks() {
+    let blocklist = Blocklist::reserved();
+
+    for internal in [
+        "127.0.0.1:80",
+        "10.1.2.3:27015",
+        "192.168.1.1:27015",
+        "169.254.169.254:80",
+        "[::1]:27015",
+        "[fd00::1]:27015",
+        "[::ffff:172.16.0.1]:27015",
+    ] {
+        assert!(blocklist.is_blocked(target(internal)), "{internal}");
+    }
+
+    assert!(blocklist.check(target("8.8.8.8:53")).is_ok());
+    assert!(blocklist.check(target("[2606:4700::1]:27015")).is_ok());
+    assert_eq!(
+        blocklist
+            .check(target("10.0.0.1:1"))
+            .unwrap_err()
+            .rule
+            .source
+            .as_deref(),
+        Some("reserved")
+    );
+}
+
+#[test]
+fn reserved_ranges_block_ipv6_transition_addresses() {
+    let blocklist = Blocklist::reserved();
+
+    // Each of these carries 127.0.0.1 or 10.0.0.1 inside an IPv6 address.
+    for (internal, range) in [
+        ("[::127.0.0.1]:27015", "::/96"),
+        ("[64:ff9b::10.0.0.1]:27015", "64:ff9b::/96"),
+        ("[2002:7f00:1::1]:27015", "2002::/16"),
+        ("[2001:0:4136:e378:8000:63bf:80ff:fffe]:27015", "2001::/32"),
+    ] {
+        assert_eq!(
+            blocklist.check(target(internal)).unwrap_err().rule.net,
+            range.parse::<IpNet>().unwrap(),
+            "{internal}"
+        );
+    }
+}
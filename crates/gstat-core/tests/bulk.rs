@@ -0,0 +1,174 @@
+//! Querying many servers with `query_many`.
+
+use gstat_core::{
+    blocklist::{Blocklist, IpNet},
+    bulk::{query_many, QueryMany},
+    prelude::{Error, ErrorDetail, TimeoutSettings},
+};
+
+use std::{
+    future::Future,
+    net::SocketAddr,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    task::{Context, Poll},
+};
+
+use common::{block_on, Mock, MockError, MockGame, MockResponse};
+use futures_util::StreamExt;
+
+mod common;
+
+/// Returns `Poll::Pending` a number of times before completing.
+struct Yield(usize);
+
+impl Future for Yield {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        if self.0 == 0 {
+            return Poll::Ready(());
+        }
+
+        self.0 -= 1;
+        cx.waker().wake_by_ref();
+        Poll::Pending
+    }
+}
+
+/// Tracks how many queries run at the same time.
+#[derive(Default)]
+struct Load {
+    current: AtomicUsize,
+    peak: AtomicUsize,
+}
+
+/// Returns a game answering with the port of the server after a number of polls that
+/// depends on the port, and failing for odd ports.
+fn ports(load: &Arc<Load>) -> MockGame {
+    let (connecting, answering) = (load.clone(), load.clone());
+
+    Mock::new()
+        .on_connect(move |_| {
+            let current = connecting.current.fetch_add(1, Ordering::SeqCst) + 1;
+            connecting.peak.fetch_max(current, Ordering::SeqCst);
+            Ok(())
+        })
+        .respond_later(move |exchange| {
+            let load = answering.clone();
+            let port = exchange.address.unwrap().port();
+
+            async move {
+                Yield(usize::from(port % 5)).await;
+                load.current.fetch_sub(1, Ordering::SeqCst);
+
+                match port % 2 {
+                    1 => Err(Error::QueryError(ErrorDetail::new(
+                        "no answer",
+                        Some(MockError("no answer")),
+                    ))),
+                    _ => Ok(MockResponse::named(port.to_string())),
+                }
+            }
+        })
+        .game()
+}
+
+fn targets(count: u16) -> Vec<SocketAddr> {
+    (1..=count)
+        .map(|port| SocketAddr::from(([192, 0, 2, 1], port)))
+        .collect()
+}
+
+#[test]
+fn queries_every_target_within_the_concurrency_limit() {
+    let load = Arc::default();
+    let game = ports(&load);
+
+    let results = block_on(query_many(&game, targets(20), 4, TimeoutSettings::default()).collect());
+
+    assert_eq!(results.len(), 20);
+    assert_eq!(load.peak.load(Ordering::SeqCst), 4);
+
+    for (target, result) in &results {
+        match result {
+            Ok(fetched) => assert_eq!(fetched.response.info.name, target.port().to_string()),
+            Err(err) => {
+                assert_eq!(target.port() % 2, 1);
+                assert!(err.to_string().contains("no answer"));
+            }
+        }
+    }
+
+    let failed = results.iter().filter(|(_, result)| result.is_err()).count();
+    assert_eq!(failed, 10);
+}
+
+#[test]
+fn results_are_yielded_as_they_complete() {
+    let started = Arc::new(Mutex::new(Vec::new()));
+    let log = started.clone();
+
+    let mut queries = QueryMany::new([3usize, 0, 1], 2, move |&polls| {
+        log.lock().unwrap().push(polls);
+        async move {
+            Yield(polls).await;
+            polls * 10
+        }
+    });
+
+    assert_eq!(block_on(queries.next()), Some((0, 0)));
+    assert_eq!(queries.in_flight(), 1);
+    assert_eq!(*started.lock().unwrap(), [3, 0]);
+
+    assert_eq!(block_on(queries.next()), Some((1, 10)));
+    assert_eq!(block_on(queries.next()), Some((3, 30)));
+    assert_eq!(block_on(queries.next()), None);
+}
+
+#[test]
+fn results_can_be_consumed_as_a_stream() {
+    let game = ports(&Arc::default());
+
+    let mut answered = block_on(
+        query_many(&game, targets(10), 3, TimeoutSettings::default())
+            .filter_map(|(target, result)| async move { result.ok().map(|_| target.port()) })
+            .collect::<Vec<_>>(),
+    );
+
+    answered.sort();
+    assert_eq!(answered, [2, 4, 6, 8, 10]);
+}
+
+#[test]
+fn blocked_targets_are_skipped() {
+    let queried = Arc::new(Mutex::new(Vec::new()));
+    let log = queried.clone();
+    let mut blocklist = Blocklist::new();
+    blocklist.block("192.0.2.0/30".parse::<IpNet>().unwrap());
+
+    let targets =
+        [[192, 0, 2, 1], [192, 0, 2, 9], [192, 0, 2, 2]].map(|ip| SocketAddr::from((ip, 27015)));
+    let mut results = block_on(
+        QueryMany::new(targets, 2, move |target: &SocketAddr| {
+            log.lock().unwrap().push(*target);
+            async {}
+        })
+        .with_blocklist(blocklist)
+        .collect(),
+    );
+
+    results.sort_by_key(|(target, _)| *target);
+    let blocked: Vec<_> = results
+        .iter()
+        .filter_map(|(_, result)| result.as_ref().err())
+        .map(|blocked| blocked.address)
+        .collect();
+
+    assert_eq!(*queried.lock().unwrap(), [targets[1]]);
+    assert_eq!(blocked, [targets[0], targets[2]]);
+    assert_eq!(results[2], (targets[1], Ok(())));
+}
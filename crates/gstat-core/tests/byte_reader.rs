@@ -0,0 +1,68 @@
+//! Bounds-checked decoding with `ByteReader`.
+
+use gstat_core::{
+    bytes::Bytes,
+    codec::reader::{ByteReader, DecodeError},
+    prelude::{Error, ErrorKind},
+};
+
+#[test]
+fn reads_typed_values_in_order() {
+    let mut reader = ByteReader::new(Bytes::from_static(
+        b"\x49\x11\x00Server\x00\x03map\x2a\x00\x00\x00\xac\x02\xff\xff\xff\xff\x0f",
+    ));
+
+    assert_eq!(reader.read_u8().unwrap(), 0x49);
+    assert_eq!(reader.read_u16_le().unwrap(), 0x11);
+    assert_eq!(reader.read_cstring().unwrap(), "Server");
+    assert_eq!(reader.read_pascal_string().unwrap(), "map");
+    assert_eq!(reader.read_u32_le().unwrap(), 42);
+    assert_eq!(reader.read_varint_u32().unwrap(), 300);
+    assert_eq!(reader.read_varint_i32().unwrap(), -1);
+    assert!(reader.is_empty());
+}
+
+#[test]
+fn truncation_is_a_parser_error_and_consumes_nothing() {
+    let mut reader = ByteReader::new(Bytes::from_static(b"\x01\x02\x03"));
+    reader.skip(1).unwrap();
+
+    let error = reader.read_u32_le().unwrap_err();
+
+    assert!(matches!(error, Error::ParserError(_)));
+    assert_eq!(error.kind(), ErrorKind::Truncated);
+    assert_eq!(
+        error.detail().inner(),
+        Some(&DecodeError::Truncated {
+            offset: 1,
+            needed: 4,
+            remaining: 2,
+        })
+    );
+    assert_eq!(reader.position(), 1);
+    assert_eq!(reader.read_u16_be().unwrap(), 0x0203);
+}
+
+#[test]
+fn malformed_values_are_rejected() {
+    let mut unterminated = ByteReader::new(Bytes::from_static(b"name"));
+    assert_eq!(
+        unterminated.read_cstring().unwrap_err().kind(),
+        ErrorKind::Truncated
+    );
+
+    let mut invalid = ByteReader::new(Bytes::from_static(b"\xff\x00"));
+    assert_eq!(
+        invalid.read_cstring().unwrap_err().kind(),
+        ErrorKind::InvalidPacket
+    );
+    assert_eq!(invalid.position(), 0);
+    assert_eq!(invalid.read_cstring_lossy().unwrap(), "\u{fffd}");
+
+    let mut overflow = ByteReader::new(Bytes::from_static(b"\xff\xff\xff\xff\x1f"));
+    assert_eq!(
+        overflow.read_varint_u32().unwrap_err().detail().inner(),
+        Some(&DecodeError::VarintOverflow { offset: 0 })
+    );
+    assert_eq!(overflow.position(), 0);
+}
@@ -0,0 +1,77 @@
+//! Building query payloads with `ByteWriter`.
+
+use gstat_core::{
+    codec::{
+        reader::ByteReader,
+        writer::{ByteWriter, Checksum, EncodeError},
+        Endian, LengthWidth,
+    },
+    prelude::Error,
+};
+
+#[test]
+fn builds_an_a2s_info_request() {
+    let mut writer = ByteWriter::new();
+    writer
+        .write_bytes(b"\xff\xff\xff\xff")
+        .write_u8(b'T')
+        .write_cstring("Source Engine Query")
+        .write_i32_le(-1);
+
+    assert_eq!(
+        writer.finish().unwrap(),
+        b"\xff\xff\xff\xffTSource Engine Query\x00\xff\xff\xff\xff"
+    );
+}
+
+#[test]
+fn length_prefixes_nest_and_round_trip() {
+    let mut writer = ByteWriter::new();
+    writer
+        .open_length_inclusive(LengthWidth::U32, Endian::Little)
+        .write_u16_be(7)
+        .write_pascal_string("map")
+        .write_varint_string("motd")
+        .close_length()
+        .write_checksum(Checksum::Crc32, 0, Endian::Little);
+
+    let packet = writer.finish_bytes().unwrap();
+    let mut reader = ByteReader::new(packet.clone());
+
+    assert_eq!(reader.read_u32_le().unwrap() as usize, packet.len() - 4);
+    assert_eq!(reader.read_u16_be().unwrap(), 7);
+    assert_eq!(reader.read_pascal_string().unwrap(), "map");
+    assert_eq!(reader.read_varint_string().unwrap(), "motd");
+    assert_eq!(
+        reader.read_u32_le().unwrap(),
+        Checksum::Crc32.compute(&packet[..packet.len() - 4])
+    );
+}
+
+#[test]
+fn checksums_match_reference_values() {
+    assert_eq!(Checksum::Crc32.compute(b"123456789"), 0xcbf4_3926);
+    assert_eq!(Checksum::Sum8.compute(&[0xff, 0x02]), 0x01);
+    assert_eq!(Checksum::Xor8.compute(&[0x0f, 0xf0]), 0xff);
+}
+
+#[test]
+fn the_first_failure_is_reported_when_finished() {
+    let mut writer = ByteWriter::new();
+    writer
+        .write_u8(1)
+        .write_cstring("a\0b")
+        .write_pascal_string(&"x".repeat(256));
+
+    let Err(Error::ParserError(detail)) = writer.finish() else {
+        panic!("expected a parser error");
+    };
+    assert_eq!(
+        detail.inner(),
+        Some(&EncodeError::InteriorNul { offset: 1 })
+    );
+
+    let mut writer = ByteWriter::new();
+    writer.open_length(LengthWidth::U8, Endian::Big);
+    assert!(writer.finish().is_err());
+}
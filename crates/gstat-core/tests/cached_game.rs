@@ -0,0 +1,268 @@
+//! Caching responses with `CachedGame` on a `ManualClock`.
+
+use gstat_core::{
+    cache::{CacheStatus, CachedGame, CachedGameBuilder},
+    clock::ManualClock,
+    prelude::ErrorKind,
+};
+
+use std::{
+    future::{poll_fn, Future},
+    net::SocketAddr,
+    pin::pin,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc,
+    },
+    task::{Context, Poll, Waker},
+    time::Duration,
+};
+
+use common::{block_on, failure, Mock, MockGame, MockProtocol, MockResponse};
+
+mod common;
+
+/// The state shared by a game and the protocols it creates.
+#[derive(Clone)]
+struct Server {
+    queries: Arc<AtomicUsize>,
+    online: Arc<AtomicBool>,
+    /// Whether responses are held back until it is set.
+    open: Arc<AtomicBool>,
+}
+
+impl Server {
+    fn new() -> Self {
+        Server {
+            queries: Arc::default(),
+            online: Arc::new(AtomicBool::new(true)),
+            open: Arc::new(AtomicBool::new(true)),
+        }
+    }
+
+    fn queries(&self) -> usize {
+        self.queries.load(Ordering::SeqCst)
+    }
+}
+
+impl Server {
+    /// Returns a game counting its queries and answering with their number.
+    fn game(&self) -> MockGame {
+        let server = self.clone();
+
+        Mock::new()
+            .respond_later(move |_| {
+                let server = server.clone();
+
+                async move {
+                    poll_fn(|_| match server.open.load(Ordering::SeqCst) {
+                        true => Poll::Ready(()),
+                        false => Poll::Pending,
+                    })
+                    .await;
+
+                    let number = server.queries.fetch_add(1, Ordering::SeqCst) + 1;
+
+                    match server.online.load(Ordering::SeqCst) {
+                        true => Ok(MockResponse::named(format!("query {number}"))),
+                        false => Err(failure(ErrorKind::Timeout, "No response")),
+                    }
+                }
+            })
+            .game()
+    }
+}
+
+/// Returns the number of the query that received `response`.
+fn number(response: &MockResponse) -> usize {
+    response.info.name["query ".len()..].parse().unwrap()
+}
+
+fn address() -> SocketAddr {
+    SocketAddr::from(([192, 0, 2, 1], 27015))
+}
+
+fn builder(server: &Server, clock: &ManualClock) -> CachedGameBuilder<MockGame, MockProtocol> {
+    CachedGame::builder(server.game(), Duration::from_secs(10))
+        .with_stale_while_revalidate(Duration::from_secs(20))
+        .with_clock(clock.clone())
+}
+
+fn cached(server: &Server, clock: &ManualClock) -> CachedGame<MockGame, MockProtocol> {
+    builder(server, clock).build()
+}
+
+#[test]
+fn responses_are_served_from_the_cache_until_they_expire() {
+    let (server, clock) = (Server::new(), ManualClock::new());
+    let cache = cached(&server, &clock);
+
+    let first = block_on(cache.fetch(address())).unwrap();
+    assert_eq!(first.status, CacheStatus::Miss);
+    assert_eq!(number(&first.fetched.response), 1);
+
+    clock.advance(Duration::from_secs(9));
+    let second = block_on(cache.fetch(address())).unwrap();
+    assert_eq!(second.status, CacheStatus::Hit);
+    assert_eq!(second.age, Duration::from_secs(9));
+    assert!(Arc::ptr_eq(&first.fetched, &second.fetched));
+    assert_eq!(server.queries(), 1);
+
+    cache.invalidate(address());
+    let third = block_on(cache.fetch(address())).unwrap();
+    assert_eq!(third.status, CacheStatus::Miss);
+    assert_eq!(number(&third.fetched.response), 2);
+}
+
+#[test]
+fn stale_responses_are_served_within_the_window() {
+    let (server, clock) = (Server::new(), ManualClock::new());
+    let cache = cached(&server, &clock);
+
+    block_on(cache.fetch(address())).unwrap();
+
+    // Without a runtime the refresh can't be spawned, so the stale response is kept.
+    clock.advance(Duration::from_secs(15));
+    let stale = block_on(cache.fetch(address())).unwrap();
+    assert_eq!(stale.status, CacheStatus::Stale);
+    assert_eq!(number(&stale.fetched.response), 1);
+
+    clock.advance(Duration::from_secs(15));
+    let expired = block_on(cache.fetch(address())).unwrap();
+    assert_eq!(expired.status, CacheStatus::Miss);
+    assert_eq!(number(&expired.fetched.response), 2);
+}
+
+// Refreshes run in a background task, which the silent feature never spawns.
+#[cfg(not(feature = "silent"))]
+#[test]
+fn stale_responses_are_refreshed_in_the_background() {
+    let (server, clock) = (Server::new(), ManualClock::new());
+    let cache = cached(&server, &clock);
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_time()
+        .build()
+        .unwrap();
+
+    runtime.block_on(async {
+        cache.fetch(address()).await.unwrap();
+        clock.advance(Duration::from_secs(15));
+
+        let stale = cache.fetch(address()).await.unwrap();
+        assert_eq!(stale.status, CacheStatus::Stale);
+
+        while server.queries() < 2 {
+            tokio::task::yield_now().await;
+        }
+        tokio::task::yield_now().await;
+
+        let refreshed = cache.fetch(address()).await.unwrap();
+        assert_eq!(refreshed.status, CacheStatus::Hit);
+        assert_eq!(number(&refreshed.fetched.response), 2);
+    });
+}
+
+#[test]
+fn refreshes_dropped_with_their_runtime_release_the_entry() {
+    let (server, clock) = (Server::new(), ManualClock::new());
+    let cache = cached(&server, &clock);
+    let runtime = || {
+        tokio::runtime::Builder::new_current_thread()
+            .enable_time()
+            .build()
+            .unwrap()
+    };
+
+    runtime().block_on(cache.fetch(address())).unwrap();
+    clock.advance(Duration::from_secs(15));
+
+    // The refresh waits for the server, so it is dropped with the runtime it was spawned on.
+    server.open.store(false, Ordering::SeqCst);
+    let stale = runtime().block_on(cache.fetch(address())).unwrap();
+    assert_eq!(stale.status, CacheStatus::Stale);
+    server.open.store(true, Ordering::SeqCst);
+
+    runtime().block_on(async {
+        let stale = cache.fetch(address()).await.unwrap();
+        assert_eq!(stale.status, CacheStatus::Stale);
+
+        for _ in 0..100 {
+            if server.queries() == 2 {
+                break;
+            }
+            tokio::task::yield_now().await;
+        }
+        tokio::task::yield_now().await;
+
+        let refreshed = cache.fetch(address()).await.unwrap();
+        assert_eq!(refreshed.status, CacheStatus::Hit);
+        assert_eq!(number(&refreshed.fetched.response), 2);
+    });
+}
+
+#[test]
+fn concurrent_misses_share_one_query() {
+    let (server, clock) = (Server::new(), ManualClock::new());
+    let cache = cached(&server, &clock);
+    let mut context = Context::from_waker(Waker::noop());
+
+    server.open.store(false, Ordering::SeqCst);
+
+    let mut first = pin!(cache.fetch(address()));
+    let mut second = pin!(cache.fetch(address()));
+    assert!(first.as_mut().poll(&mut context).is_pending());
+    assert!(second.as_mut().poll(&mut context).is_pending());
+
+    server.open.store(true, Ordering::SeqCst);
+
+    let Poll::Ready(first) = first.as_mut().poll(&mut context) else {
+        panic!("the query was answered");
+    };
+    let Poll::Ready(second) = second.as_mut().poll(&mut context) else {
+        panic!("the waiting fetch was woken");
+    };
+
+    assert_eq!(first.unwrap().status, CacheStatus::Miss);
+    assert_eq!(second.unwrap().status, CacheStatus::Hit);
+    assert_eq!(server.queries(), 1);
+}
+
+#[test]
+fn failures_are_not_cached() {
+    let (server, clock) = (Server::new(), ManualClock::new());
+    let cache = cached(&server, &clock);
+
+    server.online.store(false, Ordering::SeqCst);
+    assert!(block_on(cache.fetch(address())).is_err());
+    assert!(block_on(cache.fetch(address())).is_err());
+    assert_eq!(server.queries(), 2);
+    assert!(cache.is_empty());
+
+    server.online.store(true, Ordering::SeqCst);
+    block_on(cache.fetch(address())).unwrap();
+    assert_eq!(cache.len(), 1);
+
+    clock.advance(Duration::from_secs(30));
+    cache.purge();
+    assert!(cache.is_empty());
+}
+
+#[test]
+fn the_oldest_responses_are_evicted_beyond_the_maximum() {
+    let (server, clock) = (Server::new(), ManualClock::new());
+    let cache = builder(&server, &clock).with_max_entries(2).build();
+    let server_at = |port| SocketAddr::from(([192, 0, 2, 1], port));
+
+    block_on(cache.fetch(server_at(1))).unwrap();
+    clock.advance(Duration::from_secs(1));
+    block_on(cache.fetch(server_at(2))).unwrap();
+    clock.advance(Duration::from_secs(1));
+    block_on(cache.fetch(server_at(3))).unwrap();
+    assert_eq!(cache.len(), 2);
+
+    let second = block_on(cache.fetch(server_at(2))).unwrap();
+    assert_eq!(second.status, CacheStatus::Hit);
+    let first = block_on(cache.fetch(server_at(1))).unwrap();
+    assert_eq!(first.status, CacheStatus::Miss);
+    assert_eq!(server.queries(), 4);
+}
@@ -0,0 +1,148 @@
+//! Cancelling fetches and bulk queries with a `CancellationToken`.
+
+use gstat_core::{
+    bulk::QueryMany,
+    cancel::CancellationToken,
+    prelude::{ErrorKind, Game, TimeoutSettings},
+};
+
+use std::{
+    future::{pending, Future},
+    net::SocketAddr,
+    pin::{pin, Pin},
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        Arc,
+    },
+    task::{Context, Poll, Wake, Waker},
+    time::{Duration, Instant},
+};
+
+use common::{Mock, MockQuery};
+
+mod common;
+
+/// A waker counting how often it was woken.
+#[derive(Default)]
+struct Counting(AtomicU32);
+
+impl Wake for Counting {
+    fn wake(self: Arc<Self>) {
+        self.0.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+fn poll_once<F: Future + ?Sized>(future: Pin<&mut F>) -> Poll<F::Output> {
+    future.poll(&mut Context::from_waker(Waker::noop()))
+}
+
+#[test]
+fn children_are_cancelled_with_their_parent() {
+    let parent = CancellationToken::new();
+    let child = parent.child();
+    let sibling = parent.child();
+
+    sibling.cancel();
+    assert!(sibling.is_cancelled());
+    assert!(!parent.is_cancelled() && !child.is_cancelled());
+
+    let counting = Arc::new(Counting::default());
+    let waker = Waker::from(counting.clone());
+    let mut cancelled = child.cancelled();
+
+    assert!(Pin::new(&mut cancelled)
+        .poll(&mut Context::from_waker(&waker))
+        .is_pending());
+
+    parent.cancel();
+
+    assert!(child.is_cancelled());
+    assert_eq!(counting.0.load(Ordering::SeqCst), 1);
+    assert!(poll_once(Pin::new(&mut cancelled)).is_ready());
+
+    // Children of a cancelled token start out cancelled.
+    assert!(parent.child().is_cancelled());
+}
+
+#[test]
+fn run_until_cancelled_drops_the_future() {
+    let token = CancellationToken::new();
+    let mut run = pin!(token.run_until_cancelled(pending::<()>()));
+
+    assert!(poll_once(run.as_mut()).is_pending());
+    token.cancel();
+    assert_eq!(poll_once(run.as_mut()), Poll::Ready(None));
+}
+
+/// Returns a server whose responses never arrive.
+fn blackhole() -> Mock {
+    Mock::new().respond_later(|_| pending())
+}
+
+fn address(index: u8) -> SocketAddr {
+    SocketAddr::from(([192, 0, 2, index], 27015))
+}
+
+#[test]
+fn cancelled_fetches_fail_and_release_their_connection() {
+    let server = blackhole();
+    let game = server.game();
+    let token = CancellationToken::new();
+
+    let mut fetch = pin!(game.fetch_cancellable(
+        MockQuery::default(),
+        address(1),
+        TimeoutSettings::default(),
+        &token
+    ));
+
+    assert!(poll_once(fetch.as_mut()).is_pending());
+    token.cancel();
+
+    let Poll::Ready(Err(err)) = poll_once(fetch.as_mut()) else {
+        panic!("the fetch wasn't cancelled");
+    };
+
+    assert_eq!(err.kind(), ErrorKind::Cancelled);
+    assert_eq!(server.count("schedule_disconnect"), 1);
+}
+
+#[test]
+fn cancelled_bulk_queries_stop_at_once() {
+    let server = blackhole();
+    let game = server.game();
+    let token = CancellationToken::new();
+    let targets = (1..=10).map(address);
+
+    let mut many = QueryMany::new(targets, 4, |&target| {
+        game.fetch_with(MockQuery::default(), target, TimeoutSettings::default())
+    })
+    .with_cancellation(&token);
+
+    {
+        let mut next = pin!(many.next());
+        assert!(poll_once(next.as_mut()).is_pending());
+
+        token.cancel();
+        assert!(matches!(poll_once(next.as_mut()), Poll::Ready(None)));
+    }
+
+    assert_eq!(many.in_flight(), 0);
+    assert_eq!(server.count("schedule_disconnect"), 4);
+}
+
+#[test]
+fn deadlines_bound_the_overall_limit() {
+    let soon = Instant::now() + Duration::from_secs(2);
+
+    let bounded = TimeoutSettings::default().until(soon);
+    assert!(bounded.overall.unwrap() <= Duration::from_secs(2));
+
+    let shorter = TimeoutSettings::default()
+        .with_overall(Duration::from_millis(10))
+        .until(soon);
+    assert_eq!(shorter.overall, Some(Duration::from_millis(10)));
+
+    let passed = TimeoutSettings::default().until(Instant::now() - Duration::from_secs(1));
+    assert_eq!(passed.overall, Some(Duration::ZERO));
+}
@@ -0,0 +1,114 @@
+//! Remembering which hosts answer queries without a challenge.
+
+use gstat_core::{
+    challenge::{ChallengeMemory, Requirement},
+    clock::ManualClock,
+};
+
+use std::{
+    net::{Ipv4Addr, SocketAddr},
+    thread,
+    time::Duration,
+};
+
+fn host() -> SocketAddr {
+    "192.0.2.1:27015".parse().unwrap()
+}
+
+/// Returns the `n`th of many distinct hosts, as a scanner would record them.
+fn nth_host(n: u32) -> SocketAddr {
+    SocketAddr::from((Ipv4Addr::from(0x0a00_0000 + n), 27015))
+}
+
+#[test]
+fn permissive_hosts_skip_the_challenge_until_they_require_one() {
+    let memory = ChallengeMemory::new();
+    let shared = memory.clone();
+
+    assert_eq!(memory.requirement(host()), Requirement::Unknown);
+    assert!(memory.skip_challenge(host()));
+
+    shared.record_required(host());
+    assert!(!memory.skip_challenge(host()));
+
+    memory.record_not_required(host());
+    assert!(shared.skip_challenge(host()));
+    assert_eq!(shared.requirement(host()), Requirement::NotRequired);
+
+    memory.record_required(host());
+    assert_eq!(memory.requirement(host()), Requirement::Required);
+
+    memory.forget(host());
+    assert!(memory.is_empty());
+}
+
+#[test]
+fn remembered_requirements_expire() {
+    let memory = ChallengeMemory::new().with_ttl(Duration::from_millis(10));
+
+    memory.record_required(host());
+    assert_eq!(memory.requirement(host()), Requirement::Required);
+
+    thread::sleep(Duration::from_millis(20));
+
+    assert_eq!(memory.requirement(host()), Requirement::Unknown);
+    assert!(memory.skip_challenge(host()));
+    assert_eq!(memory.len(), 0);
+}
+
+#[test]
+fn recording_removes_expired_hosts_beyond_the_limit() {
+    let clock = ManualClock::new();
+    let memory = ChallengeMemory::new()
+        .with_ttl(Duration::from_secs(60))
+        .with_max_hosts(3)
+        .with_clock(clock.clone());
+
+    for n in 0..3 {
+        memory.record_required(nth_host(n));
+    }
+
+    clock.advance(Duration::from_secs(60));
+    memory.record_not_required(host());
+
+    assert_eq!(memory.len(), 1);
+    assert_eq!(memory.requirement(host()), Requirement::NotRequired);
+}
+
+#[test]
+fn scanning_many_hosts_keeps_the_newest_within_the_limit() {
+    let clock = ManualClock::new();
+    let memory = ChallengeMemory::new()
+        .with_max_hosts(100)
+        .with_clock(clock.clone());
+
+    for n in 0..10_000 {
+        memory.record_not_required(nth_host(n));
+        clock.advance(Duration::from_millis(1));
+    }
+
+    assert_eq!(memory.len(), 100);
+    assert_eq!(memory.requirement(nth_host(0)), Requirement::Unknown);
+    assert_eq!(
+        memory.requirement(nth_host(9_999)),
+        Requirement::NotRequired
+    );
+}
+
+#[test]
+fn purging_removes_only_expired_hosts() {
+    let clock = ManualClock::new();
+    let memory = ChallengeMemory::new()
+        .with_ttl(Duration::from_secs(60))
+        .with_clock(clock.clone());
+
+    memory.record_required(nth_host(0));
+    clock.advance(Duration::from_secs(30));
+    memory.record_required(nth_host(1));
+    clock.advance(Duration::from_secs(30));
+
+    memory.purge();
+
+    assert_eq!(memory.len(), 1);
+    assert_eq!(memory.requirement(nth_host(1)), Requirement::Required);
+}
@@ -0,0 +1,138 @@
+//! Deterministic time with `ManualClock`, and running under `tokio::time::pause`.
+
+use gstat_core::{
+    clock::{Clock, ManualClock},
+    prelude::{Error, ErrorDetail, RetryPolicy},
+    rate_limit::{RateLimit, RateLimiter},
+    retry::{Backoff, Jitter},
+};
+
+use std::{
+    future::Future,
+    net::SocketAddr,
+    pin::{pin, Pin},
+    sync::atomic::{AtomicU32, Ordering},
+    task::{Context, Poll, Waker},
+    time::Duration,
+};
+
+/// Polls `future` once without waiting.
+fn poll_once<F: Future + ?Sized>(future: Pin<&mut F>) -> Poll<F::Output> {
+    future.poll(&mut Context::from_waker(Waker::noop()))
+}
+
+fn backoff(initial: Duration) -> RetryPolicy {
+    RetryPolicy::new(3)
+        .with_backoff(Backoff {
+            initial,
+            multiplier: 2.0,
+            max: Duration::from_secs(60),
+        })
+        .with_jitter(Jitter::None)
+}
+
+#[test]
+fn sleeps_complete_only_once_the_clock_is_advanced() {
+    let clock = ManualClock::new();
+    let start = clock.now();
+
+    let mut short = clock.sleep(Duration::from_secs(1));
+    let mut long = clock.sleep(Duration::from_secs(5));
+
+    assert!(poll_once(short.as_mut()).is_pending());
+    assert_eq!(clock.sleepers(), 2);
+    assert_eq!(clock.until_next(), Some(Duration::from_secs(1)));
+
+    clock.advance(Duration::from_millis(999));
+    assert!(poll_once(short.as_mut()).is_pending());
+
+    assert_eq!(clock.advance_to_next(), Some(Duration::from_millis(1)));
+    assert!(poll_once(short.as_mut()).is_ready());
+    assert!(poll_once(long.as_mut()).is_pending());
+
+    // A cancelled sleep stops counting as pending.
+    drop(long);
+    assert_eq!(clock.sleepers(), 0);
+    assert_eq!(clock.advance_to_next(), None);
+    assert_eq!(clock.now() - start, Duration::from_secs(1));
+}
+
+#[test]
+fn retry_backoff_waits_on_the_policy_clock() {
+    let clock = ManualClock::new();
+    let policy = backoff(Duration::from_secs(1)).with_clock(clock.clone());
+    let attempts = AtomicU32::new(0);
+
+    let run = policy.run(|_| {
+        let number = attempts.fetch_add(1, Ordering::SeqCst) + 1;
+
+        async move {
+            if number < 3 {
+                return Err(Error::<()>::ProtocolError(ErrorDetail::new("lost", None)));
+            }
+
+            Ok(number)
+        }
+    });
+    let mut run = pin!(run);
+
+    assert!(poll_once(run.as_mut()).is_pending());
+    assert_eq!(attempts.load(Ordering::SeqCst), 1);
+
+    // The delays double: one second, then two.
+    assert_eq!(clock.advance_to_next(), Some(Duration::from_secs(1)));
+    assert!(poll_once(run.as_mut()).is_pending());
+    assert_eq!(attempts.load(Ordering::SeqCst), 2);
+
+    assert_eq!(clock.advance_to_next(), Some(Duration::from_secs(2)));
+    assert!(matches!(poll_once(run.as_mut()), Poll::Ready(Ok(3))));
+    assert_eq!(clock.elapsed(), Duration::from_secs(3));
+}
+
+#[test]
+fn rate_limiter_refills_on_its_clock() {
+    let clock = ManualClock::new();
+    let limiter = RateLimiter::new()
+        .with_clock(clock.clone())
+        .with_global(RateLimit::new(10.0, 2));
+    let destination = SocketAddr::from(([192, 0, 2, 1], 27015));
+
+    assert_eq!(limiter.reserve(destination), Duration::ZERO);
+    assert_eq!(limiter.reserve(destination), Duration::ZERO);
+    assert_eq!(limiter.reserve(destination), Duration::from_millis(100));
+
+    // However long the test takes in real time, the bucket only refills as the clock moves.
+    clock.advance(Duration::from_millis(300));
+    assert_eq!(limiter.reserve(destination), Duration::ZERO);
+    assert_eq!(limiter.reserve(destination), Duration::ZERO);
+    assert_eq!(limiter.reserve(destination), Duration::from_millis(100));
+}
+
+#[cfg(feature = "rt-tokio")]
+#[test]
+fn the_runtime_clock_follows_paused_tokio_time() {
+    use gstat_core::runtime;
+
+    tokio::runtime::Builder::new_current_thread()
+        .enable_time()
+        .start_paused(true)
+        .build()
+        .unwrap()
+        .block_on(async {
+            let start = runtime::now();
+            let attempts = AtomicU32::new(0);
+
+            let result = backoff(Duration::from_secs(30))
+                .run(|_| {
+                    attempts.fetch_add(1, Ordering::SeqCst);
+                    async {
+                        Err::<(), _>(Error::<()>::ProtocolError(ErrorDetail::new("lost", None)))
+                    }
+                })
+                .await;
+
+            assert!(result.is_err());
+            assert_eq!(attempts.load(Ordering::SeqCst), 3);
+            assert_eq!(runtime::now() - start, Duration::from_secs(90));
+        });
+}
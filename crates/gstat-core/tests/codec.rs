@@ -0,0 +1,215 @@
+//! Edge cases of the shared varint and string encodings.
+
+use gstat_core::{
+    bytes::Bytes,
+    codec::{
+        reader::{ByteReader, DecodeError},
+        varint_len,
+        writer::{ByteWriter, EncodeError},
+        Endian, LengthWidth, MAX_VARINT_LEN,
+    },
+    prelude::ErrorKind,
+};
+
+fn encode(write: impl FnOnce(&mut ByteWriter) -> &mut ByteWriter) -> Vec<u8> {
+    let mut writer = ByteWriter::new();
+    write(&mut writer);
+    writer.finish().unwrap()
+}
+
+fn reader(bytes: &[u8]) -> ByteReader {
+    ByteReader::new(Bytes::copy_from_slice(bytes))
+}
+
+#[test]
+fn varints_match_the_minecraft_reference_encodings() {
+    let cases: &[(i32, &[u8])] = &[
+        (0, b"\x00"),
+        (1, b"\x01"),
+        (127, b"\x7f"),
+        (128, b"\x80\x01"),
+        (255, b"\xff\x01"),
+        (25565, b"\xdd\xc7\x01"),
+        (2097151, b"\xff\xff\x7f"),
+        (i32::MAX, b"\xff\xff\xff\xff\x07"),
+        (-1, b"\xff\xff\xff\xff\x0f"),
+        (i32::MIN, b"\x80\x80\x80\x80\x08"),
+    ];
+
+    for &(value, bytes) in cases {
+        assert_eq!(encode(|writer| writer.write_varint_i32(value)), bytes);
+        assert_eq!(reader(bytes).read_varint_i32().unwrap(), value);
+        assert_eq!(varint_len(u64::from(value as u32)), bytes.len());
+    }
+}
+
+#[test]
+fn varlongs_match_the_minecraft_reference_encodings() {
+    let cases: &[(i64, &[u8])] = &[
+        (0, b"\x00"),
+        (127, b"\x7f"),
+        (128, b"\x80\x01"),
+        (i64::from(i32::MAX), b"\xff\xff\xff\xff\x07"),
+        (i64::MAX, b"\xff\xff\xff\xff\xff\xff\xff\xff\x7f"),
+        (-1, b"\xff\xff\xff\xff\xff\xff\xff\xff\xff\x01"),
+        (i64::MIN, b"\x80\x80\x80\x80\x80\x80\x80\x80\x80\x01"),
+    ];
+
+    for &(value, bytes) in cases {
+        assert_eq!(encode(|writer| writer.write_varint_i64(value)), bytes);
+        assert_eq!(reader(bytes).read_varint_i64().unwrap(), value);
+        assert_eq!(varint_len(value as u64), bytes.len());
+    }
+
+    assert_eq!(varint_len(u64::MAX), MAX_VARINT_LEN);
+}
+
+#[test]
+fn malformed_varints_are_rejected_without_consuming() {
+    let overlong: &[&[u8]] = &[
+        b"\xff\xff\xff\xff\xff\xff\xff\xff\xff\x02",
+        b"\x80\x80\x80\x80\x80\x80\x80\x80\x80\x80\x00",
+    ];
+
+    for bytes in overlong {
+        let mut reader = reader(bytes);
+        assert_eq!(
+            reader.read_varint_i64().unwrap_err().detail().inner(),
+            Some(&DecodeError::VarintOverflow { offset: 0 })
+        );
+        assert_eq!(reader.position(), 0);
+    }
+
+    let mut wide = reader(b"\x80\x80\x80\x80\x10");
+    assert_eq!(
+        wide.read_varint_i32().unwrap_err().detail().inner(),
+        Some(&DecodeError::VarintOverflow { offset: 0 })
+    );
+    assert_eq!(wide.read_varint_i64().unwrap(), 1 << 32);
+
+    for bytes in [&b""[..], b"\x80", b"\xff\xff\xff"] {
+        let mut truncated = reader(bytes);
+        assert_eq!(
+            truncated.read_varint_i32().unwrap_err().kind(),
+            ErrorKind::Truncated
+        );
+        assert_eq!(truncated.position(), 0);
+    }
+}
+
+#[test]
+fn utf16_strings_round_trip_including_surrogate_pairs() {
+    for string in ["", "Server", "Ünïcödé", "日本語のサーバー", "🎮 gg"] {
+        let units = string.encode_utf16().count();
+
+        let bytes = encode(|writer| writer.write_utf16le_string(string));
+        assert_eq!(bytes.len(), units * 2);
+        assert_eq!(reader(&bytes).read_utf16le_string(units).unwrap(), string);
+
+        let terminated = encode(|writer| writer.write_utf16le_cstring(string).write_u8(7));
+        let mut decoded = reader(&terminated);
+        assert_eq!(decoded.read_utf16le_cstring().unwrap(), string);
+        assert_eq!(decoded.read_u8().unwrap(), 7);
+    }
+
+    assert_eq!(
+        encode(|writer| writer.write_utf16le_string("A€")),
+        b"A\x00\xac\x20"
+    );
+}
+
+#[test]
+fn malformed_utf16_strings_are_rejected() {
+    let mut unpaired = reader(b"A\x00\x3d\xd8\x00\x00");
+    assert_eq!(
+        unpaired
+            .read_utf16le_cstring()
+            .unwrap_err()
+            .detail()
+            .inner(),
+        Some(&DecodeError::InvalidUtf16 { offset: 0 })
+    );
+    assert_eq!(unpaired.position(), 0);
+    assert_eq!(unpaired.read_utf16le_cstring_lossy().unwrap(), "A\u{fffd}");
+    assert!(unpaired.is_empty());
+
+    let mut odd = reader(b"A\x00B");
+    assert_eq!(
+        odd.read_utf16le_string(2).unwrap_err().kind(),
+        ErrorKind::Truncated
+    );
+    assert_eq!(
+        odd.read_utf16le_string(usize::MAX).unwrap_err().kind(),
+        ErrorKind::Truncated
+    );
+    assert_eq!(odd.position(), 0);
+
+    // The terminator must be aligned to a code unit.
+    let mut misaligned = reader(b"\x00A\x00\x00");
+    assert_eq!(misaligned.read_utf16le_cstring().unwrap(), "\u{4100}");
+    let mut unterminated = reader(b"A\x00\x00");
+    assert_eq!(
+        unterminated
+            .read_utf16le_cstring()
+            .unwrap_err()
+            .detail()
+            .inner(),
+        Some(&DecodeError::Unterminated { offset: 0 })
+    );
+
+    let mut writer = ByteWriter::new();
+    writer.write_utf16le_cstring("a\0b");
+    assert_eq!(
+        writer.finish().unwrap_err().detail().inner(),
+        Some(&EncodeError::InteriorNul { offset: 0 })
+    );
+}
+
+#[test]
+fn prefixed_strings_support_every_width_and_byte_order() {
+    let cases: &[(LengthWidth, Endian, &[u8])] = &[
+        (LengthWidth::U8, Endian::Little, b"\x03map"),
+        (LengthWidth::U16, Endian::Little, b"\x03\x00map"),
+        (LengthWidth::U16, Endian::Big, b"\x00\x03map"),
+        (LengthWidth::U32, Endian::Little, b"\x03\x00\x00\x00map"),
+        (LengthWidth::U32, Endian::Big, b"\x00\x00\x00\x03map"),
+    ];
+
+    for &(width, endian, bytes) in cases {
+        assert_eq!(
+            encode(|writer| writer.write_prefixed_string("map", width, endian)),
+            bytes
+        );
+        assert_eq!(
+            reader(bytes).read_prefixed_string(width, endian).unwrap(),
+            "map"
+        );
+
+        let mut short = reader(&bytes[..bytes.len() - 1]);
+        assert_eq!(
+            short
+                .read_prefixed_string(width, endian)
+                .unwrap_err()
+                .kind(),
+            ErrorKind::Truncated
+        );
+        assert_eq!(short.position(), 0);
+    }
+
+    assert_eq!(reader(b"\x00").read_pascal_string().unwrap(), "");
+
+    let long = "x".repeat(256);
+    let mut writer = ByteWriter::new();
+    writer.write_pascal_string(&long);
+    assert_eq!(
+        writer.finish().unwrap_err().detail().inner(),
+        Some(&EncodeError::LengthOverflow {
+            length: 256,
+            width: LengthWidth::U8,
+        })
+    );
+    assert_eq!(
+        encode(|writer| writer.write_prefixed_string(&long, LengthWidth::U16, Endian::Big)).len(),
+        258
+    );
+}
@@ -0,0 +1,627 @@
+//! Helpers shared by the integration tests.
+//!
+//! `Mock` scripts a server: every call its protocol receives is logged, a step can be made
+//! to fail and the responses come from a hook, so each test only describes what differs
+//! from a server answering at once. `Mock::game` and `Mock::protocol` turn the script into
+//! a `MockGame` or a bare `MockProtocol`. Traits the mock doesn't implement, such as
+//! `Authenticate` or `Correlated`, are implemented for `MockProtocol` by the tests that need
+//! them.
+
+// Each test crate uses a different part of the mock.
+#![allow(dead_code)]
+
+use gstat_core::{
+    bytes::Bytes,
+    challenge::ChallengeMemory,
+    events::Subscribers,
+    prelude::{
+        Error, ErrorDetail, ErrorKind, Game, Handshake, Parser, Player, Protocol, Query,
+        QueryOptions, RawTransport, Response, RetryPolicy, ServerInfo, TimeoutSettings,
+    },
+    registry::GameEntry,
+    retry::{Backoff, Jitter},
+    standards::{handshake::Challenge, response::Meter},
+};
+
+use std::{
+    collections::VecDeque,
+    error::Error as StdError,
+    fmt::{Debug, Display, Formatter, Result as FmtResult},
+    future::Future,
+    net::SocketAddr,
+    pin::{pin, Pin},
+    sync::{Arc, Mutex, MutexGuard},
+    task::{Context, Poll, Waker},
+    time::Duration,
+};
+
+/// Polls a future that never waits on I/O to completion.
+pub fn block_on<F: Future>(future: F) -> F::Output {
+    let mut future = pin!(future);
+    let mut context = Context::from_waker(Waker::noop());
+
+    loop {
+        if let Poll::Ready(output) = future.as_mut().poll(&mut context) {
+            return output;
+        }
+    }
+}
+
+/// Runs a future waiting on timers or sockets to completion, on a tokio runtime of its own.
+pub fn run<F: Future>(future: F) -> F::Output {
+    tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .unwrap()
+        .block_on(future)
+}
+
+/// Returns a policy retrying without delay, so the tests need no timer.
+pub fn immediate(max_attempts: u32) -> RetryPolicy {
+    RetryPolicy::new(max_attempts)
+        .with_backoff(Backoff {
+            initial: Duration::ZERO,
+            ..Backoff::default()
+        })
+        .with_jitter(Jitter::None)
+}
+
+/// The calls a mock protocol received, in order.
+pub type Log = Arc<Mutex<Vec<String>>>;
+
+/// The result of a mock protocol's operations.
+pub type MockResult<T> = Result<T, Error<MockError>>;
+
+/// The future a `Mock::respond_later` hook answers with.
+pub type Reply<R> = Pin<Box<dyn Future<Output = MockResult<R>> + Send>>;
+
+/// The error of the mock protocol, naming what failed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MockError(pub &'static str);
+
+impl Display for MockError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.write_str(self.0)
+    }
+}
+
+impl StdError for MockError {}
+
+/// Returns a `ProtocolError` of `kind` carrying `MockError(message)`.
+pub fn failure(kind: ErrorKind, message: &'static str) -> Error<MockError> {
+    Error::ProtocolError(ErrorDetail::new(message, Some(MockError(message))).with_kind(kind))
+}
+
+/// The game-specific options of `MockQuery`, `()` unless a test needs its own.
+pub trait MockOptions: Clone + Debug + Default + Send + Sync + 'static {}
+
+impl<O: Clone + Debug + Default + Send + Sync + 'static> MockOptions for O {}
+
+/// The query of the mock protocol, carrying the options it was built from.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MockQuery<O = ()>(pub QueryOptions<O>);
+
+impl<O: MockOptions> MockQuery<O> {
+    /// Creates a query whose game-specific options are `extra`.
+    pub fn with_extra(extra: O) -> Self {
+        MockQuery(QueryOptions {
+            extra,
+            ..QueryOptions::default()
+        })
+    }
+}
+
+impl<O: MockOptions> Query for MockQuery<O> {
+    type E = MockError;
+    type Options = O;
+
+    fn from_options(options: QueryOptions<O>) -> Self {
+        MockQuery(options)
+    }
+}
+
+/// The responses a mock protocol can answer with, `MockResponse` unless a test needs a
+/// type of its own.
+pub trait MockAnswer: Response<E = MockError> + Default + 'static {}
+
+impl<R: Response<E = MockError> + Default + 'static> MockAnswer for R {}
+
+/// The response of the mock protocol.
+#[derive(Clone, Debug, Default)]
+pub struct MockResponse {
+    pub info: ServerInfo,
+    pub players: Vec<Player>,
+}
+
+impl MockResponse {
+    /// Creates a response from a server called `name`.
+    pub fn named(name: impl Into<String>) -> Self {
+        MockResponse {
+            info: ServerInfo {
+                name: name.into(),
+                ..ServerInfo::default()
+            },
+            players: Vec::new(),
+        }
+    }
+
+    /// Lists `players` on the server.
+    pub fn with_players(mut self, players: &[&str]) -> Self {
+        self.players = players.iter().copied().map(Player::new).collect();
+        self
+    }
+}
+
+impl Response for MockResponse {
+    type E = MockError;
+
+    fn new() -> Result<Self, Error<Self::E>> {
+        Ok(MockResponse::default())
+    }
+
+    fn to_common(&self) -> ServerInfo {
+        self.info.clone()
+    }
+
+    fn players(&self) -> Vec<Player> {
+        self.players.clone()
+    }
+}
+
+/// The parser of the mock protocol.
+///
+/// Queries serialize to `status`, and any non-empty packet parses into a default response.
+pub struct MockParser;
+
+impl<'a, O: MockOptions, R: MockAnswer> Parser<'a, MockQuery<O>, R> for MockParser {
+    type SE = MockError;
+    type DE = MockError;
+
+    fn _serialize_query(&self, _query: &MockQuery<O>) -> Result<Vec<u8>, Self::SE> {
+        Ok(b"status".to_vec())
+    }
+
+    fn _deserialize_response(&self, data: Bytes) -> Result<R, Self::DE> {
+        match data.is_empty() {
+            true => Err(MockError("garbled packet")),
+            false => Ok(R::default()),
+        }
+    }
+}
+
+/// Parses `packet` with `MockParser`, the way a protocol reading it from the wire would.
+pub fn parse(packet: &'static [u8]) -> MockResult<MockResponse> {
+    Parser::<MockQuery, MockResponse>::deserialize_response(&MockParser, Bytes::from_static(packet))
+}
+
+/// What a response hook knows about the exchange it answers.
+#[derive(Clone, Debug)]
+pub struct Exchange<O = ()> {
+    /// The address the protocol connected to, if it did.
+    pub address: Option<SocketAddr>,
+    /// The last query sent, or the default one if none was.
+    pub query: MockQuery<O>,
+    /// How many responses this protocol was asked for, this one included.
+    pub attempt: u32,
+}
+
+type ConnectHook = Arc<dyn Fn(SocketAddr) -> MockResult<()> + Send + Sync>;
+
+type RespondHook<O, R> = Arc<dyn Fn(Exchange<O>) -> Reply<R> + Send + Sync>;
+
+/// `Mock` scripts the server behind a `MockGame` or `MockProtocol`.
+///
+/// By default the server answers every query at once with a default response, and every call is logged by name: `connect`, `send_query`, `receive_response`,
+/// `disconnect` and `schedule_disconnect`, plus `send <bytes>` and `receive` for raw
+/// packets and the challenge accepted by the handshake. Clones share their log.
+pub struct Mock<O = (), R = MockResponse> {
+    log: Log,
+    fail_at: Option<&'static str>,
+    retry_policy: RetryPolicy,
+    subscribers: Subscribers,
+    metered: bool,
+    handshake: bool,
+    memory: Option<ChallengeMemory>,
+    packets: Vec<Option<Bytes>>,
+    options: QueryOptions<O>,
+    entry: Option<&'static GameEntry>,
+    connect: Option<ConnectHook>,
+    respond: RespondHook<O, R>,
+}
+
+// Derived, this would require `R: Clone`.
+impl<O: Clone, R> Clone for Mock<O, R> {
+    fn clone(&self) -> Self {
+        Mock {
+            log: self.log.clone(),
+            fail_at: self.fail_at,
+            retry_policy: self.retry_policy.clone(),
+            subscribers: self.subscribers.clone(),
+            metered: self.metered,
+            handshake: self.handshake,
+            memory: self.memory.clone(),
+            packets: self.packets.clone(),
+            options: self.options.clone(),
+            entry: self.entry,
+            connect: self.connect.clone(),
+            respond: self.respond.clone(),
+        }
+    }
+}
+
+impl Mock {
+    /// Creates a server answering every query at once.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<O: MockOptions, R: MockAnswer> Default for Mock<O, R> {
+    fn default() -> Self {
+        Mock {
+            log: Log::default(),
+            fail_at: None,
+            retry_policy: RetryPolicy::default(),
+            subscribers: Subscribers::default(),
+            metered: false,
+            handshake: false,
+            memory: None,
+            packets: Vec::new(),
+            options: QueryOptions::default(),
+            entry: None,
+            connect: None,
+            respond: Arc::new(|_| Box::pin(async { Ok(R::default()) })),
+        }
+    }
+}
+
+impl<O: MockOptions, R: MockAnswer> Mock<O, R> {
+    /// Fails the step named `step` with a `ProtocolError` carrying `MockError(step)`.
+    pub fn failing_at(mut self, step: &'static str) -> Self {
+        self.fail_at = Some(step);
+        self
+    }
+
+    /// Sets the retry policy of the protocol.
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    /// Sets the subscribers the protocol reports its connection events to.
+    pub fn with_subscribers(mut self, subscribers: Subscribers) -> Self {
+        self.subscribers = subscribers;
+        self
+    }
+
+    /// Meters the protocol, which records a `banner` packet when it connects and a
+    /// `response` packet per response.
+    pub fn metered(mut self) -> Self {
+        self.metered = true;
+        self
+    }
+
+    /// Obtains a challenge before each query, GameSpy 4 style: the server's packets answer
+    /// `challenge?` with `C<token>`, or anything else if no token is needed.
+    pub fn with_handshake(mut self, memory: Option<ChallengeMemory>) -> Self {
+        self.handshake = true;
+        self.memory = memory;
+        self
+    }
+
+    /// Queues the raw packets `RawTransport::receive` returns, with `None` for a packet
+    /// that times out.
+    pub fn with_packets(mut self, packets: &[Option<&'static [u8]>]) -> Self {
+        self.packets = packets
+            .iter()
+            .map(|packet| packet.map(Bytes::from_static))
+            .collect();
+        self
+    }
+
+    /// Sets the options of the game's default query.
+    pub fn with_options(mut self, options: QueryOptions<O>) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Describes the game with a registry entry, for its ports.
+    pub fn with_registry_entry(mut self, entry: Option<&'static GameEntry>) -> Self {
+        self.entry = entry;
+        self
+    }
+
+    /// Calls `connect` with the address of every connection, failing it if `connect` fails.
+    pub fn on_connect(
+        mut self,
+        connect: impl Fn(SocketAddr) -> MockResult<()> + Send + Sync + 'static,
+    ) -> Self {
+        self.connect = Some(Arc::new(connect));
+        self
+    }
+
+    /// Answers each query with what `respond` returns.
+    pub fn respond(
+        self,
+        respond: impl Fn(&Exchange<O>) -> MockResult<R> + Send + Sync + 'static,
+    ) -> Self {
+        self.respond_later(move |exchange| {
+            let response = respond(&exchange);
+            async move { response }
+        })
+    }
+
+    /// Answers each query with the output of the future `respond` returns.
+    pub fn respond_later<F>(
+        mut self,
+        respond: impl Fn(Exchange<O>) -> F + Send + Sync + 'static,
+    ) -> Self
+    where
+        F: Future<Output = MockResult<R>> + Send + 'static,
+    {
+        self.respond = Arc::new(move |exchange| Box::pin(respond(exchange)));
+        self
+    }
+
+    /// Returns the calls logged so far.
+    pub fn calls(&self) -> Vec<String> {
+        self.log.lock().unwrap().clone()
+    }
+
+    /// Returns how often `call` was logged.
+    pub fn count(&self, call: &str) -> usize {
+        self.log
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|c| *c == call)
+            .count()
+    }
+
+    /// Returns the log, for tests that interleave their own entries with the calls.
+    pub fn log(&self) -> Log {
+        self.log.clone()
+    }
+
+    /// Returns a game querying this server.
+    pub fn game(&self) -> MockGame<O, MockProtocol<O, R>> {
+        let mock = self.clone();
+
+        MockGame {
+            protocol: Arc::new(move || mock.protocol()),
+            options: self.options.clone(),
+            entry: self.entry,
+        }
+    }
+
+    /// Returns a protocol connected to this server.
+    pub fn protocol(&self) -> MockProtocol<O, R> {
+        MockProtocol {
+            mock: self.clone(),
+            state: Mutex::new(State {
+                address: None,
+                query: None,
+                responses: 0,
+                inbox: self.packets.iter().cloned().collect(),
+            }),
+            subscribers: self.subscribers.clone(),
+            meter: Meter::new(),
+        }
+    }
+}
+
+impl<O: MockOptions> Mock<O> {
+    /// Loses the first `lost` responses of each protocol, then answers `info`.
+    pub fn losing(self, lost: u32) -> Self {
+        self.respond(move |exchange| match exchange.attempt <= lost {
+            true => Err(failure(ErrorKind::Timeout, "lost")),
+            false => parse(b"info"),
+        })
+    }
+}
+
+/// The state of one `MockProtocol`.
+struct State<O> {
+    address: Option<SocketAddr>,
+    query: Option<MockQuery<O>>,
+    responses: u32,
+    inbox: VecDeque<Option<Bytes>>,
+}
+
+/// The protocol of a `Mock` server.
+pub struct MockProtocol<O = (), R = MockResponse> {
+    mock: Mock<O, R>,
+    state: Mutex<State<O>>,
+    subscribers: Subscribers,
+    meter: Meter,
+}
+
+impl<O: MockOptions, R: MockAnswer> MockProtocol<O, R> {
+    /// Logs a call.
+    pub fn record(&self, call: impl Into<String>) {
+        self.mock.log.lock().unwrap().push(call.into());
+    }
+
+    /// Logs the step `step`, failing if the mock fails at it.
+    fn step(&self, step: &'static str) -> MockResult<()> {
+        self.record(step);
+
+        match self.mock.fail_at == Some(step) {
+            true => Err(Error::ProtocolError(ErrorDetail::new(
+                "scripted failure",
+                Some(MockError(step)),
+            ))),
+            false => Ok(()),
+        }
+    }
+
+    fn state(&self) -> MutexGuard<'_, State<O>> {
+        self.state.lock().unwrap()
+    }
+}
+
+impl<'a, O: MockOptions, R: MockAnswer> Protocol<'a> for MockProtocol<O, R> {
+    type Q = MockQuery<O>;
+    type R = R;
+    type P = MockParser;
+    type E = MockError;
+
+    fn retry_policy(&self) -> RetryPolicy {
+        self.mock.retry_policy.clone()
+    }
+
+    fn subscribers(&self) -> Option<&Subscribers> {
+        Some(&self.subscribers)
+    }
+
+    fn meter(&self) -> Option<&Meter> {
+        self.mock.metered.then_some(&self.meter)
+    }
+
+    async fn _connect(
+        &self,
+        address: SocketAddr,
+        _timeouts: &TimeoutSettings,
+    ) -> Result<(), Error<Self::E>> {
+        self.meter.record(&Bytes::from_static(b"banner"));
+        self.step("connect")?;
+
+        if let Some(connect) = &self.mock.connect {
+            connect(address)?;
+        }
+
+        self.state().address = Some(address);
+        Ok(())
+    }
+
+    fn schedule_disconnect(&self) {
+        self.record("schedule_disconnect");
+    }
+
+    async fn handshake(
+        &self,
+        address: SocketAddr,
+        timeouts: &TimeoutSettings,
+    ) -> Result<(), Error<Self::E>> {
+        match self.mock.handshake {
+            true => self.perform_handshake(address, timeouts).await,
+            false => Ok(()),
+        }
+    }
+
+    async fn send_query(
+        &self,
+        query: Self::Q,
+        _timeouts: &TimeoutSettings,
+    ) -> Result<(), Error<Self::E>> {
+        self.step("send_query")?;
+        Parser::<MockQuery<O>, R>::serialize_query(&MockParser, &query)?;
+        self.state().query = Some(query);
+        Ok(())
+    }
+
+    async fn receive_response(
+        &self,
+        _timeouts: &TimeoutSettings,
+    ) -> Result<Self::R, Error<Self::E>> {
+        self.meter.record(&Bytes::from_static(b"response"));
+        self.step("receive_response")?;
+
+        let exchange = {
+            let mut state = self.state();
+            state.responses += 1;
+
+            Exchange {
+                address: state.address,
+                query: state.query.clone().unwrap_or_default(),
+                attempt: state.responses,
+            }
+        };
+
+        (self.mock.respond)(exchange).await
+    }
+
+    async fn disconnect(&self) -> Result<(), Error<Self::E>> {
+        self.step("disconnect")
+    }
+}
+
+impl<'a, O: MockOptions, R: MockAnswer> RawTransport<'a> for MockProtocol<O, R> {
+    async fn send(&self, data: &[u8], _timeouts: &TimeoutSettings) -> Result<(), Error<Self::E>> {
+        self.record(format!("send {}", data.escape_ascii()));
+        Ok(())
+    }
+
+    async fn receive(&self, _timeouts: &TimeoutSettings) -> Result<Bytes, Error<Self::E>> {
+        self.record("receive");
+
+        let packet = self.state().inbox.pop_front().flatten();
+        packet.ok_or_else(|| failure(ErrorKind::Timeout, "timed out"))
+    }
+}
+
+impl<'a, O: MockOptions, R: MockAnswer> Handshake<'a> for MockProtocol<O, R> {
+    fn challenge_request(&self) -> Vec<u8> {
+        b"challenge?".to_vec()
+    }
+
+    fn read_challenge(&self, packet: Bytes) -> Result<Challenge, Error<Self::E>> {
+        match packet.first() {
+            Some(b'C') => Ok(Challenge::Token(packet.slice(1..))),
+            Some(_) => Ok(Challenge::NotRequired(packet)),
+            None => Err(Error::ParserError(ErrorDetail::new(
+                "Empty challenge",
+                Some(MockError("empty challenge")),
+            ))),
+        }
+    }
+
+    fn accept_challenge(&self, challenge: Challenge) {
+        self.record(match challenge {
+            Challenge::Token(token) => format!("token {}", token.escape_ascii()),
+            Challenge::NotRequired(_) => "no token".to_string(),
+            Challenge::Skipped => "skipped".to_string(),
+        });
+    }
+
+    fn challenge_memory(&self) -> Option<&ChallengeMemory> {
+        self.mock.memory.as_ref()
+    }
+}
+
+/// The game of a `Mock` server, whose protocol may be wrapped in layers.
+pub struct MockGame<O = (), P = MockProtocol<O>> {
+    protocol: Arc<dyn Fn() -> P + Send + Sync>,
+    options: QueryOptions<O>,
+    entry: Option<&'static GameEntry>,
+}
+
+impl<O: MockOptions, P: 'static> MockGame<O, P> {
+    /// Wraps the protocol of every query with `wrap`, e.g. to add a `ProtocolLayer`.
+    pub fn layered<L>(self, wrap: impl Fn(P) -> L + Send + Sync + 'static) -> MockGame<O, L> {
+        let protocol = self.protocol;
+
+        MockGame {
+            protocol: Arc::new(move || wrap(protocol())),
+            options: self.options,
+            entry: self.entry,
+        }
+    }
+}
+
+impl<'a, O: MockOptions, P: Protocol<'a, Q = MockQuery<O>>> Game<'a, P> for MockGame<O, P> {
+    const GAME_NAME: &'static str = "Mock";
+    const RELEASE_YEAR: u32 = 2024;
+
+    fn _protocol(&self) -> P {
+        (self.protocol)()
+    }
+
+    fn registry_entry(&self) -> Option<&'static GameEntry> {
+        self.entry
+    }
+
+    fn query_options(&self) -> QueryOptions<O> {
+        self.options.clone()
+    }
+}
@@ -0,0 +1,84 @@
+//! Matching out-of-order responses to their requests with correlation IDs.
+
+use gstat_core::{
+    bytes::Bytes,
+    prelude::{Correlated, TimeoutSettings},
+    standards::correlation::{CorrelationId, CorrelationIds, Correlator},
+};
+
+use common::{block_on, Mock, MockProtocol};
+
+mod common;
+
+/// The packets of these tests are RCON-like, starting with a little-endian request ID.
+impl<'a> Correlated<'a> for MockProtocol {
+    fn response_id(&self, packet: &[u8]) -> Option<CorrelationId> {
+        let id = packet.get(..4)?.try_into().ok()?;
+
+        Some(CorrelationId::from_u32(u32::from_le_bytes(id)))
+    }
+}
+
+fn id(value: u32) -> CorrelationId {
+    CorrelationId::from_u32(value)
+}
+
+#[test]
+fn responses_to_other_requests_are_held_for_them() {
+    let mock = Mock::new().with_packets(&[Some(b"\x02\0\0\0second"), Some(b"\x01\0\0\0first")]);
+    let rcon = mock.protocol();
+    let correlator = Correlator::new();
+    let timeouts = TimeoutSettings::default();
+
+    let first = block_on(rcon.receive_correlated(id(1), &correlator, &timeouts)).unwrap();
+    assert_eq!(&first[4..], b"first");
+    assert_eq!(correlator.len(), 1);
+
+    let second = block_on(rcon.receive_correlated(id(2), &correlator, &timeouts)).unwrap();
+    assert_eq!(&second[4..], b"second");
+    assert!(correlator.is_empty());
+    assert_eq!(mock.count("receive"), 2);
+}
+
+#[test]
+fn packets_without_an_id_are_discarded() {
+    let rcon = Mock::new()
+        .with_packets(&[Some(b"\x07"), Some(b"\x01\0\0\0ok")])
+        .protocol();
+    let correlator = Correlator::new();
+
+    let packet =
+        block_on(rcon.receive_correlated(id(1), &correlator, &TimeoutSettings::default())).unwrap();
+
+    assert_eq!(&packet[4..], b"ok");
+    assert!(correlator.is_empty());
+}
+
+#[test]
+fn full_correlator_drops_the_oldest_packet() {
+    let correlator = Correlator::new().with_max_stashed(2);
+
+    correlator.stash(id(1), Bytes::from_static(b"a"));
+    correlator.stash(id(2), Bytes::from_static(b"b"));
+    correlator.stash(id(2), Bytes::from_static(b"c"));
+
+    assert_eq!(correlator.take(id(1)), None);
+    assert_eq!(correlator.take(id(2)).as_deref(), Some(&b"b"[..]));
+    assert_eq!(correlator.take(id(2)).as_deref(), Some(&b"c"[..]));
+}
+
+#[test]
+fn ids_wrap_within_their_mask_and_skip_zero() {
+    let ids = CorrelationIds::new().with_mask(0b11);
+    let shared = ids.clone();
+
+    let drawn: Vec<u32> = (0..4)
+        .map(|index| match index % 2 {
+            0 => ids.next_id(),
+            _ => shared.next_id(),
+        })
+        .map(|id| id.as_u32())
+        .collect();
+
+    assert_eq!(drawn, [1, 2, 3, 1]);
+}
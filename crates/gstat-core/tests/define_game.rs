@@ -0,0 +1,134 @@
+//! Games defined with `define_game!`.
+
+use gstat_core::{
+    define_game,
+    prelude::{Error, Game, QueryOptions, Response, ServerInfo},
+};
+
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+
+use common::{block_on, Mock, MockError, MockProtocol};
+
+mod common;
+
+/// The dialect a server speaks, a game-specific query option.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+enum Dialect {
+    #[default]
+    Plain,
+    Compressed,
+}
+
+/// A response echoing the options it was queried with.
+#[derive(Default)]
+struct Echo(QueryOptions<Dialect>);
+
+impl Response for Echo {
+    type E = MockError;
+
+    fn new() -> Result<Self, Error<Self::E>> {
+        Ok(Echo(QueryOptions::default()))
+    }
+
+    fn to_common(&self) -> ServerInfo {
+        ServerInfo {
+            name: format!("{:?}", self.0.extra),
+            ..ServerInfo::default()
+        }
+    }
+}
+
+/// A protocol answering with the options of the last query.
+type Mirror = MockProtocol<Dialect, Echo>;
+
+fn mirror() -> Mirror {
+    Mock::default()
+        .respond(|exchange| Ok(Echo(exchange.query.0.clone())))
+        .protocol()
+}
+
+define_game! {
+    /// A game with only the required entries.
+    struct Minimal: Mirror {
+        name: "Minimal",
+        release_year: 2001,
+        protocol: mirror(),
+    }
+}
+
+define_game! {
+    /// A game found in the registry, speaking the compressed dialect.
+    pub struct Compressed: Mirror {
+        name: "Compressed",
+        release_year: 2018,
+        protocol: mirror(),
+        registry: "rust",
+        players: true,
+        quirks: Dialect::Compressed,
+    }
+}
+
+define_game! {
+    struct Pinned: Mirror {
+        name: "Pinned",
+        release_year: 2007,
+        protocol: mirror(),
+        default_port: 7777,
+        rules: true,
+    }
+}
+
+fn host() -> IpAddr {
+    IpAddr::V4(Ipv4Addr::new(192, 0, 2, 1))
+}
+
+#[test]
+fn the_spec_becomes_the_game_constants() {
+    assert_eq!(<Minimal as Game<Mirror>>::GAME_NAME, "Minimal");
+    assert_eq!(<Minimal as Game<Mirror>>::RELEASE_YEAR, 2001);
+    assert_eq!(<Compressed as Game<Mirror>>::GAME_NAME, "Compressed");
+    assert_eq!(<Pinned as Game<Mirror>>::RELEASE_YEAR, 2007);
+}
+
+#[test]
+fn ports_come_from_the_registry_or_the_spec() {
+    assert_eq!(Minimal.default_port(), None);
+    assert_eq!(Minimal.query_address(host(), None), None);
+
+    assert_eq!(Compressed.default_port(), Some(28015));
+    assert_eq!(
+        Compressed.query_address(host(), None),
+        Some(SocketAddr::new(host(), 28017))
+    );
+
+    assert!(Pinned.registry_entry().is_none());
+    assert_eq!(
+        Pinned.query_address(host(), None),
+        Some(SocketAddr::new(host(), 7777))
+    );
+}
+
+#[test]
+fn quirks_preconfigure_the_query_options() {
+    assert_eq!(Minimal.query_options(), QueryOptions::default());
+    assert_eq!(
+        Compressed.query_options(),
+        QueryOptions {
+            players: true,
+            rules: false,
+            extra: Dialect::Compressed,
+        }
+    );
+    assert!(Pinned.query_options().rules);
+}
+
+#[test]
+fn fetches_send_the_quirks() {
+    let address = SocketAddr::new(host(), 28017);
+
+    let fetched = block_on(Compressed.fetch_default(address)).unwrap();
+    assert_eq!(fetched.response.to_common().name, "Compressed");
+
+    let fetched = block_on(Minimal.fetch_default(address)).unwrap();
+    assert_eq!(fetched.response.0, QueryOptions::default());
+}
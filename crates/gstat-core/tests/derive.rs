@@ -0,0 +1,137 @@
+//! Describing packets with `#[derive(GstatQuery, GstatResponse)]`.
+
+use gstat_core::{
+    byte_str::ByteStr,
+    bytes::Bytes,
+    codec::{
+        reader::DecodeError,
+        wire::{Decode, Encode, WireParser},
+    },
+    prelude::{AccessPolicy, ErrorKind, Parser, Player, Query, QueryOptions, Response, Value},
+    GstatQuery, GstatResponse,
+};
+
+#[derive(Clone, Debug, Default, PartialEq, GstatQuery)]
+#[gstat(header = b"\xFE\xFD", endian = "big")]
+struct StatusQuery {
+    session: u32,
+    #[gstat(players)]
+    players: bool,
+    #[gstat(rules)]
+    rules: bool,
+    #[gstat(skip)]
+    note: String,
+}
+
+#[derive(Debug, Default, GstatResponse)]
+#[gstat(header = b"\x00", endian = "big", player_list = roster)]
+struct StatusResponse {
+    session: u32,
+    #[gstat(common = "name")]
+    name: ByteStr,
+    #[gstat(common = "map", wire = "pascal_string")]
+    map: String,
+    #[gstat(common = "players")]
+    online: u8,
+    #[gstat(common = "max_players", wire = "u16_le")]
+    capacity: u16,
+    #[gstat(common = "password")]
+    locked: bool,
+    #[gstat(extra = "tick_rate", wire = "varint_u32")]
+    tick_rate: u32,
+    #[gstat(skip)]
+    cached: bool,
+    names: Bytes,
+}
+
+impl StatusResponse {
+    fn roster(&self) -> Vec<Player> {
+        self.names
+            .split(|&byte| byte == 0)
+            .filter(|name| !name.is_empty())
+            .map(|name| Player::new(String::from_utf8_lossy(name)))
+            .collect()
+    }
+}
+
+const RESPONSE: &[u8] =
+    b"\x00\x00\x00\x00\x07Skyline\x00\x05dunes\x02\x10\x00\x01\x80\x01ada\x00bo\x00";
+
+#[test]
+fn queries_are_built_from_options_and_encoded_in_field_order() {
+    let query = StatusQuery::from_options(QueryOptions {
+        players: true,
+        ..QueryOptions::default()
+    });
+
+    assert!(query.players);
+    assert!(!query.rules);
+    assert_eq!(
+        StatusQuery {
+            session: 0x0102_0304,
+            note: "not on the wire".into(),
+            ..query
+        }
+        .to_bytes()
+        .unwrap(),
+        b"\xFE\xFD\x01\x02\x03\x04\x01\x00"
+    );
+}
+
+#[test]
+fn responses_are_decoded_and_converted() {
+    let response = StatusResponse::from_bytes(Bytes::from_static(RESPONSE)).unwrap();
+
+    assert_eq!(response.session, 7);
+    assert!(!response.cached);
+
+    let info = response.to_common();
+    assert_eq!(info.name, "Skyline");
+    assert_eq!(info.map, "dunes");
+    assert_eq!((info.players, info.max_players), (2, 16));
+    assert_eq!(info.access, AccessPolicy::Password);
+    assert_eq!(info.extra["tick_rate"], "128");
+
+    let names: Vec<String> = response.players().into_iter().map(|p| p.name).collect();
+    assert_eq!(names, ["ada", "bo"]);
+
+    let fields = response.fields();
+    let raw = fields["raw"].as_map().unwrap();
+    assert_eq!(raw["capacity"], Value::Int(16));
+    assert_eq!(raw["locked"], Value::Bool(true));
+    assert!(!raw.contains_key("cached"));
+    assert_eq!(fields["player_list"].as_list().unwrap().len(), 2);
+}
+
+#[test]
+fn malformed_responses_are_rejected() {
+    let error = StatusResponse::from_bytes(Bytes::from_static(b"\x01\x00")).unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::InvalidPacket);
+    assert_eq!(
+        error.detail().inner(),
+        Some(&DecodeError::UnexpectedBytes { offset: 0 })
+    );
+
+    let error = StatusResponse::from_bytes(Bytes::from_static(&RESPONSE[..12])).unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::Truncated);
+}
+
+#[test]
+fn wire_parsers_need_no_parser_code() {
+    let parser = WireParser::<StatusQuery, StatusResponse>::new();
+
+    assert_eq!(
+        parser.serialize_query(&StatusQuery::default()).unwrap(),
+        b"\xFE\xFD\x00\x00\x00\x00\x00\x00"
+    );
+
+    let response = parser
+        .deserialize_response(Bytes::from_static(RESPONSE))
+        .unwrap();
+    assert_eq!(response.name, "Skyline");
+
+    let error = parser
+        .deserialize_response(Bytes::from_static(b"\x00"))
+        .unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::InvalidPacket);
+}
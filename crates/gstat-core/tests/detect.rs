@@ -0,0 +1,98 @@
+//! Guessing the protocol and game of servers from their answers to probes.
+
+use gstat_core::detect::{detect, detect_all, Probe};
+
+use std::{
+    net::{Ipv4Addr, SocketAddr, UdpSocket},
+    thread,
+    time::Duration,
+};
+
+use common::run;
+
+mod common;
+
+const LIMIT: Duration = Duration::from_millis(300);
+
+/// Binds a server on the loopback interface answering the datagrams `answer` returns
+/// something for, and ignoring the others.
+fn server(answer: fn(&[u8]) -> Option<Vec<u8>>) -> SocketAddr {
+    let socket = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+    let address = socket.local_addr().unwrap();
+
+    thread::spawn(move || {
+        let mut buffer = [0; 1500];
+
+        while let Ok((len, peer)) = socket.recv_from(&mut buffer) {
+            if let Some(reply) = answer(&buffer[..len]) {
+                socket.send_to(&reply, peer).unwrap();
+            }
+        }
+    });
+
+    address
+}
+
+/// Answers `A2S_INFO` for Team Fortress 2 once the query carries the challenge.
+fn a2s(request: &[u8]) -> Option<Vec<u8>> {
+    let query = request.strip_prefix(b"\xFF\xFF\xFF\xFFTSource Engine Query\0")?;
+
+    if query != b"\x0A\x0B\x0C\x0D" {
+        return Some(b"\xFF\xFF\xFF\xFFA\x0A\x0B\x0C\x0D".to_vec());
+    }
+
+    let mut reply = b"\xFF\xFF\xFF\xFFI\x11name\0map\0tf\0Team Fortress\0".to_vec();
+    reply.extend_from_slice(&440u16.to_le_bytes());
+    reply.extend_from_slice(&[3, 24, 0, b'd', b'l', 0, 1]);
+
+    Some(reply)
+}
+
+/// Answers RakNet unconnected pings as a Minecraft Bedrock server.
+fn raknet(request: &[u8]) -> Option<Vec<u8>> {
+    if request.first() != Some(&0x01) || request.len() != 33 {
+        return None;
+    }
+
+    let status = b"MCPE;gstat;594;1.20.0;0;10;1;gstat;Survival";
+    let mut reply = vec![0x1C];
+    reply.extend_from_slice(&request[1..9]);
+    reply.extend_from_slice(&7u64.to_be_bytes());
+    reply.extend_from_slice(&request[9..25]);
+    reply.extend_from_slice(&(status.len() as u16).to_be_bytes());
+    reply.extend_from_slice(status);
+
+    Some(reply)
+}
+
+#[test]
+fn a2s_servers_are_identified_by_their_appid() {
+    let address = server(a2s);
+
+    let detection = run(detect(address, LIMIT)).unwrap();
+
+    assert_eq!(detection.address, address);
+    assert_eq!(detection.protocol.id, "a2s");
+    assert_eq!(detection.game.map(|game| game.id), Some("tf2"));
+}
+
+#[test]
+fn protocols_used_by_a_single_game_name_it() {
+    let address = server(raknet);
+
+    let detections = run(detect_all(Probe::ALL.map(|probe| (probe, address)), LIMIT));
+
+    assert_eq!(detections.len(), 1);
+    assert_eq!(detections[0].protocol.id, "raknet");
+    assert_eq!(
+        detections[0].game.map(|game| game.id),
+        Some("minecraft-bedrock")
+    );
+}
+
+#[test]
+fn silent_servers_are_not_detected() {
+    let address = server(|_| None);
+
+    assert!(run(detect(address, LIMIT)).is_none());
+}
@@ -0,0 +1,137 @@
+//! Listing servers from master servers and from answers to broadcast queries.
+
+use gstat_core::{
+    bytes::Bytes,
+    discovery::{broadcast, query_master, MasterPage, MasterQuery, Region},
+    prelude::TimeoutSettings,
+};
+
+use std::{
+    net::{Ipv4Addr, SocketAddr, SocketAddrV4, UdpSocket},
+    thread,
+    time::Duration,
+};
+
+use common::run;
+
+mod common;
+
+/// Encodes a master server reply listing `servers`.
+fn page(servers: &[SocketAddrV4]) -> Vec<u8> {
+    let mut reply = vec![0xFF, 0xFF, 0xFF, 0xFF, 0x66, 0x0A];
+
+    for server in servers {
+        reply.extend_from_slice(&server.ip().octets());
+        reply.extend_from_slice(&server.port().to_be_bytes());
+    }
+
+    reply
+}
+
+fn server(last: u8, port: u16) -> SocketAddrV4 {
+    SocketAddrV4::new(Ipv4Addr::new(192, 0, 2, last), port)
+}
+
+#[test]
+fn requests_carry_the_region_seed_and_filter() {
+    let query = MasterQuery::new()
+        .with_region(Region::Europe)
+        .with_filter("\\appid\\440");
+
+    assert_eq!(
+        query.encode(SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, 0)),
+        b"\x31\x030.0.0.0:0\0\\appid\\440\0"
+    );
+    assert_eq!(
+        MasterQuery::new().encode(server(7, 27015)),
+        b"\x31\xFF192.0.2.7:27015\0\0"
+    );
+}
+
+#[test]
+fn pages_end_at_the_seed_address() {
+    let unspecified = SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, 0);
+    let decoded = MasterPage::decode(Bytes::from(page(&[server(1, 27015), unspecified]))).unwrap();
+
+    assert_eq!(decoded.servers, [server(1, 27015)]);
+    assert!(decoded.last);
+
+    let decoded = MasterPage::decode(Bytes::from(page(&[server(1, 27015)]))).unwrap();
+    assert!(!decoded.last);
+
+    assert!(MasterPage::decode(Bytes::from_static(b"\xFF\xFF\xFF\xFF\x41")).is_err());
+}
+
+#[test]
+fn lists_are_followed_across_pages() {
+    let master = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+    let address = master.local_addr().unwrap();
+    let unspecified = SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, 0);
+
+    let requests = thread::spawn(move || {
+        let mut buffer = [0; 256];
+        let mut requests = Vec::new();
+
+        for reply in [
+            page(&[server(1, 27015), server(2, 27016)]),
+            page(&[server(3, 27015), unspecified]),
+        ] {
+            let (len, peer) = master.recv_from(&mut buffer).unwrap();
+            requests.push(buffer[..len].to_vec());
+            master.send_to(&reply, peer).unwrap();
+        }
+
+        requests
+    });
+
+    let servers = run(query_master(
+        address,
+        &MasterQuery::new().with_filter("\\empty\\1"),
+        &TimeoutSettings::uniform(Duration::from_secs(2)),
+    ))
+    .unwrap();
+
+    assert_eq!(
+        servers,
+        [server(1, 27015), server(2, 27016), server(3, 27015)]
+    );
+
+    let requests = requests.join().unwrap();
+    assert!(requests[1].starts_with(b"\x31\xFF192.0.2.2:27016\0"));
+}
+
+#[test]
+fn silent_master_servers_time_out() {
+    let master = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+
+    let err = run(query_master(
+        master.local_addr().unwrap(),
+        &MasterQuery::new(),
+        &TimeoutSettings::uniform(Duration::from_millis(50)),
+    ))
+    .unwrap_err();
+
+    assert_eq!(err.kind(), std::io::ErrorKind::TimedOut);
+}
+
+#[test]
+fn every_answer_within_the_window_is_collected() {
+    let responder = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+    let address: SocketAddr = responder.local_addr().unwrap();
+
+    thread::spawn(move || {
+        let mut buffer = [0; 64];
+        let (len, peer) = responder.recv_from(&mut buffer).unwrap();
+        assert_eq!(&buffer[..len], b"ping");
+
+        responder.send_to(b"pong", peer).unwrap();
+        responder.send_to(b"again", peer).unwrap();
+    });
+
+    let answers = run(broadcast(address, b"ping", Duration::from_millis(200))).unwrap();
+
+    assert_eq!(answers.len(), 1);
+    assert_eq!(answers[0].address, address);
+    assert_eq!(answers[0].data, Bytes::from_static(b"pong"));
+    assert!(answers[0].latency < Duration::from_millis(200));
+}
@@ -0,0 +1,131 @@
+//! Choosing local addresses and falling back across address families.
+
+use gstat_core::{
+    address::{interleave_families, BindAddresses},
+    prelude::{Error, ErrorKind, Game, TimeoutSettings},
+};
+
+use std::{
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+};
+
+use common::{block_on, failure, Mock, MockGame, MockQuery, MockResponse};
+
+mod common;
+
+fn addr(address: &str) -> SocketAddr {
+    address.parse().unwrap()
+}
+
+#[test]
+fn families_alternate_starting_with_the_first_address() {
+    let resolved = [
+        addr("[2001:db8::1]:27015"),
+        addr("[2001:db8::2]:27015"),
+        addr("[2001:db8::3]:27015"),
+        addr("192.0.2.1:27015"),
+        addr("192.0.2.2:27015"),
+    ];
+
+    assert_eq!(
+        interleave_families(resolved),
+        [
+            addr("[2001:db8::1]:27015"),
+            addr("192.0.2.1:27015"),
+            addr("[2001:db8::2]:27015"),
+            addr("192.0.2.2:27015"),
+            addr("[2001:db8::3]:27015"),
+        ]
+    );
+
+    let v4_first = [addr("192.0.2.1:1"), addr("[2001:db8::1]:1")];
+    assert_eq!(interleave_families(v4_first), v4_first);
+    assert!(interleave_families([]).is_empty());
+}
+
+#[test]
+fn bind_addresses_are_chosen_by_the_family_of_the_peer() {
+    let v4_peer = addr("192.0.2.1:27015");
+    let v6_peer = addr("[2001:db8::1]:27015");
+
+    let unset = BindAddresses::new();
+    assert_eq!(unset.configured_for(v4_peer), None);
+    assert_eq!(unset.for_peer(v4_peer), addr("0.0.0.0:0"));
+    assert_eq!(unset.for_peer(v6_peer), addr("[::]:0"));
+
+    let bind = BindAddresses::new()
+        .with(addr("198.51.100.7:0"))
+        .with(addr("198.51.100.8:0"));
+
+    assert_eq!(bind.for_peer(v4_peer), addr("198.51.100.8:0"));
+    assert_eq!(bind.configured_for(v6_peer), None);
+    assert_eq!(bind.for_peer(v6_peer), addr("[::]:0"));
+
+    let bind = bind.with(addr("[2001:db8:ffff::7]:0"));
+    assert_eq!(bind.for_peer(v6_peer), addr("[2001:db8:ffff::7]:0"));
+}
+
+/// The addresses a game tried to connect to.
+type Tried = Arc<Mutex<Vec<SocketAddr>>>;
+
+/// Returns a game that can't reach IPv6 addresses, answering with the address it reached,
+/// and the addresses it tries.
+fn v4_only() -> (MockGame, Tried) {
+    let tried = Tried::default();
+    let log = tried.clone();
+
+    let game = Mock::new()
+        .on_connect(move |address| {
+            log.lock().unwrap().push(address);
+
+            match address.is_ipv6() {
+                true => Err(failure(ErrorKind::Other, "Network unreachable")),
+                false => Ok(()),
+            }
+        })
+        .respond(|exchange| Ok(MockResponse::named(exchange.address.unwrap().to_string())))
+        .game();
+
+    (game, tried)
+}
+
+#[test]
+fn fetch_any_falls_back_to_the_other_family() {
+    let (game, tried) = v4_only();
+
+    let resolved = [
+        addr("[2001:db8::1]:27015"),
+        addr("[2001:db8::2]:27015"),
+        addr("192.0.2.1:27015"),
+    ];
+
+    let fetched =
+        block_on(game.fetch_any(MockQuery::default(), resolved, TimeoutSettings::default()))
+            .unwrap();
+
+    assert_eq!(fetched.response.info.name, "192.0.2.1:27015");
+    assert_eq!(
+        *tried.lock().unwrap(),
+        [addr("[2001:db8::1]:27015"), addr("192.0.2.1:27015")]
+    );
+}
+
+#[test]
+fn fetch_any_returns_the_last_error_or_a_dns_error() {
+    let (game, tried) = v4_only();
+
+    let resolved = [addr("[2001:db8::1]:27015"), addr("[2001:db8::2]:27015")];
+    let err = block_on(game.fetch_any(MockQuery::default(), resolved, TimeoutSettings::default()))
+        .err()
+        .unwrap();
+
+    assert!(matches!(err, Error::ProtocolError(_)));
+    assert_eq!(tried.lock().unwrap().len(), 2);
+
+    let err = block_on(game.fetch_any(MockQuery::default(), [], TimeoutSettings::default()))
+        .err()
+        .unwrap();
+
+    assert_eq!(err.kind(), ErrorKind::Dns);
+}
@@ -0,0 +1,20 @@
+//! Parsing human-readable durations.
+
+use gstat_core::duration::parse_duration;
+
+use std::time::Duration;
+
+#[test]
+fn durations_are_read_with_their_unit() {
+    assert_eq!(parse_duration("500ms"), Ok(Duration::from_millis(500)));
+    assert_eq!(parse_duration("5s"), Ok(Duration::from_secs(5)));
+    assert_eq!(parse_duration(" 1.5 "), Ok(Duration::from_millis(1500)));
+    assert_eq!(parse_duration("2m"), Ok(Duration::from_secs(120)));
+    assert_eq!(parse_duration("1h"), Ok(Duration::from_secs(3600)));
+
+    assert!(parse_duration("5d")
+        .unwrap_err()
+        .contains("unknown duration unit"));
+    assert!(parse_duration("fast").is_err());
+    assert!(parse_duration("-1s").is_err());
+}
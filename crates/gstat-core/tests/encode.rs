@@ -0,0 +1,155 @@
+//! Encoding `Value` documents as JSON, CSV, MessagePack and XML, and the query document.
+
+use gstat_core::{
+    document::{self, document},
+    encode::{Csv, Encode, Encoding, Json, MessagePack, Xml},
+    prelude::{Fetched, Player, ResponseMeta, ServerInfo, Value},
+};
+
+use std::{collections::BTreeMap, time::Duration};
+
+use common::{MockError, MockResponse};
+
+mod common;
+
+fn map<const N: usize>(entries: [(&str, Value); N]) -> Value {
+    Value::Map(
+        entries
+            .into_iter()
+            .map(|(key, value)| (key.to_string(), value))
+            .collect::<BTreeMap<_, _>>(),
+    )
+}
+
+fn server(name: &str, players: &[&str]) -> Value {
+    map([
+        ("name", name.into()),
+        ("players", (players.len() as u32).into()),
+        (
+            "player_list",
+            Value::List(
+                players
+                    .iter()
+                    .map(|player| map([("name", (*player).into())]))
+                    .collect(),
+            ),
+        ),
+    ])
+}
+
+fn text(encoder: impl Encode, value: &Value) -> String {
+    String::from_utf8(encoder.encode(value)).unwrap()
+}
+
+#[test]
+fn json_escapes_strings_and_drops_non_finite_floats() {
+    let document = map([
+        ("name", "say \"hi\"\n".into()),
+        ("ratio", Value::Float(f64::NAN)),
+        ("tags", Value::List(vec![Value::Bool(true), Value::Null])),
+    ]);
+
+    assert_eq!(
+        text(Json, &document),
+        r#"{"name":"say \"hi\"\n","ratio":null,"tags":[true,null]}"#
+    );
+}
+
+#[test]
+fn csv_flattens_documents_into_one_row_each() {
+    let results = Value::List(vec![
+        server("alpha, the first", &["ann"]),
+        server("beta", &["bob", "cid"]),
+    ]);
+
+    assert_eq!(
+        text(Csv, &results),
+        "name,player_list.0.name,players,player_list.1.name\r\n\
+         \"alpha, the first\",ann,1,\r\n\
+         beta,bob,2,cid\r\n"
+    );
+}
+
+#[test]
+fn message_pack_uses_the_smallest_encodings() {
+    let document = map([
+        ("a", Value::Int(-1)),
+        ("b", Value::Int(300)),
+        ("c", Value::Null),
+    ]);
+
+    assert_eq!(
+        MessagePack.encode(&document),
+        [0x83, 0xA1, b'a', 0xFF, 0xA1, b'b', 0xCD, 0x01, 0x2C, 0xA1, b'c', 0xC0]
+    );
+}
+
+#[test]
+fn xml_names_elements_by_key_when_it_can() {
+    let document = map([
+        ("map", "<de_dust2>".into()),
+        ("1st", Value::Null),
+        ("rules", Value::List(vec!["a & b".into()])),
+    ]);
+
+    assert_eq!(
+        text(Xml, &document),
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <response><field name=\"1st\"/><map>&lt;de_dust2&gt;</map>\
+         <rules><item>a &amp; b</item></rules></response>\n"
+    );
+}
+
+#[test]
+fn encodings_are_selected_by_name() {
+    for encoding in Encoding::ALL {
+        assert_eq!(encoding.name().parse::<Encoding>(), Ok(encoding));
+    }
+
+    assert_eq!("MsgPack".parse::<Encoding>(), Ok(Encoding::MessagePack));
+    assert!("yaml".parse::<Encoding>().is_err());
+    assert_eq!(Encoding::Csv.content_type(), "text/csv");
+    assert!(!Encoding::MessagePack.is_text());
+}
+
+fn status() -> MockResponse {
+    MockResponse {
+        info: ServerInfo {
+            name: "Dust".to_string(),
+            players: 1,
+            max_players: 16,
+            extra: BTreeMap::from([("tickrate".to_string(), "64".to_string())]),
+            ..ServerInfo::default()
+        },
+        players: vec![Player {
+            score: Some(7),
+            ..Player::new("ann")
+        }],
+    }
+}
+
+#[test]
+fn query_documents_are_versioned_and_hold_the_metadata() {
+    let meta =
+        ResponseMeta::new(Duration::from_millis(12)).with_address(([192, 0, 2, 1], 27015).into());
+    let fetched: Fetched<MockResponse, MockError> = Fetched::new(status(), meta);
+    let document = document("source", "Source", &fetched);
+    let json = text(Json, &document);
+
+    assert_eq!(
+        document.as_map().unwrap()["version"],
+        Value::from(document::VERSION)
+    );
+    assert!(
+        json.contains(r#""game":{"id":"source","name":"Source"}"#),
+        "{json}"
+    );
+    assert!(json.contains(r#""address":"192.0.2.1:27015""#), "{json}");
+    assert!(json.contains(r#""latency_ms":12,"#), "{json}");
+    assert!(json.contains(r#""max_players":16,"#), "{json}");
+    assert!(
+        json.contains(r#""players":[{"duration_secs":null,"extra":{},"name":"ann","ping_ms":null,"score":7,"team":null}]"#),
+        "{json}"
+    );
+    assert!(json.contains(r#""rules":{"tickrate":"64"}"#), "{json}");
+}
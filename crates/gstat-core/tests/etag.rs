@@ -0,0 +1,56 @@
+//! Entity tags for conditional queries.
+
+use gstat_core::{
+    etag::ETag,
+    prelude::{AccessPolicy, Player, ServerInfo},
+};
+
+use std::time::Duration;
+
+fn server() -> (ServerInfo, Vec<Player>) {
+    let info = ServerInfo {
+        name: "Badlands".into(),
+        map: "cp_badlands".into(),
+        players: 2,
+        max_players: 24,
+        ..ServerInfo::default()
+    };
+
+    (info, vec![Player::new("alice"), Player::new("bob")])
+}
+
+#[test]
+fn tags_change_only_with_what_widgets_show() {
+    let (info, mut players) = server();
+    let tag = ETag::of(&info, &players);
+
+    players[0].duration = Some(Duration::from_secs(61));
+    players[1].ping = Some(48);
+    assert_eq!(ETag::of(&info, &players), tag);
+
+    let mut changed = info.clone();
+    changed.map = "cp_granary".into();
+    assert_ne!(ETag::of(&changed, &players), tag);
+
+    let mut changed = info.clone();
+    changed.access = AccessPolicy::Password;
+    assert_ne!(ETag::of(&changed, &players), tag);
+
+    players[1].score = Some(3);
+    assert_ne!(ETag::of(&info, &players), tag);
+}
+
+#[test]
+fn tags_round_trip_through_headers() {
+    let tag = ETag::from_u64(0x5f0c_8a1e_93b2_d447);
+
+    assert_eq!(tag.to_string(), "\"5f0c8a1e93b2d447\"");
+    assert_eq!("W/\"5f0c8a1e93b2d447\"".parse(), Ok(tag));
+    assert_eq!("5f0c8a1e93b2d447".parse(), Ok(tag));
+    assert!("\"5f0c\"".parse::<ETag>().is_err());
+
+    assert!(tag.matches_any("\"0000000000000001\", \"5f0c8a1e93b2d447\""));
+    assert!(tag.matches_any("*"));
+    assert!(!tag.matches_any("\"0000000000000001\", garbage"));
+    assert!(!tag.matches_any(""));
+}
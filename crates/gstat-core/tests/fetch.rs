@@ -0,0 +1,174 @@
+//! Behaviour of the default `Game::fetch` pipeline at each failure point.
+
+use gstat_core::{
+    bytes::Bytes,
+    prelude::{Error, ErrorDetail, Fetched, Game, RetryPolicy},
+    retry::{Attempt, Backoff, Jitter},
+};
+
+use std::{
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use common::{block_on, immediate, Mock, MockError, MockQuery, MockResponse};
+
+mod common;
+
+type Outcome = Result<Fetched<MockResponse, MockError>, Error<MockError>>;
+
+/// Runs a fetch that fails at `fail_at`, returning its outcome and the recorded steps.
+fn fetch(fail_at: Option<&'static str>) -> (Outcome, Vec<String>) {
+    fetch_retrying(fail_at, RetryPolicy::default())
+}
+
+/// Runs a fetch that fails at `fail_at` under `retry_policy`.
+///
+/// The server's meter sees a packet while connecting and one per response.
+fn fetch_retrying(
+    fail_at: Option<&'static str>,
+    retry_policy: RetryPolicy,
+) -> (Outcome, Vec<String>) {
+    let mut mock = Mock::new().metered().with_retry_policy(retry_policy);
+    if let Some(step) = fail_at {
+        mock = mock.failing_at(step);
+    }
+    let address: SocketAddr = "127.0.0.1:27015".parse().unwrap();
+
+    let outcome = block_on(mock.game().fetch(MockQuery::default(), address));
+
+    (outcome, mock.calls())
+}
+
+/// Returns the step a failed fetch reported.
+fn failed_step(outcome: Outcome) -> &'static str {
+    match outcome {
+        Ok(_) => panic!("fetch unexpectedly succeeded"),
+        Err(err) => err.detail().inner().unwrap().0,
+    }
+}
+
+#[test]
+fn successful_fetch_runs_every_step_once() {
+    let (outcome, steps) = fetch(None);
+
+    assert!(!outcome.unwrap().has_warnings());
+    assert_eq!(
+        steps,
+        ["connect", "send_query", "receive_response", "disconnect"]
+    );
+}
+
+#[test]
+fn response_meta_holds_only_the_packets_of_the_response() {
+    let (outcome, _) = fetch(None);
+    let meta = outcome.unwrap().meta;
+
+    assert_eq!(meta.raw(), [Bytes::from_static(b"response")]);
+    assert_eq!((meta.packet_count(), meta.bytes_received()), (1, 8));
+    assert!(meta.received_at().unwrap().elapsed() < Duration::from_secs(5));
+}
+
+#[test]
+fn connect_failure_is_fatal_and_has_nothing_to_release() {
+    let (outcome, steps) = fetch(Some("connect"));
+
+    assert_eq!(failed_step(outcome), "connect");
+    assert_eq!(steps, ["connect"]);
+}
+
+#[test]
+fn send_failure_is_fatal_and_schedules_a_disconnect() {
+    let (outcome, steps) = fetch(Some("send_query"));
+
+    assert_eq!(failed_step(outcome), "send_query");
+    assert_eq!(steps, ["connect", "send_query", "schedule_disconnect"]);
+}
+
+#[test]
+fn receive_failure_is_fatal_and_schedules_a_disconnect() {
+    let (outcome, steps) = fetch(Some("receive_response"));
+
+    assert_eq!(failed_step(outcome), "receive_response");
+    assert_eq!(
+        steps,
+        [
+            "connect",
+            "send_query",
+            "receive_response",
+            "schedule_disconnect"
+        ]
+    );
+}
+
+#[test]
+fn disconnect_failure_keeps_the_response_and_reports_a_warning() {
+    let (outcome, steps) = fetch(Some("disconnect"));
+    let fetched = outcome.unwrap();
+
+    assert_eq!(fetched.warnings.len(), 1);
+    assert_eq!(
+        fetched.warnings[0].detail().inner().unwrap().0,
+        "disconnect"
+    );
+    assert_eq!(
+        steps,
+        ["connect", "send_query", "receive_response", "disconnect"]
+    );
+}
+
+#[test]
+fn transient_failures_are_retried_until_the_policy_gives_up() {
+    let attempts = Arc::new(Mutex::new(Vec::new()));
+    let observed = attempts.clone();
+    let policy = immediate(3).on_attempt(move |attempt: &Attempt| {
+        observed.lock().unwrap().push(attempt.clone());
+    });
+
+    let (outcome, steps) = fetch_retrying(Some("receive_response"), policy);
+
+    assert_eq!(failed_step(outcome), "receive_response");
+    assert_eq!(steps.iter().filter(|s| **s == "connect").count(), 3);
+
+    let attempts = attempts.lock().unwrap();
+    assert_eq!(
+        attempts.iter().map(|a| a.number).collect::<Vec<_>>(),
+        [1, 2, 3]
+    );
+    assert_eq!(attempts[0].retry_in, Some(Duration::ZERO));
+    assert_eq!(attempts[2].retry_in, None);
+    assert!(attempts.iter().all(|a| a.error.is_some()));
+}
+
+#[test]
+fn non_transient_failures_are_not_retried() {
+    let policy = immediate(3);
+    let error: Error<MockError> = Error::QueryError(ErrorDetail::new("bad query", None));
+
+    assert!(!policy.should_retry(1, &error));
+    assert!(policy.should_retry(
+        1,
+        &Error::<MockError>::ProtocolError(ErrorDetail::new("lost", None))
+    ));
+}
+
+#[test]
+fn backoff_grows_exponentially_up_to_the_cap() {
+    let policy = RetryPolicy::new(10)
+        .with_backoff(Backoff {
+            initial: Duration::from_millis(100),
+            multiplier: 2.0,
+            max: Duration::from_millis(500),
+        })
+        .with_jitter(Jitter::None);
+
+    assert_eq!(policy.delay_for(1), Duration::from_millis(100));
+    assert_eq!(policy.delay_for(2), Duration::from_millis(200));
+    assert_eq!(policy.delay_for(3), Duration::from_millis(400));
+    assert_eq!(policy.delay_for(4), Duration::from_millis(500));
+
+    let jittered = policy.with_jitter(Jitter::Equal);
+    let delay = jittered.delay_for(2);
+    assert!(delay >= Duration::from_millis(100) && delay <= Duration::from_millis(200));
+}
@@ -0,0 +1,63 @@
+//! Stability of server fingerprints across the values that change between queries.
+
+use gstat_core::prelude::{Fingerprint, ServerInfo};
+
+fn server() -> ServerInfo {
+    let mut info = ServerInfo {
+        name: "Rust EU #3 | wiped 12/04".to_string(),
+        map: "Procedural Map".to_string(),
+        game: "rust".to_string(),
+        players: 57,
+        max_players: 200,
+        password: false,
+        version: "2401.1.7".to_string(),
+        ..ServerInfo::default()
+    };
+    info.extra.insert("build".to_string(), "2401".to_string());
+    info.extra.insert("fps".to_string(), "60".to_string());
+
+    info
+}
+
+#[test]
+fn fingerprint_ignores_volatile_values() {
+    let original = server();
+    let mut later = server();
+
+    later.name = "Rust  EU #4 | wiped 19/04".to_string();
+    later.map = "Barren".to_string();
+    later.players = 3;
+    later.version = "2401.1.9".to_string();
+    later.extra.insert("fps".to_string(), "30".to_string());
+
+    assert_eq!(original.fingerprint(), later.fingerprint());
+}
+
+#[test]
+fn fingerprint_changes_with_stable_characteristics() {
+    let original = server().fingerprint();
+
+    let mut renamed = server();
+    renamed.name = "Rust US #3 | wiped 12/04".to_string();
+    assert_ne!(renamed.fingerprint(), original);
+
+    let mut other_game = server();
+    other_game.game = "valheim".to_string();
+    assert_ne!(other_game.fingerprint(), original);
+
+    let mut new_rules = server();
+    new_rules.extra.insert("seed".to_string(), "1".to_string());
+    assert_ne!(new_rules.fingerprint(), original);
+
+    let mut upgraded = server();
+    upgraded.version = "2402.0.1".to_string();
+    assert_ne!(upgraded.fingerprint(), original);
+}
+
+#[test]
+fn fingerprint_value_is_stable() {
+    let fingerprint = Fingerprint::of(&ServerInfo::default());
+
+    assert_eq!(Fingerprint::from_u64(fingerprint.as_u64()), fingerprint);
+    assert_eq!(fingerprint.to_string(), "f998341be47bae14");
+}
@@ -0,0 +1,116 @@
+//! Loading fleets from TOML files and reloading them when they change.
+
+use gstat_core::{
+    fleet::{Fleet, FleetError, Sink, DEFAULT_TIMEOUT},
+    notify::{Format, Webhook},
+    uptime::Alert,
+};
+
+use std::{fs, time::Duration};
+
+use common::run;
+
+mod common;
+
+const FLEET: &str = r#"
+interval = "1m"
+scripts = ["games/tf2.rhai"]
+
+[[target]]
+name = "main"
+game = "tf2"
+address = "play.example.com"
+port = 27016
+interval = "10s"
+
+[[target]]
+game = "minecraft"
+address = "127.0.0.1:25565"
+
+[[alert]]
+kind = "down"
+polls = 3
+
+[[alert]]
+kind = "availability_below"
+ratio = 0.99
+window = "24h"
+
+[[sink]]
+kind = "webhook"
+url = "http://alerts.lan/gstat"
+format = "discord"
+targets = ["main"]
+
+[[sink]]
+kind = "store"
+path = "history.db"
+"#;
+
+#[test]
+fn fleets_describe_targets_alerts_and_sinks() {
+    let fleet: Fleet = FLEET.parse().unwrap();
+
+    assert_eq!(fleet.timeout, DEFAULT_TIMEOUT);
+    assert_eq!(fleet.targets.len(), 2);
+
+    let main = fleet.target("main").unwrap();
+    assert_eq!(main.address.to_string(), "play.example.com:27016");
+    assert_eq!(fleet.interval_of(main), Duration::from_secs(10));
+
+    let minecraft = fleet.target("127.0.0.1:25565").unwrap();
+    assert_eq!(fleet.interval_of(minecraft), Duration::from_secs(60));
+
+    assert_eq!(
+        fleet.alerts,
+        [
+            Alert::Down { polls: 3 },
+            Alert::AvailabilityBelow {
+                ratio: 0.99,
+                window: Duration::from_secs(86_400),
+            },
+        ]
+    );
+    assert_eq!(
+        fleet.webhooks().collect::<Vec<_>>(),
+        [&Webhook::new("http://alerts.lan/gstat")
+            .with_format(Format::Discord)
+            .with_targets(["main".to_string()])]
+    );
+    assert!(matches!(&fleet.sinks[1], Sink::Store { path } if path.ends_with("history.db")));
+}
+
+#[test]
+fn invalid_fleets_are_rejected() {
+    let duplicate = "[[target]]\ngame = \"tf2\"\naddress = \"a\"\n\n\
+                     [[target]]\ngame = \"css\"\naddress = \"a\"\n";
+    let unknown = "[[sink]]\nkind = \"webhook\"\nurl = \"http://a\"\ntargets = [\"b\"]\n";
+
+    for source in [
+        duplicate,
+        unknown,
+        "interval = \"5 weeks\"",
+        "[[alert]]\nkind = \"loud\"",
+    ] {
+        assert!(
+            matches!(source.parse::<Fleet>(), Err(FleetError::Invalid(_))),
+            "{source}"
+        );
+    }
+}
+
+#[test]
+fn watchers_reload_changed_files() {
+    let path = std::env::temp_dir().join("gstat-fleet-watch.toml");
+    fs::write(&path, FLEET).unwrap();
+
+    let fleet = Fleet::from_path(&path).unwrap();
+    assert_eq!(fleet.scripts, [std::env::temp_dir().join("games/tf2.rhai")]);
+
+    let mut watcher = Fleet::watch(&path).with_interval(Duration::from_millis(10));
+    fs::write(&path, "[[target]]\ngame = \"tf2\"\naddress = \"b\"\n").unwrap();
+
+    let reloaded = run(watcher.changed()).unwrap();
+    assert_eq!(reloaded.targets.len(), 1);
+    assert_eq!(reloaded.targets[0].name, "b");
+}
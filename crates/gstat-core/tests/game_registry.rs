@@ -0,0 +1,210 @@
+//! Dispatching on game names at runtime through a `GameRegistry`.
+
+use gstat_core::{
+    game_registry::{DuplicateGame, GameRegistry},
+    prelude::{Game, QueryOptions, TimeoutSettings},
+    registry::{self, GameEntry, QueryPort},
+    standards::dyn_game::ErasedGame,
+};
+
+use std::{
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+};
+
+use common::{block_on, Mock, MockProtocol, MockResponse};
+
+mod common;
+
+/// The query options a server received.
+type Sent = Arc<Mutex<Vec<QueryOptions>>>;
+
+/// Returns a protocol naming the server after the game `name`, recording the options of
+/// its queries in `sent`.
+fn echo(name: &'static str, sent: &Sent) -> MockProtocol {
+    let sent = sent.clone();
+
+    Mock::new()
+        .respond(move |exchange| {
+            sent.lock().unwrap().push(exchange.query.0.clone());
+            Ok(MockResponse::named(name))
+        })
+        .protocol()
+}
+
+#[derive(Default)]
+struct Fortress {
+    sent: Sent,
+}
+
+impl<'a> Game<'a, MockProtocol> for Fortress {
+    const GAME_NAME: &'static str = "Team Fortress 2";
+    const RELEASE_YEAR: u32 = 2007;
+
+    fn _protocol(&self) -> MockProtocol {
+        echo(Self::GAME_NAME, &self.sent)
+    }
+}
+
+/// A game using the registry's Rust metadata, whose query port is offset from the game port.
+struct Survival;
+
+impl<'a> Game<'a, MockProtocol> for Survival {
+    const GAME_NAME: &'static str = "Rust";
+    const RELEASE_YEAR: u32 = 2018;
+
+    fn _protocol(&self) -> MockProtocol {
+        echo(Self::GAME_NAME, &Sent::default())
+    }
+
+    fn registry_entry(&self) -> Option<&'static GameEntry> {
+        registry::game("rust")
+    }
+}
+
+struct Lantern;
+
+impl<'a> Game<'a, MockProtocol> for Lantern {
+    const GAME_NAME: &'static str = "Lantern";
+    const RELEASE_YEAR: u32 = 2021;
+
+    fn _protocol(&self) -> MockProtocol {
+        echo(Self::GAME_NAME, &Sent::default())
+    }
+}
+
+fn registry() -> GameRegistry {
+    let mut games = GameRegistry::new();
+
+    games
+        .register_entry(
+            registry::game("tf2").unwrap(),
+            ErasedGame::new(Fortress::default()),
+        )
+        .unwrap();
+    games
+        .register("lantern", &["ltn"], ErasedGame::new(Lantern))
+        .unwrap();
+
+    games
+}
+
+#[test]
+fn games_are_found_by_identifier_or_alias() {
+    let games = registry();
+
+    assert_eq!(games.len(), 2);
+    assert_eq!(games.get("TeamFortress2").unwrap().id(), "tf2");
+    assert_eq!(games.get("LTN").unwrap().game().dyn_name(), "Lantern");
+    assert!(!games.contains("minecraft"));
+
+    let ids: Vec<&str> = games.iter().map(|game| game.id()).collect();
+    assert_eq!(ids, ["tf2", "lantern"]);
+}
+
+#[test]
+fn games_are_found_by_steam_appid() {
+    let mut games = registry();
+
+    assert_eq!(games.get_by_appid(440).unwrap().id(), "tf2");
+    assert!(games.get_by_appid(730).is_none());
+
+    games
+        .register_with_appids("lamp", &["lmp"], &[2_000_001], ErasedGame::new(Lantern))
+        .unwrap();
+    assert_eq!(games.get_by_appid(2_000_001).unwrap().appids(), [2_000_001]);
+
+    let err = games
+        .register_with_appids("torch", &[] as &[&str], &[440], ErasedGame::new(Lantern))
+        .unwrap_err();
+    assert_eq!(err.identifier, "appid 440");
+}
+
+#[test]
+fn builtin_appid_table_is_sorted_and_matches_lookups() {
+    let appids: Vec<u32> = registry::appids().map(|(appid, _)| appid).collect();
+
+    assert!(appids.windows(2).all(|pair| pair[0] < pair[1]));
+    for (appid, game) in registry::appids() {
+        assert_eq!(registry::game_by_appid(appid).unwrap().id, game.id);
+    }
+}
+
+#[test]
+fn taken_identifiers_are_rejected() {
+    let mut games = registry();
+
+    let err = games
+        .register("lamp", &["LTN"], ErasedGame::new(Lantern))
+        .unwrap_err();
+
+    assert_eq!(
+        err,
+        DuplicateGame {
+            identifier: "ltn".into()
+        }
+    );
+    assert!(!games.contains("lamp"));
+}
+
+#[test]
+fn registered_games_fetch_through_the_common_model() {
+    let fortress = Fortress::default();
+    let sent = fortress.sent.clone();
+
+    let mut games = GameRegistry::new();
+    games
+        .register_entry(registry::game("tf2").unwrap(), ErasedGame::new(fortress))
+        .unwrap();
+
+    let address: SocketAddr = "192.0.2.1:27015".parse().unwrap();
+    let options = QueryOptions {
+        players: true,
+        ..QueryOptions::default()
+    };
+
+    let game = games.get("tf2").unwrap().game();
+    let fetched =
+        block_on(game.dyn_fetch(options.clone(), address, TimeoutSettings::default())).unwrap();
+
+    assert_eq!(fetched.info.name, "Team Fortress 2");
+    assert!(fetched.warnings.is_empty());
+    assert_eq!(*sent.lock().unwrap(), [options]);
+}
+
+#[test]
+fn query_addresses_follow_the_port_metadata() {
+    let host = [192, 0, 2, 1].into();
+
+    assert_eq!(Survival.default_port(), Some(28015));
+    assert_eq!(
+        Survival.query_address(host, None),
+        Some(SocketAddr::new(host, 28017))
+    );
+    assert_eq!(Survival.query_port_for(28100), Some(28102));
+
+    // An offset past the end of the port range has no query port, rather than wrapping.
+    assert_eq!(Survival.query_port_for(65535), None);
+    assert_eq!(Survival.query_address(host, Some(65534)), None);
+
+    // Offsets wider than 16 bits still land on valid ports.
+    assert_eq!(
+        QueryPort::Offset(40_000).for_game_port(20_000),
+        Some(60_000)
+    );
+    assert_eq!(
+        QueryPort::Offset(-40_000).for_game_port(60_000),
+        Some(20_000)
+    );
+    assert_eq!(QueryPort::Offset(-40_000).for_game_port(30_000), None);
+
+    assert_eq!(Lantern.query_address(host, None), None);
+    assert_eq!(
+        registry()
+            .get("lantern")
+            .unwrap()
+            .game()
+            .dyn_query_address(host, Some(7777)),
+        Some(SocketAddr::new(host, 7777))
+    );
+}
@@ -0,0 +1,72 @@
+//! Rendering results in the shape of node-gamedig.
+
+use gstat_core::{
+    compat::gamedig::Gamedig,
+    prelude::{Player, ServerInfo},
+};
+
+use std::{net::SocketAddr, time::Duration};
+
+use serde_json::json;
+
+fn address() -> SocketAddr {
+    "203.0.113.7:27015".parse().unwrap()
+}
+
+#[test]
+fn server_fields_use_gamedig_names() {
+    let mut info = ServerInfo {
+        name: "Badlands".into(),
+        map: "cp_badlands".into(),
+        game: "Team Fortress".into(),
+        players: 1,
+        max_players: 24,
+        password: true,
+        version: "8622567".into(),
+        ..ServerInfo::default()
+    };
+    info.extra
+        .insert("steamid".into(), "90071992547409920".into());
+
+    let mut player = Player::new("Scout");
+    player.score = Some(12);
+    player.duration = Some(Duration::from_millis(90_500));
+    let players = [player];
+
+    let result = Gamedig::new(&info, address())
+        .with_players(&players)
+        .with_ping(Duration::from_millis(42))
+        .to_value();
+
+    assert_eq!(
+        result,
+        json!({
+            "name": "Badlands",
+            "map": "cp_badlands",
+            "password": true,
+            "numplayers": 1,
+            "maxplayers": 24,
+            "players": [{"name": "Scout", "raw": {"score": 12, "time": 90.5}}],
+            "bots": [],
+            "connect": "203.0.113.7:27015",
+            "ping": 42,
+            "queryPort": 27015,
+            "version": "8622567",
+            "raw": {"game": "Team Fortress", "steamid": "90071992547409920"},
+        })
+    );
+}
+
+#[test]
+fn connect_address_can_differ_from_the_query_address() {
+    let info = ServerInfo::default();
+
+    let result = Gamedig::new(&info, address())
+        .with_connect("play.example.com:27015")
+        .to_value();
+
+    assert_eq!(result["connect"], "play.example.com:27015");
+    assert_eq!(result["queryPort"], 27015);
+    assert_eq!(result["ping"], 0);
+    assert_eq!(result["raw"], json!({}));
+}
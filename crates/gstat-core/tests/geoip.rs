@@ -0,0 +1,148 @@
+//! Locating servers with MaxMind databases.
+
+use gstat_core::{
+    geoip::{GeoIp, GeoIpError, Location},
+    prelude::{Fetched, ResponseMeta},
+};
+
+use std::{
+    net::{IpAddr, Ipv4Addr, SocketAddr},
+    time::Duration,
+};
+
+fn string(text: &str) -> Vec<u8> {
+    let mut data = match text.len() {
+        len @ 0..=28 => vec![0x40 | len as u8],
+        len => vec![0x40 | 29, (len - 29) as u8],
+    };
+    data.extend_from_slice(text.as_bytes());
+    data
+}
+
+fn map(entries: &[(&str, Vec<u8>)]) -> Vec<u8> {
+    let mut data = vec![0xE0 | entries.len() as u8];
+
+    for (key, value) in entries {
+        data.extend(string(key));
+        data.extend_from_slice(value);
+    }
+
+    data
+}
+
+fn uint16(value: u16) -> Vec<u8> {
+    let mut data = vec![0xA2];
+    data.extend_from_slice(&value.to_be_bytes());
+    data
+}
+
+fn uint32(value: u32) -> Vec<u8> {
+    let mut data = vec![0xC4];
+    data.extend_from_slice(&value.to_be_bytes());
+    data
+}
+
+/// Builds an IPv4 database where `record` describes every address below 128.0.0.0, and
+/// the other addresses are unknown.
+fn database(kind: &str, record: Vec<u8>) -> Vec<u8> {
+    // A single node: its left record points at the start of the data section, past the
+    // node count and the separator, and its right record means "not found".
+    let mut data = vec![0, 0, 17, 0, 0, 1];
+    data.extend_from_slice(&[0; 16]);
+    data.extend(record);
+    data.extend_from_slice(b"\xAB\xCD\xEFMaxMind.com");
+    data.extend(map(&[
+        ("binary_format_major_version", uint16(2)),
+        ("binary_format_minor_version", uint16(0)),
+        ("build_epoch", vec![0x08, 0x02, 0, 0, 0, 0, 0, 0, 0, 0]),
+        ("database_type", string(kind)),
+        ("description", map(&[])),
+        ("ip_version", uint16(4)),
+        ("languages", vec![0x01, 0x04, 0x42, b'e', b'n']),
+        ("node_count", uint32(1)),
+        ("record_size", uint16(24)),
+    ]));
+    data
+}
+
+fn geoip() -> GeoIp {
+    let city = database(
+        "GeoLite2-City",
+        map(&[
+            (
+                "city",
+                map(&[("names", map(&[("en", string("Amsterdam"))]))]),
+            ),
+            (
+                "country",
+                map(&[
+                    ("iso_code", string("NL")),
+                    ("names", map(&[("en", string("Netherlands"))])),
+                ]),
+            ),
+        ]),
+    );
+    let asn = database(
+        "GeoLite2-ASN",
+        map(&[
+            ("autonomous_system_number", uint32(1136)),
+            ("autonomous_system_organization", string("KPN B.V.")),
+        ]),
+    );
+    let path = std::env::temp_dir().join(format!("gstat-geoip-{}.mmdb", std::process::id()));
+    std::fs::write(&path, asn).unwrap();
+
+    let geoip = GeoIp::from_bytes(city)
+        .unwrap()
+        .with_database(&path)
+        .unwrap();
+    std::fs::remove_file(path).unwrap();
+
+    geoip
+}
+
+const KNOWN: Ipv4Addr = Ipv4Addr::new(94, 142, 241, 111);
+
+#[test]
+fn lookups_merge_every_database() {
+    let location = geoip().lookup(IpAddr::V4(KNOWN)).unwrap();
+
+    assert_eq!(
+        location,
+        Location {
+            country_code: Some("NL".into()),
+            country: Some("Netherlands".into()),
+            city: Some("Amsterdam".into()),
+            asn: Some(1136),
+            as_organization: Some("KPN B.V.".into()),
+        }
+    );
+}
+
+#[test]
+fn unknown_addresses_have_no_location() {
+    assert_eq!(geoip().lookup(IpAddr::V4(Ipv4Addr::BROADCAST)), None);
+}
+
+#[test]
+fn fetched_responses_are_enriched_with_the_server_location() {
+    let geoip = geoip();
+    let meta = |ip| ResponseMeta::new(Duration::ZERO).with_address(SocketAddr::from((ip, 27015)));
+    let mut results = vec![
+        ("known", Ok(Fetched::<(), ()>::new((), meta(KNOWN)))),
+        ("unknown", Ok(Fetched::new((), meta(Ipv4Addr::BROADCAST)))),
+    ];
+
+    geoip.enrich_all(&mut results);
+
+    let location = |index: usize| results[index].1.as_ref().unwrap().meta.location().cloned();
+    assert_eq!(location(0).and_then(|location| location.asn), Some(1136));
+    assert_eq!(location(1), None);
+}
+
+#[test]
+fn invalid_databases_are_rejected() {
+    let err = GeoIp::from_bytes(b"not a database".to_vec()).unwrap_err();
+
+    assert!(matches!(err, GeoIpError::Invalid(_)));
+}
@@ -0,0 +1,107 @@
+//! Obtaining challenge tokens with `Handshake` before the real query.
+
+use gstat_core::{challenge::ChallengeMemory, prelude::Game};
+
+use std::net::SocketAddr;
+
+use common::{block_on, Mock, MockQuery};
+
+mod common;
+
+fn address() -> SocketAddr {
+    "127.0.0.1:6500".parse().unwrap()
+}
+
+/// A challenge request that times out.
+const TIMEOUT: Option<&[u8]> = None;
+
+/// A challenge carrying the token `1234`.
+const TOKEN: Option<&[u8]> = Some(b"C1234");
+
+/// An answer to the challenge request that is already the response.
+const DIRECT: Option<&[u8]> = Some(b"I");
+
+/// Fetches once from a GameSpy 4 style server answering challenge requests with `answers`,
+/// returning whether it succeeded and the calls it received.
+fn fetch(
+    answers: &[Option<&'static [u8]>],
+    memory: Option<ChallengeMemory>,
+) -> (bool, Vec<String>) {
+    let mock = Mock::new().with_handshake(memory).with_packets(answers);
+
+    let succeeded = block_on(mock.game().fetch(MockQuery::default(), address())).is_ok();
+
+    (succeeded, mock.calls())
+}
+
+#[test]
+fn fetch_obtains_a_token_before_querying() {
+    let (succeeded, log) = fetch(&[TIMEOUT, TOKEN], None);
+
+    assert!(succeeded);
+    assert_eq!(
+        log,
+        [
+            "connect",
+            "send challenge?",
+            "receive",
+            "send challenge?",
+            "receive",
+            "token 1234",
+            "send_query",
+            "receive_response",
+            "disconnect",
+        ]
+    );
+}
+
+#[test]
+fn repeated_timeouts_fail_the_handshake() {
+    let (succeeded, log) = fetch(&[TIMEOUT; 3], None);
+
+    assert!(!succeeded);
+    assert_eq!(
+        log,
+        [
+            "connect",
+            "send challenge?",
+            "receive",
+            "send challenge?",
+            "receive",
+            "send challenge?",
+            "receive",
+            "schedule_disconnect",
+        ]
+    );
+}
+
+#[test]
+fn remembered_hosts_skip_the_handshake() {
+    let memory = ChallengeMemory::new();
+
+    let (_, first) = fetch(&[DIRECT], Some(memory.clone()));
+    let (_, second) = fetch(&[], Some(memory));
+
+    assert_eq!(
+        first,
+        [
+            "connect",
+            "send challenge?",
+            "receive",
+            "no token",
+            "send_query",
+            "receive_response",
+            "disconnect",
+        ]
+    );
+    assert_eq!(
+        second,
+        [
+            "connect",
+            "skipped",
+            "send_query",
+            "receive_response",
+            "disconnect",
+        ]
+    );
+}
@@ -0,0 +1,125 @@
+//! Wrapping protocols with `ProtocolLayer`s.
+
+use gstat_core::{
+    clock::ManualClock,
+    prelude::{Error, ErrorKind, Game, Protocol, RetryPolicy},
+    retry::{Backoff, Jitter, RetryOn},
+    standards::layer::{Call, FaultLayer, Operation, ProtocolLayer, RetryLayer},
+};
+
+use std::{future::Future, net::SocketAddr, time::Duration};
+
+use common::{block_on, Log, Mock, MockQuery};
+
+mod common;
+
+/// A layer logging the calls passing through it under its name.
+struct Record {
+    name: &'static str,
+    log: Log,
+}
+
+impl<'a, P: Protocol<'a>> ProtocolLayer<'a, P> for Record {
+    fn around<T, F>(
+        &self,
+        call: Call,
+        future: F,
+    ) -> impl Future<Output = Result<T, Error<P::E>>> + Send
+    where
+        F: Future<Output = Result<T, Error<P::E>>> + Send,
+    {
+        let peer = call.peer.map(|peer| peer.to_string()).unwrap_or_default();
+
+        self.log
+            .lock()
+            .unwrap()
+            .push(format!("{}:{}@{peer}", self.name, call.operation));
+
+        future
+    }
+}
+
+fn address() -> SocketAddr {
+    SocketAddr::from(([192, 0, 2, 1], 27015))
+}
+
+#[test]
+fn calls_pass_through_the_last_layer_first() {
+    let mock = Mock::new();
+    let log = mock.log();
+    let game = mock.game().layered(move |protocol| {
+        protocol
+            .layer(Record {
+                name: "inner",
+                log: log.clone(),
+            })
+            .layer(Record {
+                name: "outer",
+                log: log.clone(),
+            })
+    });
+
+    block_on(game.fetch(MockQuery::default(), address())).unwrap();
+
+    let mut expected = Vec::new();
+
+    for operation in [
+        "connect",
+        "handshake",
+        "send_query",
+        "receive_response",
+        "disconnect",
+    ] {
+        expected.push(format!("outer:{operation}@{}", address()));
+        expected.push(format!("inner:{operation}@{}", address()));
+
+        if operation != "handshake" {
+            expected.push(operation.to_string());
+        }
+    }
+
+    assert_eq!(mock.calls(), expected);
+}
+
+#[test]
+fn injected_faults_skip_the_operation_and_are_retried() {
+    let faults = FaultLayer::new(1.0)
+        .with_kind(ErrorKind::Network)
+        .with_operations([Operation::ReceiveResponse]);
+    let retries = RetryPolicy::new(3)
+        .with_backoff(Backoff {
+            initial: Duration::ZERO,
+            multiplier: 1.0,
+            max: Duration::ZERO,
+        })
+        .with_jitter(Jitter::None)
+        .with_retry_on(RetryOn::Any)
+        .with_clock(ManualClock::new());
+
+    let mock = Mock::new();
+    let game = mock.game().layered(move |protocol| {
+        protocol
+            .layer(faults.clone())
+            .layer(RetryLayer::new(retries.clone()))
+    });
+
+    let err = block_on(game.fetch(MockQuery::default(), address()))
+        .err()
+        .unwrap();
+    assert_eq!(err.kind(), ErrorKind::Network);
+
+    assert_eq!(mock.count("connect"), 3);
+    assert_eq!(mock.count("send_query"), 3);
+    assert_eq!(mock.count("receive_response"), 0);
+}
+
+#[test]
+fn faults_are_only_injected_at_their_rate() {
+    let mock = Mock::new();
+    let game = mock
+        .game()
+        .layered(|protocol| protocol.layer(FaultLayer::new(0.0)));
+
+    assert!(block_on(game.fetch(MockQuery::default(), address())).is_ok());
+    assert_eq!(mock.calls().len(), 4);
+}
@@ -0,0 +1,122 @@
+//! Driving a protocol through its connection states with `Link`.
+
+use gstat_core::{
+    events::{ConnectionEvent, Subscribers},
+    prelude::{Authenticate, Error, ErrorDetail, Link, TimeoutSettings},
+};
+
+use std::{
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+};
+
+use common::{block_on, Mock, MockError, MockProtocol, MockQuery, MockResponse};
+
+mod common;
+
+/// The mock server logs login attempts and accepts only one password.
+impl<'a> Authenticate<'a> for MockProtocol {
+    type Credentials = &'static str;
+
+    async fn authenticate(
+        &self,
+        credentials: &Self::Credentials,
+        _timeouts: &TimeoutSettings,
+    ) -> Result<(), Error<Self::E>> {
+        self.record("authenticate");
+
+        match *credentials {
+            "hunter2" => Ok(()),
+            _ => Err(Error::ProtocolError(ErrorDetail::new(
+                "Authentication failed",
+                Some(MockError("wrong password")),
+            ))),
+        }
+    }
+}
+
+fn address() -> SocketAddr {
+    "127.0.0.1:27015".parse().unwrap()
+}
+
+#[test]
+fn links_walk_through_every_state() {
+    let mock = Mock::new();
+    let console = mock.protocol();
+    let timeouts = TimeoutSettings::default();
+
+    block_on(async {
+        let link = Link::new(&console).connect(address(), &timeouts).await?;
+        let _: MockResponse = link.query(MockQuery::default(), &timeouts).await?;
+
+        let link = link.authenticate(&"hunter2", &timeouts).await?;
+        let _: MockResponse = link.query(MockQuery::default(), &timeouts).await?;
+
+        let link = link.disconnect().await?;
+        let link = link.connect(address(), &timeouts).await?;
+        link.disconnect().await?;
+
+        Ok::<_, Error<MockError>>(())
+    })
+    .unwrap();
+
+    assert_eq!(
+        mock.calls(),
+        [
+            "connect",
+            "send_query",
+            "receive_response",
+            "authenticate",
+            "send_query",
+            "receive_response",
+            "disconnect",
+            "connect",
+            "disconnect",
+        ]
+    );
+}
+
+#[test]
+fn failed_authentication_schedules_a_disconnect() {
+    let mock = Mock::new();
+    let console = mock.protocol();
+    let timeouts = TimeoutSettings::default();
+
+    let result = block_on(async {
+        let link = Link::new(&console).connect(address(), &timeouts).await?;
+        link.authenticate(&"wrong", &timeouts).await
+    });
+
+    assert!(result.is_err());
+    assert_eq!(
+        mock.calls(),
+        ["connect", "authenticate", "schedule_disconnect"]
+    );
+}
+
+#[test]
+fn failed_authentication_is_reported_to_subscribers() {
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let mut subscribers = Subscribers::new();
+    let timeouts = TimeoutSettings::default();
+
+    let sink = Arc::clone(&events);
+    subscribers.add(move |event: &ConnectionEvent| sink.lock().unwrap().push(event.clone()));
+    let console = Mock::new().with_subscribers(subscribers).protocol();
+
+    let result = block_on(async {
+        let link = Link::new(&console).connect(address(), &timeouts).await?;
+        assert_eq!(link.peer(), Some(address()));
+        link.authenticate(&"wrong", &timeouts).await
+    });
+
+    assert!(result.is_err());
+
+    let events = events.lock().unwrap();
+    assert_eq!(events.len(), 1);
+    assert!(matches!(
+        &events[0],
+        ConnectionEvent::AuthFailed { peer, reason }
+            if *peer == address() && reason.contains("Authentication failed")
+    ));
+}
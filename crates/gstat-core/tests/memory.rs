@@ -0,0 +1,67 @@
+//! Memory accounting with `HeapSize`, `MemoryBudget` and `LruStore`.
+
+use gstat_core::{
+    memory::{total_size, HeapSize, LruStore, MemoryBudget},
+    prelude::{Player, ServerInfo},
+};
+
+fn info(name: &str) -> ServerInfo {
+    ServerInfo {
+        name: name.into(),
+        ..ServerInfo::default()
+    }
+}
+
+#[test]
+fn heap_size_counts_owned_strings_and_collections() {
+    let mut info = info("");
+    assert_eq!(info.heap_size(), 0);
+
+    info.name = String::with_capacity(32);
+    info.extra.insert("tick".into(), "64".into());
+    assert!(info.heap_size() >= 32 + 4 + 2);
+
+    let players = vec![Player::new("Ada"), Player::new("Grace")];
+    assert!(players.heap_size() >= 2 * std::mem::size_of::<Player>() + 3 + 5);
+}
+
+#[test]
+fn stores_report_usage_per_subsystem_and_release_it_when_dropped() {
+    let budget = MemoryBudget::unlimited();
+    let mut cache = LruStore::new(budget.clone(), "cache");
+    let mut monitor = LruStore::new(budget.clone(), "monitor");
+
+    cache.insert(1, info("one"));
+    monitor.insert(1, info("two"));
+    monitor.insert(2, info("three"));
+
+    assert_eq!(budget.used_by("cache"), total_size(&info("one")));
+    assert_eq!(budget.used_by("monitor"), monitor.size());
+    assert_eq!(budget.used(), cache.size() + monitor.size());
+
+    drop(monitor);
+    assert_eq!(budget.usage().get("monitor"), Some(&0));
+    assert!(!budget.is_exceeded());
+}
+
+#[test]
+fn exceeding_the_budget_evicts_the_least_recently_used_entries() {
+    let entry = total_size(&info("server"));
+    let budget = MemoryBudget::with_limit(entry * 2);
+    let mut store = LruStore::new(budget.clone(), "cache");
+
+    store.insert("a", info("server"));
+    store.insert("b", info("server"));
+    assert!(store.get(&"a").is_some());
+
+    store.insert("c", info("server"));
+
+    assert_eq!(store.peek(&"b"), None);
+    assert!(store.peek(&"a").is_some() && store.peek(&"c").is_some());
+    assert_eq!(store.evictions(), 1);
+    assert_eq!(budget.used(), entry * 2);
+
+    store.insert("huge", info(&"x".repeat(entry * 4)));
+    assert!(store.is_empty());
+    assert_eq!(budget.used(), 0);
+}
@@ -0,0 +1,145 @@
+//! Reporting fetches to a `MetricsSink`.
+
+use gstat_core::{
+    metrics::{Counters, CountersSnapshot, MetricsSink},
+    prelude::{ErrorKind, Game, Protocol},
+    standards::layer::MetricsLayer,
+};
+
+use std::{
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use common::{block_on, failure, parse, Mock, MockQuery};
+
+mod common;
+
+/// How the server answers queries.
+#[derive(Clone, Copy)]
+enum Answer {
+    Valid,
+    Garbled,
+    Refused,
+}
+
+/// Returns a server answering with `answer`.
+fn server(answer: Answer) -> Mock {
+    Mock::new()
+        .on_connect(move |_| match answer {
+            Answer::Refused => Err(failure(ErrorKind::ConnectionRefused, "Refused")),
+            _ => Ok(()),
+        })
+        .respond(move |_| match answer {
+            Answer::Garbled => parse(b""),
+            _ => parse(b"info"),
+        })
+}
+
+fn address() -> SocketAddr {
+    SocketAddr::from(([192, 0, 2, 1], 27015))
+}
+
+fn fetch<S: MetricsSink + 'static>(answer: Answer, sink: &Arc<S>) -> bool {
+    let sink = sink.clone();
+    let game = server(answer)
+        .game()
+        .layered(move |protocol| protocol.layer(MetricsLayer::new(sink.clone())));
+
+    block_on(game.fetch(MockQuery::default(), address())).is_ok()
+}
+
+#[test]
+fn fetches_are_counted_by_outcome() {
+    let counters = Arc::new(Counters::new());
+
+    assert!(fetch(Answer::Valid, &counters));
+    assert!(fetch(Answer::Valid, &counters));
+    assert!(!fetch(Answer::Garbled, &counters));
+    assert!(!fetch(Answer::Refused, &counters));
+
+    let snapshot = counters.snapshot();
+
+    assert_eq!(
+        CountersSnapshot {
+            rtt_buckets: Default::default(),
+            rtt_sum: Duration::ZERO,
+            ..snapshot.clone()
+        },
+        CountersSnapshot {
+            queries_sent: 3,
+            responses_ok: 2,
+            parse_failures: 1,
+            queries_failed: 2,
+            ..CountersSnapshot::default()
+        }
+    );
+
+    // Both round trips were well below the first bound.
+    assert_eq!(snapshot.rtt_buckets[0], 2);
+    assert!(snapshot.mean_rtt().unwrap() < Duration::from_millis(10));
+}
+
+/// A sink recording the failures it is told about.
+#[derive(Default)]
+struct Failures(Mutex<Vec<(String, ErrorKind)>>);
+
+impl MetricsSink for Failures {
+    fn query_failed(&self, game: &str, _peer: SocketAddr, kind: ErrorKind) {
+        self.0.lock().unwrap().push((game.to_string(), kind));
+    }
+}
+
+#[test]
+fn sinks_receive_the_game_and_the_error_kind() {
+    let failures = Arc::new(Failures::default());
+    let protocol = server(Answer::Refused)
+        .protocol()
+        .layer(MetricsLayer::new(failures.clone()));
+
+    assert!(protocol.metrics().is_some());
+    assert!(server(Answer::Valid).protocol().metrics().is_none());
+
+    assert!(!fetch(Answer::Refused, &failures));
+    assert_eq!(
+        *failures.0.lock().unwrap(),
+        [("Mock".to_string(), ErrorKind::ConnectionRefused)]
+    );
+}
+
+/// A game whose name is only known at runtime, like a scripted one.
+struct Named<G>(G, String);
+
+impl<'a, G, P> Game<'a, P> for Named<G>
+where
+    G: Game<'a, P>,
+    P: Protocol<'a>,
+{
+    const GAME_NAME: &'static str = "unnamed";
+    const RELEASE_YEAR: u32 = 0;
+
+    fn _protocol(&self) -> P {
+        self.0._protocol()
+    }
+
+    fn game_name(&self) -> &str {
+        &self.1
+    }
+}
+
+#[test]
+fn games_named_at_runtime_are_reported_under_their_name() {
+    let failures = Arc::new(Failures::default());
+    let sink = failures.clone();
+    let game = server(Answer::Refused)
+        .game()
+        .layered(move |protocol| protocol.layer(MetricsLayer::new(sink.clone())));
+    let game = Named(game, "Lantern".to_string());
+
+    assert!(block_on(game.fetch(MockQuery::default(), address())).is_err());
+    assert_eq!(
+        *failures.0.lock().unwrap(),
+        [("Lantern".to_string(), ErrorKind::ConnectionRefused)]
+    );
+}
@@ -0,0 +1,235 @@
+//! Posting poll events to webhooks, with filters, retries and dedup.
+
+use gstat_core::{
+    clock::ManualClock,
+    notify::{EventKind, Format, HttpClient, Notifier, Post, Webhook, WebhookClient},
+    poller::PollEvent,
+    retry::{Backoff, Jitter, RetryPolicy},
+};
+
+use std::{
+    collections::VecDeque,
+    io::{self, Read, Write},
+    net::TcpListener,
+    sync::{Arc, Mutex},
+    thread,
+    time::Duration,
+};
+
+use common::run;
+
+mod common;
+
+/// Records the bodies posted to it and answers with the queued status codes, then `204`.
+#[derive(Clone, Default)]
+struct Recorder {
+    posts: Arc<Mutex<Vec<(String, String)>>>,
+    statuses: Arc<Mutex<VecDeque<u16>>>,
+}
+
+impl Recorder {
+    fn answering(statuses: impl IntoIterator<Item = u16>) -> Self {
+        let recorder = Recorder::default();
+        recorder.statuses.lock().unwrap().extend(statuses);
+        recorder
+    }
+
+    fn posts(&self) -> Vec<(String, String)> {
+        self.posts.lock().unwrap().clone()
+    }
+}
+
+impl WebhookClient for Recorder {
+    fn post<'a>(&'a self, url: &'a str, _: &'a str, body: &'a [u8]) -> Post<'a> {
+        self.posts
+            .lock()
+            .unwrap()
+            .push((url.to_string(), String::from_utf8(body.to_vec()).unwrap()));
+        let status = self.statuses.lock().unwrap().pop_front().unwrap_or(204);
+
+        Box::pin(async move { Ok(status) })
+    }
+}
+
+fn offline(target: &'static str) -> PollEvent<&'static str, String> {
+    PollEvent::Offline {
+        target,
+        error: "timed out".to_string(),
+    }
+}
+
+#[test]
+fn events_are_formatted_for_each_webhook() {
+    run(async {
+        let recorder = Recorder::default();
+        let notifier = Notifier::new()
+            .with_client(recorder.clone())
+            .with_webhook(Webhook::new("http://a"))
+            .with_webhook(Webhook::new("http://discord").with_format(Format::Discord))
+            .with_webhook(Webhook::new("http://slack").with_format(Format::Slack));
+
+        assert!(notifier.notify(&offline("fort")).await.is_empty());
+
+        assert_eq!(
+            recorder.posts(),
+            [
+                (
+                    "http://a".to_string(),
+                    r#"{"error":"timed out","event":"offline","message":"fort went offline: timed out","target":"fort"}"#
+                        .to_string()
+                ),
+                (
+                    "http://discord".to_string(),
+                    r#"{"embeds":[{"color":15158332,"description":"fort went offline: timed out","title":"fort"}]}"#
+                        .to_string()
+                ),
+                (
+                    "http://slack".to_string(),
+                    r##"{"attachments":[{"color":"#E74C3C","text":"fort went offline: timed out"}],"text":"fort went offline: timed out"}"##
+                        .to_string()
+                ),
+            ]
+        );
+    });
+}
+
+#[test]
+fn webhooks_filter_targets_and_kinds() {
+    run(async {
+        let recorder = Recorder::default();
+        let notifier = Notifier::new()
+            .with_client(recorder.clone())
+            .with_webhook(Webhook::new("http://fort").with_targets(["fort"]))
+            .with_webhook(Webhook::new("http://all").with_kinds(EventKind::ALL));
+        let players: PollEvent<_, String> = PollEvent::PlayersChanged {
+            target: "fort",
+            from: 3,
+            to: 4,
+        };
+
+        notifier.notify(&offline("keep")).await;
+        notifier.notify(&players).await;
+
+        let urls: Vec<_> = recorder.posts().into_iter().map(|(url, _)| url).collect();
+        assert_eq!(urls, ["http://all", "http://all"]);
+        assert!(recorder.posts()[1]
+            .1
+            .contains("fort has 4 players, up from 3"));
+    });
+}
+
+#[test]
+fn repeated_messages_are_dropped_and_failures_retried() {
+    run(async {
+        let recorder = Recorder::answering([503, 400]);
+        let notifier = Notifier::new()
+            .with_client(recorder.clone())
+            .with_retry(
+                RetryPolicy::new(3)
+                    .with_backoff(Backoff {
+                        initial: Duration::from_millis(1),
+                        ..Backoff::default()
+                    })
+                    .with_jitter(Jitter::None),
+            )
+            .with_webhook(Webhook::new("http://a"));
+
+        // The 503 is retried, the 400 is not, and the undelivered message isn't deduped.
+        let errors = notifier.notify(&offline("fort")).await;
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].attempts, 2);
+        assert_eq!(errors[0].error.to_string(), "HTTP status 400");
+
+        assert!(notifier.notify(&offline("fort")).await.is_empty());
+        assert!(notifier.notify(&offline("fort")).await.is_empty());
+        assert!(notifier.notify(&offline("keep")).await.is_empty());
+        assert_eq!(recorder.posts().len(), 4);
+    });
+}
+
+#[test]
+fn dedup_windows_and_backoff_follow_the_clock() {
+    run(async {
+        let clock = ManualClock::new();
+        let recorder = Recorder::answering([503]);
+        let notifier = Notifier::new()
+            .with_client(recorder.clone())
+            .with_retry(
+                RetryPolicy::new(2)
+                    .with_backoff(Backoff {
+                        initial: Duration::from_secs(10),
+                        max: Duration::from_secs(10),
+                        ..Backoff::default()
+                    })
+                    .with_jitter(Jitter::None),
+            )
+            .with_clock(clock.clone())
+            .with_dedup(Duration::from_secs(60))
+            .with_webhook(Webhook::new("http://a"));
+
+        // The retry after the 503 waits on the clock rather than on real time.
+        let event = offline("fort");
+        let delivery = notifier.notify(&event);
+        let advance = async {
+            while clock.sleepers() == 0 {
+                tokio::task::yield_now().await;
+            }
+            assert_eq!(clock.advance_to_next(), Some(Duration::from_secs(10)));
+        };
+        let (errors, ()) = tokio::join!(delivery, advance);
+        assert!(errors.is_empty());
+        assert_eq!(recorder.posts().len(), 2);
+
+        // The window counts from the first attempt, 10 seconds before the delivery.
+        clock.advance(Duration::from_secs(49));
+        assert!(notifier.notify(&offline("fort")).await.is_empty());
+        assert_eq!(recorder.posts().len(), 2);
+
+        // Each repeat restarts the window.
+        clock.advance(Duration::from_secs(59));
+        assert!(notifier.notify(&offline("fort")).await.is_empty());
+        assert_eq!(recorder.posts().len(), 2);
+
+        clock.advance(Duration::from_secs(60));
+        assert!(notifier.notify(&offline("fort")).await.is_empty());
+        assert_eq!(recorder.posts().len(), 3);
+    });
+}
+
+#[test]
+fn http_clients_post_over_plain_http() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+
+    let server = thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut request = Vec::new();
+        let mut buffer = [0; 1024];
+
+        while !request.ends_with(b"{}") {
+            let read = stream.read(&mut buffer).unwrap();
+            request.extend_from_slice(&buffer[..read]);
+        }
+
+        stream
+            .write_all(b"HTTP/1.1 204 No Content\r\n\r\n")
+            .unwrap();
+        String::from_utf8(request).unwrap()
+    });
+
+    let client = HttpClient::new();
+    let status = run(client.post(
+        &format!("http://{address}/hooks/gstat"),
+        "application/json",
+        b"{}",
+    ))
+    .unwrap();
+    let request = server.join().unwrap();
+
+    assert_eq!(status, 204);
+    assert!(request.starts_with("POST /hooks/gstat HTTP/1.1\r\n"));
+    assert!(request.contains("Content-Length: 2\r\n"));
+
+    let err = run(client.post("https://discord.com/api/webhooks/1", "", b"")).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::Unsupported);
+}
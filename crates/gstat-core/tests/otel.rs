@@ -0,0 +1,137 @@
+//! OpenTelemetry spans per fetch and metrics through `OtelMetrics`.
+
+// The `silent` feature disables the fetch spans.
+#![cfg(not(feature = "silent"))]
+
+use gstat_core::{
+    otel::OtelMetrics,
+    prelude::{ErrorKind, Game, Protocol},
+    standards::layer::MetricsLayer,
+};
+
+use std::net::SocketAddr;
+
+use opentelemetry::{
+    global,
+    metrics::MeterProvider,
+    trace::{SpanKind, Status},
+    KeyValue, Value,
+};
+use opentelemetry_sdk::{
+    metrics::{
+        data::{AggregatedMetrics, MetricData, ResourceMetrics},
+        InMemoryMetricExporter, PeriodicReader, SdkMeterProvider,
+    },
+    trace::{InMemorySpanExporter, SdkTracerProvider},
+};
+
+use common::{block_on, failure, Mock, MockQuery};
+
+mod common;
+
+fn address() -> SocketAddr {
+    SocketAddr::from(([192, 0, 2, 1], 27015))
+}
+
+fn attribute<'k>(attributes: &'k [KeyValue], key: &str) -> Option<&'k Value> {
+    attributes
+        .iter()
+        .find(|attribute| attribute.key.as_str() == key)
+        .map(|attribute| &attribute.value)
+}
+
+/// Returns the sum of the `u64` counter `name` for the data points with `attribute`.
+fn counter(metrics: &[ResourceMetrics], name: &str, attribute: (&str, &str)) -> u64 {
+    metrics
+        .iter()
+        .flat_map(|resource| resource.scope_metrics())
+        .flat_map(|scope| scope.metrics())
+        .filter(|metric| metric.name() == name)
+        .map(|metric| match metric.data() {
+            AggregatedMetrics::U64(MetricData::Sum(sum)) => sum
+                .data_points()
+                .filter(|point| {
+                    point.attributes().any(|kv| {
+                        kv.key.as_str() == attribute.0 && kv.value.as_str() == attribute.1
+                    })
+                })
+                .map(|point| point.value())
+                .sum(),
+            _ => 0,
+        })
+        .sum()
+}
+
+#[test]
+fn fetches_are_reported_as_spans_and_metrics() {
+    let spans = InMemorySpanExporter::default();
+    global::set_tracer_provider(
+        SdkTracerProvider::builder()
+            .with_simple_exporter(spans.clone())
+            .build(),
+    );
+
+    let exporter = InMemoryMetricExporter::default();
+    let meters = SdkMeterProvider::builder()
+        .with_reader(PeriodicReader::builder(exporter.clone()).build())
+        .build();
+    let metrics = OtelMetrics::with_meter(&meters.meter("gstat")).with_protocol("direct");
+
+    for reachable in [true, true, false] {
+        let metrics = metrics.clone();
+        let game = Mock::new()
+            .on_connect(move |_| match reachable {
+                true => Ok(()),
+                false => Err(failure(ErrorKind::Network, "Host unreachable")),
+            })
+            .game()
+            .layered(move |protocol| protocol.layer(MetricsLayer::new(metrics.clone())));
+
+        assert_eq!(
+            block_on(game.fetch(MockQuery::default(), address())).is_ok(),
+            reachable
+        );
+    }
+
+    let spans = spans.get_finished_spans().unwrap();
+    assert_eq!(spans.len(), 3);
+
+    for span in &spans {
+        assert_eq!(span.name, "fetch");
+        assert_eq!(span.span_kind, SpanKind::Client);
+        assert_eq!(
+            attribute(&span.attributes, "gstat.game"),
+            Some(&Value::from("Mock"))
+        );
+        assert_eq!(
+            attribute(&span.attributes, "server.port"),
+            Some(&Value::I64(27015))
+        );
+    }
+
+    assert_eq!(spans[0].status, Status::Ok);
+    assert!(matches!(spans[2].status, Status::Error { .. }));
+    assert_eq!(
+        attribute(&spans[2].attributes, "error.type"),
+        Some(&Value::from("network"))
+    );
+
+    meters.force_flush().unwrap();
+    let exported = exporter.get_finished_metrics().unwrap();
+
+    let game = ("gstat.game", "Mock");
+    assert_eq!(counter(&exported, "gstat.queries.sent", game), 2);
+    assert_eq!(counter(&exported, "gstat.responses.ok", game), 2);
+    assert_eq!(
+        counter(&exported, "gstat.queries.failed", ("error.type", "network")),
+        1
+    );
+    assert_eq!(
+        counter(
+            &exported,
+            "gstat.queries.sent",
+            ("gstat.protocol", "direct")
+        ),
+        2
+    );
+}
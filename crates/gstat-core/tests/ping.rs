@@ -0,0 +1,155 @@
+//! Measuring round-trip times with the lightest exchange of each protocol.
+
+use gstat_core::{
+    ping::Ping,
+    registry::{self, Capabilities, ProtocolEntry, Transport},
+};
+
+use std::{
+    io::{self, Read, Write},
+    net::{Ipv4Addr, SocketAddr, TcpListener, UdpSocket},
+    thread,
+    time::Duration,
+};
+
+use common::run;
+
+mod common;
+
+fn pings(samples: usize) -> Ping {
+    Ping::new()
+        .with_samples(samples)
+        .with_interval(Duration::ZERO)
+        .with_limit(Duration::from_millis(200))
+}
+
+/// Binds a server answering `A2A_PING`, ignoring the first `ignored` pings.
+fn a2a_server(mut ignored: usize) -> SocketAddr {
+    let socket = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+    let address = socket.local_addr().unwrap();
+
+    thread::spawn(move || {
+        let mut buffer = [0; 1500];
+
+        while let Ok((len, peer)) = socket.recv_from(&mut buffer) {
+            if &buffer[..len] != b"\xFF\xFF\xFF\xFFi" {
+                continue;
+            }
+
+            if ignored > 0 {
+                ignored -= 1;
+            } else {
+                socket.send_to(b"\xFF\xFF\xFF\xFFj\0", peer).unwrap();
+            }
+        }
+    });
+
+    address
+}
+
+#[test]
+fn a2s_servers_are_pinged_with_a2a_ping() {
+    let address = a2a_server(1);
+    let a2s = registry::protocol("a2s").unwrap();
+
+    let rtt = run(pings(4).run(address, a2s)).unwrap();
+
+    assert_eq!(rtt.samples().len(), 3);
+    assert_eq!(rtt.lost(), 1);
+    assert_eq!(rtt.loss(), 0.25);
+    assert!(rtt.min() <= rtt.median() && rtt.median() <= rtt.max());
+    assert_eq!(rtt.percentile(100.0), rtt.max());
+    assert_eq!(rtt.percentile(0.0), rtt.min());
+}
+
+#[test]
+fn tcp_protocols_time_the_connect() {
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+    let address = listener.local_addr().unwrap();
+    thread::spawn(move || for _stream in listener.incoming() {});
+
+    let ts3 = registry::protocol("ts3-serverquery").unwrap();
+
+    let rtt = run(pings(2).run(address, ts3)).unwrap();
+
+    assert_eq!(rtt.samples().len(), 2);
+    assert_eq!(rtt.lost(), 0);
+}
+
+/// Binds a Minecraft server answering pings, which checks that no status is requested.
+fn slp_server() -> SocketAddr {
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+    let address = listener.local_addr().unwrap();
+
+    thread::spawn(move || {
+        for mut stream in listener.incoming().flatten() {
+            let mut request = Vec::new();
+            let mut buffer = [0; 256];
+
+            // The handshake, whose length fits in one byte, then a 10 byte ping.
+            while request
+                .first()
+                .is_none_or(|&len| request.len() < len as usize + 11)
+            {
+                match stream.read(&mut buffer) {
+                    Ok(0) | Err(_) => break,
+                    Ok(len) => request.extend_from_slice(&buffer[..len]),
+                }
+            }
+
+            let ping = &request[request[0] as usize + 1..];
+            assert_eq!(request[1], 0x00, "not a handshake");
+            assert_eq!(ping[..2], [9, 0x01], "not a ping");
+
+            stream.write_all(ping).unwrap();
+        }
+    });
+
+    address
+}
+
+#[test]
+fn minecraft_servers_are_pinged_without_their_status() {
+    let slp = registry::protocol("minecraft-slp").unwrap();
+
+    let rtt = run(pings(3).run(slp_server(), slp)).unwrap();
+
+    assert_eq!(rtt.samples().len(), 3);
+    assert_eq!(rtt.lost(), 0);
+
+    // A server accepting the connection without answering the ping loses it.
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+    let address = listener.local_addr().unwrap();
+    thread::spawn(move || for _stream in listener.incoming() {});
+
+    let rtt = run(pings(1).run(address, slp)).unwrap();
+
+    assert_eq!(rtt.lost(), 1);
+}
+
+#[test]
+fn unanswered_pings_have_no_percentiles() {
+    let address = a2a_server(usize::MAX);
+    let a2s = registry::protocol("a2s").unwrap();
+
+    let rtt = run(pings(2).run(address, a2s)).unwrap();
+
+    assert_eq!(rtt.lost(), 2);
+    assert_eq!(rtt.loss(), 1.0);
+    assert_eq!(rtt.median(), None);
+}
+
+#[test]
+fn protocols_without_a_ping_are_rejected() {
+    let custom = ProtocolEntry {
+        id: "custom",
+        name: "Custom",
+        transport: Transport::Udp,
+        capabilities: Capabilities::empty(),
+    };
+    let address = SocketAddr::from((Ipv4Addr::LOCALHOST, 9));
+
+    let err = run(pings(1).run(address, &custom)).unwrap_err();
+
+    assert_eq!(err.kind(), io::ErrorKind::Unsupported);
+}
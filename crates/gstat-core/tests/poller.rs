@@ -0,0 +1,187 @@
+//! Polling servers with `Poller` on a `ManualClock`.
+
+use gstat_core::{
+    cancel::CancellationToken,
+    clock::ManualClock,
+    poller::{Debounce, PollEvent, Poller},
+    prelude::ServerInfo,
+    schedule::{Phase, Schedule},
+};
+
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{Arc, Mutex},
+    task::{Context, Poll, Waker},
+    time::Duration,
+};
+
+/// The map and player count of an answer, or `None` for a failed poll.
+type Answer = Option<(&'static str, u32)>;
+
+/// The answers each server gives, in order.
+type Script = Arc<Mutex<HashMap<&'static str, VecDeque<Answer>>>>;
+
+fn script(servers: &[(&'static str, &[Answer])]) -> Script {
+    Arc::new(Mutex::new(
+        servers
+            .iter()
+            .map(|(server, answers)| (*server, answers.iter().copied().collect()))
+            .collect(),
+    ))
+}
+
+fn poller(script: &Script, clock: &ManualClock) -> Poller<'static, &'static str, String> {
+    let script = script.clone();
+    let servers: Vec<&'static str> = script.lock().unwrap().keys().copied().collect();
+    let schedule = Schedule::new(Duration::from_secs(10)).with_phase(Phase::Aligned);
+
+    Poller::new(servers, schedule, move |server: &&'static str| {
+        let answer = script
+            .lock()
+            .unwrap()
+            .get_mut(server)
+            .and_then(VecDeque::pop_front)
+            .flatten();
+
+        async move {
+            let (map, players) = answer.ok_or_else(|| "no answer".to_string())?;
+
+            Ok(ServerInfo {
+                map: map.into(),
+                players,
+                ..ServerInfo::default()
+            })
+        }
+    })
+    .with_clock(clock.clone())
+}
+
+/// Collects `count` events, advancing the clock whenever the poller waits.
+fn events(
+    poller: &mut Poller<'static, &'static str, String>,
+    clock: &ManualClock,
+    count: usize,
+) -> Vec<PollEvent<&'static str, String>> {
+    let mut context = Context::from_waker(Waker::noop());
+    let mut events = Vec::new();
+
+    while events.len() < count {
+        match poller.poll_next(&mut context) {
+            Poll::Ready(Some(event)) => events.push(event),
+            Poll::Ready(None) => break,
+            Poll::Pending => {
+                clock
+                    .advance_to_next()
+                    .expect("the poller waits on its clock");
+            }
+        }
+    }
+
+    events
+}
+
+#[test]
+fn changes_of_online_servers_are_reported() {
+    let clock = ManualClock::new();
+    let script = script(&[(
+        "a",
+        &[
+            Some(("cp_badlands", 10)),
+            Some(("cp_badlands", 10)),
+            Some(("cp_badlands", 12)),
+            Some(("pl_upward", 3)),
+        ],
+    )]);
+    let mut poller = poller(&script, &clock);
+
+    let events = events(&mut poller, &clock, 4);
+
+    assert!(matches!(&events[0], PollEvent::Online { info, .. } if info.players == 10));
+    assert_eq!(
+        events[1],
+        PollEvent::PlayersChanged {
+            target: "a",
+            from: 10,
+            to: 12
+        }
+    );
+    assert_eq!(
+        events[2],
+        PollEvent::MapChanged {
+            target: "a",
+            from: "cp_badlands".into(),
+            to: "pl_upward".into()
+        }
+    );
+    assert_eq!(
+        events[3],
+        PollEvent::PlayersChanged {
+            target: "a",
+            from: 12,
+            to: 3
+        }
+    );
+
+    // The four answers took three intervals after the first poll.
+    assert_eq!(clock.elapsed(), Duration::from_secs(30));
+}
+
+#[test]
+fn single_failures_are_not_reported() {
+    let clock = ManualClock::new();
+    let script = script(&[(
+        "a",
+        &[
+            Some(("cp_badlands", 10)),
+            None,
+            Some(("cp_badlands", 10)),
+            None,
+            None,
+            Some(("cp_badlands", 4)),
+        ],
+    )]);
+    let mut poller = poller(&script, &clock).with_debounce(Debounce::new().with_offline_after(2));
+
+    let events = events(&mut poller, &clock, 3);
+
+    assert!(matches!(events[0], PollEvent::Online { .. }));
+    assert_eq!(
+        events[1],
+        PollEvent::Offline {
+            target: "a",
+            error: "no answer".into()
+        }
+    );
+    assert!(matches!(&events[2], PollEvent::Online { info, .. } if info.players == 4));
+    assert_eq!(clock.elapsed(), Duration::from_secs(50));
+}
+
+#[test]
+fn servers_are_tracked_independently() {
+    let clock = ManualClock::new();
+    let script = script(&[("up", &[Some(("de_dust2", 5))]), ("down", &[None, None])]);
+    let mut poller = poller(&script, &clock).with_debounce(Debounce::none());
+
+    let mut events = events(&mut poller, &clock, 2);
+    events.sort_by_key(|event| *event.target());
+
+    assert!(matches!(
+        events[0],
+        PollEvent::Offline { target: "down", .. }
+    ));
+    assert!(matches!(events[1], PollEvent::Online { target: "up", .. }));
+}
+
+#[test]
+fn cancelled_pollers_stop() {
+    let clock = ManualClock::new();
+    let script = script(&[("a", &[Some(("cp_badlands", 10))])]);
+    let token = CancellationToken::new();
+    let mut poller = poller(&script, &clock).with_cancellation(&token);
+
+    assert_eq!(events(&mut poller, &clock, 1).len(), 1);
+
+    token.cancel();
+    assert!(events(&mut poller, &clock, 1).is_empty());
+    assert_eq!(poller.in_flight(), 0);
+}
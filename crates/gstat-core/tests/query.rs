@@ -0,0 +1,108 @@
+//! Building queries from options and passing builders to `Game::fetch`.
+
+use gstat_core::prelude::{Game, Query, QueryOptions};
+
+use std::{
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+};
+
+use common::{block_on, Mock, MockGame, MockQuery, MockResponse};
+
+mod common;
+
+/// The protocol revisions a server may answer.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+enum Revision {
+    #[default]
+    Modern,
+    Legacy,
+}
+
+type StatusQuery = MockQuery<Revision>;
+
+/// The queries a server received.
+type Sent = Arc<Mutex<Vec<StatusQuery>>>;
+
+/// Returns a game whose default query asks for `revision`, and the queries its server
+/// receives.
+fn recording(revision: Revision) -> (MockGame<Revision>, Sent) {
+    let sent = Sent::default();
+    let log = sent.clone();
+
+    let game = Mock::default()
+        .with_options(QueryOptions {
+            extra: revision,
+            ..QueryOptions::default()
+        })
+        .respond(move |exchange| {
+            log.lock().unwrap().push(exchange.query.clone());
+            Ok(MockResponse::default())
+        })
+        .game();
+
+    (game, sent)
+}
+
+#[test]
+fn builder_collects_common_and_game_specific_options() {
+    let query = StatusQuery::builder()
+        .players(true)
+        .rules(false)
+        .extra(Revision::Legacy)
+        .build();
+
+    assert_eq!(
+        query,
+        MockQuery(QueryOptions {
+            players: true,
+            rules: false,
+            extra: Revision::Legacy,
+        })
+    );
+    assert_eq!(
+        StatusQuery::new().unwrap(),
+        MockQuery(QueryOptions::default())
+    );
+}
+
+#[test]
+fn fetch_accepts_a_query_or_a_builder() {
+    let (game, sent) = recording(Revision::Modern);
+    let address: SocketAddr = "192.0.2.1:27015".parse().unwrap();
+
+    let builder = StatusQuery::builder()
+        .rules(true)
+        .configure(|revision| *revision = Revision::Legacy);
+    assert!(builder.options().rules);
+
+    block_on(game.fetch(builder, address)).unwrap();
+    block_on(game.fetch(StatusQuery::new().unwrap(), address)).unwrap();
+
+    let sent = sent.lock().unwrap();
+    assert_eq!(sent[0].0.extra, Revision::Legacy);
+    assert!(sent[0].0.rules && !sent[0].0.players);
+    assert_eq!(sent[1], MockQuery(QueryOptions::default()));
+}
+
+#[test]
+fn games_preconfigure_their_default_query() {
+    let (game, sent) = recording(Revision::Legacy);
+    let address: SocketAddr = "192.0.2.1:27015".parse().unwrap();
+
+    assert_eq!(game.default_query().0.extra, Revision::Legacy);
+
+    block_on(game.fetch_default(address)).unwrap();
+    block_on(game.fetch(game.query_builder().players(true), address)).unwrap();
+
+    let sent = sent.lock().unwrap();
+    assert_eq!(sent[0], game.default_query());
+    assert_eq!(
+        sent[1],
+        MockQuery(QueryOptions {
+            players: true,
+            rules: false,
+            extra: Revision::Legacy,
+        })
+    );
+}
@@ -0,0 +1,127 @@
+//! Pacing packets with `RateLimiter`.
+
+use gstat_core::{
+    bulk::QueryMany,
+    clock::ManualClock,
+    rate_limit::{RateLimit, RateLimiter},
+};
+
+use std::{
+    net::SocketAddr,
+    time::{Duration, Instant},
+};
+
+fn address(address: &str) -> SocketAddr {
+    address.parse().unwrap()
+}
+
+/// Rounds a wait to whole milliseconds, hiding the time spent between reservations.
+fn millis(wait: Duration) -> u128 {
+    (wait.as_secs_f64() * 1000.0).round() as u128
+}
+
+#[test]
+fn the_global_bucket_allows_a_burst_then_paces() {
+    let limiter = RateLimiter::new().with_global(RateLimit::new(10.0, 3));
+    let target = address("192.0.2.1:27015");
+
+    let waits: Vec<u128> = (0..5).map(|_| millis(limiter.reserve(target))).collect();
+
+    assert_eq!(waits, [0, 0, 0, 100, 200]);
+}
+
+#[test]
+fn subnets_are_limited_independently() {
+    let limiter = RateLimiter::new().with_per_subnet(RateLimit::new(1.0, 1));
+
+    assert!(limiter.reserve(address("192.0.2.1:27015")).is_zero());
+    assert_eq!(millis(limiter.reserve(address("192.0.2.200:28015"))), 1000);
+    assert!(limiter.reserve(address("192.0.3.1:27015")).is_zero());
+
+    assert!(limiter.reserve(address("[2001:db8::1]:27015")).is_zero());
+    assert_eq!(
+        millis(limiter.reserve(address("[2001:db8::ffff:1]:27015"))),
+        1000
+    );
+    assert!(limiter
+        .reserve(address("[2001:db8:0:1::1]:27015"))
+        .is_zero());
+
+    // IPv4-mapped addresses share the bucket of their IPv4 subnet.
+    assert_eq!(
+        millis(limiter.reserve(address("[::ffff:192.0.2.7]:27015"))),
+        2000
+    );
+
+    assert!(RateLimiter::new().reserve(address("192.0.2.1:1")).is_zero());
+}
+
+#[test]
+fn refused_attempts_take_no_permits() {
+    let clock = ManualClock::new();
+    let limiter = RateLimiter::new()
+        .with_clock(clock.clone())
+        .with_global(RateLimit::new(10.0, 2))
+        .with_per_subnet(RateLimit::new(1.0, 1));
+    let (first, second) = (address("192.0.2.1:27015"), address("198.51.100.1:27015"));
+
+    assert_eq!(limiter.try_acquire(first), Ok(()));
+    assert_eq!(limiter.try_acquire(first), Err(Duration::from_secs(1)));
+    assert_eq!(limiter.try_acquire(second), Ok(()));
+
+    // The global bucket is empty now, and refusals didn't push it further back.
+    for _ in 0..3 {
+        assert_eq!(limiter.try_acquire(second), Err(Duration::from_secs(1)));
+    }
+
+    clock.advance(Duration::from_secs(1));
+    assert_eq!(limiter.try_acquire(first), Ok(()));
+    assert_eq!(limiter.try_acquire(second), Ok(()));
+}
+
+#[test]
+fn many_permits_are_taken_at_once_or_not_at_all() {
+    let clock = ManualClock::new();
+    let limiter = RateLimiter::new()
+        .with_clock(clock.clone())
+        .with_per_subnet(RateLimit::new(1.0, 4));
+    let client = address("192.0.2.1:50000");
+
+    assert_eq!(limiter.try_acquire_many(client, 3), Ok(()));
+    assert_eq!(
+        limiter.try_acquire_many(client, 3),
+        Err(Duration::from_secs(2))
+    );
+    assert_eq!(limiter.try_acquire(client), Ok(()));
+
+    // More permits than the burst take the whole burst once it refilled.
+    clock.advance(Duration::from_secs(4));
+    assert_eq!(limiter.try_acquire_many(client, 10), Ok(()));
+    assert_eq!(limiter.try_acquire(client), Err(Duration::from_secs(1)));
+}
+
+#[cfg(feature = "rt-tokio")]
+#[test]
+fn bulk_queries_wait_for_their_permit() {
+    let limiter = RateLimiter::new().with_global(RateLimit::new(50.0, 1));
+    let targets: Vec<SocketAddr> = (1..=5)
+        .map(|port| SocketAddr::from(([192, 0, 2, 1], port)))
+        .collect();
+
+    let started = Instant::now();
+    let results = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .unwrap()
+        .block_on(
+            QueryMany::new(targets, 5, |target| {
+                let port = target.port();
+                async move { port }
+            })
+            .with_rate_limiter(limiter)
+            .collect(),
+        );
+
+    assert_eq!(results.len(), 5);
+    assert!(started.elapsed() >= Duration::from_millis(80));
+}
@@ -0,0 +1,96 @@
+//! Joining split responses with `Reassembler`.
+
+use gstat_core::{
+    bytes::Bytes,
+    prelude::Error,
+    reassembly::{Fragment, Packet, Reassembler, ReassemblyError},
+};
+
+use std::{collections::VecDeque, thread, time::Duration};
+
+use common::block_on;
+
+mod common;
+
+fn fragment(id: u32, index: usize, total: usize, payload: &'static [u8]) -> Fragment {
+    Fragment {
+        id,
+        index,
+        total,
+        payload: Bytes::from_static(payload),
+    }
+}
+
+#[test]
+fn joins_interleaved_out_of_order_fragments() {
+    let mut reassembler = Reassembler::new();
+
+    assert_eq!(reassembler.push(fragment(1, 2, 3, b"c")).unwrap(), None);
+    assert_eq!(reassembler.push(fragment(2, 1, 2, b"y")).unwrap(), None);
+    assert_eq!(reassembler.push(fragment(1, 0, 3, b"a")).unwrap(), None);
+    assert_eq!(reassembler.push(fragment(1, 0, 3, b"a")).unwrap(), None);
+    assert_eq!(reassembler.pending(), 2);
+
+    let joined = reassembler.push(fragment(1, 1, 3, b"b")).unwrap();
+    assert_eq!(joined.as_deref(), Some(&b"abc"[..]));
+    assert!(!reassembler.is_pending(1));
+    assert!(reassembler.is_pending(2));
+}
+
+#[test]
+fn rejects_inconsistent_fragments() {
+    let mut reassembler = Reassembler::new().with_max_fragments(4);
+
+    let error = reassembler.push(fragment(1, 3, 3, b"")).unwrap_err();
+    assert_eq!(
+        error.detail().inner(),
+        Some(&ReassemblyError::InvalidIndex { index: 3, total: 3 })
+    );
+
+    reassembler.push(fragment(1, 0, 3, b"a")).unwrap();
+    let error = reassembler.push(fragment(1, 1, 2, b"b")).unwrap_err();
+    assert_eq!(
+        error.detail().inner(),
+        Some(&ReassemblyError::TotalMismatch {
+            expected: 3,
+            found: 2,
+        })
+    );
+
+    assert!(reassembler.push(fragment(2, 0, 5, b"")).is_err());
+}
+
+#[test]
+fn bounds_incomplete_sets_by_age_and_count() {
+    let mut reassembler = Reassembler::new()
+        .with_timeout(Duration::from_millis(20))
+        .with_max_sets(2);
+
+    reassembler.push(fragment(1, 0, 2, b"a")).unwrap();
+    reassembler.push(fragment(2, 0, 2, b"a")).unwrap();
+    reassembler.push(fragment(3, 0, 2, b"a")).unwrap();
+    assert!(!reassembler.is_pending(1));
+    assert_eq!(reassembler.pending(), 2);
+
+    thread::sleep(Duration::from_millis(30));
+    assert_eq!(reassembler.expire(), 2);
+}
+
+#[test]
+fn assembles_from_a_packet_source() {
+    let mut packets = VecDeque::from([
+        Packet::Fragment(fragment(7, 1, 2, b"world")),
+        Packet::Fragment(fragment(7, 0, 2, b"hello ")),
+        Packet::Single(Bytes::from_static(b"unused")),
+    ]);
+    let mut reassembler = Reassembler::new();
+
+    let response = block_on(reassembler.assemble(|| {
+        let packet = packets.pop_front().unwrap();
+        async move { Ok::<_, Error<ReassemblyError>>(packet) }
+    }))
+    .unwrap();
+
+    assert_eq!(response, "hello world");
+    assert_eq!(packets.len(), 1);
+}
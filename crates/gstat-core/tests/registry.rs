@@ -0,0 +1,91 @@
+//! Looking up the built-in game and protocol metadata generated from `data/games.toml`.
+
+use gstat_core::registry::{self, Capabilities, QueryPort, Transport, GAMES, PROTOCOLS};
+
+use std::collections::HashSet;
+
+#[test]
+fn games_are_found_by_identifier_or_alias_ignoring_case() {
+    let tf2 = registry::game("tf2").unwrap();
+
+    assert_eq!(tf2.name, "Team Fortress 2");
+    assert_eq!(registry::game("TeamFortress2").unwrap().id, "tf2");
+    assert_eq!(registry::game("MC").unwrap().id, "minecraft");
+
+    assert!(registry::game("unknown").is_none());
+    assert!(registry::game("").is_none());
+}
+
+#[test]
+fn protocols_are_found_by_identifier() {
+    let a2s = registry::protocol("a2s").unwrap();
+
+    assert_eq!(a2s.name, "Valve Source Query");
+    assert_eq!(a2s.transport, Transport::Udp);
+    assert!(a2s
+        .capabilities
+        .contains(Capabilities::CHALLENGE.union(Capabilities::MULTI_PACKET)));
+
+    let slp = registry::protocol("minecraft-slp").unwrap();
+    assert_eq!(slp.transport, Transport::Tcp);
+    assert_eq!(registry::game("minecraft").unwrap().protocol().id, slp.id);
+
+    // Protocol identifiers are matched exactly.
+    assert!(registry::protocol("A2S").is_none());
+}
+
+#[test]
+fn games_are_found_by_steam_appid() {
+    assert_eq!(registry::game_by_appid(440).unwrap().id, "tf2");
+    assert_eq!(registry::game_by_appid(252490).unwrap().id, "rust");
+    assert!(registry::game_by_appid(0).is_none());
+
+    let listed: usize = GAMES.iter().map(|game| game.steam_appids.len()).sum();
+    assert_eq!(registry::appids().count(), listed);
+
+    for game in GAMES {
+        for &appid in game.steam_appids {
+            assert_eq!(registry::game_by_appid(appid).unwrap().id, game.id);
+        }
+    }
+}
+
+#[test]
+fn generated_tables_hold_the_checks_of_the_build_script() {
+    let mut identifiers = HashSet::new();
+    let protocols: HashSet<&str> = PROTOCOLS.iter().map(|protocol| protocol.id).collect();
+
+    assert_eq!(protocols.len(), PROTOCOLS.len());
+
+    for game in GAMES {
+        for identifier in std::iter::once(&game.id).chain(game.aliases) {
+            assert!(identifiers.insert(*identifier), "`{identifier}` is taken");
+        }
+
+        assert!(protocols.contains(game.protocol), "`{}`", game.id);
+        assert!(game.query_port_for(game.game_port).is_some());
+
+        if let Some(srv) = game.srv {
+            assert_eq!(srv.split('.').count(), 2, "`{srv}`");
+        }
+    }
+}
+
+#[test]
+fn query_ports_follow_each_kind_of_metadata() {
+    // Rust servers answer queries two ports above the game port, by default 28017.
+    let rust = registry::game("rust").unwrap();
+    assert_eq!(rust.query_port, QueryPort::Offset(2));
+    assert_eq!((rust.game_port, rust.default_query_port()), (28015, 28017));
+
+    let minecraft = registry::game("minecraft").unwrap();
+    assert_eq!(minecraft.query_port, QueryPort::SameAsGame);
+    assert_eq!(minecraft.default_query_port(), 25565);
+    assert_eq!(minecraft.srv, Some("_minecraft._tcp"));
+
+    let fixed = GAMES
+        .iter()
+        .find(|game| matches!(game.query_port, QueryPort::Fixed(_)))
+        .unwrap();
+    assert_eq!(fixed.query_port_for(1), fixed.query_port_for(65535));
+}
@@ -0,0 +1,231 @@
+//! Resolving host names, with SRV records, and fetching by host name.
+
+use gstat_core::{
+    blocklist::Blocklist,
+    prelude::{ErrorKind, Game, TimeoutSettings},
+    registry::{self, GameEntry},
+    resolve::{resolve, HostPort, Resolution, Resolver, SrvRecord, StaticResolver},
+};
+
+use std::{
+    io,
+    net::{IpAddr, SocketAddr},
+};
+
+use common::{block_on, Mock, MockGame, MockQuery};
+
+mod common;
+
+fn ip(address: &str) -> IpAddr {
+    address.parse().unwrap()
+}
+
+fn addr(address: &str) -> SocketAddr {
+    address.parse().unwrap()
+}
+
+fn minecraft() -> Option<&'static GameEntry> {
+    registry::game("minecraft")
+}
+
+#[test]
+fn hosts_parse_with_and_without_ports() {
+    let cases = [
+        ("play.example.com", "play.example.com", None),
+        ("play.example.com:25566", "play.example.com", Some(25566)),
+        ("192.0.2.1:27015", "192.0.2.1", Some(27015)),
+        ("[2001:db8::1]:27015", "2001:db8::1", Some(27015)),
+        ("[2001:db8::1]", "2001:db8::1", None),
+        ("2001:db8::1", "2001:db8::1", None),
+    ];
+
+    for (input, host, port) in cases {
+        let parsed: HostPort = input.parse().unwrap();
+        assert_eq!(parsed, HostPort::new(host, port), "{input}");
+        assert_eq!(parsed.to_string().parse::<HostPort>().unwrap(), parsed);
+    }
+
+    assert_eq!(HostPort::new("::1", Some(7)).to_string(), "[::1]:7");
+
+    for invalid in ["", ":27015", "example.com:port", "[2001:db8::1", "a b:1"] {
+        assert!(invalid.parse::<HostPort>().is_err(), "{invalid}");
+    }
+}
+
+#[test]
+fn srv_records_are_ordered_by_priority_then_weight() {
+    let records = vec![
+        SrvRecord::new(20, 0, 25565, "backup.example.com"),
+        SrvRecord::new(10, 0, 25565, "never.example.com"),
+        SrvRecord::new(10, 100, 25565, "main.example.com"),
+    ];
+
+    for _ in 0..32 {
+        let ordered = SrvRecord::order(records.clone());
+        let targets: Vec<&str> = ordered
+            .iter()
+            .map(|record| record.target.as_str())
+            .collect();
+
+        assert_eq!(targets[2], "backup.example.com");
+        assert!(targets[..2].contains(&"main.example.com"));
+        assert!(targets[..2].contains(&"never.example.com"));
+    }
+}
+
+#[test]
+fn srv_records_take_precedence_when_no_port_is_given() {
+    let resolver = StaticResolver::new()
+        .with_host("example.com", [ip("192.0.2.1")])
+        .with_host("mc.example.net", [ip("2001:db8::5"), ip("198.51.100.5")])
+        .with_srv(
+            "_minecraft._tcp.example.com",
+            SrvRecord::new(0, 5, 25600, "mc.example.net."),
+        );
+
+    let target = "example.com".parse().unwrap();
+    let resolved = block_on(resolve(&resolver, &target, minecraft())).unwrap();
+
+    assert_eq!(
+        resolved.addresses,
+        [addr("[2001:db8::5]:25600"), addr("198.51.100.5:25600")]
+    );
+    assert_eq!(
+        resolved.resolution,
+        Resolution {
+            host: "example.com".to_string(),
+            srv: Some(SrvRecord::new(0, 5, 25600, "mc.example.net.")),
+        }
+    );
+
+    // An explicit port skips the SRV record.
+    let target = "example.com:25570".parse().unwrap();
+    let resolved = block_on(resolve(&resolver, &target, minecraft())).unwrap();
+
+    assert_eq!(resolved.addresses, [addr("192.0.2.1:25570")]);
+    assert_eq!(resolved.resolution.srv, None);
+}
+
+#[test]
+fn ports_are_mapped_to_query_ports() {
+    let resolver = StaticResolver::new()
+        .with_host("voice.example.com", [ip("192.0.2.9")])
+        .with_host("ts.example.com", [ip("192.0.2.10")])
+        .with_srv(
+            "_ts3._udp.example.com",
+            SrvRecord::new(0, 0, 9988, "ts.example.com"),
+        );
+
+    // TeamSpeak publishes the voice port; queries go to the fixed ServerQuery port.
+    let target = "example.com".parse().unwrap();
+    let resolved = block_on(resolve(&resolver, &target, registry::game("ts3"))).unwrap();
+    assert_eq!(resolved.addresses, [addr("192.0.2.10:10011")]);
+
+    // Rust answers queries on the game port plus two.
+    let target = "[2001:db8::1]:28015".parse().unwrap();
+    let resolved = block_on(resolve(&resolver, &target, registry::game("rust"))).unwrap();
+    assert_eq!(resolved.addresses, [addr("[2001:db8::1]:28017")]);
+
+    let target = "voice.example.com".parse().unwrap();
+    let err = block_on(resolve(&resolver, &target, None)).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+}
+
+#[test]
+fn unavailable_services_and_unknown_hosts_fail() {
+    let resolver = StaticResolver::new()
+        .with_host("closed.example.com", [ip("192.0.2.1")])
+        .with_srv(
+            "_minecraft._tcp.closed.example.com",
+            SrvRecord::new(0, 0, 0, "."),
+        );
+
+    let target = "closed.example.com".parse().unwrap();
+    let err = block_on(resolve(&resolver, &target, minecraft())).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::NotFound);
+
+    let target = "missing.example.com".parse().unwrap();
+    let err = block_on(resolve(&resolver, &target, minecraft())).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::NotFound);
+}
+
+/// Returns a game described by the Minecraft registry entry, answering at once.
+fn minecraft_game() -> MockGame {
+    Mock::new().with_registry_entry(minecraft()).game()
+}
+
+#[test]
+fn fetching_by_host_reports_the_resolution() {
+    let resolver = StaticResolver::new()
+        .with_host("mc.example.net", [ip("198.51.100.5")])
+        .with_srv(
+            "_minecraft._tcp.example.com",
+            SrvRecord::new(0, 0, 25600, "mc.example.net"),
+        );
+    let resolver: &dyn Resolver = &resolver;
+
+    let game = minecraft_game();
+
+    let fetched = block_on(game.fetch_host(
+        MockQuery::default(),
+        "example.com",
+        resolver,
+        TimeoutSettings::default(),
+    ))
+    .unwrap();
+
+    assert_eq!(fetched.meta.address(), Some(addr("198.51.100.5:25600")));
+
+    let resolution = fetched.meta.resolution().unwrap();
+    assert_eq!(resolution.host, "example.com");
+    assert_eq!(resolution.srv.as_ref().unwrap().target, "mc.example.net");
+
+    let err = block_on(game.fetch_host(
+        MockQuery::default(),
+        "unknown.example.com",
+        resolver,
+        TimeoutSettings::default(),
+    ))
+    .err()
+    .unwrap();
+
+    assert_eq!(err.kind(), ErrorKind::Dns);
+}
+
+#[test]
+fn fetching_by_host_skips_blocked_addresses() {
+    let resolver = StaticResolver::new()
+        .with_host(
+            "mixed.example.com",
+            [ip("198.51.100.5"), ip("198.51.100.6")],
+        )
+        .with_host("blocked.example.com", [ip("198.51.100.5")]);
+    let resolver: &dyn Resolver = &resolver;
+    let mut blocklist = Blocklist::new();
+    blocklist.block(ip("198.51.100.5"));
+    let game = minecraft_game();
+
+    let fetched = block_on(game.fetch_host_checked(
+        MockQuery::default(),
+        "mixed.example.com:25565",
+        resolver,
+        Some(&blocklist),
+        TimeoutSettings::default(),
+    ))
+    .unwrap();
+
+    assert_eq!(fetched.meta.address(), Some(addr("198.51.100.6:25565")));
+
+    let err = block_on(game.fetch_host_checked(
+        MockQuery::default(),
+        "blocked.example.com:25565",
+        resolver,
+        Some(&blocklist),
+        TimeoutSettings::default(),
+    ))
+    .err()
+    .unwrap();
+
+    assert_eq!(err.kind(), ErrorKind::Blocked);
+    assert!(err.to_string().contains("198.51.100.5:25565"), "{err}");
+}
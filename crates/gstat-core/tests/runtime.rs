@@ -0,0 +1,72 @@
+//! The runtime abstraction on whichever backend the crate was built for.
+
+use gstat_core::{
+    prelude::{Error, ErrorKind},
+    runtime::{self, Elapsed, UdpSocket},
+};
+
+#[cfg(not(feature = "rt-tokio"))]
+use std::future::Future;
+use std::{
+    future::pending,
+    net::SocketAddr,
+    time::{Duration, Instant},
+};
+
+#[cfg(feature = "rt-tokio")]
+use common::run;
+
+mod common;
+
+#[cfg(all(feature = "rt-async-std", not(feature = "rt-tokio")))]
+fn run<F: Future>(future: F) -> F::Output {
+    async_std::task::block_on(future)
+}
+
+#[cfg(all(
+    feature = "rt-smol",
+    not(any(feature = "rt-tokio", feature = "rt-async-std"))
+))]
+fn run<F: Future>(future: F) -> F::Output {
+    smol::block_on(future)
+}
+
+#[test]
+fn sleep_and_timeout_use_the_runtime_timer() {
+    run(async {
+        let started = Instant::now();
+        runtime::sleep(Duration::from_millis(20)).await;
+        assert!(started.elapsed() >= Duration::from_millis(20));
+
+        let elapsed = runtime::timeout(Duration::from_millis(10), pending::<()>()).await;
+        assert_eq!(elapsed, Err(Elapsed));
+
+        let error: Error<()> = elapsed.unwrap_err().into();
+        assert_eq!(error.kind(), ErrorKind::Timeout);
+
+        assert_eq!(
+            runtime::timeout(Duration::from_secs(1), async { 7 }).await,
+            Ok(7)
+        );
+    });
+}
+
+#[test]
+fn udp_sockets_exchange_datagrams() {
+    run(async {
+        let loopback = SocketAddr::from(([127, 0, 0, 1], 0));
+        let server = UdpSocket::bind(loopback).await.unwrap();
+        let client = UdpSocket::bind(loopback).await.unwrap();
+
+        client.connect(server.local_addr().unwrap()).await.unwrap();
+        client.send(b"ping").await.unwrap();
+
+        let mut buffer = [0; 16];
+        let (len, source) = server.recv_from(&mut buffer).await.unwrap();
+        assert_eq!(&buffer[..len], b"ping");
+
+        server.send_to(b"pong", source).await.unwrap();
+        let len = client.recv(&mut buffer).await.unwrap();
+        assert_eq!(&buffer[..len], b"pong");
+    });
+}
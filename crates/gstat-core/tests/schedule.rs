@@ -0,0 +1,157 @@
+//! Distribution of polls produced by `Schedule`.
+
+use gstat_core::{
+    prelude::ServerInfo,
+    schedule::{Mode, Phase, Sampling, Schedule},
+};
+
+use std::{net::SocketAddr, time::Duration};
+
+fn targets(count: u16) -> Vec<SocketAddr> {
+    (0..count)
+        .map(|port| SocketAddr::from(([192, 0, 2, 1], 27015 + port)))
+        .collect()
+}
+
+#[test]
+fn aligned_phase_polls_every_target_at_once() {
+    let schedule = Schedule::new(Duration::from_secs(60)).with_phase(Phase::Aligned);
+
+    assert!(schedule
+        .plan(&targets(10))
+        .iter()
+        .all(|(_, offset)| offset.is_zero()));
+}
+
+#[test]
+fn even_phase_spreads_targets_over_the_interval() {
+    let schedule = Schedule::new(Duration::from_secs(60)).with_phase(Phase::Even);
+    let offsets: Vec<Duration> = schedule
+        .plan(&targets(4))
+        .into_iter()
+        .map(|(_, offset)| offset)
+        .collect();
+
+    assert_eq!(offsets, [0, 15, 30, 45].map(Duration::from_secs).to_vec());
+}
+
+#[test]
+fn hashed_phase_is_stable_and_fills_the_interval() {
+    let interval = Duration::from_secs(60);
+    let schedule = Schedule::new(interval);
+    let targets = targets(1000);
+    let plan = schedule.plan(&targets);
+
+    assert!(plan.iter().all(|(_, offset)| *offset < interval));
+    assert_eq!(schedule.first_delay(targets[7]), plan[7].1);
+    assert_eq!(schedule.plan(&targets[..10]), plan[..10]);
+
+    let mut buckets = [0; 6];
+    for (_, offset) in &plan {
+        buckets[(offset.as_secs() / 10) as usize] += 1;
+    }
+    assert!(buckets.iter().all(|&count| count > 100), "{buckets:?}");
+}
+
+#[test]
+fn jitter_stays_within_its_bounds() {
+    let interval = Duration::from_secs(10);
+
+    assert_eq!(Schedule::new(interval).next_delay(), interval);
+
+    let schedule = Schedule::new(interval).with_jitter(0.2);
+    for _ in 0..1000 {
+        let delay = schedule.next_delay();
+        assert!(delay >= Duration::from_secs(8) && delay <= Duration::from_secs(12));
+    }
+
+    assert_eq!(Schedule::new(interval).with_jitter(7.0).jitter, 1.0);
+}
+
+#[test]
+fn jitter_set_directly_is_clamped() {
+    let interval = Duration::from_secs(10);
+
+    for jitter in [2.0, f64::INFINITY] {
+        let schedule = Schedule {
+            jitter,
+            ..Schedule::new(interval)
+        };
+
+        for _ in 0..1000 {
+            assert!(schedule.next_delay() <= interval * 2);
+        }
+    }
+
+    for jitter in [-1.0, f64::NAN] {
+        let schedule = Schedule {
+            jitter,
+            ..Schedule::new(interval)
+        };
+
+        assert_eq!(schedule.next_delay(), interval);
+    }
+}
+
+#[test]
+fn every_mode_selects_all_targets() {
+    let schedule = Schedule::new(Duration::from_secs(60));
+
+    assert_eq!(schedule.select([(1, 0.0), (2, 5.0), (3, 1.0)]), [1, 2, 3]);
+}
+
+#[test]
+fn sampling_favours_heavier_targets() {
+    let schedule =
+        Schedule::new(Duration::from_secs(60)).with_mode(Mode::Sample(Sampling::new(10)));
+    let mut heavy = 0;
+
+    for _ in 0..100 {
+        let candidates = (0..100).map(|target| (target, if target < 10 { 1000.0 } else { 1.0 }));
+        let selected = schedule.select(candidates);
+
+        assert_eq!(selected.len(), 10);
+
+        let mut unique = selected.clone();
+        unique.sort();
+        unique.dedup();
+        assert_eq!(unique.len(), 10);
+
+        heavy += selected.iter().filter(|target| **target < 10).count();
+    }
+
+    assert!(heavy > 800, "{heavy}");
+}
+
+#[test]
+fn sampling_floor_keeps_quiet_targets_reachable() {
+    let sampling = Sampling::new(1).with_floor(1.0);
+    let mut quiet = 0;
+
+    for _ in 0..1000 {
+        if sampling.select([("busy", 1.0), ("quiet", 0.0)]) == ["quiet"] {
+            quiet += 1;
+        }
+    }
+
+    assert!(quiet > 300 && quiet < 700, "{quiet}");
+    assert!(Sampling::new(5).select([("only", f64::NAN)]) == ["only"]);
+}
+
+#[test]
+fn weights_reflect_popularity_and_volatility() {
+    let previous = ServerInfo {
+        players: 10,
+        map: "de_dust2".to_string(),
+        ..ServerInfo::default()
+    };
+    let current = ServerInfo {
+        players: 4,
+        map: "de_inferno".to_string(),
+        ..ServerInfo::default()
+    };
+
+    assert_eq!(Sampling::popularity(&previous), 10.0);
+    assert_eq!(Sampling::volatility(&previous, &current), 16.0);
+    assert_eq!(Sampling::volatility(&current, &current), 0.0);
+}
@@ -0,0 +1,82 @@
+//! JSON Schemas of the serializable models with the `schemars` feature.
+
+use gstat_core::{
+    prelude::{AccessPolicy, Player, ServerInfo},
+    schema::{schema_for, schemas},
+};
+
+use std::collections::BTreeSet;
+
+use serde_json::{json, Value as Json};
+
+/// Returns the names of the properties the schema `schema` describes.
+fn properties(schema: &Json) -> BTreeSet<String> {
+    schema["properties"]
+        .as_object()
+        .unwrap()
+        .keys()
+        .cloned()
+        .collect()
+}
+
+/// Returns the names of the fields of the JSON object `value`.
+fn keys(value: Json) -> BTreeSet<String> {
+    value.as_object().unwrap().keys().cloned().collect()
+}
+
+#[test]
+fn schemas_describe_the_serialized_fields() {
+    let info = schema_for::<ServerInfo>();
+    assert_eq!(
+        properties(info.as_value()),
+        keys(serde_json::to_value(ServerInfo::default()).unwrap())
+    );
+    assert_eq!(info.get("title"), Some(&json!("ServerInfo")));
+
+    let player = schema_for::<Player>();
+    assert_eq!(
+        properties(player.as_value()),
+        keys(serde_json::to_value(Player::new("Ada")).unwrap())
+    );
+
+    // Every field has a default, so none is required.
+    assert_eq!(info.get("required"), None);
+}
+
+#[test]
+fn enums_list_their_serialized_names() {
+    let access = schema_for::<AccessPolicy>();
+    let names: Vec<Json> = [
+        AccessPolicy::Open,
+        AccessPolicy::Password,
+        AccessPolicy::Whitelist,
+        AccessPolicy::InviteOnly,
+    ]
+    .iter()
+    .map(|policy| serde_json::to_value(policy).unwrap())
+    .collect();
+
+    let variants: Vec<Json> = access
+        .get("oneOf")
+        .unwrap()
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|variant| variant["const"].clone())
+        .collect();
+
+    assert_eq!(variants, names);
+    assert!(access.get("description").is_some());
+}
+
+#[test]
+fn every_public_model_has_a_schema() {
+    let schemas = schemas();
+
+    for name in ["Player", "ServerInfo", "Value", "Provenance", "ETag"] {
+        assert!(schemas.contains_key(name), "missing the schema of {name}");
+    }
+
+    // Values are untagged, so their schema accepts any of the JSON types.
+    assert!(schemas["Value"].get("anyOf").is_some());
+}
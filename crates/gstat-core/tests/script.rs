@@ -0,0 +1,204 @@
+//! Describing games with Rhai scripts.
+
+use gstat_core::{
+    bytes::Bytes,
+    prelude::{AccessPolicy, ErrorKind, Parser, Response},
+    registry::Transport,
+    script::{ScriptError, ScriptGame, ScriptQuery, ScriptRegistry},
+};
+
+use std::time::Duration;
+
+const SCRIPT: &str = r#"
+    fn game() {
+        #{ id: "lantern", name: "Lantern", aliases: ["ltn"], transport: "udp", port: 7777 }
+    }
+
+    fn serialize() {
+        let query = blob();
+        query.push(0xFE);
+        query.push(0x01);
+        query
+    }
+
+    fn parse(reader) {
+        let info = #{
+            name: reader.read_cstring(),
+            map: reader.read_cstring(),
+            players: reader.read_u8(),
+            max_players: reader.read_u8(),
+            password: reader.read_u8() == 1,
+            extra: #{ tick: reader.read_u16_le() },
+            player_list: [],
+        };
+
+        for index in 0..info.players {
+            info.player_list.push(#{ name: reader.read_cstring(), duration: 2.5 });
+        }
+
+        info
+    }
+"#;
+
+#[test]
+fn scripts_serialize_queries_and_parse_responses() {
+    let game = ScriptGame::compile(SCRIPT).unwrap();
+
+    assert_eq!(game.id(), "lantern");
+    assert_eq!(game.transport(), Transport::Udp);
+    assert_eq!(game.port(), 7777);
+
+    let parser = game.parser();
+    assert_eq!(parser.serialize_query(&ScriptQuery).unwrap(), [0xFE, 0x01]);
+
+    let packet = Bytes::from_static(b"Camp\0forest\0\x01\x08\x00\x40\x00Ada\0");
+    let response = parser.deserialize_response(packet).unwrap();
+    let info = response.to_common();
+
+    assert_eq!(info.name, "Camp");
+    assert_eq!(info.map, "forest");
+    assert_eq!((info.players, info.max_players), (1, 8));
+    assert!(!info.password);
+    assert!(info.access.is_open());
+    assert_eq!(info.extra["tick"], "64");
+
+    let players = response.players();
+    assert_eq!(players[0].name, "Ada");
+    assert_eq!(players[0].duration, Some(Duration::from_millis(2500)));
+}
+
+#[test]
+fn failed_reads_are_invalid_packets() {
+    let parser = ScriptGame::compile(SCRIPT).unwrap().parser();
+
+    let err = parser
+        .deserialize_response(Bytes::from_static(b"Camp\0forest"))
+        .unwrap_err();
+
+    assert_eq!(err.kind(), ErrorKind::InvalidPacket);
+    assert!(matches!(
+        err.detail().inner(),
+        Some(ScriptError::Runtime(_))
+    ));
+}
+
+#[test]
+fn registry_looks_up_scripts_and_rejects_taken_identifiers() {
+    let mut registry = ScriptRegistry::new();
+    registry.load(SCRIPT).unwrap();
+
+    assert_eq!(registry.get("LTN").unwrap().id(), "lantern");
+
+    let again = registry.load(SCRIPT).unwrap_err();
+    assert_eq!(
+        again.detail().inner(),
+        Some(&ScriptError::Duplicate("lantern".into()))
+    );
+
+    let builtin = SCRIPT.replace(r#"id: "lantern""#, r#"id: "tf2""#);
+    let shadowing = registry.load(&builtin).unwrap_err();
+    assert_eq!(
+        shadowing.detail().inner(),
+        Some(&ScriptError::Duplicate("tf2".into()))
+    );
+
+    assert_eq!(registry.len(), 1);
+}
+
+#[test]
+fn scripts_must_define_every_function() {
+    let err = ScriptGame::compile("fn game() { #{} }").unwrap_err();
+
+    assert_eq!(
+        err.detail().inner(),
+        Some(&ScriptError::MissingFunction("serialize"))
+    );
+}
+
+#[test]
+fn scripts_record_where_values_came_from() {
+    let game = ScriptGame::compile(
+        r#"
+        fn game() {
+            #{ id: "beacon", name: "Beacon", transport: "udp", port: 7778 }
+        }
+
+        fn serialize() {
+            "status"
+        }
+
+        fn parse(reader) {
+            let byte = reader.read_u8();
+            let keywords = reader.read_cstring();
+
+            if keywords.starts_with("p") {
+                let players = parse_int(keywords.sub_string(1));
+                #{ players: players, provenance: #{ players: "keywords `p<count>`" } }
+            } else {
+                #{ players: byte, provenance: #{ players: "info byte 0" } }
+            }
+        }
+    "#,
+    )
+    .unwrap();
+
+    let parser = game.parser();
+
+    let response = parser
+        .deserialize_response(Bytes::from_static(b"\x03p12\0"))
+        .unwrap();
+    assert_eq!(response.to_common().players, 12);
+
+    let provenance = response.provenance();
+    assert_eq!(provenance.source("players"), Some("keywords `p<count>`"));
+    assert_eq!(provenance.source("map"), None);
+    assert_eq!(provenance.to_string(), "players <- keywords `p<count>`\n");
+
+    let response = parser
+        .deserialize_response(Bytes::from_static(b"\x03\0"))
+        .unwrap();
+    assert_eq!(response.to_common().players, 3);
+    assert_eq!(response.provenance().source("players"), Some("info byte 0"));
+
+    let plain = ScriptGame::compile(SCRIPT)
+        .unwrap()
+        .parser()
+        .deserialize_response(Bytes::from_static(b"Camp\0forest\0\x00\x08\x00\x40\x00"))
+        .unwrap();
+    assert!(plain.provenance().is_empty());
+}
+
+#[test]
+fn scripts_report_access_by_name_or_password_flag() {
+    let game = ScriptGame::compile(
+        r#"
+        fn game() { #{ id: "gate", name: "Gate", transport: "udp", port: 9000 } }
+        fn serialize() { blob() }
+
+        fn parse(reader) {
+            let info = #{ password: reader.read_u8() == 1 };
+
+            if reader.read_u8() == 1 {
+                info.access = "whitelist";
+            }
+
+            info
+        }
+    "#,
+    )
+    .unwrap();
+
+    let access = |packet: &'static [u8]| {
+        let info = game
+            .parser()
+            .deserialize_response(Bytes::from_static(packet))
+            .unwrap()
+            .to_common();
+        (info.password, info.access)
+    };
+
+    assert_eq!(access(b"\x00\x00"), (false, AccessPolicy::Open));
+    assert_eq!(access(b"\x01\x00"), (true, AccessPolicy::Password));
+    assert_eq!(access(b"\x01\x01"), (true, AccessPolicy::Whitelist));
+    assert_eq!(access(b"\x00\x01"), (false, AccessPolicy::Whitelist));
+}
@@ -0,0 +1,82 @@
+//! Serializing responses with the `serde` feature.
+
+use gstat_core::{
+    byte_str::ByteStr,
+    prelude::{AccessPolicy, Fingerprint, Player, ServerInfo, Value},
+};
+
+use std::time::Duration;
+
+use serde_json::json;
+
+#[test]
+fn server_info_round_trips_through_json() {
+    let mut info = ServerInfo {
+        name: "Badlands".into(),
+        map: "cp_badlands".into(),
+        players: 3,
+        max_players: 24,
+        access: AccessPolicy::InviteOnly,
+        ..ServerInfo::default()
+    };
+    info.extra.insert("sv_tags".into(), "payload".into());
+
+    let value = serde_json::to_value(&info).unwrap();
+    assert_eq!(value["map"], "cp_badlands");
+    assert_eq!(value["access"], "invite_only");
+    assert_eq!(value["extra"], json!({"sv_tags": "payload"}));
+
+    let back: ServerInfo = serde_json::from_value(value).unwrap();
+    assert_eq!(back, info);
+}
+
+#[test]
+fn missing_fields_take_their_default() {
+    let player: Player = serde_json::from_value(json!({"name": "Ada", "score": 7})).unwrap();
+
+    assert_eq!(player.score, Some(7));
+    assert_eq!(player.duration, None);
+
+    let mut timed = Player::new("Ada");
+    timed.duration = Some(Duration::from_secs(90));
+    let back: Player = serde_json::from_str(&serde_json::to_string(&timed).unwrap()).unwrap();
+    assert_eq!(back, timed);
+}
+
+#[test]
+fn byte_strings_and_fingerprints_serialize_as_plain_values() {
+    assert_eq!(
+        serde_json::to_value(ByteStr::from_static("de_dust2")).unwrap(),
+        json!("de_dust2")
+    );
+
+    let fingerprint = Fingerprint::from_u64(42);
+    assert_eq!(serde_json::to_value(fingerprint).unwrap(), json!(42));
+    assert_eq!(
+        serde_json::from_value::<Fingerprint>(json!(42)).unwrap(),
+        fingerprint
+    );
+}
+
+#[test]
+fn fields_serialize_as_plain_json() {
+    let mut player = Player::new("Ada");
+    player.ping = Some(42);
+
+    let value = serde_json::to_value(player.fields()).unwrap();
+    assert_eq!(
+        value,
+        json!({"name": "Ada", "score": null, "duration": null, "ping": 42, "team": null, "extra": {}})
+    );
+
+    let back: Value = serde_json::from_value(json!([1, 2.5, "three", true])).unwrap();
+    assert_eq!(
+        back,
+        Value::List(vec![
+            Value::Int(1),
+            Value::Float(2.5),
+            "three".into(),
+            true.into()
+        ])
+    );
+}
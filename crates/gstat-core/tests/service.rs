@@ -0,0 +1,76 @@
+//! Composing `GameService` with tower middleware.
+
+use gstat_core::{
+    prelude::TimeoutSettings,
+    service::{GameService, QueryRequest},
+};
+
+use std::{net::SocketAddr, time::Duration};
+
+use tower::{timeout::error::Elapsed, BoxError, ServiceBuilder, ServiceExt};
+
+use common::{run, Mock, MockGame, MockQuery, MockResponse};
+
+mod common;
+
+/// Returns a game whose server waits for the delay in the options of the query, then
+/// answers with the milliseconds it waited.
+fn patient() -> MockGame<Duration> {
+    Mock::default()
+        .respond_later(|exchange| async move {
+            let delay = exchange.query.0.extra;
+            tokio::time::sleep(delay).await;
+
+            Ok(MockResponse::named(delay.as_millis().to_string()))
+        })
+        .game()
+}
+
+fn address() -> SocketAddr {
+    SocketAddr::from(([192, 0, 2, 1], 27015))
+}
+
+#[test]
+fn requests_default_to_the_game_query() {
+    let service = GameService::new(patient());
+
+    let fetched = run(service.oneshot(QueryRequest::new(address()))).unwrap();
+    assert_eq!(fetched.response.info.name, "0");
+    assert_eq!(fetched.meta.address(), Some(address()));
+}
+
+#[test]
+fn middleware_bounds_the_queries() {
+    let service = ServiceBuilder::new()
+        .concurrency_limit(1)
+        .timeout(Duration::from_millis(100))
+        .service(GameService::new(patient()));
+
+    run(async {
+        let quick = QueryRequest::new(address())
+            .with_query(MockQuery::with_extra(Duration::from_millis(10)));
+        let fetched = service.clone().oneshot(quick).await.unwrap();
+        assert_eq!(fetched.response.info.name, "10");
+
+        let slow =
+            QueryRequest::new(address()).with_query(MockQuery::with_extra(Duration::from_secs(5)));
+        let err: BoxError = service.oneshot(slow).await.err().unwrap();
+        assert!(err.is::<Elapsed>());
+    });
+}
+
+#[test]
+fn requests_override_the_timeouts_of_the_service() {
+    let service = GameService::new(patient()).with_timeouts(TimeoutSettings {
+        overall: Some(Duration::from_secs(5)),
+        ..TimeoutSettings::default()
+    });
+    let request = QueryRequest::new(address())
+        .with_query(MockQuery::with_extra(Duration::from_secs(1)))
+        .with_timeouts(TimeoutSettings {
+            overall: Some(Duration::from_millis(20)),
+            ..TimeoutSettings::default()
+        });
+
+    assert!(run(service.oneshot(request)).is_err());
+}
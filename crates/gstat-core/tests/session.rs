@@ -0,0 +1,131 @@
+//! Keeping one connection across several queries with `Session`.
+
+use gstat_core::prelude::{Error, ErrorDetail, Game, TimeoutSettings};
+
+use std::{
+    net::SocketAddr,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+
+use common::{block_on, Exchange, Mock, MockError, MockGame, MockQuery, MockResponse};
+
+mod common;
+
+/// A query asking for one section of the server's data.
+type Section = MockQuery<&'static str>;
+
+/// Returns a console answering each section in upper case, whose connection breaks when
+/// asked for `"quit"`.
+fn console() -> (Mock<&'static str>, MockGame<&'static str>) {
+    let open = Arc::new(AtomicBool::new(false));
+    let connected = open.clone();
+
+    let mock = Mock::default()
+        .on_connect(move |peer| {
+            assert_eq!(peer, address());
+            connected.store(true, Ordering::SeqCst);
+            Ok(())
+        })
+        .respond(move |exchange: &Exchange<&str>| {
+            let section = exchange.query.0.extra;
+
+            if section == "quit" {
+                open.store(false, Ordering::SeqCst);
+            }
+
+            match open.load(Ordering::SeqCst) {
+                true => Ok(MockResponse::named(section.to_uppercase())),
+                false => Err(Error::ProtocolError(ErrorDetail::new(
+                    "connection closed",
+                    Some(MockError("connection closed")),
+                ))),
+            }
+        });
+    let game = mock.game();
+
+    (mock, game)
+}
+
+fn address() -> SocketAddr {
+    SocketAddr::from(([192, 0, 2, 1], 10011))
+}
+
+#[test]
+fn queries_share_one_connection() {
+    let (mock, game) = console();
+
+    block_on(async {
+        let mut session = game
+            .session(address(), TimeoutSettings::default())
+            .await
+            .unwrap();
+
+        for section in ["info", "players", "rules"] {
+            let fetched = session.query(Section::with_extra(section)).await.unwrap();
+            assert_eq!(fetched.response.info.name, section.to_uppercase());
+        }
+
+        session.disconnect().await.unwrap();
+    });
+
+    assert_eq!(
+        mock.calls(),
+        [
+            "connect",
+            "send_query",
+            "receive_response",
+            "send_query",
+            "receive_response",
+            "send_query",
+            "receive_response",
+            "disconnect",
+        ]
+    );
+}
+
+#[test]
+fn broken_sessions_reconnect_and_dropped_sessions_disconnect() {
+    let (mock, game) = console();
+
+    block_on(async {
+        let mut session = game
+            .session(address(), TimeoutSettings::default())
+            .await
+            .unwrap();
+
+        assert!(session.query(Section::with_extra("quit")).await.is_err());
+        assert!(session.query(Section::with_extra("info")).await.is_err());
+
+        session.reconnect().await.unwrap();
+        assert!(session.is_connected());
+        assert_eq!(
+            session
+                .query(Section::with_extra("info"))
+                .await
+                .unwrap()
+                .response
+                .info
+                .name,
+            "INFO"
+        );
+    });
+
+    assert_eq!(
+        mock.calls(),
+        [
+            "connect",
+            "send_query",
+            "receive_response",
+            "send_query",
+            "receive_response",
+            "disconnect",
+            "connect",
+            "send_query",
+            "receive_response",
+            "schedule_disconnect",
+        ]
+    );
+}
@@ -0,0 +1,121 @@
+//! The `silent` feature: no logging, telemetry or background tasks.
+
+use gstat_core::{prelude::Game, runtime};
+
+use std::{
+    net::SocketAddr,
+    sync::{
+        atomic::{AtomicBool, AtomicU32, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use tracing::{
+    span::{Attributes, Id, Record},
+    subscriber::Interest,
+    Event, Metadata, Subscriber,
+};
+
+use common::{block_on, immediate, Mock, MockGame, MockQuery};
+
+mod common;
+
+/// Returns a game whose servers lose the first `lost` responses, so fetching takes the
+/// retry path.
+fn lossy(lost: u32) -> MockGame {
+    Mock::new()
+        .with_retry_policy(immediate(2))
+        .losing(lost)
+        .game()
+}
+
+/// A subscriber counting every interaction, including callsite registration.
+#[derive(Clone, Default)]
+struct Counter {
+    calls: Arc<AtomicU32>,
+}
+
+impl Counter {
+    fn touch(&self) {
+        self.calls.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+impl Subscriber for Counter {
+    fn register_callsite(&self, _metadata: &'static Metadata<'static>) -> Interest {
+        self.touch();
+        Interest::always()
+    }
+
+    fn enabled(&self, _metadata: &Metadata<'_>) -> bool {
+        self.touch();
+        true
+    }
+
+    fn new_span(&self, _span: &Attributes<'_>) -> Id {
+        self.touch();
+        Id::from_u64(1)
+    }
+
+    fn record(&self, _span: &Id, _values: &Record<'_>) {
+        self.touch();
+    }
+
+    fn record_follows_from(&self, _span: &Id, _follows: &Id) {
+        self.touch();
+    }
+
+    fn event(&self, _event: &Event<'_>) {
+        self.touch();
+    }
+
+    fn enter(&self, _span: &Id) {
+        self.touch();
+    }
+
+    fn exit(&self, _span: &Id) {
+        self.touch();
+    }
+}
+
+fn address() -> SocketAddr {
+    SocketAddr::from(([192, 0, 2, 1], 27015))
+}
+
+#[test]
+fn fetching_emits_nothing_even_with_tracing_enabled() {
+    let counter = Counter::default();
+
+    tracing::subscriber::with_default(counter.clone(), || {
+        assert!(block_on(lossy(1).fetch(MockQuery::default(), address())).is_ok());
+
+        // A fetch that fails for good takes the warning path as well.
+        assert!(block_on(lossy(2).fetch(MockQuery::default(), address())).is_err());
+    });
+
+    assert_eq!(counter.calls.load(Ordering::SeqCst), 0);
+
+    // The counter itself works: a callsite outside gstat reaches it.
+    tracing::subscriber::with_default(counter.clone(), || tracing::info!("outside"));
+    assert!(counter.calls.load(Ordering::SeqCst) > 0);
+}
+
+#[test]
+fn no_background_tasks_are_spawned() {
+    let ran = Arc::new(AtomicBool::new(false));
+
+    tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .unwrap()
+        .block_on(async {
+            let flag = ran.clone();
+            let spawned = runtime::spawn(async move { flag.store(true, Ordering::SeqCst) });
+
+            assert!(!spawned);
+            runtime::sleep(Duration::from_millis(10)).await;
+        });
+
+    assert!(!ran.load(Ordering::SeqCst));
+}
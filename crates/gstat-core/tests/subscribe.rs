@@ -0,0 +1,170 @@
+//! Sharing poll results through a `Feed`.
+
+use gstat_core::{
+    clock::ManualClock,
+    poller::{Debounce, Poller},
+    prelude::ServerInfo,
+    schedule::{Phase, Schedule},
+    subscribe::{Feed, Subscription},
+};
+
+use std::{
+    pin::Pin,
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        Arc,
+    },
+    task::{Context, Poll, Waker},
+    time::Duration,
+};
+
+use futures_core::Stream;
+
+/// A poller whose servers answer with one more player on every poll, except `"down"`,
+/// which never answers.
+fn poller(
+    servers: &[&'static str],
+    clock: &ManualClock,
+    feed: &Feed<&'static str>,
+    polls: &Arc<AtomicU32>,
+) -> Poller<'static, &'static str, String> {
+    let polls = polls.clone();
+    let schedule = Schedule::new(Duration::from_secs(10)).with_phase(Phase::Aligned);
+
+    Poller::new(servers.to_vec(), schedule, move |server: &&'static str| {
+        let players = polls.fetch_add(1, Ordering::SeqCst);
+        let server = *server;
+
+        async move {
+            if server == "down" {
+                return Err("no answer".to_string());
+            }
+
+            Ok(ServerInfo {
+                players,
+                ..ServerInfo::default()
+            })
+        }
+    })
+    .with_debounce(Debounce::none())
+    .with_clock(clock.clone())
+    .with_feed(feed.clone())
+}
+
+/// Drives the poller through `rounds` rounds of polls.
+fn drive(poller: &mut Poller<'static, &'static str, String>, clock: &ManualClock, rounds: u32) {
+    let mut context = Context::from_waker(Waker::noop());
+
+    for _ in 0..rounds {
+        while let Poll::Ready(Some(_)) = poller.poll_next(&mut context) {}
+        clock.advance(Duration::from_secs(10));
+    }
+
+    while let Poll::Ready(Some(_)) = poller.poll_next(&mut context) {}
+}
+
+/// Takes the snapshots a subscription holds without waiting.
+fn held(subscription: &mut Subscription<&'static str>) -> Vec<(&'static str, Option<u32>)> {
+    let mut context = Context::from_waker(Waker::noop());
+    let mut snapshots = Vec::new();
+
+    while let Poll::Ready(Some(snapshot)) = Pin::new(&mut *subscription).poll_next(&mut context) {
+        snapshots.push((snapshot.target, snapshot.info.map(|info| info.players)));
+    }
+
+    snapshots
+}
+
+#[test]
+fn subscriptions_see_only_their_server() {
+    let clock = ManualClock::new();
+    let feed = Feed::new();
+    let polls = Arc::new(AtomicU32::new(0));
+    let mut poller = poller(&["up", "down"], &clock, &feed, &polls);
+
+    let mut up = feed.subscribe("up");
+    let mut down = feed.subscribe("down");
+
+    drive(&mut poller, &clock, 2);
+
+    let up = held(&mut up);
+    assert_eq!(up.len(), 3);
+    assert!(up
+        .iter()
+        .all(|(target, info)| *target == "up" && info.is_some()));
+
+    assert_eq!(held(&mut down), [("down", None); 3]);
+}
+
+#[test]
+fn every_consumer_shares_the_same_polls() {
+    let clock = ManualClock::new();
+    let feed = Feed::new();
+    let polls = Arc::new(AtomicU32::new(0));
+    let mut poller = poller(&["up"], &clock, &feed, &polls);
+
+    let mut ui = feed.subscribe_all();
+    let mut alerts = feed.subscribe_all();
+    let mut logger = feed.subscribe("up");
+
+    drive(&mut poller, &clock, 1);
+
+    let seen = held(&mut ui);
+    assert_eq!(seen, [("up", Some(0)), ("up", Some(1))]);
+    assert_eq!(held(&mut alerts), seen);
+    assert_eq!(held(&mut logger), seen);
+    assert_eq!(polls.load(Ordering::SeqCst), 2);
+}
+
+#[test]
+fn late_subscribers_start_with_the_latest_snapshot() {
+    let clock = ManualClock::new();
+    let feed = Feed::new();
+    let polls = Arc::new(AtomicU32::new(0));
+    let mut poller = poller(&["up"], &clock, &feed, &polls);
+
+    drive(&mut poller, &clock, 2);
+
+    let mut late = feed.subscribe("up");
+    assert_eq!(held(&mut late), [("up", Some(2))]);
+    assert!(feed.latest(&"up").unwrap().online);
+}
+
+#[test]
+fn slow_subscribers_miss_the_oldest_snapshots() {
+    let clock = ManualClock::new();
+    let feed = Feed::new().with_capacity(2);
+    let polls = Arc::new(AtomicU32::new(0));
+    let mut poller = poller(&["up"], &clock, &feed, &polls);
+
+    let mut slow = feed.subscribe("up");
+    drive(&mut poller, &clock, 4);
+
+    assert_eq!(held(&mut slow), [("up", Some(3)), ("up", Some(4))]);
+    assert_eq!(slow.missed(), 3);
+}
+
+#[test]
+fn subscriptions_end_with_their_poller() {
+    let clock = ManualClock::new();
+    let feed = Feed::new();
+    let polls = Arc::new(AtomicU32::new(0));
+    let mut poller = poller(&["up"], &clock, &feed, &polls);
+
+    let mut subscription = feed.subscribe("up");
+    drive(&mut poller, &clock, 0);
+    drop(poller);
+
+    let mut context = Context::from_waker(Waker::noop());
+    assert!(matches!(
+        subscription.poll_next(&mut context),
+        Poll::Ready(Some(_))
+    ));
+    assert!(matches!(
+        subscription.poll_next(&mut context),
+        Poll::Ready(None)
+    ));
+
+    drop(subscription);
+    assert_eq!(feed.subscribers(), 0);
+}
@@ -0,0 +1,110 @@
+//! Spans and events emitted by `Game::fetch` with the `tracing` feature.
+
+// The `silent` feature turns the instrumentation off.
+#![cfg(not(feature = "silent"))]
+
+use gstat_core::prelude::Game;
+
+use std::{
+    fmt::Debug,
+    net::SocketAddr,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+};
+
+use tracing::{
+    field::{Field, Visit},
+    span::{Attributes, Id, Record},
+    Event, Metadata, Subscriber,
+};
+
+use common::{block_on, immediate, Mock, MockQuery};
+
+mod common;
+
+/// Collects the fields of a span or event as `name=value` pairs.
+#[derive(Default)]
+struct Fields(Vec<String>);
+
+impl Visit for Fields {
+    fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+        self.0.push(format!("{}={value:?}", field.name()));
+    }
+}
+
+/// A subscriber recording every span and event as a line of text.
+struct Recorder {
+    log: Arc<Mutex<Vec<String>>>,
+    next_id: AtomicU64,
+}
+
+impl Subscriber for Recorder {
+    fn enabled(&self, _metadata: &Metadata<'_>) -> bool {
+        true
+    }
+
+    fn new_span(&self, span: &Attributes<'_>) -> Id {
+        let mut fields = Fields::default();
+        span.record(&mut fields);
+
+        let line = format!("span {} {}", span.metadata().name(), fields.0.join(" "));
+        self.log.lock().unwrap().push(line.trim_end().to_string());
+
+        Id::from_u64(self.next_id.fetch_add(1, Ordering::SeqCst))
+    }
+
+    fn record(&self, _span: &Id, _values: &Record<'_>) {}
+
+    fn record_follows_from(&self, _span: &Id, _follows: &Id) {}
+
+    fn event(&self, event: &Event<'_>) {
+        let mut fields = Fields::default();
+        event.record(&mut fields);
+
+        self.log
+            .lock()
+            .unwrap()
+            .push(format!("event {}", fields.0.join(" ")));
+    }
+
+    fn enter(&self, _span: &Id) {}
+
+    fn exit(&self, _span: &Id) {}
+}
+
+#[test]
+fn fetch_reports_spans_attempts_and_sizes() {
+    let log = Arc::new(Mutex::new(Vec::new()));
+    let recorder = Recorder {
+        log: log.clone(),
+        next_id: AtomicU64::new(1),
+    };
+    let address: SocketAddr = "192.0.2.1:27015".parse().unwrap();
+
+    // The first response is lost, so the fetch takes a second attempt.
+    let game = Mock::new().with_retry_policy(immediate(2)).losing(1).game();
+
+    let fetched = tracing::subscriber::with_default(recorder, || {
+        block_on(game.fetch(MockQuery::default(), address))
+    });
+    assert!(fetched.is_ok());
+
+    let log = log.lock().unwrap();
+    let position = |prefix: &str| {
+        log.iter()
+            .position(|line| line.starts_with(prefix))
+            .unwrap_or_else(|| panic!("no `{prefix}` in {log:#?}"))
+    };
+
+    let fetch = &log[position("span fetch")];
+    assert!(fetch.contains(r#"game="Mock""#), "{fetch}");
+    assert!(fetch.contains("address=192.0.2.1:27015"), "{fetch}");
+    assert!(fetch.contains("MockProtocol"), "{fetch}");
+
+    assert!(position("span attempt number=1") < position("span receive_response"));
+    assert!(position("event message=attempt failed attempt=1") < position("span attempt number=2"));
+    assert!(log.contains(&"event message=serialized query bytes=6".to_string()));
+    assert!(log.contains(&"event message=deserializing response bytes=4".to_string()));
+}
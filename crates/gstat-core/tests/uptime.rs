@@ -0,0 +1,131 @@
+//! Tracking failures and availability of polled servers, and alerting on thresholds.
+
+use gstat_core::{
+    prelude::ServerInfo,
+    subscribe::{Feed, Snapshot},
+    uptime::{Alert, UptimeEvent, UptimeMonitor},
+};
+
+use std::time::{Duration, Instant};
+
+use common::run;
+
+mod common;
+
+fn snapshot(at: Instant, players: Option<u32>) -> Snapshot<&'static str> {
+    Snapshot {
+        target: "a",
+        info: players.map(|players| ServerInfo {
+            players,
+            ..ServerInfo::default()
+        }),
+        error: players.is_none().then(|| "timed out".to_string()),
+        online: players.is_some(),
+        polled_at: at,
+    }
+}
+
+#[test]
+fn down_alerts_fire_after_consecutive_failures_and_resolve() {
+    let down = Alert::Down { polls: 2 };
+    let mut monitor = UptimeMonitor::new().with_alert(down);
+    let start = Instant::now();
+
+    assert!(monitor.observe(&snapshot(start, Some(1))).is_empty());
+    assert!(monitor.observe(&snapshot(start, None)).is_empty());
+    assert_eq!(monitor.consecutive_failures(&"a"), 1);
+
+    assert_eq!(
+        monitor.observe(&snapshot(start, None)),
+        [UptimeEvent::Firing {
+            target: "a",
+            alert: down
+        }]
+    );
+    assert!(monitor.observe(&snapshot(start, None)).is_empty());
+    assert_eq!(monitor.firing(&"a"), [down]);
+
+    assert_eq!(
+        monitor.observe(&snapshot(start, Some(1))),
+        [UptimeEvent::Resolved {
+            target: "a",
+            alert: down
+        }]
+    );
+    assert_eq!(monitor.consecutive_failures(&"a"), 0);
+    assert!(monitor.firing(&"a").is_empty());
+}
+
+#[test]
+fn player_alerts_count_answered_polls_below_the_floor() {
+    let empty = Alert::PlayersBelow {
+        players: 2,
+        polls: 2,
+    };
+    let mut monitor = UptimeMonitor::new().with_alert(empty);
+    let start = Instant::now();
+
+    assert!(monitor.observe(&snapshot(start, Some(1))).is_empty());
+    assert!(monitor.observe(&snapshot(start, Some(3))).is_empty());
+    assert!(monitor.observe(&snapshot(start, Some(0))).is_empty());
+    assert_eq!(monitor.observe(&snapshot(start, Some(1))).len(), 1);
+
+    // A silent server keeps the alert firing, an answer above the floor resolves it.
+    assert!(monitor.observe(&snapshot(start, None)).is_empty());
+    assert!(matches!(
+        monitor.observe(&snapshot(start, Some(5)))[..],
+        [UptimeEvent::Resolved { .. }]
+    ));
+}
+
+#[test]
+fn availability_covers_a_sliding_window() {
+    let start = Instant::now();
+    let minute = Duration::from_secs(60);
+    let outage = Alert::AvailabilityBelow {
+        ratio: 0.5,
+        window: minute * 4,
+    };
+    let mut monitor = UptimeMonitor::new()
+        .with_retention(minute * 2)
+        .with_alert(outage);
+
+    assert_eq!(monitor.availability(&"a", minute), None);
+
+    monitor.observe(&snapshot(start, Some(1)));
+    monitor.observe(&snapshot(start + minute, None));
+    monitor.observe(&snapshot(start + minute * 2, Some(1)));
+    assert!(monitor.firing(&"a").is_empty());
+
+    let events = monitor.observe(&snapshot(start + minute * 3, None));
+    assert!(events.is_empty());
+    assert_eq!(monitor.availability(&"a", minute * 4), Some(0.5));
+    assert_eq!(monitor.availability(&"a", minute * 2), Some(0.5));
+    assert_eq!(monitor.availability(&"a", minute), Some(0.0));
+
+    // The first answer leaves the window, so availability drops to one in four.
+    let events = monitor.observe(&snapshot(start + minute * 4, None));
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].alert(), &outage);
+    assert_eq!(monitor.availability(&"a", minute * 4), Some(0.25));
+}
+
+#[test]
+fn monitors_read_events_from_a_subscription() {
+    run(async {
+        let feed = Feed::new();
+        let mut monitor = UptimeMonitor::new()
+            .with_alert(Alert::Down { polls: 1 })
+            .with_subscription(feed.subscribe_all());
+        let start = Instant::now();
+
+        feed.publish(snapshot(start, Some(1)));
+        feed.publish(snapshot(start, None));
+        feed.close();
+
+        let event = monitor.next().await.unwrap();
+        assert!(matches!(event, UptimeEvent::Firing { .. }));
+        assert_eq!(event.target(), &"a");
+        assert_eq!(monitor.next().await, None);
+    });
+}
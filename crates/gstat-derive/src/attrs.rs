@@ -0,0 +1,294 @@
+use proc_macro2::{Span, TokenStream};
+use quote::{format_ident, quote};
+use syn::{
+    meta::ParseNestedMeta, Attribute, Data, DeriveInput, Fields, Ident, LitByteStr, LitStr, Type,
+};
+
+/// The byte order of multi-byte numbers.
+#[derive(Clone, Copy, Default)]
+pub(crate) enum Endian {
+    #[default]
+    Little,
+    Big,
+}
+
+/// The attributes on the struct itself.
+#[derive(Default)]
+pub(crate) struct Container {
+    /// `error = Type`: the error type of the query or response.
+    pub error: Option<Type>,
+    /// `header = b"..."`: the bytes preceding the fields.
+    pub header: Option<LitByteStr>,
+    /// `endian = "little" | "big"`: the byte order of numbers without an explicit one.
+    pub endian: Endian,
+    /// `player_list = method`: the method returning the players of a response.
+    pub player_list: Option<Ident>,
+}
+
+impl Container {
+    pub(crate) fn parse(attrs: &[Attribute]) -> syn::Result<Self> {
+        let mut container = Container::default();
+
+        for attr in attrs.iter().filter(|attr| attr.path().is_ident("gstat")) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("error") {
+                    container.error = Some(meta.value()?.parse()?);
+                } else if meta.path.is_ident("header") {
+                    container.header = Some(meta.value()?.parse()?);
+                } else if meta.path.is_ident("player_list") {
+                    container.player_list = Some(meta.value()?.parse()?);
+                } else if meta.path.is_ident("endian") {
+                    let endian: LitStr = meta.value()?.parse()?;
+
+                    container.endian = match endian.value().as_str() {
+                        "little" => Endian::Little,
+                        "big" => Endian::Big,
+                        _ => return Err(meta.error("expected `\"little\"` or `\"big\"`")),
+                    };
+                } else {
+                    return Err(unknown(&meta));
+                }
+
+                Ok(())
+            })?;
+        }
+
+        Ok(container)
+    }
+}
+
+/// The field of `ServerInfo` a response field is copied to.
+#[derive(Clone, Copy)]
+pub(crate) enum Common {
+    Name,
+    Map,
+    Game,
+    Version,
+    Players,
+    MaxPlayers,
+    Password,
+}
+
+/// A field and its attributes.
+pub(crate) struct Field {
+    pub ident: Ident,
+    /// How the field is encoded, or `None` if it is not on the wire.
+    pub wire: Option<Wire>,
+    /// `players`: the field is set from `QueryOptions::players`.
+    pub players: bool,
+    /// `rules`: the field is set from `QueryOptions::rules`.
+    pub rules: bool,
+    /// `common = "..."`: the `ServerInfo` field the value is copied to.
+    pub common: Option<Common>,
+    /// `extra = "..."`: the `ServerInfo::extra` key the value is copied to.
+    pub extra: Option<LitStr>,
+}
+
+/// Returns the fields of the struct `input`, in declaration order.
+pub(crate) fn fields(input: &DeriveInput, container: &Container) -> syn::Result<Vec<Field>> {
+    let Data::Struct(data) = &input.data else {
+        return Err(syn::Error::new_spanned(
+            &input.ident,
+            "only structs can be derived",
+        ));
+    };
+
+    let fields = match &data.fields {
+        Fields::Named(fields) => &fields.named,
+        Fields::Unit => return Ok(Vec::new()),
+        Fields::Unnamed(fields) => {
+            return Err(syn::Error::new_spanned(
+                fields,
+                "tuple structs can't be derived, name the fields",
+            ))
+        }
+    };
+
+    fields
+        .iter()
+        .map(|field| {
+            let ident = field.ident.clone().expect("named field");
+            let mut wire = None;
+            let mut skip = false;
+            let mut parsed = Field {
+                ident,
+                wire: None,
+                players: false,
+                rules: false,
+                common: None,
+                extra: None,
+            };
+
+            for attr in field
+                .attrs
+                .iter()
+                .filter(|attr| attr.path().is_ident("gstat"))
+            {
+                attr.parse_nested_meta(|meta| {
+                    if meta.path.is_ident("wire") {
+                        let name: LitStr = meta.value()?.parse()?;
+                        wire = Some(Wire::named(&name.value(), container.endian, name.span())?);
+                    } else if meta.path.is_ident("skip") {
+                        skip = true;
+                    } else if meta.path.is_ident("players") {
+                        parsed.players = true;
+                    } else if meta.path.is_ident("rules") {
+                        parsed.rules = true;
+                    } else if meta.path.is_ident("extra") {
+                        parsed.extra = Some(meta.value()?.parse()?);
+                    } else if meta.path.is_ident("common") {
+                        let name: LitStr = meta.value()?.parse()?;
+
+                        parsed.common = Some(match name.value().as_str() {
+                            "name" => Common::Name,
+                            "map" => Common::Map,
+                            "game" => Common::Game,
+                            "version" => Common::Version,
+                            "players" => Common::Players,
+                            "max_players" => Common::MaxPlayers,
+                            "password" => Common::Password,
+                            _ => {
+                                return Err(syn::Error::new(
+                                    name.span(),
+                                    "expected one of `name`, `map`, `game`, `version`, \
+                                     `players`, `max_players` or `password`",
+                                ))
+                            }
+                        });
+                    } else {
+                        return Err(unknown(&meta));
+                    }
+
+                    Ok(())
+                })?;
+            }
+
+            if !skip {
+                parsed.wire = Some(match wire {
+                    Some(wire) => wire,
+                    None => Wire::inferred(&field.ty, container.endian)?,
+                });
+            }
+
+            Ok(parsed)
+        })
+        .collect()
+}
+
+/// What a wire encoding reads into and writes from.
+#[derive(Clone, Copy)]
+enum Kind {
+    /// A number written and read as is.
+    Number,
+    /// A flag stored as a byte.
+    Bool,
+    /// A string read as a `ByteStr` and written from a `&str`.
+    Str,
+    /// The rest of the packet.
+    Rest,
+}
+
+/// How a field is encoded.
+pub(crate) struct Wire {
+    kind: Kind,
+    read: Ident,
+    write: Ident,
+}
+
+impl Wire {
+    /// Resolves the encoding named in `#[gstat(wire = "...")]`.
+    fn named(name: &str, endian: Endian, span: Span) -> syn::Result<Self> {
+        let suffix = match endian {
+            Endian::Little => "le",
+            Endian::Big => "be",
+        };
+        let number = |name: String| (Kind::Number, name.clone(), name);
+
+        let (kind, read, write) = match name {
+            "u8" | "i8" => number(name.to_string()),
+            "u16" | "i16" | "u32" | "i32" | "u64" | "i64" => number(format!("{name}_{suffix}")),
+            "u16_le" | "u16_be" | "i16_le" | "i16_be" | "u32_le" | "u32_be" | "i32_le"
+            | "i32_be" | "u64_le" | "u64_be" | "i64_le" | "i64_be" | "f32_le" | "f64_le"
+            | "varint_u32" | "varint_i32" | "varint_u64" | "varint_i64" => number(name.to_string()),
+            "f32" | "f64" => number(format!("{name}_le")),
+            "bool" => (Kind::Bool, "u8".into(), "u8".into()),
+            "cstring" | "pascal_string" | "utf16le_cstring" | "varint_string" => {
+                (Kind::Str, name.to_string(), name.to_string())
+            }
+            "cstring_lossy" => (Kind::Str, name.to_string(), "cstring".into()),
+            "utf16le_cstring_lossy" => (Kind::Str, name.to_string(), "utf16le_cstring".into()),
+            "rest" => (Kind::Rest, "rest".into(), "bytes".into()),
+            _ => {
+                return Err(syn::Error::new(
+                    span,
+                    format!("unknown wire encoding `{name}`"),
+                ))
+            }
+        };
+
+        Ok(Wire {
+            kind,
+            read: Ident::new(&format!("read_{read}"), span),
+            write: Ident::new(&format!("write_{write}"), span),
+        })
+    }
+
+    /// Infers the encoding from the type of the field.
+    fn inferred(ty: &Type, endian: Endian) -> syn::Result<Self> {
+        let name = match ty {
+            Type::Path(path) => path.path.segments.last().map(|segment| &segment.ident),
+            _ => None,
+        };
+
+        let wire = match name.map(Ident::to_string).as_deref() {
+            Some(
+                name @ ("u8" | "i8" | "u16" | "i16" | "u32" | "i32" | "u64" | "i64" | "f32" | "f64"
+                | "bool"),
+            ) => name.to_string(),
+            Some("String" | "ByteStr") => "cstring".into(),
+            Some("Bytes" | "Vec") => "rest".into(),
+            _ => {
+                return Err(syn::Error::new_spanned(
+                    ty,
+                    "can't infer the wire encoding, add `#[gstat(wire = \"...\")]` \
+                     or `#[gstat(skip)]`",
+                ))
+            }
+        };
+
+        Self::named(&wire, endian, Span::call_site())
+    }
+
+    /// Returns the expression reading the field from `reader`.
+    pub(crate) fn read(&self) -> TokenStream {
+        let read = &self.read;
+
+        match self.kind {
+            Kind::Number => quote!(reader.#read()?),
+            Kind::Bool => quote!(reader.#read()? != 0),
+            Kind::Str => quote!(::core::convert::Into::into(reader.#read()?)),
+            Kind::Rest => quote!(::core::convert::Into::into(reader.#read())),
+        }
+    }
+
+    /// Returns the statement writing the field `ident` of `self` to `writer`.
+    pub(crate) fn write(&self, ident: &Ident) -> TokenStream {
+        let write = &self.write;
+
+        match self.kind {
+            Kind::Number => quote!(writer.#write(self.#ident);),
+            Kind::Bool => quote!(writer.#write(::core::primitive::u8::from(self.#ident));),
+            Kind::Str | Kind::Rest => quote!(writer.#write(&self.#ident);),
+        }
+    }
+}
+
+/// Returns the error for an attribute neither derive knows.
+fn unknown(meta: &ParseNestedMeta) -> syn::Error {
+    let name = meta
+        .path
+        .get_ident()
+        .map_or_else(|| format_ident!("attribute"), Clone::clone);
+
+    meta.error(format!("unknown gstat attribute `{name}`"))
+}
@@ -0,0 +1,94 @@
+//! Derive macros for the `Query` and `Response` boilerplate of simple binary protocols.
+//!
+//! Use them through the `derive` feature of `gstat-core`, which re-exports them. The
+//! annotated struct describes the packet: its fields are encoded in declaration order,
+//! after an optional fixed header, with an encoding inferred from their type or chosen
+//! with `#[gstat(wire = "...")]`.
+//!
+//! ```ignore
+//! #[derive(Clone, Default, GstatQuery)]
+//! #[gstat(header = b"\xFF\xFF\xFF\xFFTSource Engine Query\0")]
+//! struct InfoQuery;
+//!
+//! #[derive(Default, GstatResponse)]
+//! #[gstat(header = b"\xFF\xFF\xFF\xFFI")]
+//! struct InfoResponse {
+//!     protocol: u8,
+//!     #[gstat(common = "name")]
+//!     name: ByteStr,
+//!     #[gstat(common = "map")]
+//!     map: ByteStr,
+//!     folder: ByteStr,
+//!     #[gstat(common = "game")]
+//!     game: ByteStr,
+//!     #[gstat(extra = "app_id")]
+//!     app_id: u16,
+//!     #[gstat(common = "players")]
+//!     players: u8,
+//!     #[gstat(common = "max_players")]
+//!     max_players: u8,
+//! }
+//!
+//! type InfoParser = WireParser<InfoQuery, InfoResponse>;
+//! ```
+//!
+//! # Struct attributes
+//!
+//! * `error = Type`: The error type of the query or response. Defaults to `EncodeError`
+//!   for queries and `DecodeError` for responses.
+//! * `header = b"..."`: Bytes written before the fields, and checked before reading them.
+//! * `endian = "little" | "big"`: The byte order of numbers without an explicit one.
+//!   Defaults to little-endian.
+//! * `player_list = method`: For responses, an inherent method returning the players as
+//!   `Vec<Player>`, used for `Response::players`.
+//!
+//! # Field attributes
+//!
+//! * `wire = "..."`: The encoding of the field, one of the `ByteReader` methods without
+//!   their `read_` prefix (e.g. `u16_be`, `varint_i32`, `pascal_string`,
+//!   `cstring_lossy`), `u16` to `i64` in the struct's byte order, `bool` for a byte that
+//!   is non-zero when set, or `rest` for the remaining bytes. Without it, numbers and
+//!   `bool` use their own name, `String` and `ByteStr` use `cstring`, and `Bytes` and
+//!   `Vec<u8>` use `rest`.
+//! * `skip`: The field is not on the wire and takes its default value.
+//! * `players`, `rules`: For queries, the field is set from the matching `QueryOptions`
+//!   flag; it is still encoded unless skipped.
+//! * `common = "..."`: For responses, the `ServerInfo` field the value is copied to: `name`,
+//!   `map`, `game`, `version`, `players`, `max_players` or `password`.
+//! * `extra = "key"`: For responses, the `ServerInfo::extra` entry the value is copied to.
+//!
+//! Both derives require `Default`, and `GstatQuery` requires `Clone` as `Query` does; the
+//! serde derives can be added alongside them as usual.
+
+mod attrs;
+mod query;
+mod response;
+
+use proc_macro::TokenStream;
+use syn::{parse_macro_input, DeriveInput};
+
+/// Implements `Query` and `Encode` for a struct describing a query packet.
+///
+/// `Query::from_options` starts from `Default::default()` and sets the fields marked
+/// `players` or `rules`.
+#[proc_macro_derive(GstatQuery, attributes(gstat))]
+pub fn derive_query(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    query::expand(&input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// Implements `Response` and `Decode` for a struct describing a response packet.
+///
+/// `Response::to_common` copies the fields marked `common` or `extra`, and
+/// `Response::fields` adds every field on the wire under `raw`.
+#[proc_macro_derive(GstatResponse, attributes(gstat))]
+pub fn derive_response(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    response::expand(&input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
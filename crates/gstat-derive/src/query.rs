@@ -0,0 +1,68 @@
+use crate::attrs::{fields, Container};
+
+use proc_macro2::TokenStream;
+use quote::{quote, ToTokens};
+use syn::DeriveInput;
+
+/// Implements `Query` and `Encode` for the struct `input`.
+pub(crate) fn expand(input: &DeriveInput) -> syn::Result<TokenStream> {
+    let container = Container::parse(&input.attrs)?;
+    let fields = fields(input, &container)?;
+
+    for field in &fields {
+        if field.common.is_some() || field.extra.is_some() {
+            return Err(syn::Error::new_spanned(
+                &field.ident,
+                "`common` and `extra` only apply to responses",
+            ));
+        }
+    }
+
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let error = container.error.map_or_else(
+        || quote!(::gstat_core::codec::writer::EncodeError),
+        |error| error.into_token_stream(),
+    );
+
+    let options = fields.iter().filter_map(|field| {
+        let ident = &field.ident;
+
+        match (field.players, field.rules) {
+            (true, _) => Some(quote!(#ident: options.players,)),
+            (_, true) => Some(quote!(#ident: options.rules,)),
+            _ => None,
+        }
+    });
+    let header = container
+        .header
+        .map(|header| quote!(writer.write_bytes(#header);));
+    let writes = fields
+        .iter()
+        .filter_map(|field| field.wire.as_ref().map(|wire| wire.write(&field.ident)));
+
+    Ok(quote! {
+        #[automatically_derived]
+        impl #impl_generics ::gstat_core::prelude::Query for #name #ty_generics #where_clause {
+            type E = #error;
+            type Options = ();
+
+            #[allow(unused_variables)]
+            fn from_options(options: ::gstat_core::prelude::QueryOptions) -> Self {
+                Self {
+                    #(#options)*
+                    ..::core::default::Default::default()
+                }
+            }
+        }
+
+        #[automatically_derived]
+        impl #impl_generics ::gstat_core::codec::wire::Encode for #name #ty_generics #where_clause {
+            #[allow(unused_variables)]
+            fn encode(&self, writer: &mut ::gstat_core::codec::writer::ByteWriter) {
+                #header
+                #(#writes)*
+            }
+        }
+    })
+}
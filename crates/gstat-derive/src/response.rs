@@ -0,0 +1,161 @@
+use crate::attrs::{fields, Common, Container};
+
+use proc_macro2::TokenStream;
+use quote::{quote, ToTokens};
+use syn::DeriveInput;
+
+/// Implements `Response` and `Decode` for the struct `input`.
+pub(crate) fn expand(input: &DeriveInput) -> syn::Result<TokenStream> {
+    let container = Container::parse(&input.attrs)?;
+    let fields = fields(input, &container)?;
+
+    for field in &fields {
+        if field.players || field.rules {
+            return Err(syn::Error::new_spanned(
+                &field.ident,
+                "`players` and `rules` only apply to queries",
+            ));
+        }
+    }
+
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let error = container.error.map_or_else(
+        || quote!(::gstat_core::codec::reader::DecodeError),
+        |error| error.into_token_stream(),
+    );
+
+    let header = container
+        .header
+        .map(|header| quote!(reader.expect_bytes(#header)?;));
+    let reads = fields.iter().map(|field| {
+        let ident = &field.ident;
+
+        match &field.wire {
+            Some(wire) => {
+                let read = wire.read();
+                quote!(#ident: #read,)
+            }
+            None => quote!(#ident: ::core::default::Default::default(),),
+        }
+    });
+
+    let common = fields.iter().map(|field| {
+        let ident = &field.ident;
+        let string = quote!(::std::string::ToString::to_string(&self.#ident));
+        let count = quote! {
+            ::core::convert::TryFrom::try_from(self.#ident)
+                .unwrap_or(::core::primitive::u32::MAX)
+        };
+
+        let common = field.common.map(|common| match common {
+            Common::Name => quote!(info.name = #string;),
+            Common::Map => quote!(info.map = #string;),
+            Common::Game => quote!(info.game = #string;),
+            Common::Version => quote!(info.version = #string;),
+            Common::Players => quote!(info.players = #count;),
+            Common::MaxPlayers => quote!(info.max_players = #count;),
+            Common::Password => quote! {
+                info.password = self.#ident;
+                info.access = ::gstat_core::prelude::AccessPolicy::from_signals(
+                    info.password,
+                    false,
+                    false,
+                );
+            },
+        });
+        let extra = field
+            .extra
+            .as_ref()
+            .map(|key| quote!(info.extra.insert(::std::string::String::from(#key), #string);));
+
+        quote!(#common #extra)
+    });
+
+    let raw = fields
+        .iter()
+        .filter(|field| field.wire.is_some())
+        .map(|field| {
+            let ident = &field.ident;
+            let key = ident.to_string();
+
+            quote! {
+                (
+                    ::std::string::String::from(#key),
+                    ::gstat_core::prelude::Value::from(::core::clone::Clone::clone(&self.#ident)),
+                ),
+            }
+        });
+
+    let players = container.player_list.map(|method| {
+        quote! {
+            fn players(&self) -> ::std::vec::Vec<::gstat_core::prelude::Player> {
+                self.#method()
+            }
+        }
+    });
+
+    Ok(quote! {
+        #[automatically_derived]
+        impl #impl_generics ::gstat_core::prelude::Response for #name #ty_generics #where_clause {
+            type E = #error;
+
+            fn new() -> ::core::result::Result<Self, ::gstat_core::prelude::Error<Self::E>> {
+                ::core::result::Result::Ok(::core::default::Default::default())
+            }
+
+            fn to_common(&self) -> ::gstat_core::prelude::ServerInfo {
+                #[allow(unused_mut)]
+                let mut info = ::gstat_core::prelude::ServerInfo::default();
+                #(#common)*
+                info
+            }
+
+            #players
+
+            fn fields(
+                &self,
+            ) -> ::std::collections::BTreeMap<::std::string::String, ::gstat_core::prelude::Value>
+            {
+                let mut fields =
+                    ::gstat_core::prelude::Response::to_common(self).fields();
+                let players = ::gstat_core::prelude::Response::players(self);
+
+                if !players.is_empty() {
+                    let players = players
+                        .iter()
+                        .map(|player| ::gstat_core::prelude::Value::Map(player.fields()))
+                        .collect();
+
+                    fields.insert(
+                        ::std::string::String::from("player_list"),
+                        ::gstat_core::prelude::Value::List(players),
+                    );
+                }
+
+                fields.insert(
+                    ::std::string::String::from("raw"),
+                    ::gstat_core::prelude::Value::Map(::std::collections::BTreeMap::from([
+                        #(#raw)*
+                    ])),
+                );
+
+                fields
+            }
+        }
+
+        #[automatically_derived]
+        impl #impl_generics ::gstat_core::codec::wire::Decode for #name #ty_generics #where_clause {
+            #[allow(unused_variables)]
+            fn decode(
+                reader: &mut ::gstat_core::codec::reader::ByteReader,
+            ) -> ::gstat_core::codec::reader::DecodeResult<Self> {
+                #header
+
+                ::core::result::Result::Ok(Self {
+                    #(#reads)*
+                })
+            }
+        }
+    })
+}
@@ -0,0 +1,173 @@
+use gstat_core::{
+    blocklist::Blocklist,
+    duration,
+    fleet::{Fleet, Sink},
+    prelude::TimeoutSettings,
+};
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use serde::Deserialize;
+
+/// How often targets are polled when neither they nor the file set an interval.
+pub const DEFAULT_INTERVAL: Duration = Duration::from_secs(30);
+
+/// The limit applied to every step of a poll when the file sets none.
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// A server to poll.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Target {
+    /// The game the server runs, as the identifier of a loaded script.
+    pub game: String,
+    /// The server address, as `host` or `host:port`. Without a port, the game's default
+    /// port is used.
+    pub address: String,
+    /// How often the server is polled, overriding `interval`.
+    #[serde(default, deserialize_with = "duration::deserialize_optional")]
+    pub interval: Option<Duration>,
+}
+
+/// Where poll snapshots are published over MQTT, written as an `[mqtt]` table.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Mqtt {
+    /// The broker, as `host` or `host:port`. Without a port, 1883 is used.
+    pub broker: String,
+    /// The client identifier, `gstat-exporter` by default.
+    #[serde(default)]
+    pub client_id: Option<String>,
+    /// The first level of every topic, `gstat` by default.
+    #[serde(default)]
+    pub prefix: Option<String>,
+    /// The user name to log in with.
+    #[serde(default)]
+    pub username: Option<String>,
+    /// The password to log in with, used together with `username`.
+    #[serde(default)]
+    pub password: Option<String>,
+}
+
+/// The contents of the target list.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// How often every server is polled, e.g. `"30s"`.
+    #[serde(default, deserialize_with = "duration::deserialize_optional")]
+    pub interval: Option<Duration>,
+    /// The limit applied to every step of a poll, e.g. `"5s"`.
+    #[serde(default, deserialize_with = "duration::deserialize_optional")]
+    pub timeout: Option<Duration>,
+    /// The game scripts to load, relative to the file.
+    #[serde(default)]
+    pub scripts: Vec<PathBuf>,
+    /// Opt-out lists of addresses and ranges that are never polled, relative to the file.
+    #[serde(default)]
+    pub blocklist: Vec<PathBuf>,
+    /// The servers to poll, written as `[[target]]` tables.
+    #[serde(default, rename = "target")]
+    pub targets: Vec<Target>,
+    /// The MQTT broker snapshots are published to, if any.
+    #[serde(default)]
+    pub mqtt: Option<Mqtt>,
+}
+
+impl Config {
+    /// Loads the target list.
+    ///
+    /// Script and blocklist paths are resolved against the directory of the file.
+    ///
+    /// # Parameters
+    ///
+    /// * `path`: The path of the file.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing either the configuration or a description of why it couldn't
+    /// be read.
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let source = fs::read_to_string(path)
+            .map_err(|err| format!("failed to read {}: {err}", path.display()))?;
+        let mut config: Config =
+            toml::from_str(&source).map_err(|err| format!("invalid {}: {err}", path.display()))?;
+
+        let base = path.parent().unwrap_or(Path::new(""));
+        config.scripts = config
+            .scripts
+            .into_iter()
+            .map(|script| base.join(script))
+            .collect();
+        config.blocklist = config
+            .blocklist
+            .into_iter()
+            .map(|list| base.join(list))
+            .collect();
+
+        Ok(config)
+    }
+
+    /// Builds the target list from a fleet.
+    ///
+    /// The exporter uses the targets, intervals, scripts and MQTT sink of the fleet; alerts
+    /// and the other sinks are left to the monitors that handle them.
+    pub fn from_fleet(fleet: &Fleet) -> Self {
+        Config {
+            interval: Some(fleet.interval),
+            timeout: Some(fleet.timeout),
+            scripts: fleet.scripts.clone(),
+            blocklist: Vec::new(),
+            targets: fleet
+                .targets
+                .iter()
+                .map(|target| Target {
+                    game: target.game.clone(),
+                    address: target.address.to_string(),
+                    interval: target.interval,
+                })
+                .collect(),
+            mqtt: fleet.sinks.iter().find_map(|sink| match sink {
+                Sink::Mqtt { broker, prefix } => Some(Mqtt {
+                    broker: broker.clone(),
+                    client_id: None,
+                    prefix: prefix.clone(),
+                    username: None,
+                    password: None,
+                }),
+                _ => None,
+            }),
+        }
+    }
+
+    /// Returns how often servers without an interval of their own are polled.
+    pub fn interval(&self) -> Duration {
+        self.interval.unwrap_or(DEFAULT_INTERVAL)
+    }
+
+    /// Loads the opt-out lists.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing either the addresses that are never polled or a description
+    /// of why a list couldn't be read.
+    pub fn blocklist(&self) -> Result<Blocklist, String> {
+        let mut blocklist = Blocklist::new();
+
+        for path in &self.blocklist {
+            blocklist
+                .load(path)
+                .map_err(|err| format!("{}: {err}", path.display()))?;
+        }
+
+        Ok(blocklist)
+    }
+
+    /// Returns the time limits applied to each poll.
+    pub fn timeouts(&self) -> TimeoutSettings {
+        TimeoutSettings::uniform(self.timeout.unwrap_or(DEFAULT_TIMEOUT))
+    }
+}
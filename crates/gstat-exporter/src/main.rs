@@ -0,0 +1,357 @@
+mod config;
+mod metrics;
+#[cfg(feature = "mqtt")]
+mod mqtt;
+
+use config::Config;
+use metrics::{Metrics, Sample};
+
+use gstat_core::{
+    blocklist::Blocklist,
+    cancel::CancellationToken,
+    fleet::{Fleet, FleetWatcher},
+    poller::Poller,
+    prelude::{Game, Response, ServerInfo, TimeoutSettings},
+    resolve::{HostPort, SystemResolver},
+    schedule::Schedule,
+    script::{ScriptQuery, ScriptRegistry},
+    subscribe::Feed,
+};
+use gstat_tcp::{
+    lookup::{self, QueryGame},
+    slp::{Minecraft, SlpQuery},
+};
+use gstat_udp::script::Scripted;
+
+use std::{
+    fmt::Display,
+    fs,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    process::ExitCode,
+    sync::Arc,
+    time::Duration,
+};
+
+use axum::{extract::State, http::header, response::IntoResponse, routing::get, Router};
+use clap::Parser;
+use tokio::net::TcpListener;
+
+/// Poll game servers and export what they report to Prometheus.
+#[derive(Debug, Parser)]
+#[command(name = "gstat-exporter", version)]
+struct Args {
+    /// The target list: the servers to poll and the scripts describing their games.
+    #[arg(
+        long,
+        env = "GSTAT_EXPORTER_CONFIG",
+        value_name = "PATH",
+        default_value = "gstat-exporter.toml"
+    )]
+    config: PathBuf,
+
+    /// A fleet file to poll instead of the target list, reloaded whenever it changes.
+    #[arg(
+        long,
+        env = "GSTAT_EXPORTER_FLEET",
+        value_name = "PATH",
+        conflicts_with = "config"
+    )]
+    fleet: Option<PathBuf>,
+
+    /// The address `/metrics` is served on.
+    #[arg(long, env = "GSTAT_EXPORTER_LISTEN", default_value = "0.0.0.0:9788")]
+    listen: SocketAddr,
+
+    /// A game script to load besides those of the target list. May be repeated.
+    #[arg(long = "script", value_name = "PATH")]
+    scripts: Vec<PathBuf>,
+}
+
+fn main() -> ExitCode {
+    match run(Args::parse()) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("gstat-exporter: {err}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(args: Args) -> Result<(), String> {
+    let source = args.fleet.as_ref().unwrap_or(&args.config);
+    let mut config = match &args.fleet {
+        Some(path) => Config::from_fleet(&Fleet::from_path(path).map_err(|err| err.to_string())?),
+        None => Config::load(&args.config)?,
+    };
+    check(&config, &args.scripts, source)?;
+
+    let metrics = Arc::new(Metrics::new(&config.targets));
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .map_err(|err| format!("failed to start the runtime: {err}"))?;
+
+    runtime.block_on(async {
+        let listener = TcpListener::bind(args.listen)
+            .await
+            .map_err(|err| format!("failed to listen on {}: {err}", args.listen))?;
+        let local = listener.local_addr().map_err(|err| err.to_string())?;
+        let router = Router::new()
+            .route("/metrics", get(render))
+            .with_state(metrics.clone());
+
+        tokio::spawn(async move {
+            if let Err(err) = axum::serve(listener, router).await {
+                eprintln!("gstat-exporter: {err}");
+            }
+        });
+
+        eprintln!(
+            "gstat-exporter: polling {} targets, serving metrics on http://{local}/metrics",
+            config.targets.len()
+        );
+
+        let mut watcher = args.fleet.as_ref().map(Fleet::watch);
+
+        loop {
+            let scripts = load_scripts(config.scripts.iter().chain(&args.scripts))?;
+            let targets = targets(&config, &scripts, source)?;
+            let blocklist = config.blocklist()?;
+            let generation = CancellationToken::new();
+            let feed = Feed::new();
+
+            metrics.reset(&config.targets);
+
+            #[cfg(feature = "mqtt")]
+            if let Some(mqtt) = &config.mqtt {
+                mqtt::Publisher::spawn(
+                    mqtt,
+                    &config.targets,
+                    feed.subscribe_all(),
+                    generation.clone(),
+                )?;
+            }
+
+            let poller = poller(&config, &targets, &blocklist, &metrics).with_feed(feed);
+
+            let reloaded = tokio::select! {
+                () = poller.run() => None,
+                config = reload(watcher.as_mut(), &args.scripts, source) => Some(config),
+            };
+
+            generation.cancel();
+
+            match reloaded {
+                Some(reloaded) => {
+                    eprintln!(
+                        "gstat-exporter: reloaded {}, polling {} targets",
+                        source.display(),
+                        reloaded.targets.len()
+                    );
+                    config = reloaded;
+                }
+                None => return Ok(()),
+            }
+        }
+    })
+}
+
+/// Waits for the fleet file to change into a valid target list.
+///
+/// Without a fleet file, or while it describes an invalid one, the previous target list is
+/// kept and polled.
+async fn reload(watcher: Option<&mut FleetWatcher>, scripts: &[PathBuf], source: &Path) -> Config {
+    let Some(watcher) = watcher else {
+        return std::future::pending().await;
+    };
+
+    loop {
+        let checked = watcher
+            .changed()
+            .await
+            .map_err(|err| err.to_string())
+            .map(|fleet| Config::from_fleet(&fleet))
+            .and_then(|config| check(&config, scripts, source).map(|()| config));
+
+        match checked {
+            Ok(config) => return config,
+            Err(err) => eprintln!("gstat-exporter: keeping the previous targets: {err}"),
+        }
+    }
+}
+
+/// Checks that the scripts and opt-out lists of a target list load and that the scripts
+/// describe the games of its targets.
+fn check(config: &Config, scripts: &[PathBuf], source: &Path) -> Result<(), String> {
+    let loaded = load_scripts(config.scripts.iter().chain(scripts))?;
+    config.blocklist()?;
+    targets(config, &loaded, source).map(|_| ())
+}
+
+/// Finds the game of every target and parses its address.
+fn targets<'s>(
+    config: &Config,
+    scripts: &'s ScriptRegistry,
+    source: &Path,
+) -> Result<Vec<(QueryGame<'s>, HostPort)>, String> {
+    let targets = config
+        .targets
+        .iter()
+        .map(|target| {
+            let game = find_game(scripts, &target.game)?;
+            let address: HostPort = target
+                .address
+                .parse()
+                .map_err(|err| format!("invalid target address: {err}"))?;
+
+            Ok((game, address))
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+
+    if targets.is_empty() {
+        return Err(format!("{} lists no targets", source.display()));
+    }
+
+    if cfg!(not(feature = "mqtt")) && config.mqtt.is_some() {
+        return Err(format!(
+            "{} configures MQTT, but gstat-exporter was built without the `mqtt` feature",
+            source.display()
+        ));
+    }
+
+    Ok(targets)
+}
+
+/// Serves `/metrics`.
+async fn render(State(metrics): State<Arc<Metrics>>) -> impl IntoResponse {
+    (
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+        metrics.render(),
+    )
+}
+
+/// Creates the poller of every target, recording each outcome in `metrics`.
+///
+/// Targets are polled by their index in the target list. Addresses `blocklist` refuses are
+/// never queried.
+fn poller<'a>(
+    config: &Config,
+    targets: &'a [(QueryGame<'a>, HostPort)],
+    blocklist: &'a Blocklist,
+    metrics: &Arc<Metrics>,
+) -> Poller<'a, usize, String> {
+    let schedule = Schedule::new(config.interval());
+    let timeouts = config.timeouts();
+    let own: Vec<(usize, Duration)> = config
+        .targets
+        .iter()
+        .enumerate()
+        .filter_map(|(index, target)| Some((index, target.interval?)))
+        .collect();
+    let shared = (0..targets.len()).filter(|index| own.iter().all(|(own, _)| own != index));
+    let metrics = metrics.clone();
+
+    let mut poller = Poller::new(shared, schedule, move |&index: &usize| {
+        let (game, address) = &targets[index];
+        let metrics = metrics.clone();
+
+        async move {
+            match fetch(*game, address, blocklist, timeouts).await {
+                Ok((info, latency)) => {
+                    metrics.record(
+                        index,
+                        Sample::Online {
+                            players: info.players,
+                            max_players: info.max_players,
+                            latency,
+                        },
+                    );
+
+                    Ok(info)
+                }
+                Err(err) => {
+                    metrics.record(index, Sample::Offline);
+                    Err(err)
+                }
+            }
+        }
+    });
+
+    for (index, interval) in own {
+        poller = poller.with_target(
+            index,
+            Schedule {
+                interval,
+                ..schedule
+            },
+        );
+    }
+
+    poller
+}
+
+/// Loads the game scripts of the target list and those given with `--script`.
+fn load_scripts<'p>(
+    paths: impl IntoIterator<Item = &'p PathBuf>,
+) -> Result<ScriptRegistry, String> {
+    let mut scripts = ScriptRegistry::new();
+
+    for path in paths {
+        let source = fs::read_to_string(path)
+            .map_err(|err| format!("failed to read {}: {err}", path.display()))?;
+
+        scripts
+            .load(&source)
+            .map_err(|err| format!("invalid script {}: {err}", path.display()))?;
+    }
+
+    Ok(scripts)
+}
+
+/// Looks up the game of a target, scripted or native.
+fn find_game<'s>(scripts: &'s ScriptRegistry, identifier: &str) -> Result<QueryGame<'s>, String> {
+    lookup::find_game(scripts, identifier).map_err(|err| err.to_string())
+}
+
+/// Queries a server at each address its host resolves to until one answers, skipping the
+/// addresses `blocklist` refuses.
+///
+/// # Returns
+///
+/// A `Result` containing either what the server reported and the round trip of the
+/// query, or a description of the error of the last address tried.
+async fn fetch(
+    game: QueryGame<'_>,
+    target: &HostPort,
+    blocklist: &Blocklist,
+    timeouts: TimeoutSettings,
+) -> Result<(ServerInfo, Duration), String> {
+    let target = target.to_string();
+    let failed = |err: &dyn Display| format!("{target}: {err}");
+
+    match game {
+        QueryGame::Scripted(game) => Scripted(game)
+            .fetch_host_checked(
+                ScriptQuery,
+                &target,
+                &SystemResolver,
+                Some(blocklist),
+                timeouts,
+            )
+            .await
+            .map(|fetched| (fetched.response.to_common(), fetched.meta.latency()))
+            .map_err(|err| failed(&err)),
+        QueryGame::Native(_) => Minecraft
+            .fetch_host_checked(
+                SlpQuery::default(),
+                &target,
+                &SystemResolver,
+                Some(blocklist),
+                timeouts,
+            )
+            .await
+            .map(|fetched| (fetched.response.to_common(), fetched.meta.latency()))
+            .map_err(|err| failed(&err)),
+    }
+}
@@ -0,0 +1,150 @@
+use crate::config::Target;
+
+use std::{fmt::Write, sync::Mutex, time::Duration};
+
+/// The outcome of the last poll of a server.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Sample {
+    /// The server answered.
+    Online {
+        /// The number of players online.
+        players: u32,
+        /// The number of player slots.
+        max_players: u32,
+        /// The round trip of the query.
+        latency: Duration,
+    },
+    /// The server could not be queried.
+    Offline,
+}
+
+/// A gauge and the value it takes from a sample, if any.
+struct Gauge {
+    name: &'static str,
+    help: &'static str,
+    value: fn(&Sample) -> Option<f64>,
+}
+
+/// The gauges exported for every server.
+///
+/// A server that didn't answer its last poll only reports `gstat_online`, so its player
+/// counts disappear instead of freezing at their last value.
+const GAUGES: [Gauge; 4] = [
+    Gauge {
+        name: "gstat_online",
+        help: "Whether the server answered its last query.",
+        value: |sample| Some(f64::from(u8::from(matches!(sample, Sample::Online { .. })))),
+    },
+    Gauge {
+        name: "gstat_players",
+        help: "The number of players online.",
+        value: |sample| match sample {
+            Sample::Online { players, .. } => Some(f64::from(*players)),
+            Sample::Offline => None,
+        },
+    },
+    Gauge {
+        name: "gstat_max_players",
+        help: "The number of player slots.",
+        value: |sample| match sample {
+            Sample::Online { max_players, .. } => Some(f64::from(*max_players)),
+            Sample::Offline => None,
+        },
+    },
+    Gauge {
+        name: "gstat_query_latency_seconds",
+        help: "The round trip of the last query.",
+        value: |sample| match sample {
+            Sample::Online { latency, .. } => Some(latency.as_secs_f64()),
+            Sample::Offline => None,
+        },
+    },
+];
+
+/// `Metrics` holds the last sample of every target and renders them for Prometheus.
+pub struct Metrics {
+    targets: Mutex<Targets>,
+}
+
+/// The labels and last samples of the targets, by index in the target list.
+struct Targets {
+    labels: Vec<String>,
+    samples: Vec<Option<Sample>>,
+}
+
+impl Metrics {
+    /// Creates the metrics of `targets`, none of which has been polled yet.
+    pub fn new(targets: &[Target]) -> Self {
+        Metrics {
+            targets: Mutex::new(Targets::new(targets)),
+        }
+    }
+
+    /// Replaces the targets after the target list was reloaded, forgetting every sample.
+    pub fn reset(&self, targets: &[Target]) {
+        *self.targets.lock().expect("metrics lock poisoned") = Targets::new(targets);
+    }
+
+    /// Records the outcome of a poll.
+    ///
+    /// # Parameters
+    ///
+    /// * `target`: The index of the target in the target list.
+    /// * `sample`: The outcome of the poll.
+    pub fn record(&self, target: usize, sample: Sample) {
+        let mut targets = self.targets.lock().expect("metrics lock poisoned");
+
+        if let Some(slot) = targets.samples.get_mut(target) {
+            *slot = Some(sample);
+        }
+    }
+
+    /// Renders the metrics in the Prometheus text exposition format.
+    ///
+    /// Targets that haven't been polled yet are left out.
+    pub fn render(&self) -> String {
+        let targets = self.targets.lock().expect("metrics lock poisoned");
+        let mut output = String::new();
+
+        for gauge in &GAUGES {
+            let _ = writeln!(output, "# HELP {} {}", gauge.name, gauge.help);
+            let _ = writeln!(output, "# TYPE {} gauge", gauge.name);
+
+            for (labels, sample) in targets.labels.iter().zip(&targets.samples) {
+                if let Some(value) = sample.as_ref().and_then(gauge.value) {
+                    let _ = writeln!(output, "{}{{{labels}}} {value}", gauge.name);
+                }
+            }
+        }
+
+        output
+    }
+}
+
+impl Targets {
+    fn new(targets: &[Target]) -> Self {
+        let labels = targets
+            .iter()
+            .map(|target| {
+                format!(
+                    "game=\"{}\",address=\"{}\"",
+                    escape(&target.game),
+                    escape(&target.address)
+                )
+            })
+            .collect();
+
+        Targets {
+            labels,
+            samples: vec![None; targets.len()],
+        }
+    }
+}
+
+/// Escapes a label value: backslashes, double quotes and line feeds.
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
@@ -0,0 +1,163 @@
+use crate::config::{Mqtt, Target};
+
+use gstat_core::{
+    cancel::CancellationToken,
+    resolve::HostPort,
+    subscribe::{Snapshot, Subscription},
+};
+
+use std::time::Duration;
+
+use rumqttc::{AsyncClient, Event, EventLoop, LastWill, MqttOptions, Outgoing, Packet, QoS};
+
+/// The port brokers listen on when the configuration gives none.
+const DEFAULT_PORT: u16 = 1883;
+
+/// How long to wait before reconnecting to a broker that dropped the connection.
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+/// `Publisher` publishes the snapshots of every target as retained messages.
+///
+/// Each target publishes under `<prefix>/<game>/<address>`: `online` as `true` or `false`,
+/// and while it answers, `players`, `max_players`, `map` and `name`. The exporter itself
+/// publishes `online` or `offline` to `<prefix>/exporter/status`, with `offline` as its last
+/// will, so dashboards notice when it stops.
+pub struct Publisher {
+    client: AsyncClient,
+    topics: Vec<String>,
+}
+
+impl Publisher {
+    /// Connects to the broker and starts publishing the snapshots of `subscription`.
+    ///
+    /// Must be called on the runtime; the connection and the subscription are driven by
+    /// tasks spawned on it.
+    ///
+    /// # Parameters
+    ///
+    /// * `config`: The `[mqtt]` table of the target list.
+    /// * `targets`: The targets, whose indices name the targets of the snapshots.
+    /// * `subscription`: A subscription to the feed of the poller.
+    /// * `token`: The token that disconnects from the broker, e.g. when the target list is
+    ///   reloaded.
+    pub fn spawn(
+        config: &Mqtt,
+        targets: &[Target],
+        mut subscription: Subscription<usize>,
+        token: CancellationToken,
+    ) -> Result<(), String> {
+        let broker: HostPort = config
+            .broker
+            .parse()
+            .map_err(|err| format!("invalid MQTT broker: {err}"))?;
+        let prefix = config.prefix.as_deref().unwrap_or("gstat");
+        let status = format!("{prefix}/exporter/status");
+
+        let mut options = MqttOptions::new(
+            config.client_id.as_deref().unwrap_or("gstat-exporter"),
+            broker.host,
+            broker.port.unwrap_or(DEFAULT_PORT),
+        );
+        options
+            .set_keep_alive(Duration::from_secs(30))
+            .set_last_will(LastWill::new(&status, "offline", QoS::AtLeastOnce, true));
+
+        if let Some(username) = &config.username {
+            options.set_credentials(username, config.password.as_deref().unwrap_or(""));
+        }
+
+        let (client, events) = AsyncClient::new(options, 64);
+        let publisher = Publisher {
+            client: client.clone(),
+            topics: targets
+                .iter()
+                .map(|target| {
+                    format!(
+                        "{prefix}/{}/{}",
+                        level(&target.game),
+                        level(&target.address)
+                    )
+                })
+                .collect(),
+        };
+
+        tokio::spawn(drive(events, client, status, token));
+        tokio::spawn(async move {
+            while let Some(snapshot) = subscription.next().await {
+                publisher.publish(&snapshot).await;
+            }
+        });
+
+        Ok(())
+    }
+
+    /// Publishes the state of a target after a poll.
+    async fn publish(&self, snapshot: &Snapshot<usize>) {
+        let topic = &self.topics[snapshot.target];
+        let mut messages = vec![("online", snapshot.info.is_some().to_string())];
+
+        if let Some(info) = &snapshot.info {
+            messages.extend([
+                ("players", info.players.to_string()),
+                ("max_players", info.max_players.to_string()),
+                ("map", info.map.clone()),
+                ("name", info.name.clone()),
+            ]);
+        }
+
+        for (name, payload) in messages {
+            let published = self
+                .client
+                .publish(format!("{topic}/{name}"), QoS::AtLeastOnce, true, payload)
+                .await;
+
+            if let Err(err) = published {
+                eprintln!("gstat-exporter: failed to publish {topic}/{name}: {err}");
+            }
+        }
+    }
+}
+
+/// Runs the connection to the broker, reconnecting when it drops and announcing the
+/// exporter as online on every connection, until `token` is cancelled.
+async fn drive(
+    mut events: EventLoop,
+    client: AsyncClient,
+    status: String,
+    token: CancellationToken,
+) {
+    loop {
+        let Some(event) = token.run_until_cancelled(events.poll()).await else {
+            // Disconnecting cleanly keeps the broker from publishing the last will.
+            if client.try_disconnect().is_ok() {
+                while let Ok(event) = events.poll().await {
+                    if let Event::Outgoing(Outgoing::Disconnect) = event {
+                        break;
+                    }
+                }
+            }
+
+            return;
+        };
+
+        match event {
+            Ok(Event::Incoming(Packet::ConnAck(_))) => {
+                // The event loop is the one sending queued messages, so it must not wait
+                // for room in the queue itself.
+                if let Err(err) = client.try_publish(&status, QoS::AtLeastOnce, true, "online") {
+                    eprintln!("gstat-exporter: failed to publish {status}: {err}");
+                }
+            }
+            Ok(_) => {}
+            Err(err) => {
+                eprintln!("gstat-exporter: MQTT connection failed: {err}");
+                tokio::time::sleep(RECONNECT_DELAY).await;
+            }
+        }
+    }
+}
+
+/// Turns a name into a topic level, replacing the separator and wildcards of MQTT.
+fn level(name: &str) -> String {
+    name.replace(['/', '+', '#'], "_")
+}
@@ -0,0 +1,209 @@
+//! Serving `/metrics` from `gstat-exporter`.
+
+use gstat_test::{loopback_server, SOURCE_INFO_PACKET, SOURCE_INFO_SCRIPT_PATH};
+
+use std::{
+    fs,
+    io::{self, BufRead, BufReader, Read, Write},
+    net::{Ipv4Addr, SocketAddr, TcpStream, UdpSocket},
+    path::PathBuf,
+    process::{Child, Command, Stdio},
+    thread,
+    time::{Duration, Instant},
+};
+
+/// Writes a target list to a temporary file.
+fn target_list(name: &str, contents: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("gstat-exporter-{name}.toml"));
+    fs::write(&path, contents).unwrap();
+    path
+}
+
+/// Kills the exporter when the test ends.
+struct Exporter(Child);
+
+impl Drop for Exporter {
+    fn drop(&mut self) {
+        let _ = self.0.kill();
+        let _ = self.0.wait();
+    }
+}
+
+/// Starts the exporter on an ephemeral port and returns the address it serves on.
+///
+/// # Parameters
+///
+/// * `flag`: How the file is passed, `--config` or `--fleet`.
+/// * `config`: The target list or fleet file.
+fn start(flag: &str, config: &PathBuf) -> (Exporter, SocketAddr) {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_gstat-exporter"))
+        .arg(flag)
+        .arg(config)
+        .args(["--listen", "127.0.0.1:0"])
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    let mut line = String::new();
+    let mut stderr = BufReader::new(child.stderr.take().unwrap());
+    stderr.read_line(&mut line).unwrap();
+
+    // Keep reading, so later messages don't fail on a closed pipe.
+    thread::spawn(move || io::copy(&mut stderr, &mut io::sink()));
+
+    let address = line
+        .split("http://")
+        .nth(1)
+        .and_then(|rest| rest.strip_suffix("/metrics\n"))
+        .unwrap_or_else(|| panic!("unexpected output: {line}"))
+        .parse()
+        .unwrap();
+
+    (Exporter(child), address)
+}
+
+/// Fetches `/metrics` until `expected` appears in it.
+fn scrape_until(address: SocketAddr, expected: &str) -> String {
+    let deadline = Instant::now() + Duration::from_secs(10);
+
+    loop {
+        let mut stream = TcpStream::connect(address).unwrap();
+        write!(
+            stream,
+            "GET /metrics HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n"
+        )
+        .unwrap();
+
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+
+        if response.contains(expected) || Instant::now() > deadline {
+            return response;
+        }
+
+        thread::sleep(Duration::from_millis(50));
+    }
+}
+
+#[test]
+fn polled_servers_are_exported() {
+    let online = loopback_server(SOURCE_INFO_PACKET).address();
+    let silent = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+    let config = target_list(
+        "polled",
+        &format!(
+            "interval = \"200ms\"\n\
+             timeout = \"200ms\"\n\
+             scripts = [{SOURCE_INFO_SCRIPT_PATH:?}]\n\n\
+             [[target]]\n\
+             game = \"selftest-source-info\"\n\
+             address = \"{online}\"\n\n\
+             [[target]]\n\
+             game = \"selftest-source-info\"\n\
+             address = \"{}\"\n",
+            silent.local_addr().unwrap()
+        ),
+    );
+
+    let (_exporter, address) = start("--config", &config);
+    let labels = format!("game=\"selftest-source-info\",address=\"{online}\"");
+    let response = scrape_until(address, &format!("gstat_players{{{labels}}} 12"));
+
+    assert!(response.starts_with("HTTP/1.1 200"), "{response}");
+    assert!(response.contains("# TYPE gstat_online gauge"));
+    assert!(response.contains(&format!("gstat_online{{{labels}}} 1")));
+    assert!(response.contains(&format!("gstat_players{{{labels}}} 12")));
+    assert!(response.contains(&format!("gstat_max_players{{{labels}}}")));
+    assert!(response.contains(&format!("gstat_query_latency_seconds{{{labels}}}")));
+
+    let silent = format!(
+        "game=\"selftest-source-info\",address=\"{}\"",
+        silent.local_addr().unwrap()
+    );
+    let response = scrape_until(address, &format!("gstat_online{{{silent}}} 0"));
+
+    assert!(response.contains(&format!("gstat_online{{{silent}}} 0")));
+    assert!(!response.contains(&format!("gstat_players{{{silent}}}")));
+}
+
+#[test]
+fn unknown_games_are_rejected() {
+    let config = target_list(
+        "unknown",
+        "[[target]]\ngame = \"no-such-game\"\naddress = \"192.0.2.1\"\n",
+    );
+
+    let output = Command::new(env!("CARGO_BIN_EXE_gstat-exporter"))
+        .arg("--config")
+        .arg(&config)
+        .output()
+        .unwrap();
+
+    assert!(!output.status.success());
+    assert!(String::from_utf8(output.stderr)
+        .unwrap()
+        .contains("unknown game `no-such-game`"));
+}
+
+#[test]
+fn fleets_are_reloaded_when_they_change() {
+    let first = loopback_server(SOURCE_INFO_PACKET).address();
+    let second = loopback_server(SOURCE_INFO_PACKET).address();
+    let fleet = |addresses: &[SocketAddr]| {
+        let mut fleet = format!(
+            "interval = \"200ms\"\n\
+             timeout = \"200ms\"\n\
+             scripts = [{SOURCE_INFO_SCRIPT_PATH:?}]\n"
+        );
+
+        for address in addresses {
+            fleet += &format!(
+                "\n[[target]]\n\
+                 game = \"selftest-source-info\"\n\
+                 address = \"{address}\"\n"
+            );
+        }
+
+        fleet
+    };
+    let path = target_list("fleet", &fleet(&[first]));
+
+    let (_exporter, address) = start("--fleet", &path);
+    let labels = |server| format!("game=\"selftest-source-info\",address=\"{server}\"");
+
+    let response = scrape_until(address, &format!("gstat_online{{{}}} 1", labels(first)));
+    assert!(response.contains(&format!("gstat_online{{{}}} 1", labels(first))));
+
+    fs::write(&path, fleet(&[second])).unwrap();
+
+    let response = scrape_until(address, &format!("gstat_online{{{}}} 1", labels(second)));
+    assert!(response.contains(&format!("gstat_online{{{}}} 1", labels(second))));
+    assert!(!response.contains(&labels(first)));
+}
+
+#[test]
+fn blocked_targets_are_never_polled() {
+    let blocked = loopback_server(SOURCE_INFO_PACKET).address();
+    let optout = std::env::temp_dir().join("gstat-exporter-optout.txt");
+    fs::write(&optout, format!("# opt-out requests\n{}\n", blocked.ip())).unwrap();
+
+    let config = target_list(
+        "blocked",
+        &format!(
+            "interval = \"200ms\"\n\
+             timeout = \"200ms\"\n\
+             scripts = [{SOURCE_INFO_SCRIPT_PATH:?}]\n\
+             blocklist = [{optout:?}]\n\n\
+             [[target]]\n\
+             game = \"selftest-source-info\"\n\
+             address = \"{blocked}\"\n"
+        ),
+    );
+
+    let (_exporter, address) = start("--config", &config);
+    let labels = format!("game=\"selftest-source-info\",address=\"{blocked}\"");
+    let response = scrape_until(address, &format!("gstat_online{{{labels}}} 0"));
+
+    assert!(response.contains(&format!("gstat_online{{{labels}}} 0")));
+    assert!(!response.contains(&format!("gstat_players{{{labels}}}")));
+}
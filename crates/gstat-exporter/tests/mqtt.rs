@@ -0,0 +1,142 @@
+//! Publishing poll snapshots from `gstat-exporter` to an MQTT broker.
+
+use gstat_test::{loopback_server, SOURCE_INFO_PACKET, SOURCE_INFO_SCRIPT_PATH};
+
+use std::{
+    fs,
+    io::{Read, Write},
+    net::{Ipv4Addr, TcpListener, TcpStream},
+    process::{Child, Command, Stdio},
+    time::Duration,
+};
+
+/// Kills the exporter when the test ends.
+struct Exporter(Child);
+
+impl Drop for Exporter {
+    fn drop(&mut self) {
+        let _ = self.0.kill();
+        let _ = self.0.wait();
+    }
+}
+
+/// Reads an MQTT control packet, returning its first byte and the rest of the packet.
+fn packet(stream: &mut TcpStream) -> (u8, Vec<u8>) {
+    let mut byte = [0; 1];
+    stream.read_exact(&mut byte).unwrap();
+    let kind = byte[0];
+
+    let (mut length, mut shift) = (0, 0);
+    loop {
+        stream.read_exact(&mut byte).unwrap();
+        length |= usize::from(byte[0] & 0x7F) << shift;
+        shift += 7;
+
+        if byte[0] & 0x80 == 0 {
+            break;
+        }
+    }
+
+    let mut body = vec![0; length];
+    stream.read_exact(&mut body).unwrap();
+    (kind, body)
+}
+
+/// A retained message published by the exporter.
+struct Message {
+    topic: String,
+    payload: String,
+    retain: bool,
+}
+
+/// Acknowledges the connection of the exporter and collects what it publishes until
+/// `until` is published.
+fn broker(listener: TcpListener, until: &str) -> (Vec<u8>, Vec<Message>) {
+    let (mut stream, _) = listener.accept().unwrap();
+    stream
+        .set_read_timeout(Some(Duration::from_secs(10)))
+        .unwrap();
+
+    let (kind, connect) = packet(&mut stream);
+    assert_eq!(kind, 0x10);
+    stream.write_all(&[0x20, 0x02, 0x00, 0x00]).unwrap();
+
+    let mut messages = Vec::new();
+
+    loop {
+        let (kind, body) = packet(&mut stream);
+
+        match kind >> 4 {
+            3 => {
+                let length = usize::from(u16::from_be_bytes([body[0], body[1]]));
+                let topic = String::from_utf8(body[2..2 + length].to_vec()).unwrap();
+                let id = &body[2 + length..4 + length];
+                stream.write_all(&[0x40, 0x02, id[0], id[1]]).unwrap();
+
+                let done = topic == until;
+                messages.push(Message {
+                    topic,
+                    payload: String::from_utf8(body[4 + length..].to_vec()).unwrap(),
+                    retain: kind & 1 == 1,
+                });
+
+                if done {
+                    return (connect, messages);
+                }
+            }
+            12 => stream.write_all(&[0xD0, 0x00]).unwrap(),
+            _ => {}
+        }
+    }
+}
+
+#[test]
+fn snapshots_are_published_as_retained_messages() {
+    let online = loopback_server(SOURCE_INFO_PACKET).address();
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+    let config = std::env::temp_dir().join("gstat-exporter-mqtt.toml");
+    fs::write(
+        &config,
+        format!(
+            "interval = \"200ms\"\n\
+             timeout = \"200ms\"\n\
+             scripts = [{SOURCE_INFO_SCRIPT_PATH:?}]\n\n\
+             [[target]]\n\
+             game = \"selftest-source-info\"\n\
+             address = \"{online}\"\n\n\
+             [mqtt]\n\
+             broker = \"{}\"\n\
+             prefix = \"lab\"\n",
+            listener.local_addr().unwrap()
+        ),
+    )
+    .unwrap();
+
+    let _exporter = Exporter(
+        Command::new(env!("CARGO_BIN_EXE_gstat-exporter"))
+            .arg("--config")
+            .arg(&config)
+            .args(["--listen", "127.0.0.1:0"])
+            .stderr(Stdio::null())
+            .spawn()
+            .unwrap(),
+    );
+
+    let base = format!("lab/selftest-source-info/{online}");
+    let (connect, messages) = broker(listener, &format!("{base}/players"));
+
+    // The last will announces the exporter as offline, retained.
+    let will = b"lab/exporter/status";
+    assert!(connect.windows(will.len()).any(|window| window == will));
+    assert!(connect.ends_with(b"\x00\x07offline"));
+    assert_eq!(connect[7] & 0b0010_0100, 0b0010_0100);
+
+    assert!(messages.iter().all(|message| message.retain));
+    assert!(messages
+        .iter()
+        .any(|message| message.topic == "lab/exporter/status" && message.payload == "online"));
+    assert!(messages
+        .iter()
+        .any(|message| message.topic == format!("{base}/online") && message.payload == "true"));
+    assert_eq!(messages.last().unwrap().payload, "12");
+}
@@ -0,0 +1,140 @@
+#ifndef GSTAT_H
+#define GSTAT_H
+
+/* Generated by cbindgen from crates/gstat-ffi. Do not edit by hand. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+// The version of the JSON documents produced, matching `gstat query --format json`.
+#define GSTAT_JSON_VERSION 1
+
+// The outcome of a call.
+typedef enum GstatStatus {
+  // The call succeeded.
+  GSTAT_STATUS_OK = 0,
+  // A pointer was null or a string was not valid UTF-8.
+  GSTAT_STATUS_INVALID_ARGUMENT = 1,
+  // No loaded or built-in game has the identifier.
+  GSTAT_STATUS_UNKNOWN_GAME = 2,
+  // The game can't be queried by the library, e.g. because it uses TCP.
+  GSTAT_STATUS_UNSUPPORTED = 3,
+  // The host did not resolve to any address.
+  GSTAT_STATUS_RESOLVE_FAILED = 4,
+  // The server did not answer in time.
+  GSTAT_STATUS_TIMEOUT = 5,
+  // The server could not be queried or its answer could not be parsed.
+  GSTAT_STATUS_QUERY_FAILED = 6,
+  // A game script could not be loaded.
+  GSTAT_STATUS_SCRIPT_ERROR = 7,
+  // The library panicked; the call had no effect.
+  GSTAT_STATUS_PANIC = 8,
+  // Every address of the host is blocked, see `gstat_set_blocklist`.
+  GSTAT_STATUS_BLOCKED = 9,
+} GstatStatus;
+
+// Receives the outcome of `gstat_query_async`.
+//
+// `payload` is the JSON document of the response if `status` is `GSTAT_STATUS_OK`, or a
+// description of the failure otherwise. It is only valid until the callback returns.
+typedef void (*GstatCallback)(GstatStatus status, const char *payload, void *user_data);
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+// Compiles a game script and makes its game available to queries.
+//
+// # Safety
+//
+// `source` must be null or point to a nul-terminated string.
+//
+// # Returns
+//
+// `GSTAT_STATUS_SCRIPT_ERROR` if the script is invalid or its game is already loaded.
+GstatStatus gstat_load_script(const char *source);
+
+// Sets the limit for every step of later queries, in milliseconds. Defaults to 5000.
+void gstat_set_timeout_ms(uint64_t timeout_ms);
+
+// Replaces the addresses that are never queried.
+//
+// # Parameters
+//
+// * `list`: An opt-out list, with one address or CIDR range per line and `#` starting a
+//   comment, or null for none.
+// * `reserved`: Whether private, loopback and otherwise reserved ranges are blocked too,
+//   as they are until this is called.
+//
+// # Safety
+//
+// `list` must be null or point to a nul-terminated string.
+//
+// # Returns
+//
+// `GSTAT_STATUS_INVALID_ARGUMENT` if an entry of `list` is invalid, in which case the
+// blocklist is left unchanged.
+GstatStatus gstat_set_blocklist(const char *list, bool reserved);
+
+// Queries a server, blocking until it answers or the query fails.
+//
+// Must not be called from a `GstatCallback`, which runs on the library's own threads.
+//
+// # Parameters
+//
+// * `game_id`: The identifier or an alias of a loaded game.
+// * `host`: The host name or IP address of the server.
+// * `port`: The query port, or `0` for the game's default.
+// * `out_json`: Receives the JSON document of the response, to be released with
+//   `gstat_string_free`, or null if the query fails.
+//
+// # Safety
+//
+// `game_id` and `host` must be null or point to nul-terminated strings, and `out_json`
+// must be null or point to writable memory.
+GstatStatus gstat_query(const char *game_id, const char *host, uint16_t port, char **out_json);
+
+// Starts a query in the background, reporting its outcome to `callback` on one of the
+// library's threads.
+//
+// The arguments are checked before the query starts: if this returns anything but
+// `GSTAT_STATUS_OK`, `callback` is never called. Otherwise it is called exactly once,
+// with `GSTAT_STATUS_PANIC` if the query panicked.
+//
+// # Parameters
+//
+// * `game_id`: The identifier or an alias of a loaded game.
+// * `host`: The host name or IP address of the server.
+// * `port`: The query port, or `0` for the game's default.
+// * `callback`: Receives the outcome.
+// * `user_data`: Passed to `callback` as is.
+//
+// # Safety
+//
+// `game_id` and `host` must be null or point to nul-terminated strings, and `user_data`
+// must stay valid, and be usable from another thread, until `callback` is called.
+GstatStatus gstat_query_async(const char *game_id,
+                              const char *host,
+                              uint16_t port,
+                              GstatCallback callback,
+                              void *user_data);
+
+// Returns the description of the last failure on the calling thread, or null if no call
+// failed yet. The string stays valid until the next failing call on the thread.
+const char *gstat_last_error(void);
+
+// Releases a string returned by the library.
+//
+// # Safety
+//
+// `string` must be null or a string returned by the library that wasn't released yet.
+void gstat_string_free(char *string);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* GSTAT_H */
@@ -0,0 +1,452 @@
+//! C bindings for gstat, so game panels written in C, C++ or C# can query servers by
+//! linking against the library rather than running the CLI.
+//!
+//! Games are loaded from scripts with `gstat_load_script`, or built in like Minecraft: Java
+//! Edition, and queried by identifier with `gstat_query`, which blocks, or
+//! `gstat_query_async`, which reports to a callback. Both produce the JSON document of
+//! `gstat query --format json`. Functions return a `GstatStatus`; when it isn't
+//! `GSTAT_STATUS_OK`, `gstat_last_error` describes the failure.
+//!
+//! Private, loopback and otherwise reserved ranges are never queried unless
+//! `gstat_set_blocklist` unblocks them, so panels passing on addresses from their users
+//! can't be made to probe the networks they run in.
+//!
+//! The declarations are in `include/gstat.h`, generated with cbindgen from this file.
+
+use gstat_core::{
+    blocklist::Blocklist,
+    document,
+    encode::{Encode, Json},
+    prelude::{ErrorKind, Game, Response, TimeoutSettings},
+    registry::GameEntry,
+    resolve::{HostPort, SystemResolver},
+    script::{ScriptGame, ScriptQuery, ScriptRegistry},
+    standards::game::FetchResult,
+};
+use gstat_tcp::{
+    lookup::{self, LookupError, QueryGame},
+    slp::{Minecraft, SlpQuery},
+};
+use gstat_udp::script::Scripted;
+
+use std::{
+    cell::RefCell,
+    error::Error as StdError,
+    ffi::{c_char, c_void, CStr, CString},
+    panic::{catch_unwind, AssertUnwindSafe},
+    ptr,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        LazyLock, OnceLock, RwLock,
+    },
+    time::Duration,
+};
+
+use tokio::runtime::{Builder, Runtime};
+
+/// The version of the JSON documents produced, matching `gstat query --format json`.
+///
+/// The C header can't refer to `gstat_core::document::VERSION`, so it is repeated here.
+pub const GSTAT_JSON_VERSION: u32 = 1;
+
+const _: () = assert!(GSTAT_JSON_VERSION == document::VERSION);
+
+/// The games loaded with `gstat_load_script`.
+static SCRIPTS: LazyLock<RwLock<ScriptRegistry>> = LazyLock::new(Default::default);
+
+/// The addresses that are never queried, set with `gstat_set_blocklist`.
+static BLOCKLIST: LazyLock<RwLock<Blocklist>> =
+    LazyLock::new(|| RwLock::new(Blocklist::reserved()));
+
+/// The limit for every step of a query, in milliseconds.
+static TIMEOUT_MS: AtomicU64 = AtomicU64::new(5000);
+
+/// The runtime queries run on, started by the first query.
+static RUNTIME: OnceLock<Runtime> = OnceLock::new();
+
+thread_local! {
+    /// The description of the last failure on this thread.
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+/// The outcome of a call.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GstatStatus {
+    /// The call succeeded.
+    Ok = 0,
+    /// A pointer was null or a string was not valid UTF-8.
+    InvalidArgument = 1,
+    /// No loaded or built-in game has the identifier.
+    UnknownGame = 2,
+    /// The game can't be queried by the library, e.g. because it uses TCP.
+    Unsupported = 3,
+    /// The host did not resolve to any address.
+    ResolveFailed = 4,
+    /// The server did not answer in time.
+    Timeout = 5,
+    /// The server could not be queried or its answer could not be parsed.
+    QueryFailed = 6,
+    /// A game script could not be loaded.
+    ScriptError = 7,
+    /// The library panicked; the call had no effect.
+    Panic = 8,
+    /// Every address of the host is blocked, see `gstat_set_blocklist`.
+    Blocked = 9,
+}
+
+/// Receives the outcome of `gstat_query_async`.
+///
+/// `payload` is the JSON document of the response if `status` is `GSTAT_STATUS_OK`, or a
+/// description of the failure otherwise. It is only valid until the callback returns.
+pub type GstatCallback =
+    extern "C" fn(status: GstatStatus, payload: *const c_char, user_data: *mut c_void);
+
+/// Why a call failed.
+struct Failure(GstatStatus, String);
+
+impl Failure {
+    fn invalid(message: impl Into<String>) -> Self {
+        Failure(GstatStatus::InvalidArgument, message.into())
+    }
+}
+
+/// Runs the body of an exported function, recording its failure for `gstat_last_error`.
+fn guard(body: impl FnOnce() -> Result<(), Failure>) -> GstatStatus {
+    let failure = match catch_unwind(AssertUnwindSafe(body)) {
+        Ok(Ok(())) => return GstatStatus::Ok,
+        Ok(Err(failure)) => failure,
+        Err(_) => Failure(GstatStatus::Panic, "gstat panicked".into()),
+    };
+
+    set_last_error(&failure.1);
+    failure.0
+}
+
+fn set_last_error(message: &str) {
+    let message = CString::new(message.replace('\0', " ")).unwrap_or_default();
+
+    LAST_ERROR.with(|last| *last.borrow_mut() = Some(message));
+}
+
+/// Reads a C string argument.
+///
+/// # Safety
+///
+/// `string` must be null or point to a nul-terminated string.
+unsafe fn argument<'s>(string: *const c_char, name: &str) -> Result<&'s str, Failure> {
+    if string.is_null() {
+        return Err(Failure::invalid(format!("`{name}` is null")));
+    }
+
+    CStr::from_ptr(string)
+        .to_str()
+        .map_err(|_| Failure::invalid(format!("`{name}` is not valid UTF-8")))
+}
+
+/// A game to query, with the scripted ones copied out of the registry so it isn't held
+/// during the query.
+enum QueriedGame {
+    /// A game loaded with `gstat_load_script`.
+    Scripted(ScriptGame),
+    /// A game of the built-in registry queried natively.
+    Native(&'static GameEntry),
+}
+
+/// Looks up a loaded or native game, as every frontend does with `lookup::find_game`.
+fn find_game(identifier: &str) -> Result<QueriedGame, Failure> {
+    let scripts = SCRIPTS.read().unwrap_or_else(|err| err.into_inner());
+
+    match lookup::find_game(&scripts, identifier) {
+        Ok(QueryGame::Scripted(game)) => Ok(QueriedGame::Scripted(game.clone())),
+        Ok(QueryGame::Native(entry)) => Ok(QueriedGame::Native(entry)),
+        Err(err) => {
+            let status = match err {
+                LookupError::UnknownGame(_) => GstatStatus::UnknownGame,
+                _ => GstatStatus::Unsupported,
+            };
+
+            Err(Failure(status, err.to_string()))
+        }
+    }
+}
+
+fn runtime() -> Result<&'static Runtime, Failure> {
+    if let Some(runtime) = RUNTIME.get() {
+        return Ok(runtime);
+    }
+
+    let runtime = Builder::new_multi_thread()
+        .enable_all()
+        .build()
+        .map_err(|err| {
+            Failure(
+                GstatStatus::QueryFailed,
+                format!("failed to start the runtime: {err}"),
+            )
+        })?;
+
+    Ok(RUNTIME.get_or_init(|| runtime))
+}
+
+/// Queries a server at each address its host resolves to until one answers, returning the
+/// response as JSON.
+async fn query(game: QueriedGame, host: String, port: u16) -> Result<String, Failure> {
+    let game = match &game {
+        QueriedGame::Scripted(game) => QueryGame::Scripted(game),
+        QueriedGame::Native(entry) => QueryGame::Native(entry),
+    };
+    let port = if port == 0 { game.port() } else { port };
+    let target = HostPort::new(host, Some(port)).to_string();
+    let timeouts =
+        TimeoutSettings::uniform(Duration::from_millis(TIMEOUT_MS.load(Ordering::Relaxed)));
+    let blocklist = BLOCKLIST
+        .read()
+        .unwrap_or_else(|err| err.into_inner())
+        .clone();
+
+    match game {
+        QueryGame::Scripted(scripted) => answer(
+            game,
+            &target,
+            Scripted(scripted)
+                .fetch_host_checked(
+                    ScriptQuery,
+                    &target,
+                    &SystemResolver,
+                    Some(&blocklist),
+                    timeouts,
+                )
+                .await,
+        ),
+        QueryGame::Native(_) => answer(
+            game,
+            &target,
+            Minecraft
+                .fetch_host_checked(
+                    SlpQuery::default(),
+                    &target,
+                    &SystemResolver,
+                    Some(&blocklist),
+                    timeouts,
+                )
+                .await,
+        ),
+    }
+}
+
+/// Converts the outcome of a query to `target` into the JSON document or the failure.
+fn answer<R: Response, E: StdError>(
+    game: QueryGame<'_>,
+    target: &str,
+    result: FetchResult<R, E>,
+) -> Result<String, Failure> {
+    match result {
+        Ok(fetched) => {
+            let document = document::document(game.id(), game.name(), &fetched);
+
+            Ok(String::from_utf8_lossy(&Json.encode(&document)).into_owned())
+        }
+        Err(err) => {
+            let status = match err.kind() {
+                ErrorKind::Dns => GstatStatus::ResolveFailed,
+                ErrorKind::Timeout => GstatStatus::Timeout,
+                ErrorKind::Blocked => GstatStatus::Blocked,
+                _ => GstatStatus::QueryFailed,
+            };
+
+            Err(Failure(status, format!("{target}: {err}")))
+        }
+    }
+}
+
+/// Compiles a game script and makes its game available to queries.
+///
+/// # Safety
+///
+/// `source` must be null or point to a nul-terminated string.
+///
+/// # Returns
+///
+/// `GSTAT_STATUS_SCRIPT_ERROR` if the script is invalid or its game is already loaded.
+#[no_mangle]
+pub unsafe extern "C" fn gstat_load_script(source: *const c_char) -> GstatStatus {
+    guard(|| {
+        let source = argument(source, "source")?;
+        let mut scripts = SCRIPTS.write().unwrap_or_else(|err| err.into_inner());
+
+        scripts.load(source).map(|_| ()).map_err(|err| {
+            let message = err
+                .detail()
+                .inner()
+                .map_or_else(|| err.to_string(), ToString::to_string);
+
+            Failure(GstatStatus::ScriptError, message)
+        })
+    })
+}
+
+/// Sets the limit for every step of later queries, in milliseconds. Defaults to 5000.
+#[no_mangle]
+pub extern "C" fn gstat_set_timeout_ms(timeout_ms: u64) {
+    TIMEOUT_MS.store(timeout_ms, Ordering::Relaxed);
+}
+
+/// Replaces the addresses that are never queried.
+///
+/// # Parameters
+///
+/// * `list`: An opt-out list, with one address or CIDR range per line and `#` starting a
+///   comment, or null for none.
+/// * `reserved`: Whether private, loopback and otherwise reserved ranges are blocked too,
+///   as they are until this is called.
+///
+/// # Safety
+///
+/// `list` must be null or point to a nul-terminated string.
+///
+/// # Returns
+///
+/// `GSTAT_STATUS_INVALID_ARGUMENT` if an entry of `list` is invalid, in which case the
+/// blocklist is left unchanged.
+#[no_mangle]
+pub unsafe extern "C" fn gstat_set_blocklist(list: *const c_char, reserved: bool) -> GstatStatus {
+    guard(|| {
+        let mut blocklist = if reserved {
+            Blocklist::reserved()
+        } else {
+            Blocklist::new()
+        };
+
+        if !list.is_null() {
+            blocklist
+                .extend_from_list(argument(list, "list")?, Some("gstat_set_blocklist"))
+                .map_err(|err| Failure::invalid(err.to_string()))?;
+        }
+
+        *BLOCKLIST.write().unwrap_or_else(|err| err.into_inner()) = blocklist;
+        Ok(())
+    })
+}
+
+/// Queries a server, blocking until it answers or the query fails.
+///
+/// Must not be called from a `GstatCallback`, which runs on the library's own threads.
+///
+/// # Parameters
+///
+/// * `game_id`: The identifier or an alias of a loaded game.
+/// * `host`: The host name or IP address of the server.
+/// * `port`: The query port, or `0` for the game's default.
+/// * `out_json`: Receives the JSON document of the response, to be released with
+///   `gstat_string_free`, or null if the query fails.
+///
+/// # Safety
+///
+/// `game_id` and `host` must be null or point to nul-terminated strings, and `out_json`
+/// must be null or point to writable memory.
+#[no_mangle]
+pub unsafe extern "C" fn gstat_query(
+    game_id: *const c_char,
+    host: *const c_char,
+    port: u16,
+    out_json: *mut *mut c_char,
+) -> GstatStatus {
+    guard(|| {
+        if out_json.is_null() {
+            return Err(Failure::invalid("`out_json` is null"));
+        }
+
+        *out_json = ptr::null_mut();
+
+        let game = find_game(argument(game_id, "game_id")?)?;
+        let host = argument(host, "host")?.to_string();
+        let json = runtime()?.block_on(query(game, host, port))?;
+
+        *out_json = CString::new(json).unwrap_or_default().into_raw();
+        Ok(())
+    })
+}
+
+/// Starts a query in the background, reporting its outcome to `callback` on one of the
+/// library's threads.
+///
+/// The arguments are checked before the query starts: if this returns anything but
+/// `GSTAT_STATUS_OK`, `callback` is never called. Otherwise it is called exactly once,
+/// with `GSTAT_STATUS_PANIC` if the query panicked.
+///
+/// # Parameters
+///
+/// * `game_id`: The identifier or an alias of a loaded game.
+/// * `host`: The host name or IP address of the server.
+/// * `port`: The query port, or `0` for the game's default.
+/// * `callback`: Receives the outcome.
+/// * `user_data`: Passed to `callback` as is.
+///
+/// # Safety
+///
+/// `game_id` and `host` must be null or point to nul-terminated strings, and `user_data`
+/// must stay valid, and be usable from another thread, until `callback` is called.
+#[no_mangle]
+pub unsafe extern "C" fn gstat_query_async(
+    game_id: *const c_char,
+    host: *const c_char,
+    port: u16,
+    callback: Option<GstatCallback>,
+    user_data: *mut c_void,
+) -> GstatStatus {
+    /// Lets the pointer of the caller travel to the thread running the callback.
+    struct UserData(*mut c_void);
+
+    // SAFETY: the caller of `gstat_query_async` guarantees that `user_data` can be used
+    // from another thread.
+    unsafe impl Send for UserData {}
+
+    guard(|| {
+        let callback = callback.ok_or_else(|| Failure::invalid("`callback` is null"))?;
+        let game = find_game(argument(game_id, "game_id")?)?;
+        let host = argument(host, "host")?.to_string();
+        let user_data = UserData(user_data);
+        let runtime = runtime()?;
+
+        runtime.spawn(async move {
+            // The query runs in its own task, so a panic surfaces here instead of dropping
+            // the callback. The runtime is never shut down, so joining only fails on a
+            // panic.
+            let (status, payload) = match runtime.spawn(query(game, host, port)).await {
+                Ok(Ok(json)) => (GstatStatus::Ok, json),
+                Ok(Err(Failure(status, message))) => (status, message),
+                Err(_) => (GstatStatus::Panic, "gstat panicked".to_string()),
+            };
+            let payload = CString::new(payload).unwrap_or_default();
+            let user_data = user_data;
+
+            callback(status, payload.as_ptr(), user_data.0);
+        });
+
+        Ok(())
+    })
+}
+
+/// Returns the description of the last failure on the calling thread, or null if no call
+/// failed yet. The string stays valid until the next failing call on the thread.
+#[no_mangle]
+pub extern "C" fn gstat_last_error() -> *const c_char {
+    LAST_ERROR.with(|last| {
+        last.borrow()
+            .as_ref()
+            .map_or(ptr::null(), |message| message.as_ptr())
+    })
+}
+
+/// Releases a string returned by the library.
+///
+/// # Safety
+///
+/// `string` must be null or a string returned by the library that wasn't released yet.
+#[no_mangle]
+pub unsafe extern "C" fn gstat_string_free(string: *mut c_char) {
+    if !string.is_null() {
+        drop(CString::from_raw(string));
+    }
+}
@@ -0,0 +1,61 @@
+//! Blocking addresses through the C API, in a process of its own since the blocklist is
+//! shared by every query.
+
+use gstat_ffi::{
+    gstat_last_error, gstat_load_script, gstat_query, gstat_set_blocklist, gstat_set_timeout_ms,
+    GstatStatus,
+};
+use gstat_test::{loopback_server, SOURCE_INFO_GAME, SOURCE_INFO_PACKET, SOURCE_INFO_SCRIPT};
+
+use std::{
+    ffi::{CStr, CString},
+    ptr,
+};
+
+fn last_error() -> String {
+    unsafe { CStr::from_ptr(gstat_last_error()) }
+        .to_string_lossy()
+        .into_owned()
+}
+
+#[test]
+fn reserved_and_listed_addresses_are_blocked() {
+    let source = CString::new(SOURCE_INFO_SCRIPT).unwrap();
+    assert_eq!(
+        unsafe { gstat_load_script(source.as_ptr()) },
+        GstatStatus::Ok
+    );
+    gstat_set_timeout_ms(200);
+
+    let server = loopback_server(SOURCE_INFO_PACKET);
+    let (game, host) = (
+        CString::new(SOURCE_INFO_GAME).unwrap(),
+        CString::new("127.0.0.1").unwrap(),
+    );
+    let port = server.address().port();
+    let mut json = ptr::null_mut();
+
+    // The reserved ranges are blocked by default.
+    let status = unsafe { gstat_query(game.as_ptr(), host.as_ptr(), port, &mut json) };
+    assert_eq!(status, GstatStatus::Blocked);
+    assert!(last_error().contains("is blocked by"));
+
+    let list = CString::new("# opted out\n127.0.0.1\n").unwrap();
+    assert_eq!(
+        unsafe { gstat_set_blocklist(list.as_ptr(), false) },
+        GstatStatus::Ok
+    );
+    let status = unsafe { gstat_query(game.as_ptr(), host.as_ptr(), port, &mut json) };
+    assert_eq!(status, GstatStatus::Blocked);
+    assert!(last_error().contains("gstat_set_blocklist"));
+
+    // An invalid list leaves the blocklist unchanged.
+    let invalid = CString::new("not an address\n").unwrap();
+    assert_eq!(
+        unsafe { gstat_set_blocklist(invalid.as_ptr(), false) },
+        GstatStatus::InvalidArgument
+    );
+    let status = unsafe { gstat_query(game.as_ptr(), host.as_ptr(), port, &mut json) };
+    assert_eq!(status, GstatStatus::Blocked);
+    assert_eq!(server.queries(), 0);
+}
@@ -0,0 +1,130 @@
+//! Querying servers through the C API.
+
+use gstat_ffi::{
+    gstat_last_error, gstat_load_script, gstat_query, gstat_query_async, gstat_set_blocklist,
+    gstat_set_timeout_ms, gstat_string_free, GstatStatus,
+};
+use gstat_test::{loopback_server, SOURCE_INFO_GAME, SOURCE_INFO_PACKET, SOURCE_INFO_SCRIPT};
+
+use std::{
+    ffi::{c_char, c_void, CStr, CString},
+    net::{Ipv4Addr, UdpSocket},
+    ptr,
+    sync::{mpsc, Once},
+    time::Duration,
+};
+
+/// Loads the game once for every test of this process, and unblocks the reserved ranges
+/// the test servers run in.
+fn load() {
+    static LOADED: Once = Once::new();
+
+    LOADED.call_once(|| {
+        let source = CString::new(SOURCE_INFO_SCRIPT).unwrap();
+
+        assert_eq!(
+            unsafe { gstat_load_script(source.as_ptr()) },
+            GstatStatus::Ok
+        );
+        gstat_set_timeout_ms(200);
+        assert_eq!(
+            unsafe { gstat_set_blocklist(ptr::null(), false) },
+            GstatStatus::Ok
+        );
+    });
+}
+
+fn last_error() -> String {
+    unsafe { CStr::from_ptr(gstat_last_error()) }
+        .to_string_lossy()
+        .into_owned()
+}
+
+#[test]
+fn servers_are_queried() {
+    load();
+    let server = loopback_server(SOURCE_INFO_PACKET).address();
+    let (game, host) = (
+        CString::new(SOURCE_INFO_GAME).unwrap(),
+        CString::new("127.0.0.1").unwrap(),
+    );
+    let mut json = ptr::null_mut();
+
+    let status = unsafe { gstat_query(game.as_ptr(), host.as_ptr(), server.port(), &mut json) };
+    assert_eq!(status, GstatStatus::Ok);
+
+    let document: serde_json::Value =
+        serde_json::from_str(unsafe { CStr::from_ptr(json) }.to_str().unwrap()).unwrap();
+    assert_eq!(document["version"], 1);
+    assert_eq!(document["game"]["id"], SOURCE_INFO_GAME);
+    assert_eq!(document["info"]["players"], 12);
+
+    unsafe { gstat_string_free(json) };
+}
+
+#[test]
+fn failures_are_described() {
+    load();
+    let silent = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+    let host = CString::new("127.0.0.1").unwrap();
+    let mut json = ptr::null_mut();
+
+    let unknown = CString::new("no-such-game").unwrap();
+    let status = unsafe { gstat_query(unknown.as_ptr(), host.as_ptr(), 27015, &mut json) };
+    assert_eq!(status, GstatStatus::UnknownGame);
+    assert!(json.is_null());
+    assert!(last_error().contains("no-such-game"));
+
+    let game = CString::new(SOURCE_INFO_GAME).unwrap();
+    let port = silent.local_addr().unwrap().port();
+    let status = unsafe { gstat_query(game.as_ptr(), host.as_ptr(), port, &mut json) };
+    assert_eq!(status, GstatStatus::Timeout);
+    assert!(json.is_null());
+
+    let status = unsafe { gstat_query(game.as_ptr(), ptr::null(), port, &mut json) };
+    assert_eq!(status, GstatStatus::InvalidArgument);
+    assert!(last_error().contains("host"));
+
+    let source = CString::new(SOURCE_INFO_SCRIPT).unwrap();
+    let status = unsafe { gstat_load_script(source.as_ptr()) };
+    assert_eq!(status, GstatStatus::ScriptError);
+    assert!(last_error().contains("already registered"));
+}
+
+#[test]
+fn async_queries_report_to_the_callback() {
+    extern "C" fn report(status: GstatStatus, payload: *const c_char, user_data: *mut c_void) {
+        // The callback owns the sender, which may still be in use after the test received
+        // the outcome.
+        let sender =
+            unsafe { Box::from_raw(user_data as *mut mpsc::Sender<(GstatStatus, String)>) };
+        let payload = unsafe { CStr::from_ptr(payload) }
+            .to_string_lossy()
+            .into_owned();
+
+        sender.send((status, payload)).unwrap();
+    }
+
+    load();
+    let server = loopback_server(SOURCE_INFO_PACKET).address();
+    let (game, host) = (
+        CString::new(SOURCE_INFO_GAME).unwrap(),
+        CString::new("127.0.0.1").unwrap(),
+    );
+    let (sender, receiver) = mpsc::channel::<(GstatStatus, String)>();
+
+    let status = unsafe {
+        gstat_query_async(
+            game.as_ptr(),
+            host.as_ptr(),
+            server.port(),
+            Some(report),
+            Box::into_raw(Box::new(sender)) as *mut c_void,
+        )
+    };
+    assert_eq!(status, GstatStatus::Ok);
+
+    let (status, payload) = receiver.recv_timeout(Duration::from_secs(5)).unwrap();
+    assert_eq!(status, GstatStatus::Ok);
+    assert!(payload.contains("\"players\":12"), "{payload}");
+}
@@ -0,0 +1,44 @@
+use tonic_build::manual::{Builder, Method, Service};
+
+/// Describes a method of the `Gstat` service, whose messages live in `crate::proto`.
+fn method(name: &str, route: &str, input: &str, output: &str, streaming: bool) -> Method {
+    let method = Method::builder()
+        .name(name)
+        .route_name(route)
+        .input_type(format!("crate::proto::{input}"))
+        .output_type(format!("crate::proto::{output}"))
+        .codec_path("tonic_prost::ProstCodec");
+
+    if streaming {
+        method.server_streaming().build()
+    } else {
+        method.build()
+    }
+}
+
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+
+    // The service of `proto/gstat.proto`, generated without `protoc`.
+    let service = Service::builder()
+        .name("Gstat")
+        .package("gstat.v1")
+        .method(method(
+            "query",
+            "Query",
+            "QueryRequest",
+            "QueryResponse",
+            false,
+        ))
+        .method(method(
+            "bulk_query",
+            "BulkQuery",
+            "BulkQueryRequest",
+            "BulkQueryResult",
+            true,
+        ))
+        .method(method("watch", "Watch", "WatchRequest", "WatchEvent", true))
+        .build();
+
+    Builder::new().compile(&[service]);
+}
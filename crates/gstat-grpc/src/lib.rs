@@ -0,0 +1,4 @@
+pub mod proto;
+pub mod service;
+
+pub use service::GstatService;
@@ -0,0 +1,102 @@
+use gstat_core::{blocklist::Blocklist, prelude::TimeoutSettings, script::ScriptRegistry};
+use gstat_grpc::GstatService;
+
+use std::{fs, net::SocketAddr, path::PathBuf, process::ExitCode, time::Duration};
+
+use clap::Parser;
+use tokio::net::TcpListener;
+use tokio_stream::wrappers::TcpListenerStream;
+use tonic::transport::Server;
+
+/// The limit applied to every step of a query unless `--timeout` sets one.
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Serve game server queries over gRPC.
+#[derive(Debug, Parser)]
+#[command(name = "gstat-grpc", version)]
+struct Args {
+    /// The address the service is served on.
+    #[arg(long, env = "GSTAT_GRPC_LISTEN", default_value = "0.0.0.0:50051")]
+    listen: SocketAddr,
+
+    /// A game script to load, making its game available to clients besides the native
+    /// ones. May be repeated.
+    #[arg(long = "script", value_name = "PATH")]
+    scripts: Vec<PathBuf>,
+
+    /// The limit for every step of a query, in milliseconds.
+    #[arg(long, value_name = "MS")]
+    timeout_ms: Option<u64>,
+
+    /// An opt-out list of addresses and ranges that are never queried. May be repeated.
+    #[arg(long = "blocklist", value_name = "PATH")]
+    blocklists: Vec<PathBuf>,
+
+    /// Allow querying private, loopback and otherwise reserved ranges, which are blocked
+    /// so clients can't probe the network the service runs in.
+    #[arg(long)]
+    allow_reserved: bool,
+}
+
+fn main() -> ExitCode {
+    match run(Args::parse()) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("gstat-grpc: {err}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(args: Args) -> Result<(), String> {
+    let mut scripts = ScriptRegistry::new();
+
+    for path in &args.scripts {
+        let source = fs::read_to_string(path)
+            .map_err(|err| format!("failed to read {}: {err}", path.display()))?;
+
+        scripts
+            .load(&source)
+            .map_err(|err| format!("invalid script {}: {err}", path.display()))?;
+    }
+
+    let mut blocklist = if args.allow_reserved {
+        Blocklist::new()
+    } else {
+        Blocklist::reserved()
+    };
+
+    for path in &args.blocklists {
+        blocklist
+            .load(path)
+            .map_err(|err| format!("{}: {err}", path.display()))?;
+    }
+
+    let games = scripts.len();
+    let timeout = args
+        .timeout_ms
+        .map_or(DEFAULT_TIMEOUT, Duration::from_millis);
+    let service = GstatService::new(scripts)
+        .with_blocklist(blocklist)
+        .with_timeouts(TimeoutSettings::uniform(timeout));
+
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()
+        .map_err(|err| format!("failed to start the runtime: {err}"))?;
+
+    runtime.block_on(async {
+        let listener = TcpListener::bind(args.listen)
+            .await
+            .map_err(|err| format!("failed to listen on {}: {err}", args.listen))?;
+        let local = listener.local_addr().map_err(|err| err.to_string())?;
+
+        eprintln!("gstat-grpc: serving {games} scripted games on {local}");
+
+        Server::builder()
+            .add_service(service.into_server())
+            .serve_with_incoming(TcpListenerStream::new(listener))
+            .await
+            .map_err(|err| err.to_string())
+    })
+}
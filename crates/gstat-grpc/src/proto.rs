@@ -0,0 +1,161 @@
+//! The messages of `proto/gstat.proto` and the generated `Gstat` client and server.
+//!
+//! The messages are derived by hand rather than generated, so the crate builds without
+//! `protoc`. Their field tags must match the `.proto` file, which is the contract clients
+//! in other languages are generated from.
+
+use std::collections::BTreeMap;
+
+include!(concat!(env!("OUT_DIR"), "/gstat.v1.Gstat.rs"));
+
+/// A server to query.
+#[derive(Clone, PartialEq, Eq, Hash, prost::Message)]
+pub struct Target {
+    /// The game the server runs, as the identifier of a loaded script.
+    #[prost(string, tag = "1")]
+    pub game: String,
+    /// The server address, as `host` or `host:port`.
+    #[prost(string, tag = "2")]
+    pub address: String,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct QueryRequest {
+    #[prost(message, optional, tag = "1")]
+    pub target: Option<Target>,
+}
+
+/// What a server reports in common with every other game.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct ServerInfo {
+    #[prost(string, tag = "1")]
+    pub name: String,
+    #[prost(string, tag = "2")]
+    pub map: String,
+    #[prost(string, tag = "3")]
+    pub game: String,
+    #[prost(string, tag = "4")]
+    pub version: String,
+    #[prost(uint32, tag = "5")]
+    pub players: u32,
+    #[prost(uint32, tag = "6")]
+    pub max_players: u32,
+    #[prost(bool, tag = "7")]
+    pub password: bool,
+    #[prost(string, tag = "8")]
+    pub access: String,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct Player {
+    #[prost(string, tag = "1")]
+    pub name: String,
+    #[prost(int64, optional, tag = "2")]
+    pub score: Option<i64>,
+    #[prost(double, optional, tag = "3")]
+    pub duration_secs: Option<f64>,
+    #[prost(uint32, optional, tag = "4")]
+    pub ping_ms: Option<u32>,
+    #[prost(string, optional, tag = "5")]
+    pub team: Option<String>,
+    #[prost(btree_map = "string, string", tag = "6")]
+    pub extra: BTreeMap<String, String>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct QueryResponse {
+    /// The identifier of the game.
+    #[prost(string, tag = "1")]
+    pub game: String,
+    /// The address that answered.
+    #[prost(string, tag = "2")]
+    pub address: String,
+    #[prost(double, tag = "3")]
+    pub latency_ms: f64,
+    #[prost(message, optional, tag = "4")]
+    pub info: Option<ServerInfo>,
+    #[prost(message, repeated, tag = "5")]
+    pub players: Vec<Player>,
+    #[prost(btree_map = "string, string", tag = "6")]
+    pub rules: BTreeMap<String, String>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct BulkQueryRequest {
+    /// The servers to query, up to the server's limit.
+    #[prost(message, repeated, tag = "1")]
+    pub targets: Vec<Target>,
+    /// The most queries run at once, up to the server's default; `0` uses the default.
+    #[prost(uint32, tag = "2")]
+    pub concurrency: u32,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct BulkQueryResult {
+    #[prost(message, optional, tag = "1")]
+    pub target: Option<Target>,
+    #[prost(oneof = "bulk_query_result::Outcome", tags = "2, 3")]
+    pub outcome: Option<bulk_query_result::Outcome>,
+}
+
+pub mod bulk_query_result {
+    #[derive(Clone, PartialEq, prost::Oneof)]
+    pub enum Outcome {
+        #[prost(message, tag = "2")]
+        Response(Box<super::QueryResponse>),
+        /// Why the query failed.
+        #[prost(string, tag = "3")]
+        Error(String),
+    }
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct WatchRequest {
+    /// The servers to poll, up to the server's limit.
+    #[prost(message, repeated, tag = "1")]
+    pub targets: Vec<Target>,
+    /// The time between two polls of the same server, at least the server's minimum; `0`
+    /// uses the server's default.
+    #[prost(uint32, tag = "2")]
+    pub interval_ms: u32,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct WatchEvent {
+    #[prost(message, optional, tag = "1")]
+    pub target: Option<Target>,
+    #[prost(oneof = "watch_event::Kind", tags = "2, 3, 4, 5")]
+    pub kind: Option<watch_event::Kind>,
+}
+
+pub mod watch_event {
+    #[derive(Clone, PartialEq, prost::Oneof)]
+    pub enum Kind {
+        /// The server answered after being offline or unknown.
+        #[prost(message, tag = "2")]
+        Online(super::ServerInfo),
+        /// The server stopped answering, and why.
+        #[prost(string, tag = "3")]
+        Offline(String),
+        #[prost(message, tag = "4")]
+        PlayersChanged(super::PlayersChanged),
+        #[prost(message, tag = "5")]
+        MapChanged(super::MapChanged),
+    }
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct PlayersChanged {
+    #[prost(uint32, tag = "1")]
+    pub from: u32,
+    #[prost(uint32, tag = "2")]
+    pub to: u32,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct MapChanged {
+    #[prost(string, tag = "1")]
+    pub from: String,
+    #[prost(string, tag = "2")]
+    pub to: String,
+}
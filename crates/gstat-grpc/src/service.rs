@@ -0,0 +1,344 @@
+use crate::proto::{
+    self, bulk_query_result::Outcome, gstat_server::Gstat, watch_event::Kind, BulkQueryRequest,
+    BulkQueryResult, MapChanged, PlayersChanged, QueryRequest, QueryResponse, Target, WatchEvent,
+    WatchRequest,
+};
+
+use gstat_core::{
+    blocklist::Blocklist,
+    bulk::QueryMany,
+    poller::{PollEvent, Poller},
+    prelude::{Error, ErrorKind, Game, ServerInfo, TimeoutSettings},
+    resolve::SystemResolver,
+    schedule::Schedule,
+    script::{ScriptQuery, ScriptRegistry},
+    standards::dyn_game::DynFetched,
+};
+use gstat_tcp::{
+    lookup::{self, LookupError, QueryGame},
+    slp::{Minecraft, SlpQuery},
+};
+use gstat_udp::script::Scripted;
+
+use std::{error::Error as StdError, sync::Arc, time::Duration};
+
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::{Request, Response, Status};
+
+/// The most queries a bulk query runs at once, and the limit unless the request sets a
+/// lower one.
+pub const DEFAULT_CONCURRENCY: usize = 64;
+
+/// How often watched servers are polled unless the request sets an interval.
+pub const DEFAULT_INTERVAL: Duration = Duration::from_secs(30);
+
+/// The shortest interval a watch may poll its servers at, so one client can't flood them.
+pub const MIN_INTERVAL: Duration = Duration::from_secs(1);
+
+/// The most servers a bulk query or a watch may list.
+pub const MAX_TARGETS: usize = 1024;
+
+/// The number of results buffered for a slow client before the stream waits for it.
+const STREAM_BUFFER: usize = 64;
+
+/// `GstatService` answers the `Gstat` service of `proto/gstat.proto` by querying games
+/// described by scripts and the games `gstat-tcp` queries natively.
+///
+/// Serve it with `GstatService::into_server`, e.g. through `tonic::transport::Server`.
+///
+/// Clients choose the addresses queried, so the reserved ranges are blocked unless
+/// `GstatService::with_blocklist` replaces the blocklist.
+#[derive(Clone)]
+pub struct GstatService {
+    scripts: Arc<ScriptRegistry>,
+    blocklist: Arc<Blocklist>,
+    timeouts: TimeoutSettings,
+}
+
+impl GstatService {
+    /// Creates the service over the loaded games.
+    ///
+    /// # Parameters
+    ///
+    /// * `scripts`: The scripted games that can be queried besides the native ones.
+    pub fn new(scripts: ScriptRegistry) -> Self {
+        GstatService {
+            scripts: Arc::new(scripts),
+            blocklist: Arc::new(Blocklist::reserved()),
+            timeouts: TimeoutSettings::default(),
+        }
+    }
+
+    /// Sets the servers that are never queried, which are the reserved ranges by default.
+    pub fn with_blocklist(mut self, blocklist: Blocklist) -> Self {
+        self.blocklist = Arc::new(blocklist);
+        self
+    }
+
+    /// Sets the time limits of every query.
+    pub fn with_timeouts(mut self, timeouts: TimeoutSettings) -> Self {
+        self.timeouts = timeouts;
+        self
+    }
+
+    /// Wraps the service in the generated tonic server.
+    pub fn into_server(self) -> proto::gstat_server::GstatServer<Self> {
+        proto::gstat_server::GstatServer::new(self)
+    }
+}
+
+#[tonic::async_trait]
+impl Gstat for GstatService {
+    async fn query(
+        &self,
+        request: Request<QueryRequest>,
+    ) -> Result<Response<QueryResponse>, Status> {
+        let target = request
+            .into_inner()
+            .target
+            .ok_or_else(|| Status::invalid_argument("missing target"))?;
+
+        query(&self.scripts, &self.blocklist, &target, self.timeouts)
+            .await
+            .map(Response::new)
+    }
+
+    type BulkQueryStream = ReceiverStream<Result<BulkQueryResult, Status>>;
+
+    async fn bulk_query(
+        &self,
+        request: Request<BulkQueryRequest>,
+    ) -> Result<Response<Self::BulkQueryStream>, Status> {
+        let request = request.into_inner();
+        check_targets(&request.targets)?;
+
+        let concurrency = match request.concurrency {
+            0 => DEFAULT_CONCURRENCY,
+            limit => (limit as usize).min(DEFAULT_CONCURRENCY),
+        };
+        let (sender, receiver) = mpsc::channel(STREAM_BUFFER);
+        let (scripts, blocklist, timeouts) =
+            (self.scripts.clone(), self.blocklist.clone(), self.timeouts);
+
+        tokio::spawn(async move {
+            let mut results = QueryMany::new(request.targets, concurrency, |target: &Target| {
+                let target = target.clone();
+                let (scripts, blocklist) = (&scripts, &blocklist);
+
+                async move { query(scripts, blocklist, &target, timeouts).await }
+            });
+
+            while let Some((target, outcome)) = results.next().await {
+                let outcome = match outcome {
+                    Ok(response) => Outcome::Response(Box::new(response)),
+                    Err(status) => Outcome::Error(status.message().to_string()),
+                };
+                let result = BulkQueryResult {
+                    target: Some(target),
+                    outcome: Some(outcome),
+                };
+
+                if sender.send(Ok(result)).await.is_err() {
+                    break;
+                }
+            }
+        });
+
+        Ok(Response::new(ReceiverStream::new(receiver)))
+    }
+
+    type WatchStream = ReceiverStream<Result<WatchEvent, Status>>;
+
+    async fn watch(
+        &self,
+        request: Request<WatchRequest>,
+    ) -> Result<Response<Self::WatchStream>, Status> {
+        let request = request.into_inner();
+        check_targets(&request.targets)?;
+
+        let interval = match request.interval_ms {
+            0 => DEFAULT_INTERVAL,
+            interval => Duration::from_millis(interval.into()),
+        };
+
+        if interval < MIN_INTERVAL {
+            return Err(Status::invalid_argument(format!(
+                "the interval must be at least {}ms",
+                MIN_INTERVAL.as_millis()
+            )));
+        }
+
+        for target in &request.targets {
+            find_game(&self.scripts, &target.game)?;
+        }
+
+        let (sender, receiver) = mpsc::channel(STREAM_BUFFER);
+        let (scripts, blocklist, timeouts) =
+            (self.scripts.clone(), self.blocklist.clone(), self.timeouts);
+
+        tokio::spawn(async move {
+            let mut poller = Poller::new(
+                request.targets,
+                Schedule::new(interval),
+                |target: &Target| {
+                    let target = target.clone();
+                    let (scripts, blocklist) = (&scripts, &blocklist);
+
+                    async move {
+                        fetch(scripts, blocklist, &target, timeouts)
+                            .await
+                            .map(|(_, fetched)| fetched.info)
+                            .map_err(|status| status.message().to_string())
+                    }
+                },
+            );
+
+            loop {
+                let event = tokio::select! {
+                    event = poller.next() => event,
+                    () = sender.closed() => break,
+                };
+                let Some(event) = event else {
+                    break;
+                };
+
+                if sender.send(Ok(watch_event(event))).await.is_err() {
+                    break;
+                }
+            }
+        });
+
+        Ok(Response::new(ReceiverStream::new(receiver)))
+    }
+}
+
+/// Refuses requests listing more than `MAX_TARGETS` servers.
+fn check_targets(targets: &[Target]) -> Result<(), Status> {
+    if targets.len() > MAX_TARGETS {
+        return Err(Status::invalid_argument(format!(
+            "{} targets exceed the limit of {MAX_TARGETS}",
+            targets.len()
+        )));
+    }
+
+    Ok(())
+}
+
+/// Looks up the game of a target, scripted or native.
+fn find_game<'s>(scripts: &'s ScriptRegistry, identifier: &str) -> Result<QueryGame<'s>, Status> {
+    lookup::find_game(scripts, identifier).map_err(|err| match err {
+        LookupError::UnknownGame(_) => Status::not_found(err.to_string()),
+        err => Status::unimplemented(err.to_string()),
+    })
+}
+
+/// Queries a server at each address its host resolves to until one answers, skipping the
+/// blocked ones.
+async fn fetch<'s>(
+    scripts: &'s ScriptRegistry,
+    blocklist: &Blocklist,
+    target: &Target,
+    timeouts: TimeoutSettings,
+) -> Result<(QueryGame<'s>, DynFetched), Status> {
+    let game = find_game(scripts, &target.game)?;
+    let (host, resolver) = (&target.address, &SystemResolver);
+    let fetched = match game {
+        QueryGame::Scripted(game) => Scripted(game)
+            .fetch_host_checked(ScriptQuery, host, resolver, Some(blocklist), timeouts)
+            .await
+            .map(DynFetched::from)
+            .map_err(|err| status(host, err))?,
+        QueryGame::Native(_) => Minecraft
+            .fetch_host_checked(
+                SlpQuery::default(),
+                host,
+                resolver,
+                Some(blocklist),
+                timeouts,
+            )
+            .await
+            .map(DynFetched::from)
+            .map_err(|err| status(host, err))?,
+    };
+
+    Ok((game, fetched))
+}
+
+/// Converts the error of a query to `host` into the status answering it.
+fn status<E: StdError>(host: &str, err: Error<E>) -> Status {
+    let message = format!("{host}: {err}");
+
+    match err.kind() {
+        ErrorKind::Timeout => Status::deadline_exceeded(message),
+        ErrorKind::Blocked => Status::permission_denied(message),
+        _ => Status::unavailable(message),
+    }
+}
+
+/// Queries a server and converts its response.
+async fn query(
+    scripts: &ScriptRegistry,
+    blocklist: &Blocklist,
+    target: &Target,
+    timeouts: TimeoutSettings,
+) -> Result<QueryResponse, Status> {
+    let (game, fetched) = fetch(scripts, blocklist, target, timeouts).await?;
+    let info = fetched.info;
+    let players = fetched
+        .players
+        .into_iter()
+        .map(|player| proto::Player {
+            name: player.name,
+            score: player.score,
+            duration_secs: player.duration.map(|duration| duration.as_secs_f64()),
+            ping_ms: player.ping,
+            team: player.team,
+            extra: player.extra,
+        })
+        .collect();
+
+    Ok(QueryResponse {
+        game: game.id().to_string(),
+        address: fetched
+            .meta
+            .address()
+            .map(|address| address.to_string())
+            .unwrap_or_default(),
+        latency_ms: fetched.meta.latency().as_secs_f64() * 1000.0,
+        rules: info.extra.clone(),
+        info: Some(server_info(info)),
+        players,
+    })
+}
+
+fn server_info(info: ServerInfo) -> proto::ServerInfo {
+    proto::ServerInfo {
+        access: info.access.to_string(),
+        name: info.name,
+        map: info.map,
+        game: info.game,
+        version: info.version,
+        players: info.players,
+        max_players: info.max_players,
+        password: info.password,
+    }
+}
+
+fn watch_event(event: PollEvent<Target, String>) -> WatchEvent {
+    let (target, kind) = match event {
+        PollEvent::Online { target, info } => (target, Kind::Online(server_info(info))),
+        PollEvent::Offline { target, error } => (target, Kind::Offline(error)),
+        PollEvent::PlayersChanged { target, from, to } => {
+            (target, Kind::PlayersChanged(PlayersChanged { from, to }))
+        }
+        PollEvent::MapChanged { target, from, to } => {
+            (target, Kind::MapChanged(MapChanged { from, to }))
+        }
+    };
+
+    WatchEvent {
+        target: Some(target),
+        kind: Some(kind),
+    }
+}
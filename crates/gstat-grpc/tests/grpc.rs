@@ -0,0 +1,200 @@
+//! Querying servers through the `Gstat` service with the generated client.
+
+use gstat_core::{blocklist::Blocklist, prelude::TimeoutSettings, script::ScriptRegistry};
+use gstat_grpc::{
+    proto::{
+        bulk_query_result::Outcome, gstat_client::GstatClient, watch_event::Kind, BulkQueryRequest,
+        QueryRequest, Target, WatchRequest,
+    },
+    service::MAX_TARGETS,
+    GstatService,
+};
+use gstat_test::{loopback_server, SOURCE_INFO_GAME, SOURCE_INFO_PACKET, SOURCE_INFO_SCRIPT};
+
+use std::{
+    net::{Ipv4Addr, UdpSocket},
+    time::Duration,
+};
+
+use tokio::{net::TcpListener, runtime::Runtime};
+use tokio_stream::wrappers::TcpListenerStream;
+use tonic::{transport::Channel, Code};
+
+fn target(address: impl ToString) -> Target {
+    Target {
+        game: SOURCE_INFO_GAME.into(),
+        address: address.to_string(),
+    }
+}
+
+/// Starts the service on an ephemeral port and connects a client to it.
+///
+/// The test servers run on the loopback interface, so the reserved ranges are only
+/// blocked with `blocklist`.
+fn connect(runtime: &Runtime, blocklist: Blocklist) -> GstatClient<Channel> {
+    let mut scripts = ScriptRegistry::new();
+    scripts.load(SOURCE_INFO_SCRIPT).unwrap();
+
+    let service = GstatService::new(scripts)
+        .with_blocklist(blocklist)
+        .with_timeouts(TimeoutSettings::uniform(Duration::from_millis(200)));
+
+    runtime.block_on(async {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).await.unwrap();
+        let address = listener.local_addr().unwrap();
+
+        tokio::spawn(
+            tonic::transport::Server::builder()
+                .add_service(service.into_server())
+                .serve_with_incoming(TcpListenerStream::new(listener)),
+        );
+
+        GstatClient::connect(format!("http://{address}"))
+            .await
+            .unwrap()
+    })
+}
+
+#[test]
+fn servers_are_queried() {
+    let runtime = Runtime::new().unwrap();
+    let mut client = connect(&runtime, Blocklist::new());
+    let online = loopback_server(SOURCE_INFO_PACKET).address();
+
+    runtime.block_on(async {
+        let response = client
+            .query(QueryRequest {
+                target: Some(target(online)),
+            })
+            .await
+            .unwrap()
+            .into_inner();
+
+        assert_eq!(response.game, SOURCE_INFO_GAME);
+        assert_eq!(response.address, online.to_string());
+        assert_eq!(response.info.unwrap().players, 12);
+
+        let status = client
+            .query(QueryRequest {
+                target: Some(Target {
+                    game: "no-such-game".into(),
+                    address: "192.0.2.1".into(),
+                }),
+            })
+            .await
+            .unwrap_err();
+
+        assert_eq!(status.code(), Code::NotFound);
+    });
+}
+
+#[test]
+fn bulk_queries_stream_every_result() {
+    let runtime = Runtime::new().unwrap();
+    let mut client = connect(&runtime, Blocklist::new());
+    let online = loopback_server(SOURCE_INFO_PACKET).address();
+    let silent = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+
+    runtime.block_on(async {
+        let mut stream = client
+            .bulk_query(BulkQueryRequest {
+                targets: vec![target(online), target(silent.local_addr().unwrap())],
+                concurrency: 0,
+            })
+            .await
+            .unwrap()
+            .into_inner();
+
+        let mut results = Vec::new();
+        while let Some(result) = stream.message().await.unwrap() {
+            results.push(result);
+        }
+
+        assert_eq!(results.len(), 2);
+
+        for result in results {
+            let answered = result.target.unwrap() == target(online);
+
+            match result.outcome.unwrap() {
+                Outcome::Response(response) => {
+                    assert!(answered);
+                    assert_eq!(response.info.unwrap().players, 12);
+                }
+                Outcome::Error(_) => assert!(!answered),
+            }
+        }
+    });
+}
+
+#[test]
+fn watched_servers_report_changes() {
+    let runtime = Runtime::new().unwrap();
+    let mut client = connect(&runtime, Blocklist::new());
+    let online = loopback_server(SOURCE_INFO_PACKET).address();
+
+    runtime.block_on(async {
+        let mut stream = client
+            .watch(WatchRequest {
+                targets: vec![target(online)],
+                interval_ms: 1000,
+            })
+            .await
+            .unwrap()
+            .into_inner();
+
+        let event = stream.message().await.unwrap().unwrap();
+
+        assert_eq!(event.target, Some(target(online)));
+        assert!(matches!(event.kind, Some(Kind::Online(info)) if info.players == 12));
+    });
+}
+
+#[test]
+fn reserved_addresses_are_blocked() {
+    let runtime = Runtime::new().unwrap();
+    let mut client = connect(&runtime, Blocklist::reserved());
+    let online = loopback_server(SOURCE_INFO_PACKET);
+
+    runtime.block_on(async {
+        let status = client
+            .query(QueryRequest {
+                target: Some(target(online.address())),
+            })
+            .await
+            .unwrap_err();
+
+        assert_eq!(status.code(), Code::PermissionDenied);
+        assert!(status.message().contains("is blocked by"));
+    });
+
+    assert_eq!(online.queries(), 0);
+}
+
+#[test]
+fn oversized_requests_are_refused() {
+    let runtime = Runtime::new().unwrap();
+    let mut client = connect(&runtime, Blocklist::new());
+    let online = loopback_server(SOURCE_INFO_PACKET).address();
+
+    runtime.block_on(async {
+        let status = client
+            .bulk_query(BulkQueryRequest {
+                targets: vec![target(online); MAX_TARGETS + 1],
+                concurrency: 0,
+            })
+            .await
+            .unwrap_err();
+
+        assert_eq!(status.code(), Code::InvalidArgument);
+
+        let status = client
+            .watch(WatchRequest {
+                targets: vec![target(online)],
+                interval_ms: 10,
+            })
+            .await
+            .unwrap_err();
+
+        assert_eq!(status.code(), Code::InvalidArgument);
+    });
+}
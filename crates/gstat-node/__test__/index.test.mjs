@@ -0,0 +1,110 @@
+// Querying servers and consoles through the addon; run `npm run build:debug` first.
+
+import assert from 'node:assert/strict'
+import { once } from 'node:events'
+import { createSocket } from 'node:dgram'
+import { readFileSync } from 'node:fs'
+import { createServer } from 'node:net'
+import { createRequire } from 'node:module'
+import test from 'node:test'
+
+const { loadScript, query, rcon, setBlocklist } = createRequire(import.meta.url)('../index.js')
+
+const fixture = (name) => readFileSync(new URL(`../../gstat-test/fixtures/${name}`, import.meta.url))
+const GAME = loadScript(fixture('source-info.rhai').toString())
+
+// The test servers run on the loopback interface, which is reserved.
+setBlocklist(null, false)
+
+/** Binds a server answering every query with a response listing 12 players. */
+async function server() {
+  const socket = createSocket('udp4')
+  socket.on('message', (_, peer) => socket.send(fixture('source-info.bin'), peer.port, peer.address))
+  socket.bind(0, '127.0.0.1')
+  await once(socket, 'listening')
+  socket.unref()
+
+  return socket.address().port
+}
+
+/** Encodes a Source RCON packet with its length prefix. */
+function packet(id, kind, body) {
+  const payload = Buffer.concat([Buffer.alloc(8), Buffer.from(body), Buffer.alloc(2)])
+  payload.writeInt32LE(id, 0)
+  payload.writeInt32LE(kind, 4)
+
+  const length = Buffer.alloc(4)
+  length.writeInt32LE(payload.length)
+
+  return Buffer.concat([length, payload])
+}
+
+/** Starts a console accepting `secret` and echoing commands back. */
+async function rconServer() {
+  const listener = createServer((stream) => {
+    let buffered = Buffer.alloc(0)
+
+    stream.on('data', (data) => {
+      buffered = Buffer.concat([buffered, data])
+
+      while (buffered.length >= 4 && buffered.length >= 4 + buffered.readInt32LE(0)) {
+        const length = buffered.readInt32LE(0)
+        const [id, kind] = [buffered.readInt32LE(4), buffered.readInt32LE(8)]
+        const body = buffered.subarray(12, 4 + length - 2).toString()
+        buffered = buffered.subarray(4 + length)
+
+        if (kind === 3) {
+          stream.write(packet(body === 'secret' ? id : -1, 2, ''))
+        } else {
+          stream.write(packet(id, 0, kind === 2 ? `ran ${body}` : ''))
+        }
+      }
+    })
+  })
+  listener.listen(0, '127.0.0.1')
+  await once(listener, 'listening')
+  listener.unref()
+
+  return listener.address().port
+}
+
+test('servers are queried', async () => {
+  const result = await query(GAME, '127.0.0.1', await server())
+
+  assert.equal(result.game.id, GAME)
+  assert.equal(result.info.players, 12)
+  assert.equal(typeof result.latencyMs, 'number')
+})
+
+test('failures reject the promise', async () => {
+  await assert.rejects(query('no-such-game', '127.0.0.1', 27015), /unknown game/)
+
+  const silent = createSocket('udp4')
+  silent.bind(0, '127.0.0.1')
+  await once(silent, 'listening')
+  await assert.rejects(query(GAME, '127.0.0.1', silent.address().port, { timeoutMs: 200 }))
+  silent.close()
+})
+
+test('blocked addresses are refused', async () => {
+  const [port, console] = [await server(), await rconServer()]
+
+  try {
+    setBlocklist(null, true)
+    await assert.rejects(query(GAME, '127.0.0.1', port), /is blocked by/)
+
+    setBlocklist('# opted out\n127.0.0.1\n', false)
+    await assert.rejects(rcon('127.0.0.1', console, 'secret', 'status'), /setBlocklist/)
+
+    assert.throws(() => setBlocklist('not an address', false))
+  } finally {
+    setBlocklist(null, false)
+  }
+})
+
+test('consoles run commands', async () => {
+  const port = await rconServer()
+
+  assert.equal(await rcon('127.0.0.1', port, 'secret', 'status'), 'ran status')
+  await assert.rejects(rcon('127.0.0.1', port, 'wrong', 'status'))
+})
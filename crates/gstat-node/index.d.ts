@@ -0,0 +1,90 @@
+/* The declarations napi-rs generates from src/lib.rs; regenerate them with `npm run build`. */
+
+/** The options of `query`. */
+export interface QueryOptions {
+  /** The limit for every step of the query, in milliseconds. */
+  timeoutMs?: number
+}
+/** The options of `rcon`. */
+export interface RconOptions {
+  /** The limit for connecting, logging in and running the command, in milliseconds. */
+  timeoutMs?: number
+}
+export interface Game {
+  id: string
+  name: string
+}
+/** What a server reports in common with every other game. */
+export interface ServerInfo {
+  name: string
+  map: string
+  game: string
+  version: string
+  players: number
+  maxPlayers: number
+  password: boolean
+  access: string
+}
+export interface Player {
+  name: string
+  score?: number
+  durationSecs?: number
+  pingMs?: number
+  team?: string
+  extra: Record<string, string>
+}
+/** The response of a server. */
+export interface QueryResult {
+  game: Game
+  /** The address that answered. */
+  address?: string
+  latencyMs: number
+  info: ServerInfo
+  players: Array<Player>
+  rules: Record<string, string>
+}
+/**
+ * Compiles a game script and makes its game available to `query`.
+ *
+ * # Returns
+ *
+ * The identifier of the game.
+ */
+export function loadScript(source: string): string
+/**
+ * Replaces the addresses that are never queried or connected to.
+ *
+ * # Parameters
+ *
+ * * `list`: An opt-out list, with one address or CIDR range per line and `#` starting a
+ *   comment, or `null` for none.
+ * * `reserved`: Whether private, loopback and otherwise reserved ranges are blocked too,
+ *   as they are until this is called.
+ */
+export function setBlocklist(list: string | undefined | null, reserved: boolean): void
+/**
+ * Queries a server at each address its host resolves to until one answers, skipping the
+ * blocked ones.
+ *
+ * # Parameters
+ *
+ * * `game`: The identifier or an alias of a loaded or built-in game.
+ * * `host`: The host name or IP address of the server.
+ * * `port`: The query port, or the game's default if omitted.
+ * * `opts`: The options of the query.
+ */
+export function query(game: string, host: string, port?: number | undefined | null, opts?: QueryOptions | undefined | null): Promise<QueryResult>
+/**
+ * Logs in to a Source RCON console, runs a command and returns its output.
+ *
+ * Blocked addresses of the host are skipped like those of `query`.
+ *
+ * # Parameters
+ *
+ * * `host`: The host name or IP address of the server.
+ * * `port`: The port of the console.
+ * * `password`: The RCON password.
+ * * `command`: The command to run.
+ * * `opts`: The options of the connection.
+ */
+export function rcon(host: string, port: number, password: string, command: string, opts?: RconOptions | undefined | null): Promise<string>
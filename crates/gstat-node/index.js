@@ -0,0 +1,17 @@
+// Loads the native addon built for this platform by `napi build --platform`, falling back
+// to a plain `gstat.node` for local builds.
+
+const { existsSync } = require('node:fs')
+const { join } = require('node:path')
+
+const { platform, arch } = process
+const glibc = platform === 'linux' && process.report?.getReport().header.glibcVersionRuntime
+const libc = platform === 'linux' ? (glibc ? '-gnu' : '-musl') : ''
+const candidates = [`gstat.${platform}-${arch}${libc}.node`, `gstat.${platform}-${arch}.node`, 'gstat.node']
+const addon = candidates.map((name) => join(__dirname, name)).find((path) => existsSync(path))
+
+if (!addon) {
+  throw new Error(`gstat has no native addon for ${platform}-${arch}${libc}; build it with \`npm run build\``)
+}
+
+module.exports = require(addon)
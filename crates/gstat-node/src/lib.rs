@@ -0,0 +1,327 @@
+//! Node.js bindings for gstat, built with napi-rs.
+//!
+//! Games are loaded from scripts with `loadScript`, or built in like Minecraft: Java
+//! Edition, and queried with `query`, which resolves to the response in the shape of
+//! `gstat query --format json` with camel-cased keys. `rcon` runs a command on a Source
+//! RCON console. Failures reject the promise with an `Error` describing them. `index.d.ts`
+//! declares the API for TypeScript.
+//!
+//! Private, loopback and otherwise reserved ranges are neither queried nor connected to
+//! unless `setBlocklist` unblocks them, so applications passing on addresses from their
+//! users can't be made to probe the networks they run in.
+
+use gstat_core::{
+    blocklist::Blocklist,
+    prelude::{Game as _, TimeoutSettings},
+    registry::GameEntry,
+    resolve::{resolve, HostPort, SystemResolver},
+    script::{ScriptGame, ScriptQuery, ScriptRegistry},
+    standards::dyn_game::DynFetched,
+};
+use gstat_tcp::{
+    lookup::{self, QueryGame},
+    slp::{Minecraft, SlpQuery},
+    SourceRcon,
+};
+use gstat_udp::script::Scripted;
+
+use std::{
+    collections::HashMap,
+    sync::{LazyLock, RwLock},
+    time::Duration,
+};
+
+use napi::{Error, Result};
+use napi_derive::napi;
+
+/// The limit for every step of a query or RCON command unless the options set one.
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The games loaded with `loadScript`.
+static SCRIPTS: LazyLock<RwLock<ScriptRegistry>> = LazyLock::new(Default::default);
+
+/// The addresses that are never queried or connected to, set with `setBlocklist`.
+static BLOCKLIST: LazyLock<RwLock<Blocklist>> =
+    LazyLock::new(|| RwLock::new(Blocklist::reserved()));
+
+/// The options of `query`.
+#[napi(object)]
+pub struct QueryOptions {
+    /// The limit for every step of the query, in milliseconds.
+    pub timeout_ms: Option<u32>,
+}
+
+/// The options of `rcon`.
+#[napi(object)]
+pub struct RconOptions {
+    /// The limit for connecting, logging in and running the command, in milliseconds.
+    pub timeout_ms: Option<u32>,
+}
+
+#[napi(object)]
+pub struct Game {
+    pub id: String,
+    pub name: String,
+}
+
+/// What a server reports in common with every other game.
+#[napi(object)]
+pub struct ServerInfo {
+    pub name: String,
+    pub map: String,
+    pub game: String,
+    pub version: String,
+    pub players: u32,
+    pub max_players: u32,
+    pub password: bool,
+    pub access: String,
+}
+
+#[napi(object)]
+pub struct Player {
+    pub name: String,
+    pub score: Option<i64>,
+    pub duration_secs: Option<f64>,
+    pub ping_ms: Option<u32>,
+    pub team: Option<String>,
+    pub extra: HashMap<String, String>,
+}
+
+/// The response of a server.
+#[napi(object)]
+pub struct QueryResult {
+    pub game: Game,
+    /// The address that answered.
+    pub address: Option<String>,
+    pub latency_ms: f64,
+    pub info: ServerInfo,
+    pub players: Vec<Player>,
+    pub rules: HashMap<String, String>,
+}
+
+fn timeouts(timeout_ms: Option<u32>) -> TimeoutSettings {
+    TimeoutSettings::uniform(
+        timeout_ms.map_or(DEFAULT_TIMEOUT, |ms| Duration::from_millis(ms.into())),
+    )
+}
+
+/// Compiles a game script and makes its game available to `query`.
+///
+/// # Returns
+///
+/// The identifier of the game.
+#[napi]
+pub fn load_script(source: String) -> Result<String> {
+    let mut scripts = SCRIPTS.write().unwrap_or_else(|err| err.into_inner());
+    let game = scripts.load(&source).map_err(|err| {
+        Error::from_reason(
+            err.detail()
+                .inner()
+                .map_or_else(|| err.to_string(), ToString::to_string),
+        )
+    })?;
+
+    Ok(game.id().to_string())
+}
+
+/// Replaces the addresses that are never queried or connected to.
+///
+/// # Parameters
+///
+/// * `list`: An opt-out list, with one address or CIDR range per line and `#` starting a
+///   comment, or `null` for none.
+/// * `reserved`: Whether private, loopback and otherwise reserved ranges are blocked too,
+///   as they are until this is called.
+#[napi]
+pub fn set_blocklist(list: Option<String>, reserved: bool) -> Result<()> {
+    let mut blocklist = if reserved {
+        Blocklist::reserved()
+    } else {
+        Blocklist::new()
+    };
+
+    if let Some(list) = list {
+        blocklist
+            .extend_from_list(&list, Some("setBlocklist"))
+            .map_err(|err| Error::from_reason(err.to_string()))?;
+    }
+
+    *BLOCKLIST.write().unwrap_or_else(|err| err.into_inner()) = blocklist;
+
+    Ok(())
+}
+
+/// Returns a copy of the blocklist, so it isn't held during a query.
+fn blocklist() -> Blocklist {
+    BLOCKLIST
+        .read()
+        .unwrap_or_else(|err| err.into_inner())
+        .clone()
+}
+
+/// A game to query, with the scripted ones copied out of the registry so it isn't held
+/// during the query.
+enum QueriedGame {
+    /// A game loaded with `loadScript`.
+    Scripted(ScriptGame),
+    /// A game of the built-in registry queried natively.
+    Native(&'static GameEntry),
+}
+
+/// Looks up a loaded or native game, as every frontend does with `lookup::find_game`.
+fn find_game(identifier: &str) -> Result<QueriedGame> {
+    let scripts = SCRIPTS.read().unwrap_or_else(|err| err.into_inner());
+
+    match lookup::find_game(&scripts, identifier) {
+        Ok(QueryGame::Scripted(game)) => Ok(QueriedGame::Scripted(game.clone())),
+        Ok(QueryGame::Native(entry)) => Ok(QueriedGame::Native(entry)),
+        Err(err) => Err(Error::from_reason(err.to_string())),
+    }
+}
+
+/// Queries a server at each address its host resolves to until one answers, skipping the
+/// blocked ones.
+///
+/// # Parameters
+///
+/// * `game`: The identifier or an alias of a loaded or built-in game.
+/// * `host`: The host name or IP address of the server.
+/// * `port`: The query port, or the game's default if omitted.
+/// * `opts`: The options of the query.
+#[napi]
+pub async fn query(
+    game: String,
+    host: String,
+    port: Option<u16>,
+    opts: Option<QueryOptions>,
+) -> Result<QueryResult> {
+    let game = find_game(&game)?;
+    let game = match &game {
+        QueriedGame::Scripted(game) => QueryGame::Scripted(game),
+        QueriedGame::Native(entry) => QueryGame::Native(entry),
+    };
+    let target = HostPort::new(host, port).to_string();
+    let timeouts = timeouts(opts.and_then(|opts| opts.timeout_ms));
+    let blocklist = blocklist();
+    let fetched = match game {
+        QueryGame::Scripted(game) => Scripted(game)
+            .fetch_host_checked(
+                ScriptQuery,
+                &target,
+                &SystemResolver,
+                Some(&blocklist),
+                timeouts,
+            )
+            .await
+            .map(DynFetched::from)
+            .map_err(|err| Error::from_reason(format!("{target}: {err}")))?,
+        QueryGame::Native(_) => Minecraft
+            .fetch_host_checked(
+                SlpQuery::default(),
+                &target,
+                &SystemResolver,
+                Some(&blocklist),
+                timeouts,
+            )
+            .await
+            .map(DynFetched::from)
+            .map_err(|err| Error::from_reason(format!("{target}: {err}")))?,
+    };
+    let info = fetched.info;
+    let players = fetched
+        .players
+        .into_iter()
+        .map(|player| Player {
+            name: player.name,
+            score: player.score,
+            duration_secs: player.duration.map(|duration| duration.as_secs_f64()),
+            ping_ms: player.ping,
+            team: player.team,
+            extra: player.extra.into_iter().collect(),
+        })
+        .collect();
+
+    Ok(QueryResult {
+        game: Game {
+            id: game.id().to_string(),
+            name: game.name().to_string(),
+        },
+        address: fetched.meta.address().map(|address| address.to_string()),
+        latency_ms: fetched.meta.latency().as_secs_f64() * 1000.0,
+        rules: info.extra.clone().into_iter().collect(),
+        info: ServerInfo {
+            name: info.name,
+            map: info.map,
+            game: info.game,
+            version: info.version,
+            players: info.players,
+            max_players: info.max_players,
+            password: info.password,
+            access: info.access.to_string(),
+        },
+        players,
+    })
+}
+
+/// Logs in to a Source RCON console, runs a command and returns its output.
+///
+/// Blocked addresses of the host are skipped like those of `query`.
+///
+/// # Parameters
+///
+/// * `host`: The host name or IP address of the server.
+/// * `port`: The port of the console.
+/// * `password`: The RCON password.
+/// * `command`: The command to run.
+/// * `opts`: The options of the connection.
+#[napi]
+pub async fn rcon(
+    host: String,
+    port: u16,
+    password: String,
+    command: String,
+    opts: Option<RconOptions>,
+) -> Result<String> {
+    let target = HostPort::new(host, Some(port));
+    let timeouts = timeouts(opts.and_then(|opts| opts.timeout_ms));
+    let failed = |err: &dyn std::fmt::Display| Error::from_reason(format!("{target}: {err}"));
+    let resolved = resolve(&SystemResolver, &target, None)
+        .await
+        .map_err(|err| failed(&err))?;
+    let blocklist = blocklist();
+    let (mut last, mut blocked) = (None, None);
+
+    for address in resolved.addresses {
+        if let Err(refused) = blocklist.check(address) {
+            blocked = Some(refused);
+            continue;
+        }
+
+        let mut console = match SourceRcon::connect(address, &timeouts).await {
+            Ok(console) => console,
+            Err(err) => {
+                last = Some(err);
+                continue;
+            }
+        };
+
+        console
+            .authenticate(&password, &timeouts)
+            .await
+            .map_err(|err| failed(&err))?;
+
+        let output = console
+            .exec(&command, &timeouts)
+            .await
+            .map_err(|err| failed(&err))?;
+        let _ = console.close().await;
+
+        return Ok(output);
+    }
+
+    Err(match (last, blocked) {
+        (Some(err), _) => failed(&err),
+        (None, Some(blocked)) => failed(&blocked),
+        (None, None) => Error::from_reason(format!("{target} did not resolve to any address")),
+    })
+}
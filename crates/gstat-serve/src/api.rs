@@ -0,0 +1,503 @@
+#[cfg(feature = "graphql")]
+use gstat_core::prelude::ResponseMeta;
+use gstat_core::{
+    badge::Badge,
+    blocklist::Blocklist,
+    cache::{CacheStatus, Cached, CachedGame},
+    document,
+    encode::{self, Encode},
+    etag::ETag,
+    prelude::{
+        Error, ErrorKind, Game, Player, Protocol, Response as _, ServerInfo, TimeoutSettings, Value,
+    },
+    rate_limit::RateLimiter,
+    resolve::{HostPort, SystemResolver},
+    script::{ScriptRegistry, ScriptResponse},
+    standards::dyn_protocol::BoxFuture,
+};
+use gstat_tcp::{
+    lookup::{self, LookupError, QueryGame},
+    slp::{Minecraft, SlpProtocol, SlpResponse},
+    TcpError,
+};
+use gstat_udp::{
+    script::{ScriptProtocol, Scripted},
+    UdpError,
+};
+
+use std::{
+    collections::HashMap, error::Error as StdError, net::SocketAddr, sync::Arc, time::Duration,
+};
+
+use axum::{
+    extract::{ConnectInfo, Path, Query, State},
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+    routing::get,
+    Json, Router,
+};
+use serde::Deserialize;
+use serde_json::json;
+
+/// How the responses of every game are cached.
+#[derive(Clone, Copy, Debug)]
+pub struct CacheSettings {
+    /// How long a response is served from the cache.
+    pub ttl: Duration,
+    /// How long after its TTL a response is still served while it is refreshed.
+    pub stale_while_revalidate: Duration,
+    /// The time limits of the queries sent on a miss.
+    pub timeouts: TimeoutSettings,
+}
+
+impl CacheSettings {
+    /// Wraps `game` in a cache with these settings.
+    fn cache<G, P, R, E>(&self, game: G) -> CachedGame<G, P>
+    where
+        G: for<'a> Game<'a, P> + Send + Sync + 'static,
+        P: for<'a> Protocol<'a, R = R, E = E>,
+        R: Send + Sync + 'static,
+        E: Send + Sync + 'static,
+    {
+        CachedGame::builder(game, self.ttl)
+            .with_stale_while_revalidate(self.stale_while_revalidate)
+            .with_timeouts(self.timeouts)
+            .build()
+    }
+}
+
+/// The state shared by every request.
+pub struct Api {
+    scripts: &'static ScriptRegistry,
+    caches: HashMap<String, CachedGame<Scripted<'static>, ScriptProtocol>>,
+    /// The cache of the games `gstat-tcp` queries natively, keyed like their responses by
+    /// server address.
+    native: CachedGame<Minecraft, SlpProtocol>,
+    api_keys: Vec<String>,
+    limiter: Option<RateLimiter>,
+    blocklist: Blocklist,
+    #[cfg(feature = "graphql")]
+    schema: crate::graphql::GstatSchema,
+    #[cfg(feature = "graphql")]
+    max_targets: usize,
+}
+
+impl Api {
+    /// Creates the API over the loaded games.
+    ///
+    /// # Parameters
+    ///
+    /// * `scripts`: The scripted games that can be queried besides the native ones.
+    /// * `cache`: How the responses of every game are cached.
+    /// * `api_keys`: The keys clients must present, or none to leave the API open.
+    /// * `limiter`: Limits the requests of each client subnet, if set.
+    /// * `blocklist`: The servers that are never queried.
+    pub fn new(
+        scripts: &'static ScriptRegistry,
+        cache: CacheSettings,
+        api_keys: Vec<String>,
+        limiter: Option<RateLimiter>,
+        blocklist: Blocklist,
+    ) -> Self {
+        let caches = scripts
+            .iter()
+            .map(|game| (game.id().to_string(), cache.cache(Scripted(game))))
+            .collect();
+
+        Api {
+            scripts,
+            caches,
+            native: cache.cache(Minecraft),
+            api_keys,
+            limiter,
+            blocklist,
+            #[cfg(feature = "graphql")]
+            schema: crate::graphql::schema(),
+            #[cfg(feature = "graphql")]
+            max_targets: crate::config::DEFAULT_MAX_TARGETS,
+        }
+    }
+
+    /// Sets the most servers one `servers` field of a GraphQL query may ask for.
+    #[cfg(feature = "graphql")]
+    pub fn with_max_targets(mut self, max_targets: usize) -> Self {
+        self.max_targets = max_targets;
+        self
+    }
+
+    /// Removes the responses that can no longer be served from the cache of every game.
+    pub fn purge(&self) {
+        for cache in self.caches.values() {
+            cache.purge();
+        }
+
+        self.native.purge();
+    }
+
+    /// Returns the routes of the API.
+    pub fn router(self: Arc<Self>) -> Router {
+        let router = Router::new()
+            .route("/v1/query/{game}/{address}", get(query))
+            .route("/v1/badge/{game}/{address}", get(badge));
+
+        #[cfg(feature = "graphql")]
+        let router = router.route(
+            "/v1/graphql",
+            get(crate::graphql::sdl).post(crate::graphql::execute),
+        );
+
+        router.with_state(self)
+    }
+
+    /// Returns the GraphQL schema served at `/v1/graphql`.
+    #[cfg(feature = "graphql")]
+    pub(crate) fn schema(&self) -> &crate::graphql::GstatSchema {
+        &self.schema
+    }
+
+    /// Returns the most servers one `servers` field of a GraphQL query may ask for.
+    #[cfg(feature = "graphql")]
+    pub(crate) fn max_targets(&self) -> usize {
+        self.max_targets
+    }
+
+    /// Takes one rate limit permit of a client per server a request asks for, on top of
+    /// the permit `refusal` took for the request itself.
+    ///
+    /// # Returns
+    ///
+    /// `Ok` if the permits were taken, or how long until they are available.
+    #[cfg(feature = "graphql")]
+    pub(crate) fn charge(
+        &self,
+        client: SocketAddr,
+        servers: usize,
+    ) -> Result<(), std::time::Duration> {
+        match &self.limiter {
+            Some(limiter) => {
+                limiter.try_acquire_many(client, u32::try_from(servers).unwrap_or(u32::MAX))
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// Checks the API key of a request and takes its client's rate limit permit if one is
+    /// available.
+    ///
+    /// # Returns
+    ///
+    /// The response to answer with instead if the request is refused.
+    pub(crate) fn refusal(&self, client: SocketAddr, headers: &HeaderMap) -> Option<Response> {
+        if !self.authorized(headers) {
+            return Some(
+                Failure(
+                    StatusCode::UNAUTHORIZED,
+                    "missing or unknown API key".into(),
+                )
+                .into_response(),
+            );
+        }
+
+        if let Some(limiter) = &self.limiter {
+            // Refused requests take no permit, so clients retrying after `Retry-After`
+            // aren't refused again for the requests they were refused for.
+            if let Err(wait) = limiter.try_acquire(client) {
+                let mut response =
+                    Failure(StatusCode::TOO_MANY_REQUESTS, "too many requests".into())
+                        .into_response();
+                response.headers_mut().insert(
+                    header::RETRY_AFTER,
+                    HeaderValue::from(wait.as_secs_f64().ceil() as u64),
+                );
+
+                return Some(response);
+            }
+        }
+
+        None
+    }
+
+    /// Checks the API key of a request, taken from `Authorization: Bearer` or
+    /// `X-Api-Key`.
+    fn authorized(&self, headers: &HeaderMap) -> bool {
+        if self.api_keys.is_empty() {
+            return true;
+        }
+
+        let bearer = headers
+            .get(header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "));
+        let header = headers
+            .get("x-api-key")
+            .and_then(|value| value.to_str().ok());
+
+        // Every presented key is compared with every configured one, so the time taken
+        // doesn't reveal which key matched or how much of it did.
+        [bearer, header]
+            .into_iter()
+            .flatten()
+            .flat_map(|key| self.api_keys.iter().map(move |allowed| (key, allowed)))
+            .fold(false, |found, (key, allowed)| {
+                found | same_key(key.as_bytes(), allowed.as_bytes())
+            })
+    }
+}
+
+/// Compares a presented API key with a configured one in constant time.
+///
+/// The time taken depends only on the length of `allowed`, not on where the keys differ.
+fn same_key(presented: &[u8], allowed: &[u8]) -> bool {
+    let mut difference = presented.len() ^ allowed.len();
+
+    for (index, &byte) in allowed.iter().enumerate() {
+        let other = presented.get(index).copied().unwrap_or_default();
+        difference |= usize::from(byte ^ other);
+    }
+
+    difference == 0
+}
+
+/// An error answered with its status and a JSON body.
+pub(crate) struct Failure(pub StatusCode, pub String);
+
+impl IntoResponse for Failure {
+    fn into_response(self) -> Response {
+        (self.0, Json(json!({ "error": self.1 }))).into_response()
+    }
+}
+
+/// The query parameters of `GET /v1/query`.
+#[derive(Debug, Default, Deserialize)]
+struct Conditional {
+    /// The tags of responses the client already holds, like `If-None-Match`.
+    if_changed_since: Option<String>,
+}
+
+/// Serves `GET /v1/query/{game}/{host}:{port}`.
+///
+/// Every response carries the `ETag` of what the server reported. Clients sending one of
+/// their tags in `If-None-Match` or the `if_changed_since` parameter are answered with 304
+/// while the server reports the same.
+async fn query(
+    State(api): State<Arc<Api>>,
+    ConnectInfo(client): ConnectInfo<SocketAddr>,
+    Path((identifier, address)): Path<(String, String)>,
+    Query(conditional): Query<Conditional>,
+    headers: HeaderMap,
+) -> Response {
+    if let Some(response) = api.refusal(client, &headers) {
+        return response;
+    }
+
+    match fetch(&api, &identifier, &address).await {
+        Ok(served) => {
+            let status = match served.status() {
+                CacheStatus::Hit => "HIT",
+                CacheStatus::Stale => "STALE",
+                CacheStatus::Miss => "MISS",
+            };
+            let etag = ETag::of(&served.info(), &served.players());
+            let unchanged = headers
+                .get(header::IF_NONE_MATCH)
+                .and_then(|value| value.to_str().ok())
+                .into_iter()
+                .chain(conditional.if_changed_since.as_deref())
+                .any(|tags| etag.matches_any(tags));
+
+            let headers = [
+                (header::AGE, served.age().as_secs().to_string()),
+                (header::HeaderName::from_static("x-cache"), status.into()),
+                (header::ETAG, etag.to_string()),
+            ];
+
+            if unchanged {
+                return (StatusCode::NOT_MODIFIED, headers).into_response();
+            }
+
+            (
+                headers,
+                [(header::CONTENT_TYPE, encode::Json.content_type())],
+                encode::Json.encode(&served.document()),
+            )
+                .into_response()
+        }
+        Err(failure) => failure.into_response(),
+    }
+}
+
+/// Serves `GET /v1/badge/{game}/{host}:{port}`, an SVG status badge for READMEs and forums.
+///
+/// Servers that don't answer get an offline badge; other failures, such as an unknown
+/// game, are answered like `/v1/query` does.
+async fn badge(
+    State(api): State<Arc<Api>>,
+    ConnectInfo(client): ConnectInfo<SocketAddr>,
+    Path((identifier, address)): Path<(String, String)>,
+    headers: HeaderMap,
+) -> Response {
+    if let Some(response) = api.refusal(client, &headers) {
+        return response;
+    }
+
+    let badge = match fetch(&api, &identifier, &address).await {
+        Ok(served) => Badge::online(&served.info()),
+        Err(failure) if failure.0 == StatusCode::BAD_GATEWAY => Badge::offline(),
+        Err(failure) => return failure.into_response(),
+    };
+
+    (
+        [
+            (header::CONTENT_TYPE, "image/svg+xml"),
+            (header::CACHE_CONTROL, "no-cache"),
+        ],
+        badge.to_svg(),
+    )
+        .into_response()
+}
+
+/// A response of a scripted or native game, as answered by the cache of the game.
+#[derive(Clone)]
+pub(crate) struct Served {
+    /// The game the server runs.
+    pub game: QueryGame<'static>,
+    answer: Answer,
+}
+
+/// The cached response of either kind of game.
+#[derive(Clone)]
+enum Answer {
+    Scripted(Cached<ScriptResponse, UdpError>),
+    Native(Cached<SlpResponse, TcpError>),
+}
+
+impl Served {
+    /// Returns what the server reports in common with every other game.
+    pub(crate) fn info(&self) -> ServerInfo {
+        match &self.answer {
+            Answer::Scripted(cached) => cached.fetched.response.to_common(),
+            Answer::Native(cached) => cached.fetched.response.to_common(),
+        }
+    }
+
+    /// Returns the players on the server.
+    pub(crate) fn players(&self) -> Vec<Player> {
+        match &self.answer {
+            Answer::Scripted(cached) => cached.fetched.response.players(),
+            Answer::Native(cached) => cached.fetched.response.players(),
+        }
+    }
+
+    /// Returns how the response was received.
+    #[cfg(feature = "graphql")]
+    pub(crate) fn meta(&self) -> &ResponseMeta {
+        match &self.answer {
+            Answer::Scripted(cached) => &cached.fetched.meta,
+            Answer::Native(cached) => &cached.fetched.meta,
+        }
+    }
+
+    /// Returns how long ago the response was received.
+    pub(crate) fn age(&self) -> Duration {
+        match &self.answer {
+            Answer::Scripted(cached) => cached.age,
+            Answer::Native(cached) => cached.age,
+        }
+    }
+
+    /// Returns how the response was answered by the cache.
+    pub(crate) fn status(&self) -> CacheStatus {
+        match &self.answer {
+            Answer::Scripted(cached) => cached.status,
+            Answer::Native(cached) => cached.status,
+        }
+    }
+
+    /// Returns the response as the document of `gstat query --format json`.
+    fn document(&self) -> Value {
+        let (id, name) = (self.game.id(), self.game.name());
+
+        match &self.answer {
+            Answer::Scripted(cached) => document::document(id, name, &cached.fetched),
+            Answer::Native(cached) => document::document(id, name, &cached.fetched),
+        }
+    }
+}
+
+/// Queries a server through the cache of its game, at each address its host resolves to
+/// until one answers.
+///
+/// Games are looked up like every frontend does, with `lookup::find_game`. Addresses
+/// refused by the blocklist are skipped; if every address is, the request is answered
+/// with 403.
+pub(crate) async fn fetch(api: &Api, identifier: &str, address: &str) -> Result<Served, Failure> {
+    let game = lookup::find_game(api.scripts, identifier).map_err(|err| {
+        let status = match err {
+            LookupError::UnknownGame(_) => StatusCode::NOT_FOUND,
+            _ => StatusCode::NOT_IMPLEMENTED,
+        };
+
+        Failure(status, err.to_string())
+    })?;
+
+    address
+        .parse::<HostPort>()
+        .map_err(|err| Failure(StatusCode::BAD_REQUEST, format!("invalid address: {err}")))?;
+
+    let answer = match game {
+        QueryGame::Scripted(scripted) => {
+            Answer::Scripted(fetch_cached(api, &api.caches[scripted.id()], address).await?)
+        }
+        QueryGame::Native(_) => Answer::Native(fetch_cached(api, &api.native, address).await?),
+    };
+
+    Ok(Served { game, answer })
+}
+
+/// Queries a server through `cache`, at each address its host resolves to until one
+/// answers.
+///
+/// The future is boxed so the handlers can be shown to be `Send` for either game.
+fn fetch_cached<'f, G, P, R, E>(
+    api: &'f Api,
+    cache: &'f CachedGame<G, P>,
+    address: &'f str,
+) -> BoxFuture<'f, Result<Cached<R, E>, Failure>>
+where
+    G: for<'a> Game<'a, P> + Send + Sync + 'static,
+    P: for<'a> Protocol<'a, R = R, E = E>,
+    R: Send + Sync + 'static,
+    E: StdError + Send + Sync + 'static,
+{
+    Box::pin(async move {
+        let resolved = cache
+            .game()
+            .resolve_host(
+                address,
+                &SystemResolver,
+                Some(&api.blocklist),
+                cache.timeouts(),
+            )
+            .await
+            .map_err(|err| match err.kind() {
+                ErrorKind::Blocked => Failure(StatusCode::FORBIDDEN, err.detail().message().into()),
+                _ => Failure(StatusCode::BAD_GATEWAY, format!("{address}: {err}")),
+            })?;
+        let mut last: Option<Error<E>> = None;
+
+        for address in resolved.addresses {
+            match cache.fetch(address).await {
+                Ok(cached) => return Ok(cached),
+                Err(err) => last = Some(err),
+            }
+        }
+
+        Err(match last {
+            Some(err) => Failure(StatusCode::BAD_GATEWAY, format!("{address}: {err}")),
+            None => Failure(
+                StatusCode::BAD_GATEWAY,
+                format!("{address} did not resolve to any address"),
+            ),
+        })
+    })
+}
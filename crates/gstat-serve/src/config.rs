@@ -0,0 +1,185 @@
+use gstat_core::{blocklist::Blocklist, duration, prelude::TimeoutSettings, rate_limit::RateLimit};
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use serde::Deserialize;
+
+/// How long a response is served from the cache when the file sets no TTL.
+pub const DEFAULT_TTL: Duration = Duration::from_secs(10);
+
+/// The limit applied to every step of a query when the file sets none.
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The most servers one `servers` field of a GraphQL query may ask for when the file sets
+/// no limit.
+#[cfg(feature = "graphql")]
+pub const DEFAULT_MAX_TARGETS: usize = 100;
+
+/// How long responses are cached.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CacheConfig {
+    /// How long a response is served from the cache, e.g. `"10s"`.
+    #[serde(default, deserialize_with = "duration::deserialize_optional")]
+    pub ttl: Option<Duration>,
+    /// How long after its TTL a response is still served while it is refreshed.
+    #[serde(default, deserialize_with = "duration::deserialize_optional")]
+    pub stale_while_revalidate: Option<Duration>,
+}
+
+/// How many requests each client may make.
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RateLimitConfig {
+    /// The sustained number of requests per second of each client subnet.
+    pub requests_per_second: f64,
+    /// The number of requests a client may make at once after a quiet period.
+    pub burst: u32,
+}
+
+impl RateLimitConfig {
+    /// Returns the limit applied to each client subnet.
+    pub fn limit(&self) -> RateLimit {
+        RateLimit::new(self.requests_per_second, self.burst)
+    }
+}
+
+/// Which servers may never be queried, written as a `[blocklist]` table.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BlocklistConfig {
+    /// Whether private, loopback and otherwise reserved ranges are blocked, which keeps
+    /// clients from probing the network the service runs in. On by default.
+    #[serde(default = "enabled")]
+    pub reserved: bool,
+    /// Opt-out lists of addresses and ranges, relative to the file.
+    #[serde(default)]
+    pub lists: Vec<PathBuf>,
+}
+
+impl Default for BlocklistConfig {
+    fn default() -> Self {
+        BlocklistConfig {
+            reserved: true,
+            lists: Vec::new(),
+        }
+    }
+}
+
+fn enabled() -> bool {
+    true
+}
+
+/// The limits of the GraphQL endpoint, written as a `[graphql]` table.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct GraphqlConfig {
+    /// The most servers one `servers` field may ask for.
+    pub max_targets: Option<usize>,
+}
+
+impl GraphqlConfig {
+    /// Returns the most servers one `servers` field may ask for.
+    #[cfg(feature = "graphql")]
+    pub fn max_targets(&self) -> usize {
+        self.max_targets.unwrap_or(DEFAULT_MAX_TARGETS)
+    }
+}
+
+/// The contents of the configuration file.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// The limit applied to every step of a query, e.g. `"5s"`.
+    #[serde(default, deserialize_with = "duration::deserialize_optional")]
+    pub timeout: Option<Duration>,
+    /// The game scripts to load, relative to the file.
+    #[serde(default)]
+    pub scripts: Vec<PathBuf>,
+    /// The keys clients must present. The API is open if none are set.
+    #[serde(default)]
+    pub api_keys: Vec<String>,
+    /// How long responses are cached.
+    #[serde(default)]
+    pub cache: CacheConfig,
+    /// How many requests each client may make, unlimited if unset.
+    pub rate_limit: Option<RateLimitConfig>,
+    /// Which servers may never be queried.
+    #[serde(default)]
+    pub blocklist: BlocklistConfig,
+    /// The limits of the GraphQL endpoint.
+    #[serde(default)]
+    pub graphql: GraphqlConfig,
+}
+
+impl Config {
+    /// Loads the configuration file.
+    ///
+    /// Script and blocklist paths are resolved against the directory of the file. Without a file, the
+    /// defaults apply.
+    ///
+    /// # Parameters
+    ///
+    /// * `path`: The path of the file, if one was given.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing either the configuration or a description of why it couldn't
+    /// be read.
+    pub fn load(path: Option<&Path>) -> Result<Self, String> {
+        let Some(path) = path else {
+            return Ok(Config::default());
+        };
+
+        let source = fs::read_to_string(path)
+            .map_err(|err| format!("failed to read {}: {err}", path.display()))?;
+        let mut config: Config =
+            toml::from_str(&source).map_err(|err| format!("invalid {}: {err}", path.display()))?;
+
+        let base = path.parent().unwrap_or(Path::new(""));
+        config.scripts = config
+            .scripts
+            .into_iter()
+            .map(|script| base.join(script))
+            .collect();
+        config.blocklist.lists = config
+            .blocklist
+            .lists
+            .into_iter()
+            .map(|list| base.join(list))
+            .collect();
+
+        Ok(config)
+    }
+
+    /// Builds the blocklist, loading its opt-out lists.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing either the addresses that are never queried or a description
+    /// of why a list couldn't be read.
+    pub fn blocklist(&self) -> Result<Blocklist, String> {
+        let mut blocklist = if self.blocklist.reserved {
+            Blocklist::reserved()
+        } else {
+            Blocklist::new()
+        };
+
+        for path in &self.blocklist.lists {
+            blocklist
+                .load(path)
+                .map_err(|err| format!("{}: {err}", path.display()))?;
+        }
+
+        Ok(blocklist)
+    }
+
+    /// Returns the time limits applied to each query.
+    pub fn timeouts(&self) -> TimeoutSettings {
+        TimeoutSettings::uniform(self.timeout.unwrap_or(DEFAULT_TIMEOUT))
+    }
+}
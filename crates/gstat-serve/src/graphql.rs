@@ -0,0 +1,331 @@
+//! The GraphQL schema served at `/v1/graphql`, over the same games and caches as the REST
+//! routes.
+//!
+//! Servers are looked up through a per-request `DataLoader`, so every `server` and `servers`
+//! field of one query is collected into a single batch and queried concurrently by
+//! `QueryMany`. Fields are only computed when selected, which lets dashboards fetch just the
+//! player count of a hundred servers without transferring their rules and player lists.
+
+use crate::api::{fetch, Api, Served};
+
+use gstat_core::{bulk::QueryMany, cache::CacheStatus};
+
+use std::{collections::HashMap, convert::Infallible, net::SocketAddr, sync::Arc};
+
+use async_graphql::{
+    dataloader::{DataLoader, Loader},
+    Context, EmptyMutation, EmptySubscription, Enum, InputObject, Object, Schema, SimpleObject,
+};
+use axum::{
+    extract::{ConnectInfo, State},
+    http::HeaderMap,
+    response::{IntoResponse, Response},
+    Json,
+};
+
+/// The most servers of one batch queried at once.
+const BATCH_CONCURRENCY: usize = 64;
+
+/// The schema served at `/v1/graphql`.
+pub type GstatSchema = Schema<QueryRoot, EmptyMutation, EmptySubscription>;
+
+/// Builds the schema.
+pub fn schema() -> GstatSchema {
+    Schema::new(QueryRoot, EmptyMutation, EmptySubscription)
+}
+
+/// Serves `GET /v1/graphql`, answering with the schema in the GraphQL SDL.
+pub async fn sdl(State(api): State<Arc<Api>>) -> String {
+    api.schema().sdl()
+}
+
+/// Serves `POST /v1/graphql`, executing the query of a JSON GraphQL request.
+pub async fn execute(
+    State(api): State<Arc<Api>>,
+    ConnectInfo(client): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    Json(request): Json<async_graphql::Request>,
+) -> Response {
+    if let Some(response) = api.refusal(client, &headers) {
+        return response;
+    }
+
+    let servers = DataLoader::new(
+        Servers {
+            api: api.clone(),
+            client,
+        },
+        tokio::spawn,
+    );
+
+    Json(api.schema().execute(request.data(servers)).await).into_response()
+}
+
+/// A server to query.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, SimpleObject, InputObject)]
+#[graphql(input_name = "TargetInput")]
+pub struct Target {
+    /// The identifier of the game the server runs.
+    pub game: String,
+    /// The server address, as `host` or `host:port`.
+    pub address: String,
+}
+
+/// Queries the batched targets of a request through the caches of their games.
+///
+/// Each batch takes one rate limit permit of the client per target, so a query asking for
+/// many servers costs as much as as many REST requests.
+pub struct Servers {
+    api: Arc<Api>,
+    client: SocketAddr,
+}
+
+impl Loader<Target> for Servers {
+    type Value = Result<Server, String>;
+    type Error = Infallible;
+
+    async fn load(&self, keys: &[Target]) -> Result<HashMap<Target, Self::Value>, Infallible> {
+        let api = &*self.api;
+
+        if let Err(wait) = api.charge(self.client, keys.len()) {
+            let refused = format!(
+                "too many requests; retry in {}s",
+                wait.as_secs_f64().ceil() as u64
+            );
+
+            return Ok(keys
+                .iter()
+                .map(|target| (target.clone(), Err(refused.clone())))
+                .collect());
+        }
+
+        let mut results = QueryMany::new(keys.to_vec(), BATCH_CONCURRENCY, |target: &Target| {
+            let target = target.clone();
+
+            async move { fetch(api, &target.game, &target.address).await }
+        });
+        let mut servers = HashMap::with_capacity(keys.len());
+
+        while let Some((target, result)) = results.next().await {
+            let server = result
+                .map(|served| Server { served })
+                .map_err(|failure| failure.1);
+
+            servers.insert(target, server);
+        }
+
+        Ok(servers)
+    }
+}
+
+pub struct QueryRoot;
+
+#[Object]
+impl QueryRoot {
+    /// Queries one server, failing the field if it doesn't answer.
+    async fn server(
+        &self,
+        context: &Context<'_>,
+        game: String,
+        address: String,
+    ) -> async_graphql::Result<Server> {
+        let Ok(server) = context
+            .data_unchecked::<DataLoader<Servers>>()
+            .load_one(Target { game, address })
+            .await;
+
+        match server {
+            Some(server) => server.map_err(Into::into),
+            None => Err("the server was not queried".into()),
+        }
+    }
+
+    /// Queries many servers, reporting each failure alongside its target.
+    ///
+    /// Fails the field if it asks for more servers than the service allows.
+    async fn servers(
+        &self,
+        context: &Context<'_>,
+        targets: Vec<Target>,
+    ) -> async_graphql::Result<Vec<TargetResult>> {
+        let loader = context.data_unchecked::<DataLoader<Servers>>();
+        let max_targets = loader.loader().api.max_targets();
+
+        if targets.len() > max_targets {
+            return Err(format!(
+                "{} targets exceed the limit of {max_targets}",
+                targets.len()
+            )
+            .into());
+        }
+
+        let Ok(mut servers) = loader.load_many(targets.iter().cloned()).await;
+
+        Ok(targets
+            .into_iter()
+            .map(|target| {
+                let outcome = servers
+                    .remove(&target)
+                    .unwrap_or_else(|| Err("the server was not queried".into()));
+
+                TargetResult { target, outcome }
+            })
+            .collect())
+    }
+}
+
+/// The outcome of querying one of the targets of `servers`.
+pub struct TargetResult {
+    target: Target,
+    outcome: Result<Server, String>,
+}
+
+#[Object]
+impl TargetResult {
+    async fn target(&self) -> &Target {
+        &self.target
+    }
+
+    /// The server, if it answered.
+    async fn server(&self) -> Option<&Server> {
+        self.outcome.as_ref().ok()
+    }
+
+    /// Why the query failed, if it did.
+    async fn error(&self) -> Option<&str> {
+        self.outcome.as_ref().err().map(String::as_str)
+    }
+}
+
+/// A server that answered, possibly from the cache.
+#[derive(Clone)]
+pub struct Server {
+    served: Served,
+}
+
+#[Object]
+impl Server {
+    /// The game the server runs.
+    async fn game(&self) -> Game {
+        Game {
+            id: self.served.game.id().to_string(),
+            name: self.served.game.name().to_string(),
+        }
+    }
+
+    /// The address that answered.
+    async fn address(&self) -> Option<String> {
+        self.served
+            .meta()
+            .address()
+            .map(|address| address.to_string())
+    }
+
+    /// The round-trip time of the query, in milliseconds.
+    async fn latency_ms(&self) -> f64 {
+        self.served.meta().latency().as_secs_f64() * 1000.0
+    }
+
+    /// How the response was answered by the cache.
+    async fn cache(&self) -> Cache {
+        match self.served.status() {
+            CacheStatus::Hit => Cache::Hit,
+            CacheStatus::Stale => Cache::Stale,
+            CacheStatus::Miss => Cache::Miss,
+        }
+    }
+
+    /// How long ago the response was received, in seconds.
+    async fn age_secs(&self) -> f64 {
+        self.served.age().as_secs_f64()
+    }
+
+    /// What the server reports in common with every other game.
+    async fn info(&self) -> Info {
+        let info = self.served.info();
+
+        Info {
+            name: info.name,
+            map: info.map,
+            game: info.game,
+            version: info.version,
+            players: info.players,
+            max_players: info.max_players,
+            password: info.password,
+            access: info.access.to_string(),
+        }
+    }
+
+    /// The players on the server.
+    async fn players(&self) -> Vec<Player> {
+        self.served
+            .players()
+            .into_iter()
+            .map(|player| Player {
+                name: player.name,
+                score: player.score,
+                duration_secs: player.duration.map(|duration| duration.as_secs_f64()),
+                ping_ms: player.ping,
+                team: player.team,
+                extra: entries(player.extra),
+            })
+            .collect()
+    }
+
+    /// The game-specific details of the server.
+    async fn rules(&self) -> Vec<Entry> {
+        entries(self.served.info().extra)
+    }
+}
+
+/// How a response was answered by the cache.
+#[derive(Clone, Copy, PartialEq, Eq, Enum)]
+pub enum Cache {
+    /// The response was cached and within its time-to-live.
+    Hit,
+    /// The response had outlived its time-to-live and is being refreshed.
+    Stale,
+    /// The server was queried.
+    Miss,
+}
+
+#[derive(SimpleObject)]
+pub struct Game {
+    id: String,
+    name: String,
+}
+
+#[derive(SimpleObject)]
+pub struct Info {
+    name: String,
+    map: String,
+    game: String,
+    version: String,
+    players: u32,
+    max_players: u32,
+    password: bool,
+    access: String,
+}
+
+#[derive(SimpleObject)]
+pub struct Player {
+    name: String,
+    score: Option<i64>,
+    duration_secs: Option<f64>,
+    ping_ms: Option<u32>,
+    team: Option<String>,
+    extra: Vec<Entry>,
+}
+
+/// A key and its value, as GraphQL has no map type.
+#[derive(SimpleObject)]
+pub struct Entry {
+    key: String,
+    value: String,
+}
+
+fn entries(map: impl IntoIterator<Item = (String, String)>) -> Vec<Entry> {
+    map.into_iter()
+        .map(|(key, value)| Entry { key, value })
+        .collect()
+}
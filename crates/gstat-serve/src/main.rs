@@ -0,0 +1,120 @@
+mod api;
+mod config;
+#[cfg(feature = "graphql")]
+mod graphql;
+
+use api::{Api, CacheSettings};
+use config::{Config, DEFAULT_TTL};
+
+use gstat_core::{rate_limit::RateLimiter, script::ScriptRegistry};
+
+use std::{fs, net::SocketAddr, path::PathBuf, process::ExitCode, sync::Arc, time::Duration};
+
+use clap::Parser;
+use tokio::net::TcpListener;
+
+/// The shortest time between two purges of the caches.
+const MIN_PURGE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Serve game server queries over HTTP.
+#[derive(Debug, Parser)]
+#[command(name = "gstat-serve", version)]
+struct Args {
+    /// The configuration file: scripts, API keys, caching and rate limits.
+    #[arg(long, env = "GSTAT_SERVE_CONFIG", value_name = "PATH")]
+    config: Option<PathBuf>,
+
+    /// The address the API is served on.
+    #[arg(long, env = "GSTAT_SERVE_LISTEN", default_value = "0.0.0.0:8080")]
+    listen: SocketAddr,
+
+    /// A game script to load besides those of the configuration file. May be repeated.
+    #[arg(long = "script", value_name = "PATH")]
+    scripts: Vec<PathBuf>,
+}
+
+fn main() -> ExitCode {
+    match run(Args::parse()) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("gstat-serve: {err}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(args: Args) -> Result<(), String> {
+    let config = Config::load(args.config.as_deref())?;
+    let mut scripts = ScriptRegistry::new();
+
+    for path in config.scripts.iter().chain(&args.scripts) {
+        let source = fs::read_to_string(path)
+            .map_err(|err| format!("failed to read {}: {err}", path.display()))?;
+
+        scripts
+            .load(&source)
+            .map_err(|err| format!("invalid script {}: {err}", path.display()))?;
+    }
+
+    // The games are shared by every request until the process exits.
+    let scripts: &'static ScriptRegistry = Box::leak(Box::new(scripts));
+    let timeouts = config.timeouts();
+    let ttl = config.cache.ttl.unwrap_or(DEFAULT_TTL);
+    let stale = config.cache.stale_while_revalidate.unwrap_or_default();
+    let blocklist = config.blocklist()?;
+    let limiter = config
+        .rate_limit
+        .map(|limit| RateLimiter::new().with_per_subnet(limit.limit()));
+
+    let api = Api::new(
+        scripts,
+        CacheSettings {
+            ttl,
+            stale_while_revalidate: stale,
+            timeouts,
+        },
+        config.api_keys,
+        limiter,
+        blocklist,
+    );
+    #[cfg(feature = "graphql")]
+    let api = api.with_max_targets(config.graphql.max_targets());
+    let api = Arc::new(api);
+
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()
+        .map_err(|err| format!("failed to start the runtime: {err}"))?;
+
+    runtime.block_on(async {
+        let listener = TcpListener::bind(args.listen)
+            .await
+            .map_err(|err| format!("failed to listen on {}: {err}", args.listen))?;
+        let local = listener.local_addr().map_err(|err| err.to_string())?;
+
+        // Responses are only replaced when their server is queried again, so those of
+        // servers nobody asks for anymore are purged once they can't be served.
+        let purged = api.clone();
+        tokio::spawn(async move {
+            let interval = (ttl + stale).max(MIN_PURGE_INTERVAL);
+
+            loop {
+                tokio::time::sleep(interval).await;
+                purged.purge();
+            }
+        });
+
+        eprintln!(
+            "gstat-serve: serving {} scripted games on http://{local}/v1/",
+            scripts.len()
+        );
+
+        axum::serve(
+            listener,
+            api.router()
+                .into_make_service_with_connect_info::<SocketAddr>(),
+        )
+        .await
+        .map_err(|err| err.to_string())
+    })
+}
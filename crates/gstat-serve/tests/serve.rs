@@ -0,0 +1,376 @@
+//! Querying servers through `gstat-serve`.
+
+use gstat_test::{loopback_server, SOURCE_INFO_GAME, SOURCE_INFO_PACKET, SOURCE_INFO_SCRIPT_PATH};
+
+use std::{
+    fs,
+    io::{BufRead, BufReader, Read, Write},
+    net::{Ipv4Addr, SocketAddr, TcpListener, TcpStream, UdpSocket},
+    path::PathBuf,
+    process::{Child, Command, Stdio},
+    sync::atomic::{AtomicUsize, Ordering},
+    thread,
+};
+
+/// Numbers the configuration files written by the tests of this process.
+static CONFIGS: AtomicUsize = AtomicUsize::new(0);
+
+/// Kills the service when the test ends.
+struct Service(Child, SocketAddr);
+
+impl Drop for Service {
+    fn drop(&mut self) {
+        let _ = self.0.kill();
+        let _ = self.0.wait();
+    }
+}
+
+/// Starts the service on an ephemeral port with an optional configuration file.
+///
+/// The test servers run on the loopback interface, which the service only queries once
+/// reserved ranges are unblocked.
+fn start(config: Option<&str>) -> Service {
+    start_with(&format!(
+        "{}\n[blocklist]\nreserved = false\n",
+        config.unwrap_or_default()
+    ))
+}
+
+/// Starts the service on an ephemeral port with a configuration file.
+fn start_with(config: &str) -> Service {
+    let path = std::env::temp_dir().join(format!(
+        "gstat-serve-{}-{}.toml",
+        std::process::id(),
+        CONFIGS.fetch_add(1, Ordering::SeqCst)
+    ));
+    fs::write(&path, config).unwrap();
+
+    let mut command = Command::new(env!("CARGO_BIN_EXE_gstat-serve"));
+    command
+        .args([
+            "--script",
+            SOURCE_INFO_SCRIPT_PATH,
+            "--listen",
+            "127.0.0.1:0",
+        ])
+        .arg("--config")
+        .arg::<PathBuf>(path)
+        .env_remove("GSTAT_SERVE_CONFIG")
+        .stderr(Stdio::piped());
+
+    let mut child = command.spawn().unwrap();
+    let mut line = String::new();
+    BufReader::new(child.stderr.take().unwrap())
+        .read_line(&mut line)
+        .unwrap();
+
+    let address = line
+        .split("http://")
+        .nth(1)
+        .and_then(|rest| rest.strip_suffix("/v1/\n"))
+        .unwrap_or_else(|| panic!("unexpected output: {line}"))
+        .parse()
+        .unwrap();
+
+    Service(child, address)
+}
+
+/// Sends a GET request and returns the status code and the whole response.
+fn get(service: &Service, path: &str, headers: &[&str]) -> (u16, String) {
+    let mut stream = TcpStream::connect(service.1).unwrap();
+    let mut request = format!("GET {path} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n");
+
+    for header in headers {
+        request += &format!("{header}\r\n");
+    }
+
+    stream
+        .write_all(format!("{request}\r\n").as_bytes())
+        .unwrap();
+
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+
+    let status = response[9..12].parse().unwrap();
+    (status, response)
+}
+
+/// Sends a POST request with a JSON body and returns the status code and the whole response.
+#[cfg(feature = "graphql")]
+fn post(service: &Service, path: &str, body: &str) -> (u16, String) {
+    let mut stream = TcpStream::connect(service.1).unwrap();
+    let request = format!(
+        "POST {path} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\
+         Content-Type: application/json\r\nContent-Length: {}\r\n\r\n{body}",
+        body.len()
+    );
+
+    stream.write_all(request.as_bytes()).unwrap();
+
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+
+    let status = response[9..12].parse().unwrap();
+    (status, response)
+}
+
+#[test]
+fn responses_are_cached() {
+    let loopback = loopback_server(SOURCE_INFO_PACKET);
+    let server = loopback.address();
+    let service = start(None);
+    let path = format!("/v1/query/{SOURCE_INFO_GAME}/{server}");
+
+    let (status, response) = get(&service, &path, &[]);
+    assert_eq!(status, 200, "{response}");
+    assert!(response.contains("x-cache: MISS"));
+
+    let body: serde_json::Value =
+        serde_json::from_str(response.split("\r\n\r\n").nth(1).unwrap()).unwrap();
+    assert_eq!(body["version"], 1);
+    assert_eq!(body["game"]["id"], SOURCE_INFO_GAME);
+    assert_eq!(body["info"]["players"], 12);
+
+    let (status, response) = get(&service, &path, &[]);
+    assert_eq!(status, 200, "{response}");
+    assert!(response.contains("x-cache: HIT"));
+    assert_eq!(loopback.queries(), 1);
+}
+
+#[test]
+fn unchanged_responses_are_not_sent_again() {
+    let server = loopback_server(SOURCE_INFO_PACKET).address();
+    let service = start(None);
+    let path = format!("/v1/query/{SOURCE_INFO_GAME}/{server}");
+
+    let (status, response) = get(&service, &path, &[]);
+    assert_eq!(status, 200, "{response}");
+
+    let etag = response
+        .lines()
+        .find_map(|line| line.strip_prefix("etag: "))
+        .unwrap()
+        .to_string();
+
+    let (status, response) = get(&service, &path, &[&format!("If-None-Match: {etag}")]);
+    assert_eq!(status, 304, "{response}");
+    assert!(response.contains(&format!("etag: {etag}")));
+
+    let bare = etag.trim_matches('"');
+    let (status, _) = get(&service, &format!("{path}?if_changed_since={bare}"), &[]);
+    assert_eq!(status, 304);
+
+    let (status, _) = get(&service, &path, &["If-None-Match: \"0000000000000000\""]);
+    assert_eq!(status, 200);
+}
+
+#[test]
+fn badges_show_the_player_count() {
+    let server = loopback_server(SOURCE_INFO_PACKET).address();
+    let silent = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+    let service = start(Some("timeout = \"200ms\"\n"));
+
+    let (status, response) = get(
+        &service,
+        &format!("/v1/badge/{SOURCE_INFO_GAME}/{server}"),
+        &[],
+    );
+    assert_eq!(status, 200, "{response}");
+    assert!(response.contains("content-type: image/svg+xml"));
+    assert!(response.contains("12/"), "{response}");
+
+    let path = format!(
+        "/v1/badge/{SOURCE_INFO_GAME}/{}",
+        silent.local_addr().unwrap()
+    );
+    let (status, response) = get(&service, &path, &[]);
+    assert_eq!(status, 200, "{response}");
+    assert!(response.contains("offline"));
+
+    let (status, _) = get(&service, "/v1/badge/no-such-game/192.0.2.1:27015", &[]);
+    assert_eq!(status, 404);
+}
+
+#[test]
+fn unknown_games_and_silent_servers_fail() {
+    let silent = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+    let service = start(Some("timeout = \"200ms\"\n"));
+
+    let (status, response) = get(&service, "/v1/query/no-such-game/192.0.2.1:27015", &[]);
+    assert_eq!(status, 404, "{response}");
+    assert!(response.contains("unknown game"));
+
+    let path = format!(
+        "/v1/query/{SOURCE_INFO_GAME}/{}",
+        silent.local_addr().unwrap()
+    );
+    let (status, response) = get(&service, &path, &[]);
+    assert_eq!(status, 502, "{response}");
+}
+
+#[test]
+fn native_games_are_served_without_a_script() {
+    let closed = TcpListener::bind((Ipv4Addr::LOCALHOST, 0))
+        .unwrap()
+        .local_addr()
+        .unwrap();
+    let service = start(Some("timeout = \"200ms\"\n"));
+
+    // Nothing listens on the port, so the query fails after the game was found.
+    let (status, response) = get(&service, &format!("/v1/query/minecraft/{closed}"), &[]);
+    assert_eq!(status, 502, "{response}");
+
+    let (status, response) = get(&service, "/v1/query/bf2/192.0.2.1:29900", &[]);
+    assert_eq!(status, 501, "{response}");
+}
+
+#[test]
+fn api_keys_are_required_once_configured() {
+    let server = loopback_server(SOURCE_INFO_PACKET).address();
+    let service = start(Some("api_keys = [\"secret\", \"backup\"]\n"));
+    let path = format!("/v1/query/{SOURCE_INFO_GAME}/{server}");
+
+    let (status, _) = get(&service, &path, &[]);
+    assert_eq!(status, 401);
+
+    for wrong in ["wrong", "secre", "secrets", "Secret"] {
+        let (status, _) = get(&service, &path, &[&format!("X-Api-Key: {wrong}")]);
+        assert_eq!(status, 401, "{wrong}");
+    }
+
+    let (status, _) = get(&service, &path, &["Authorization: Bearer secret"]);
+    assert_eq!(status, 200);
+
+    let (status, _) = get(&service, &path, &["X-Api-Key: backup"]);
+    assert_eq!(status, 200);
+}
+
+#[test]
+fn reserved_and_opted_out_servers_are_refused() {
+    let loopback = loopback_server(SOURCE_INFO_PACKET);
+    let server = loopback.address();
+    let service = start_with("");
+    let path = format!("/v1/query/{SOURCE_INFO_GAME}/{server}");
+
+    let (status, response) = get(&service, &path, &[]);
+    assert_eq!(status, 403, "{response}");
+    assert!(response.contains("is blocked by 127.0.0.0/8 (reserved)"));
+
+    let optout =
+        std::env::temp_dir().join(format!("gstat-serve-{}-optout.txt", std::process::id()));
+    fs::write(&optout, format!("{}\n", server.ip())).unwrap();
+    let service = start_with(&format!(
+        "[blocklist]\nreserved = false\nlists = [{optout:?}]\n"
+    ));
+
+    let (status, _) = get(&service, &path, &[]);
+    assert_eq!(status, 403);
+    assert_eq!(loopback.queries(), 0);
+}
+
+#[test]
+fn clients_are_rate_limited() {
+    let server = loopback_server(SOURCE_INFO_PACKET).address();
+    let service = start(Some(
+        "[rate_limit]\nrequests_per_second = 0.01\nburst = 1\n",
+    ));
+    let path = format!("/v1/query/{SOURCE_INFO_GAME}/{server}");
+
+    let (status, _) = get(&service, &path, &[]);
+    assert_eq!(status, 200);
+
+    let (status, response) = get(&service, &path, &[]);
+    assert_eq!(status, 429, "{response}");
+    assert!(response.contains("retry-after: "));
+}
+
+#[test]
+fn refused_requests_take_no_permits() {
+    let server = loopback_server(SOURCE_INFO_PACKET).address();
+    let service = start(Some("[rate_limit]\nrequests_per_second = 4\nburst = 1\n"));
+    let path = format!("/v1/query/{SOURCE_INFO_GAME}/{server}");
+
+    let (status, _) = get(&service, &path, &[]);
+    assert_eq!(status, 200);
+
+    // Hammering while limited doesn't delay the next permit.
+    for _ in 0..10 {
+        get(&service, &path, &[]);
+    }
+    thread::sleep(std::time::Duration::from_millis(300));
+
+    let (status, response) = get(&service, &path, &[]);
+    assert_eq!(status, 200, "{response}");
+}
+
+#[cfg(feature = "graphql")]
+#[test]
+fn graphql_queries_are_batched() {
+    let loopback = loopback_server(SOURCE_INFO_PACKET);
+    let server = loopback.address();
+    let silent = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+    let service = start(Some("timeout = \"200ms\"\n"));
+    let query = format!(
+        r#"{{
+            one: server(game: "{SOURCE_INFO_GAME}", address: "{server}") {{ info {{ players }} }}
+            all: servers(targets: [
+                {{ game: "{SOURCE_INFO_GAME}", address: "{server}" }}
+                {{ game: "{SOURCE_INFO_GAME}", address: "{}" }}
+            ]) {{ server {{ cache }} error }}
+        }}"#,
+        silent.local_addr().unwrap()
+    );
+    let body = serde_json::json!({ "query": query }).to_string();
+
+    let (status, response) = post(&service, "/v1/graphql", &body);
+    assert_eq!(status, 200, "{response}");
+
+    let body: serde_json::Value =
+        serde_json::from_str(response.split("\r\n\r\n").nth(1).unwrap()).unwrap();
+    assert_eq!(body["data"]["one"]["info"]["players"], 12, "{body}");
+    assert_eq!(body["data"]["all"][0]["server"]["cache"], "MISS");
+    assert!(body["data"]["all"][1]["server"].is_null());
+    assert!(body["data"]["all"][1]["error"].is_string());
+
+    // Both fields asked for the same server, which the batch queried once.
+    assert_eq!(loopback.queries(), 1);
+}
+
+#[cfg(feature = "graphql")]
+#[test]
+fn graphql_targets_are_limited_and_each_take_a_permit() {
+    let server = loopback_server(SOURCE_INFO_PACKET).address();
+    let service = start(Some(
+        "[graphql]\nmax_targets = 2\n\n[rate_limit]\nrequests_per_second = 0.01\nburst = 3\n",
+    ));
+    let servers = |count: usize| {
+        let targets = (0..count)
+            .map(|port| format!(r#"{{ game: "{SOURCE_INFO_GAME}", address: "127.0.0.1:{port}" }}"#))
+            .collect::<Vec<_>>()
+            .join(" ");
+        let query = format!("{{ servers(targets: [{targets}]) {{ error }} }}");
+
+        serde_json::json!({ "query": query }).to_string()
+    };
+
+    // Lists above the limit are refused without querying, which only takes the permit of
+    // the request.
+    let (status, response) = post(&service, "/v1/graphql", &servers(3));
+    assert_eq!(status, 200, "{response}");
+    assert!(
+        response.contains("3 targets exceed the limit of 2"),
+        "{response}"
+    );
+
+    // The request and its server take the last two permits.
+    let query = format!(
+        r#"{{ server(game: "{SOURCE_INFO_GAME}", address: "{server}") {{ info {{ players }} }} }}"#
+    );
+    let body = serde_json::json!({ "query": query }).to_string();
+    let (status, response) = post(&service, "/v1/graphql", &body);
+    assert_eq!(status, 200, "{response}");
+    assert!(response.contains(r#""players":12"#), "{response}");
+
+    let (status, _) = post(&service, "/v1/graphql", &body);
+    assert_eq!(status, 429);
+}
@@ -0,0 +1,20 @@
+//! Historical server statistics in SQLite.
+//!
+//! `Store` keeps a row per poll of a server, with its players, map, online state and
+//! latency, in a single SQLite file, and answers the questions small monitors ask of their
+//! history: what a server looked like over a period, and how busy and reliable it was each
+//! day. SQLite is compiled in, so nothing has to be installed or run next to the monitor.
+//!
+//! Snapshots published by a `Poller` through a `Feed` can be stored as they arrive with
+//! `Store::record`. `Rollup` turns records into hourly or daily population curves, peaks
+//! and weekly heatmaps, so dashboards don't have to derive them from raw samples.
+
+pub mod error;
+pub mod record;
+pub mod rollup;
+pub mod store;
+
+pub use error::StoreError;
+pub use record::{Daily, Record};
+pub use rollup::{Bucket, Heatmap, Peak, Resolution, Rollup};
+pub use store::Store;
@@ -0,0 +1,97 @@
+use gstat_core::subscribe::Snapshot;
+
+use std::{
+    fmt::Display,
+    time::{Duration, SystemTime},
+};
+
+/// The state of a server after one poll, as stored.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Record {
+    /// The server, e.g. its address.
+    pub server: String,
+    /// When the server was polled.
+    pub at: SystemTime,
+    /// Whether the server was reported online.
+    pub online: bool,
+    /// The number of players connected, if the server answered.
+    pub players: Option<u32>,
+    /// The maximum number of players, if the server answered.
+    pub max_players: Option<u32>,
+    /// The map being played, if the server answered.
+    pub map: Option<String>,
+    /// The round-trip time of the query, if it was measured.
+    pub latency: Option<Duration>,
+}
+
+impl Record {
+    /// Creates the record of a poll the server didn't answer.
+    ///
+    /// # Parameters
+    ///
+    /// * `server`: The server, e.g. its address.
+    /// * `at`: When the server was polled.
+    pub fn offline(server: impl Into<String>, at: SystemTime) -> Self {
+        Record {
+            server: server.into(),
+            at,
+            online: false,
+            players: None,
+            max_players: None,
+            map: None,
+            latency: None,
+        }
+    }
+
+    /// Creates the record of a snapshot published by a poller.
+    ///
+    /// # Parameters
+    ///
+    /// * `snapshot`: The snapshot; its target is stored as text.
+    /// * `at`: When the server was polled. Snapshots carry an `Instant`, which can't be
+    ///   stored, so this is usually the time the snapshot was received.
+    pub fn from_snapshot<T: Display>(snapshot: &Snapshot<T>, at: SystemTime) -> Self {
+        let info = snapshot.info.as_ref();
+
+        Record {
+            online: snapshot.online,
+            players: info.map(|info| info.players),
+            max_players: info.map(|info| info.max_players),
+            map: info.map(|info| info.map.clone()),
+            ..Record::offline(snapshot.target.to_string(), at)
+        }
+    }
+
+    /// Sets the round-trip time of the query, e.g. from `ResponseMeta::latency`.
+    pub fn with_latency(mut self, latency: Duration) -> Self {
+        self.latency = Some(latency);
+        self
+    }
+}
+
+/// The summary of the records of a server over one day, in UTC.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Daily {
+    /// The start of the day.
+    pub day: SystemTime,
+    /// The number of records.
+    pub samples: u64,
+    /// The number of records in which the server was online.
+    pub online: u64,
+    /// The average number of players over the records in which the server answered.
+    pub average_players: Option<f64>,
+    /// The largest number of players.
+    pub peak_players: Option<u32>,
+    /// The average round-trip time over the records that measured one.
+    pub average_latency: Option<Duration>,
+}
+
+impl Daily {
+    /// Returns the share of records in which the server was online, between 0 and 1.
+    pub fn uptime(&self) -> f64 {
+        match self.samples {
+            0 => 0.0,
+            samples => self.online as f64 / samples as f64,
+        }
+    }
+}
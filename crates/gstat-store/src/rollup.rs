@@ -0,0 +1,257 @@
+use crate::record::Record;
+
+use std::{
+    collections::BTreeMap,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+/// The width of the buckets of a population curve.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Resolution {
+    /// One bucket per hour.
+    Hourly,
+    /// One bucket per UTC day.
+    Daily,
+}
+
+impl Resolution {
+    /// Returns the width of a bucket.
+    pub fn width(&self) -> Duration {
+        Duration::from_secs(self.seconds() as u64)
+    }
+
+    fn seconds(&self) -> i64 {
+        match self {
+            Resolution::Hourly => 3_600,
+            Resolution::Daily => 86_400,
+        }
+    }
+}
+
+/// The population of a server over one bucket of a curve.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Bucket {
+    /// The start of the bucket.
+    pub start: SystemTime,
+    /// The number of records, `0` for the gaps of a curve.
+    pub samples: u64,
+    /// The number of records in which the server was online.
+    pub online: u64,
+    /// The average number of players over the records in which the server answered.
+    pub average_players: Option<f64>,
+    /// The smallest number of players.
+    pub min_players: Option<u32>,
+    /// The largest number of players.
+    pub peak_players: Option<u32>,
+}
+
+impl Bucket {
+    /// Returns the share of records in which the server was online, between 0 and 1.
+    pub fn uptime(&self) -> f64 {
+        match self.samples {
+            0 => 0.0,
+            samples => self.online as f64 / samples as f64,
+        }
+    }
+}
+
+/// The largest number of players seen, and when it was first seen.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Peak {
+    /// The number of players.
+    pub players: u32,
+    /// When the number was first recorded.
+    pub at: SystemTime,
+}
+
+/// The players over the records of one bucket or heatmap cell.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+struct Tally {
+    samples: u64,
+    online: u64,
+    answered: u64,
+    players: u64,
+    min: Option<u32>,
+    peak: Option<u32>,
+}
+
+impl Tally {
+    fn add(&mut self, record: &Record) {
+        self.samples += 1;
+        self.online += u64::from(record.online);
+
+        if let Some(players) = record.players {
+            self.answered += 1;
+            self.players += u64::from(players);
+            self.min = Some(self.min.map_or(players, |min| min.min(players)));
+            self.peak = Some(self.peak.map_or(players, |peak| peak.max(players)));
+        }
+    }
+
+    fn average(&self) -> Option<f64> {
+        (self.answered > 0).then(|| self.players as f64 / self.answered as f64)
+    }
+}
+
+/// The population of a server by day of the week and hour of the day, in UTC.
+///
+/// Rows are the days of the week from Monday, columns the hours of the day, so the grid
+/// can be drawn as is.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Heatmap {
+    cells: [[Tally; 24]; 7],
+}
+
+impl Heatmap {
+    /// Returns the average number of players in an hour of a day of the week.
+    ///
+    /// # Parameters
+    ///
+    /// * `weekday`: The day of the week, from `0` for Monday to `6` for Sunday.
+    /// * `hour`: The hour of the day, from `0` to `23`.
+    ///
+    /// # Returns
+    ///
+    /// The average, or `None` if the server never answered in that hour or the cell is
+    /// out of range.
+    pub fn average(&self, weekday: usize, hour: usize) -> Option<f64> {
+        self.cells.get(weekday)?.get(hour)?.average()
+    }
+
+    /// Returns the largest number of players in an hour of a day of the week.
+    pub fn peak(&self, weekday: usize, hour: usize) -> Option<u32> {
+        self.cells.get(weekday)?.get(hour)?.peak
+    }
+
+    /// Returns the average number of players of every cell.
+    pub fn averages(&self) -> [[Option<f64>; 24]; 7] {
+        self.cells.map(|row| row.map(|cell| cell.average()))
+    }
+
+    fn add(&mut self, record: &Record) {
+        let seconds = seconds(record.at);
+        let days = seconds.div_euclid(86_400);
+        // The Unix epoch was a Thursday.
+        let weekday = (days + 3).rem_euclid(7) as usize;
+        let hour = (seconds.rem_euclid(86_400) / 3_600) as usize;
+
+        self.cells[weekday][hour].add(record);
+    }
+}
+
+/// `Rollup` aggregates the records of a server into the figures dashboards chart.
+///
+/// Records can be added in any order, e.g. from `Store::series` or as snapshots arrive,
+/// and the curve, peak and heatmap are read at any time without going back to the records.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Rollup {
+    resolution: Resolution,
+    buckets: BTreeMap<i64, Tally>,
+    peak: Option<Peak>,
+    heatmap: Heatmap,
+}
+
+impl Rollup {
+    /// Creates an empty rollup.
+    ///
+    /// # Parameters
+    ///
+    /// * `resolution`: The width of the buckets of the curve.
+    pub fn new(resolution: Resolution) -> Self {
+        Rollup {
+            resolution,
+            buckets: BTreeMap::new(),
+            peak: None,
+            heatmap: Heatmap::default(),
+        }
+    }
+
+    /// Adds a record.
+    pub fn add(&mut self, record: &Record) {
+        let bucket = seconds(record.at).div_euclid(self.resolution.seconds());
+
+        self.buckets.entry(bucket).or_default().add(record);
+        self.heatmap.add(record);
+
+        if let Some(players) = record.players {
+            let higher = match self.peak {
+                Some(peak) => {
+                    players > peak.players || (players == peak.players && record.at < peak.at)
+                }
+                None => true,
+            };
+
+            if higher {
+                self.peak = Some(Peak {
+                    players,
+                    at: record.at,
+                });
+            }
+        }
+    }
+
+    /// Returns the population curve, oldest bucket first.
+    ///
+    /// Buckets without records between the first and last record are included with no
+    /// samples, so charts show gaps in the data as gaps.
+    pub fn curve(&self) -> Vec<Bucket> {
+        let (Some((&first, _)), Some((&last, _))) = (
+            self.buckets.first_key_value(),
+            self.buckets.last_key_value(),
+        ) else {
+            return Vec::new();
+        };
+
+        (first..=last)
+            .map(|bucket| {
+                let tally = self.buckets.get(&bucket).copied().unwrap_or_default();
+
+                Bucket {
+                    start: time(bucket * self.resolution.seconds()),
+                    samples: tally.samples,
+                    online: tally.online,
+                    average_players: tally.average(),
+                    min_players: tally.min,
+                    peak_players: tally.peak,
+                }
+            })
+            .collect()
+    }
+
+    /// Returns the largest number of players recorded.
+    pub fn peak(&self) -> Option<Peak> {
+        self.peak
+    }
+
+    /// Returns the population by day of the week and hour of the day.
+    pub fn heatmap(&self) -> &Heatmap {
+        &self.heatmap
+    }
+}
+
+impl<'r> Extend<&'r Record> for Rollup {
+    fn extend<I: IntoIterator<Item = &'r Record>>(&mut self, records: I) {
+        for record in records {
+            self.add(record);
+        }
+    }
+}
+
+/// Converts a time to whole seconds since the Unix epoch, rounding down.
+fn seconds(time: SystemTime) -> i64 {
+    match time.duration_since(UNIX_EPOCH) {
+        Ok(since) => since.as_secs() as i64,
+        Err(err) => -(err.duration().as_secs_f64().ceil() as i64),
+    }
+}
+
+/// Converts seconds since the Unix epoch to a time.
+fn time(seconds: i64) -> SystemTime {
+    let offset = Duration::from_secs(seconds.unsigned_abs());
+
+    if seconds >= 0 {
+        UNIX_EPOCH + offset
+    } else {
+        UNIX_EPOCH - offset
+    }
+}
@@ -0,0 +1,263 @@
+use crate::{
+    error::StoreError,
+    record::{Daily, Record},
+    rollup::{Resolution, Rollup},
+};
+
+use gstat_core::subscribe::Snapshot;
+
+use std::{
+    fmt::{Debug, Display, Formatter, Result as FmtResult},
+    ops::Range,
+    path::Path,
+    sync::{Mutex, MutexGuard},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use rusqlite::{params, Connection, Row};
+
+/// The milliseconds in a day, the width of the buckets of `Store::daily`.
+const DAY_MILLIS: i64 = 86_400_000;
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS snapshots (
+        server TEXT NOT NULL,
+        at INTEGER NOT NULL,
+        online INTEGER NOT NULL,
+        players INTEGER,
+        max_players INTEGER,
+        map TEXT,
+        latency_us INTEGER
+    );
+    CREATE INDEX IF NOT EXISTS snapshots_server_at ON snapshots (server, at);
+";
+
+/// A SQLite database of server records.
+///
+/// Times are stored as milliseconds since the Unix epoch and latencies as microseconds.
+/// Every method runs its statement at once and blocks until SQLite is done, which takes
+/// well under a millisecond for the writes of a poll; monitors with many servers can move
+/// the calls to a blocking thread. Share a store between tasks behind an `Arc`.
+pub struct Store {
+    connection: Mutex<Connection>,
+}
+
+impl Store {
+    /// Opens the database file at `path`, creating it and its tables if needed.
+    ///
+    /// # Parameters
+    ///
+    /// * `path`: The SQLite file.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, StoreError> {
+        Self::with_connection(Connection::open(path)?)
+    }
+
+    /// Opens a database that only lives in memory, e.g. for tests.
+    pub fn in_memory() -> Result<Self, StoreError> {
+        Self::with_connection(Connection::open_in_memory()?)
+    }
+
+    fn with_connection(connection: Connection) -> Result<Self, StoreError> {
+        connection.execute_batch(SCHEMA)?;
+
+        Ok(Store {
+            connection: Mutex::new(connection),
+        })
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Connection> {
+        self.connection
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+    }
+
+    /// Stores a record.
+    ///
+    /// # Parameters
+    ///
+    /// * `record`: The state of a server after a poll.
+    pub fn insert(&self, record: &Record) -> Result<(), StoreError> {
+        self.lock().execute(
+            "INSERT INTO snapshots (server, at, online, players, max_players, map, latency_us)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                record.server,
+                millis(record.at),
+                record.online,
+                record.players,
+                record.max_players,
+                record.map,
+                record.latency.map(|latency| latency.as_micros() as i64),
+            ],
+        )?;
+
+        Ok(())
+    }
+
+    /// Stores a snapshot published by a poller, as polled now.
+    ///
+    /// # Parameters
+    ///
+    /// * `snapshot`: The snapshot; its target is stored as text.
+    pub fn record<T: Display>(&self, snapshot: &Snapshot<T>) -> Result<(), StoreError> {
+        self.insert(&Record::from_snapshot(snapshot, SystemTime::now()))
+    }
+
+    /// Returns the records of a server over a period, oldest first.
+    ///
+    /// # Parameters
+    ///
+    /// * `server`: The server, as stored in its records.
+    /// * `period`: The times the records were taken at, end excluded.
+    pub fn series(
+        &self,
+        server: &str,
+        period: Range<SystemTime>,
+    ) -> Result<Vec<Record>, StoreError> {
+        let connection = self.lock();
+        let mut statement = connection.prepare_cached(
+            "SELECT server, at, online, players, max_players, map, latency_us FROM snapshots
+             WHERE server = ?1 AND at >= ?2 AND at < ?3
+             ORDER BY at",
+        )?;
+
+        let records = statement
+            .query_map(
+                params![server, millis(period.start), millis(period.end)],
+                record,
+            )?
+            .collect::<Result<_, _>>()?;
+
+        Ok(records)
+    }
+
+    /// Summarizes the records of a server for each UTC day of a period.
+    ///
+    /// # Parameters
+    ///
+    /// * `server`: The server, as stored in its records.
+    /// * `period`: The times the records were taken at, end excluded. Days only partly in
+    ///   the period are summarized from the records inside it.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing a summary for every day with records, oldest first, or a
+    /// `StoreError`.
+    pub fn daily(&self, server: &str, period: Range<SystemTime>) -> Result<Vec<Daily>, StoreError> {
+        let connection = self.lock();
+        let mut statement = connection.prepare_cached(
+            "SELECT at / ?4 AS day, COUNT(*), SUM(online), AVG(players), MAX(players),
+                    AVG(latency_us)
+             FROM snapshots
+             WHERE server = ?1 AND at >= ?2 AND at < ?3
+             GROUP BY day
+             ORDER BY day",
+        )?;
+
+        let days = statement
+            .query_map(
+                params![server, millis(period.start), millis(period.end), DAY_MILLIS],
+                |row| {
+                    Ok(Daily {
+                        day: time(row.get::<_, i64>(0)? * DAY_MILLIS),
+                        samples: row.get(1)?,
+                        online: row.get(2)?,
+                        average_players: row.get(3)?,
+                        peak_players: row.get(4)?,
+                        average_latency: row
+                            .get::<_, Option<f64>>(5)?
+                            .map(|micros| Duration::from_micros(micros.round() as u64)),
+                    })
+                },
+            )?
+            .collect::<Result<_, _>>()?;
+
+        Ok(days)
+    }
+
+    /// Aggregates the records of a server over a period into a population curve, its peak
+    /// and a heatmap.
+    ///
+    /// # Parameters
+    ///
+    /// * `server`: The server, as stored in its records.
+    /// * `period`: The times the records were taken at, end excluded.
+    /// * `resolution`: The width of the buckets of the curve.
+    pub fn rollup(
+        &self,
+        server: &str,
+        period: Range<SystemTime>,
+        resolution: Resolution,
+    ) -> Result<Rollup, StoreError> {
+        let mut rollup = Rollup::new(resolution);
+        rollup.extend(&self.series(server, period)?);
+
+        Ok(rollup)
+    }
+
+    /// Returns every server with records, in alphabetical order.
+    pub fn servers(&self) -> Result<Vec<String>, StoreError> {
+        let connection = self.lock();
+        let mut statement =
+            connection.prepare_cached("SELECT DISTINCT server FROM snapshots ORDER BY server")?;
+
+        let servers = statement
+            .query_map([], |row| row.get(0))?
+            .collect::<Result<_, _>>()?;
+
+        Ok(servers)
+    }
+
+    /// Deletes the records taken before `before`, keeping the database from growing
+    /// forever.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the number of records deleted, or a `StoreError`.
+    pub fn prune(&self, before: SystemTime) -> Result<usize, StoreError> {
+        Ok(self
+            .lock()
+            .execute("DELETE FROM snapshots WHERE at < ?1", [millis(before)])?)
+    }
+}
+
+impl Debug for Store {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.debug_struct("Store")
+            .field("path", &self.lock().path())
+            .finish_non_exhaustive()
+    }
+}
+
+fn record(row: &Row<'_>) -> rusqlite::Result<Record> {
+    Ok(Record {
+        server: row.get(0)?,
+        at: time(row.get(1)?),
+        online: row.get(2)?,
+        players: row.get(3)?,
+        max_players: row.get(4)?,
+        map: row.get(5)?,
+        latency: row
+            .get::<_, Option<i64>>(6)?
+            .map(|micros| Duration::from_micros(micros as u64)),
+    })
+}
+
+/// Converts a time to milliseconds since the Unix epoch.
+fn millis(time: SystemTime) -> i64 {
+    match time.duration_since(UNIX_EPOCH) {
+        Ok(since) => since.as_millis() as i64,
+        Err(err) => -(err.duration().as_millis() as i64),
+    }
+}
+
+/// Converts milliseconds since the Unix epoch to a time.
+fn time(millis: i64) -> SystemTime {
+    let offset = Duration::from_millis(millis.unsigned_abs());
+
+    if millis >= 0 {
+        UNIX_EPOCH + offset
+    } else {
+        UNIX_EPOCH - offset
+    }
+}
@@ -0,0 +1,88 @@
+//! Aggregating records into population curves, peaks and heatmaps.
+
+use gstat_store::{Record, Resolution, Rollup, Store};
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const HOUR: Duration = Duration::from_secs(3_600);
+const DAY: Duration = Duration::from_secs(86_400);
+
+/// Noon on the 100th day after the Unix epoch, a Saturday.
+fn noon() -> SystemTime {
+    UNIX_EPOCH + DAY * 100 + DAY / 2
+}
+
+fn online(at: SystemTime, players: u32) -> Record {
+    Record {
+        online: true,
+        players: Some(players),
+        max_players: Some(32),
+        ..Record::offline("a", at)
+    }
+}
+
+#[test]
+fn hourly_curves_include_gaps() {
+    let mut rollup = Rollup::new(Resolution::Hourly);
+    let records = [
+        online(noon() + HOUR * 3, 9),
+        online(noon(), 4),
+        online(noon() + Duration::from_secs(600), 10),
+        Record::offline("a", noon() + Duration::from_secs(1_200)),
+    ];
+    rollup.extend(&records);
+
+    let curve = rollup.curve();
+
+    assert_eq!(curve.len(), 4);
+    assert_eq!(curve[0].start, noon());
+    assert_eq!(curve[0].samples, 3);
+    assert_eq!(curve[0].average_players, Some(7.0));
+    assert_eq!(curve[0].min_players, Some(4));
+    assert_eq!(curve[0].peak_players, Some(10));
+    assert_eq!(curve[1].samples, 0);
+    assert_eq!(curve[1].average_players, None);
+    assert_eq!(curve[3].start, noon() + HOUR * 3);
+    assert_eq!(curve[3].uptime(), 1.0);
+
+    let peak = rollup.peak().unwrap();
+    assert_eq!(peak.players, 10);
+    assert_eq!(peak.at, noon() + Duration::from_secs(600));
+}
+
+#[test]
+fn heatmaps_group_by_weekday_and_hour() {
+    let mut rollup = Rollup::new(Resolution::Daily);
+
+    rollup.add(&online(noon(), 6));
+    rollup.add(&online(noon() + DAY * 7, 2));
+    rollup.add(&online(noon() + DAY + HOUR * 9, 20));
+
+    let heatmap = rollup.heatmap();
+
+    assert_eq!(heatmap.average(5, 12), Some(4.0));
+    assert_eq!(heatmap.peak(5, 12), Some(6));
+    assert_eq!(heatmap.average(6, 21), Some(20.0));
+    assert_eq!(heatmap.average(0, 0), None);
+    assert_eq!(heatmap.average(7, 0), None);
+    assert_eq!(heatmap.averages()[6][21], Some(20.0));
+    assert_eq!(rollup.curve().len(), 8);
+}
+
+#[test]
+fn stores_roll_up_the_records_of_a_period() {
+    let store = Store::in_memory().unwrap();
+
+    store.insert(&online(noon(), 3)).unwrap();
+    store.insert(&online(noon() + DAY, 5)).unwrap();
+    store.insert(&online(noon() + DAY * 2, 50)).unwrap();
+
+    let rollup = store
+        .rollup("a", noon()..noon() + DAY * 2, Resolution::Daily)
+        .unwrap();
+    let curve = rollup.curve();
+
+    assert_eq!(curve.len(), 2);
+    assert_eq!(curve[0].start, UNIX_EPOCH + DAY * 100);
+    assert_eq!(rollup.peak().map(|peak| peak.players), Some(5));
+}
@@ -0,0 +1,100 @@
+//! Storing poll records and reading them back as time series and daily rollups.
+
+use gstat_core::{prelude::ServerInfo, subscribe::Snapshot};
+use gstat_store::{Record, Store};
+
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+const DAY: Duration = Duration::from_secs(86_400);
+
+/// Noon on the 100th day after the Unix epoch.
+fn noon() -> SystemTime {
+    UNIX_EPOCH + DAY * 100 + DAY / 2
+}
+
+fn online(server: &str, at: SystemTime, players: u32, latency_ms: u64) -> Record {
+    Record {
+        online: true,
+        players: Some(players),
+        max_players: Some(24),
+        map: Some("cp_dustbowl".into()),
+        ..Record::offline(server, at)
+    }
+    .with_latency(Duration::from_millis(latency_ms))
+}
+
+#[test]
+fn series_return_the_records_of_a_server_in_order() {
+    let store = Store::in_memory().unwrap();
+    let later = online("a", noon() + Duration::from_secs(60), 5, 30);
+    let earlier = online("a", noon(), 3, 20);
+
+    store.insert(&later).unwrap();
+    store.insert(&earlier).unwrap();
+    store.insert(&online("b", noon(), 1, 10)).unwrap();
+
+    let series = store.series("a", noon()..noon() + DAY).unwrap();
+
+    assert_eq!(series, vec![earlier, later]);
+    assert_eq!(store.servers().unwrap(), ["a", "b"]);
+}
+
+#[test]
+fn daily_rollups_summarize_each_day() {
+    let store = Store::in_memory().unwrap();
+
+    store.insert(&online("a", noon(), 4, 20)).unwrap();
+    store
+        .insert(&online("a", noon() + Duration::from_secs(60), 8, 40))
+        .unwrap();
+    store
+        .insert(&Record::offline("a", noon() + Duration::from_secs(120)))
+        .unwrap();
+    store
+        .insert(&Record::offline("a", noon() + Duration::from_secs(180)))
+        .unwrap();
+    store.insert(&online("a", noon() + DAY, 2, 10)).unwrap();
+
+    let days = store.daily("a", UNIX_EPOCH..noon() + DAY * 2).unwrap();
+
+    assert_eq!(days.len(), 2);
+    assert_eq!(days[0].day, UNIX_EPOCH + DAY * 100);
+    assert_eq!(days[0].samples, 4);
+    assert_eq!(days[0].uptime(), 0.5);
+    assert_eq!(days[0].average_players, Some(6.0));
+    assert_eq!(days[0].peak_players, Some(8));
+    assert_eq!(days[0].average_latency, Some(Duration::from_millis(30)));
+    assert_eq!(days[1].day, UNIX_EPOCH + DAY * 101);
+    assert_eq!(days[1].uptime(), 1.0);
+}
+
+#[test]
+fn snapshots_are_recorded_and_old_records_pruned() {
+    let store = Store::in_memory().unwrap();
+    let snapshot = Snapshot {
+        target: "127.0.0.1:27015",
+        info: Some(ServerInfo {
+            map: "de_dust2".into(),
+            players: 7,
+            max_players: 16,
+            ..ServerInfo::default()
+        }),
+        error: None,
+        online: true,
+        polled_at: Instant::now(),
+    };
+
+    store
+        .insert(&Record::offline("127.0.0.1:27015", noon()))
+        .unwrap();
+    store.record(&snapshot).unwrap();
+
+    assert_eq!(store.prune(noon() + DAY).unwrap(), 1);
+
+    let series = store
+        .series("127.0.0.1:27015", noon()..SystemTime::now() + DAY)
+        .unwrap();
+    assert_eq!(series.len(), 1);
+    assert_eq!(series[0].players, Some(7));
+    assert_eq!(series[0].map.as_deref(), Some("de_dust2"));
+}
@@ -0,0 +1,51 @@
+use std::{
+    error::Error as StdError,
+    fmt::{Display, Formatter, Result as FmtResult},
+    io,
+};
+
+/// Errors produced by the TCP transport.
+#[derive(Debug)]
+pub enum TcpError {
+    /// An I/O error occurred on the underlying stream.
+    Io(io::Error),
+    /// An operation required a connection, but none was established.
+    NotConnected,
+    /// The peer closed the connection before a complete frame was received.
+    Closed,
+    /// A frame could not be encoded or decoded.
+    Frame(String),
+    /// The parser failed to serialize a query or deserialize a response.
+    Parse(Box<dyn StdError + Send + Sync>),
+    /// The server rejected the credentials, e.g. a wrong RCON password.
+    Rejected,
+}
+
+impl Display for TcpError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            Self::Io(err) => write!(f, "I/O error: {err}"),
+            Self::NotConnected => write!(f, "not connected"),
+            Self::Closed => write!(f, "connection closed by peer"),
+            Self::Frame(message) => write!(f, "framing error: {message}"),
+            Self::Parse(err) => write!(f, "parse error: {err}"),
+            Self::Rejected => write!(f, "credentials rejected"),
+        }
+    }
+}
+
+impl StdError for TcpError {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        match self {
+            Self::Io(err) => Some(err),
+            Self::Parse(err) => Some(err.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for TcpError {
+    fn from(err: io::Error) -> Self {
+        Self::Io(err)
+    }
+}
@@ -0,0 +1,270 @@
+use crate::error::TcpError;
+
+use std::ops::Range;
+
+use bytes::{Buf, Bytes, BytesMut};
+
+pub use gstat_core::codec::{Endian, LengthWidth};
+
+/// Describes a frame that starts with a fixed-size header containing a length field.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FixedHeader {
+    /// The total size of the header in bytes.
+    pub header_len: usize,
+    /// The position of the length field within the header.
+    pub length_offset: usize,
+    /// The width of the length field.
+    pub width: LengthWidth,
+    /// The byte order of the length field.
+    pub endian: Endian,
+    /// Whether the length covers the whole frame (`true`) or only the body after the header (`false`).
+    pub inclusive: bool,
+}
+
+impl FixedHeader {
+    /// Returns the position of the length field, which must lie within the header.
+    fn length_field(&self) -> Result<Range<usize>, TcpError> {
+        self.length_offset
+            .checked_add(self.width.bytes())
+            .filter(|end| *end <= self.header_len)
+            .map(|end| self.length_offset..end)
+            .ok_or_else(|| {
+                TcpError::Frame(format!(
+                    "a {} byte length field at offset {} doesn't fit in a {} byte header",
+                    self.width.bytes(),
+                    self.length_offset,
+                    self.header_len
+                ))
+            })
+    }
+}
+
+/// The strategy used to split a TCP byte stream into frames.
+///
+/// Stream based query protocols differ in how they delimit messages; `Framing` covers the
+/// common strategies so protocol implementors don't have to reinvent stream splitting.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Framing {
+    /// Each frame is preceded by a length field, which is stripped from decoded frames
+    /// (e.g. Source RCON).
+    LengthPrefixed {
+        /// The width of the length prefix.
+        width: LengthWidth,
+        /// The byte order of the length prefix.
+        endian: Endian,
+        /// Whether the length includes the prefix itself.
+        inclusive: bool,
+    },
+    /// Each frame is terminated by a delimiter, which is stripped from decoded frames
+    /// (e.g. TeamSpeak ServerQuery).
+    Delimited(Vec<u8>),
+    /// Each frame starts with a fixed-size header holding the frame length (e.g. Frostbite).
+    ///
+    /// Decoded frames keep their header, and frames passed for encoding must already contain
+    /// it; the length field is filled in during encoding. A header whose length field
+    /// doesn't fit in it fails every encode and decode with `TcpError::Frame`.
+    FixedHeader(FixedHeader),
+}
+
+impl Framing {
+    /// Returns a framing strategy for newline-terminated frames.
+    pub fn newline_delimited() -> Self {
+        Self::Delimited(b"\n".to_vec())
+    }
+
+    /// Encodes a single frame for transmission.
+    ///
+    /// # Parameters
+    ///
+    /// * `payload`: The frame content to encode.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing either the bytes to write to the stream or a `TcpError`.
+    pub fn encode(&self, payload: &[u8]) -> Result<Vec<u8>, TcpError> {
+        match self {
+            Self::LengthPrefixed {
+                width,
+                endian,
+                inclusive,
+            } => {
+                let length = payload.len() + if *inclusive { width.bytes() } else { 0 };
+                let mut frame = Vec::with_capacity(width.bytes() + payload.len());
+
+                frame.extend_from_slice(&write_length(length, *width, *endian)?);
+                frame.extend_from_slice(payload);
+
+                Ok(frame)
+            }
+
+            Self::Delimited(delimiter) => {
+                let mut frame = Vec::with_capacity(payload.len() + delimiter.len());
+
+                frame.extend_from_slice(payload);
+                frame.extend_from_slice(delimiter);
+
+                Ok(frame)
+            }
+
+            Self::FixedHeader(header) => {
+                let field = header.length_field()?;
+
+                if payload.len() < header.header_len {
+                    return Err(TcpError::Frame(
+                        "frame is shorter than its header".to_string(),
+                    ));
+                }
+
+                let length = if header.inclusive {
+                    payload.len()
+                } else {
+                    payload.len() - header.header_len
+                };
+
+                let mut frame = payload.to_vec();
+                frame[field].copy_from_slice(&write_length(length, header.width, header.endian)?);
+
+                Ok(frame)
+            }
+        }
+    }
+
+    /// Attempts to decode a single frame from the front of `buffer`.
+    ///
+    /// Consumed bytes are removed from the buffer. If the buffer does not yet contain a
+    /// complete frame, nothing is consumed and `None` is returned. Decoded frames share the
+    /// buffer's memory rather than being copied out of it.
+    ///
+    /// # Parameters
+    ///
+    /// * `buffer`: The bytes read from the stream so far.
+    /// * `max_len`: The largest frame that will be accepted.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing either an optional complete frame or a `TcpError`.
+    pub fn decode(&self, buffer: &mut BytesMut, max_len: usize) -> Result<Option<Bytes>, TcpError> {
+        match self {
+            Self::LengthPrefixed {
+                width,
+                endian,
+                inclusive,
+            } => {
+                if buffer.len() < width.bytes() {
+                    return Ok(None);
+                }
+
+                let length = read_length(&buffer[..width.bytes()], *endian);
+                let body = if *inclusive {
+                    length.checked_sub(width.bytes()).ok_or_else(|| {
+                        TcpError::Frame("length prefix is shorter than itself".to_string())
+                    })?
+                } else {
+                    length
+                };
+
+                check_len(body, max_len)?;
+
+                if buffer.len() < width.bytes() + body {
+                    return Ok(None);
+                }
+
+                buffer.advance(width.bytes());
+
+                Ok(Some(buffer.split_to(body).freeze()))
+            }
+
+            Self::Delimited(delimiter) => {
+                if delimiter.is_empty() {
+                    return Err(TcpError::Frame("frame delimiter is empty".to_string()));
+                }
+
+                let position = buffer
+                    .windows(delimiter.len())
+                    .position(|window| window == delimiter.as_slice());
+
+                match position {
+                    Some(position) => {
+                        let frame = buffer.split_to(position).freeze();
+                        buffer.advance(delimiter.len());
+
+                        Ok(Some(frame))
+                    }
+                    None => {
+                        check_len(buffer.len(), max_len)?;
+                        Ok(None)
+                    }
+                }
+            }
+
+            Self::FixedHeader(header) => {
+                let field = header.length_field()?;
+
+                if buffer.len() < header.header_len {
+                    return Ok(None);
+                }
+
+                let length = read_length(&buffer[field], header.endian);
+                let total = if header.inclusive {
+                    if length < header.header_len {
+                        return Err(TcpError::Frame(
+                            "frame length is shorter than its header".to_string(),
+                        ));
+                    }
+                    length
+                } else {
+                    header
+                        .header_len
+                        .checked_add(length)
+                        .ok_or_else(|| TcpError::Frame("frame length overflows".to_string()))?
+                };
+
+                check_len(total, max_len)?;
+
+                if buffer.len() < total {
+                    return Ok(None);
+                }
+
+                Ok(Some(buffer.split_to(total).freeze()))
+            }
+        }
+    }
+}
+
+/// Ensures a frame does not exceed the configured maximum length.
+fn check_len(len: usize, max_len: usize) -> Result<(), TcpError> {
+    if len > max_len {
+        return Err(TcpError::Frame(format!(
+            "frame of {len} bytes exceeds the maximum of {max_len} bytes"
+        )));
+    }
+
+    Ok(())
+}
+
+/// Reads a length field of the given byte order.
+fn read_length(bytes: &[u8], endian: Endian) -> usize {
+    let fold = |length: usize, byte: &u8| (length << 8) | *byte as usize;
+
+    match endian {
+        Endian::Big => bytes.iter().fold(0, fold),
+        Endian::Little => bytes.iter().rev().fold(0, fold),
+    }
+}
+
+/// Writes a length field of the given width and byte order.
+fn write_length(length: usize, width: LengthWidth, endian: Endian) -> Result<Vec<u8>, TcpError> {
+    if length > width.max() {
+        return Err(TcpError::Frame(format!(
+            "frame length {length} does not fit in a {} byte length field",
+            width.bytes()
+        )));
+    }
+
+    let bytes = (length as u32).to_be_bytes()[4 - width.bytes()..].to_vec();
+
+    Ok(match endian {
+        Endian::Big => bytes,
+        Endian::Little => bytes.into_iter().rev().collect(),
+    })
+}
@@ -0,0 +1,12 @@
+pub mod error;
+pub mod framing;
+#[cfg(feature = "script-rhai")]
+pub mod lookup;
+pub mod protocol;
+pub mod rcon;
+pub mod slp;
+
+pub use error::TcpError;
+pub use framing::{Endian, FixedHeader, Framing, LengthWidth};
+pub use protocol::TcpProtocol;
+pub use rcon::SourceRcon;
@@ -0,0 +1,115 @@
+use gstat_core::{
+    registry::{self, GameEntry},
+    script::{ScriptError, ScriptGame, ScriptRegistry},
+};
+
+use std::{
+    error::Error as StdError,
+    fmt::{Display, Formatter, Result as FmtResult},
+};
+
+/// The protocol of the registry games this crate queries natively, with `slp::Minecraft`.
+const MINECRAFT_SLP: &str = "minecraft-slp";
+
+/// A game the frontends can query, either through its script or natively.
+#[derive(Clone, Copy, Debug)]
+pub enum QueryGame<'s> {
+    /// A game described by a loaded script, queried over UDP.
+    Scripted(&'s ScriptGame),
+    /// A game of the built-in registry queried with `slp::Minecraft`.
+    Native(&'static GameEntry),
+}
+
+impl QueryGame<'_> {
+    /// Returns the identifier of the game.
+    pub fn id(&self) -> &str {
+        match self {
+            Self::Scripted(game) => game.id(),
+            Self::Native(entry) => entry.id,
+        }
+    }
+
+    /// Returns the name of the game.
+    pub fn name(&self) -> &str {
+        match self {
+            Self::Scripted(game) => game.name(),
+            Self::Native(entry) => entry.name,
+        }
+    }
+
+    /// Returns the port queried when the target doesn't give one.
+    pub fn port(&self) -> u16 {
+        match self {
+            Self::Scripted(game) => game.port(),
+            Self::Native(entry) => entry.default_query_port(),
+        }
+    }
+}
+
+/// Errors produced when looking up a game to query.
+#[derive(Debug)]
+pub enum LookupError {
+    /// Neither a loaded script nor the registry knows the identifier.
+    UnknownGame(String),
+    /// The registry knows the game, but its protocol isn't implemented.
+    UnsupportedProtocol(&'static GameEntry),
+    /// The script of the game can't be queried, e.g. because it runs over TCP.
+    Script(ScriptError),
+}
+
+impl Display for LookupError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            Self::UnknownGame(id) => write!(f, "unknown game `{id}`; load its script"),
+            Self::UnsupportedProtocol(entry) => write!(
+                f,
+                "{} uses the {} protocol, which can't be queried yet; describe it in a game \
+                 script",
+                entry.name,
+                entry.protocol().name
+            ),
+            Self::Script(err) => write!(f, "{err}"),
+        }
+    }
+}
+
+impl StdError for LookupError {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        match self {
+            Self::Script(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+/// Looks up the game to query by its identifier or one of its aliases.
+///
+/// Loaded scripts take precedence, so a script can replace a native implementation.
+/// Otherwise registry games whose protocol this crate implements, which cover Minecraft:
+/// Java Edition, are queried natively.
+///
+/// # Parameters
+///
+/// * `scripts`: The scripted games loaded by the frontend.
+/// * `identifier`: The game to look up.
+///
+/// # Returns
+///
+/// A `Result` containing either the game or a `LookupError` describing why it can't be
+/// queried.
+pub fn find_game<'s>(
+    scripts: &'s ScriptRegistry,
+    identifier: &str,
+) -> Result<QueryGame<'s>, LookupError> {
+    match scripts.find_queryable(identifier) {
+        Ok(game) => return Ok(QueryGame::Scripted(game)),
+        Err(ScriptError::UnknownGame(_)) => {}
+        Err(err) => return Err(LookupError::Script(err)),
+    }
+
+    match registry::game(identifier) {
+        Some(entry) if entry.protocol().id == MINECRAFT_SLP => Ok(QueryGame::Native(entry)),
+        Some(entry) => Err(LookupError::UnsupportedProtocol(entry)),
+        None => Err(LookupError::UnknownGame(identifier.to_string())),
+    }
+}
@@ -0,0 +1,436 @@
+use crate::{error::TcpError, framing::Framing};
+
+use gstat_core::{
+    address::BindAddresses,
+    events::{ConnectionEvent, ConnectionSubscriber, Subscribers},
+    metrics::MetricsSink,
+    prelude::{
+        Error, ErrorDetail, ErrorKind, Parser, Protocol, Query, RawTransport, Response,
+        RetryPolicy, TimeoutSettings,
+    },
+    rate_limit::RateLimiter,
+    runtime::{self, TcpStream, READ_CHUNK_LEN},
+    standards::response::Meter,
+    timeout::with_timeout,
+};
+
+use std::{
+    marker::PhantomData,
+    net::SocketAddr,
+    sync::{Arc, Mutex as SyncMutex},
+};
+
+use async_lock::Mutex;
+use bytes::{Bytes, BytesMut};
+
+/// The default upper bound for a single frame, in bytes.
+const DEFAULT_MAX_FRAME_LEN: usize = 1024 * 1024;
+
+/// An established connection and the bytes read from it that are not yet part of a frame.
+struct Connection {
+    peer: SocketAddr,
+    stream: TcpStream,
+    buffer: BytesMut,
+}
+
+/// A `Protocol` implementation over a TCP stream.
+///
+/// Queries are serialized with the parser `P` and written as a single frame, and each
+/// response is read as a single frame before being handed to the parser. The `Framing`
+/// decides how frames are delimited on the stream.
+///
+/// Calling the `Protocol` methods out of order fails at runtime with
+/// `TcpError::NotConnected`. Drive the protocol through a `Link` to have the connection
+/// state checked at compile time instead.
+///
+/// Subscribers added with `with_subscriber` are told when connections are established,
+/// re-established to the same peer, closed by the peer and closed locally, and of every
+/// frame written and read.
+pub struct TcpProtocol<Q, R, P> {
+    parser: P,
+    framing: Framing,
+    max_frame_len: usize,
+    bind: BindAddresses,
+    retry_policy: RetryPolicy,
+    rate_limiter: Option<RateLimiter>,
+    metrics: Option<Arc<dyn MetricsSink>>,
+    subscribers: Subscribers,
+    meter: Meter,
+    connection: Mutex<Option<Connection>>,
+    last_peer: SyncMutex<Option<SocketAddr>>,
+    _marker: PhantomData<fn() -> (Q, R)>,
+}
+
+impl<Q, R, P> TcpProtocol<Q, R, P> {
+    /// Creates a new, disconnected `TcpProtocol`.
+    ///
+    /// # Parameters
+    ///
+    /// * `parser`: The parser used for queries and responses.
+    /// * `framing`: The strategy used to split the stream into frames.
+    pub fn new(parser: P, framing: Framing) -> Self {
+        TcpProtocol {
+            parser,
+            framing,
+            max_frame_len: DEFAULT_MAX_FRAME_LEN,
+            bind: BindAddresses::new(),
+            retry_policy: RetryPolicy::default(),
+            rate_limiter: None,
+            metrics: None,
+            subscribers: Subscribers::new(),
+            meter: Meter::new(),
+            connection: Mutex::new(None),
+            last_peer: SyncMutex::new(None),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Sets the largest frame that will be accepted from the peer.
+    ///
+    /// # Parameters
+    ///
+    /// * `max_frame_len`: The maximum frame length in bytes.
+    pub fn with_max_frame_len(mut self, max_frame_len: usize) -> Self {
+        self.max_frame_len = max_frame_len;
+        self
+    }
+
+    /// Binds the streams of future connections to a local address.
+    ///
+    /// Call it once per address family to bind both IPv4 and IPv6 streams; peers of a
+    /// family without a local address are connected to from any address of that family.
+    /// Binding requires the `rt-tokio` runtime; on other runtimes, connecting from a
+    /// configured address fails.
+    ///
+    /// # Parameters
+    ///
+    /// * `local`: The local address, usually with port `0` to pick any free port.
+    pub fn with_local_addr(mut self, local: SocketAddr) -> Self {
+        self.bind = self.bind.with(local);
+        self
+    }
+
+    /// Sets the policy used to retry failed exchanges.
+    ///
+    /// # Parameters
+    ///
+    /// * `retry_policy`: The retry policy reported through `Protocol::retry_policy`.
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    /// Paces connection attempts with a rate limiter.
+    ///
+    /// Each connection attempt takes a permit before it opens the stream; frames sent over
+    /// an established connection don't.
+    ///
+    /// # Parameters
+    ///
+    /// * `rate_limiter`: The limiter to take permits from, usually shared across protocols.
+    pub fn with_rate_limiter(mut self, rate_limiter: RateLimiter) -> Self {
+        self.rate_limiter = Some(rate_limiter);
+        self
+    }
+
+    /// Reports the frames sent and received to a metrics sink.
+    ///
+    /// `Game::fetch_with` reports its queries to the same sink.
+    ///
+    /// # Parameters
+    ///
+    /// * `metrics`: The sink reported through `Protocol::metrics`. Pass an `Arc` to share it
+    ///   between protocols.
+    pub fn with_metrics(mut self, metrics: impl MetricsSink + 'static) -> Self {
+        self.metrics = Some(Arc::new(metrics));
+        self
+    }
+
+    /// Adds a subscriber to the protocol's connection events.
+    ///
+    /// # Parameters
+    ///
+    /// * `subscriber`: The subscriber reported through `Protocol::subscribers`.
+    pub fn with_subscriber(mut self, subscriber: impl ConnectionSubscriber + 'static) -> Self {
+        self.subscribers.add(subscriber);
+        self
+    }
+
+    /// Returns the parser used by the protocol.
+    pub fn parser(&self) -> &P {
+        &self.parser
+    }
+
+    /// Returns the framing strategy used by the protocol.
+    pub fn framing(&self) -> &Framing {
+        &self.framing
+    }
+
+    /// Encodes and writes a single frame to the stream within the write time limit.
+    async fn write_frame(
+        &self,
+        payload: &[u8],
+        timeouts: &TimeoutSettings,
+    ) -> Result<(), Error<TcpError>> {
+        let frame = self.framing.encode(payload).map_err(protocol_error)?;
+
+        let mut connection = self.connection.lock().await;
+        let connection = connection.as_mut().ok_or_else(not_connected)?;
+
+        #[cfg(all(feature = "tracing", not(feature = "silent")))]
+        tracing::trace!(peer = %connection.peer, bytes = frame.len(), "writing frame");
+
+        let write = async {
+            connection
+                .stream
+                .write_all(&frame)
+                .await
+                .map_err(|err| protocol_error(TcpError::Io(err)))?;
+
+            if let Some(metrics) = &self.metrics {
+                metrics.packet_sent(connection.peer, frame.len());
+            }
+
+            self.subscribers.emit(ConnectionEvent::PacketSent {
+                peer: connection.peer,
+                bytes: frame.len(),
+            });
+
+            Ok(())
+        };
+
+        with_timeout(timeouts.write, "TCP write timed out", write).await
+    }
+
+    /// Reads from the stream until a complete frame is available within the read time limit.
+    async fn read_frame(&self, timeouts: &TimeoutSettings) -> Result<Bytes, Error<TcpError>> {
+        with_timeout(timeouts.read, "TCP read timed out", self.read_frame_inner()).await
+    }
+
+    /// Reads from the stream until a complete frame is available.
+    async fn read_frame_inner(&self) -> Result<Bytes, Error<TcpError>> {
+        let mut connection = self.connection.lock().await;
+        let Connection {
+            peer,
+            stream,
+            buffer,
+        } = connection.as_mut().ok_or_else(not_connected)?;
+
+        loop {
+            if let Some(frame) = self
+                .framing
+                .decode(buffer, self.max_frame_len)
+                .map_err(protocol_error)?
+            {
+                #[cfg(all(feature = "tracing", not(feature = "silent")))]
+                tracing::trace!(peer = %peer, bytes = frame.len(), "read frame");
+
+                self.meter.record(&frame);
+
+                if let Some(metrics) = &self.metrics {
+                    metrics.packet_received(*peer, frame.len());
+                }
+
+                self.subscribers.emit(ConnectionEvent::PacketReceived {
+                    peer: *peer,
+                    bytes: frame.len(),
+                });
+
+                return Ok(frame);
+            }
+
+            let mut chunk = [0; READ_CHUNK_LEN];
+            let read = stream
+                .read(&mut chunk)
+                .await
+                .map_err(|err| protocol_error(TcpError::Io(err)))?;
+
+            if read == 0 {
+                self.subscribers
+                    .emit(ConnectionEvent::ClosedByPeer { peer: *peer });
+
+                return Err(protocol_error(TcpError::Closed));
+            }
+
+            buffer.extend_from_slice(&chunk[..read]);
+        }
+    }
+}
+
+impl<'a, Q, R, P> Protocol<'a> for TcpProtocol<Q, R, P>
+where
+    Q: Query + 'a,
+    R: Response + 'a,
+    P: Parser<'a, Q, R> + Send + Sync,
+    P::SE: Send + Sync,
+    P::DE: Send + Sync,
+{
+    type Q = Q;
+    type R = R;
+    type P = P;
+    type E = TcpError;
+
+    fn retry_policy(&self) -> RetryPolicy {
+        self.retry_policy.clone()
+    }
+
+    fn subscribers(&self) -> Option<&Subscribers> {
+        Some(&self.subscribers)
+    }
+
+    fn meter(&self) -> Option<&Meter> {
+        Some(&self.meter)
+    }
+
+    fn metrics(&self) -> Option<&dyn MetricsSink> {
+        self.metrics.as_deref()
+    }
+
+    async fn _connect(
+        &self,
+        address: SocketAddr,
+        timeouts: &TimeoutSettings,
+    ) -> Result<(), Error<Self::E>> {
+        if let Some(rate_limiter) = &self.rate_limiter {
+            rate_limiter.acquire(address).await;
+        }
+
+        let connect = async {
+            let stream = match self.bind.configured_for(address) {
+                Some(local) => TcpStream::connect_from(local, address).await,
+                None => TcpStream::connect(address).await,
+            };
+
+            stream.map_err(|err| protocol_error(TcpError::Io(err)))
+        };
+
+        let stream = with_timeout(timeouts.connect, "TCP connect timed out", connect).await?;
+
+        *self.connection.lock().await = Some(Connection {
+            peer: address,
+            stream,
+            buffer: BytesMut::new(),
+        });
+
+        let previous = self.last_peer.lock().unwrap().replace(address);
+
+        self.subscribers.emit(if previous == Some(address) {
+            ConnectionEvent::Reconnected { peer: address }
+        } else {
+            ConnectionEvent::Connected { peer: address }
+        });
+
+        Ok(())
+    }
+
+    async fn send_query(
+        &self,
+        query: Self::Q,
+        timeouts: &TimeoutSettings,
+    ) -> Result<(), Error<Self::E>> {
+        let payload = self
+            .parser
+            .serialize_query(&query)
+            .map_err(|err| err.map(|err| TcpError::Parse(Box::new(err))))?;
+
+        self.write_frame(&payload, timeouts).await
+    }
+
+    async fn receive_response(
+        &self,
+        timeouts: &TimeoutSettings,
+    ) -> Result<Self::R, Error<Self::E>> {
+        let frame = self.read_frame(timeouts).await?;
+
+        self.parser
+            .deserialize_response(frame)
+            .map_err(|err| err.map(|err| TcpError::Parse(Box::new(err))))
+    }
+
+    /// Takes the stream out of the protocol and shuts it down in the background.
+    ///
+    /// If no runtime is available or the `silent` feature is enabled, the stream is
+    /// dropped instead, which closes the socket immediately. If the connection is
+    /// currently in use, it is left to its user and closed when the protocol is dropped.
+    fn schedule_disconnect(&self) {
+        let Some(mut connection) = self.connection.try_lock() else {
+            return;
+        };
+
+        let Some(mut connection) = connection.take() else {
+            return;
+        };
+
+        self.subscribers.emit(ConnectionEvent::Disconnected {
+            peer: connection.peer,
+        });
+
+        runtime::spawn(async move {
+            let _ = connection.stream.shutdown().await;
+        });
+    }
+
+    async fn disconnect(&self) -> Result<(), Error<Self::E>> {
+        let connection = self.connection.lock().await.take();
+
+        let Some(mut connection) = connection else {
+            return Ok(());
+        };
+
+        self.subscribers.emit(ConnectionEvent::Disconnected {
+            peer: connection.peer,
+        });
+
+        connection
+            .stream
+            .shutdown()
+            .await
+            .map_err(|err| protocol_error(TcpError::Io(err)))
+    }
+}
+
+impl<'a, Q, R, P> RawTransport<'a> for TcpProtocol<Q, R, P>
+where
+    Q: Query + 'a,
+    R: Response + 'a,
+    P: Parser<'a, Q, R> + Send + Sync,
+    P::SE: Send + Sync,
+    P::DE: Send + Sync,
+{
+    /// Sends `data` as a single frame, bypassing the parser.
+    async fn send(&self, data: &[u8], timeouts: &TimeoutSettings) -> Result<(), Error<Self::E>> {
+        self.write_frame(data, timeouts).await
+    }
+
+    /// Receives the next frame, bypassing the parser.
+    async fn receive(&self, timeouts: &TimeoutSettings) -> Result<Bytes, Error<Self::E>> {
+        self.read_frame(timeouts).await
+    }
+}
+
+/// Wraps a transport error as a protocol error.
+pub(crate) fn protocol_error(err: TcpError) -> Error<TcpError> {
+    let message = match &err {
+        TcpError::Io(_) => "TCP stream operation failed",
+        TcpError::NotConnected => "TCP protocol is not connected",
+        TcpError::Closed => "TCP connection closed by peer",
+        TcpError::Frame(_) => "Invalid TCP frame",
+        TcpError::Parse(_) => "Failed to parse TCP frame",
+        TcpError::Rejected => "The server rejected the credentials",
+    };
+
+    let kind = match &err {
+        TcpError::Io(err) => ErrorKind::from_io(err),
+        TcpError::NotConnected => ErrorKind::NotConnected,
+        TcpError::Closed => ErrorKind::Truncated,
+        TcpError::Frame(_) | TcpError::Parse(_) => ErrorKind::InvalidPacket,
+        TcpError::Rejected => ErrorKind::Other,
+    };
+
+    Error::ProtocolError(ErrorDetail::new(message, Some(err)).with_kind(kind))
+}
+
+/// Builds the error returned when an operation requires a connection.
+fn not_connected() -> Error<TcpError> {
+    protocol_error(TcpError::NotConnected)
+}
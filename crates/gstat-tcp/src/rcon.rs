@@ -0,0 +1,333 @@
+use crate::{
+    error::TcpError,
+    framing::{Endian, Framing, LengthWidth},
+    protocol::protocol_error,
+};
+
+use gstat_core::{
+    prelude::{Error, TimeoutSettings},
+    runtime::{TcpStream, READ_CHUNK_LEN},
+    standards::correlation::CorrelationIds,
+    timeout::with_timeout,
+};
+
+use std::net::SocketAddr;
+
+use bytes::{Buf, Bytes, BytesMut};
+
+/// The packet type of authentication requests.
+pub const SERVERDATA_AUTH: i32 = 3;
+/// The packet type of answers to authentication requests.
+pub const SERVERDATA_AUTH_RESPONSE: i32 = 2;
+/// The packet type of commands, which shares its value with `SERVERDATA_AUTH_RESPONSE`.
+pub const SERVERDATA_EXECCOMMAND: i32 = 2;
+/// The packet type of command output.
+pub const SERVERDATA_RESPONSE_VALUE: i32 = 0;
+
+/// The largest packet accepted from the server, in bytes.
+const MAX_PACKET_LEN: usize = 64 * 1024;
+
+/// A packet of the Source RCON protocol, without its length prefix.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Packet {
+    /// The request ID, echoed by the server in its answers.
+    pub id: i32,
+    /// The packet type, e.g. `SERVERDATA_EXECCOMMAND`.
+    pub kind: i32,
+    /// The body, e.g. the command or its output.
+    pub body: Bytes,
+}
+
+impl Packet {
+    /// Creates a packet.
+    ///
+    /// # Parameters
+    ///
+    /// * `id`: The request ID.
+    /// * `kind`: The packet type.
+    /// * `body`: The body.
+    pub fn new(id: i32, kind: i32, body: impl Into<Bytes>) -> Self {
+        Packet {
+            id,
+            kind,
+            body: body.into(),
+        }
+    }
+
+    /// Encodes the packet as the ID, the type and the null terminated body, followed by
+    /// the empty string every packet ends with.
+    pub fn encode(&self) -> Vec<u8> {
+        let mut payload = Vec::with_capacity(self.body.len() + 10);
+
+        payload.extend_from_slice(&self.id.to_le_bytes());
+        payload.extend_from_slice(&self.kind.to_le_bytes());
+        payload.extend_from_slice(&self.body);
+        payload.extend_from_slice(&[0, 0]);
+
+        payload
+    }
+
+    /// Decodes a packet from a frame without its length prefix.
+    ///
+    /// # Parameters
+    ///
+    /// * `frame`: The bytes following the length prefix.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing either the packet or a `TcpError` if the frame is too short.
+    pub fn decode(mut frame: Bytes) -> Result<Self, TcpError> {
+        if frame.len() < 10 {
+            return Err(TcpError::Frame(format!(
+                "RCON packet of {} bytes is shorter than its header",
+                frame.len()
+            )));
+        }
+
+        let id = frame.get_i32_le();
+        let kind = frame.get_i32_le();
+        let end = frame
+            .iter()
+            .position(|&byte| byte == 0)
+            .unwrap_or(frame.len());
+
+        Ok(Packet {
+            id,
+            kind,
+            body: frame.split_to(end),
+        })
+    }
+}
+
+/// Returns the framing of Source RCON packets: a little endian 32-bit length, which
+/// doesn't count itself.
+pub fn framing() -> Framing {
+    Framing::LengthPrefixed {
+        width: LengthWidth::U32,
+        endian: Endian::Little,
+        inclusive: false,
+    }
+}
+
+/// `SourceRcon` is a remote console connection speaking the Source RCON protocol.
+///
+/// Besides Source engine games, Minecraft servers accept the protocol on their RCON port.
+/// Commands only run once `authenticate` has succeeded, and their output is collected
+/// across as many packets as the server splits it into.
+///
+/// ```no_run
+/// # async fn run() -> Result<(), gstat_core::prelude::Error<gstat_tcp::TcpError>> {
+/// use gstat_core::prelude::TimeoutSettings;
+/// use gstat_tcp::SourceRcon;
+///
+/// let timeouts = TimeoutSettings::default();
+/// let mut rcon = SourceRcon::connect("192.0.2.1:27015".parse().unwrap(), &timeouts).await?;
+///
+/// rcon.authenticate("secret", &timeouts).await?;
+/// println!("{}", rcon.exec("status", &timeouts).await?);
+/// # Ok(())
+/// # }
+/// ```
+pub struct SourceRcon {
+    peer: SocketAddr,
+    stream: TcpStream,
+    buffer: BytesMut,
+    framing: Framing,
+    ids: CorrelationIds,
+}
+
+impl SourceRcon {
+    /// Connects to the remote console of a server.
+    ///
+    /// # Parameters
+    ///
+    /// * `address`: The address of the remote console.
+    /// * `timeouts`: The time limit for connecting.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing either the unauthenticated connection or an `Error`.
+    pub async fn connect(
+        address: SocketAddr,
+        timeouts: &TimeoutSettings,
+    ) -> Result<Self, Error<TcpError>> {
+        let connect = async {
+            TcpStream::connect(address)
+                .await
+                .map_err(|err| protocol_error(TcpError::Io(err)))
+        };
+
+        let stream = with_timeout(timeouts.connect, "RCON connect timed out", connect).await?;
+
+        Ok(SourceRcon {
+            peer: address,
+            stream,
+            buffer: BytesMut::new(),
+            framing: framing(),
+            ids: CorrelationIds::new().with_mask(0x7fff_ffff),
+        })
+    }
+
+    /// Returns the address of the remote console.
+    pub fn peer(&self) -> SocketAddr {
+        self.peer
+    }
+
+    /// Authenticates the connection with the RCON password of the server.
+    ///
+    /// # Parameters
+    ///
+    /// * `password`: The RCON password.
+    /// * `timeouts`: The time limits for sending the password and reading the answer.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` which is `Ok` if the server accepted the password, or an `Error` carrying
+    /// `TcpError::Rejected` if it didn't.
+    pub async fn authenticate(
+        &mut self,
+        password: &str,
+        timeouts: &TimeoutSettings,
+    ) -> Result<(), Error<TcpError>> {
+        let id = self.next_id();
+
+        self.write(
+            &Packet::new(id, SERVERDATA_AUTH, password.as_bytes().to_vec()),
+            timeouts,
+        )
+        .await?;
+
+        // Source servers send an empty `SERVERDATA_RESPONSE_VALUE` before the answer.
+        loop {
+            let packet = self.read(timeouts).await?;
+
+            if packet.kind != SERVERDATA_AUTH_RESPONSE {
+                continue;
+            }
+
+            match packet.id {
+                -1 => return Err(protocol_error(TcpError::Rejected)),
+                answer if answer == id => return Ok(()),
+                _ => continue,
+            }
+        }
+    }
+
+    /// Runs a command and returns its output.
+    ///
+    /// Servers split long output across several packets without marking the last one, so
+    /// an empty `SERVERDATA_RESPONSE_VALUE` follows the command. Servers answer packets in
+    /// order, so the output is complete once the answer to that packet arrives.
+    ///
+    /// # Parameters
+    ///
+    /// * `command`: The console command, e.g. `status`.
+    /// * `timeouts`: The time limits for sending the command and reading each packet.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing either the output of the command or an `Error`.
+    pub async fn exec(
+        &mut self,
+        command: &str,
+        timeouts: &TimeoutSettings,
+    ) -> Result<String, Error<TcpError>> {
+        let id = self.next_id();
+        let marker = self.next_id();
+
+        self.write(
+            &Packet::new(id, SERVERDATA_EXECCOMMAND, command.as_bytes().to_vec()),
+            timeouts,
+        )
+        .await?;
+        self.write(
+            &Packet::new(marker, SERVERDATA_RESPONSE_VALUE, Bytes::new()),
+            timeouts,
+        )
+        .await?;
+
+        let mut output = Vec::new();
+
+        loop {
+            let packet = self.read(timeouts).await?;
+
+            if packet.id == marker {
+                return Ok(String::from_utf8_lossy(&output).into_owned());
+            }
+
+            if packet.id == id && packet.kind == SERVERDATA_RESPONSE_VALUE {
+                output.extend_from_slice(&packet.body);
+            }
+        }
+    }
+
+    /// Shuts the connection down.
+    pub async fn close(mut self) -> Result<(), Error<TcpError>> {
+        self.stream
+            .shutdown()
+            .await
+            .map_err(|err| protocol_error(TcpError::Io(err)))
+    }
+
+    /// Returns the next request ID.
+    fn next_id(&self) -> i32 {
+        self.ids.next_id().as_u32() as i32
+    }
+
+    /// Writes a packet within the write time limit.
+    async fn write(
+        &mut self,
+        packet: &Packet,
+        timeouts: &TimeoutSettings,
+    ) -> Result<(), Error<TcpError>> {
+        let frame = self
+            .framing
+            .encode(&packet.encode())
+            .map_err(protocol_error)?;
+        let stream = &mut self.stream;
+
+        let write = async {
+            stream
+                .write_all(&frame)
+                .await
+                .map_err(|err| protocol_error(TcpError::Io(err)))
+        };
+
+        with_timeout(timeouts.write, "RCON write timed out", write).await
+    }
+
+    /// Reads the next packet within the read time limit.
+    async fn read(&mut self, timeouts: &TimeoutSettings) -> Result<Packet, Error<TcpError>> {
+        let Self {
+            stream,
+            buffer,
+            framing,
+            ..
+        } = self;
+
+        let read = async {
+            loop {
+                if let Some(frame) = framing
+                    .decode(buffer, MAX_PACKET_LEN)
+                    .map_err(protocol_error)?
+                {
+                    return Packet::decode(frame).map_err(protocol_error);
+                }
+
+                let mut chunk = [0; READ_CHUNK_LEN];
+                let read = stream
+                    .read(&mut chunk)
+                    .await
+                    .map_err(|err| protocol_error(TcpError::Io(err)))?;
+
+                if read == 0 {
+                    return Err(protocol_error(TcpError::Closed));
+                }
+
+                buffer.extend_from_slice(&chunk[..read]);
+            }
+        };
+
+        with_timeout(timeouts.read, "RCON read timed out", read).await
+    }
+}
@@ -0,0 +1,365 @@
+use crate::{error::TcpError, protocol::protocol_error};
+
+use gstat_core::{
+    prelude::{
+        Error, Game, Parser, Player, Protocol, Query, QueryOptions, Response, ServerInfo,
+        TimeoutSettings,
+    },
+    registry::{self, GameEntry},
+    slp::SlpConnection,
+    standards::query::IntoQuery,
+};
+
+pub use gstat_core::slp::SlpMode;
+
+use std::{io, net::SocketAddr, sync::Mutex as SyncMutex, time::Duration};
+
+use async_lock::Mutex;
+use bytes::Bytes;
+use serde_json::Value as Json;
+
+/// A Minecraft Server List Ping query, whose game-specific options are its `SlpMode`.
+///
+/// ```
+/// use gstat_core::prelude::Query;
+/// use gstat_tcp::slp::{SlpMode, SlpQuery};
+///
+/// let query = SlpQuery::builder().extra(SlpMode::PingOnly).build();
+/// assert_eq!(query.mode(), SlpMode::PingOnly);
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SlpQuery {
+    mode: SlpMode,
+}
+
+impl SlpQuery {
+    /// Returns what the query asks the server for.
+    pub fn mode(&self) -> SlpMode {
+        self.mode
+    }
+}
+
+impl Query for SlpQuery {
+    type E = TcpError;
+    type Options = SlpMode;
+
+    fn from_options(options: QueryOptions<Self::Options>) -> Self {
+        SlpQuery {
+            mode: options.extra,
+        }
+    }
+}
+
+/// The answer to a Server List Ping.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SlpResponse {
+    /// The status document as sent by the server, or `None` for `SlpMode::PingOnly`.
+    pub status: Option<String>,
+    /// The time between sending the ping and receiving its pong.
+    pub latency: Duration,
+}
+
+impl SlpResponse {
+    /// Parses the status document, or returns `None` if there is none or it isn't JSON.
+    fn document(&self) -> Option<Json> {
+        serde_json::from_str(self.status.as_deref()?).ok()
+    }
+}
+
+impl Response for SlpResponse {
+    type E = TcpError;
+
+    fn new() -> Result<Self, Error<Self::E>> {
+        Ok(SlpResponse::default())
+    }
+
+    fn to_common(&self) -> ServerInfo {
+        let Some(document) = self.document() else {
+            return ServerInfo::default();
+        };
+        let count = |field: &str| {
+            document["players"][field]
+                .as_u64()
+                .map_or(0, |count| u32::try_from(count).unwrap_or(u32::MAX))
+        };
+
+        let mut info = ServerInfo {
+            name: strip_formatting(&text(&document["description"])),
+            players: count("online"),
+            max_players: count("max"),
+            version: document["version"]["name"]
+                .as_str()
+                .unwrap_or_default()
+                .to_string(),
+            ..ServerInfo::default()
+        };
+
+        if let Some(protocol) = document["version"]["protocol"].as_i64() {
+            info.extra
+                .insert("protocol".to_string(), protocol.to_string());
+        }
+
+        info
+    }
+
+    /// Returns the sample of players the server lists, which is often a few of them only.
+    fn players(&self) -> Vec<Player> {
+        let Some(document) = self.document() else {
+            return Vec::new();
+        };
+        let Some(sample) = document["players"]["sample"].as_array() else {
+            return Vec::new();
+        };
+
+        sample
+            .iter()
+            .filter_map(|entry| {
+                let mut player = Player::new(entry["name"].as_str()?);
+
+                if let Some(id) = entry["id"].as_str() {
+                    player.extra.insert("id".to_string(), id.to_string());
+                }
+
+                Some(player)
+            })
+            .collect()
+    }
+
+    fn heap_size(&self) -> usize {
+        self.status.as_ref().map_or(0, String::capacity)
+    }
+}
+
+/// Queries a Minecraft server with the Server List Ping.
+///
+/// The exchange is the `SlpConnection` of gstat-core, which `detect` and `ping` use too. In
+/// `SlpMode::Status` the status document is requested first; in `SlpMode::PingOnly` the
+/// ping follows the handshake directly, so the server never builds its status document.
+/// Either way, the latency is the time the ping took to be answered.
+///
+/// # Parameters
+///
+/// * `address`: The address of the server.
+/// * `query`: The query, or a `QueryBuilder` of it, choosing the mode.
+/// * `timeouts`: The time limits for connecting, and for writing and reading each packet.
+///
+/// # Returns
+///
+/// A `Result` containing either the response or an `Error`.
+pub async fn fetch(
+    address: SocketAddr,
+    query: impl IntoQuery<SlpQuery>,
+    timeouts: &TimeoutSettings,
+) -> Result<SlpResponse, Error<TcpError>> {
+    let query = query.into_query();
+    let connection = SlpConnection::connect(address, timeouts)
+        .await
+        .map_err(|err| err.map(tcp_error))?;
+
+    exchange(connection, query.mode()).await
+}
+
+/// Runs the exchange of a query on an open connection, then closes it.
+async fn exchange(
+    mut connection: SlpConnection,
+    mode: SlpMode,
+) -> Result<SlpResponse, Error<TcpError>> {
+    let exchange = async {
+        let status = match mode {
+            SlpMode::Status => Some(connection.status().await?),
+            SlpMode::PingOnly => None,
+        };
+        let latency = connection.ping().await?;
+
+        Ok((status, latency))
+    };
+
+    let outcome = exchange
+        .await
+        .map_err(|err: Error<io::Error>| err.map(tcp_error));
+    connection.close().await;
+    let (status, latency) = outcome?;
+
+    Ok(SlpResponse { status, latency })
+}
+
+/// The parser of `SlpProtocol`, which encodes the status request and reads status
+/// documents without their framing.
+///
+/// `SlpProtocol` runs the exchange with an `SlpConnection`, so this is only called by
+/// tools working on the packets themselves, such as fixtures.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SlpParser;
+
+impl<'a> Parser<'a, SlpQuery, SlpResponse> for SlpParser {
+    type SE = TcpError;
+    type DE = TcpError;
+
+    fn _serialize_query(&self, query: &SlpQuery) -> Result<Vec<u8>, Self::SE> {
+        Ok(match query.mode() {
+            SlpMode::Status => vec![0x01, 0x00],
+            SlpMode::PingOnly => Vec::new(),
+        })
+    }
+
+    fn _deserialize_response(&self, data: Bytes) -> Result<SlpResponse, Self::DE> {
+        let status = String::from_utf8(data.to_vec())
+            .map_err(|err| TcpError::Frame(format!("status document is not UTF-8: {err}")))?;
+
+        Ok(SlpResponse {
+            status: Some(status),
+            latency: Duration::ZERO,
+        })
+    }
+}
+
+/// The Server List Ping as a `Protocol`, so Minecraft servers are queried through `Game`
+/// like any other game, e.g. with `Game::fetch_host`.
+///
+/// Connecting opens the connection, the query only chooses the `SlpMode`, and receiving
+/// the response runs the exchange of `fetch` and closes the connection.
+#[derive(Debug, Default)]
+pub struct SlpProtocol {
+    connection: Mutex<Option<SlpConnection>>,
+    mode: SyncMutex<SlpMode>,
+}
+
+impl<'a> Protocol<'a> for SlpProtocol {
+    type Q = SlpQuery;
+    type R = SlpResponse;
+    type P = SlpParser;
+    type E = TcpError;
+
+    async fn _connect(
+        &self,
+        address: SocketAddr,
+        timeouts: &TimeoutSettings,
+    ) -> Result<(), Error<Self::E>> {
+        let connection = SlpConnection::connect(address, timeouts)
+            .await
+            .map_err(|err| err.map(tcp_error))?;
+
+        *self.connection.lock().await = Some(connection);
+
+        Ok(())
+    }
+
+    async fn send_query(
+        &self,
+        query: Self::Q,
+        _timeouts: &TimeoutSettings,
+    ) -> Result<(), Error<Self::E>> {
+        *self.mode.lock().expect("SLP mode lock poisoned") = query.mode();
+
+        Ok(())
+    }
+
+    async fn receive_response(
+        &self,
+        _timeouts: &TimeoutSettings,
+    ) -> Result<Self::R, Error<Self::E>> {
+        let connection = self.connection.lock().await.take();
+        let connection = connection.ok_or_else(|| protocol_error(TcpError::NotConnected))?;
+        let mode = *self.mode.lock().expect("SLP mode lock poisoned");
+
+        exchange(connection, mode).await
+    }
+
+    /// Drops the connection, which closes the socket immediately.
+    fn schedule_disconnect(&self) {
+        if let Some(mut connection) = self.connection.try_lock() {
+            connection.take();
+        }
+    }
+
+    async fn disconnect(&self) -> Result<(), Error<Self::E>> {
+        let connection = self.connection.lock().await.take();
+
+        if let Some(connection) = connection {
+            connection.close().await;
+        }
+
+        Ok(())
+    }
+}
+
+/// Minecraft: Java Edition, queried with the Server List Ping.
+///
+/// ```no_run
+/// # async fn run() {
+/// use gstat_core::{prelude::{Game, TimeoutSettings}, resolve::SystemResolver};
+/// use gstat_tcp::slp::{Minecraft, SlpQuery};
+///
+/// let fetched = Minecraft
+///     .fetch_host(SlpQuery::default(), "play.example.com", &SystemResolver, TimeoutSettings::default())
+///     .await;
+/// # }
+/// ```
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Minecraft;
+
+impl<'a> Game<'a, SlpProtocol> for Minecraft {
+    const GAME_NAME: &'static str = "Minecraft: Java Edition";
+    const RELEASE_YEAR: u32 = 2011;
+
+    fn _protocol(&self) -> SlpProtocol {
+        SlpProtocol::default()
+    }
+
+    fn registry_entry(&self) -> Option<&'static GameEntry> {
+        registry::game("minecraft")
+    }
+}
+
+/// Converts an error of the exchange into the equivalent `TcpError`.
+fn tcp_error(err: io::Error) -> TcpError {
+    match err.kind() {
+        io::ErrorKind::InvalidData => TcpError::Frame(err.to_string()),
+        io::ErrorKind::UnexpectedEof => TcpError::Closed,
+        _ => TcpError::Io(err),
+    }
+}
+
+/// Flattens a chat component, or a plain string, into its text.
+fn text(component: &Json) -> String {
+    match component {
+        Json::String(text) => text.clone(),
+        Json::Object(fields) => {
+            let mut text = fields
+                .get("text")
+                .and_then(Json::as_str)
+                .unwrap_or_default()
+                .to_string();
+
+            for child in fields
+                .get("extra")
+                .and_then(Json::as_array)
+                .into_iter()
+                .flatten()
+            {
+                text.push_str(&self::text(child));
+            }
+
+            text
+        }
+        Json::Array(children) => children.iter().map(text).collect(),
+        _ => String::new(),
+    }
+}
+
+/// Removes the `§` formatting codes of legacy Minecraft text.
+fn strip_formatting(text: &str) -> String {
+    let mut stripped = String::with_capacity(text.len());
+    let mut chars = text.chars();
+
+    while let Some(char) = chars.next() {
+        match char {
+            '§' => {
+                chars.next();
+            }
+            char => stripped.push(char),
+        }
+    }
+
+    stripped
+}
@@ -0,0 +1,12 @@
+//! Helpers shared by the integration tests.
+
+use std::future::Future;
+
+/// Runs a future to completion on a tokio runtime of its own.
+pub fn run<F: Future>(future: F) -> F::Output {
+    tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .unwrap()
+        .block_on(future)
+}
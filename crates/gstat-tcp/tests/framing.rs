@@ -0,0 +1,173 @@
+//! Splitting byte streams into frames with each `Framing` strategy.
+
+use gstat_tcp::{Endian, FixedHeader, Framing, LengthWidth, TcpError};
+
+use bytes::BytesMut;
+
+const MAX_LEN: usize = 1024;
+
+/// Decodes every complete frame of `bytes`, fed to the framing in two halves.
+fn decode_split(framing: &Framing, bytes: &[u8]) -> Vec<Vec<u8>> {
+    let (first, second) = bytes.split_at(bytes.len() / 2);
+    let mut buffer = BytesMut::from(first);
+    let mut frames = Vec::new();
+
+    while let Some(frame) = framing.decode(&mut buffer, MAX_LEN).unwrap() {
+        frames.push(frame.to_vec());
+    }
+
+    buffer.extend_from_slice(second);
+    while let Some(frame) = framing.decode(&mut buffer, MAX_LEN).unwrap() {
+        frames.push(frame.to_vec());
+    }
+
+    assert!(buffer.is_empty());
+    frames
+}
+
+fn is_frame_error<T>(result: Result<T, TcpError>) -> bool {
+    matches!(result, Err(TcpError::Frame(_)))
+}
+
+#[test]
+fn length_prefixed_frames_round_trip() {
+    for (width, endian, inclusive, prefix) in [
+        (LengthWidth::U8, Endian::Big, false, vec![5]),
+        (LengthWidth::U16, Endian::Big, false, vec![0, 5]),
+        (LengthWidth::U16, Endian::Little, false, vec![5, 0]),
+        (LengthWidth::U32, Endian::Little, true, vec![9, 0, 0, 0]),
+        (LengthWidth::U32, Endian::Big, true, vec![0, 0, 0, 9]),
+    ] {
+        let framing = Framing::LengthPrefixed {
+            width,
+            endian,
+            inclusive,
+        };
+
+        let frame = framing.encode(b"hello").unwrap();
+        assert_eq!(frame[..prefix.len()], prefix[..]);
+        assert_eq!(&frame[prefix.len()..], b"hello");
+
+        let stream = [frame.clone(), frame].concat();
+        assert_eq!(decode_split(&framing, &stream), [b"hello", b"hello"]);
+    }
+}
+
+#[test]
+fn length_prefixed_frames_are_checked() {
+    let framing = Framing::LengthPrefixed {
+        width: LengthWidth::U16,
+        endian: Endian::Big,
+        inclusive: true,
+    };
+
+    // A partial frame leaves the buffer untouched.
+    let mut buffer = BytesMut::from(&[0, 7, b'a'][..]);
+    assert_eq!(framing.decode(&mut buffer, MAX_LEN).unwrap(), None);
+    assert_eq!(buffer.len(), 3);
+
+    // An inclusive length can't be shorter than the prefix.
+    let mut buffer = BytesMut::from(&[0, 1][..]);
+    assert!(is_frame_error(framing.decode(&mut buffer, MAX_LEN)));
+
+    // Oversize frames fail as soon as their length is known.
+    let mut buffer = BytesMut::from(&[0xff, 0xff][..]);
+    assert!(is_frame_error(framing.decode(&mut buffer, MAX_LEN)));
+
+    // Payloads too long for the length field can't be encoded.
+    let narrow = Framing::LengthPrefixed {
+        width: LengthWidth::U8,
+        endian: Endian::Big,
+        inclusive: false,
+    };
+    assert!(is_frame_error(narrow.encode(&[0; 256])));
+}
+
+#[test]
+fn delimited_frames_are_split_at_the_delimiter() {
+    let framing = Framing::Delimited(b"\r\n".to_vec());
+
+    let stream = [
+        framing.encode(b"one").unwrap(),
+        framing.encode(b"two").unwrap(),
+    ]
+    .concat();
+    assert_eq!(stream, b"one\r\ntwo\r\n");
+    assert_eq!(decode_split(&framing, &stream), [b"one", b"two"]);
+
+    // Without a delimiter, the frame is incomplete until it gets too long.
+    let mut buffer = BytesMut::from(&b"partial"[..]);
+    assert_eq!(framing.decode(&mut buffer, MAX_LEN).unwrap(), None);
+
+    let mut buffer = BytesMut::from(&[b'a'; MAX_LEN + 1][..]);
+    assert!(is_frame_error(framing.decode(&mut buffer, MAX_LEN)));
+
+    let mut buffer = BytesMut::from(&b"x\n"[..]);
+    assert!(is_frame_error(
+        Framing::Delimited(Vec::new()).decode(&mut buffer, MAX_LEN)
+    ));
+}
+
+#[test]
+fn fixed_header_frames_round_trip() {
+    for (endian, inclusive, field) in [
+        (Endian::Little, true, [10, 0]),
+        (Endian::Big, true, [0, 10]),
+        (Endian::Little, false, [6, 0]),
+        (Endian::Big, false, [0, 6]),
+    ] {
+        let framing = Framing::FixedHeader(FixedHeader {
+            header_len: 4,
+            length_offset: 2,
+            width: LengthWidth::U16,
+            endian,
+            inclusive,
+        });
+
+        let frame = framing.encode(b"\x01\x02\0\0body!!").unwrap();
+        assert_eq!(frame[..2], [1, 2]);
+        assert_eq!(frame[2..4], field);
+
+        let stream = [frame.clone(), frame.clone()].concat();
+        assert_eq!(decode_split(&framing, &stream), [frame.clone(), frame]);
+    }
+}
+
+#[test]
+fn fixed_header_frames_are_checked() {
+    let header = FixedHeader {
+        header_len: 4,
+        length_offset: 0,
+        width: LengthWidth::U32,
+        endian: Endian::Little,
+        inclusive: true,
+    };
+    let framing = Framing::FixedHeader(header);
+
+    let mut buffer = BytesMut::from(&[8, 0, 0, 0, 1][..]);
+    assert_eq!(framing.decode(&mut buffer, MAX_LEN).unwrap(), None);
+    assert_eq!(buffer.len(), 5);
+
+    let mut buffer = BytesMut::from(&[2, 0, 0, 0][..]);
+    assert!(is_frame_error(framing.decode(&mut buffer, MAX_LEN)));
+
+    let mut buffer = BytesMut::from(&[0, 0, 1, 0][..]);
+    assert!(is_frame_error(framing.decode(&mut buffer, MAX_LEN)));
+
+    assert!(is_frame_error(framing.encode(b"ab")));
+
+    // A length field reaching past the header fails instead of panicking.
+    let misplaced = Framing::FixedHeader(FixedHeader {
+        length_offset: 2,
+        ..header
+    });
+    let mut buffer = BytesMut::from(&[0; 8][..]);
+    assert!(is_frame_error(misplaced.decode(&mut buffer, MAX_LEN)));
+    assert!(is_frame_error(misplaced.encode(&[0; 8])));
+
+    let overflowing = Framing::FixedHeader(FixedHeader {
+        length_offset: usize::MAX,
+        ..header
+    });
+    assert!(is_frame_error(overflowing.encode(&[0; 8])));
+}
@@ -0,0 +1,28 @@
+//! Looking up the games the frontends query, scripted or native.
+
+use gstat_core::script::ScriptRegistry;
+use gstat_tcp::lookup::{self, LookupError, QueryGame};
+
+#[test]
+fn native_games_need_no_script() {
+    let scripts = ScriptRegistry::new();
+    let game = lookup::find_game(&scripts, "MC").unwrap();
+
+    assert!(matches!(game, QueryGame::Native(_)));
+    assert_eq!(game.id(), "minecraft");
+    assert_eq!(game.name(), "Minecraft: Java Edition");
+    assert_eq!(game.port(), 25565);
+}
+
+#[test]
+fn unknown_and_unimplemented_games_are_told_apart() {
+    let scripts = ScriptRegistry::new();
+
+    let err = lookup::find_game(&scripts, "no-such-game").unwrap_err();
+    assert!(matches!(err, LookupError::UnknownGame(_)));
+    assert!(err.to_string().contains("unknown game `no-such-game`"));
+
+    let err = lookup::find_game(&scripts, "bf2").unwrap_err();
+    assert!(matches!(err, LookupError::UnsupportedProtocol(entry) if entry.id == "bf2"));
+    assert!(err.to_string().starts_with("Battlefield 2 uses the "));
+}
@@ -0,0 +1,143 @@
+//! Exchanging frames with a server through `TcpProtocol`.
+
+use gstat_core::{
+    bytes::Bytes,
+    events::ConnectionEvent,
+    prelude::{
+        Error, Parser, Protocol, Query, QueryOptions, RawTransport, Response, ServerInfo,
+        TimeoutSettings,
+    },
+};
+use gstat_tcp::{Framing, TcpError, TcpProtocol};
+
+use std::{
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+    net::TcpListener,
+};
+
+use common::run;
+
+mod common;
+
+#[derive(Clone)]
+struct Status;
+
+impl Query for Status {
+    type E = TcpError;
+    type Options = ();
+
+    fn from_options(_options: QueryOptions) -> Self {
+        Status
+    }
+}
+
+struct Line(String);
+
+impl Response for Line {
+    type E = TcpError;
+
+    fn new() -> Result<Self, Error<Self::E>> {
+        Ok(Line(String::new()))
+    }
+
+    fn to_common(&self) -> ServerInfo {
+        ServerInfo {
+            name: self.0.clone(),
+            ..ServerInfo::default()
+        }
+    }
+}
+
+struct LineParser;
+
+impl<'a> Parser<'a, Status, Line> for LineParser {
+    type SE = TcpError;
+    type DE = TcpError;
+
+    fn _serialize_query(&self, _query: &Status) -> Result<Vec<u8>, Self::SE> {
+        Ok(b"status".to_vec())
+    }
+
+    fn _deserialize_response(&self, data: Bytes) -> Result<Line, Self::DE> {
+        Ok(Line(String::from_utf8_lossy(&data).into_owned()))
+    }
+}
+
+/// Binds a server answering every line with `echo: <line>`, written in two pieces.
+async fn server() -> SocketAddr {
+    let listener = TcpListener::bind(SocketAddr::from(([127, 0, 0, 1], 0)))
+        .await
+        .unwrap();
+    let address = listener.local_addr().unwrap();
+
+    tokio::spawn(async move {
+        let (stream, _) = listener.accept().await.unwrap();
+        let (reader, mut writer) = stream.into_split();
+        let mut lines = BufReader::new(reader).lines();
+
+        while let Ok(Some(line)) = lines.next_line().await {
+            writer.write_all(b"echo: ").await.unwrap();
+            writer.flush().await.unwrap();
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            writer
+                .write_all(format!("{line}\n").as_bytes())
+                .await
+                .unwrap();
+        }
+    });
+
+    address
+}
+
+#[test]
+fn frames_are_exchanged_over_the_stream() {
+    run(async {
+        let peer = server().await;
+        let timeouts = TimeoutSettings::uniform(Duration::from_secs(5));
+        let events = Arc::new(Mutex::new(Vec::new()));
+
+        let protocol = {
+            let events = events.clone();
+
+            TcpProtocol::new(LineParser, Framing::newline_delimited()).with_subscriber(
+                move |event: &ConnectionEvent| events.lock().unwrap().push(event.clone()),
+            )
+        };
+
+        protocol._connect(peer, &timeouts).await.unwrap();
+        protocol.send_query(Status, &timeouts).await.unwrap();
+        let response = protocol.receive_response(&timeouts).await.unwrap();
+        assert_eq!(response.0, "echo: status");
+
+        RawTransport::send(&protocol, b"raw", &timeouts)
+            .await
+            .unwrap();
+        assert_eq!(
+            RawTransport::receive(&protocol, &timeouts).await.unwrap()[..],
+            b"echo: raw"[..]
+        );
+
+        protocol.disconnect().await.unwrap();
+        assert!(RawTransport::send(&protocol, b"late", &timeouts)
+            .await
+            .is_err());
+
+        assert_eq!(
+            *events.lock().unwrap(),
+            [
+                ConnectionEvent::Connected { peer },
+                ConnectionEvent::PacketSent { peer, bytes: 7 },
+                ConnectionEvent::PacketReceived { peer, bytes: 12 },
+                ConnectionEvent::PacketSent { peer, bytes: 4 },
+                ConnectionEvent::PacketReceived { peer, bytes: 9 },
+                ConnectionEvent::Disconnected { peer },
+            ]
+        );
+    });
+}
@@ -0,0 +1,182 @@
+//! Querying Minecraft servers with the Server List Ping, with and without the status.
+
+use gstat_core::{
+    prelude::{Game, Query, Response, TimeoutSettings},
+    resolve::{Resolver, StaticResolver},
+};
+use gstat_tcp::{
+    slp::{self, Minecraft, SlpMode, SlpQuery},
+    TcpError,
+};
+
+use std::{
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+};
+
+use common::run;
+
+mod common;
+
+const STATUS: &str = concat!(
+    r#"{"version":{"name":"1.21","protocol":767},"#,
+    r#""players":{"max":20,"online":2,"sample":[{"name":"Steve","id":"069a79f4"}]},"#,
+    r#""description":{"text":"§aA ","extra":[{"text":"world"}]}}"#
+);
+
+fn timeouts() -> TimeoutSettings {
+    TimeoutSettings::uniform(Duration::from_secs(5))
+}
+
+/// Reads a packet whose length fits in one byte, returning it without its length.
+async fn read_packet(stream: &mut TcpStream) -> Option<Vec<u8>> {
+    let len = stream.read_u8().await.ok()?;
+    let mut packet = vec![0; len.into()];
+    stream.read_exact(&mut packet).await.ok()?;
+
+    Some(packet)
+}
+
+/// Encodes a varint of up to 2 bytes.
+fn varint(value: usize) -> Vec<u8> {
+    match value {
+        0..0x80 => vec![value as u8],
+        _ => vec![value as u8 | 0x80, (value >> 7) as u8],
+    }
+}
+
+/// Binds a server answering status requests and pings, recording the ID of every packet
+/// it receives.
+async fn server() -> (SocketAddr, Arc<Mutex<Vec<u8>>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    let received = Arc::new(Mutex::new(Vec::new()));
+    let log = received.clone();
+
+    tokio::spawn(async move {
+        let (mut stream, _) = listener.accept().await.unwrap();
+
+        while let Some(packet) = read_packet(&mut stream).await {
+            log.lock().unwrap().push(packet[0]);
+
+            match packet[..] {
+                // The handshake, which ends with the next state.
+                [0x00, .., state] if packet.len() > 1 => assert_eq!(state, 0x01),
+                [0x00] => {
+                    let mut body = vec![0x00];
+                    body.extend(varint(STATUS.len()));
+                    body.extend_from_slice(STATUS.as_bytes());
+
+                    let mut answer = varint(body.len());
+                    answer.extend(body);
+                    stream.write_all(&answer).await.unwrap();
+                }
+                [0x01, ..] => {
+                    stream.write_all(&[9]).await.unwrap();
+                    stream.write_all(&packet).await.unwrap();
+                }
+                _ => panic!("unexpected packet {packet:?}"),
+            }
+        }
+    });
+
+    (address, received)
+}
+
+#[test]
+fn status_queries_read_the_status_and_time_a_ping() {
+    run(async {
+        let (address, received) = server().await;
+
+        let response = slp::fetch(address, SlpQuery::new().unwrap(), &timeouts())
+            .await
+            .unwrap();
+
+        assert_eq!(response.status.as_deref(), Some(STATUS));
+        assert!(response.latency > Duration::ZERO);
+        assert_eq!(*received.lock().unwrap(), [0x00, 0x00, 0x01]);
+
+        let info = response.to_common();
+        assert_eq!(info.name, "A world");
+        assert_eq!((info.players, info.max_players), (2, 20));
+        assert_eq!(info.version, "1.21");
+        assert_eq!(info.extra["protocol"], "767");
+
+        let players = response.players();
+        assert_eq!(players.len(), 1);
+        assert_eq!(players[0].name, "Steve");
+        assert_eq!(players[0].extra["id"], "069a79f4");
+    });
+}
+
+#[test]
+fn ping_only_queries_skip_the_status() {
+    run(async {
+        let (address, received) = server().await;
+        let builder = SlpQuery::builder().extra(SlpMode::PingOnly);
+
+        let response = slp::fetch(address, builder, &timeouts()).await.unwrap();
+
+        assert_eq!(response.status, None);
+        assert!(response.latency > Duration::ZERO);
+        assert_eq!(*received.lock().unwrap(), [0x00, 0x01]);
+    });
+}
+
+#[test]
+fn minecraft_servers_are_queried_by_host_name() {
+    run(async {
+        let (address, received) = server().await;
+        let resolver = StaticResolver::new().with_host("mc.example.net", [address.ip()]);
+        let resolver: &dyn Resolver = &resolver;
+        let host = format!("mc.example.net:{}", address.port());
+
+        let fetched = Minecraft
+            .fetch_host(SlpQuery::default(), &host, resolver, timeouts())
+            .await
+            .unwrap();
+
+        assert_eq!(fetched.meta.address(), Some(address));
+        assert_eq!(fetched.response.status.as_deref(), Some(STATUS));
+        assert_eq!(fetched.response.to_common().name, "A world");
+        assert_eq!(*received.lock().unwrap(), [0x00, 0x00, 0x01]);
+    });
+}
+
+#[test]
+fn pongs_must_repeat_the_ping() {
+    run(async {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+
+            read_packet(&mut stream).await.unwrap();
+            read_packet(&mut stream).await.unwrap();
+            stream
+                .write_all(&[9, 0x01, 0, 0, 0, 0, 0, 0, 0, 0])
+                .await
+                .unwrap();
+        });
+
+        let err = slp::fetch(
+            address,
+            SlpQuery::builder().extra(SlpMode::PingOnly),
+            &timeouts(),
+        )
+        .await
+        .unwrap_err();
+
+        assert!(matches!(
+            std::error::Error::source(&err).and_then(|source| source.downcast_ref::<TcpError>()),
+            Some(TcpError::Frame(_))
+        ));
+    });
+}
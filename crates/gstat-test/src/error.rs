@@ -0,0 +1,85 @@
+use bytes::Bytes;
+
+use std::{
+    error::Error as StdError,
+    fmt::{Display, Formatter, Result as FmtResult},
+    io,
+};
+
+/// Errors produced by the mock transport.
+#[derive(Debug)]
+pub enum MockError {
+    /// An operation required a connection, but none was established.
+    NotConnected,
+    /// A packet was sent after every scripted exchange was used up.
+    Exhausted(Bytes),
+    /// A packet differs from the request the next exchange expects.
+    Mismatch {
+        /// The request the exchange expects.
+        expected: Bytes,
+        /// The packet that was sent.
+        actual: Bytes,
+    },
+    /// The parser failed to serialize a query or deserialize a response.
+    Parse(Box<dyn StdError + Send + Sync>),
+}
+
+impl Display for MockError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            Self::NotConnected => write!(f, "not connected"),
+            Self::Exhausted(actual) => write!(f, "unscripted packet {actual:?}"),
+            Self::Mismatch { expected, actual } => {
+                write!(f, "expected packet {expected:?}, got {actual:?}")
+            }
+            Self::Parse(err) => write!(f, "parse error: {err}"),
+        }
+    }
+}
+
+impl StdError for MockError {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        match self {
+            Self::Parse(err) => Some(err.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+/// Errors produced while reading or writing a fixture.
+#[derive(Debug)]
+pub enum FixtureError {
+    /// The fixture file could not be read or written.
+    Io(io::Error),
+    /// A line of the fixture is malformed.
+    Syntax {
+        /// The line number, starting at 1.
+        line: usize,
+        /// What is wrong with the line.
+        message: String,
+    },
+}
+
+impl Display for FixtureError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            Self::Io(err) => write!(f, "I/O error: {err}"),
+            Self::Syntax { line, message } => write!(f, "line {line}: {message}"),
+        }
+    }
+}
+
+impl StdError for FixtureError {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        match self {
+            Self::Io(err) => Some(err),
+            Self::Syntax { .. } => None,
+        }
+    }
+}
+
+impl From<io::Error> for FixtureError {
+    fn from(err: io::Error) -> Self {
+        Self::Io(err)
+    }
+}
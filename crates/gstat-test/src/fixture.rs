@@ -0,0 +1,204 @@
+use crate::{
+    error::FixtureError,
+    transport::{Exchange, MockTransport, Reply},
+};
+
+use gstat_core::prelude::ResponseMeta;
+
+use std::{
+    fmt::{Display, Formatter, Result as FmtResult, Write as _},
+    fs,
+    path::Path,
+    str::FromStr,
+    time::Duration,
+};
+
+use bytes::Bytes;
+
+/// The first line of every fixture file.
+const HEADER: &str = "# gstat fixture v1";
+
+/// A recording of the packets exchanged with a server, replayable through a
+/// `MockTransport`.
+///
+/// Fixtures are stored as text, one packet per line in hexadecimal, so they diff well and
+/// can be edited by hand:
+///
+/// ```text
+/// # gstat fixture v1
+/// > ffffffff54536f7572636520456e67696e6520517565727900
+/// < ffffffff4911676174...
+/// ```
+///
+/// A `>` line is a request, and the `<` lines after it are its replies. `> *` accepts any
+/// request, `< -` is a dropped reply and `< +250ms <hex>` a reply delayed by 250
+/// milliseconds. Blank lines and lines starting with `#` are ignored.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Fixture {
+    exchanges: Vec<Exchange>,
+}
+
+impl Fixture {
+    /// Creates an empty fixture.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records a query answered by a live server.
+    ///
+    /// # Parameters
+    ///
+    /// * `request`: The serialized query that was sent.
+    /// * `meta`: The metadata of the response, whose raw packets become the replies. The
+    ///   protocol must record its packets through `Protocol::meter`, as the UDP and TCP
+    ///   transports do.
+    pub fn record(request: impl Into<Bytes>, meta: &ResponseMeta) -> Self {
+        let exchange = meta
+            .raw()
+            .iter()
+            .fold(Exchange::new(request), |exchange, packet| {
+                exchange.respond(packet.clone())
+            });
+
+        Self::new().with_exchange(exchange)
+    }
+
+    /// Appends an exchange to the fixture.
+    pub fn with_exchange(mut self, exchange: Exchange) -> Self {
+        self.exchanges.push(exchange);
+        self
+    }
+
+    /// Returns the exchanges of the fixture, in order.
+    pub fn exchanges(&self) -> &[Exchange] {
+        &self.exchanges
+    }
+
+    /// Returns a transport playing the fixture back.
+    pub fn replay(&self) -> MockTransport {
+        self.exchanges
+            .iter()
+            .cloned()
+            .fold(MockTransport::new(), MockTransport::with_exchange)
+    }
+
+    /// Reads a fixture file.
+    ///
+    /// # Parameters
+    ///
+    /// * `path`: The file to read.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, FixtureError> {
+        fs::read_to_string(path)?.parse()
+    }
+
+    /// Writes the fixture to a file, replacing it if it exists.
+    ///
+    /// # Parameters
+    ///
+    /// * `path`: The file to write.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), FixtureError> {
+        Ok(fs::write(path, self.to_string())?)
+    }
+}
+
+impl Display for Fixture {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        writeln!(f, "{HEADER}")?;
+
+        for exchange in &self.exchanges {
+            match exchange.request() {
+                Some(request) => writeln!(f, "> {}", hex(request))?,
+                None => writeln!(f, "> *")?,
+            }
+
+            for reply in exchange.replies() {
+                match reply {
+                    Reply::Packet(packet) => writeln!(f, "< {}", hex(packet))?,
+                    Reply::Delayed(delay, packet) => {
+                        writeln!(f, "< +{}ms {}", delay.as_millis(), hex(packet))?
+                    }
+                    Reply::Dropped => writeln!(f, "< -")?,
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl FromStr for Fixture {
+    type Err = FixtureError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let mut exchanges = Vec::new();
+
+        for (index, line) in text.lines().enumerate() {
+            let line = line.trim();
+            let syntax = |message: &str| FixtureError::Syntax {
+                line: index + 1,
+                message: message.to_string(),
+            };
+
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let mut chars = line.chars();
+            let direction = chars.next();
+            let rest = chars.as_str().trim_start();
+
+            match direction {
+                Some('>') => exchanges.push(match rest {
+                    "*" => Exchange::any(),
+                    packet => Exchange::new(unhex(packet).ok_or_else(|| syntax("invalid hex"))?),
+                }),
+                Some('<') => {
+                    let exchange = exchanges
+                        .pop()
+                        .ok_or_else(|| syntax("reply before the first request"))?;
+                    let reply = match rest.strip_prefix('+') {
+                        _ if rest == "-" => Reply::Dropped,
+                        Some(delayed) => {
+                            let (delay, packet) = delayed
+                                .split_once(char::is_whitespace)
+                                .ok_or_else(|| syntax("delayed reply without a packet"))?;
+                            let millis = delay
+                                .strip_suffix("ms")
+                                .and_then(|millis| millis.parse().ok())
+                                .ok_or_else(|| syntax("invalid delay"))?;
+                            let packet =
+                                unhex(packet.trim()).ok_or_else(|| syntax("invalid hex"))?;
+
+                            Reply::Delayed(Duration::from_millis(millis), packet)
+                        }
+                        None => Reply::Packet(unhex(rest).ok_or_else(|| syntax("invalid hex"))?),
+                    };
+
+                    exchanges.push(exchange.reply(reply));
+                }
+                _ => return Err(syntax("expected a line starting with `>` or `<`")),
+            }
+        }
+
+        Ok(Fixture { exchanges })
+    }
+}
+
+fn hex(data: &[u8]) -> String {
+    data.iter().fold(String::new(), |mut text, byte| {
+        let _ = write!(text, "{byte:02x}");
+        text
+    })
+}
+
+fn unhex(text: &str) -> Option<Bytes> {
+    if !text.len().is_multiple_of(2) || !text.is_ascii() {
+        return None;
+    }
+
+    (0..text.len())
+        .step_by(2)
+        .map(|index| u8::from_str_radix(&text[index..index + 2], 16).ok())
+        .collect::<Option<Vec<u8>>>()
+        .map(Bytes::from)
+}
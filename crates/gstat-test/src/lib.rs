@@ -0,0 +1,29 @@
+//! Test doubles for game modules.
+//!
+//! `MockTransport` plays back a script of `Exchange`s: each expects a request and answers
+//! it with canned packets, possibly late or not at all. `MockProtocol` runs a parser over
+//! such a transport, so a game module's queries and responses can be tested through the
+//! same `Protocol` calls `Game::fetch` makes, without a real server.
+//!
+//! A `Fixture` records the packets of a live query, e.g. with `gstat query --record`, and
+//! replays them later, so parsers keep being tested against real servers after those go
+//! offline.
+//!
+//! `loopback_server` binds a real UDP server answering with a fixed packet, such as
+//! `SOURCE_INFO_PACKET`, for the tests of services and bindings that resolve and query
+//! addresses themselves.
+
+pub mod error;
+pub mod fixture;
+pub mod loopback;
+pub mod protocol;
+pub mod transport;
+
+pub use error::{FixtureError, MockError};
+pub use fixture::Fixture;
+pub use loopback::{
+    loopback_server, LoopbackServer, SOURCE_INFO_GAME, SOURCE_INFO_PACKET, SOURCE_INFO_SCRIPT,
+    SOURCE_INFO_SCRIPT_PATH,
+};
+pub use protocol::MockProtocol;
+pub use transport::{Exchange, MockTransport, Reply};
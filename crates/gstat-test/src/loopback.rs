@@ -0,0 +1,71 @@
+use std::{
+    net::{Ipv4Addr, SocketAddr, UdpSocket},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    thread,
+};
+
+/// The script of the `selftest-source-info` game, a Source server answering `A2S_INFO`.
+pub const SOURCE_INFO_SCRIPT: &str = include_str!("../fixtures/source-info.rhai");
+
+/// The path of `SOURCE_INFO_SCRIPT`, for programs loading their scripts from files.
+pub const SOURCE_INFO_SCRIPT_PATH: &str =
+    concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures/source-info.rhai");
+
+/// An `A2S_INFO` answer of `selftest-source-info`: the server `gstat fixture` on
+/// `cp_badlands`, with 12 players.
+pub const SOURCE_INFO_PACKET: &[u8] = include_bytes!("../fixtures/source-info.bin");
+
+/// The identifier of the game `SOURCE_INFO_SCRIPT` describes.
+pub const SOURCE_INFO_GAME: &str = "selftest-source-info";
+
+/// A UDP server on the loopback interface answering every datagram with the same packet,
+/// for tests of the services and bindings that query real sockets.
+///
+/// The server runs on a thread of its own until the process exits.
+#[derive(Clone, Debug)]
+pub struct LoopbackServer {
+    address: SocketAddr,
+    queries: Arc<AtomicUsize>,
+}
+
+impl LoopbackServer {
+    /// Returns the address the server is bound to.
+    pub fn address(&self) -> SocketAddr {
+        self.address
+    }
+
+    /// Returns the number of datagrams the server has received.
+    pub fn queries(&self) -> usize {
+        self.queries.load(Ordering::SeqCst)
+    }
+}
+
+/// Binds a server on the loopback interface answering every datagram with `answer`.
+///
+/// # Parameters
+///
+/// * `answer`: The packet sent back, e.g. `SOURCE_INFO_PACKET`.
+///
+/// # Panics
+///
+/// Panics if no port can be bound.
+pub fn loopback_server(answer: &'static [u8]) -> LoopbackServer {
+    let socket = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).expect("a loopback port is free");
+    let address = socket.local_addr().expect("the socket is bound");
+    let queries = Arc::new(AtomicUsize::new(0));
+    let counted = Arc::clone(&queries);
+
+    thread::spawn(move || {
+        let mut buffer = [0; 64];
+
+        while let Ok((_, peer)) = socket.recv_from(&mut buffer) {
+            counted.fetch_add(1, Ordering::SeqCst);
+            let _ = socket.send_to(answer, peer);
+        }
+    });
+
+    LoopbackServer { address, queries }
+}
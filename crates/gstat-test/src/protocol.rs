@@ -0,0 +1,156 @@
+use crate::{error::MockError, transport::MockTransport};
+
+use gstat_core::{
+    prelude::{
+        Error, Parser, Protocol, Query, RawTransport, Response, RetryPolicy, TimeoutSettings,
+    },
+    standards::response::Meter,
+};
+
+use std::{marker::PhantomData, net::SocketAddr};
+
+use bytes::Bytes;
+
+/// A `Protocol` implementation over a `MockTransport`.
+///
+/// Queries are serialized with the parser `P` and sent as a single packet, and each
+/// response is parsed from a single reply, as `UdpProtocol` does over a socket. Handing a
+/// game module's parser to it tests the module against canned packets.
+pub struct MockProtocol<Q, R, P> {
+    parser: P,
+    transport: MockTransport,
+    retry_policy: RetryPolicy,
+    meter: Meter,
+    _marker: PhantomData<fn() -> (Q, R)>,
+}
+
+impl<Q, R, P> MockProtocol<Q, R, P> {
+    /// Creates a new, disconnected `MockProtocol`.
+    ///
+    /// # Parameters
+    ///
+    /// * `parser`: The parser used for queries and responses.
+    /// * `transport`: The scripted transport packets go through.
+    pub fn new(parser: P, transport: MockTransport) -> Self {
+        MockProtocol {
+            parser,
+            transport,
+            retry_policy: RetryPolicy::default(),
+            meter: Meter::new(),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Sets the policy used to retry failed exchanges.
+    ///
+    /// # Parameters
+    ///
+    /// * `retry_policy`: The retry policy reported through `Protocol::retry_policy`.
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    /// Returns the parser used by the protocol.
+    pub fn parser(&self) -> &P {
+        &self.parser
+    }
+
+    /// Returns the transport of the protocol.
+    pub fn transport(&self) -> &MockTransport {
+        &self.transport
+    }
+
+    /// Receives a reply and records it in the meter.
+    async fn receive_packet(&self, timeouts: &TimeoutSettings) -> Result<Bytes, Error<MockError>> {
+        let packet = self.transport.receive(timeouts).await?;
+        self.meter.record(&packet);
+
+        Ok(packet)
+    }
+}
+
+impl<'a, Q, R, P> Protocol<'a> for MockProtocol<Q, R, P>
+where
+    Q: Query + 'a,
+    R: Response + 'a,
+    P: Parser<'a, Q, R> + Send + Sync,
+    P::SE: Send + Sync,
+    P::DE: Send + Sync,
+{
+    type Q = Q;
+    type R = R;
+    type P = P;
+    type E = MockError;
+
+    fn retry_policy(&self) -> RetryPolicy {
+        self.retry_policy.clone()
+    }
+
+    fn meter(&self) -> Option<&Meter> {
+        Some(&self.meter)
+    }
+
+    async fn _connect(
+        &self,
+        address: SocketAddr,
+        _timeouts: &TimeoutSettings,
+    ) -> Result<(), Error<Self::E>> {
+        self.transport.connect(address);
+
+        Ok(())
+    }
+
+    async fn send_query(
+        &self,
+        query: Self::Q,
+        timeouts: &TimeoutSettings,
+    ) -> Result<(), Error<Self::E>> {
+        let payload = self
+            .parser
+            .serialize_query(&query)
+            .map_err(|err| err.map(|err| MockError::Parse(Box::new(err))))?;
+
+        self.transport.send(&payload, timeouts).await
+    }
+
+    async fn receive_response(
+        &self,
+        timeouts: &TimeoutSettings,
+    ) -> Result<Self::R, Error<Self::E>> {
+        let packet = self.receive_packet(timeouts).await?;
+
+        self.parser
+            .deserialize_response(packet)
+            .map_err(|err| err.map(|err| MockError::Parse(Box::new(err))))
+    }
+
+    fn schedule_disconnect(&self) {
+        self.transport.disconnect();
+    }
+
+    async fn disconnect(&self) -> Result<(), Error<Self::E>> {
+        self.transport.disconnect();
+
+        Ok(())
+    }
+}
+
+impl<'a, Q, R, P> RawTransport<'a> for MockProtocol<Q, R, P>
+where
+    Q: Query + 'a,
+    R: Response + 'a,
+    P: Parser<'a, Q, R> + Send + Sync,
+    P::SE: Send + Sync,
+    P::DE: Send + Sync,
+{
+    /// Sends `data` as a single packet, bypassing the parser.
+    async fn send(&self, data: &[u8], timeouts: &TimeoutSettings) -> Result<(), Error<Self::E>> {
+        self.transport.send(data, timeouts).await
+    }
+
+    /// Receives the next reply, bypassing the parser.
+    async fn receive(&self, timeouts: &TimeoutSettings) -> Result<Bytes, Error<Self::E>> {
+        self.receive_packet(timeouts).await
+    }
+}
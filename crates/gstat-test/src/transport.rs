@@ -0,0 +1,286 @@
+use crate::error::MockError;
+
+use gstat_core::{
+    prelude::{Error, ErrorDetail, ErrorKind, TimeoutSettings},
+    runtime,
+    timeout::with_timeout,
+};
+
+use std::{
+    collections::VecDeque,
+    fmt::{Debug, Formatter, Result as FmtResult},
+    net::SocketAddr,
+    sync::{Arc, Mutex, MutexGuard},
+    time::Duration,
+};
+
+use bytes::Bytes;
+
+/// How the mock server answers one request.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Reply {
+    /// A packet available at once.
+    Packet(Bytes),
+    /// A packet available after a delay, which counts against the read time limit.
+    Delayed(Duration, Bytes),
+    /// A packet lost on the way, so the receive waits out the read time limit.
+    Dropped,
+}
+
+/// A scripted request and the replies it triggers.
+///
+/// The replies are read in order by the receives following the request. A receive with
+/// no reply left behaves like a `Reply::Dropped`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Exchange {
+    request: Option<Bytes>,
+    replies: Vec<Reply>,
+}
+
+impl Exchange {
+    /// Creates an exchange expecting exactly `request`.
+    ///
+    /// # Parameters
+    ///
+    /// * `request`: The bytes the next packet sent must equal.
+    pub fn new(request: impl Into<Bytes>) -> Self {
+        Exchange {
+            request: Some(request.into()),
+            replies: Vec::new(),
+        }
+    }
+
+    /// Creates an exchange accepting any request, e.g. one carrying a random challenge.
+    pub fn any() -> Self {
+        Exchange {
+            request: None,
+            replies: Vec::new(),
+        }
+    }
+
+    /// Answers the request with a packet.
+    pub fn respond(self, packet: impl Into<Bytes>) -> Self {
+        self.reply(Reply::Packet(packet.into()))
+    }
+
+    /// Answers the request with a packet after `delay`.
+    pub fn respond_after(self, delay: Duration, packet: impl Into<Bytes>) -> Self {
+        self.reply(Reply::Delayed(delay, packet.into()))
+    }
+
+    /// Drops the next answer to the request.
+    pub fn drop_reply(self) -> Self {
+        self.reply(Reply::Dropped)
+    }
+
+    /// Adds a reply to the request.
+    pub fn reply(mut self, reply: Reply) -> Self {
+        self.replies.push(reply);
+        self
+    }
+
+    /// Returns the request the exchange expects, or `None` if it accepts any.
+    pub fn request(&self) -> Option<&Bytes> {
+        self.request.as_ref()
+    }
+
+    /// Returns the replies to the request.
+    pub fn replies(&self) -> &[Reply] {
+        &self.replies
+    }
+}
+
+#[derive(Default)]
+struct State {
+    exchanges: VecDeque<Exchange>,
+    replies: VecDeque<Reply>,
+    sent: Vec<Bytes>,
+    peer: Option<SocketAddr>,
+    connects: usize,
+}
+
+/// An in-memory transport playing back a script of exchanges.
+///
+/// Every packet sent takes the next `Exchange` of the script and fails unless it matches
+/// the expected request; the receives that follow read the exchange's replies. Clones
+/// share the script, so a test can keep one to inspect what was sent after handing
+/// another to a `MockProtocol`.
+#[derive(Clone, Default)]
+pub struct MockTransport {
+    state: Arc<Mutex<State>>,
+}
+
+impl MockTransport {
+    /// Creates a transport with an empty script.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends an exchange to the script.
+    ///
+    /// # Parameters
+    ///
+    /// * `exchange`: The exchange played after the ones already scripted.
+    pub fn with_exchange(self, exchange: Exchange) -> Self {
+        self.push(exchange);
+        self
+    }
+
+    /// Appends an exchange to the script, even while the transport is in use.
+    pub fn push(&self, exchange: Exchange) {
+        self.lock().exchanges.push_back(exchange);
+    }
+
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|err| err.into_inner())
+    }
+
+    /// Marks the transport as connected to `address`.
+    ///
+    /// Replies left over from a previous connection are discarded.
+    pub fn connect(&self, address: SocketAddr) {
+        let mut state = self.lock();
+
+        state.peer = Some(address);
+        state.connects += 1;
+        state.replies.clear();
+    }
+
+    /// Marks the transport as disconnected.
+    pub fn disconnect(&self) {
+        self.lock().peer = None;
+    }
+
+    /// Sends a packet, which must match the next exchange of the script.
+    ///
+    /// # Parameters
+    ///
+    /// * `data`: The packet to send.
+    /// * `timeouts`: Unused, since sends complete at once.
+    pub async fn send(
+        &self,
+        data: &[u8],
+        _timeouts: &TimeoutSettings,
+    ) -> Result<(), Error<MockError>> {
+        let mut state = self.lock();
+
+        if state.peer.is_none() {
+            return Err(mock_error(MockError::NotConnected));
+        }
+
+        let actual = Bytes::copy_from_slice(data);
+        state.sent.push(actual.clone());
+
+        let exchange = state
+            .exchanges
+            .pop_front()
+            .ok_or_else(|| mock_error(MockError::Exhausted(actual.clone())))?;
+
+        if let Some(expected) = exchange.request {
+            if expected != actual {
+                return Err(mock_error(MockError::Mismatch { expected, actual }));
+            }
+        }
+
+        state.replies.extend(exchange.replies);
+
+        Ok(())
+    }
+
+    /// Receives the next reply of the current exchange within the read time limit.
+    ///
+    /// # Parameters
+    ///
+    /// * `timeouts`: The time limits to respect; `TimeoutSettings::read` applies here.
+    pub async fn receive(&self, timeouts: &TimeoutSettings) -> Result<Bytes, Error<MockError>> {
+        let reply = {
+            let mut state = self.lock();
+
+            if state.peer.is_none() {
+                return Err(mock_error(MockError::NotConnected));
+            }
+
+            state.replies.pop_front().unwrap_or(Reply::Dropped)
+        };
+
+        match reply {
+            Reply::Packet(packet) => Ok(packet),
+            Reply::Delayed(delay, packet) => {
+                with_timeout(timeouts.read, "Mock receive timed out", async {
+                    runtime::sleep(delay).await;
+
+                    Ok(packet)
+                })
+                .await
+            }
+            Reply::Dropped => {
+                // Waits out the limit rather than pending forever, so a script can't hang a
+                // test that sets no read limit.
+                if let Some(limit) = timeouts.read {
+                    runtime::sleep(limit).await;
+                }
+
+                Err(Error::ProtocolError(
+                    ErrorDetail::new("Mock receive timed out", None).with_kind(ErrorKind::Timeout),
+                ))
+            }
+        }
+    }
+
+    /// Returns every packet sent so far, in order.
+    pub fn sent(&self) -> Vec<Bytes> {
+        self.lock().sent.clone()
+    }
+
+    /// Returns the address the transport is connected to.
+    pub fn peer(&self) -> Option<SocketAddr> {
+        self.lock().peer
+    }
+
+    /// Returns how many times the transport was connected.
+    pub fn connects(&self) -> usize {
+        self.lock().connects
+    }
+
+    /// Returns the number of exchanges not played yet.
+    pub fn remaining(&self) -> usize {
+        self.lock().exchanges.len()
+    }
+
+    /// Panics unless every exchange of the script was played.
+    #[track_caller]
+    pub fn assert_finished(&self) {
+        let state = self.lock();
+
+        assert!(
+            state.exchanges.is_empty(),
+            "{} scripted exchanges were not played: {:?}",
+            state.exchanges.len(),
+            state.exchanges
+        );
+    }
+}
+
+impl Debug for MockTransport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        let state = self.lock();
+
+        f.debug_struct("MockTransport")
+            .field("peer", &state.peer)
+            .field("remaining", &state.exchanges.len())
+            .field("sent", &state.sent.len())
+            .finish_non_exhaustive()
+    }
+}
+
+/// Wraps a mock error as a protocol error.
+fn mock_error(err: MockError) -> Error<MockError> {
+    let (message, kind) = match &err {
+        MockError::NotConnected => ("Mock protocol is not connected", ErrorKind::NotConnected),
+        MockError::Exhausted(_) => ("Packet sent past the end of the script", ErrorKind::Other),
+        MockError::Mismatch { .. } => ("Packet differs from the script", ErrorKind::Other),
+        MockError::Parse(_) => ("Failed to parse mock packet", ErrorKind::InvalidPacket),
+    };
+
+    Error::ProtocolError(ErrorDetail::new(message, Some(err)).with_kind(kind))
+}
@@ -0,0 +1,66 @@
+//! Reading, writing and replaying fixtures.
+
+use gstat_core::{
+    bytes::Bytes,
+    prelude::{ResponseMeta, TimeoutSettings},
+};
+use gstat_test::{Exchange, Fixture, FixtureError};
+
+use std::time::Duration;
+
+#[test]
+fn fixtures_round_trip_through_text() {
+    let meta = ResponseMeta::new(Duration::from_millis(20)).with_packets(vec![
+        Bytes::from_static(b"\xff\x01"),
+        Bytes::from_static(b"\xff\x02"),
+    ]);
+    let fixture = Fixture::record(&b"\xffinfo"[..], &meta).with_exchange(
+        Exchange::any()
+            .drop_reply()
+            .respond_after(Duration::from_millis(250), &b"\x00"[..]),
+    );
+    let text = fixture.to_string();
+
+    assert_eq!(
+        text,
+        "# gstat fixture v1\n\
+         > ff696e666f\n\
+         < ff01\n\
+         < ff02\n\
+         > *\n\
+         < -\n\
+         < +250ms 00\n"
+    );
+    assert_eq!(text.parse::<Fixture>().unwrap(), fixture);
+}
+
+#[test]
+fn malformed_fixtures_are_rejected() {
+    let err = "# recorded\n< ff\n".parse::<Fixture>().unwrap_err();
+    assert!(matches!(err, FixtureError::Syntax { line: 2, .. }), "{err}");
+
+    let err = "> fff\n".parse::<Fixture>().unwrap_err();
+    assert_eq!(err.to_string(), "line 1: invalid hex");
+}
+
+#[test]
+fn fixtures_are_replayed() {
+    let fixture: Fixture = "> 01\n< 02\n".parse().unwrap();
+    let transport = fixture.replay();
+    let timeouts = TimeoutSettings::default();
+
+    transport.connect("127.0.0.1:27015".parse().unwrap());
+
+    let packet = tokio::runtime::Builder::new_current_thread()
+        .enable_time()
+        .build()
+        .unwrap()
+        .block_on(async {
+            transport.send(&[1], &timeouts).await?;
+            transport.receive(&timeouts).await
+        })
+        .unwrap();
+
+    assert_eq!(packet, [2][..]);
+    transport.assert_finished();
+}
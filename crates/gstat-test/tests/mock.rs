@@ -0,0 +1,172 @@
+//! Playing back scripted exchanges through `MockProtocol`.
+
+use gstat_core::{
+    bytes::Bytes,
+    prelude::{
+        Error, ErrorKind, Game, Parser, Protocol, Query, QueryOptions, RawTransport, Response,
+        ServerInfo, TimeoutSettings,
+    },
+};
+use gstat_test::{Exchange, MockError, MockProtocol, MockTransport};
+
+use std::{
+    error::Error as StdError,
+    fmt::{Display, Formatter, Result as FmtResult},
+    future::Future,
+    net::SocketAddr,
+    time::Duration,
+};
+
+#[derive(Debug)]
+struct EchoError;
+
+impl Display for EchoError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(f, "not a pong")
+    }
+}
+
+impl StdError for EchoError {}
+
+#[derive(Clone)]
+struct Ping;
+
+impl Query for Ping {
+    type E = EchoError;
+    type Options = ();
+
+    fn from_options(_options: QueryOptions) -> Self {
+        Ping
+    }
+}
+
+#[derive(Debug)]
+struct Pong(String);
+
+impl Response for Pong {
+    type E = EchoError;
+
+    fn new() -> Result<Self, Error<Self::E>> {
+        Ok(Pong(String::new()))
+    }
+
+    fn to_common(&self) -> ServerInfo {
+        ServerInfo {
+            name: self.0.clone(),
+            ..ServerInfo::default()
+        }
+    }
+}
+
+/// Sends `ping` and parses `pong <name>`.
+struct EchoParser;
+
+impl<'a> Parser<'a, Ping, Pong> for EchoParser {
+    type SE = EchoError;
+    type DE = EchoError;
+
+    fn _serialize_query(&self, _query: &Ping) -> Result<Vec<u8>, Self::SE> {
+        Ok(b"ping".to_vec())
+    }
+
+    fn _deserialize_response(&self, data: Bytes) -> Result<Pong, Self::DE> {
+        let name = data.strip_prefix(b"pong ").ok_or(EchoError)?;
+
+        Ok(Pong(String::from_utf8_lossy(name).into_owned()))
+    }
+}
+
+type EchoProtocol = MockProtocol<Ping, Pong, EchoParser>;
+
+struct Echo(MockTransport);
+
+impl<'a> Game<'a, EchoProtocol> for Echo {
+    const GAME_NAME: &'static str = "Echo";
+    const RELEASE_YEAR: u32 = 2024;
+
+    fn _protocol(&self) -> EchoProtocol {
+        MockProtocol::new(EchoParser, self.0.clone())
+    }
+}
+
+fn address() -> SocketAddr {
+    "127.0.0.1:27015".parse().unwrap()
+}
+
+fn block_on<F: Future>(future: F) -> F::Output {
+    tokio::runtime::Builder::new_current_thread()
+        .enable_time()
+        .build()
+        .unwrap()
+        .block_on(future)
+}
+
+#[test]
+fn games_are_fetched_from_canned_exchanges() {
+    let transport = MockTransport::new().with_exchange(Exchange::new("ping").respond("pong Echo"));
+
+    let fetched = block_on(Echo(transport.clone()).fetch(Ping, address())).unwrap();
+
+    assert_eq!(fetched.response.0, "Echo");
+    assert_eq!(fetched.meta.packet_count(), 1);
+    assert_eq!(transport.sent(), [Bytes::from_static(b"ping")]);
+    assert_eq!(transport.connects(), 1);
+    assert_eq!(transport.peer(), None);
+    transport.assert_finished();
+}
+
+#[test]
+fn unscripted_packets_are_refused() {
+    let transport = MockTransport::new().with_exchange(Exchange::new("info").respond("pong"));
+    let protocol = EchoProtocol::new(EchoParser, transport.clone());
+    let timeouts = TimeoutSettings::default();
+
+    block_on(async {
+        let err = protocol.send(b"ping", &timeouts).await.unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::NotConnected);
+
+        let _guard = protocol.connect(address(), &timeouts).await.unwrap();
+
+        let err = protocol.send_query(Ping, &timeouts).await.unwrap_err();
+        assert!(matches!(
+            err.detail().inner(),
+            Some(MockError::Mismatch { expected, actual }) if expected == "info" && actual == "ping"
+        ));
+
+        let err = protocol.send(b"ping", &timeouts).await.unwrap_err();
+        assert!(matches!(
+            err.detail().inner(),
+            Some(MockError::Exhausted(_))
+        ));
+    });
+
+    assert_eq!(transport.remaining(), 0);
+}
+
+#[test]
+fn replies_can_be_late_or_dropped() {
+    let transport = MockTransport::new()
+        .with_exchange(
+            Exchange::any()
+                .drop_reply()
+                .respond_after(Duration::from_millis(10), "pong late"),
+        )
+        .with_exchange(Exchange::any().respond_after(Duration::from_secs(5), "pong too late"));
+    let protocol = EchoProtocol::new(EchoParser, transport);
+    let timeouts = TimeoutSettings::uniform(Duration::from_millis(100));
+
+    block_on(async {
+        let _guard = protocol.connect(address(), &timeouts).await.unwrap();
+        protocol.send_query(Ping, &timeouts).await.unwrap();
+
+        let err = protocol.receive_response(&timeouts).await.unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Timeout);
+
+        let pong = protocol.receive_response(&timeouts).await.unwrap();
+        assert_eq!(pong.0, "late");
+
+        protocol.send_query(Ping, &timeouts).await.unwrap();
+        let err = protocol.receive_response(&timeouts).await.unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Timeout);
+    });
+}
@@ -0,0 +1,404 @@
+//! Batched datagram I/O for `SharedSocket::bind_batched`, using `sendmmsg` and `recvmmsg`.
+//!
+//! When many thousands of servers are polled through one socket, a system call per datagram
+//! is the bottleneck. The reader of a batched socket drains up to a batch of datagrams per
+//! call, and concurrent sends are combined: the first sender takes the flusher role, lets
+//! the other tasks queue their datagrams, and sends them all at once, the same way the
+//! reader role of `SharedSocket` works without a background task.
+
+use crate::socket::MAX_DATAGRAM_LEN;
+
+use std::{
+    future::poll_fn,
+    io, mem,
+    net::{Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV4, SocketAddrV6, UdpSocket},
+    os::fd::AsRawFd,
+    ptr,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    task::{Poll, Waker},
+};
+
+use bytes::Bytes;
+use tokio::io::unix::AsyncFd;
+
+/// The number of datagrams sent or received per system call unless configured otherwise.
+pub const DEFAULT_BATCH_SIZE: usize = 32;
+
+/// A UDP socket sending and receiving datagrams in batches.
+pub(crate) struct Batched {
+    socket: AsyncFd<UdpSocket>,
+    size: usize,
+    /// The receive buffers, used by the reader of the `SharedSocket` only.
+    buffers: Mutex<Vec<Vec<u8>>>,
+    /// The datagrams waiting for the flusher.
+    outbox: Mutex<Vec<Outgoing>>,
+    flushing: AtomicBool,
+}
+
+/// A datagram waiting to be sent.
+struct Outgoing {
+    data: Vec<u8>,
+    peer: SocketAddr,
+    sent: Arc<Sent>,
+}
+
+/// Fails the send if the datagram is dropped unsent, e.g. because the flushing task was
+/// cancelled, so its sender doesn't wait forever.
+impl Drop for Outgoing {
+    fn drop(&mut self) {
+        self.sent.complete(Err(io::Error::new(
+            io::ErrorKind::Interrupted,
+            "the batch was dropped before the datagram was sent",
+        )));
+    }
+}
+
+/// The outcome of sending a datagram, and the task waiting for it.
+#[derive(Default)]
+struct Sent {
+    state: Mutex<(Option<io::Result<()>>, Option<Waker>)>,
+}
+
+impl Sent {
+    /// Records the outcome, unless one was recorded already, and wakes the sender.
+    fn complete(&self, result: io::Result<()>) {
+        let mut state = self.state.lock().expect("send lock poisoned");
+        state.0.get_or_insert(result);
+
+        if let Some(waker) = state.1.take() {
+            waker.wake();
+        }
+    }
+
+    fn wake(&self) {
+        if let Some(waker) = self.state.lock().expect("send lock poisoned").1.take() {
+            waker.wake();
+        }
+    }
+}
+
+/// Holds the flusher role, waking the senders of the datagrams left behind when dropped,
+/// e.g. because the flushing task timed out, so one of them takes over.
+struct FlushGuard<'b> {
+    batched: &'b Batched,
+}
+
+impl Drop for FlushGuard<'_> {
+    fn drop(&mut self) {
+        self.batched.flushing.store(false, Ordering::SeqCst);
+
+        for outgoing in self.batched.outbox().iter() {
+            outgoing.sent.wake();
+        }
+    }
+}
+
+impl Batched {
+    /// Binds a batched socket on the current Tokio runtime.
+    pub(crate) fn bind(address: SocketAddr, size: usize) -> io::Result<Self> {
+        let socket = UdpSocket::bind(address)?;
+        socket.set_nonblocking(true)?;
+
+        Ok(Batched {
+            socket: AsyncFd::new(socket)?,
+            size: size.max(1),
+            buffers: Mutex::new(Vec::new()),
+            outbox: Mutex::new(Vec::new()),
+            flushing: AtomicBool::new(false),
+        })
+    }
+
+    pub(crate) fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.socket.get_ref().local_addr()
+    }
+
+    /// Sends a datagram to `peer` along with those of the other senders.
+    pub(crate) async fn send_to(&self, data: &[u8], peer: SocketAddr) -> io::Result<()> {
+        let sent = Arc::new(Sent::default());
+
+        self.outbox().push(Outgoing {
+            data: data.to_vec(),
+            peer,
+            sent: sent.clone(),
+        });
+
+        loop {
+            if self
+                .flushing
+                .compare_exchange(false, true, Ordering::SeqCst, Ordering::SeqCst)
+                .is_ok()
+            {
+                let guard = FlushGuard { batched: self };
+                self.flush().await;
+                drop(guard);
+            }
+
+            // Until the datagram is sent, the task waits for the flusher, or takes over the
+            // role when it is free, e.g. because the datagram was queued as it was handed back.
+            let result = poll_fn(|cx| {
+                let mut state = sent.state.lock().expect("send lock poisoned");
+
+                if let Some(result) = state.0.take() {
+                    return Poll::Ready(Some(result));
+                }
+
+                state.1 = Some(cx.waker().clone());
+                drop(state);
+
+                match self.flushing.load(Ordering::SeqCst) {
+                    true => Poll::Pending,
+                    false => Poll::Ready(None),
+                }
+            })
+            .await;
+
+            if let Some(result) = result {
+                return result;
+            }
+        }
+    }
+
+    /// Sends the queued datagrams until the outbox is empty.
+    async fn flush(&self) {
+        // Lets the tasks scheduled alongside this one queue their datagrams first.
+        tokio::task::yield_now().await;
+
+        loop {
+            let batch = {
+                let mut outbox = self.outbox();
+                let len = outbox.len().min(self.size);
+                outbox.drain(..len).collect::<Vec<_>>()
+            };
+
+            if batch.is_empty() {
+                return;
+            }
+
+            self.send_batch(&batch).await;
+        }
+    }
+
+    /// Sends a batch of datagrams, completing each with its outcome.
+    async fn send_batch(&self, batch: &[Outgoing]) {
+        let mut start = 0;
+
+        while start < batch.len() {
+            let pending = &batch[start..];
+
+            let sent = loop {
+                let mut guard = match self.socket.writable().await {
+                    Ok(guard) => guard,
+                    Err(err) => break Err(err),
+                };
+
+                if let Ok(sent) = guard.try_io(|socket| sendmmsg(socket.as_raw_fd(), pending)) {
+                    break sent;
+                }
+            };
+
+            match sent {
+                Ok(count) => {
+                    for outgoing in &pending[..count] {
+                        outgoing.sent.complete(Ok(()));
+                    }
+
+                    start += count;
+                }
+                // The first datagram failed, e.g. because its network is unreachable.
+                Err(err) => {
+                    pending[0].sent.complete(Err(err));
+                    start += 1;
+                }
+            }
+        }
+    }
+
+    /// Receives the datagrams available, waiting for at least one.
+    pub(crate) async fn receive(&self) -> io::Result<Vec<(SocketAddr, Bytes)>> {
+        let mut buffers = mem::take(&mut *self.buffers.lock().expect("buffers lock poisoned"));
+        buffers.resize_with(self.size, || vec![0; MAX_DATAGRAM_LEN]);
+
+        let received = loop {
+            let mut guard = self.socket.readable().await?;
+
+            if let Ok(received) = guard.try_io(|socket| recvmmsg(socket.as_raw_fd(), &mut buffers))
+            {
+                break received;
+            }
+        };
+
+        *self.buffers.lock().expect("buffers lock poisoned") = buffers;
+
+        received
+    }
+
+    fn outbox(&self) -> std::sync::MutexGuard<'_, Vec<Outgoing>> {
+        self.outbox.lock().expect("outbox lock poisoned")
+    }
+}
+
+/// Sends the datagrams of `batch` with one system call.
+///
+/// # Returns
+///
+/// The number of datagrams sent, or the error of the first one if none was.
+fn sendmmsg(fd: i32, batch: &[Outgoing]) -> io::Result<usize> {
+    let mut addresses: Vec<_> = batch
+        .iter()
+        .map(|outgoing| to_sockaddr(outgoing.peer))
+        .collect();
+    let mut iovecs: Vec<_> = batch
+        .iter()
+        .map(|outgoing| libc::iovec {
+            iov_base: outgoing.data.as_ptr() as *mut libc::c_void,
+            iov_len: outgoing.data.len(),
+        })
+        .collect();
+    let mut headers: Vec<_> = addresses
+        .iter_mut()
+        .zip(&mut iovecs)
+        .map(|((address, len), iovec)| header(address, *len, iovec))
+        .collect();
+
+    // SAFETY: every header points to an address and an iovec that outlive the call, and
+    // the iovecs point to the datagrams of `batch`, which the kernel only reads.
+    let sent = unsafe { libc::sendmmsg(fd, headers.as_mut_ptr(), headers.len() as u32, 0) };
+
+    match sent {
+        -1 => Err(io::Error::last_os_error()),
+        sent => Ok(sent as usize),
+    }
+}
+
+/// Receives up to one datagram per buffer with one system call, without waiting.
+///
+/// # Returns
+///
+/// The datagrams received whole, with their senders, or the error of the call.
+fn recvmmsg(fd: i32, buffers: &mut [Vec<u8>]) -> io::Result<Vec<(SocketAddr, Bytes)>> {
+    // SAFETY: `sockaddr_storage` is plain data, for which all zeroes is a valid value.
+    let mut addresses = vec![unsafe { mem::zeroed::<libc::sockaddr_storage>() }; buffers.len()];
+    let mut iovecs: Vec<_> = buffers
+        .iter_mut()
+        .map(|buffer| libc::iovec {
+            iov_base: buffer.as_mut_ptr() as *mut libc::c_void,
+            iov_len: buffer.len(),
+        })
+        .collect();
+    let len = mem::size_of::<libc::sockaddr_storage>() as libc::socklen_t;
+    let mut headers: Vec<_> = addresses
+        .iter_mut()
+        .zip(&mut iovecs)
+        .map(|(address, iovec)| header(address, len, iovec))
+        .collect();
+
+    // SAFETY: every header points to an address and an iovec that outlive the call, and
+    // the iovecs point to buffers of the length they give.
+    let received = unsafe {
+        libc::recvmmsg(
+            fd,
+            headers.as_mut_ptr(),
+            headers.len() as u32,
+            libc::MSG_DONTWAIT,
+            ptr::null_mut(),
+        )
+    };
+
+    if received == -1 {
+        return Err(io::Error::last_os_error());
+    }
+
+    Ok(headers
+        .iter()
+        .zip(&addresses)
+        .zip(buffers.iter())
+        .take(received as usize)
+        .filter_map(|((header, address), buffer)| {
+            // A datagram longer than its buffer was cut short by the kernel, and parsing
+            // what is left would only produce a misleading error, so it is dropped.
+            if header.msg_hdr.msg_flags & libc::MSG_TRUNC != 0 {
+                return None;
+            }
+
+            let datagram = Bytes::copy_from_slice(&buffer[..header.msg_len as usize]);
+
+            Some((from_sockaddr(address)?, datagram))
+        })
+        .collect())
+}
+
+/// Describes a message of one datagram for `sendmmsg` or `recvmmsg`.
+fn header(
+    address: &mut libc::sockaddr_storage,
+    len: libc::socklen_t,
+    iovec: &mut libc::iovec,
+) -> libc::mmsghdr {
+    // SAFETY: `mmsghdr` is plain data, for which all zeroes is a valid value.
+    let mut header: libc::mmsghdr = unsafe { mem::zeroed() };
+    header.msg_hdr.msg_name = address as *mut _ as *mut libc::c_void;
+    header.msg_hdr.msg_namelen = len;
+    header.msg_hdr.msg_iov = iovec;
+    header.msg_hdr.msg_iovlen = 1;
+    header
+}
+
+/// Converts an address to its C representation and length.
+fn to_sockaddr(address: SocketAddr) -> (libc::sockaddr_storage, libc::socklen_t) {
+    // SAFETY: `sockaddr_storage` is plain data, for which all zeroes is a valid value.
+    let mut storage: libc::sockaddr_storage = unsafe { mem::zeroed() };
+
+    let len = match address {
+        SocketAddr::V4(address) => {
+            // SAFETY: `sockaddr_storage` is large and aligned enough for any address.
+            let v4 = unsafe { &mut *(&mut storage as *mut _ as *mut libc::sockaddr_in) };
+            v4.sin_family = libc::AF_INET as libc::sa_family_t;
+            v4.sin_port = address.port().to_be();
+            v4.sin_addr.s_addr = u32::from_ne_bytes(address.ip().octets());
+            mem::size_of::<libc::sockaddr_in>()
+        }
+        SocketAddr::V6(address) => {
+            // SAFETY: `sockaddr_storage` is large and aligned enough for any address.
+            let v6 = unsafe { &mut *(&mut storage as *mut _ as *mut libc::sockaddr_in6) };
+            v6.sin6_family = libc::AF_INET6 as libc::sa_family_t;
+            v6.sin6_port = address.port().to_be();
+            v6.sin6_flowinfo = address.flowinfo();
+            v6.sin6_addr.s6_addr = address.ip().octets();
+            v6.sin6_scope_id = address.scope_id();
+            mem::size_of::<libc::sockaddr_in6>()
+        }
+    };
+
+    (storage, len as libc::socklen_t)
+}
+
+/// Converts a C address back, or returns `None` for families other than IPv4 and IPv6.
+fn from_sockaddr(storage: &libc::sockaddr_storage) -> Option<SocketAddr> {
+    match storage.ss_family as i32 {
+        libc::AF_INET => {
+            // SAFETY: the family says the storage holds a `sockaddr_in`.
+            let v4 = unsafe { &*(storage as *const _ as *const libc::sockaddr_in) };
+            let ip = Ipv4Addr::from(v4.sin_addr.s_addr.to_ne_bytes());
+
+            Some(SocketAddrV4::new(ip, u16::from_be(v4.sin_port)).into())
+        }
+        libc::AF_INET6 => {
+            // SAFETY: the family says the storage holds a `sockaddr_in6`.
+            let v6 = unsafe { &*(storage as *const _ as *const libc::sockaddr_in6) };
+            let ip = Ipv6Addr::from(v6.sin6_addr.s6_addr);
+
+            Some(
+                SocketAddrV6::new(
+                    ip,
+                    u16::from_be(v6.sin6_port),
+                    v6.sin6_flowinfo,
+                    v6.sin6_scope_id,
+                )
+                .into(),
+            )
+        }
+        _ => None,
+    }
+}
@@ -0,0 +1,50 @@
+use gstat_core::reassembly::ReassemblyError;
+
+use std::{
+    error::Error as StdError,
+    fmt::{Display, Formatter, Result as FmtResult},
+    io,
+};
+
+/// Errors produced by the UDP transport.
+#[derive(Debug)]
+pub enum UdpError {
+    /// An I/O error occurred on the underlying socket.
+    Io(io::Error),
+    /// An operation required a connection, but none was established.
+    NotConnected,
+    /// The parser failed to serialize a query or deserialize a response.
+    Parse(Box<dyn StdError + Send + Sync>),
+}
+
+impl Display for UdpError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            Self::Io(err) => write!(f, "I/O error: {err}"),
+            Self::NotConnected => write!(f, "not connected"),
+            Self::Parse(err) => write!(f, "parse error: {err}"),
+        }
+    }
+}
+
+impl StdError for UdpError {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        match self {
+            Self::Io(err) => Some(err),
+            Self::Parse(err) => Some(err.as_ref()),
+            Self::NotConnected => None,
+        }
+    }
+}
+
+impl From<ReassemblyError> for UdpError {
+    fn from(err: ReassemblyError) -> Self {
+        Self::Parse(Box::new(err))
+    }
+}
+
+impl From<io::Error> for UdpError {
+    fn from(err: io::Error) -> Self {
+        Self::Io(err)
+    }
+}
@@ -0,0 +1,16 @@
+#[cfg(all(feature = "batch", target_os = "linux"))]
+mod batch;
+pub mod error;
+pub mod pool;
+pub mod protocol;
+pub mod scanner;
+#[cfg(feature = "script-rhai")]
+pub mod script;
+pub mod socket;
+
+#[cfg(all(feature = "batch", target_os = "linux"))]
+pub use batch::DEFAULT_BATCH_SIZE;
+pub use error::UdpError;
+pub use pool::BufferPool;
+pub use protocol::UdpProtocol;
+pub use socket::{SharedSocket, SocketStrategy};
@@ -0,0 +1,121 @@
+//! Reusing receive buffers across queries.
+//!
+//! A datagram can be up to 64 KiB long, so every receive needs a buffer that large, even
+//! though answers are usually a few hundred bytes and are copied out of it. Allocating
+//! and zeroing such a buffer per receive churns the allocator when thousands of servers
+//! are polled; a `BufferPool` hands out buffers released by earlier receives instead.
+
+use crate::socket::MAX_DATAGRAM_LEN;
+
+use std::{
+    fmt::{Debug, Formatter, Result as FmtResult},
+    ops::{Deref, DerefMut},
+    sync::{Arc, LazyLock, Mutex},
+};
+
+/// The number of idle buffers the process-wide pool keeps.
+const GLOBAL_MAX_IDLE: usize = 64;
+
+/// The pool used by protocols and shared sockets that weren't given one.
+static GLOBAL: LazyLock<BufferPool> = LazyLock::new(|| BufferPool::new(GLOBAL_MAX_IDLE));
+
+struct PoolInner {
+    idle: Mutex<Vec<Vec<u8>>>,
+    max_idle: usize,
+}
+
+/// `BufferPool` keeps the receive buffers of finished receives for the next ones.
+///
+/// A buffer is taken for the length of a receive and returned afterwards, so a pool holds
+/// about as many buffers as receives run at once, up to `max_idle`; buffers returned to a
+/// full pool are freed. Clones share their buffers, so one pool can be handed to every
+/// protocol instance. Unless given another one, protocols and shared sockets use a
+/// process-wide pool.
+#[derive(Clone)]
+pub struct BufferPool {
+    inner: Arc<PoolInner>,
+}
+
+impl BufferPool {
+    /// Creates an empty pool.
+    ///
+    /// # Parameters
+    ///
+    /// * `max_idle`: The largest number of buffers kept between receives. Each takes
+    ///   64 KiB.
+    pub fn new(max_idle: usize) -> Self {
+        BufferPool {
+            inner: Arc::new(PoolInner {
+                idle: Mutex::new(Vec::new()),
+                max_idle,
+            }),
+        }
+    }
+
+    /// Returns the process-wide pool, which keeps up to 64 buffers.
+    pub fn global() -> Self {
+        GLOBAL.clone()
+    }
+
+    /// Returns the number of buffers waiting to be reused.
+    pub fn idle(&self) -> usize {
+        self.idle_buffers().len()
+    }
+
+    /// Takes a buffer large enough for any datagram, allocating one if none is idle.
+    pub(crate) fn take(&self) -> PooledBuffer {
+        let buffer = self
+            .idle_buffers()
+            .pop()
+            .unwrap_or_else(|| vec![0; MAX_DATAGRAM_LEN]);
+
+        PooledBuffer {
+            buffer,
+            pool: self.clone(),
+        }
+    }
+
+    /// Locks the idle buffers.
+    fn idle_buffers(&self) -> std::sync::MutexGuard<'_, Vec<Vec<u8>>> {
+        self.inner.idle.lock().expect("buffer pool lock poisoned")
+    }
+}
+
+impl Debug for BufferPool {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.debug_struct("BufferPool")
+            .field("idle", &self.idle())
+            .field("max_idle", &self.inner.max_idle)
+            .finish()
+    }
+}
+
+/// A buffer taken from a `BufferPool`, returned to it when dropped.
+pub(crate) struct PooledBuffer {
+    buffer: Vec<u8>,
+    pool: BufferPool,
+}
+
+impl Deref for PooledBuffer {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.buffer
+    }
+}
+
+impl DerefMut for PooledBuffer {
+    fn deref_mut(&mut self) -> &mut [u8] {
+        &mut self.buffer
+    }
+}
+
+impl Drop for PooledBuffer {
+    fn drop(&mut self) {
+        let mut idle = self.pool.idle_buffers();
+
+        if idle.len() < self.pool.inner.max_idle {
+            idle.push(std::mem::take(&mut self.buffer));
+        }
+    }
+}
@@ -0,0 +1,486 @@
+use crate::{
+    error::UdpError,
+    pool::BufferPool,
+    socket::{SharedSocket, SocketStrategy},
+};
+
+use gstat_core::{
+    address::BindAddresses,
+    events::{ConnectionEvent, ConnectionSubscriber, Subscribers},
+    metrics::MetricsSink,
+    prelude::{
+        Error, ErrorDetail, ErrorKind, Parser, Protocol, Query, RawTransport, Response,
+        RetryPolicy, TimeoutSettings,
+    },
+    rate_limit::RateLimiter,
+    reassembly::{Packet, Reassembler},
+    runtime::UdpSocket,
+    standards::{correlation::CorrelationId, response::Meter},
+    timeout::with_timeout,
+};
+
+use std::{marker::PhantomData, net::SocketAddr, sync::Arc};
+
+use async_lock::Mutex;
+use bytes::Bytes;
+
+/// The socket a connected `UdpProtocol` exchanges datagrams through.
+enum Socket {
+    /// A socket owned by this protocol instance, connected to the peer.
+    Ephemeral(UdpSocket),
+    /// A socket shared with other protocol instances.
+    Shared(SharedSocket),
+}
+
+/// Classifies a received datagram as a whole response or a fragment of one.
+type Classify = Box<dyn Fn(Bytes) -> Packet + Send + Sync>;
+
+/// Joins responses that were split across several datagrams.
+struct Reassembly {
+    classify: Classify,
+    reassembler: Mutex<Reassembler>,
+}
+
+/// An established association with a peer.
+struct Connection {
+    peer: SocketAddr,
+    socket: Socket,
+    /// The correlation ID of the last request sent through a shared socket reading them.
+    expected: Option<CorrelationId>,
+}
+
+impl Connection {
+    /// Sends a single datagram to the peer.
+    async fn send(&mut self, data: &[u8]) -> std::io::Result<()> {
+        match &self.socket {
+            Socket::Ephemeral(socket) => socket.send(data).await.map(|_| ()),
+            Socket::Shared(socket) => {
+                self.expected = socket.correlation_id(data);
+                socket.send_to(data, self.peer).await
+            }
+        }
+    }
+
+    /// Receives a single datagram from the peer, into a buffer taken from `buffers`.
+    async fn receive(&self, buffers: &BufferPool) -> std::io::Result<Bytes> {
+        match &self.socket {
+            Socket::Ephemeral(socket) => {
+                // On the heap, so the receive future stays small.
+                let mut buffer = buffers.take();
+                let len = socket.recv(&mut buffer).await?;
+
+                Ok(Bytes::copy_from_slice(&buffer[..len]))
+            }
+            Socket::Shared(socket) => socket.recv_from(self.peer, self.expected).await,
+        }
+    }
+}
+
+/// Releases the association with the peer, so a shared socket stops routing its datagrams
+/// even if the protocol is dropped while connected.
+impl Drop for Connection {
+    fn drop(&mut self) {
+        if let Socket::Shared(socket) = &self.socket {
+            socket.forget(self.peer);
+        }
+    }
+}
+
+/// A `Protocol` implementation over UDP.
+///
+/// Queries are serialized with the parser `P` and sent as a single datagram, and each
+/// response is read from a single datagram before being handed to the parser. The
+/// `SocketStrategy` decides whether each connection uses its own socket or a shared one.
+///
+/// Subscribers added with `with_subscriber` are told of every datagram sent and received.
+/// Protocols that split large responses join them with `with_reassembly`.
+pub struct UdpProtocol<Q, R, P> {
+    parser: P,
+    strategy: SocketStrategy,
+    bind: BindAddresses,
+    retry_policy: RetryPolicy,
+    rate_limiter: Option<RateLimiter>,
+    metrics: Option<Arc<dyn MetricsSink>>,
+    subscribers: Subscribers,
+    meter: Meter,
+    buffers: BufferPool,
+    reassembly: Option<Reassembly>,
+    connection: Mutex<Option<Connection>>,
+    _marker: PhantomData<fn() -> (Q, R)>,
+}
+
+impl<Q, R, P> UdpProtocol<Q, R, P> {
+    /// Creates a new, disconnected `UdpProtocol` using an ephemeral socket per connection.
+    ///
+    /// # Parameters
+    ///
+    /// * `parser`: The parser used for queries and responses.
+    pub fn new(parser: P) -> Self {
+        UdpProtocol {
+            parser,
+            strategy: SocketStrategy::default(),
+            bind: BindAddresses::new(),
+            retry_policy: RetryPolicy::default(),
+            rate_limiter: None,
+            metrics: None,
+            subscribers: Subscribers::new(),
+            meter: Meter::new(),
+            buffers: BufferPool::global(),
+            reassembly: None,
+            connection: Mutex::new(None),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Sets how the protocol obtains its socket.
+    ///
+    /// # Parameters
+    ///
+    /// * `strategy`: The socket strategy to use for future connections.
+    pub fn with_strategy(mut self, strategy: SocketStrategy) -> Self {
+        self.strategy = strategy;
+        self
+    }
+
+    /// Binds the sockets of future connections to a local address.
+    ///
+    /// Call it once per address family to bind both IPv4 and IPv6 sockets; peers of a
+    /// family without a local address are queried from any address of that family. Only
+    /// ephemeral sockets are affected, since a `SharedSocket` is bound when it is created.
+    ///
+    /// # Parameters
+    ///
+    /// * `local`: The local address, usually with port `0` to pick any free port.
+    pub fn with_local_addr(mut self, local: SocketAddr) -> Self {
+        self.bind = self.bind.with(local);
+        self
+    }
+
+    /// Sets the policy used to retry failed exchanges.
+    ///
+    /// # Parameters
+    ///
+    /// * `retry_policy`: The retry policy reported through `Protocol::retry_policy`.
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    /// Paces the datagrams sent with a rate limiter.
+    ///
+    /// Every datagram, including retries and handshake packets, takes a permit before it is
+    /// sent. Waiting for a permit doesn't count against the write time limit.
+    ///
+    /// # Parameters
+    ///
+    /// * `rate_limiter`: The limiter to take permits from, usually shared across protocols.
+    pub fn with_rate_limiter(mut self, rate_limiter: RateLimiter) -> Self {
+        self.rate_limiter = Some(rate_limiter);
+        self
+    }
+
+    /// Reports the datagrams sent and received to a metrics sink.
+    ///
+    /// `Game::fetch_with` reports its queries to the same sink.
+    ///
+    /// # Parameters
+    ///
+    /// * `metrics`: The sink reported through `Protocol::metrics`. Pass an `Arc` to share it
+    ///   between protocols.
+    pub fn with_metrics(mut self, metrics: impl MetricsSink + 'static) -> Self {
+        self.metrics = Some(Arc::new(metrics));
+        self
+    }
+
+    /// Adds a subscriber to the protocol's connection events.
+    ///
+    /// # Parameters
+    ///
+    /// * `subscriber`: The subscriber reported through `Protocol::subscribers`.
+    pub fn with_subscriber(mut self, subscriber: impl ConnectionSubscriber + 'static) -> Self {
+        self.subscribers.add(subscriber);
+        self
+    }
+
+    /// Sets the pool that receive buffers are taken from, instead of the process-wide one.
+    ///
+    /// Only ephemeral sockets are affected, since a `SharedSocket` receives for all of its
+    /// users.
+    ///
+    /// # Parameters
+    ///
+    /// * `buffers`: The pool, usually shared across protocols.
+    pub fn with_buffer_pool(mut self, buffers: BufferPool) -> Self {
+        self.buffers = buffers;
+        self
+    }
+
+    /// Joins responses split across several datagrams before they are parsed.
+    ///
+    /// Every datagram received for a response is classified by `classify`, which parses the
+    /// protocol's split header, e.g. that of A2S. Fragments are collected until their set
+    /// is complete, in whatever order they arrive, and the joined response is handed to the
+    /// parser. The whole response, every fragment included, is received within the read
+    /// time limit. `RawTransport::receive` still yields single datagrams.
+    ///
+    /// # Parameters
+    ///
+    /// * `reassembler`: Collects the fragments, bounding how many are kept.
+    /// * `classify`: Tells whole responses from fragments.
+    pub fn with_reassembly(
+        mut self,
+        reassembler: Reassembler,
+        classify: impl Fn(Bytes) -> Packet + Send + Sync + 'static,
+    ) -> Self {
+        self.reassembly = Some(Reassembly {
+            classify: Box::new(classify),
+            reassembler: Mutex::new(reassembler),
+        });
+        self
+    }
+
+    /// Returns the parser used by the protocol.
+    pub fn parser(&self) -> &P {
+        &self.parser
+    }
+
+    /// Returns the socket strategy used by the protocol.
+    pub fn strategy(&self) -> &SocketStrategy {
+        &self.strategy
+    }
+
+    /// Sends a single datagram within the write time limit.
+    async fn send_datagram(
+        &self,
+        data: &[u8],
+        timeouts: &TimeoutSettings,
+    ) -> Result<(), Error<UdpError>> {
+        let mut connection = self.connection.lock().await;
+        let connection = connection.as_mut().ok_or_else(not_connected)?;
+
+        if let Some(rate_limiter) = &self.rate_limiter {
+            rate_limiter.acquire(connection.peer).await;
+        }
+
+        #[cfg(all(feature = "tracing", not(feature = "silent")))]
+        tracing::trace!(peer = %connection.peer, bytes = data.len(), "sending datagram");
+
+        let send = async {
+            connection
+                .send(data)
+                .await
+                .map_err(|err| protocol_error(UdpError::Io(err)))?;
+
+            if let Some(metrics) = &self.metrics {
+                metrics.packet_sent(connection.peer, data.len());
+            }
+
+            self.subscribers.emit(ConnectionEvent::PacketSent {
+                peer: connection.peer,
+                bytes: data.len(),
+            });
+
+            Ok(())
+        };
+
+        with_timeout(timeouts.write, "UDP send timed out", send).await
+    }
+
+    /// Receives a single datagram within the read time limit.
+    async fn receive_datagram(&self, timeouts: &TimeoutSettings) -> Result<Bytes, Error<UdpError>> {
+        with_timeout(timeouts.read, "UDP receive timed out", self.next_datagram()).await
+    }
+
+    /// Receives a single datagram, however long it takes.
+    async fn next_datagram(&self) -> Result<Bytes, Error<UdpError>> {
+        let connection = self.connection.lock().await;
+        let connection = connection.as_ref().ok_or_else(not_connected)?;
+
+        let datagram = connection
+            .receive(&self.buffers)
+            .await
+            .map_err(|err| protocol_error(UdpError::Io(err)))?;
+
+        #[cfg(all(feature = "tracing", not(feature = "silent")))]
+        tracing::trace!(peer = %connection.peer, bytes = datagram.len(), "received datagram");
+
+        self.meter.record(&datagram);
+
+        if let Some(metrics) = &self.metrics {
+            metrics.packet_received(connection.peer, datagram.len());
+        }
+
+        self.subscribers.emit(ConnectionEvent::PacketReceived {
+            peer: connection.peer,
+            bytes: datagram.len(),
+        });
+
+        Ok(datagram)
+    }
+}
+
+impl<'a, Q, R, P> Protocol<'a> for UdpProtocol<Q, R, P>
+where
+    Q: Query + 'a,
+    R: Response + 'a,
+    P: Parser<'a, Q, R> + Send + Sync,
+    P::SE: Send + Sync,
+    P::DE: Send + Sync,
+{
+    type Q = Q;
+    type R = R;
+    type P = P;
+    type E = UdpError;
+
+    fn retry_policy(&self) -> RetryPolicy {
+        self.retry_policy.clone()
+    }
+
+    fn subscribers(&self) -> Option<&Subscribers> {
+        Some(&self.subscribers)
+    }
+
+    fn meter(&self) -> Option<&Meter> {
+        Some(&self.meter)
+    }
+
+    fn metrics(&self) -> Option<&dyn MetricsSink> {
+        self.metrics.as_deref()
+    }
+
+    async fn _connect(
+        &self,
+        address: SocketAddr,
+        timeouts: &TimeoutSettings,
+    ) -> Result<(), Error<Self::E>> {
+        let socket = match &self.strategy {
+            SocketStrategy::Ephemeral => {
+                let connect = async {
+                    bind_connected(self.bind.for_peer(address), address)
+                        .await
+                        .map_err(|err| protocol_error(UdpError::Io(err)))
+                };
+
+                Socket::Ephemeral(
+                    with_timeout(timeouts.connect, "UDP connect timed out", connect).await?,
+                )
+            }
+            SocketStrategy::Shared(socket) => {
+                socket.register(address);
+                Socket::Shared(socket.clone())
+            }
+        };
+
+        // Replacing a previous connection releases its association.
+        drop(self.connection.lock().await.replace(Connection {
+            peer: address,
+            socket,
+            expected: None,
+        }));
+
+        Ok(())
+    }
+
+    async fn send_query(
+        &self,
+        query: Self::Q,
+        timeouts: &TimeoutSettings,
+    ) -> Result<(), Error<Self::E>> {
+        let payload = self
+            .parser
+            .serialize_query(&query)
+            .map_err(|err| err.map(|err| UdpError::Parse(Box::new(err))))?;
+
+        self.send_datagram(&payload, timeouts).await
+    }
+
+    async fn receive_response(
+        &self,
+        timeouts: &TimeoutSettings,
+    ) -> Result<Self::R, Error<Self::E>> {
+        let datagram = match &self.reassembly {
+            Some(reassembly) => {
+                let assemble = async {
+                    reassembly
+                        .reassembler
+                        .lock()
+                        .await
+                        .assemble(|| async {
+                            let datagram = self.next_datagram().await?;
+                            Ok((reassembly.classify)(datagram))
+                        })
+                        .await
+                };
+
+                with_timeout(timeouts.read, "UDP receive timed out", assemble).await?
+            }
+            None => self.receive_datagram(timeouts).await?,
+        };
+
+        self.parser
+            .deserialize_response(datagram)
+            .map_err(|err| err.map(|err| UdpError::Parse(Box::new(err))))
+    }
+
+    /// Releases the socket immediately; UDP needs no asynchronous shutdown.
+    fn schedule_disconnect(&self) {
+        if let Some(mut connection) = self.connection.try_lock() {
+            connection.take();
+        }
+    }
+
+    async fn disconnect(&self) -> Result<(), Error<Self::E>> {
+        self.connection.lock().await.take();
+
+        Ok(())
+    }
+}
+
+impl<'a, Q, R, P> RawTransport<'a> for UdpProtocol<Q, R, P>
+where
+    Q: Query + 'a,
+    R: Response + 'a,
+    P: Parser<'a, Q, R> + Send + Sync,
+    P::SE: Send + Sync,
+    P::DE: Send + Sync,
+{
+    /// Sends `data` as a single datagram, bypassing the parser.
+    async fn send(&self, data: &[u8], timeouts: &TimeoutSettings) -> Result<(), Error<Self::E>> {
+        self.send_datagram(data, timeouts).await
+    }
+
+    /// Receives the next datagram, bypassing the parser.
+    async fn receive(&self, timeouts: &TimeoutSettings) -> Result<Bytes, Error<Self::E>> {
+        self.receive_datagram(timeouts).await
+    }
+}
+
+/// Binds a socket to `local` and connects it to `address`.
+async fn bind_connected(local: SocketAddr, address: SocketAddr) -> std::io::Result<UdpSocket> {
+    let socket = UdpSocket::bind(local).await?;
+    socket.connect(address).await?;
+
+    Ok(socket)
+}
+
+/// Wraps a transport error as a protocol error.
+fn protocol_error(err: UdpError) -> Error<UdpError> {
+    let message = match &err {
+        UdpError::Io(_) => "UDP socket operation failed",
+        UdpError::NotConnected => "UDP protocol is not connected",
+        UdpError::Parse(_) => "Failed to parse UDP datagram",
+    };
+
+    let kind = match &err {
+        UdpError::Io(err) => ErrorKind::from_io(err),
+        UdpError::NotConnected => ErrorKind::NotConnected,
+        UdpError::Parse(_) => ErrorKind::InvalidPacket,
+    };
+
+    Error::ProtocolError(ErrorDetail::new(message, Some(err)).with_kind(kind))
+}
+
+/// Builds the error returned when an operation requires a connection.
+fn not_connected() -> Error<UdpError> {
+    protocol_error(UdpError::NotConnected)
+}
@@ -0,0 +1,651 @@
+//! Scanning very large lists of servers.
+//!
+//! `QueryMany` suits fleets of hundreds of servers. Scanning the hundreds of thousands of
+//! addresses a master server lists takes more: a single socket for every query, a query
+//! rate that adapts to what the network sustains, politeness towards each provider's
+//! subnet, and the ability to resume a scan that was interrupted. `Scanner` runs such
+//! scans and streams each result to a `ScanSink` as soon as it arrives, so nothing is
+//! buffered for the length of the scan.
+
+use crate::{
+    error::UdpError,
+    protocol::UdpProtocol,
+    socket::{SharedSocket, SocketStrategy},
+};
+
+use gstat_core::{
+    blocklist::{Blocked, Blocklist},
+    bulk::QueryMany,
+    cancel::CancellationToken,
+    prelude::{ErrorKind, Game, Parser, Query, Response, TimeoutSettings},
+    rate_limit::{RateLimit, RateLimiter},
+    runtime,
+    standards::game::FetchResult,
+};
+
+use std::{
+    collections::BTreeSet,
+    fmt::{Debug, Formatter, Result as FmtResult},
+    fs, io,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+/// The number of queries in flight at once by default.
+const DEFAULT_CONCURRENCY: usize = 1024;
+
+/// The number of completed queries between two checkpoints by default.
+const DEFAULT_CHECKPOINT_INTERVAL: usize = 1000;
+
+/// The number of completed queries over which the share of timeouts is measured.
+const PACING_WINDOW: u32 = 64;
+
+/// How much the share of timeouts may exceed the usual share before the rate is halved.
+const LOSS_TOLERANCE: f64 = 0.1;
+
+/// The number of windows without extra timeouts it takes to go from the lowest rate to the
+/// highest.
+const PACING_STEPS: f64 = 16.0;
+
+/// The first line of a checkpoint file.
+const CHECKPOINT_HEADER: &str = "gstat-scan 1";
+
+/// The rate at which a `Scanner` starts queries, in queries per second.
+///
+/// An adaptive pacing starts at `min` and moves between `min` and `max` with the share of
+/// queries that time out. Scanned addresses often don't answer at all, so the usual share
+/// of timeouts is learned during the scan; while it holds, the rate grows by a
+/// sixteenth of the range for every 64 completed queries, and once it is exceeded by more
+/// than a tenth, which is what happens when packets start to get lost, the rate is halved.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Pacing {
+    /// The lowest rate, which is also the rate the scan starts at.
+    pub min: f64,
+    /// The highest rate.
+    pub max: f64,
+}
+
+impl Pacing {
+    /// Creates a pacing that keeps the same rate for the whole scan.
+    ///
+    /// # Parameters
+    ///
+    /// * `queries_per_second`: The rate. Rates that aren't positive are treated as one
+    ///   query per second.
+    pub fn fixed(queries_per_second: f64) -> Self {
+        Self::adaptive(queries_per_second, queries_per_second)
+    }
+
+    /// Creates a pacing that adapts the rate between `min` and `max`.
+    ///
+    /// # Parameters
+    ///
+    /// * `min`: The lowest rate, and the rate the scan starts at. Rates that aren't
+    ///   positive are treated as one query per second.
+    /// * `max`: The highest rate. Rates below `min` are treated as `min`.
+    pub fn adaptive(min: f64, max: f64) -> Self {
+        let min = if min > 0.0 { min } else { 1.0 };
+
+        Pacing {
+            min,
+            max: max.max(min),
+        }
+    }
+}
+
+impl Default for Pacing {
+    /// Starts at 100 queries per second and goes up to 10 000.
+    fn default() -> Self {
+        Pacing::adaptive(100.0, 10_000.0)
+    }
+}
+
+/// The state of the pacing during a scan.
+struct Pacer {
+    pacing: Pacing,
+    rate: f64,
+    /// The instant the next query may start at.
+    next: Instant,
+    completed: u32,
+    timed_out: u32,
+    /// The usual share of timeouts, once a window completed.
+    baseline: Option<f64>,
+}
+
+impl Pacer {
+    fn new(pacing: Pacing, now: Instant) -> Self {
+        Pacer {
+            pacing,
+            rate: pacing.min,
+            next: now,
+            completed: 0,
+            timed_out: 0,
+            baseline: None,
+        }
+    }
+
+    /// Takes the next free start time, returning how long the caller must wait for it.
+    fn reserve(&mut self, now: Instant) -> Duration {
+        let start = self.next.max(now);
+        self.next = start + Duration::from_secs_f64(1.0 / self.rate);
+
+        start - now
+    }
+
+    /// Accounts for a completed query, adapting the rate once a window is complete.
+    fn record(&mut self, timed_out: bool) {
+        self.completed += 1;
+        self.timed_out += u32::from(timed_out);
+
+        if self.completed < PACING_WINDOW {
+            return;
+        }
+
+        let loss = f64::from(self.timed_out) / f64::from(self.completed);
+        (self.completed, self.timed_out) = (0, 0);
+
+        // The baseline creeps up with every window, so one lucky window doesn't hold the
+        // rate down for the rest of the scan.
+        let baseline = self
+            .baseline
+            .map_or(loss, |baseline| loss.min(baseline + LOSS_TOLERANCE / 4.0));
+        self.baseline = Some(baseline);
+
+        let Pacing { min, max } = self.pacing;
+        self.rate = if loss > baseline + LOSS_TOLERANCE {
+            (self.rate / 2.0).max(min)
+        } else {
+            (self.rate + (max - min) / PACING_STEPS).min(max)
+        };
+    }
+}
+
+/// The targets of a scan that have been completed, so it can be resumed.
+///
+/// Targets are identified by their position in the list being scanned, so a scan can only
+/// be resumed with the same list in the same order. Queries complete out of order; a
+/// checkpoint keeps the number of leading targets that are all complete, and the positions
+/// of the targets completed beyond them, of which there are at most as many as queries in
+/// flight.
+///
+/// Checkpoint files are text: a header line, a `prefix N` line and a `done N` line for
+/// every target completed beyond the prefix.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Checkpoint {
+    prefix: u64,
+    beyond: BTreeSet<u64>,
+}
+
+impl Checkpoint {
+    /// Creates a checkpoint without completed targets.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Reads a checkpoint from a file.
+    ///
+    /// # Parameters
+    ///
+    /// * `path`: The file written by `save`.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the checkpoint, which is empty if the file doesn't exist, or
+    /// an `io::Error` if it can't be read or isn't a checkpoint.
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let contents = match fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Self::new()),
+            Err(err) => return Err(err),
+        };
+
+        let invalid = |line: &str| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("invalid checkpoint line `{line}`"),
+            )
+        };
+
+        let mut lines = contents.lines();
+        if lines.next() != Some(CHECKPOINT_HEADER) {
+            return Err(invalid(contents.lines().next().unwrap_or_default()));
+        }
+
+        let mut checkpoint = Self::new();
+
+        for line in lines {
+            let (key, value) = line.split_once(' ').ok_or_else(|| invalid(line))?;
+            let value = value.parse().map_err(|_| invalid(line))?;
+
+            match key {
+                "prefix" => checkpoint.prefix = checkpoint.prefix.max(value),
+                "done" => {
+                    checkpoint.beyond.insert(value);
+                }
+                _ => return Err(invalid(line)),
+            }
+        }
+
+        // Fold in targets that were listed before the prefix line.
+        checkpoint.advance();
+
+        Ok(checkpoint)
+    }
+
+    /// Writes the checkpoint to a file.
+    ///
+    /// The file is replaced atomically, so an interrupted write leaves the previous
+    /// checkpoint intact.
+    ///
+    /// # Parameters
+    ///
+    /// * `path`: The file to write.
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let mut contents = format!("{CHECKPOINT_HEADER}\nprefix {}\n", self.prefix);
+        for index in &self.beyond {
+            contents.push_str(&format!("done {index}\n"));
+        }
+
+        let mut temporary = path.as_os_str().to_owned();
+        temporary.push(".tmp");
+
+        fs::write(&temporary, contents)?;
+        fs::rename(&temporary, path)
+    }
+
+    /// Marks the target at `index` as completed.
+    pub fn record(&mut self, index: u64) {
+        if index >= self.prefix {
+            self.beyond.insert(index);
+            self.advance();
+        }
+    }
+
+    /// Returns `true` if the target at `index` was completed.
+    pub fn is_done(&self, index: u64) -> bool {
+        index < self.prefix || self.beyond.contains(&index)
+    }
+
+    /// Returns the number of completed targets.
+    pub fn completed(&self) -> u64 {
+        self.prefix + self.beyond.len() as u64
+    }
+
+    /// Moves the targets that now continue the prefix into it.
+    fn advance(&mut self) {
+        self.beyond.retain(|&index| index >= self.prefix);
+
+        while self.beyond.remove(&self.prefix) {
+            self.prefix += 1;
+        }
+    }
+}
+
+/// A trait for receiving the results of a scan as they arrive.
+///
+/// Results are delivered in the order the queries complete, from the task running the
+/// scan, so implementations should return quickly, e.g. by writing a line to a buffered
+/// file or forwarding the result to a channel. An error aborts the scan.
+///
+/// The trait is implemented for closures taking the address and result of a query.
+pub trait ScanSink<R> {
+    /// Called for each completed query.
+    ///
+    /// # Parameters
+    ///
+    /// * `address`: The address of the server.
+    /// * `result`: The outcome of its query.
+    fn accept(&mut self, address: SocketAddr, result: FetchResult<R, UdpError>) -> io::Result<()>;
+
+    /// Called for each target skipped because the scanner's blocklist refused it.
+    ///
+    /// The default implementation does nothing.
+    ///
+    /// # Parameters
+    ///
+    /// * `blocked`: The target and the rule that matched it.
+    fn blocked(&mut self, blocked: &Blocked) -> io::Result<()> {
+        let _ = blocked;
+        Ok(())
+    }
+
+    /// Makes the results accepted so far durable.
+    ///
+    /// Called before every checkpoint is written, so a resumed scan doesn't skip results
+    /// that were still buffered. The default implementation does nothing.
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl<R, F> ScanSink<R> for F
+where
+    F: FnMut(SocketAddr, FetchResult<R, UdpError>) -> io::Result<()>,
+{
+    fn accept(&mut self, address: SocketAddr, result: FetchResult<R, UdpError>) -> io::Result<()> {
+        self(address, result)
+    }
+}
+
+/// The outcome of a scan.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ScanSummary {
+    /// The number of servers that answered.
+    pub answered: u64,
+    /// The number of servers that didn't answer in time.
+    pub timed_out: u64,
+    /// The number of queries that failed for another reason.
+    pub failed: u64,
+    /// The number of targets skipped because the checkpoint listed them as completed.
+    pub skipped: u64,
+    /// The number of targets skipped because the blocklist refused them.
+    pub blocked: u64,
+    /// The rate queries were started at when the scan ended, in queries per second.
+    pub queries_per_second: f64,
+}
+
+/// A game whose protocols query through the scanner's socket and limiter.
+struct Scanned<'g, G> {
+    game: &'g G,
+    socket: SharedSocket,
+    politeness: RateLimiter,
+}
+
+impl<'a, G, Q, R, P> Game<'a, UdpProtocol<Q, R, P>> for Scanned<'_, G>
+where
+    G: Game<'a, UdpProtocol<Q, R, P>>,
+    Q: Query + 'a,
+    R: Response + 'a,
+    P: Parser<'a, Q, R> + Send + Sync,
+    P::SE: Send + Sync,
+    P::DE: Send + Sync,
+{
+    const GAME_NAME: &'static str = G::GAME_NAME;
+    const RELEASE_YEAR: u32 = G::RELEASE_YEAR;
+
+    fn _protocol(&self) -> UdpProtocol<Q, R, P> {
+        self.game
+            ._protocol()
+            .with_strategy(SocketStrategy::Shared(self.socket.clone()))
+            .with_rate_limiter(self.politeness.clone())
+    }
+
+    fn game_name(&self) -> &str {
+        self.game.game_name()
+    }
+}
+
+/// `Scanner` queries very large lists of servers of a UDP game.
+///
+/// Every query goes through one `SharedSocket`, so a scan uses a single local port however
+/// many queries are in flight; `Scanner::bind` sends and receives in batches where
+/// possible. Queries are started at the rate of the `Pacing`, and every datagram sent to a
+/// /24 (IPv4) or /64 (IPv6) subnet is limited by the politeness limit, so the servers of
+/// one provider aren't flooded. A query waiting for its subnet occupies a slot, so lists
+/// sorted by address are best shuffled first.
+///
+/// Targets refused by the blocklist are never sent a datagram; they are handed to
+/// `ScanSink::blocked` and counted in the summary instead.
+///
+/// With a checkpoint file, progress is saved periodically and when the scan ends or is
+/// cancelled, and a scan started with an existing checkpoint skips the targets it lists.
+/// Results are handed to the sink before they count as completed, so a resumed scan
+/// repeats the queries whose results were not saved, but never loses one.
+///
+/// The protocols of the game are used with their socket strategy and rate limiter
+/// replaced; the rest of their configuration, such as retries and subscribers, applies.
+#[derive(Clone)]
+pub struct Scanner {
+    socket: SharedSocket,
+    concurrency: usize,
+    timeouts: TimeoutSettings,
+    pacing: Pacing,
+    politeness: RateLimit,
+    checkpoint: Option<PathBuf>,
+    checkpoint_interval: usize,
+    cancellation: Option<CancellationToken>,
+    blocklist: Blocklist,
+}
+
+impl Scanner {
+    /// Creates a scanner querying through `socket`.
+    ///
+    /// # Parameters
+    ///
+    /// * `socket`: The socket every query is sent through.
+    pub fn new(socket: SharedSocket) -> Self {
+        Scanner {
+            socket,
+            concurrency: DEFAULT_CONCURRENCY,
+            timeouts: TimeoutSettings::default(),
+            pacing: Pacing::default(),
+            politeness: RateLimit::new(10.0, 4),
+            checkpoint: None,
+            checkpoint_interval: DEFAULT_CHECKPOINT_INTERVAL,
+            cancellation: None,
+            blocklist: Blocklist::new(),
+        }
+    }
+
+    /// Binds a socket and creates a scanner querying through it.
+    ///
+    /// With the `batch` feature on Linux, the socket exchanges datagrams in batches of
+    /// `DEFAULT_BATCH_SIZE`.
+    ///
+    /// # Parameters
+    ///
+    /// * `address`: The local address to bind, e.g. `0.0.0.0:0` for any port.
+    pub async fn bind(address: SocketAddr) -> io::Result<Self> {
+        #[cfg(all(feature = "batch", target_os = "linux"))]
+        let socket = SharedSocket::bind_batched(address, crate::DEFAULT_BATCH_SIZE).await?;
+        #[cfg(not(all(feature = "batch", target_os = "linux")))]
+        let socket = SharedSocket::bind(address).await?;
+
+        Ok(Self::new(socket))
+    }
+
+    /// Sets the largest number of queries in flight. `0` is treated as `1`.
+    pub fn with_concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    /// Sets the time limits of each query.
+    pub fn with_timeouts(mut self, timeouts: TimeoutSettings) -> Self {
+        self.timeouts = timeouts;
+        self
+    }
+
+    /// Sets the rate queries are started at.
+    pub fn with_pacing(mut self, pacing: Pacing) -> Self {
+        self.pacing = pacing;
+        self
+    }
+
+    /// Sets the limit of datagrams sent to each subnet. The default is 10 per second with
+    /// bursts of 4.
+    pub fn with_politeness(mut self, limit: RateLimit) -> Self {
+        self.politeness = limit;
+        self
+    }
+
+    /// Saves the progress of scans to a file and resumes from it.
+    ///
+    /// # Parameters
+    ///
+    /// * `path`: The checkpoint file. It is created if it doesn't exist.
+    pub fn with_checkpoint(mut self, path: impl Into<PathBuf>) -> Self {
+        self.checkpoint = Some(path.into());
+        self
+    }
+
+    /// Sets the number of completed queries between two checkpoints. `0` is treated as
+    /// `1`.
+    pub fn with_checkpoint_interval(mut self, interval: usize) -> Self {
+        self.checkpoint_interval = interval.max(1);
+        self
+    }
+
+    /// Stops scans once `token` is cancelled.
+    ///
+    /// The queries in flight are dropped without results and the checkpoint is saved, so
+    /// the scan can be resumed later.
+    pub fn with_cancellation(mut self, token: &CancellationToken) -> Self {
+        self.cancellation = Some(token.clone());
+        self
+    }
+
+    /// Skips the targets `blocklist` refuses, e.g. the ranges of operators that opted out.
+    pub fn with_blocklist(mut self, blocklist: Blocklist) -> Self {
+        self.blocklist = blocklist;
+        self
+    }
+
+    /// Returns the socket queries are sent through.
+    pub fn socket(&self) -> &SharedSocket {
+        &self.socket
+    }
+
+    /// Queries every target with the game's default query.
+    ///
+    /// # Parameters
+    ///
+    /// * `game`: The game the servers run.
+    /// * `targets`: The servers to query, read lazily as slots free up.
+    /// * `sink`: Receives the result of every query as it completes.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the `ScanSummary`, or an `io::Error` if the checkpoint
+    /// couldn't be read or written or the sink failed.
+    pub async fn scan<G, Q, R, P, I, S>(
+        &self,
+        game: &G,
+        targets: I,
+        sink: &mut S,
+    ) -> io::Result<ScanSummary>
+    where
+        G: for<'a> Game<'a, UdpProtocol<Q, R, P>> + Sync,
+        Q: Query + 'static,
+        R: Response + Send + 'static,
+        P: for<'a> Parser<'a, Q, R> + Send + Sync,
+        for<'a> <P as Parser<'a, Q, R>>::SE: Send + Sync,
+        for<'a> <P as Parser<'a, Q, R>>::DE: Send + Sync,
+        I: IntoIterator<Item = SocketAddr>,
+        I::IntoIter: Send,
+        S: ScanSink<R>,
+    {
+        let path = self.checkpoint.as_deref();
+        let mut checkpoint = match path {
+            Some(path) => Checkpoint::load(path)?,
+            None => Checkpoint::new(),
+        };
+
+        let mut summary = ScanSummary {
+            skipped: checkpoint.completed(),
+            ..ScanSummary::default()
+        };
+
+        let resumed = checkpoint.clone();
+        let targets = targets
+            .into_iter()
+            .zip(0u64..)
+            .filter(move |(_, index)| !resumed.is_done(*index));
+
+        let scanned = Scanned {
+            game,
+            socket: self.socket.clone(),
+            politeness: RateLimiter::new().with_per_subnet(self.politeness),
+        };
+        let pacer = Arc::new(Mutex::new(Pacer::new(self.pacing, runtime::now())));
+        let query = game.default_query();
+        let timeouts = self.timeouts;
+
+        let start = {
+            let (scanned, pacer, blocklist) = (&scanned, pacer.clone(), &self.blocklist);
+
+            // Blocked targets neither take a turn of the pacer nor send a datagram.
+            move |&(address, _): &(SocketAddr, u64)| {
+                let checked = blocklist.check(address).map(|()| {
+                    let wait = lock(&pacer).reserve(runtime::now());
+                    (wait, scanned.fetch_with(query.clone(), address, timeouts))
+                });
+
+                async move {
+                    let (wait, fetch) = checked?;
+
+                    if !wait.is_zero() {
+                        runtime::sleep(wait).await;
+                    }
+
+                    Ok::<_, Blocked>(fetch.await)
+                }
+            }
+        };
+
+        let mut queries = QueryMany::new(targets, self.concurrency, start);
+        if let Some(token) = &self.cancellation {
+            queries = queries.with_cancellation(token);
+        }
+
+        let mut unsaved = 0;
+
+        while let Some(((address, index), result)) = queries.next().await {
+            match result {
+                Ok(result) => {
+                    let timed_out = matches!(&result, Err(err) if err.kind() == ErrorKind::Timeout);
+                    lock(&pacer).record(timed_out);
+
+                    match &result {
+                        Ok(_) => summary.answered += 1,
+                        Err(_) if timed_out => summary.timed_out += 1,
+                        Err(_) => summary.failed += 1,
+                    }
+
+                    sink.accept(address, result)?;
+                }
+                Err(blocked) => {
+                    summary.blocked += 1;
+                    sink.blocked(&blocked)?;
+                }
+            }
+
+            checkpoint.record(index);
+            unsaved += 1;
+
+            if let Some(path) = path.filter(|_| unsaved >= self.checkpoint_interval) {
+                sink.flush()?;
+                checkpoint.save(path)?;
+                unsaved = 0;
+            }
+        }
+
+        sink.flush()?;
+        if let Some(path) = path {
+            checkpoint.save(path)?;
+        }
+
+        summary.queries_per_second = lock(&pacer).rate;
+
+        Ok(summary)
+    }
+}
+
+impl Debug for Scanner {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.debug_struct("Scanner")
+            .field("concurrency", &self.concurrency)
+            .field("pacing", &self.pacing)
+            .field("politeness", &self.politeness)
+            .field("checkpoint", &self.checkpoint)
+            .field("blocklist", &self.blocklist.rules().len())
+            .finish_non_exhaustive()
+    }
+}
+
+/// Locks the pacer.
+fn lock(pacer: &Mutex<Pacer>) -> std::sync::MutexGuard<'_, Pacer> {
+    pacer.lock().expect("scanner pacer lock poisoned")
+}
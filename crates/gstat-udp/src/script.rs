@@ -0,0 +1,44 @@
+use crate::protocol::UdpProtocol;
+
+use gstat_core::{
+    prelude::Game,
+    script::{ScriptGame, ScriptParser, ScriptQuery, ScriptResponse},
+};
+
+/// The protocol scripted games are queried with.
+pub type ScriptProtocol = UdpProtocol<ScriptQuery, ScriptResponse, ScriptParser>;
+
+/// `Scripted` queries a game described by a script over UDP, through `Game` like a native
+/// one.
+///
+/// ```no_run
+/// # async fn run(game: &gstat_core::script::ScriptGame) {
+/// use gstat_core::{prelude::{Game, TimeoutSettings}, script::ScriptQuery};
+/// use gstat_udp::script::Scripted;
+///
+/// let address = "192.0.2.1:27015".parse().unwrap();
+/// let fetched = Scripted(game)
+///     .fetch_with(ScriptQuery, address, TimeoutSettings::default())
+///     .await;
+/// # }
+/// ```
+#[derive(Clone, Copy)]
+pub struct Scripted<'g>(pub &'g ScriptGame);
+
+impl<'a> Game<'a, ScriptProtocol> for Scripted<'_> {
+    /// Scripted games only know their name at runtime; `game_name` reports the declared one.
+    const GAME_NAME: &'static str = "scripted";
+    const RELEASE_YEAR: u32 = 0;
+
+    fn _protocol(&self) -> ScriptProtocol {
+        UdpProtocol::new(self.0.parser())
+    }
+
+    fn game_name(&self) -> &str {
+        self.0.name()
+    }
+
+    fn default_port(&self) -> Option<u16> {
+        Some(self.0.port())
+    }
+}
@@ -0,0 +1,423 @@
+use std::{
+    collections::{hash_map::Entry, HashMap, VecDeque},
+    future::poll_fn,
+    io,
+    net::SocketAddr,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    task::{Context, Poll, Waker},
+};
+
+#[cfg(all(feature = "batch", target_os = "linux"))]
+use crate::batch::Batched;
+use crate::pool::BufferPool;
+
+use bytes::Bytes;
+use gstat_core::{
+    runtime::UdpSocket,
+    standards::correlation::{CorrelationId, Correlator},
+};
+
+/// The largest datagram that can be received over UDP.
+pub(crate) const MAX_DATAGRAM_LEN: usize = 65_535;
+
+/// The number of datagrams kept per peer until its owner picks them up.
+const MAX_QUEUED_PER_PEER: usize = 16;
+
+/// Reads the correlation ID of a request or response.
+type ReadId = Box<dyn Fn(&[u8]) -> Option<CorrelationId> + Send + Sync>;
+
+/// How a `UdpProtocol` obtains its socket.
+///
+/// Both strategies have legitimate use cases, so the choice is made per protocol instance.
+#[derive(Clone, Default)]
+pub enum SocketStrategy {
+    /// Bind a fresh socket for every connection.
+    ///
+    /// Each query is isolated from every other one, at the cost of one socket (and one
+    /// local port) per in-flight query.
+    #[default]
+    Ephemeral,
+    /// Send every query through a single socket shared with other protocol instances.
+    ///
+    /// This scales to many thousands of concurrent queries with a single local port.
+    /// Inbound datagrams are demultiplexed by their source address.
+    Shared(SharedSocket),
+}
+
+/// A UDP socket shared between many protocol instances.
+///
+/// Every connected protocol instance registers the address of its peer, and inbound
+/// datagrams are routed to the instance that registered their source address. There is no
+/// background task: one of the instances waiting for a datagram takes the role of the
+/// reader, stores datagrams for other peers in their inboxes and wakes their owners, so a
+/// peer that never answers doesn't hold up the others. When the reader has received its
+/// own datagram, or gives up because of a timeout, another waiting instance takes over.
+///
+/// Datagrams from addresses nobody registered, such as late answers to finished queries,
+/// are dropped and counted by `unrouted`. IPv4-mapped IPv6 sources are matched with their
+/// IPv4 address, so a dual-stack socket routes IPv4 peers correctly.
+///
+/// Instances querying the same peer at the same time share its inbox, so they may receive
+/// each other's answers. For protocols whose packets carry a request ID, `with_correlation`
+/// routes each answer to the instance whose request carried its ID; otherwise use
+/// `SocketStrategy::Ephemeral` when that matters.
+#[derive(Clone)]
+pub struct SharedSocket {
+    inner: Arc<SharedInner>,
+}
+
+struct SharedInner {
+    socket: Transport,
+    routes: Mutex<HashMap<SocketAddr, Route>>,
+    reading: AtomicBool,
+    readers: Mutex<Vec<Waker>>,
+    unrouted: AtomicU64,
+    read_id: Option<ReadId>,
+}
+
+/// The socket of a `SharedSocket`.
+enum Transport {
+    /// A socket exchanging one datagram per system call.
+    Single(UdpSocket),
+    /// A socket exchanging batches of datagrams per system call.
+    #[cfg(all(feature = "batch", target_os = "linux"))]
+    Batched(Batched),
+}
+
+impl Transport {
+    fn local_addr(&self) -> io::Result<SocketAddr> {
+        match self {
+            Transport::Single(socket) => socket.local_addr(),
+            #[cfg(all(feature = "batch", target_os = "linux"))]
+            Transport::Batched(socket) => socket.local_addr(),
+        }
+    }
+
+    async fn send_to(&self, data: &[u8], peer: SocketAddr) -> io::Result<()> {
+        match self {
+            Transport::Single(socket) => socket.send_to(data, peer).await.map(|_| ()),
+            #[cfg(all(feature = "batch", target_os = "linux"))]
+            Transport::Batched(socket) => socket.send_to(data, peer).await,
+        }
+    }
+
+    /// Receives the next datagrams, waiting for at least one.
+    ///
+    /// Single datagrams are received into `buffer`, so a reader reuses it across calls.
+    async fn receive(&self, buffer: &mut [u8]) -> io::Result<Vec<(SocketAddr, Bytes)>> {
+        match self {
+            Transport::Single(socket) => {
+                let (len, source) = socket.recv_from(buffer).await?;
+
+                Ok(vec![(source, Bytes::copy_from_slice(&buffer[..len]))])
+            }
+            #[cfg(all(feature = "batch", target_os = "linux"))]
+            Transport::Batched(socket) => socket.receive().await,
+        }
+    }
+}
+
+/// The inbox of a registered peer.
+struct Route {
+    /// The number of protocol instances associated with the peer.
+    associations: usize,
+    /// Datagrams received from the peer that nobody picked up yet.
+    queue: VecDeque<Bytes>,
+    /// Datagrams carrying a correlation ID, held for the instance that sent the request.
+    correlated: Correlator,
+    /// The tasks waiting for a datagram from the peer.
+    waiting: Vec<Waker>,
+}
+
+impl Default for Route {
+    fn default() -> Self {
+        Route {
+            associations: 0,
+            queue: VecDeque::new(),
+            correlated: Correlator::new().with_max_stashed(MAX_QUEUED_PER_PEER),
+            waiting: Vec::new(),
+        }
+    }
+}
+
+impl Route {
+    /// Takes the oldest datagram for the request `expected`, or any uncorrelated one if the
+    /// request carried no ID.
+    fn take(&mut self, expected: Option<CorrelationId>) -> Option<Bytes> {
+        match expected {
+            Some(id) => self.correlated.take(id),
+            None => self.queue.pop_front(),
+        }
+    }
+}
+
+/// What a task waiting in `SharedSocket::recv_from` gets to do next.
+enum Turn<'s> {
+    /// Another reader delivered a datagram from the peer.
+    Datagram(Bytes),
+    /// The task became the reader.
+    Reader(ReaderGuard<'s>),
+}
+
+/// Holds the reader role, handing it over to the waiting tasks when dropped.
+struct ReaderGuard<'s> {
+    inner: &'s SharedInner,
+}
+
+impl Drop for ReaderGuard<'_> {
+    fn drop(&mut self) {
+        self.inner.reading.store(false, Ordering::SeqCst);
+
+        let readers =
+            std::mem::take(&mut *self.inner.readers.lock().expect("readers lock poisoned"));
+
+        for reader in readers {
+            reader.wake();
+        }
+    }
+}
+
+impl SharedSocket {
+    /// Binds a new shared socket.
+    ///
+    /// # Parameters
+    ///
+    /// * `address`: The local address to bind, e.g. `0.0.0.0:0` for any port.
+    pub async fn bind(address: SocketAddr) -> io::Result<Self> {
+        let socket = UdpSocket::bind(address).await?;
+
+        Ok(Self::new(Transport::Single(socket)))
+    }
+
+    /// Binds a new shared socket that sends and receives datagrams in batches, with
+    /// `sendmmsg` and `recvmmsg`.
+    ///
+    /// This is for mass scanning, where a system call per datagram is the bottleneck: the
+    /// reader drains up to `batch_size` datagrams per call, and sends from concurrent queries
+    /// are combined into one call. Combining delays each send until the tasks scheduled
+    /// alongside it had their turn, so single queries are slightly slower.
+    ///
+    /// Only available on Linux with the `batch` feature, and only on Tokio.
+    ///
+    /// # Parameters
+    ///
+    /// * `address`: The local address to bind, e.g. `0.0.0.0:0` for any port.
+    /// * `batch_size`: The most datagrams per system call, e.g. `DEFAULT_BATCH_SIZE`. `0` is
+    ///   treated as `1`.
+    #[cfg(all(feature = "batch", target_os = "linux"))]
+    pub async fn bind_batched(address: SocketAddr, batch_size: usize) -> io::Result<Self> {
+        let socket = Batched::bind(address, batch_size)?;
+
+        Ok(Self::new(Transport::Batched(socket)))
+    }
+
+    fn new(socket: Transport) -> Self {
+        SharedSocket {
+            inner: Arc::new(SharedInner {
+                socket,
+                routes: Mutex::new(HashMap::new()),
+                reading: AtomicBool::new(false),
+                readers: Mutex::new(Vec::new()),
+                unrouted: AtomicU64::new(0),
+                read_id: None,
+            }),
+        }
+    }
+
+    /// Routes the answers from a peer by the correlation ID they carry, so several
+    /// instances can query the same peer at once.
+    ///
+    /// Each instance expects the ID of the last request it sent, read with `read_id` as
+    /// well, and receives only the answers carrying it. Datagrams without an ID go to
+    /// instances whose requests carried none.
+    ///
+    /// Only takes effect before the socket is cloned or used.
+    ///
+    /// # Parameters
+    ///
+    /// * `read_id`: Reads the ID of a request or response, e.g. the request ID of Source
+    ///   RCON.
+    pub fn with_correlation(
+        mut self,
+        read_id: impl Fn(&[u8]) -> Option<CorrelationId> + Send + Sync + 'static,
+    ) -> Self {
+        if let Some(inner) = Arc::get_mut(&mut self.inner) {
+            inner.read_id = Some(Box::new(read_id));
+        }
+        self
+    }
+
+    /// Returns the correlation ID of a request or response, if the socket reads them.
+    pub(crate) fn correlation_id(&self, datagram: &[u8]) -> Option<CorrelationId> {
+        self.inner.read_id.as_ref()?(datagram)
+    }
+
+    /// Returns the local address the socket is bound to.
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.inner.socket.local_addr()
+    }
+
+    /// Returns the number of peers currently registered by connected protocol instances.
+    pub fn peers(&self) -> usize {
+        self.routes().len()
+    }
+
+    /// Returns the number of datagrams dropped because no instance registered their source.
+    pub fn unrouted(&self) -> u64 {
+        self.inner.unrouted.load(Ordering::Relaxed)
+    }
+
+    /// Routes datagrams from `peer` to this socket's users until the matching `forget`.
+    pub(crate) fn register(&self, peer: SocketAddr) {
+        self.routes()
+            .entry(canonical(peer))
+            .or_default()
+            .associations += 1;
+    }
+
+    /// Ends an association registered with `register`, discarding the peer's queued
+    /// datagrams once no association is left.
+    pub(crate) fn forget(&self, peer: SocketAddr) {
+        if let Entry::Occupied(mut route) = self.routes().entry(canonical(peer)) {
+            route.get_mut().associations -= 1;
+
+            if route.get().associations == 0 {
+                for waiting in route.remove().waiting {
+                    waiting.wake();
+                }
+            }
+        }
+    }
+
+    /// Sends a datagram to `peer`.
+    pub(crate) async fn send_to(&self, data: &[u8], peer: SocketAddr) -> io::Result<()> {
+        self.inner.socket.send_to(data, peer).await
+    }
+
+    /// Receives the next datagram sent by `peer` that answers the request `expected`.
+    pub(crate) async fn recv_from(
+        &self,
+        peer: SocketAddr,
+        expected: Option<CorrelationId>,
+    ) -> io::Result<Bytes> {
+        let peer = canonical(peer);
+
+        let _reader = match poll_fn(|cx| self.poll_turn(peer, expected, cx)).await {
+            Turn::Datagram(datagram) => return Ok(datagram),
+            Turn::Reader(reader) => reader,
+        };
+
+        // Another reader may have delivered our datagram before handing over the role.
+        if let Some(datagram) = self.take_queued(peer, expected) {
+            return Ok(datagram);
+        }
+
+        let mut buffer = BufferPool::global().take();
+
+        loop {
+            let mut own = None;
+
+            for (source, datagram) in self.inner.socket.receive(&mut buffer).await? {
+                let source = canonical(source);
+
+                match own {
+                    None if source == peer && self.correlation_id(&datagram) == expected => {
+                        own = Some(datagram)
+                    }
+                    _ => self.deliver(source, datagram),
+                }
+            }
+
+            if let Some(datagram) = own {
+                return Ok(datagram);
+            }
+        }
+    }
+
+    /// Takes a datagram queued for `peer`, or else tries to become the reader, registering
+    /// the task to be woken when either becomes possible.
+    fn poll_turn(
+        &self,
+        peer: SocketAddr,
+        expected: Option<CorrelationId>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Turn<'_>> {
+        if let Some(route) = self.routes().get_mut(&peer) {
+            if let Some(datagram) = route.take(expected) {
+                return Poll::Ready(Turn::Datagram(datagram));
+            }
+
+            register_waker(&mut route.waiting, cx.waker());
+        }
+
+        // The waker is registered before trying, so a reader handing over the role in
+        // between still wakes this task.
+        register_waker(
+            &mut self.inner.readers.lock().expect("readers lock poisoned"),
+            cx.waker(),
+        );
+
+        if self
+            .inner
+            .reading
+            .compare_exchange(false, true, Ordering::SeqCst, Ordering::SeqCst)
+            .is_ok()
+        {
+            return Poll::Ready(Turn::Reader(ReaderGuard { inner: &self.inner }));
+        }
+
+        Poll::Pending
+    }
+
+    /// Queues a datagram for its peer, or holds it for its request if it carries a
+    /// correlation ID, and wakes the tasks waiting for it.
+    fn deliver(&self, source: SocketAddr, datagram: Bytes) {
+        let id = self.correlation_id(&datagram);
+        let mut routes = self.routes();
+
+        let Some(route) = routes.get_mut(&source) else {
+            self.inner.unrouted.fetch_add(1, Ordering::Relaxed);
+            return;
+        };
+
+        match id {
+            Some(id) => route.correlated.stash(id, datagram),
+            None => {
+                if route.queue.len() == MAX_QUEUED_PER_PEER {
+                    route.queue.pop_front();
+                }
+
+                route.queue.push_back(datagram);
+            }
+        }
+
+        for waiting in route.waiting.drain(..) {
+            waiting.wake();
+        }
+    }
+
+    /// Takes the oldest datagram queued for `peer` that answers the request `expected`.
+    fn take_queued(&self, peer: SocketAddr, expected: Option<CorrelationId>) -> Option<Bytes> {
+        self.routes().get_mut(&peer)?.take(expected)
+    }
+
+    /// Locks the routing table.
+    fn routes(&self) -> std::sync::MutexGuard<'_, HashMap<SocketAddr, Route>> {
+        self.inner.routes.lock().expect("routes lock poisoned")
+    }
+}
+
+/// Adds `waker` to `wakers` unless it would wake the same task as one already there.
+fn register_waker(wakers: &mut Vec<Waker>, waker: &Waker) {
+    if !wakers.iter().any(|registered| registered.will_wake(waker)) {
+        wakers.push(waker.clone());
+    }
+}
+
+/// Returns `address` with an IPv4-mapped IPv6 address replaced by the IPv4 address.
+fn canonical(address: SocketAddr) -> SocketAddr {
+    SocketAddr::new(address.ip().to_canonical(), address.port())
+}
@@ -0,0 +1,138 @@
+//! Exchanging datagrams in batches over a `SharedSocket`.
+
+use gstat_core::{
+    bulk::QueryMany,
+    bytes::Bytes,
+    prelude::{
+        Error, Parser, Protocol, Query, QueryOptions, RawTransport, Response, ServerInfo,
+        TimeoutSettings,
+    },
+    runtime::UdpSocket,
+};
+use gstat_udp::{SharedSocket, SocketStrategy, UdpError, UdpProtocol, DEFAULT_BATCH_SIZE};
+
+use std::{net::SocketAddr, time::Duration};
+
+use common::run;
+
+mod common;
+
+#[derive(Clone)]
+struct Ping;
+
+impl Query for Ping {
+    type E = UdpError;
+    type Options = ();
+
+    fn from_options(_options: QueryOptions) -> Self {
+        Ping
+    }
+}
+
+struct Pong;
+
+impl Response for Pong {
+    type E = UdpError;
+
+    fn new() -> Result<Self, Error<Self::E>> {
+        Ok(Pong)
+    }
+
+    fn to_common(&self) -> ServerInfo {
+        ServerInfo::default()
+    }
+}
+
+struct PingParser;
+
+impl<'a> Parser<'a, Ping, Pong> for PingParser {
+    type SE = UdpError;
+    type DE = UdpError;
+
+    fn _serialize_query(&self, _query: &Ping) -> Result<Vec<u8>, Self::SE> {
+        Ok(b"ping".to_vec())
+    }
+
+    fn _deserialize_response(&self, _data: Bytes) -> Result<Pong, Self::DE> {
+        Ok(Pong)
+    }
+}
+
+fn loopback() -> SocketAddr {
+    SocketAddr::from(([127, 0, 0, 1], 0))
+}
+
+fn timeouts() -> TimeoutSettings {
+    TimeoutSettings::uniform(Duration::from_secs(5))
+}
+
+/// Binds a server echoing every datagram it receives.
+async fn echo() -> SocketAddr {
+    let socket = UdpSocket::bind(loopback()).await.unwrap();
+    let address = socket.local_addr().unwrap();
+
+    tokio::spawn(async move {
+        let mut buffer = [0; 16];
+
+        while let Ok((len, source)) = socket.recv_from(&mut buffer).await {
+            socket.send_to(&buffer[..len], source).await.unwrap();
+        }
+    });
+
+    address
+}
+
+#[test]
+fn batched_sockets_route_every_answer() {
+    run(async {
+        let shared = SharedSocket::bind_batched(loopback(), 8).await.unwrap();
+        let mut protocols = Vec::new();
+
+        // More queries than fit in a batch, so sends and receives take several calls.
+        for _ in 0..50 {
+            let protocol: UdpProtocol<Ping, Pong, PingParser> =
+                UdpProtocol::new(PingParser).with_strategy(SocketStrategy::Shared(shared.clone()));
+            protocol._connect(echo().await, &timeouts()).await.unwrap();
+            protocols.push(protocol);
+        }
+
+        let timeouts = timeouts();
+        let received = QueryMany::new(0..protocols.len(), protocols.len(), |&index| {
+            let protocol = &protocols[index];
+
+            async move {
+                RawTransport::send(protocol, &[index as u8], &timeouts).await?;
+                RawTransport::receive(protocol, &timeouts).await
+            }
+        })
+        .collect()
+        .await;
+
+        assert_eq!(received.len(), 50);
+
+        for (index, datagram) in received {
+            assert_eq!(datagram.unwrap()[..], [index as u8]);
+        }
+
+        assert_eq!(shared.unrouted(), 0);
+    });
+}
+
+#[test]
+fn batched_sockets_report_failed_sends() {
+    run(async {
+        let shared = SharedSocket::bind_batched(loopback(), DEFAULT_BATCH_SIZE)
+            .await
+            .unwrap();
+        let protocol: UdpProtocol<Ping, Pong, PingParser> =
+            UdpProtocol::new(PingParser).with_strategy(SocketStrategy::Shared(shared));
+
+        // An IPv4 socket can't send to an IPv6 peer.
+        let peer = SocketAddr::from(([0, 0, 0, 0, 0, 0, 0, 1], 27015));
+        protocol._connect(peer, &timeouts()).await.unwrap();
+
+        assert!(RawTransport::send(&protocol, b"ping", &timeouts())
+            .await
+            .is_err());
+    });
+}
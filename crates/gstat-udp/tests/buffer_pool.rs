@@ -0,0 +1,122 @@
+//! Reusing receive buffers with a `BufferPool`.
+
+use gstat_core::{
+    bulk::QueryMany,
+    bytes::Bytes,
+    prelude::{
+        Error, Parser, Protocol, Query, QueryOptions, RawTransport, Response, ServerInfo,
+        TimeoutSettings,
+    },
+    runtime::UdpSocket,
+};
+use gstat_udp::{BufferPool, UdpError, UdpProtocol};
+
+use std::{net::SocketAddr, time::Duration};
+
+use common::run;
+
+mod common;
+
+#[derive(Clone)]
+struct Ping;
+
+impl Query for Ping {
+    type E = UdpError;
+    type Options = ();
+
+    fn from_options(_options: QueryOptions) -> Self {
+        Ping
+    }
+}
+
+struct Pong;
+
+impl Response for Pong {
+    type E = UdpError;
+
+    fn new() -> Result<Self, Error<Self::E>> {
+        Ok(Pong)
+    }
+
+    fn to_common(&self) -> ServerInfo {
+        ServerInfo::default()
+    }
+}
+
+struct PingParser;
+
+impl<'a> Parser<'a, Ping, Pong> for PingParser {
+    type SE = UdpError;
+    type DE = UdpError;
+
+    fn _serialize_query(&self, _query: &Ping) -> Result<Vec<u8>, Self::SE> {
+        Ok(b"ping".to_vec())
+    }
+
+    fn _deserialize_response(&self, _data: Bytes) -> Result<Pong, Self::DE> {
+        Ok(Pong)
+    }
+}
+
+/// Binds a server echoing every datagram it receives.
+async fn echo() -> SocketAddr {
+    let socket = UdpSocket::bind(SocketAddr::from(([127, 0, 0, 1], 0)))
+        .await
+        .unwrap();
+    let address = socket.local_addr().unwrap();
+
+    tokio::spawn(async move {
+        let mut buffer = [0; 16];
+
+        while let Ok((len, source)) = socket.recv_from(&mut buffer).await {
+            socket.send_to(&buffer[..len], source).await.unwrap();
+        }
+    });
+
+    address
+}
+
+#[test]
+fn receive_buffers_are_returned_to_the_pool() {
+    run(async {
+        let pool = BufferPool::new(2);
+        let timeouts = TimeoutSettings::uniform(Duration::from_secs(5));
+        let mut protocols = Vec::new();
+
+        for _ in 0..5 {
+            let protocol: UdpProtocol<Ping, Pong, PingParser> =
+                UdpProtocol::new(PingParser).with_buffer_pool(pool.clone());
+            protocol._connect(echo().await, &timeouts).await.unwrap();
+            protocols.push(protocol);
+        }
+
+        // Five receives at once take five buffers, of which the pool keeps two.
+        let received = QueryMany::new(0..protocols.len(), protocols.len(), |&index| {
+            let protocol = &protocols[index];
+
+            async move {
+                RawTransport::send(protocol, &[index as u8], &timeouts).await?;
+                RawTransport::receive(protocol, &timeouts).await
+            }
+        })
+        .collect()
+        .await;
+
+        for (index, datagram) in received {
+            assert_eq!(datagram.unwrap()[..], [index as u8]);
+        }
+        assert_eq!(pool.idle(), 2);
+
+        // Later receives reuse the idle buffers.
+        for protocol in &protocols {
+            RawTransport::send(protocol, b"again", &timeouts)
+                .await
+                .unwrap();
+            assert_eq!(
+                RawTransport::receive(protocol, &timeouts).await.unwrap()[..],
+                b"again"[..]
+            );
+        }
+        assert_eq!(pool.idle(), 2);
+    });
+}
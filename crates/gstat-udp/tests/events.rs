@@ -0,0 +1,144 @@
+//! Reporting the progress of queries to subscribers.
+
+use gstat_core::{
+    bytes::Bytes,
+    events::ConnectionEvent,
+    prelude::{Error, Game, Parser, Query, QueryOptions, Response, ServerInfo, TimeoutSettings},
+    runtime::UdpSocket,
+};
+use gstat_udp::{UdpError, UdpProtocol};
+
+use std::{
+    io,
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use common::run;
+
+mod common;
+
+#[derive(Clone)]
+struct Ping;
+
+impl Query for Ping {
+    type E = UdpError;
+    type Options = ();
+
+    fn from_options(_options: QueryOptions) -> Self {
+        Ping
+    }
+}
+
+struct Pong;
+
+impl Response for Pong {
+    type E = UdpError;
+
+    fn new() -> Result<Self, Error<Self::E>> {
+        Ok(Pong)
+    }
+
+    fn to_common(&self) -> ServerInfo {
+        ServerInfo::default()
+    }
+}
+
+/// A parser accepting only `pong` as the response.
+struct PingParser;
+
+impl<'a> Parser<'a, Ping, Pong> for PingParser {
+    type SE = UdpError;
+    type DE = UdpError;
+
+    fn _serialize_query(&self, _query: &Ping) -> Result<Vec<u8>, Self::SE> {
+        Ok(b"ping".to_vec())
+    }
+
+    fn _deserialize_response(&self, data: Bytes) -> Result<Pong, Self::DE> {
+        match &data[..] {
+            b"pong" => Ok(Pong),
+            _ => Err(UdpError::Io(io::Error::other("not a pong"))),
+        }
+    }
+}
+
+type PingProtocol = UdpProtocol<Ping, Pong, PingParser>;
+
+/// A game recording the events of every protocol it creates.
+#[derive(Clone, Default)]
+struct Recorded(Arc<Mutex<Vec<ConnectionEvent>>>);
+
+impl<'a> Game<'a, PingProtocol> for Recorded {
+    const GAME_NAME: &'static str = "Recorded";
+    const RELEASE_YEAR: u32 = 2024;
+
+    fn _protocol(&self) -> PingProtocol {
+        let events = self.0.clone();
+
+        UdpProtocol::new(PingParser).with_subscriber(move |event: &ConnectionEvent| {
+            events.lock().unwrap().push(event.clone())
+        })
+    }
+}
+
+/// Binds a server answering every datagram with `answer`.
+async fn server(answer: &'static [u8]) -> SocketAddr {
+    let socket = UdpSocket::bind(SocketAddr::from(([127, 0, 0, 1], 0)))
+        .await
+        .unwrap();
+    let address = socket.local_addr().unwrap();
+
+    tokio::spawn(async move {
+        let mut buffer = [0; 16];
+
+        while let Ok((_, source)) = socket.recv_from(&mut buffer).await {
+            socket.send_to(answer, source).await.unwrap();
+        }
+    });
+
+    address
+}
+
+#[test]
+fn queries_report_each_step() {
+    let game = Recorded::default();
+
+    run(async {
+        let peer = server(b"pong").await;
+        let timeouts = TimeoutSettings::uniform(Duration::from_secs(5));
+
+        game.fetch_with(Ping, peer, timeouts).await.unwrap();
+
+        assert_eq!(
+            *game.0.lock().unwrap(),
+            [
+                ConnectionEvent::ConnectStarted { peer },
+                ConnectionEvent::PacketSent { peer, bytes: 4 },
+                ConnectionEvent::PacketReceived { peer, bytes: 4 },
+            ]
+        );
+    });
+}
+
+#[test]
+fn parse_failures_are_reported() {
+    let game = Recorded::default();
+
+    run(async {
+        let peer = server(b"garbage").await;
+        let timeouts = TimeoutSettings::uniform(Duration::from_secs(5));
+
+        assert!(game.fetch_with(Ping, peer, timeouts).await.is_err());
+
+        let events = game.0.lock().unwrap();
+        assert_eq!(
+            events[2],
+            ConnectionEvent::PacketReceived { peer, bytes: 7 }
+        );
+        assert!(
+            matches!(&events[3], ConnectionEvent::ParseFailed { peer: failed, .. } if *failed == peer)
+        );
+    });
+}
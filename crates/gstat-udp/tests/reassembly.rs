@@ -0,0 +1,179 @@
+//! Joining responses split across several datagrams.
+
+use gstat_core::{
+    bytes::Bytes,
+    prelude::{
+        Error, Parser, Protocol, Query, QueryOptions, Response, ServerInfo, TimeoutSettings,
+    },
+    reassembly::{Fragment, Packet, Reassembler},
+    runtime::UdpSocket,
+};
+use gstat_udp::{UdpError, UdpProtocol};
+
+use std::{net::SocketAddr, time::Duration};
+
+use common::run;
+
+mod common;
+
+#[derive(Clone)]
+struct Ping;
+
+impl Query for Ping {
+    type E = UdpError;
+    type Options = ();
+
+    fn from_options(_options: QueryOptions) -> Self {
+        Ping
+    }
+}
+
+#[derive(Debug)]
+struct Joined(Bytes);
+
+impl Response for Joined {
+    type E = UdpError;
+
+    fn new() -> Result<Self, Error<Self::E>> {
+        Ok(Joined(Bytes::new()))
+    }
+
+    fn to_common(&self) -> ServerInfo {
+        ServerInfo::default()
+    }
+}
+
+struct JoinedParser;
+
+impl<'a> Parser<'a, Ping, Joined> for JoinedParser {
+    type SE = UdpError;
+    type DE = UdpError;
+
+    fn _serialize_query(&self, _query: &Ping) -> Result<Vec<u8>, Self::SE> {
+        Ok(b"ping".to_vec())
+    }
+
+    fn _deserialize_response(&self, data: Bytes) -> Result<Joined, Self::DE> {
+        Ok(Joined(data))
+    }
+}
+
+/// Reads the split header of the test protocol: a fragment starts with `0xfe`, followed by
+/// the set ID, the index and the number of fragments.
+fn classify(datagram: Bytes) -> Packet {
+    match datagram[..] {
+        [0xfe, id, index, total, ..] => Packet::Fragment(Fragment {
+            id: id.into(),
+            index: index.into(),
+            total: total.into(),
+            payload: datagram.slice(4..),
+        }),
+        _ => Packet::Single(datagram),
+    }
+}
+
+/// Binds a server answering every query with `datagrams`, in order.
+async fn server(datagrams: Vec<Vec<u8>>) -> SocketAddr {
+    let socket = UdpSocket::bind(SocketAddr::from(([127, 0, 0, 1], 0)))
+        .await
+        .unwrap();
+    let address = socket.local_addr().unwrap();
+
+    tokio::spawn(async move {
+        let mut buffer = [0; 16];
+
+        while let Ok((_, source)) = socket.recv_from(&mut buffer).await {
+            for datagram in &datagrams {
+                socket.send_to(datagram, source).await.unwrap();
+            }
+        }
+    });
+
+    address
+}
+
+async fn receive(protocol: &UdpProtocol<Ping, Joined, JoinedParser>, server: SocketAddr) -> Bytes {
+    let timeouts = TimeoutSettings::uniform(Duration::from_secs(5));
+
+    protocol._connect(server, &timeouts).await.unwrap();
+    protocol.send_query(Ping, &timeouts).await.unwrap();
+    protocol.receive_response(&timeouts).await.unwrap().0
+}
+
+#[test]
+fn fragments_arriving_out_of_order_are_joined() {
+    run(async {
+        let server = server(vec![
+            b"\xfe\x07\x02\x03ld!".to_vec(),
+            b"\xfe\x07\x00\x03hello, ".to_vec(),
+            b"\xfe\x07\x01\x03wor".to_vec(),
+        ])
+        .await;
+        let protocol = UdpProtocol::new(JoinedParser).with_reassembly(Reassembler::new(), classify);
+
+        assert_eq!(receive(&protocol, server).await, b"hello, world!"[..]);
+    });
+}
+
+#[test]
+fn single_datagrams_pass_through() {
+    run(async {
+        let server = server(vec![b"whole".to_vec()]).await;
+        let protocol = UdpProtocol::new(JoinedParser).with_reassembly(Reassembler::new(), classify);
+
+        assert_eq!(receive(&protocol, server).await, b"whole"[..]);
+    });
+}
+
+#[test]
+fn inconsistent_fragments_fail_the_receive() {
+    run(async {
+        let server = server(vec![
+            b"\xfe\x01\x00\x02a".to_vec(),
+            b"\xfe\x01\x05\x03b".to_vec(),
+        ])
+        .await;
+        let protocol = UdpProtocol::new(JoinedParser).with_reassembly(Reassembler::new(), classify);
+        let timeouts = TimeoutSettings::uniform(Duration::from_secs(5));
+
+        protocol._connect(server, &timeouts).await.unwrap();
+        protocol.send_query(Ping, &timeouts).await.unwrap();
+
+        let err = protocol.receive_response(&timeouts).await.unwrap_err();
+        assert!(err.to_string().contains("fragment"), "{err}");
+    });
+}
+
+#[test]
+fn the_read_limit_bounds_the_whole_response() {
+    run(async {
+        let socket = UdpSocket::bind(SocketAddr::from(([127, 0, 0, 1], 0)))
+            .await
+            .unwrap();
+        let server = socket.local_addr().unwrap();
+
+        // Each fragment arrives well within the limit, the last one well after it.
+        tokio::spawn(async move {
+            let mut buffer = [0; 16];
+            let (_, source) = socket.recv_from(&mut buffer).await.unwrap();
+
+            for index in 0..4u8 {
+                socket
+                    .send_to(&[0xfe, 0x01, index, 4, b'x'], source)
+                    .await
+                    .unwrap();
+                tokio::time::sleep(Duration::from_millis(60)).await;
+            }
+        });
+
+        let protocol = UdpProtocol::new(JoinedParser).with_reassembly(Reassembler::new(), classify);
+        let timeouts =
+            TimeoutSettings::uniform(Duration::from_secs(5)).with_read(Duration::from_millis(100));
+
+        protocol._connect(server, &timeouts).await.unwrap();
+        protocol.send_query(Ping, &timeouts).await.unwrap();
+
+        let err = protocol.receive_response(&timeouts).await.unwrap_err();
+        assert!(err.to_string().contains("timed out"), "{err}");
+    });
+}
@@ -0,0 +1,228 @@
+//! Scanning lists of servers with a `Scanner`.
+
+use gstat_core::{
+    blocklist::Blocklist,
+    bytes::Bytes,
+    prelude::{Error, Game, Parser, Query, QueryOptions, Response, ServerInfo, TimeoutSettings},
+    rate_limit::RateLimit,
+    runtime::UdpSocket,
+};
+use gstat_udp::{
+    scanner::{Checkpoint, Pacing, Scanner},
+    UdpError, UdpProtocol,
+};
+
+use std::{io, net::SocketAddr, path::PathBuf, time::Duration};
+
+use common::run;
+
+mod common;
+
+#[derive(Clone)]
+struct Ping;
+
+impl Query for Ping {
+    type E = UdpError;
+    type Options = ();
+
+    fn from_options(_options: QueryOptions) -> Self {
+        Ping
+    }
+}
+
+struct Pong;
+
+impl Response for Pong {
+    type E = UdpError;
+
+    fn new() -> Result<Self, Error<Self::E>> {
+        Ok(Pong)
+    }
+
+    fn to_common(&self) -> ServerInfo {
+        ServerInfo::default()
+    }
+}
+
+struct PingParser;
+
+impl<'a> Parser<'a, Ping, Pong> for PingParser {
+    type SE = UdpError;
+    type DE = UdpError;
+
+    fn _serialize_query(&self, _query: &Ping) -> Result<Vec<u8>, Self::SE> {
+        Ok(b"ping".to_vec())
+    }
+
+    fn _deserialize_response(&self, _data: Bytes) -> Result<Pong, Self::DE> {
+        Ok(Pong)
+    }
+}
+
+type PingProtocol = UdpProtocol<Ping, Pong, PingParser>;
+
+struct Pinged;
+
+impl<'a> Game<'a, PingProtocol> for Pinged {
+    const GAME_NAME: &'static str = "Pinged";
+    const RELEASE_YEAR: u32 = 2024;
+
+    fn _protocol(&self) -> PingProtocol {
+        UdpProtocol::new(PingParser)
+    }
+}
+
+/// Binds a server answering every datagram.
+async fn server() -> SocketAddr {
+    let socket = UdpSocket::bind(SocketAddr::from(([127, 0, 0, 1], 0)))
+        .await
+        .unwrap();
+    let address = socket.local_addr().unwrap();
+
+    tokio::spawn(async move {
+        let mut buffer = [0; 16];
+
+        while let Ok((_, source)) = socket.recv_from(&mut buffer).await {
+            socket.send_to(b"pong", source).await.unwrap();
+        }
+    });
+
+    address
+}
+
+fn checkpoint(name: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!(
+        "gstat-scan-{}-{name}.checkpoint",
+        std::process::id()
+    ));
+    let _ = std::fs::remove_file(&path);
+
+    path
+}
+
+async fn scanner(path: &PathBuf) -> Scanner {
+    Scanner::bind(SocketAddr::from(([127, 0, 0, 1], 0)))
+        .await
+        .unwrap()
+        .with_timeouts(TimeoutSettings::uniform(Duration::from_secs(5)))
+        .with_pacing(Pacing::fixed(1000.0))
+        // Every target is on the loopback subnet.
+        .with_politeness(RateLimit::new(1000.0, 100))
+        .with_checkpoint(path)
+}
+
+#[test]
+fn scans_stream_results_and_resume() {
+    let path = checkpoint("resume");
+
+    run(async {
+        let mut targets = Vec::new();
+        for _ in 0..20 {
+            targets.push(server().await);
+        }
+
+        let scanner = scanner(&path).await;
+        let mut answered = Vec::new();
+        let summary = scanner
+            .scan(
+                &Pinged,
+                targets.clone(),
+                &mut |address, result: Result<_, _>| {
+                    answered.push(address);
+                    result.map(|_| ()).map_err(io::Error::other)
+                },
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(summary.answered, 20);
+        answered.sort();
+        targets.sort();
+        assert_eq!(answered, targets);
+
+        // Every target is in the checkpoint, so scanning again queries none of them.
+        let summary = scanner
+            .scan(&Pinged, targets, &mut |_, _| -> io::Result<()> {
+                panic!("completed target queried again")
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(summary.skipped, 20);
+        assert_eq!(summary.answered, 0);
+    });
+
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn scans_skip_targets_completed_out_of_order() {
+    let path = checkpoint("partial");
+
+    let mut saved = Checkpoint::new();
+    for index in [0, 1, 2, 4] {
+        saved.record(index);
+    }
+    saved.save(&path).unwrap();
+    assert_eq!(Checkpoint::load(&path).unwrap(), saved);
+
+    run(async {
+        let mut targets = Vec::new();
+        for _ in 0..6 {
+            targets.push(server().await);
+        }
+
+        let mut queried = Vec::new();
+        let summary = scanner(&path)
+            .await
+            .scan(&Pinged, targets.clone(), &mut |address, _| {
+                queried.push(address);
+                Ok(())
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(summary.skipped, 4);
+        let mut expected = [targets[3], targets[5]];
+        expected.sort();
+        queried.sort();
+        assert_eq!(queried, expected);
+    });
+
+    assert_eq!(Checkpoint::load(&path).unwrap().completed(), 6);
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn blocked_targets_are_skipped_without_being_queried() {
+    let path = checkpoint("blocked");
+
+    run(async {
+        let open = server().await;
+        let refused = SocketAddr::from(([192, 0, 2, 1], 27015));
+        let mut blocklist = Blocklist::new();
+        blocklist.block(refused.ip());
+
+        let mut answered = Vec::new();
+        let summary = scanner(&path)
+            .await
+            .with_blocklist(blocklist)
+            .scan(
+                &Pinged,
+                [refused, open],
+                &mut |address, result: Result<_, _>| {
+                    answered.push(address);
+                    result.map(|_| ()).map_err(io::Error::other)
+                },
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(summary.blocked, 1);
+        assert_eq!(summary.answered, 1);
+        assert_eq!(answered, [open]);
+    });
+
+    assert_eq!(Checkpoint::load(&path).unwrap().completed(), 2);
+    std::fs::remove_file(path).unwrap();
+}
@@ -0,0 +1,239 @@
+//! Multiplexing many queries over one `SharedSocket`.
+
+use gstat_core::{
+    bulk::QueryMany,
+    bytes::Bytes,
+    prelude::{
+        Error, Parser, Protocol, Query, QueryOptions, RawTransport, Response, ServerInfo,
+        TimeoutSettings,
+    },
+    runtime::UdpSocket,
+    standards::correlation::CorrelationId,
+};
+use gstat_udp::{SharedSocket, SocketStrategy, UdpError, UdpProtocol};
+
+use std::{net::SocketAddr, time::Duration};
+
+use common::run;
+
+mod common;
+
+#[derive(Clone)]
+struct Ping;
+
+impl Query for Ping {
+    type E = UdpError;
+    type Options = ();
+
+    fn from_options(_options: QueryOptions) -> Self {
+        Ping
+    }
+}
+
+struct Pong;
+
+impl Response for Pong {
+    type E = UdpError;
+
+    fn new() -> Result<Self, Error<Self::E>> {
+        Ok(Pong)
+    }
+
+    fn to_common(&self) -> ServerInfo {
+        ServerInfo::default()
+    }
+}
+
+struct PingParser;
+
+impl<'a> Parser<'a, Ping, Pong> for PingParser {
+    type SE = UdpError;
+    type DE = UdpError;
+
+    fn _serialize_query(&self, _query: &Ping) -> Result<Vec<u8>, Self::SE> {
+        Ok(b"ping".to_vec())
+    }
+
+    fn _deserialize_response(&self, _data: Bytes) -> Result<Pong, Self::DE> {
+        Ok(Pong)
+    }
+}
+
+type PingProtocol = UdpProtocol<Ping, Pong, PingParser>;
+
+fn loopback() -> SocketAddr {
+    SocketAddr::from(([127, 0, 0, 1], 0))
+}
+
+fn timeouts() -> TimeoutSettings {
+    TimeoutSettings::uniform(Duration::from_secs(5))
+}
+
+async fn connected(socket: &SharedSocket, peer: SocketAddr) -> PingProtocol {
+    let protocol =
+        UdpProtocol::new(PingParser).with_strategy(SocketStrategy::Shared(socket.clone()));
+    protocol._connect(peer, &timeouts()).await.unwrap();
+    protocol
+}
+
+#[test]
+fn datagrams_are_routed_to_the_query_of_their_source() {
+    run(async {
+        let shared = SharedSocket::bind(loopback()).await.unwrap();
+
+        let mut peers = Vec::new();
+        let mut protocols = Vec::new();
+
+        for _ in 0..8 {
+            let peer = UdpSocket::bind(loopback()).await.unwrap();
+            protocols.push(connected(&shared, peer.local_addr().unwrap()).await);
+            peers.push(peer);
+        }
+
+        assert_eq!(shared.peers(), 8);
+
+        for protocol in &protocols {
+            RawTransport::send(protocol, b"ping", &timeouts())
+                .await
+                .unwrap();
+        }
+
+        // Every peer answers with its index once all queries are waiting, in reverse order.
+        let answers = tokio::spawn(async move {
+            let mut sources = Vec::new();
+
+            for peer in &peers {
+                let mut buffer = [0; 16];
+                let (len, source) = peer.recv_from(&mut buffer).await.unwrap();
+                assert_eq!(&buffer[..len], b"ping");
+                sources.push(source);
+            }
+
+            tokio::time::sleep(Duration::from_millis(20)).await;
+
+            for (index, (peer, source)) in peers.iter().zip(sources).enumerate().rev() {
+                peer.send_to(&[index as u8], source).await.unwrap();
+            }
+        });
+
+        let timeouts = timeouts();
+        let received = QueryMany::new(0..protocols.len(), protocols.len(), |&index| {
+            RawTransport::receive(&protocols[index], &timeouts)
+        })
+        .collect()
+        .await;
+
+        answers.await.unwrap();
+
+        assert_eq!(received.len(), 8);
+
+        for (index, datagram) in received {
+            assert_eq!(datagram.unwrap()[..], [index as u8]);
+        }
+
+        for protocol in &protocols {
+            protocol.disconnect().await.unwrap();
+        }
+
+        assert_eq!(shared.peers(), 0);
+        assert_eq!(shared.unrouted(), 0);
+    });
+}
+
+#[test]
+fn datagrams_from_unregistered_sources_are_dropped() {
+    run(async {
+        let shared = SharedSocket::bind(loopback()).await.unwrap();
+        let local = shared.local_addr().unwrap();
+
+        let peer = UdpSocket::bind(loopback()).await.unwrap();
+        let stranger = UdpSocket::bind(loopback()).await.unwrap();
+
+        let protocol = connected(&shared, peer.local_addr().unwrap()).await;
+
+        stranger.send_to(b"spoofed", local).await.unwrap();
+        peer.send_to(b"answer", local).await.unwrap();
+
+        let datagram = RawTransport::receive(&protocol, &timeouts()).await.unwrap();
+        assert_eq!(&datagram[..], b"answer");
+        assert_eq!(shared.unrouted(), 1);
+
+        // Once the query is gone, late answers from its peer aren't kept either.
+        drop(protocol);
+        assert_eq!(shared.peers(), 0);
+
+        peer.send_to(b"late", local).await.unwrap();
+
+        let other = UdpSocket::bind(loopback()).await.unwrap();
+        let protocol = connected(&shared, other.local_addr().unwrap()).await;
+        other.send_to(b"fresh", local).await.unwrap();
+
+        let datagram = RawTransport::receive(&protocol, &timeouts()).await.unwrap();
+        assert_eq!(&datagram[..], b"fresh");
+        assert_eq!(shared.unrouted(), 2);
+    });
+}
+
+#[test]
+fn correlated_answers_reach_the_query_that_sent_their_request() {
+    run(async {
+        // The first byte of every request and answer is its ID.
+        let shared = SharedSocket::bind(loopback())
+            .await
+            .unwrap()
+            .with_correlation(|datagram| {
+                datagram
+                    .first()
+                    .map(|&id| CorrelationId::from_u32(id.into()))
+            });
+
+        let peer = UdpSocket::bind(loopback()).await.unwrap();
+        let address = peer.local_addr().unwrap();
+
+        let mut protocols = Vec::new();
+        for _ in 0..4 {
+            protocols.push(connected(&shared, address).await);
+        }
+
+        for (index, protocol) in protocols.iter().enumerate() {
+            RawTransport::send(protocol, &[index as u8, 0], &timeouts())
+                .await
+                .unwrap();
+        }
+
+        // The peer answers every request once all queries are waiting, in reverse order.
+        let answers = tokio::spawn(async move {
+            let mut requests = Vec::new();
+
+            for _ in 0..4 {
+                let mut buffer = [0; 16];
+                let (len, source) = peer.recv_from(&mut buffer).await.unwrap();
+                requests.push((buffer[..len].to_vec(), source));
+            }
+
+            tokio::time::sleep(Duration::from_millis(20)).await;
+
+            for (mut request, source) in requests.into_iter().rev() {
+                request[1] = request[0] + 100;
+                peer.send_to(&request, source).await.unwrap();
+            }
+        });
+
+        let timeouts = timeouts();
+        let received = QueryMany::new(0..protocols.len(), protocols.len(), |&index| {
+            RawTransport::receive(&protocols[index], &timeouts)
+        })
+        .collect()
+        .await;
+
+        answers.await.unwrap();
+
+        assert_eq!(received.len(), 4);
+
+        for (index, datagram) in received {
+            assert_eq!(datagram.unwrap()[..], [index as u8, index as u8 + 100]);
+        }
+
+        assert_eq!(shared.unrouted(), 0);
+    });
+}
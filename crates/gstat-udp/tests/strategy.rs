@@ -0,0 +1,200 @@
+//! Querying through each `SocketStrategy`.
+
+use gstat_core::{
+    bytes::Bytes,
+    prelude::{
+        Error, Parser, Protocol, Query, QueryOptions, Response, ServerInfo, TimeoutSettings,
+    },
+    runtime::UdpSocket,
+};
+use gstat_udp::{SharedSocket, SocketStrategy, UdpError, UdpProtocol};
+
+use std::{io, net::SocketAddr, time::Duration};
+
+use common::run;
+
+mod common;
+
+#[derive(Clone)]
+struct Ping;
+
+impl Query for Ping {
+    type E = UdpError;
+    type Options = ();
+
+    fn from_options(_options: QueryOptions) -> Self {
+        Ping
+    }
+}
+
+struct Pong(Vec<u8>);
+
+impl Response for Pong {
+    type E = UdpError;
+
+    fn new() -> Result<Self, Error<Self::E>> {
+        Ok(Pong(Vec::new()))
+    }
+
+    fn to_common(&self) -> ServerInfo {
+        ServerInfo::default()
+    }
+}
+
+/// A parser accepting any answer starting with `pong`.
+struct PingParser;
+
+impl<'a> Parser<'a, Ping, Pong> for PingParser {
+    type SE = UdpError;
+    type DE = UdpError;
+
+    fn _serialize_query(&self, _query: &Ping) -> Result<Vec<u8>, Self::SE> {
+        Ok(b"ping".to_vec())
+    }
+
+    fn _deserialize_response(&self, data: Bytes) -> Result<Pong, Self::DE> {
+        match data.strip_prefix(b"pong") {
+            Some(rest) => Ok(Pong(rest.to_vec())),
+            None => Err(UdpError::Io(io::Error::other("not a pong"))),
+        }
+    }
+}
+
+type PingProtocol = UdpProtocol<Ping, Pong, PingParser>;
+
+fn loopback() -> SocketAddr {
+    SocketAddr::from(([127, 0, 0, 1], 0))
+}
+
+fn timeouts() -> TimeoutSettings {
+    TimeoutSettings::uniform(Duration::from_secs(5))
+}
+
+/// Receives a ping and answers it with `pong` followed by `tag`.
+///
+/// # Returns
+///
+/// The address the ping was sent from.
+async fn answer(peer: &UdpSocket, tag: u8) -> SocketAddr {
+    let mut buffer = [0; 16];
+    let (len, source) = peer.recv_from(&mut buffer).await.unwrap();
+    assert_eq!(&buffer[..len], b"ping");
+
+    peer.send_to(&[b'p', b'o', b'n', b'g', tag], source)
+        .await
+        .unwrap();
+
+    source
+}
+
+/// Sends a ping through `protocol` and receives the answer of `peer`.
+///
+/// # Returns
+///
+/// The address the ping was sent from and the tag of the answer.
+async fn exchange(protocol: &PingProtocol, peer: &UdpSocket, tag: u8) -> (SocketAddr, Vec<u8>) {
+    let timeouts = timeouts();
+    protocol.send_query(Ping, &timeouts).await.unwrap();
+
+    let source = answer(peer, tag).await;
+    let pong = protocol.receive_response(&timeouts).await.unwrap();
+
+    (source, pong.0)
+}
+
+#[test]
+fn ephemeral_is_the_default_strategy() {
+    let protocol: PingProtocol = UdpProtocol::new(PingParser);
+
+    assert!(matches!(protocol.strategy(), SocketStrategy::Ephemeral));
+}
+
+#[test]
+fn ephemeral_connections_each_bind_their_own_socket() {
+    run(async {
+        let peer = UdpSocket::bind(loopback()).await.unwrap();
+        let address = peer.local_addr().unwrap();
+
+        let first: PingProtocol = UdpProtocol::new(PingParser);
+        let second: PingProtocol = UdpProtocol::new(PingParser);
+        first._connect(address, &timeouts()).await.unwrap();
+        second._connect(address, &timeouts()).await.unwrap();
+
+        let (first_source, first_tag) = exchange(&first, &peer, 1).await;
+        let (second_source, second_tag) = exchange(&second, &peer, 2).await;
+
+        assert_ne!(first_source, second_source);
+        assert_eq!((first_tag, second_tag), (vec![1], vec![2]));
+
+        // A connection made again binds a fresh socket and keeps working.
+        first._connect(address, &timeouts()).await.unwrap();
+        let (source, tag) = exchange(&first, &peer, 3).await;
+
+        assert_ne!(source, first_source);
+        assert_eq!(tag, vec![3]);
+    });
+}
+
+#[test]
+fn ephemeral_connections_ignore_other_sources() {
+    run(async {
+        let peer = UdpSocket::bind(loopback()).await.unwrap();
+        let stranger = UdpSocket::bind(loopback()).await.unwrap();
+
+        let protocol: PingProtocol = UdpProtocol::new(PingParser);
+        protocol
+            ._connect(peer.local_addr().unwrap(), &timeouts())
+            .await
+            .unwrap();
+        protocol.send_query(Ping, &timeouts()).await.unwrap();
+
+        let mut buffer = [0; 16];
+        let (_, source) = peer.recv_from(&mut buffer).await.unwrap();
+
+        stranger.send_to(b"pong\xff", source).await.unwrap();
+        peer.send_to(b"pong\x01", source).await.unwrap();
+
+        let pong = protocol.receive_response(&timeouts()).await.unwrap();
+        assert_eq!(pong.0, vec![1]);
+    });
+}
+
+#[test]
+fn shared_connections_send_from_the_shared_socket() {
+    run(async {
+        let shared = SharedSocket::bind(loopback()).await.unwrap();
+        let local = shared.local_addr().unwrap();
+
+        let peers = [
+            UdpSocket::bind(loopback()).await.unwrap(),
+            UdpSocket::bind(loopback()).await.unwrap(),
+        ];
+
+        let mut protocols = Vec::new();
+        for peer in &peers {
+            let protocol: PingProtocol =
+                UdpProtocol::new(PingParser).with_strategy(SocketStrategy::Shared(shared.clone()));
+            protocol
+                ._connect(peer.local_addr().unwrap(), &timeouts())
+                .await
+                .unwrap();
+            protocols.push(protocol);
+        }
+
+        assert!(matches!(protocols[0].strategy(), SocketStrategy::Shared(_)));
+        assert_eq!(shared.peers(), 2);
+
+        for (tag, (protocol, peer)) in protocols.iter().zip(&peers).enumerate() {
+            let (source, answer) = exchange(protocol, peer, tag as u8).await;
+
+            assert_eq!(source, local);
+            assert_eq!(answer, vec![tag as u8]);
+        }
+
+        for protocol in &protocols {
+            protocol.disconnect().await.unwrap();
+        }
+
+        assert_eq!(shared.peers(), 0);
+    });
+}